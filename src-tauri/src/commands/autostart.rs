@@ -1,11 +1,12 @@
 // Autostart 命令
+use crate::error::{AppError, AppResult};
 use tauri_plugin_autostart::ManagerExt;
 
 #[tauri::command]
 pub async fn toggle_auto_launch(
     app: tauri::AppHandle,
     enable: bool,
-) -> Result<(), String> {
+) -> AppResult<()> {
     let manager = app.autolaunch();
     
     if enable {
@@ -23,7 +24,7 @@ pub async fn toggle_auto_launch(
                 if err_msg.contains("os error 2") || err_msg.contains("找不到指定的文件") {
                     crate::modules::logger::log_info("开机自启项已不存在，视为禁用成功");
                 } else {
-                    return Err(format!("禁用自动启动失败: {}", e));
+                    return Err(format!("禁用自动启动失败: {}", e).into());
                 }
             }
         }
@@ -33,7 +34,7 @@ pub async fn toggle_auto_launch(
 }
 
 #[tauri::command]
-pub async fn is_auto_launch_enabled(app: tauri::AppHandle) -> Result<bool, String> {
+pub async fn is_auto_launch_enabled(app: tauri::AppHandle) -> AppResult<bool> {
     let manager = app.autolaunch();
-    manager.is_enabled().map_err(|e| e.to_string())
+    manager.is_enabled().map_err(|e| AppError::Unknown(e.to_string()))
 }
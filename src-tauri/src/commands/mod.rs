@@ -1,4 +1,5 @@
-use crate::models::{Account, AppConfig, QuotaData, TokenData};
+use crate::error::{AppError, AppResult};
+use crate::models::{Account, AccountSummary, AppConfig, QuotaData, TokenData};
 use crate::modules;
 use tauri_plugin_opener::OpenerExt;
 use tauri::{Emitter, Manager};
@@ -10,8 +11,189 @@ pub mod autostart;
 
 /// 列出所有账号
 #[tauri::command]
-pub async fn list_accounts() -> Result<Vec<Account>, String> {
-    modules::list_accounts()
+pub async fn list_accounts() -> AppResult<Vec<Account>> {
+    // 设置了自定义名称时作为展示名称返回
+    Ok(modules::list_accounts().map_err(AppError::account)?
+        .into_iter()
+        .map(|mut account| {
+            if account.custom_name.is_some() {
+                account.name = account.custom_name.clone();
+            }
+            account
+        })
+        .collect())
+}
+
+/// 重命名账号 (display_name 为空时恢复 OAuth 名称)
+#[tauri::command]
+pub async fn rename_account(app: tauri::AppHandle, account_id: String, display_name: String) -> AppResult<()> {
+    modules::account::rename_account(&account_id, &display_name).map_err(AppError::account)?;
+    crate::modules::tray::update_tray_menus(&app);
+    Ok(())
+}
+
+/// 获取账号健康状态
+#[tauri::command]
+pub async fn get_account_health(account_id: String) -> AppResult<crate::models::AccountHealth> {
+    Ok(modules::load_account(&account_id).map_err(AppError::account)?.health)
+}
+
+/// 获取账号的每日配额快照 (按时间升序)
+#[tauri::command]
+pub async fn get_quota_history(account_id: String) -> AppResult<Vec<crate::models::QuotaSnapshot>> {
+    modules::account::get_quota_history(&account_id).map_err(AppError::account)
+}
+
+/// 多账号配额汇总 (读取本地缓存，不发起网络请求)
+#[tauri::command]
+pub async fn get_quota_summary() -> AppResult<modules::account::QuotaSummary> {
+    modules::account::get_quota_summary().map_err(AppError::account)
+}
+
+/// 设置账号级上游代理 (proxy_url 为空时回退到全局代理)
+#[tauri::command]
+pub async fn update_account_proxy(
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    account_id: String,
+    proxy_url: Option<String>,
+) -> AppResult<Account> {
+    let account = modules::account::update_account_proxy(&account_id, proxy_url).map_err(AppError::account)?;
+    modules::logger::log_info(&format!(
+        "账号上游代理已更新: {} -> {}",
+        account.email,
+        account.upstream_proxy.as_deref().unwrap_or("(全局)")
+    ));
+
+    // 反代服务运行中时重新加载账号池，使新代理立即生效
+    let _ = crate::commands::proxy::reload_proxy_accounts(proxy_state).await;
+
+    Ok(account)
+}
+
+/// 设置账号级系统提示词前缀 (prefix 为空时清除)
+#[tauri::command]
+pub async fn set_account_system_prompt(
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    account_id: String,
+    prefix: Option<String>,
+) -> AppResult<Account> {
+    let account = modules::account::update_account_system_prompt(&account_id, prefix).map_err(AppError::account)?;
+    modules::logger::log_info(&format!(
+        "账号系统提示词前缀已{}: {}",
+        if account.system_prompt_prefix.is_some() { "更新" } else { "清除" },
+        account.email
+    ));
+
+    // 反代服务运行中时重新加载账号池，使前缀立即生效
+    let _ = crate::commands::proxy::reload_proxy_accounts(proxy_state).await;
+
+    Ok(account)
+}
+
+/// 设置账号调度优先级 (1-10)，优先级 10 的账号被选中的频率约为 5 的两倍
+#[tauri::command]
+pub async fn set_account_priority(
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    account_id: String,
+    priority: u8,
+) -> AppResult<()> {
+    let account = modules::account::update_account_priority(&account_id, priority).map_err(AppError::account)?;
+    modules::logger::log_info(&format!("账号调度优先级已更新: {} -> {}", account.email, priority));
+
+    // 反代服务运行中时重新加载账号池，使新权重立即生效
+    let _ = crate::commands::proxy::reload_proxy_accounts(proxy_state).await;
+
+    Ok(())
+}
+
+/// 冻结账号: 保留给手动使用，不再分配给反代
+#[tauri::command]
+pub async fn freeze_account(
+    app: tauri::AppHandle,
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    account_id: String,
+    reason: String,
+) -> AppResult<()> {
+    let account = modules::account::set_account_frozen(&account_id, true, Some(reason)).map_err(AppError::account)?;
+    modules::logger::log_info(&format!("账号已冻结: {}", account.email));
+
+    // 反代服务运行中时重新加载账号池，立即移除该账号
+    let _ = crate::commands::proxy::reload_proxy_accounts(proxy_state).await;
+    crate::modules::tray::update_tray_menus(&app);
+
+    Ok(())
+}
+
+/// 解冻账号，重新加入反代账号池
+#[tauri::command]
+pub async fn unfreeze_account(
+    app: tauri::AppHandle,
+    proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
+    account_id: String,
+) -> AppResult<()> {
+    let account = modules::account::set_account_frozen(&account_id, false, None).map_err(AppError::account)?;
+    modules::logger::log_info(&format!("账号已解冻: {}", account.email));
+
+    let _ = crate::commands::proxy::reload_proxy_accounts(proxy_state).await;
+    crate::modules::tray::update_tray_menus(&app);
+
+    Ok(())
+}
+
+/// 获取已安装 / 运行中的 Antigravity 版本 (结果缓存 30 秒)
+#[tauri::command]
+pub async fn get_antigravity_version() -> AppResult<Option<String>> {
+    tokio::task::spawn_blocking(modules::version::get_antigravity_version)
+        .await
+        .map_err(|e| AppError::from(format!("获取 Antigravity 版本失败: {}", e)))
+}
+
+/// 获取 Antigravity 主进程的 CPU / 内存占用 (未运行时返回 None)
+#[tauri::command]
+pub async fn get_process_info() -> AppResult<Option<modules::process::ProcessInfo>> {
+    tokio::task::spawn_blocking(modules::process::get_antigravity_process_info)
+        .await
+        .map_err(|e| AppError::from(format!("获取进程信息失败: {}", e)))
+}
+
+/// 开始监听 Antigravity 进程，状态变化时发送 antigravity://status-changed 事件
+#[tauri::command]
+pub async fn watch_antigravity_process(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, modules::process_watch::ProcessWatchState>,
+) -> AppResult<()> {
+    if !state.start(app) {
+        modules::logger::log_info("Antigravity 进程监听已在运行");
+    }
+    Ok(())
+}
+
+/// 停止监听 Antigravity 进程
+#[tauri::command]
+pub async fn stop_watching_antigravity_process(
+    state: tauri::State<'_, modules::process_watch::ProcessWatchState>,
+) -> AppResult<()> {
+    state.stop();
+    Ok(())
+}
+
+/// 轻量检测账号 Token 是否可用 (不刷新配额)
+#[tauri::command]
+pub async fn test_account_token(account_id: String) -> AppResult<modules::account::TokenTestResult> {
+    modules::account::test_account_token(&account_id).await.map_err(AppError::account)
+}
+
+/// 设置账号备注 (notes 为空时清除)
+#[tauri::command]
+pub async fn set_account_notes(account_id: String, notes: String) -> AppResult<()> {
+    modules::account::set_account_notes(&account_id, &notes).map_err(AppError::account)?;
+    Ok(())
+}
+
+/// 获取账号备注
+#[tauri::command]
+pub async fn get_account_notes(account_id: String) -> AppResult<Option<String>> {
+    modules::account::get_account_notes(&account_id).map_err(AppError::account)
 }
 
 /// 添加账号
@@ -20,27 +202,28 @@ pub async fn add_account(
     app: tauri::AppHandle,
     _email: String,
     refresh_token: String,
-) -> Result<Account, String> {
-    // 1. 使用 refresh_token 获取 access_token
+) -> AppResult<Account> {
+    // 1-2. 校验 refresh_token 并获取用户信息 (校验失败时不写入任何数据)
     // 注意：这里我们忽略传入的 _email，而是直接去 Google 获取真实的邮箱
-    let token_res = modules::oauth::refresh_access_token(&refresh_token).await?;
-
-    // 2. 获取用户信息
-    let user_info = modules::oauth::get_user_info(&token_res.access_token).await?;
+    let (validation, token_res) = modules::account::validate_refresh_token(&refresh_token)
+        .await
+        .map_err(AppError::OAuth)?;
+    if validation.is_duplicate {
+        modules::logger::log_info(&format!("账号已存在，将更新其 Token: {}", validation.email));
+    }
 
     // 3. 构造 TokenData
     let token = TokenData::new(
         token_res.access_token,
         refresh_token, // 继续使用用户传入的 refresh_token
         token_res.expires_in,
-        Some(user_info.email.clone()),
+        Some(validation.email.clone()),
         None, // project_id 将在需要时获取
         None, // session_id
     );
 
     // 4. 使用真实的 email 添加或更新账号
-    let account =
-        modules::upsert_account(user_info.email.clone(), user_info.get_display_name(), token)?;
+    let account = modules::upsert_account(validation.email, validation.display_name, token, None).map_err(AppError::account)?;
 
     modules::logger::log_info(&format!("添加账号成功: {}", account.email));
 
@@ -57,13 +240,24 @@ pub async fn add_account(
     Ok(account)
 }
 
+/// 校验 refresh_token (不创建账号)，供添加前预览
+#[tauri::command]
+pub async fn validate_refresh_token(
+    refresh_token: String,
+) -> AppResult<modules::account::TokenValidationResult> {
+    let (validation, _) = modules::account::validate_refresh_token(&refresh_token)
+        .await
+        .map_err(AppError::OAuth)?;
+    Ok(validation)
+}
+
 /// 删除账号
 #[tauri::command]
-pub async fn delete_account(app: tauri::AppHandle, account_id: String) -> Result<(), String> {
+pub async fn delete_account(app: tauri::AppHandle, account_id: String) -> AppResult<()> {
     modules::logger::log_info(&format!("收到删除账号请求: {}", account_id));
     modules::delete_account(&account_id).map_err(|e| {
         modules::logger::log_error(&format!("删除账号失败: {}", e));
-        e
+        AppError::account(e)
     })?;
     modules::logger::log_info(&format!("账号删除成功: {}", account_id));
 
@@ -77,14 +271,14 @@ pub async fn delete_account(app: tauri::AppHandle, account_id: String) -> Result
 pub async fn delete_accounts(
     app: tauri::AppHandle,
     account_ids: Vec<String>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     modules::logger::log_info(&format!(
         "收到批量删除请求，共 {} 个账号",
         account_ids.len()
     ));
     modules::account::delete_accounts(&account_ids).map_err(|e| {
         modules::logger::log_error(&format!("批量删除失败: {}", e));
-        e
+        AppError::account(e)
     })?;
 
     // 强制同步托盘
@@ -92,39 +286,67 @@ pub async fn delete_accounts(
     Ok(())
 }
 
+/// 列出可清理的被禁止账号 (quota.is_forbidden，当前账号除外)，不做删除
+#[tauri::command]
+pub async fn list_forbidden_accounts() -> AppResult<Vec<AccountSummary>> {
+    modules::account::list_forbidden_accounts().map_err(AppError::account)
+}
+
+/// 删除所有被禁止的账号 (当前账号除外)，返回删除数量
+#[tauri::command]
+pub async fn purge_forbidden_accounts(app: tauri::AppHandle) -> AppResult<usize> {
+    let account_ids: Vec<String> = modules::account::list_forbidden_accounts()
+        .map_err(AppError::account)?
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    if account_ids.is_empty() {
+        return Ok(0);
+    }
+
+    modules::logger::log_info(&format!("清理被禁止的账号，共 {} 个", account_ids.len()));
+    modules::account::delete_accounts(&account_ids).map_err(|e| {
+        modules::logger::log_error(&format!("清理被禁止账号失败: {}", e));
+        AppError::account(e)
+    })?;
+
+    crate::modules::tray::update_tray_menus(&app);
+    Ok(account_ids.len())
+}
+
 /// 重新排序账号列表
 /// 根据传入的账号ID数组顺序更新账号排列
 #[tauri::command]
-pub async fn reorder_accounts(account_ids: Vec<String>) -> Result<(), String> {
+pub async fn reorder_accounts(account_ids: Vec<String>) -> AppResult<()> {
     modules::logger::log_info(&format!("收到账号重排序请求，共 {} 个账号", account_ids.len()));
     modules::account::reorder_accounts(&account_ids).map_err(|e| {
         modules::logger::log_error(&format!("账号重排序失败: {}", e));
-        e
+        AppError::account(e)
     })
 }
 
 /// 切换账号
 #[tauri::command]
-pub async fn switch_account(app: tauri::AppHandle, account_id: String) -> Result<(), String> {
+pub async fn switch_account(app: tauri::AppHandle, account_id: String) -> AppResult<()> {
     let res = modules::switch_account(&account_id).await;
     if res.is_ok() {
         crate::modules::tray::update_tray_menus(&app);
     }
-    res
+    res.map_err(AppError::account)
 }
 
 /// 获取当前账号
 #[tauri::command]
-pub async fn get_current_account() -> Result<Option<Account>, String> {
+pub async fn get_current_account() -> AppResult<Option<Account>> {
     // println!("🚀 Backend Command: get_current_account called"); // Commented out to reduce noise for frequent calls, relies on frontend log for frequency
     // Actually user WANTS to see it.
     modules::logger::log_info("Backend Command: get_current_account called");
 
-    let account_id = modules::get_current_account_id()?;
+    let account_id = modules::get_current_account_id().map_err(AppError::account)?;
 
     if let Some(id) = account_id {
         // modules::logger::log_info(&format!("   Found current account ID: {}", id));
-        modules::load_account(&id).map(Some)
+        modules::load_account(&id).map(Some).map_err(AppError::account)
     } else {
         modules::logger::log_info("   No current account set");
         Ok(None)
@@ -135,7 +357,7 @@ pub async fn get_current_account() -> Result<Option<Account>, String> {
 async fn internal_refresh_account_quota(
     app: &tauri::AppHandle,
     account: &mut Account,
-) -> Result<QuotaData, String> {
+) -> AppResult<QuotaData> {
     modules::logger::log_info(&format!("自动触发刷新配额: {}", account.email));
 
     // 使用带重试的查询 (Shared logic)
@@ -149,7 +371,7 @@ async fn internal_refresh_account_quota(
         }
         Err(e) => {
             modules::logger::log_warn(&format!("自动刷新配额失败 ({}): {}", account.email, e));
-            Err(e.to_string())
+            Err(e)
         }
     }
 }
@@ -163,7 +385,7 @@ pub async fn fetch_account_quota(
 ) -> crate::error::AppResult<QuotaData> {
     modules::logger::log_info(&format!("手动刷新配额请求: {}", account_id));
     let mut account =
-        modules::load_account(&account_id).map_err(crate::error::AppError::Account)?;
+        modules::load_account(&account_id).map_err(AppError::account)?;
 
     // 使用带重试的查询 (Shared logic)
     let quota = modules::account::fetch_quota_with_retry(&mut account).await?;
@@ -197,7 +419,7 @@ pub use modules::account::RefreshStats;
 #[tauri::command]
 pub async fn refresh_all_quotas(
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
-) -> Result<RefreshStats, String> {
+) -> AppResult<RefreshStats> {
     let stats = modules::account::refresh_all_quotas_logic().await?;
 
     // 同步到运行中的反代服务（如果已启动）
@@ -219,12 +441,36 @@ pub async fn refresh_all_quotas(
 
     Ok(stats)
 }
+
+/// 定时刷新所有账号配额 (间隔最小 60 秒)，每次完成后发送 quota://refreshed 事件
+#[tauri::command]
+pub async fn schedule_quota_refresh(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, modules::scheduler::QuotaRefreshState>,
+    interval_secs: u64,
+) -> AppResult<()> {
+    let interval_secs = state.schedule(app, interval_secs);
+    modules::logger::log_info(&format!("已启用定时配额刷新，间隔 {} 秒", interval_secs));
+    Ok(())
+}
+
+/// 取消定时配额刷新
+#[tauri::command]
+pub async fn cancel_quota_refresh(
+    state: tauri::State<'_, modules::scheduler::QuotaRefreshState>,
+) -> AppResult<()> {
+    if state.cancel() {
+        modules::logger::log_info("已取消定时配额刷新");
+    }
+    Ok(())
+}
+
 /// 获取设备指纹（当前 storage.json + 账号绑定）
 #[tauri::command]
 pub async fn get_device_profiles(
     account_id: String,
-) -> Result<modules::account::DeviceProfiles, String> {
-    modules::get_device_profiles(&account_id)
+) -> AppResult<modules::account::DeviceProfiles> {
+    modules::get_device_profiles(&account_id).map_err(AppError::account)
 }
 
 /// 绑定设备指纹（capture: 采集当前；generate: 生成新指纹），并写入 storage.json
@@ -232,13 +478,13 @@ pub async fn get_device_profiles(
 pub async fn bind_device_profile(
     account_id: String,
     mode: String,
-) -> Result<crate::models::DeviceProfile, String> {
-    modules::bind_device_profile(&account_id, &mode)
+) -> AppResult<crate::models::DeviceProfile> {
+    modules::bind_device_profile(&account_id, &mode).map_err(AppError::account)
 }
 
 /// 预览生成一个指纹（不落盘）
 #[tauri::command]
-pub async fn preview_generate_profile() -> Result<crate::models::DeviceProfile, String> {
+pub async fn preview_generate_profile() -> AppResult<crate::models::DeviceProfile> {
     Ok(crate::modules::device::generate_profile())
 }
 
@@ -247,30 +493,30 @@ pub async fn preview_generate_profile() -> Result<crate::models::DeviceProfile,
 pub async fn bind_device_profile_with_profile(
     account_id: String,
     profile: crate::models::DeviceProfile,
-) -> Result<crate::models::DeviceProfile, String> {
-    modules::bind_device_profile_with_profile(&account_id, profile, Some("generated".to_string()))
+) -> AppResult<crate::models::DeviceProfile> {
+    modules::bind_device_profile_with_profile(&account_id, profile, Some("generated".to_string())).map_err(AppError::account)
 }
 
 /// 将账号已绑定的指纹应用到 storage.json
 #[tauri::command]
 pub async fn apply_device_profile(
     account_id: String,
-) -> Result<crate::models::DeviceProfile, String> {
-    modules::apply_device_profile(&account_id)
+) -> AppResult<crate::models::DeviceProfile> {
+    modules::apply_device_profile(&account_id).map_err(AppError::account)
 }
 
 /// 恢复最早的 storage.json 备份（近似“原始”状态）
 #[tauri::command]
-pub async fn restore_original_device() -> Result<String, String> {
-    modules::restore_original_device()
+pub async fn restore_original_device() -> AppResult<String> {
+    modules::restore_original_device().map_err(AppError::account)
 }
 
 /// 列出指纹版本
 #[tauri::command]
 pub async fn list_device_versions(
     account_id: String,
-) -> Result<modules::account::DeviceProfiles, String> {
-    modules::list_device_versions(&account_id)
+) -> AppResult<modules::account::DeviceProfiles> {
+    modules::list_device_versions(&account_id).map_err(AppError::account)
 }
 
 /// 按版本恢复指纹
@@ -278,34 +524,35 @@ pub async fn list_device_versions(
 pub async fn restore_device_version(
     account_id: String,
     version_id: String,
-) -> Result<crate::models::DeviceProfile, String> {
-    modules::restore_device_version(&account_id, &version_id)
+) -> AppResult<crate::models::DeviceProfile> {
+    modules::restore_device_version(&account_id, &version_id).map_err(AppError::account)
 }
 
 /// 删除历史指纹（baseline 不可删）
 #[tauri::command]
-pub async fn delete_device_version(account_id: String, version_id: String) -> Result<(), String> {
-    modules::delete_device_version(&account_id, &version_id)
+pub async fn delete_device_version(account_id: String, version_id: String) -> AppResult<()> {
+    modules::delete_device_version(&account_id, &version_id).map_err(AppError::account)
 }
 
 /// 打开设备存储目录
 #[tauri::command]
-pub async fn open_device_folder(app: tauri::AppHandle) -> Result<(), String> {
-    let dir = modules::device::get_storage_dir()?;
+pub async fn open_device_folder(app: tauri::AppHandle) -> AppResult<()> {
+    let dir = modules::device::get_storage_dir().map_err(AppError::io)?;
     let dir_str = dir
         .to_str()
-        .ok_or("无法解析存储目录路径为字符串")?
+        .ok_or_else(|| AppError::io("无法解析存储目录路径为字符串".to_string()))?
         .to_string();
     app.opener()
         .open_path(dir_str, None::<&str>)
-        .map_err(|e| format!("打开目录失败: {}", e))
+        .map_err(|e| AppError::io(format!("打开目录失败: {}", e)))
 }
 
 
 /// 加载配置
 #[tauri::command]
-pub async fn load_config() -> Result<AppConfig, String> {
-    modules::load_app_config()
+/// 不做校验: 配置无效时设置页仍需加载并修正 (保存时校验)
+pub async fn load_config() -> crate::error::AppResult<AppConfig> {
+    modules::config::read_app_config()
 }
 
 /// 保存配置
@@ -314,7 +561,7 @@ pub async fn save_config(
     app: tauri::AppHandle,
     proxy_state: tauri::State<'_, crate::commands::proxy::ProxyServiceState>,
     config: AppConfig,
-) -> Result<(), String> {
+) -> crate::error::AppResult<()> {
     modules::save_app_config(&config)?;
 
     // 通知托盘配置已更新
@@ -343,79 +590,52 @@ pub async fn save_config(
 // --- OAuth 命令 ---
 
 #[tauri::command]
-pub async fn start_oauth_login(app_handle: tauri::AppHandle) -> Result<Account, String> {
+pub async fn start_oauth_login(app_handle: tauri::AppHandle) -> AppResult<Account> {
     modules::logger::log_info("开始 OAuth 授权流程...");
 
     // 1. 启动 OAuth 流程获取 Token
-    let token_res = modules::oauth_server::start_oauth_flow(app_handle.clone()).await?;
-
-    // 2. 检查 refresh_token
-    let refresh_token = token_res.refresh_token.ok_or_else(|| {
-        "未获取到 Refresh Token。\n\n\
-         可能原因:\n\
-         1. 您之前已授权过此应用,Google 不会再次返回 refresh_token\n\n\
-         解决方案:\n\
-         1. 访问 https://myaccount.google.com/permissions\n\
-         2. 撤销 'Antigravity Tools' 的访问权限\n\
-         3. 重新进行 OAuth 授权\n\n\
-         或者使用 'Refresh Token' 标签页手动添加账号"
-            .to_string()
-    })?;
-
-    // 3. 获取用户信息
-    let user_info = modules::oauth::get_user_info(&token_res.access_token).await?;
-    modules::logger::log_info(&format!("获取用户信息成功: {}", user_info.email));
-
-    // 4. 尝试获取项目ID
-    let project_id = crate::proxy::project_resolver::fetch_project_id(&token_res.access_token)
+    let token_res = modules::oauth_server::start_oauth_flow(app_handle.clone())
         .await
-        .ok();
-
-    if let Some(ref pid) = project_id {
-        modules::logger::log_info(&format!("获取项目ID成功: {}", pid));
-    } else {
-        modules::logger::log_warn("未能获取项目ID,将在后续懒加载");
-    }
+        .map_err(AppError::OAuth)?;
 
-    // 5. 构造 TokenData
-    let token_data = TokenData::new(
-        token_res.access_token,
-        refresh_token,
-        token_res.expires_in,
-        Some(user_info.email.clone()),
-        project_id,
-        None,
-    );
-
-    // 6. 添加或更新到账号列表
-    modules::logger::log_info("正在保存账号信息...");
-    let mut account = modules::upsert_account(
-        user_info.email.clone(),
-        user_info.get_display_name(),
-        token_data,
-    )?;
-
-    // 7. 自动触发刷新额度
-    let _ = internal_refresh_account_quota(&app_handle, &mut account).await;
-
-    // 8. If proxy is running, reload token pool so changes take effect immediately.
-    let _ = crate::commands::proxy::reload_proxy_accounts(
-        app_handle.state::<crate::commands::proxy::ProxyServiceState>(),
-    )
-    .await;
-
-    Ok(account)
+    // 2. 保存账号
+    finish_oauth_login(&app_handle, token_res).await
 }
 
 /// 完成 OAuth 授权（不自动打开浏览器）
 #[tauri::command]
-pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Account, String> {
+pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> AppResult<Account> {
     modules::logger::log_info("完成 OAuth 授权流程 (manual)...");
 
     // 1. 等待回调并交换 Token（不 open browser）
-    let token_res = modules::oauth_server::complete_oauth_flow(app_handle.clone()).await?;
+    let token_res = modules::oauth_server::complete_oauth_flow(app_handle.clone())
+        .await
+        .map_err(AppError::OAuth)?;
 
-    // 2. 检查 refresh_token
+    // 2. 保存账号
+    finish_oauth_login(&app_handle, token_res).await
+}
+
+/// 设备码授权 (无法打开浏览器的环境，如 SSH / Docker)
+#[tauri::command]
+pub async fn start_device_oauth_login(app_handle: tauri::AppHandle) -> AppResult<Account> {
+    modules::logger::log_info("开始设备码 OAuth 授权流程...");
+
+    // 1. 获取设备码并轮询 Token (通过 device-flow-code 事件通知前端显示验证码)
+    let token_res = modules::oauth_server::start_device_flow(app_handle.clone())
+        .await
+        .map_err(AppError::OAuth)?;
+
+    // 2. 保存账号
+    finish_oauth_login(&app_handle, token_res).await
+}
+
+/// OAuth 授权完成后的公共流程: 校验 refresh_token、获取用户信息并保存账号
+async fn finish_oauth_login(
+    app_handle: &tauri::AppHandle,
+    token_res: modules::oauth::TokenResponse,
+) -> AppResult<Account> {
+    // 1. 检查 refresh_token
     let refresh_token = token_res.refresh_token.ok_or_else(|| {
         "未获取到 Refresh Token。\n\n\
          可能原因:\n\
@@ -428,11 +648,19 @@ pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Accoun
             .to_string()
     })?;
 
-    // 3. 获取用户信息
-    let user_info = modules::oauth::get_user_info(&token_res.access_token).await?;
+    // 2. 获取用户信息
+    let user_info = modules::oauth::get_user_info(&token_res.access_token)
+        .await
+        .map_err(AppError::OAuth)?;
     modules::logger::log_info(&format!("获取用户信息成功: {}", user_info.email));
 
-    // 4. 尝试获取项目ID
+    // 邮箱域名不在允许列表中时终止授权流程，不保存账号
+    if let Err(e) = modules::account::check_email_domain_allowed(&user_info.email) {
+        modules::oauth_server::cancel_oauth_flow();
+        return Err(AppError::Account(e));
+    }
+
+    // 3. 尝试获取项目ID
     let project_id = crate::proxy::project_resolver::fetch_project_id(&token_res.access_token)
         .await
         .ok();
@@ -443,7 +671,7 @@ pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Accoun
         modules::logger::log_warn("未能获取项目ID,将在后续懒加载");
     }
 
-    // 5. 构造 TokenData
+    // 4. 构造 TokenData
     let token_data = TokenData::new(
         token_res.access_token,
         refresh_token,
@@ -453,18 +681,19 @@ pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Accoun
         None,
     );
 
-    // 6. 添加或更新到账号列表
+    // 5. 添加或更新到账号列表
     modules::logger::log_info("正在保存账号信息...");
     let mut account = modules::upsert_account(
         user_info.email.clone(),
         user_info.get_display_name(),
         token_data,
-    )?;
+        None,
+    ).map_err(AppError::account)?;
 
-    // 7. 自动触发刷新额度
-    let _ = internal_refresh_account_quota(&app_handle, &mut account).await;
+    // 6. 自动触发刷新额度
+    let _ = internal_refresh_account_quota(app_handle, &mut account).await;
 
-    // 8. If proxy is running, reload token pool so changes take effect immediately.
+    // 7. If proxy is running, reload token pool so changes take effect immediately.
     let _ = crate::commands::proxy::reload_proxy_accounts(
         app_handle.state::<crate::commands::proxy::ProxyServiceState>(),
     )
@@ -475,12 +704,12 @@ pub async fn complete_oauth_login(app_handle: tauri::AppHandle) -> Result<Accoun
 
 /// 预生成 OAuth 授权链接 (不打开浏览器)
 #[tauri::command]
-pub async fn prepare_oauth_url(app_handle: tauri::AppHandle) -> Result<String, String> {
-    crate::modules::oauth_server::prepare_oauth_url(app_handle).await
+pub async fn prepare_oauth_url(app_handle: tauri::AppHandle) -> AppResult<String> {
+    crate::modules::oauth_server::prepare_oauth_url(app_handle).await.map_err(AppError::OAuth)
 }
 
 #[tauri::command]
-pub async fn cancel_oauth_login() -> Result<(), String> {
+pub async fn cancel_oauth_login() -> AppResult<()> {
     modules::oauth_server::cancel_oauth_flow();
     Ok(())
 }
@@ -488,8 +717,8 @@ pub async fn cancel_oauth_login() -> Result<(), String> {
 // --- 导入命令 ---
 
 #[tauri::command]
-pub async fn import_v1_accounts(app: tauri::AppHandle) -> Result<Vec<Account>, String> {
-    let accounts = modules::migration::import_from_v1().await?;
+pub async fn import_v1_accounts(app: tauri::AppHandle) -> AppResult<Vec<Account>> {
+    let accounts = modules::migration::import_from_v1(false).await?.imported;
 
     // 对导入的账号尝试刷新一波
     for mut account in accounts.clone() {
@@ -499,14 +728,101 @@ pub async fn import_v1_accounts(app: tauri::AppHandle) -> Result<Vec<Account>, S
     Ok(accounts)
 }
 
+/// 预览 V1 导入结果 (校验凭证但不写入任何数据)
+#[tauri::command]
+pub async fn preview_v1_import() -> AppResult<modules::migration::ImportResult> {
+    modules::migration::import_from_v1(true).await.map_err(AppError::account)
+}
+
+/// 导出所有账号到备份文件 (password 非空时加密)
+#[tauri::command]
+pub async fn export_accounts(path: String, password: Option<String>) -> AppResult<()> {
+    modules::account::export_accounts_to_file(std::path::Path::new(&path), password.as_deref()).map_err(AppError::account)?;
+    modules::logger::log_info(&format!("账号已导出到: {}", path));
+    Ok(())
+}
+
+/// 从备份文件导入账号
+#[tauri::command]
+pub async fn import_accounts_from_file(
+    app: tauri::AppHandle,
+    path: String,
+    password: Option<String>,
+) -> AppResult<Vec<Account>> {
+    let accounts = modules::account::import_accounts_from_file(
+        std::path::Path::new(&path),
+        password.as_deref(),
+    ).map_err(AppError::account)?;
+
+    // 导入的 access_token 已过期，刷新配额时会顺带刷新 token
+    for mut account in accounts.clone() {
+        let _ = internal_refresh_account_quota(&app, &mut account).await;
+    }
+    crate::modules::tray::update_tray_menus(&app);
+
+    Ok(accounts)
+}
+
+/// 将账号目录与索引打包为 tar.gz 归档，返回归档路径
+#[tauri::command]
+pub async fn backup_accounts(dest_path: String) -> AppResult<String> {
+    let path = modules::account::backup_accounts_archive(std::path::Path::new(&dest_path)).map_err(AppError::account)?;
+    let path = path.to_string_lossy().to_string();
+    modules::logger::log_info(&format!("账号已备份到: {}", path));
+    Ok(path)
+}
+
+/// 从 tar.gz 归档恢复账号，返回恢复的账号数
+#[tauri::command]
+pub async fn restore_accounts(app: tauri::AppHandle, archive_path: String) -> AppResult<usize> {
+    let count = modules::account::restore_accounts_archive(std::path::Path::new(&archive_path)).map_err(AppError::account)?;
+    crate::modules::tray::update_tray_menus(&app);
+    Ok(count)
+}
+
+/// 从 `email:refresh_token` 文本文件批量导入账号
 #[tauri::command]
-pub async fn import_from_db(app: tauri::AppHandle) -> Result<Account, String> {
+pub async fn import_from_token_list(
+    app: tauri::AppHandle,
+    path: String,
+) -> AppResult<modules::migration::TokenListImport> {
+    let result = modules::migration::import_from_token_list(&path).await.map_err(AppError::account)?;
+    modules::logger::log_info(&format!(
+        "Token 列表导入完成: 成功 {} 个, 失败 {} 行",
+        result.accounts.len(),
+        result.errors.len()
+    ));
+
+    for mut account in result.accounts.clone() {
+        let _ = internal_refresh_account_quota(&app, &mut account).await;
+    }
+    crate::modules::tray::update_tray_menus(&app);
+
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn import_from_db(app: tauri::AppHandle) -> AppResult<Account> {
     // 同步函数包装为 async
-    let mut account = modules::migration::import_from_db().await?;
+    let mut account = modules::migration::import_from_db().await.map_err(AppError::account)?;
 
     // 既然是从数据库导入（即 IDE 当前账号），自动将其设为 Manager 的当前账号
     let account_id = account.id.clone();
-    modules::account::set_current_account_id(&account_id)?;
+    modules::account::set_current_account_id(&account_id).map_err(AppError::account)?;
+
+    // 自动触发刷新额度
+    let _ = internal_refresh_account_quota(&app, &mut account).await;
+
+    // 刷新托盘图标展示
+    crate::modules::tray::update_tray_menus(&app);
+
+    Ok(account)
+}
+
+/// 从 Cursor IDE (globalStorage/storage.json) 导入账号
+#[tauri::command]
+pub async fn import_from_cursor(app: tauri::AppHandle) -> AppResult<Account> {
+    let mut account = modules::migration::import_from_cursor().await.map_err(AppError::account)?;
 
     // 自动触发刷新额度
     let _ = internal_refresh_account_quota(&app, &mut account).await;
@@ -519,13 +835,13 @@ pub async fn import_from_db(app: tauri::AppHandle) -> Result<Account, String> {
 
 #[tauri::command]
 #[allow(dead_code)]
-pub async fn import_custom_db(app: tauri::AppHandle, path: String) -> Result<Account, String> {
+pub async fn import_custom_db(app: tauri::AppHandle, path: String) -> AppResult<Account> {
     // 调用重构后的自定义导入函数
-    let mut account = modules::migration::import_from_custom_db_path(path).await?;
+    let mut account = modules::migration::import_from_custom_db_path(path).await.map_err(AppError::account)?;
 
     // 自动设为当前账号
     let account_id = account.id.clone();
-    modules::account::set_current_account_id(&account_id)?;
+    modules::account::set_current_account_id(&account_id).map_err(AppError::account)?;
 
     // 自动触发刷新额度
     let _ = internal_refresh_account_quota(&app, &mut account).await;
@@ -537,7 +853,7 @@ pub async fn import_custom_db(app: tauri::AppHandle, path: String) -> Result<Acc
 }
 
 #[tauri::command]
-pub async fn sync_account_from_db(app: tauri::AppHandle) -> Result<Option<Account>, String> {
+pub async fn sync_account_from_db(app: tauri::AppHandle) -> AppResult<Option<Account>> {
     // 1. 获取 DB 中的 Refresh Token
     let db_refresh_token = match modules::migration::get_refresh_token_from_db() {
         Ok(token) => token,
@@ -547,8 +863,16 @@ pub async fn sync_account_from_db(app: tauri::AppHandle) -> Result<Option<Accoun
         }
     };
 
+    sync_account_with_refresh_token(app, db_refresh_token).await
+}
+
+/// 按 DB 中读取到的 Refresh Token 同步当前账号，账号未变化时返回 None
+pub async fn sync_account_with_refresh_token(
+    app: tauri::AppHandle,
+    db_refresh_token: String,
+) -> AppResult<Option<Account>> {
     // 2. 获取 Manager 当前账号
-    let curr_account = modules::account::get_current_account()?;
+    let curr_account = modules::account::get_current_account().map_err(AppError::account)?;
 
     // 3. 对比：如果 Refresh Token 相同，说明账号没变，无需导入
     if let Some(acc) = curr_account {
@@ -572,27 +896,68 @@ pub async fn sync_account_from_db(app: tauri::AppHandle) -> Result<Option<Accoun
 
 /// 保存文本文件 (绕过前端 Scope 限制)
 #[tauri::command]
-pub async fn save_text_file(path: String, content: String) -> Result<(), String> {
-    std::fs::write(&path, content).map_err(|e| format!("写入文件失败: {}", e))
+pub async fn save_text_file(path: String, content: String) -> AppResult<()> {
+    std::fs::write(&path, content).map_err(AppError::Io)
+}
+
+/// 检索内存中的日志 (最新在前，支持级别过滤与关键字搜索)
+#[tauri::command]
+pub async fn get_log_entries(
+    level_filter: Option<String>,
+    search: Option<String>,
+    limit: usize,
+    offset: usize,
+) -> AppResult<modules::logger::LogPage> {
+    Ok(modules::logger::log_cache().query(
+        level_filter.as_deref(),
+        search.as_deref(),
+        limit,
+        offset,
+    ))
+}
+
+/// 生成诊断报告 (系统 / 应用 / 反代状态，凭据已脱敏)，用于提交 Bug
+#[tauri::command]
+pub async fn generate_diagnostic_report(
+    state: tauri::State<'_, proxy::ProxyServiceState>,
+) -> AppResult<modules::diagnostics::DiagnosticReport> {
+    let proxy_running = state.instance.read().await.is_some();
+    let proxy_stats = proxy::get_proxy_stats(state).await?;
+    tokio::task::spawn_blocking(move || modules::diagnostics::collect_report(proxy_running, proxy_stats))
+        .await
+        .map_err(|e| AppError::from(format!("生成诊断报告失败: {}", e)))?
+        .map_err(AppError::io)
+}
+
+/// 生成诊断报告并以 JSON 写入指定文件
+#[tauri::command]
+pub async fn save_diagnostic_report(
+    path: String,
+    state: tauri::State<'_, proxy::ProxyServiceState>,
+) -> AppResult<()> {
+    let report = generate_diagnostic_report(state).await?;
+    let json = serde_json::to_string_pretty(&report)
+        .map_err(|e| AppError::from(format!("序列化诊断报告失败: {}", e)))?;
+    std::fs::write(&path, json).map_err(AppError::Io)
 }
 
 /// 清理日志缓存
 #[tauri::command]
-pub async fn clear_log_cache() -> Result<(), String> {
-    modules::logger::clear_logs()
+pub async fn clear_log_cache() -> AppResult<()> {
+    modules::logger::clear_logs().map_err(AppError::io)
 }
 
 /// 打开数据目录
 #[tauri::command]
-pub async fn open_data_folder() -> Result<(), String> {
-    let path = modules::account::get_data_dir()?;
+pub async fn open_data_folder() -> AppResult<()> {
+    let path = modules::account::get_data_dir().map_err(AppError::io)?;
 
     #[cfg(target_os = "macos")]
     {
         std::process::Command::new("open")
             .arg(path)
             .spawn()
-            .map_err(|e| format!("打开文件夹失败: {}", e))?;
+            .map_err(|e| AppError::io(format!("打开文件夹失败: {}", e)))?;
     }
 
     #[cfg(target_os = "windows")]
@@ -600,7 +965,7 @@ pub async fn open_data_folder() -> Result<(), String> {
         std::process::Command::new("explorer")
             .arg(path)
             .spawn()
-            .map_err(|e| format!("打开文件夹失败: {}", e))?;
+            .map_err(|e| AppError::io(format!("打开文件夹失败: {}", e)))?;
     }
 
     #[cfg(target_os = "linux")]
@@ -608,28 +973,38 @@ pub async fn open_data_folder() -> Result<(), String> {
         std::process::Command::new("xdg-open")
             .arg(path)
             .spawn()
-            .map_err(|e| format!("打开文件夹失败: {}", e))?;
+            .map_err(|e| AppError::io(format!("打开文件夹失败: {}", e)))?;
     }
 
     Ok(())
 }
 
-/// 获取数据目录绝对路径
+/// 获取数据目录绝对路径 (已迁移时为重定向后的目录)
 #[tauri::command]
-pub async fn get_data_dir_path() -> Result<String, String> {
-    let path = modules::account::get_data_dir()?;
+pub async fn get_data_dir_path() -> AppResult<String> {
+    let path = modules::account::get_data_dir().map_err(AppError::io)?;
     Ok(path.to_string_lossy().to_string())
 }
 
+/// 将数据目录迁移到新路径 (默认目录下写入 redirect.json 指向新目录)
+#[tauri::command]
+pub async fn migrate_data_dir(new_path: String) -> AppResult<()> {
+    tokio::task::spawn_blocking(move || modules::account::migrate_data_dir(&new_path))
+        .await
+        .map_err(|e| AppError::io(format!("迁移数据目录失败: {}", e)))?
+        .map_err(AppError::io)?;
+    Ok(())
+}
+
 /// 显示主窗口
 #[tauri::command]
-pub async fn show_main_window(window: tauri::Window) -> Result<(), String> {
-    window.show().map_err(|e| e.to_string())
+pub async fn show_main_window(window: tauri::Window) -> AppResult<()> {
+    window.show().map_err(AppError::Tauri)
 }
 
 /// 获取 Antigravity 可执行文件路径
 #[tauri::command]
-pub async fn get_antigravity_path(bypass_config: Option<bool>) -> Result<String, String> {
+pub async fn get_antigravity_path(bypass_config: Option<bool>) -> AppResult<String> {
     // 1. 优先从配置查询 (除非明确要求绕过)
     if bypass_config != Some(true) {
         if let Ok(config) = crate::modules::config::load_app_config() {
@@ -644,16 +1019,16 @@ pub async fn get_antigravity_path(bypass_config: Option<bool>) -> Result<String,
     // 2. 执行实时探测
     match crate::modules::process::get_antigravity_executable_path() {
         Some(path) => Ok(path.to_string_lossy().to_string()),
-        None => Err("未找到 Antigravity 安装路径".to_string()),
+        None => Err("未找到 Antigravity 安装路径".into()),
     }
 }
 
 /// 获取 Antigravity 启动参数
 #[tauri::command]
-pub async fn get_antigravity_args() -> Result<Vec<String>, String> {
+pub async fn get_antigravity_args() -> AppResult<Vec<String>> {
     match crate::modules::process::get_args_from_running_process() {
         Some(args) => Ok(args),
-        None => Err("未找到正在运行的 Antigravity 进程".to_string()),
+        None => Err("未找到正在运行的 Antigravity 进程".into()),
     }
 }
 
@@ -662,35 +1037,35 @@ pub use crate::modules::update_checker::UpdateInfo;
 
 /// 检测 GitHub releases 更新
 #[tauri::command]
-pub async fn check_for_updates() -> Result<UpdateInfo, String> {
+pub async fn check_for_updates() -> AppResult<UpdateInfo> {
     modules::logger::log_info("收到前端触发的更新检查请求");
-    crate::modules::update_checker::check_for_updates().await
+    crate::modules::update_checker::check_for_updates().await.map_err(AppError::network)
 }
 
 #[tauri::command]
-pub async fn should_check_updates() -> Result<bool, String> {
-    let settings = crate::modules::update_checker::load_update_settings()?;
+pub async fn should_check_updates() -> AppResult<bool> {
+    let settings = crate::modules::update_checker::load_update_settings().map_err(AppError::io)?;
     Ok(crate::modules::update_checker::should_check_for_updates(&settings))
 }
 
 #[tauri::command]
-pub async fn update_last_check_time() -> Result<(), String> {
-    crate::modules::update_checker::update_last_check_time()
+pub async fn update_last_check_time() -> AppResult<()> {
+    crate::modules::update_checker::update_last_check_time().map_err(AppError::io)
 }
 
 
 /// 获取更新设置
 #[tauri::command]
-pub async fn get_update_settings() -> Result<crate::modules::update_checker::UpdateSettings, String> {
-    crate::modules::update_checker::load_update_settings()
+pub async fn get_update_settings() -> AppResult<crate::modules::update_checker::UpdateSettings> {
+    crate::modules::update_checker::load_update_settings().map_err(AppError::io)
 }
 
 /// 保存更新设置
 #[tauri::command]
 pub async fn save_update_settings(
     settings: crate::modules::update_checker::UpdateSettings,
-) -> Result<(), String> {
-    crate::modules::update_checker::save_update_settings(&settings)
+) -> AppResult<()> {
+    crate::modules::update_checker::save_update_settings(&settings).map_err(AppError::io)
 }
 
 
@@ -703,7 +1078,7 @@ pub async fn toggle_proxy_status(
     account_id: String,
     enable: bool,
     reason: Option<String>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     modules::logger::log_info(&format!(
         "切换账号反代状态: {} -> {}",
         account_id,
@@ -711,15 +1086,15 @@ pub async fn toggle_proxy_status(
     ));
 
     // 1. 读取账号文件
-    let data_dir = modules::account::get_data_dir()?;
+    let data_dir = modules::account::get_data_dir().map_err(AppError::io)?;
     let account_path = data_dir.join("accounts").join(format!("{}.json", account_id));
 
     if !account_path.exists() {
-        return Err(format!("账号文件不存在: {}", account_id));
+        return Err(AppError::AccountNotFound(format!("账号文件不存在: {}", account_id)));
     }
 
     let content = std::fs::read_to_string(&account_path)
-        .map_err(|e| format!("读取账号文件失败: {}", e))?;
+        .map_err(|e| AppError::io(format!("读取账号文件失败: {}", e)))?;
 
     let mut account_json: serde_json::Value = serde_json::from_str(&content)
         .map_err(|e| format!("解析账号文件失败: {}", e))?;
@@ -742,7 +1117,7 @@ pub async fn toggle_proxy_status(
 
     // 3. 保存到磁盘
     std::fs::write(&account_path, serde_json::to_string_pretty(&account_json).unwrap())
-        .map_err(|e| format!("写入账号文件失败: {}", e))?;
+        .map_err(|e| AppError::io(format!("写入账号文件失败: {}", e)))?;
 
     modules::logger::log_info(&format!(
         "账号反代状态已更新: {} ({})",
@@ -761,12 +1136,16 @@ pub async fn toggle_proxy_status(
 
 /// 预热所有可用账号
 #[tauri::command]
-pub async fn warm_up_all_accounts() -> Result<String, String> {
-    modules::quota::warm_up_all_accounts().await
+pub async fn warm_up_all_accounts() -> AppResult<String> {
+    modules::quota::warm_up_all_accounts().await.map_err(AppError::network)
 }
 
 /// 预热指定账号
 #[tauri::command]
-pub async fn warm_up_account(account_id: String) -> Result<String, String> {
-    modules::quota::warm_up_account(&account_id).await
+pub async fn warm_up_account(account_id: String) -> AppResult<String> {
+    let account = modules::load_account(&account_id).map_err(AppError::account)?;
+    if account.quota.as_ref().is_some_and(|q| q.is_forbidden) {
+        return Err(AppError::QuotaForbidden(format!("账号无权限 (403 Forbidden): {}", account.email)));
+    }
+    modules::quota::warm_up_account(&account_id).await.map_err(AppError::network)
 }
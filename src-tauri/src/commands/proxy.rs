@@ -1,4 +1,5 @@
 use tauri::State;
+use crate::error::{AppError, AppResult};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use serde::{Serialize, Deserialize};
@@ -28,6 +29,8 @@ pub struct ProxyServiceInstance {
     pub token_manager: Arc<TokenManager>,
     pub axum_server: crate::proxy::AxumServer,
     pub server_handle: tokio::task::JoinHandle<()>,
+    /// 实际监听的端口 (主端口被占用时为备用端口)
+    pub actual_port: u16,
 }
 
 impl ProxyServiceState {
@@ -45,126 +48,340 @@ pub async fn start_proxy_service(
     config: ProxyConfig,
     state: State<'_, ProxyServiceState>,
     app_handle: tauri::AppHandle,
-) -> Result<ProxyStatus, String> {
+    preflight: Option<bool>,
+) -> AppResult<ProxyStatus> {
     let mut instance_lock = state.instance.write().await;
     
     // 防止重复启动
     if instance_lock.is_some() {
-        return Err("服务已在运行中".to_string());
+        return Err("服务已在运行中".into());
     }
 
-    // Ensure monitor exists
-    {
-        let mut monitor_lock = state.monitor.write().await;
-        if monitor_lock.is_none() {
-            *monitor_lock = Some(Arc::new(ProxyMonitor::new(1000, Some(app_handle.clone()))));
-        }
-        // Sync enabled state from config
-        if let Some(monitor) = monitor_lock.as_ref() {
-            monitor.set_enabled(config.enable_logging);
+    // 可选的启动前检查，存在失败项时直接返回汇总信息
+    if preflight.unwrap_or(false) {
+        let report = preflight_check(config.clone()).await?;
+        if !report.passed {
+            return Err(format!("启动前检查未通过: {}", report.failure_summary()).into());
         }
     }
+
+    let monitor = ensure_monitor(&state, &config, &app_handle).await;
+    let (token_manager, active_accounts) = prepare_token_manager(&config).await?;
+    let instance = launch_instance(&config, token_manager, monitor).await?;
+    let port = instance.actual_port;
+    *instance_lock = Some(instance);
+
+    save_proxy_config(&config)?;
+    notify_port_changed(&app_handle, &config, port);
     
-    let monitor = state.monitor.read().await.as_ref().unwrap().clone();
-    
-    // 2. 初始化 Token 管理器
-    let app_data_dir = crate::modules::account::get_data_dir()?;
+    Ok(ProxyStatus {
+        running: true,
+        port,
+        base_url: format!("http://127.0.0.1:{}", port),
+        active_accounts,
+    })
+}
+
+/// 实际端口与配置端口不同时通知前端 (proxy://port-changed, payload 为实际端口)
+fn notify_port_changed(app_handle: &tauri::AppHandle, config: &ProxyConfig, actual_port: u16) {
+    use tauri::Emitter;
+
+    if actual_port != config.port {
+        let _ = app_handle.emit("proxy://port-changed", actual_port);
+    }
+}
+
+/// 确保监控器存在，并同步日志开关
+async fn ensure_monitor(
+    state: &ProxyServiceState,
+    config: &ProxyConfig,
+    app_handle: &tauri::AppHandle,
+) -> Arc<ProxyMonitor> {
+    let mut monitor_lock = state.monitor.write().await;
+    let monitor = monitor_lock
+        .get_or_insert_with(|| Arc::new(ProxyMonitor::new(1000, Some(app_handle.clone()))))
+        .clone();
+    monitor.set_enabled(config.enable_logging);
+    monitor
+}
+
+/// 初始化 Token 管理器并加载账号，返回可用账号数
+async fn prepare_token_manager(config: &ProxyConfig) -> AppResult<(Arc<TokenManager>, usize)> {
+    let app_data_dir = crate::modules::account::get_data_dir().map_err(AppError::io)?;
     // Ensure accounts dir exists even if the user will only use non-Google providers (e.g. z.ai).
-    let _ = crate::modules::account::get_accounts_dir()?;
+    let _ = crate::modules::account::get_accounts_dir().map_err(AppError::io)?;
     let accounts_dir = app_data_dir.clone();
     
     let token_manager = Arc::new(TokenManager::new(accounts_dir));
     // 同步 UI 传递的调度配置
     token_manager.update_sticky_config(config.scheduling.clone()).await;
+    token_manager
+        .update_rate_limit_config(crate::proxy::token_manager::AccountRateLimitConfig::from_proxy_config(config))
+        .await;
+    token_manager.set_circuit_cooldown(config.circuit_breaker_cooldown_secs);
     
-    // 3. 加载账号
-    let active_accounts = token_manager.load_accounts().await
-        .map_err(|e| format!("加载账号失败: {}", e))?;
+    let active_accounts = match token_manager.load_accounts().await {
+        Ok(n) => n,
+        Err(e) => {
+            token_manager.stop_proactive_refresh();
+            return Err(format!("加载账号失败: {}", e).into());
+        }
+    };
     
     if active_accounts == 0 {
         let zai_enabled = config.zai.enabled
             && !matches!(config.zai.dispatch_mode, crate::proxy::ZaiDispatchMode::Off);
         if !zai_enabled {
-            return Err("没有可用账号，请先添加账号".to_string());
+            token_manager.stop_proactive_refresh();
+            return Err("没有可用账号，请先添加账号".into());
         }
     }
-    
-    // 启动 Axum 服务器
+    Ok((token_manager, active_accounts))
+}
+
+/// 启动 Axum 服务器，失败时停止 Token 管理器的后台刷新
+async fn launch_instance(
+    config: &ProxyConfig,
+    token_manager: Arc<TokenManager>,
+    monitor: Arc<ProxyMonitor>,
+) -> AppResult<ProxyServiceInstance> {
     let (axum_server, server_handle) =
         match crate::proxy::AxumServer::start(
             config.get_bind_address().to_string(),
             config.port,
+            config.port_fallback_range,
             token_manager.clone(),
             config.custom_mapping.clone(),
+            config.model_aliases.clone(),
             config.request_timeout,
             config.upstream_proxy.clone(),
-            crate::proxy::ProxySecurityConfig::from_proxy_config(&config),
+            crate::proxy::ProxySecurityConfig::from_proxy_config(config),
             config.zai.clone(),
-            monitor.clone(),
+            monitor,
             config.experimental.clone(),
-
+            crate::proxy::upstream::backoff::ExponentialBackoff::from_proxy_config(config),
+            config.mistral_compat,
+            config.mock_moderation_endpoint,
+            crate::proxy::middleware::DeduplicateLayer::from_proxy_config(config),
+            config.include_usage_headers,
+            config.fallback_model_on_503.clone(),
+            config.compression_threshold_bytes,
+            crate::proxy::middleware::RequestSizeLimitLayer::from_proxy_config(config),
+            config.propagate_request_id,
+            crate::proxy::middleware::CorsConfig::from_proxy_config(config),
+            crate::proxy::upstream::stream_timeout::StreamTimeouts::from_proxy_config(config),
+            config.max_input_tokens,
+            crate::proxy::handlers::response_cache::ResponseCache::from_proxy_config(config),
+            crate::proxy::upstream::client::UpstreamClientConfig::from_proxy_config(config),
+            crate::proxy::stream_recorder::StreamRecorder::from_proxy_config(config),
         ).await {
             Ok((server, handle)) => (server, handle),
-            Err(e) => return Err(format!("启动 Axum 服务器失败: {}", e)),
+            Err(e) => {
+                token_manager.stop_proactive_refresh();
+                return Err(format!("启动 Axum 服务器失败: {}", e).into());
+            }
         };
     
-    // 创建服务实例
-    let instance = ProxyServiceInstance {
+    Ok(ProxyServiceInstance {
         config: config.clone(),
-        token_manager: token_manager.clone(), // Clone for ProxyServiceInstance
+        token_manager,
+        actual_port: axum_server.port(),
         axum_server,
         server_handle,
-    };
-    
-    *instance_lock = Some(instance);
-    
+    })
+}
+
+/// 优雅停止服务实例，返回强制关闭时仍未完成的请求数
+async fn shutdown_instance(instance: ProxyServiceInstance) -> usize {
+    let timeout = Duration::from_secs(instance.config.shutdown_timeout_secs);
+    let remaining = instance.axum_server.stop(timeout).await;
+    // 等待服务器任务完成
+    instance.server_handle.await.ok();
+    instance.token_manager.stop_proactive_refresh();
+    instance.token_manager.flush_health().await;
+    // 保存熔断状态，重启后恢复
+    if let Err(e) = instance.token_manager.save_state() {
+        tracing::warn!("保存反代状态失败: {}", e);
+    }
+    remaining
+}
 
-    // 保存配置到全局 AppConfig
-    let mut app_config = crate::modules::config::load_app_config().map_err(|e| e)?;
+/// 保存配置到全局 AppConfig
+fn save_proxy_config(config: &ProxyConfig) -> AppResult<()> {
+    let mut app_config = crate::modules::config::load_app_config()?;
     app_config.proxy = config.clone();
-    crate::modules::config::save_app_config(&app_config).map_err(|e| e)?;
-    
-    Ok(ProxyStatus {
-        running: true,
-        port: config.port,
-        base_url: format!("http://127.0.0.1:{}", config.port),
-        active_accounts,
-    })
+    crate::modules::config::save_app_config(&app_config)
 }
 
-/// 停止反代服务
+/// 新实例就绪检查的最长等待时间
+const RESTART_READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 轮询 /healthz 直到新实例可以响应请求
+async fn wait_until_ready(port: u16, api_key: &str, timeout: Duration) -> AppResult<()> {
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .timeout(Duration::from_secs(1))
+        .build()
+        .map_err(|e| AppError::network(e.to_string()))?;
+    let url = format!("http://127.0.0.1:{}/healthz", port);
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let ready = client
+            .get(&url)
+            .bearer_auth(api_key)
+            .send()
+            .await
+            .is_ok_and(|r| r.status().is_success());
+        if ready {
+            return Ok(());
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Err(format!("新实例在 {} 秒内未就绪 (端口 {})", timeout.as_secs(), port).into());
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+/// 请求运行中反代服务的 /health 端点 (503 时同样返回 degraded 状态 JSON)
 #[tauri::command]
-pub async fn stop_proxy_service(
+pub async fn check_proxy_health(state: State<'_, ProxyServiceState>) -> AppResult<serde_json::Value> {
+    let port = match state.instance.read().await.as_ref() {
+        Some(instance) => instance.actual_port,
+        None => return Err("反代服务未运行".into()),
+    };
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .map_err(|e| AppError::network(e.to_string()))?;
+    client
+        .get(format!("http://127.0.0.1:{}/health", port))
+        .send()
+        .await
+        .map_err(|e| AppError::network(format!("健康检查请求失败: {}", e)))?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| AppError::network(format!("解析健康检查结果失败: {}", e)))
+}
+
+/// 启动实例并等待就绪，未就绪时关闭该实例
+async fn launch_ready_instance(
+    config: &ProxyConfig,
+    token_manager: Arc<TokenManager>,
+    monitor: Arc<ProxyMonitor>,
+) -> AppResult<ProxyServiceInstance> {
+    let instance = launch_instance(config, token_manager, monitor).await?;
+    if let Err(e) = wait_until_ready(instance.actual_port, &config.api_key, RESTART_READY_TIMEOUT).await {
+        shutdown_instance(instance).await;
+        return Err(e);
+    }
+    Ok(instance)
+}
+
+/// 使用新配置重启反代服务
+///
+/// 端口变化时先在新端口启动并确认就绪，再优雅停止旧实例，期间服务不中断；
+/// 同端口无法同时监听，先完成账号加载等准备工作，再短暂停机切换，新实例启动失败时恢复旧配置
+#[tauri::command]
+pub async fn restart_proxy_service(
+    config: ProxyConfig,
     state: State<'_, ProxyServiceState>,
-) -> Result<(), String> {
+    app_handle: tauri::AppHandle,
+) -> AppResult<()> {
     let mut instance_lock = state.instance.write().await;
-    
-    if instance_lock.is_none() {
-        return Err("服务未运行".to_string());
+    let monitor = ensure_monitor(&state, &config, &app_handle).await;
+    // 新管理器从 proxy_state.json 恢复熔断状态，构建前先保存旧实例的最新状态
+    if let Some(old) = instance_lock.as_ref() {
+        old.token_manager.flush_health().await;
+        if let Err(e) = old.token_manager.save_state() {
+            tracing::warn!("保存反代状态失败: {}", e);
+        }
     }
-    
-    // 停止 Axum 服务器
-    if let Some(instance) = instance_lock.take() {
-        instance.axum_server.stop();
-        // 等待服务器任务完成
-        instance.server_handle.await.ok();
+    let (token_manager, _) = prepare_token_manager(&config).await?;
+
+    let Some(old) = instance_lock.take() else {
+        // 服务未运行时等同于启动
+        let instance = launch_ready_instance(&config, token_manager, monitor).await?;
+        notify_port_changed(&app_handle, &config, instance.actual_port);
+        *instance_lock = Some(instance);
+        return save_proxy_config(&config);
+    };
+
+    // 与旧实例实际监听的端口 (可能是备用端口) 比较，端口不冲突时可以先启动新实例
+    let mut retired = None;
+    if old.actual_port != config.port {
+        match launch_ready_instance(&config, token_manager, monitor).await {
+            Ok(new) => {
+                *instance_lock = Some(new);
+                retired = Some(old);
+            }
+            Err(e) => {
+                // 新实例启动失败，旧实例继续提供服务
+                *instance_lock = Some(old);
+                return Err(e);
+            }
+        }
+    } else {
+        let old_config = old.config.clone();
+        // 回退时直接复用旧实例的账号池，避免再次加载账号失败导致没有实例在运行
+        let old_token_manager = old.token_manager.clone();
+        shutdown_instance(old).await;
+        match launch_ready_instance(&config, token_manager, monitor.clone()).await {
+            Ok(new) => *instance_lock = Some(new),
+            Err(e) => {
+                tracing::warn!("反代服务重启失败，正在恢复原配置: {}", e);
+                old_token_manager.start_proactive_refresh();
+                return match launch_instance(&old_config, old_token_manager, monitor).await {
+                    Ok(restored) => {
+                        *instance_lock = Some(restored);
+                        Err(AppError::Unknown(format!("重启失败，已恢复原配置: {}", e)))
+                    }
+                    Err(restore_err) => Err(AppError::Unknown(format!(
+                        "重启失败: {}; 恢复原配置也失败: {}",
+                        e, restore_err
+                    ))),
+                };
+            }
+        }
+    }
+
+    let actual_port = instance_lock.as_ref().map_or(config.port, |i| i.actual_port);
+    // 新实例已接管，释放写锁后再等待旧实例排空，期间状态查询等命令不被阻塞
+    drop(instance_lock);
+    if let Some(old) = retired {
+        shutdown_instance(old).await;
+    }
+    tracing::info!("反代服务已使用新配置重启 (端口 {})", actual_port);
+    notify_port_changed(&app_handle, &config, actual_port);
+    save_proxy_config(&config)
+}
+
+/// 停止反代服务
+#[tauri::command]
+pub async fn stop_proxy_service(
+    state: State<'_, ProxyServiceState>,
+) -> AppResult<usize> {
+    // 取出实例后立即释放写锁，排空期间不阻塞状态查询等其它命令
+    let instance = state.instance.write().await.take();
+
+    match instance {
+        Some(instance) => Ok(shutdown_instance(instance).await),
+        None => Err("服务未运行".into()),
     }
-    
-    Ok(())
 }
 
 /// 获取反代服务状态
 #[tauri::command]
 pub async fn get_proxy_status(
     state: State<'_, ProxyServiceState>,
-) -> Result<ProxyStatus, String> {
+) -> AppResult<ProxyStatus> {
     let instance_lock = state.instance.read().await;
     
     match instance_lock.as_ref() {
         Some(instance) => Ok(ProxyStatus {
             running: true,
-            port: instance.config.port,
-            base_url: format!("http://127.0.0.1:{}", instance.config.port),
+            port: instance.actual_port,
+            base_url: format!("http://127.0.0.1:{}", instance.actual_port),
             active_accounts: instance.token_manager.len(),
         }),
         None => Ok(ProxyStatus {
@@ -180,13 +397,46 @@ pub async fn get_proxy_status(
 #[tauri::command]
 pub async fn get_proxy_stats(
     state: State<'_, ProxyServiceState>,
-) -> Result<ProxyStats, String> {
-    let monitor_lock = state.monitor.read().await;
-    if let Some(monitor) = monitor_lock.as_ref() {
-        Ok(monitor.get_stats().await)
-    } else {
-        Ok(ProxyStats::default())
+) -> AppResult<ProxyStats> {
+    // 反代运行时请求总数只统计上次清零 (或服务启动) 之后的日志，与按模型 / 按账号统计保持一致
+    let instance_lock = state.instance.read().await;
+    let reset_at = instance_lock.as_ref().map(|instance| {
+        let elapsed = chrono::Duration::from_std(instance.axum_server.last_stats_reset_at().elapsed())
+            .unwrap_or_default();
+        chrono::Utc::now() - elapsed
+    });
+
+    let mut stats = {
+        let monitor_lock = state.monitor.read().await;
+        match monitor_lock.as_ref() {
+            Some(monitor) => monitor.get_stats(reset_at.map(|t| t.timestamp_millis())).await,
+            None => ProxyStats::default(),
+        }
+    };
+
+    if let Some(instance) = instance_lock.as_ref() {
+        let (model_stats, account_stats) = instance.axum_server.usage_snapshot();
+        stats.model_stats = model_stats;
+        stats.account_stats = account_stats;
+        stats.actual_port = instance.actual_port;
+        stats.last_stats_reset_at = reset_at.map(|t| t.to_rfc3339());
     }
+    Ok(stats)
+}
+
+/// 清零反代用量统计 (按模型 / 按账号)，并通知前端刷新
+#[tauri::command]
+pub async fn clear_proxy_stats(
+    state: State<'_, ProxyServiceState>,
+    app: tauri::AppHandle,
+) -> AppResult<()> {
+    use tauri::Emitter;
+
+    let instance_lock = state.instance.write().await;
+    let instance = instance_lock.as_ref().ok_or("服务未运行")?;
+    instance.axum_server.reset_stats();
+    let _ = app.emit("proxy://stats-cleared", ());
+    Ok(())
 }
 
 /// 获取反代请求日志
@@ -194,7 +444,7 @@ pub async fn get_proxy_stats(
 pub async fn get_proxy_logs(
     state: State<'_, ProxyServiceState>,
     limit: Option<usize>,
-) -> Result<Vec<ProxyRequestLog>, String> {
+) -> AppResult<Vec<ProxyRequestLog>> {
     let monitor_lock = state.monitor.read().await;
     if let Some(monitor) = monitor_lock.as_ref() {
         Ok(monitor.get_logs(limit.unwrap_or(100)).await)
@@ -208,7 +458,7 @@ pub async fn get_proxy_logs(
 pub async fn set_proxy_monitor_enabled(
     state: State<'_, ProxyServiceState>,
     enabled: bool,
-) -> Result<(), String> {
+) -> AppResult<()> {
     let monitor_lock = state.monitor.read().await;
     if let Some(monitor) = monitor_lock.as_ref() {
         monitor.set_enabled(enabled);
@@ -220,7 +470,7 @@ pub async fn set_proxy_monitor_enabled(
 #[tauri::command]
 pub async fn clear_proxy_logs(
     state: State<'_, ProxyServiceState>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     let monitor_lock = state.monitor.read().await;
     if let Some(monitor) = monitor_lock.as_ref() {
         monitor.clear().await;
@@ -233,19 +483,19 @@ pub async fn clear_proxy_logs(
 pub async fn get_proxy_logs_paginated(
     limit: Option<usize>,
     offset: Option<usize>,
-) -> Result<Vec<ProxyRequestLog>, String> {
+) -> AppResult<Vec<ProxyRequestLog>> {
     crate::modules::proxy_db::get_logs_summary(
         limit.unwrap_or(20),
         offset.unwrap_or(0)
-    )
+    ).map_err(AppError::from)
 }
 
 /// 获取单条日志的完整详情
 #[tauri::command]
 pub async fn get_proxy_log_detail(
     log_id: String,
-) -> Result<ProxyRequestLog, String> {
-    crate::modules::proxy_db::get_log_detail(&log_id)
+) -> AppResult<ProxyRequestLog> {
+    crate::modules::proxy_db::get_log_detail(&log_id).map_err(AppError::from)
 }
 
 /// 生成 API Key
@@ -254,20 +504,49 @@ pub fn generate_api_key() -> String {
     format!("sk-{}", uuid::Uuid::new_v4().simple())
 }
 
+/// 生成仅能使用指定账号的 API Key 并持久化到 `api_key_routes`
+#[tauri::command]
+pub async fn generate_api_key_for_accounts(
+    account_ids: Vec<String>,
+    state: State<'_, ProxyServiceState>,
+) -> AppResult<String> {
+    if account_ids.is_empty() {
+        return Err("至少需要指定一个账号".into());
+    }
+    let known: std::collections::HashSet<String> = crate::modules::account::list_accounts().map_err(AppError::account)?
+        .into_iter()
+        .map(|a| a.id)
+        .collect();
+    if let Some(missing) = account_ids.iter().find(|id| !known.contains(*id)) {
+        return Err(AppError::AccountNotFound(format!("账号不存在: {}", missing)));
+    }
+
+    let api_key = generate_api_key();
+    let mut app_config = crate::modules::config::load_app_config()?;
+    app_config.proxy.api_key_routes.insert(api_key.clone(), account_ids);
+    crate::modules::config::save_app_config(&app_config)?;
+
+    // 服务运行中则立即生效
+    if let Some(instance) = state.instance.read().await.as_ref() {
+        instance.axum_server.update_security(&app_config.proxy).await;
+    }
+    Ok(api_key)
+}
+
 /// 重新加载账号（当主应用添加/删除账号时调用）
 #[tauri::command]
 pub async fn reload_proxy_accounts(
     state: State<'_, ProxyServiceState>,
-) -> Result<usize, String> {
+) -> AppResult<usize> {
     let instance_lock = state.instance.read().await;
     
     if let Some(instance) = instance_lock.as_ref() {
         // 重新加载账号
         let count = instance.token_manager.load_accounts().await
-            .map_err(|e| format!("重新加载账号失败: {}", e))?;
+            .map_err(|e| AppError::account(format!("重新加载账号失败: {}", e)))?;
         Ok(count)
     } else {
-        Err("服务未运行".to_string())
+        Err("服务未运行".into())
     }
 }
 
@@ -276,7 +555,7 @@ pub async fn reload_proxy_accounts(
 pub async fn update_model_mapping(
     config: ProxyConfig,
     state: State<'_, ProxyServiceState>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     let instance_lock = state.instance.read().await;
     
     // 1. 如果服务正在运行，立即更新内存中的映射 (这里目前只更新了 anthropic_mapping 的 RwLock, 
@@ -287,13 +566,24 @@ pub async fn update_model_mapping(
     }
     
     // 2. 无论是否运行，都保存到全局配置持久化
-    let mut app_config = crate::modules::config::load_app_config().map_err(|e| e)?;
+    let mut app_config = crate::modules::config::load_app_config()?;
     app_config.proxy.custom_mapping = config.custom_mapping;
-    crate::modules::config::save_app_config(&app_config).map_err(|e| e)?;
+    app_config.proxy.model_aliases = config.model_aliases;
+    crate::modules::config::save_app_config(&app_config)?;
     
     Ok(())
 }
 
+/// 获取合并后的有效模型映射表 (内置映射 + 自定义映射 + 模型别名)
+#[tauri::command]
+pub async fn get_effective_model_mapping() -> AppResult<std::collections::HashMap<String, String>> {
+    let app_config = crate::modules::config::load_app_config()?;
+    Ok(crate::proxy::common::model_mapping::effective_model_mapping(
+        &app_config.proxy.model_aliases,
+        &app_config.proxy.custom_mapping,
+    ))
+}
+
 fn join_base_url(base: &str, path: &str) -> String {
     let base = base.trim_end_matches('/');
     let path = if path.starts_with('/') {
@@ -358,12 +648,12 @@ pub async fn fetch_zai_models(
     zai: crate::proxy::ZaiConfig,
     upstream_proxy: crate::proxy::config::UpstreamProxyConfig,
     request_timeout: u64,
-) -> Result<Vec<String>, String> {
+) -> AppResult<Vec<String>> {
     if zai.base_url.trim().is_empty() {
-        return Err("z.ai base_url is empty".to_string());
+        return Err("z.ai base_url is empty".into());
     }
     if zai.api_key.trim().is_empty() {
-        return Err("z.ai api_key is not set".to_string());
+        return Err("z.ai api_key is not set".into());
     }
 
     let url = join_base_url(&zai.base_url, "/v1/models");
@@ -371,12 +661,12 @@ pub async fn fetch_zai_models(
     let mut builder = reqwest::Client::builder().timeout(Duration::from_secs(request_timeout.max(5)));
     if upstream_proxy.enabled && !upstream_proxy.url.is_empty() {
         let proxy = reqwest::Proxy::all(&upstream_proxy.url)
-            .map_err(|e| format!("Invalid upstream proxy url: {}", e))?;
+            .map_err(|e| AppError::network(format!("Invalid upstream proxy url: {}", e)))?;
         builder = builder.proxy(proxy);
     }
     let client = builder
         .build()
-        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+        .map_err(|e| AppError::network(format!("Failed to build HTTP client: {}", e)))?;
 
     let resp = client
         .get(&url)
@@ -386,18 +676,18 @@ pub async fn fetch_zai_models(
         .header("accept", "application/json")
         .send()
         .await
-        .map_err(|e| format!("Upstream request failed: {}", e))?;
+        .map_err(|e| AppError::network(format!("Upstream request failed: {}", e)))?;
 
     let status = resp.status();
-    let text = resp.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
+    let text = resp.text().await.map_err(|e| AppError::network(format!("Failed to read response: {}", e)))?;
 
     if !status.is_success() {
         let preview = if text.len() > 4000 { &text[..4000] } else { &text };
-        return Err(format!("Upstream returned {}: {}", status, preview));
+        return Err(AppError::network(format!("Upstream returned {}: {}", status, preview)));
     }
 
     let json: serde_json::Value =
-        serde_json::from_str(&text).map_err(|e| format!("Invalid JSON response: {}", e))?;
+        serde_json::from_str(&text).map_err(|e| AppError::network(format!("Invalid JSON response: {}", e)))?;
     let mut models = extract_model_ids(&json);
     models.retain(|s| !s.trim().is_empty());
     models.sort();
@@ -409,7 +699,7 @@ pub async fn fetch_zai_models(
 #[tauri::command]
 pub async fn get_proxy_scheduling_config(
     state: State<'_, ProxyServiceState>,
-) -> Result<crate::proxy::sticky_config::StickySessionConfig, String> {
+) -> AppResult<crate::proxy::sticky_config::StickySessionConfig> {
     let instance_lock = state.instance.read().await;
     if let Some(instance) = instance_lock.as_ref() {
         Ok(instance.token_manager.get_sticky_config().await)
@@ -423,13 +713,13 @@ pub async fn get_proxy_scheduling_config(
 pub async fn update_proxy_scheduling_config(
     state: State<'_, ProxyServiceState>,
     config: crate::proxy::sticky_config::StickySessionConfig,
-) -> Result<(), String> {
+) -> AppResult<()> {
     let instance_lock = state.instance.read().await;
     if let Some(instance) = instance_lock.as_ref() {
         instance.token_manager.update_sticky_config(config).await;
         Ok(())
     } else {
-        Err("服务未运行，无法更新实时配置".to_string())
+        Err("服务未运行，无法更新实时配置".into())
     }
 }
 
@@ -437,13 +727,457 @@ pub async fn update_proxy_scheduling_config(
 #[tauri::command]
 pub async fn clear_proxy_session_bindings(
     state: State<'_, ProxyServiceState>,
-) -> Result<(), String> {
+) -> AppResult<()> {
     let instance_lock = state.instance.read().await;
     if let Some(instance) = instance_lock.as_ref() {
         instance.token_manager.clear_all_sessions();
         Ok(())
     } else {
-        Err("服务未运行".to_string())
+        Err("服务未运行".into())
+    }
+}
+
+/// 获取所有账号的熔断状态
+#[tauri::command]
+pub async fn get_circuit_states(
+    state: State<'_, ProxyServiceState>,
+) -> AppResult<Vec<crate::proxy::token_manager::AccountCircuitInfo>> {
+    let instance_lock = state.instance.read().await;
+    if let Some(instance) = instance_lock.as_ref() {
+        Ok(instance.token_manager.get_circuit_states())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// 获取各账号的请求延迟统计 (EMA / P50 / P95)，反代未运行时返回空列表
+#[tauri::command]
+pub async fn get_account_latency_stats(
+    state: State<'_, ProxyServiceState>,
+) -> AppResult<Vec<crate::proxy::latency_stats::AccountLatencyInfo>> {
+    let instance_lock = state.instance.read().await;
+    if let Some(instance) = instance_lock.as_ref() {
+        Ok(instance.token_manager.get_latency_stats())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// 加载录制的 SSE 文件，之后可通过 GET /debug/replay 回放 (调试用)
+#[tauri::command]
+pub async fn replay_stream(
+    state: State<'_, ProxyServiceState>,
+    path: String,
+) -> AppResult<()> {
+    let content = tokio::fs::read(&path)
+        .await
+        .map_err(|e| AppError::io(format!("读取录制文件失败: {}", e)))?;
+    let instance_lock = state.instance.read().await;
+    let instance = instance_lock.as_ref().ok_or_else(|| AppError::from("服务未运行".to_string()))?;
+    instance.axum_server.load_stream_replay(bytes::Bytes::from(content));
+    Ok(())
+}
+
+/// 获取当前打开的流式连接 (反代未运行时返回 0)
+#[tauri::command]
+pub async fn list_active_streams(
+    state: State<'_, ProxyServiceState>,
+) -> AppResult<crate::proxy::active_streams::ActiveStreamStats> {
+    let instance_lock = state.instance.read().await;
+    Ok(match instance_lock.as_ref() {
+        Some(instance) => instance.axum_server.active_stream_stats(),
+        None => crate::proxy::active_streams::ActiveStreamStats {
+            count: 0,
+            oldest_started_at: None,
+        },
+    })
+}
+
+/// 获取反代审计日志 (最新在前，支持分页)
+#[tauri::command]
+pub async fn get_audit_log_entries(
+    limit: usize,
+    offset: usize,
+) -> AppResult<Vec<crate::proxy::middleware::logging::AuditLogEntry>> {
+    let logger = crate::proxy::middleware::logging::audit_logger()
+        .ok_or_else(|| "审计日志不可用".to_string())?;
+    let entries = tokio::task::spawn_blocking(move || logger.read_entries(limit, offset))
+        .await
+        .map_err(|e| AppError::io(format!("读取审计日志失败: {}", e)))?
+        .map_err(AppError::io)?;
+    Ok(entries)
+}
+
+/// 启动前检查项状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreflightStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// 单个启动前检查项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreflightCheck {
+    pub name: String,
+    pub status: PreflightStatus,
+    pub message: String,
+}
+
+impl PreflightCheck {
+    fn new(name: &str, status: PreflightStatus, message: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// 启动前检查报告 (无 Fail 项即视为通过)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreflightReport {
+    pub checks: Vec<PreflightCheck>,
+    pub passed: bool,
+}
+
+impl PreflightReport {
+    fn from_checks(checks: Vec<PreflightCheck>) -> Self {
+        let passed = checks.iter().all(|c| c.status != PreflightStatus::Fail);
+        Self { checks, passed }
+    }
+
+    /// 失败项汇总 (用于启动失败时的错误信息)
+    pub fn failure_summary(&self) -> String {
+        self.checks
+            .iter()
+            .filter(|c| c.status == PreflightStatus::Fail)
+            .map(|c| format!("[{}] {}", c.name, c.message))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+fn check_port(bind_address: &str, port: u16, fallback_range: Option<(u16, u16)>) -> PreflightCheck {
+    // 仅试探绑定，立即释放
+    let err = match std::net::TcpListener::bind((bind_address, port)) {
+        Ok(_) => return PreflightCheck::new("port", PreflightStatus::Pass, format!("端口 {} 可用", port)),
+        Err(e) => e,
+    };
+    let fallback = fallback_range.and_then(|(start, end)| {
+        (start..=end).find(|p| *p != port && std::net::TcpListener::bind((bind_address, *p)).is_ok())
+    });
+    match fallback {
+        Some(p) => PreflightCheck::new(
+            "port",
+            PreflightStatus::Warn,
+            format!("端口 {} 不可用，将改用备用端口 {}", port, p),
+        ),
+        None => PreflightCheck::new(
+            "port",
+            PreflightStatus::Fail,
+            format!("端口 {} 不可用: {}", port, err),
+        ),
+    }
+}
+
+fn check_upstream_proxy(upstream: &crate::proxy::config::UpstreamProxyConfig) -> PreflightCheck {
+    if !upstream.enabled {
+        return PreflightCheck::new("upstream_proxy", PreflightStatus::Pass, "未启用上游代理");
+    }
+    let url = upstream.url.trim();
+    if url.is_empty() {
+        return PreflightCheck::new("upstream_proxy", PreflightStatus::Fail, "已启用上游代理但未填写地址");
+    }
+    match crate::modules::config::validate_proxy_url(url) {
+        Ok(()) => PreflightCheck::new("upstream_proxy", PreflightStatus::Pass, format!("上游代理地址有效: {}", url)),
+        Err(e) => PreflightCheck::new("upstream_proxy", PreflightStatus::Fail, e),
+    }
+}
+
+fn check_api_key(config: &ProxyConfig) -> PreflightCheck {
+    let auth_mode = crate::proxy::ProxySecurityConfig::from_proxy_config(config).effective_auth_mode();
+    if matches!(auth_mode, crate::proxy::ProxyAuthMode::Off) {
+        return PreflightCheck::new("api_key", PreflightStatus::Pass, "未启用鉴权");
     }
+    if config.api_key.trim().is_empty() && config.api_key_routes.is_empty() {
+        return PreflightCheck::new("api_key", PreflightStatus::Fail, "已启用鉴权但未设置 API Key");
+    }
+    PreflightCheck::new("api_key", PreflightStatus::Pass, "API Key 已设置")
 }
 
+/// 检查可用账号，返回检查结果与第一个可用账号 (用于连通性检查)
+fn check_accounts(
+    accounts: &[crate::models::Account],
+    zai_enabled: bool,
+) -> (PreflightCheck, Option<&crate::models::Account>) {
+    let available: Vec<_> = accounts
+        .iter()
+        .filter(|a| !a.disabled && !a.proxy_disabled)
+        .filter(|a| !a.quota.as_ref().is_some_and(|q| q.is_forbidden))
+        .collect();
+
+    let check = match (available.len(), zai_enabled) {
+        (0, false) => PreflightCheck::new("accounts", PreflightStatus::Fail, "没有可用账号，请先添加账号"),
+        (0, true) => PreflightCheck::new("accounts", PreflightStatus::Warn, "没有可用账号，仅能使用 z.ai 转发"),
+        (n, _) => PreflightCheck::new("accounts", PreflightStatus::Pass, format!("{} 个可用账号", n)),
+    };
+    (check, available.first().copied())
+}
+
+async fn check_connectivity(
+    account: &crate::models::Account,
+    upstream: &crate::proxy::config::UpstreamProxyConfig,
+) -> PreflightCheck {
+    let token = match crate::modules::oauth::ensure_fresh_token(&account.token).await {
+        Ok(t) => t,
+        Err(e) => {
+            return PreflightCheck::new(
+                "connectivity",
+                PreflightStatus::Fail,
+                format!("刷新账号 {} 的 Token 失败: {}", account.email, e),
+            )
+        }
+    };
+
+    let client = crate::utils::http::create_client_with_proxy(10, Some(upstream.clone()));
+    let url = format!("{}:loadCodeAssist", crate::proxy::upstream::client::V1_INTERNAL_BASE_URL_PROD);
+    match client.head(&url).bearer_auth(&token.access_token).send().await {
+        // 任何 HTTP 响应都说明网络可达，401/403 说明 Token 被拒绝
+        Ok(resp) if matches!(resp.status().as_u16(), 401 | 403) => PreflightCheck::new(
+            "connectivity",
+            PreflightStatus::Warn,
+            format!("上游可达，但账号 {} 的 Token 被拒绝 (HTTP {})", account.email, resp.status()),
+        ),
+        Ok(resp) => PreflightCheck::new(
+            "connectivity",
+            PreflightStatus::Pass,
+            format!("上游可达 (HTTP {})", resp.status().as_u16()),
+        ),
+        Err(e) => PreflightCheck::new(
+            "connectivity",
+            PreflightStatus::Fail,
+            format!("无法连接 Gemini 上游: {}", e),
+        ),
+    }
+}
+
+/// 启动前检查: 端口、上游代理、API Key、可用账号与上游连通性
+#[tauri::command]
+pub async fn preflight_check(config: ProxyConfig) -> AppResult<PreflightReport> {
+    let mut checks = vec![
+        check_port(config.get_bind_address(), config.port, config.port_fallback_range),
+        check_upstream_proxy(&config.upstream_proxy),
+        check_api_key(&config),
+    ];
+
+    let accounts = crate::modules::account::list_accounts()?;
+    let zai_enabled = config.zai.enabled
+        && !matches!(config.zai.dispatch_mode, crate::proxy::ZaiDispatchMode::Off);
+    let (account_check, first_account) = check_accounts(&accounts, zai_enabled);
+    checks.push(account_check);
+
+    let proxy_ok = checks
+        .iter()
+        .any(|c| c.name == "upstream_proxy" && c.status == PreflightStatus::Pass);
+    match first_account {
+        Some(account) if proxy_ok => checks.push(check_connectivity(account, &config.upstream_proxy).await),
+        Some(_) => checks.push(PreflightCheck::new(
+            "connectivity",
+            PreflightStatus::Warn,
+            "上游代理配置无效，已跳过连通性检查",
+        )),
+        None => checks.push(PreflightCheck::new(
+            "connectivity",
+            PreflightStatus::Warn,
+            "没有可用账号，已跳过连通性检查",
+        )),
+    }
+
+    Ok(PreflightReport::from_checks(checks))
+}
+
+/// 基础连通性测试的目标主机
+const CONNECTION_TEST_HOST: &str = "generativelanguage.googleapis.com";
+const CONNECTION_TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 基础连通性测试结果 (DNS -> TCP -> TLS，可选上游代理)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConnectionTestResult {
+    pub dns_resolved: bool,
+    pub tcp_connected: bool,
+    pub tls_valid: bool,
+    /// 未启用上游代理时为 None
+    pub proxy_reachable: Option<bool>,
+    pub latency_ms: u64,
+    /// 第一个失败步骤的错误信息
+    pub error: Option<String>,
+}
+
+/// 依次测试 DNS 解析、TCP 连接、TLS 握手 (证书链校验)，以及经上游代理的访问
+/// 使用独立的 reqwest::Client，不携带任何凭据
+async fn run_connection_test(
+    host: &str,
+    port: u16,
+    upstream: &crate::proxy::config::UpstreamProxyConfig,
+) -> ConnectionTestResult {
+    let start = std::time::Instant::now();
+    let mut result = ConnectionTestResult::default();
+    let url = format!("https://{}:{}/", host, port);
+
+    let direct = async {
+        let addr = tokio::time::timeout(CONNECTION_TEST_TIMEOUT, tokio::net::lookup_host((host, port)))
+            .await
+            .map_err(|_| "DNS 解析超时".to_string())?
+            .map_err(|e| format!("DNS 解析失败: {}", e))?
+            .next()
+            .ok_or_else(|| "DNS 解析未返回任何地址".to_string())?;
+        result.dns_resolved = true;
+
+        tokio::time::timeout(CONNECTION_TEST_TIMEOUT, tokio::net::TcpStream::connect(addr))
+            .await
+            .map_err(|_| format!("TCP 连接 {} 超时", addr))?
+            .map_err(|e| format!("TCP 连接 {} 失败: {}", addr, e))?;
+        result.tcp_connected = true;
+
+        // 收到任何 HTTP 响应即说明 TLS 握手与证书校验通过
+        let client = reqwest::Client::builder()
+            .timeout(CONNECTION_TEST_TIMEOUT)
+            .no_proxy()
+            .build()
+            .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
+        client
+            .head(&url)
+            .send()
+            .await
+            .map_err(|e| format!("TLS 握手失败: {}", e))?;
+        result.tls_valid = true;
+        Ok::<(), String>(())
+    }
+    .await;
+    result.error = direct.err();
+
+    if upstream.enabled && !upstream.url.trim().is_empty() {
+        let via_proxy = async {
+            let proxy = reqwest::Proxy::all(upstream.url.trim())
+                .map_err(|e| format!("上游代理地址无效: {}", e))?;
+            let client = reqwest::Client::builder()
+                .timeout(CONNECTION_TEST_TIMEOUT)
+                .proxy(proxy)
+                .build()
+                .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
+            client
+                .head(&url)
+                .send()
+                .await
+                .map_err(|e| format!("经上游代理访问失败: {}", e))?;
+            Ok::<(), String>(())
+        }
+        .await;
+        result.proxy_reachable = Some(via_proxy.is_ok());
+        if result.error.is_none() {
+            result.error = via_proxy.err();
+        }
+    }
+
+    result.latency_ms = start.elapsed().as_millis() as u64;
+    result
+}
+
+/// 基础网络连通性测试 (排查鉴权问题前先确认能否访问 Gemini API)
+#[tauri::command]
+pub async fn connection_test() -> AppResult<ConnectionTestResult> {
+    let upstream = crate::modules::config::load_app_config()
+        .map(|c| c.proxy.upstream_proxy)
+        .unwrap_or_default();
+    Ok(run_connection_test(CONNECTION_TEST_HOST, 443, &upstream).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::config::UpstreamProxyConfig;
+
+    fn upstream(enabled: bool, url: &str) -> UpstreamProxyConfig {
+        UpstreamProxyConfig {
+            enabled,
+            url: url.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_preflight_upstream_proxy_and_api_key() {
+        assert_eq!(check_upstream_proxy(&upstream(false, "")).status, PreflightStatus::Pass);
+        assert_eq!(check_upstream_proxy(&upstream(true, "")).status, PreflightStatus::Fail);
+        assert_eq!(
+            check_upstream_proxy(&upstream(true, "socks5://127.0.0.1:1080")).status,
+            PreflightStatus::Pass
+        );
+        assert_eq!(
+            check_upstream_proxy(&upstream(true, "ftp://127.0.0.1:21")).status,
+            PreflightStatus::Fail
+        );
+        // 与 reqwest 一致: 省略协议按 http 处理
+        assert_eq!(check_upstream_proxy(&upstream(true, "127.0.0.1:7890")).status, PreflightStatus::Pass);
+        assert_eq!(check_upstream_proxy(&upstream(true, "localhost:7890")).status, PreflightStatus::Fail);
+
+        let mut config = ProxyConfig {
+            auth_mode: crate::proxy::ProxyAuthMode::Strict,
+            api_key: String::new(),
+            ..Default::default()
+        };
+        assert_eq!(check_api_key(&config).status, PreflightStatus::Fail);
+        config.auth_mode = crate::proxy::ProxyAuthMode::Off;
+        assert_eq!(check_api_key(&config).status, PreflightStatus::Pass);
+    }
+
+    #[test]
+    fn test_preflight_report_summary() {
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let report = PreflightReport::from_checks(vec![
+            check_port("127.0.0.1", port, None),
+            PreflightCheck::new("accounts", PreflightStatus::Warn, "none"),
+        ]);
+        assert!(!report.passed);
+        assert!(report.failure_summary().starts_with("[port]"));
+        assert_eq!(
+            check_port("127.0.0.1", port, Some((port, port.saturating_add(10)))).status,
+            PreflightStatus::Warn
+        );
+        drop(listener);
+
+        assert_eq!(check_port("127.0.0.1", port, None).status, PreflightStatus::Pass);
+        let (check, first) = check_accounts(&[], true);
+        assert_eq!(check.status, PreflightStatus::Warn);
+        assert!(first.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_connection_test_reports_tcp_failure() {
+        // 先绑定再释放，得到一个无人监听的本地端口
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let result = run_connection_test("127.0.0.1", port, &upstream(false, "")).await;
+        assert!(result.dns_resolved);
+        assert!(!result.tcp_connected);
+        assert!(!result.tls_valid);
+        assert_eq!(result.proxy_reachable, None);
+        assert!(result.error.unwrap().contains("TCP"));
+    }
+
+    #[tokio::test]
+    async fn test_wait_until_ready() {
+        // 未监听的端口: 超时后返回错误
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+        assert!(wait_until_ready(port, "", Duration::from_millis(300)).await.is_err());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let app = axum::Router::new().route("/healthz", axum::routing::get(|| async { "ok" }));
+        tokio::spawn(async move { axum::serve(listener, app).await.ok() });
+        assert!(wait_until_ready(port, "sk-test", Duration::from_secs(2)).await.is_ok());
+    }
+}
@@ -1,6 +1,38 @@
+use serde::ser::SerializeStruct;
 use serde::Serialize;
+use std::fmt;
 use thiserror::Error;
 
+/// 单个配置字段的校验错误
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ConfigError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ConfigError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+fn join_config_errors(errors: &[ConfigError]) -> String {
+    errors
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
@@ -9,6 +41,10 @@ pub enum AppError {
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
 
+    // 模块层以 String 返回的网络错误 (reqwest::Error 无法由字符串构造)
+    #[error("Network error: {0}")]
+    Upstream(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -18,25 +54,143 @@ pub enum AppError {
     #[error("OAuth error: {0}")]
     OAuth(String),
 
-    #[error("Configuration error: {0}")]
-    Config(String),
+    #[error("Configuration error: {}", join_config_errors(.0))]
+    Config(Vec<ConfigError>),
 
     #[error("Account error: {0}")]
     Account(String),
 
+    #[error("Account not found: {0}")]
+    AccountNotFound(String),
+
+    #[error("Quota forbidden: {0}")]
+    QuotaForbidden(String),
+
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
 
-// Implement Serialize so it can be used as a return value for Tauri commands
+impl AppError {
+    /// 前端按此错误码区分错误类型
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Database(_) => "DATABASE_ERROR",
+            AppError::Network(_) | AppError::Upstream(_) => "NETWORK_ERROR",
+            AppError::Io(_) => "IO_ERROR",
+            AppError::Tauri(_) => "TAURI_ERROR",
+            AppError::OAuth(_) => "OAUTH_ERROR",
+            AppError::Config(_) => "CONFIG_ERROR",
+            AppError::Account(_) => "ACCOUNT_ERROR",
+            AppError::AccountNotFound(_) => "ACCOUNT_NOT_FOUND",
+            AppError::QuotaForbidden(_) => "QUOTA_FORBIDDEN",
+            AppError::Unknown(_) => "UNKNOWN_ERROR",
+        }
+    }
+
+    /// 不含类别前缀的错误信息
+    pub fn message(&self) -> String {
+        match self {
+            AppError::Database(e) => e.to_string(),
+            AppError::Network(e) => e.to_string(),
+            AppError::Io(e) => e.to_string(),
+            AppError::Tauri(e) => e.to_string(),
+            AppError::Config(errors) => join_config_errors(errors),
+            AppError::OAuth(msg)
+            | AppError::Upstream(msg)
+            | AppError::Account(msg)
+            | AppError::AccountNotFound(msg)
+            | AppError::QuotaForbidden(msg)
+            | AppError::Unknown(msg) => msg.clone(),
+        }
+    }
+
+    /// 账号模块的 String 错误: "账号不存在" 前缀映射为 AccountNotFound，其余为 Account
+    pub fn account(message: String) -> Self {
+        if message.starts_with("账号不存在") {
+            AppError::AccountNotFound(message)
+        } else {
+            AppError::Account(message)
+        }
+    }
+
+    /// 文件/目录操作的 String 错误
+    pub fn io(message: String) -> Self {
+        AppError::Io(std::io::Error::other(message))
+    }
+
+    /// 上游请求的 String 错误
+    pub fn network(message: String) -> Self {
+        AppError::Upstream(message)
+    }
+}
+
+// 模块层仍以 String 作为错误类型；命令中优先用 AppError::account/io/network 指明类别，
+// 无法归类的错误才通过 `?` 落到 Unknown
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        if message.starts_with("账号不存在") {
+            AppError::AccountNotFound(message)
+        } else {
+            AppError::Unknown(message)
+        }
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::from(message.to_string())
+    }
+}
+
+// Tauri 命令返回值: {"code": "OAUTH_ERROR", "message": "...", "detail": null}
 impl Serialize for AppError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(self.to_string().as_str())
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.message())?;
+        match self {
+            AppError::Config(errors) => state.serialize_field("detail", errors)?,
+            _ => state.serialize_field("detail", &Option::<()>::None)?,
+        }
+        state.end()
     }
 }
 
 // Implement alias for Result to simplify usage
 pub type AppResult<T> = Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_app_error_serialization() {
+        assert_eq!(
+            serde_json::to_value(AppError::OAuth("invalid_grant".to_string())).unwrap(),
+            json!({"code": "OAUTH_ERROR", "message": "invalid_grant", "detail": null})
+        );
+        assert_eq!(
+            serde_json::to_value(AppError::Config(vec![ConfigError::new("proxy.port", "端口无效")])).unwrap(),
+            json!({
+                "code": "CONFIG_ERROR",
+                "message": "proxy.port: 端口无效",
+                "detail": [{"field": "proxy.port", "message": "端口无效"}]
+            })
+        );
+        assert_eq!(AppError::from("账号不存在: abc").code(), "ACCOUNT_NOT_FOUND");
+        assert_eq!(AppError::from("boom".to_string()).code(), "UNKNOWN_ERROR");
+        assert_eq!(AppError::account("账号不存在: abc".to_string()).code(), "ACCOUNT_NOT_FOUND");
+        assert_eq!(AppError::account("账号已冻结".to_string()).code(), "ACCOUNT_ERROR");
+        let io = AppError::io("打开目录失败".to_string());
+        assert_eq!(io.code(), "IO_ERROR");
+        assert_eq!(io.message(), "打开目录失败");
+        assert_eq!(
+            serde_json::to_value(AppError::network("timeout".to_string())).unwrap(),
+            json!({"code": "NETWORK_ERROR", "message": "timeout", "detail": null})
+        );
+    }
+}
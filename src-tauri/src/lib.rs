@@ -38,16 +38,26 @@ pub fn run() {
                 });
         }))
         .manage(commands::proxy::ProxyServiceState::new())
+        .manage(modules::process_watch::ProcessWatchState::new())
+        .manage(modules::scheduler::QuotaRefreshState::new())
         .setup(|app| {
             info!("Setup starting...");
+            modules::account::set_app_handle(app.handle().clone());
             modules::tray::create_tray(app.handle())?;
             info!("Tray created");
             
             // 自动启动反代服务
             let handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
-                // 加载配置
-                if let Ok(config) = modules::config::load_app_config() {
+                // 加载配置 (配置无效时不自动启动反代，避免以默认配置暴露服务)
+                let config = match modules::config::load_app_config() {
+                    Ok(config) => Some(config),
+                    Err(e) => {
+                        error!("加载配置失败，跳过自动启动反代服务: {}", e);
+                        None
+                    }
+                };
+                if let Some(config) = config {
                     if config.proxy.auto_start {
                         let state = handle.state::<commands::proxy::ProxyServiceState>();
                         // 尝试启动服务
@@ -55,6 +65,7 @@ pub fn run() {
                             config.proxy,
                             state,
                             handle.clone(),
+                            None,
                         ).await {
                             error!("自动启动反代服务失败: {}", e);
                         } else {
@@ -66,6 +77,15 @@ pub fn run() {
             
             // 启动智能调度器
             modules::scheduler::start_scheduler(app.handle().clone());
+
+            // 启动后台账号同步 (db_sync_interval_secs 未设置时不启动)
+            modules::db_sync::start_db_sync(app.handle().clone());
+
+            // 自动监听 Antigravity 进程状态
+            if modules::config::load_app_config().is_ok_and(|c| c.auto_watch_process) {
+                app.state::<modules::process_watch::ProcessWatchState>()
+                    .start(app.handle().clone());
+            }
             
             Ok(())
         })
@@ -84,9 +104,27 @@ pub fn run() {
             greet,
             // 账号管理命令
             commands::list_accounts,
+            commands::get_account_health,
+            commands::get_quota_history,
+            commands::get_quota_summary,
+            commands::update_account_proxy,
+            commands::set_account_system_prompt,
+            commands::set_account_priority,
+            commands::freeze_account,
+            commands::unfreeze_account,
+            commands::rename_account,
+            commands::set_account_notes,
+            commands::get_account_notes,
+            commands::get_antigravity_version,
+            commands::get_process_info,
+            commands::watch_antigravity_process,
+            commands::stop_watching_antigravity_process,
+            commands::validate_refresh_token,
             commands::add_account,
             commands::delete_account,
             commands::delete_accounts,
+            commands::list_forbidden_accounts,
+            commands::purge_forbidden_accounts,
             commands::reorder_accounts,
             commands::switch_account,
             // 设备指纹
@@ -104,6 +142,9 @@ pub fn run() {
             // 配额命令
             commands::fetch_account_quota,
             commands::refresh_all_quotas,
+            commands::test_account_token,
+            commands::schedule_quota_refresh,
+            commands::cancel_quota_refresh,
             // 配置命令
             commands::load_config,
             commands::save_config,
@@ -111,15 +152,27 @@ pub fn run() {
             commands::prepare_oauth_url,
             commands::start_oauth_login,
             commands::complete_oauth_login,
+            commands::start_device_oauth_login,
             commands::cancel_oauth_login,
             commands::import_v1_accounts,
+            commands::preview_v1_import,
+            commands::import_from_token_list,
+            commands::export_accounts,
+            commands::import_accounts_from_file,
+            commands::backup_accounts,
+            commands::restore_accounts,
             commands::import_from_db,
             commands::import_custom_db,
+            commands::import_from_cursor,
             commands::sync_account_from_db,
             commands::save_text_file,
             commands::clear_log_cache,
+            commands::get_log_entries,
+            commands::generate_diagnostic_report,
+            commands::save_diagnostic_report,
             commands::open_data_folder,
             commands::get_data_dir_path,
+            commands::migrate_data_dir,
             commands::show_main_window,
             commands::get_antigravity_path,
             commands::get_antigravity_args,
@@ -131,21 +184,33 @@ pub fn run() {
             commands::toggle_proxy_status,
             // 反代服务命令
             commands::proxy::start_proxy_service,
+            commands::proxy::preflight_check,
             commands::proxy::stop_proxy_service,
+            commands::proxy::restart_proxy_service,
             commands::proxy::get_proxy_status,
+            commands::proxy::check_proxy_health,
+            commands::proxy::connection_test,
             commands::proxy::get_proxy_stats,
+            commands::proxy::clear_proxy_stats,
             commands::proxy::get_proxy_logs,
             commands::proxy::get_proxy_logs_paginated,
             commands::proxy::get_proxy_log_detail,
             commands::proxy::set_proxy_monitor_enabled,
             commands::proxy::clear_proxy_logs,
             commands::proxy::generate_api_key,
+            commands::proxy::generate_api_key_for_accounts,
             commands::proxy::reload_proxy_accounts,
             commands::proxy::update_model_mapping,
+            commands::proxy::get_effective_model_mapping,
             commands::proxy::fetch_zai_models,
             commands::proxy::get_proxy_scheduling_config,
             commands::proxy::update_proxy_scheduling_config,
             commands::proxy::clear_proxy_session_bindings,
+            commands::proxy::get_circuit_states,
+            commands::proxy::get_account_latency_stats,
+            commands::proxy::list_active_streams,
+            commands::proxy::replay_stream,
+            commands::proxy::get_audit_log_entries,
             // Autostart 命令
             commands::autostart::toggle_auto_launch,
             commands::autostart::is_auto_launch_enabled,
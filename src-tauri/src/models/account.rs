@@ -1,5 +1,17 @@
 use super::{quota::QuotaData, token::TokenData};
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Proxy scheduling weight range; an account with priority 10 is picked about twice as often as one with 5.
+pub const MIN_ACCOUNT_PRIORITY: u8 = 1;
+pub const MAX_ACCOUNT_PRIORITY: u8 = 10;
+pub const DEFAULT_ACCOUNT_PRIORITY: u8 = 5;
+
+fn default_priority() -> u8 {
+    DEFAULT_ACCOUNT_PRIORITY
+}
 
 /// Account data structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -7,6 +19,9 @@ pub struct Account {
     pub id: String,
     pub email: String,
     pub name: Option<String>,
+    /// User-assigned display name; takes precedence over `name` and is never overwritten by OAuth upserts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_name: Option<String>,
     pub token: TokenData,
     /// 可选的设备指纹，用于切换账号时固定机器信息
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -15,6 +30,9 @@ pub struct Account {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub device_history: Vec<DeviceProfileVersion>,
     pub quota: Option<QuotaData>,
+    /// Daily quota snapshots `(timestamp, quota)`, oldest first.
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
+    pub quota_history: VecDeque<(i64, QuotaData)>,
     /// Disabled accounts are ignored by the proxy token pool (e.g. revoked refresh_token -> invalid_grant).
     #[serde(default)]
     pub disabled: bool,
@@ -33,6 +51,28 @@ pub struct Account {
     /// Unix timestamp when the proxy was disabled.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub proxy_disabled_at: Option<i64>,
+    /// Frozen accounts are reserved for manual use: never loaded into the proxy token pool,
+    /// but still available for `switch_account`.
+    #[serde(default)]
+    pub frozen: bool,
+    /// Optional human-readable reason for freezing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freeze_reason: Option<String>,
+    /// Health statistics updated after quota refreshes and proxy requests.
+    #[serde(default)]
+    pub health: AccountHealth,
+    /// Per-account upstream proxy URL; overrides the global `upstream_proxy` when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_proxy: Option<String>,
+    /// Per-account system prompt prefix, prepended before the client's system prompt by the proxy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt_prefix: Option<String>,
+    /// Free-form user notes; kept only in the account file, not in the index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Weighted round-robin priority used by the proxy token pool (1-10).
+    #[serde(default = "default_priority")]
+    pub priority: u8,
     pub created_at: i64,
     pub last_used: i64,
 }
@@ -44,21 +84,35 @@ impl Account {
             id,
             email,
             name: None,
+            custom_name: None,
             token,
             device_profile: None,
             device_history: Vec::new(),
             quota: None,
+            quota_history: VecDeque::new(),
             disabled: false,
             disabled_reason: None,
             disabled_at: None,
             proxy_disabled: false,
             proxy_disabled_reason: None,
             proxy_disabled_at: None,
+            frozen: false,
+            freeze_reason: None,
+            health: AccountHealth::default(),
+            upstream_proxy: None,
+            system_prompt_prefix: None,
+            notes: None,
+            priority: DEFAULT_ACCOUNT_PRIORITY,
             created_at: now,
             last_used: now,
         }
     }
 
+    /// Name shown in the UI and tray: `custom_name` when set, otherwise the OAuth profile name.
+    pub fn display_name(&self) -> Option<&str> {
+        self.custom_name.as_deref().or(self.name.as_deref())
+    }
+
     pub fn update_last_used(&mut self) {
         self.last_used = chrono::Utc::now().timestamp();
     }
@@ -66,6 +120,131 @@ impl Account {
     pub fn update_quota(&mut self, quota: QuotaData) {
         self.quota = Some(quota);
     }
+
+    /// Records the quota in the daily history (one snapshot per UTC day, latest wins)
+    /// before replacing the current value. History is capped at `max_entries`.
+    pub fn update_quota_with_history(&mut self, quota: QuotaData, max_entries: usize) {
+        let timestamp = quota.last_updated;
+        let day = timestamp.div_euclid(SECONDS_PER_DAY);
+        if self
+            .quota_history
+            .back()
+            .is_some_and(|(ts, _)| ts.div_euclid(SECONDS_PER_DAY) == day)
+        {
+            self.quota_history.pop_back();
+        }
+        self.quota_history.push_back((timestamp, quota.clone()));
+        while self.quota_history.len() > max_entries {
+            self.quota_history.pop_front();
+        }
+        self.update_quota(quota);
+    }
+}
+
+/// Rolling window used for `error_rate_7d`.
+const HEALTH_WINDOW_DAYS: i64 = 7;
+/// Smoothing factor for the latency moving average.
+const LATENCY_EMA_ALPHA: f64 = 0.2;
+
+/// Account health statistics
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AccountHealth {
+    #[serde(default)]
+    pub consecutive_errors: u8,
+    #[serde(default)]
+    pub last_error_at: Option<i64>,
+    #[serde(default)]
+    pub last_success_at: Option<i64>,
+    /// Message of the most recent quota fetch / token test error (time in `last_error_at`);
+    /// cleared by the next success.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Exponential moving average of request latency.
+    #[serde(default)]
+    pub avg_latency_ms: f64,
+    /// Error ratio over the last 7 days (0.0 - 1.0).
+    #[serde(default)]
+    pub error_rate_7d: f64,
+    /// Overall health score (0 - 100, higher is healthier).
+    #[serde(default = "default_health_score")]
+    pub score: f64,
+    /// Per-day request/error counters backing `error_rate_7d`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub daily_stats: Vec<HealthDailyStat>,
+}
+
+/// Per-day request counters (day = unix timestamp / 86400)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HealthDailyStat {
+    pub day: i64,
+    pub requests: u32,
+    pub errors: u32,
+}
+
+fn default_health_score() -> f64 {
+    100.0
+}
+
+impl Default for AccountHealth {
+    fn default() -> Self {
+        Self {
+            consecutive_errors: 0,
+            last_error_at: None,
+            last_success_at: None,
+            last_error: None,
+            avg_latency_ms: 0.0,
+            error_rate_7d: 0.0,
+            score: default_health_score(),
+            daily_stats: Vec::new(),
+        }
+    }
+}
+
+impl AccountHealth {
+    pub fn record_success(&mut self, latency_ms: Option<f64>, now: i64) {
+        self.consecutive_errors = 0;
+        self.last_success_at = Some(now);
+        self.last_error = None;
+        if let Some(latency) = latency_ms {
+            self.avg_latency_ms = if self.avg_latency_ms <= 0.0 {
+                latency
+            } else {
+                self.avg_latency_ms * (1.0 - LATENCY_EMA_ALPHA) + latency * LATENCY_EMA_ALPHA
+            };
+        }
+        self.bump_day(now, false);
+    }
+
+    pub fn record_error(&mut self, now: i64) {
+        self.consecutive_errors = self.consecutive_errors.saturating_add(1);
+        self.last_error_at = Some(now);
+        self.bump_day(now, true);
+    }
+
+    fn bump_day(&mut self, now: i64, is_error: bool) {
+        let today = now.div_euclid(86400);
+        self.daily_stats.retain(|d| d.day > today - HEALTH_WINDOW_DAYS);
+        match self.daily_stats.iter_mut().find(|d| d.day == today) {
+            Some(stat) => {
+                stat.requests = stat.requests.saturating_add(1);
+                stat.errors = stat.errors.saturating_add(is_error as u32);
+            }
+            None => self.daily_stats.push(HealthDailyStat {
+                day: today,
+                requests: 1,
+                errors: is_error as u32,
+            }),
+        }
+
+        let (requests, errors) = self
+            .daily_stats
+            .iter()
+            .fold((0u64, 0u64), |(r, e), d| (r + d.requests as u64, e + d.errors as u64));
+        self.error_rate_7d = if requests == 0 { 0.0 } else { errors as f64 / requests as f64 };
+        // Each consecutive error costs 5 points on top of the 7-day error rate.
+        self.score = (100.0 * (1.0 - self.error_rate_7d) - self.consecutive_errors as f64 * 5.0)
+            .clamp(0.0, 100.0);
+    }
 }
 
 /// Account index data (accounts.json)
@@ -82,6 +261,12 @@ pub struct AccountSummary {
     pub id: String,
     pub email: String,
     pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt_prefix: Option<String>,
+    #[serde(default = "default_priority")]
+    pub priority: u8,
     pub created_at: i64,
     pub last_used: i64,
 }
@@ -21,6 +21,64 @@ pub struct AppConfig {
     pub scheduled_warmup: ScheduledWarmupConfig, // [NEW] 定时预热配置
     #[serde(default)]
     pub quota_protection: QuotaProtectionConfig, // [NEW] 配额保护配置
+    #[serde(default = "default_refresh_concurrency")]
+    pub refresh_concurrency: usize, // 批量刷新配额的并发数
+    #[serde(default)]
+    pub log_format: Option<LogFormat>, // 日志输出格式，None 时按 stdout 是否为终端自动选择
+    #[serde(default = "default_max_log_size_mb")]
+    pub max_log_size_mb: u64, // 单个日志文件大小上限 (MB)，超出后轮转
+    #[serde(default = "default_max_log_files")]
+    pub max_log_files: u8, // 最多保留的归档日志数量
+    #[serde(default)]
+    pub db_sync_interval_secs: Option<u64>, // 后台从 Antigravity DB 同步账号的间隔 (秒)，None 表示禁用
+    #[serde(default = "default_max_quota_history_entries")]
+    pub max_quota_history_entries: usize, // 每个账号保留的每日配额快照数量
+    #[serde(default)]
+    pub auto_watch_process: bool, // 启动时自动监听 Antigravity 进程状态
+    #[serde(default)]
+    pub allowed_email_domains: Vec<String>, // 允许添加的账号邮箱域名 (如 company.com)，为空表示不限制
+    #[serde(default = "default_fsync_on_write")]
+    pub fsync_on_write: bool, // 写入账号索引后 fsync 落盘 (HDD 上会增加延迟)
+}
+
+/// 日志轮转默认参数
+pub const DEFAULT_MAX_LOG_SIZE_MB: u64 = 10;
+pub const DEFAULT_MAX_LOG_FILES: u8 = 5;
+
+fn default_max_log_size_mb() -> u64 {
+    DEFAULT_MAX_LOG_SIZE_MB
+}
+
+fn default_max_log_files() -> u8 {
+    DEFAULT_MAX_LOG_FILES
+}
+
+fn default_fsync_on_write() -> bool {
+    true
+}
+
+/// 日志输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// 人类可读的文本格式
+    Plain,
+    /// 每行一个 JSON 对象 (便于服务化部署时采集)
+    Json,
+}
+
+/// 批量刷新配额的默认并发数
+pub const DEFAULT_REFRESH_CONCURRENCY: usize = 3;
+
+fn default_refresh_concurrency() -> usize {
+    DEFAULT_REFRESH_CONCURRENCY
+}
+
+/// 每日配额快照的默认保留数量 (约一个月)
+pub const DEFAULT_MAX_QUOTA_HISTORY_ENTRIES: usize = 30;
+
+fn default_max_quota_history_entries() -> usize {
+    DEFAULT_MAX_QUOTA_HISTORY_ENTRIES
 }
 
 /// 定时预热配置
@@ -108,6 +166,15 @@ impl AppConfig {
             auto_launch: false,
             scheduled_warmup: ScheduledWarmupConfig::default(),
             quota_protection: QuotaProtectionConfig::default(),
+            refresh_concurrency: DEFAULT_REFRESH_CONCURRENCY,
+            log_format: None,
+            max_log_size_mb: DEFAULT_MAX_LOG_SIZE_MB,
+            max_log_files: DEFAULT_MAX_LOG_FILES,
+            db_sync_interval_secs: None,
+            max_quota_history_entries: DEFAULT_MAX_QUOTA_HISTORY_ENTRIES,
+            auto_watch_process: false,
+            allowed_email_domains: Vec::new(),
+            fsync_on_write: true,
         }
     }
 }
@@ -3,8 +3,8 @@ pub mod token;
 pub mod quota;
 pub mod config;
 
-pub use account::{Account, AccountIndex, AccountSummary, DeviceProfile, DeviceProfileVersion};
+pub use account::{Account, AccountHealth, AccountIndex, AccountSummary, DeviceProfile, DeviceProfileVersion};
 pub use token::TokenData;
-pub use quota::QuotaData;
-pub use config::{AppConfig, QuotaProtectionConfig};
+pub use quota::{QuotaData, QuotaSnapshot};
+pub use config::{AppConfig, LogFormat, QuotaProtectionConfig};
 
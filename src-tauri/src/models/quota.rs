@@ -20,6 +20,13 @@ pub struct QuotaData {
     pub subscription_tier: Option<String>,
 }
 
+/// 配额历史快照 (返回给前端)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaSnapshot {
+    pub timestamp: i64,
+    pub quota: QuotaData,
+}
+
 impl QuotaData {
     pub fn new() -> Self {
         Self {
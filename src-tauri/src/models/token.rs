@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 
+/// Tokens are refreshed this many seconds before they actually expire
+pub const EXPIRY_SAFETY_MARGIN_SECS: i64 = 300;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenData {
     pub access_token: String,
@@ -36,4 +39,45 @@ impl TokenData {
             session_id,
         }
     }
+
+    /// Expiry time as a UTC Unix timestamp (seconds)
+    pub fn expires_at(&self) -> i64 {
+        self.expiry_timestamp
+    }
+
+    /// Seconds left until expiry, negative once expired
+    pub fn seconds_until_expiry(&self) -> i64 {
+        self.expires_at() - chrono::Utc::now().timestamp()
+    }
+
+    /// Whether the token expires within `window_secs` from now
+    pub fn is_about_to_expire(&self, window_secs: i64) -> bool {
+        self.seconds_until_expiry() <= window_secs
+    }
+
+    /// Whether the token should be refreshed (expired or within the 5-minute safety margin)
+    pub fn is_expired(&self) -> bool {
+        self.is_about_to_expire(EXPIRY_SAFETY_MARGIN_SECS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_expiry_helpers() {
+        let token = TokenData::new("at".to_string(), "rt".to_string(), 3600, None, None, None);
+        assert!(!token.is_expired());
+        assert!((3599..=3600).contains(&token.seconds_until_expiry()));
+        assert!(token.is_about_to_expire(3600));
+        assert!(!token.is_about_to_expire(3000));
+
+        let expiring = TokenData::new("at".to_string(), "rt".to_string(), EXPIRY_SAFETY_MARGIN_SECS, None, None, None);
+        assert!(expiring.is_expired());
+
+        let expired = TokenData::new("at".to_string(), "rt".to_string(), -10, None, None, None);
+        assert!(expired.is_expired());
+        assert!(expired.seconds_until_expiry() < 0);
+    }
 }
@@ -4,9 +4,10 @@ use serde_json;
 use uuid::Uuid;
 use serde::Serialize;
 
-use crate::models::{Account, AccountIndex, AccountSummary, TokenData, QuotaData, DeviceProfile, DeviceProfileVersion,};
+use crate::models::{Account, AccountHealth, AccountIndex, AccountSummary, TokenData, QuotaData, QuotaSnapshot, DeviceProfile, DeviceProfileVersion,};
 use crate::modules;
 use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Mutex;
 
 /// 全局账号写入锁，防止并发操作导致索引文件损坏
@@ -18,10 +19,66 @@ const ACCOUNTS_INDEX: &str = "accounts.json";
 const ACCOUNTS_DIR: &str = "accounts";
 
 // ... existing functions get_data_dir, get_accounts_dir, load_account_index, save_account_index ...
-/// 获取数据目录路径
-pub fn get_data_dir() -> Result<PathBuf, String> {
+const DATA_DIR_REDIRECT: &str = "redirect.json";
+
+/// 数据目录重定向 (默认目录下 redirect.json 指向的路径)，首次访问时读取，迁移后更新
+static DATA_DIR_OVERRIDE: Lazy<std::sync::RwLock<Option<PathBuf>>> =
+    Lazy::new(|| std::sync::RwLock::new(default_data_dir().ok().and_then(|d| read_data_dir_redirect(&d))));
+
+/// 全局 AppHandle (在 setup 中设置)，供后台任务向前端发送账号事件
+static APP_HANDLE: std::sync::OnceLock<tauri::AppHandle> = std::sync::OnceLock::new();
+
+pub fn set_app_handle(handle: tauri::AppHandle) {
+    let _ = APP_HANDLE.set(handle);
+}
+
+/// account://token-refresh-failed 事件负载
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenRefreshFailedEvent {
+    pub account_id: String,
+    pub email: String,
+    pub error: String,
+    pub timestamp: i64,
+}
+
+/// 后台 Token 刷新失败时通知前端 (AppHandle 未设置时仅跳过)
+pub fn notify_token_refresh_failed(account_id: &str, email: &str, error: &str) {
+    use tauri::Emitter;
+    if let Some(handle) = APP_HANDLE.get() {
+        let payload = TokenRefreshFailedEvent {
+            account_id: account_id.to_string(),
+            email: email.to_string(),
+            error: error.to_string(),
+            timestamp: chrono::Utc::now().timestamp(),
+        };
+        let _ = handle.emit("account://token-refresh-failed", payload);
+    }
+}
+
+#[derive(Serialize, serde::Deserialize)]
+struct DataDirRedirect {
+    path: PathBuf,
+}
+
+/// 默认数据目录 (~/.antigravity_tools)
+fn default_data_dir() -> Result<PathBuf, String> {
     let home = dirs::home_dir().ok_or("无法获取用户主目录")?;
-    let data_dir = home.join(DATA_DIR);
+    Ok(home.join(DATA_DIR))
+}
+
+fn read_data_dir_redirect(default_dir: &std::path::Path) -> Option<PathBuf> {
+    let content = fs::read_to_string(default_dir.join(DATA_DIR_REDIRECT)).ok()?;
+    let redirect: DataDirRedirect = serde_json::from_str(&content).ok()?;
+    Some(redirect.path).filter(|p| p.is_absolute())
+}
+
+/// 获取数据目录路径 (已迁移时返回重定向后的目录)
+pub fn get_data_dir() -> Result<PathBuf, String> {
+    let redirected = DATA_DIR_OVERRIDE.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let data_dir = match redirected {
+        Some(dir) => dir,
+        None => default_data_dir()?,
+    };
     
     // 确保目录存在
     if !data_dir.exists() {
@@ -45,6 +102,104 @@ pub fn get_accounts_dir() -> Result<PathBuf, String> {
     Ok(accounts_dir)
 }
 
+/// 将数据目录迁移到 `new_path` (如云同步目录)，并在默认目录写入 redirect.json
+///
+/// 先复制到目标目录旁的临时目录，完成后再重命名为目标目录；任一步失败时原目录保持不变。
+/// 已启动的反代服务仍使用旧目录，需重启后生效
+pub fn migrate_data_dir(new_path: &str) -> Result<PathBuf, String> {
+    let _lock = ACCOUNT_INDEX_LOCK.lock().map_err(|e| format!("获取锁失败: {}", e))?;
+    let current = get_data_dir()?;
+    let default_dir = default_data_dir()?;
+    let target = PathBuf::from(new_path.trim());
+
+    migrate_data_dir_between(&current, &default_dir, &target)?;
+
+    let redirect = (target != default_dir).then(|| target.clone());
+    *DATA_DIR_OVERRIDE.write().unwrap_or_else(|e| e.into_inner()) = redirect;
+    modules::logger::log_info(&format!("数据目录已迁移: {:?} -> {:?}", current, target));
+    Ok(target)
+}
+
+fn migrate_data_dir_between(
+    current: &std::path::Path,
+    default_dir: &std::path::Path,
+    target: &std::path::Path,
+) -> Result<(), String> {
+    if !target.is_absolute() {
+        return Err("目标路径必须为绝对路径".to_string());
+    }
+    let normalize = |p: &std::path::Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+    let (current_abs, target_abs) = (normalize(current), normalize(target));
+    if target_abs == current_abs {
+        return Err("目标目录与当前数据目录相同".to_string());
+    }
+    if target_abs.starts_with(&current_abs) || current_abs.starts_with(&target_abs) {
+        return Err("目标目录不能位于当前数据目录内 (或包含当前数据目录)".to_string());
+    }
+
+    if target == default_dir {
+        // 迁回默认目录: 覆盖复制后删除 redirect.json (复制失败时重定向仍然有效)
+        copy_dir_recursive(current, target)?;
+        return match fs::remove_file(default_dir.join(DATA_DIR_REDIRECT)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("删除重定向文件失败: {}", e)),
+            _ => Ok(()),
+        };
+    }
+
+    if target.exists() {
+        let is_empty = fs::read_dir(target)
+            .map_err(|e| format!("无法读取目标目录: {}", e))?
+            .next()
+            .is_none();
+        if !is_empty {
+            return Err(format!("目标目录非空: {}", target.display()));
+        }
+    }
+    let parent = target.parent().ok_or("目标路径无效")?;
+    fs::create_dir_all(parent).map_err(|e| format!("创建目标父目录失败: {}", e))?;
+
+    // 复制到同一父目录下的临时目录，保证最终重命名为原子操作
+    let staging = parent.join(format!(".antigravity_tools.migrating-{}", Uuid::new_v4().simple()));
+    let staged = copy_dir_recursive(current, &staging).and_then(|_| {
+        if target.exists() {
+            fs::remove_dir(target).map_err(|e| format!("移除空目标目录失败: {}", e))?;
+        }
+        fs::rename(&staging, target).map_err(|e| format!("重命名临时目录失败: {}", e))
+    });
+    if let Err(e) = staged {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    // 最后写入重定向 (临时文件 + 原子重命名)
+    fs::create_dir_all(default_dir).map_err(|e| format!("创建默认数据目录失败: {}", e))?;
+    let content = serde_json::to_string_pretty(&DataDirRedirect { path: target.to_path_buf() })
+        .map_err(|e| format!("序列化重定向文件失败: {}", e))?;
+    let temp_path = default_dir.join(format!("{}.tmp", DATA_DIR_REDIRECT));
+    fs::write(&temp_path, content).map_err(|e| format!("写入重定向文件失败: {}", e))?;
+    fs::rename(&temp_path, default_dir.join(DATA_DIR_REDIRECT))
+        .map_err(|e| format!("写入重定向文件失败: {}", e))
+}
+
+/// 递归复制目录 (跳过 redirect.json)，目标目录不存在时创建；同时验证目标可写
+fn copy_dir_recursive(from: &std::path::Path, to: &std::path::Path) -> Result<(), String> {
+    fs::create_dir_all(to).map_err(|e| format!("目标目录不可写 ({}): {}", to.display(), e))?;
+    for entry in fs::read_dir(from).map_err(|e| format!("读取数据目录失败: {}", e))? {
+        let entry = entry.map_err(|e| format!("读取数据目录失败: {}", e))?;
+        let name = entry.file_name();
+        if name == DATA_DIR_REDIRECT {
+            continue;
+        }
+        let (src, dest) = (entry.path(), to.join(&name));
+        if entry.file_type().map_err(|e| e.to_string())?.is_dir() {
+            copy_dir_recursive(&src, &dest)?;
+        } else {
+            fs::copy(&src, &dest).map_err(|e| format!("复制 {} 失败: {}", src.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
 /// 加载账号索引
 pub fn load_account_index() -> Result<AccountIndex, String> {
     let data_dir = get_data_dir()?;
@@ -70,17 +225,47 @@ pub fn load_account_index() -> Result<AccountIndex, String> {
 pub fn save_account_index(index: &AccountIndex) -> Result<(), String> {
     let data_dir = get_data_dir()?;
     let index_path = data_dir.join(ACCOUNTS_INDEX);
-    let temp_path = data_dir.join(format!("{}.tmp", ACCOUNTS_INDEX));
     
     let content = serde_json::to_string_pretty(index)
         .map_err(|e| format!("序列化账号索引失败: {}", e))?;
     
+    let fsync = modules::config::load_app_config()
+        .map(|c| c.fsync_on_write)
+        .unwrap_or(true);
+    write_file_atomically(&index_path, content.as_bytes(), fsync)
+}
+
+/// 先写入同目录下的 `.tmp` 临时文件再原子重命名，避免写入中途崩溃导致文件损坏
+///
+/// `fsync` 为 true 时在重命名前调用 `sync_all` (Linux 为 fsync，Windows 为 FlushFileBuffers)，
+/// 防止断电后重命名已生效而文件内容尚未落盘
+fn write_file_atomically(path: &std::path::Path, content: &[u8], fsync: bool) -> Result<(), String> {
+    use std::io::Write;
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("无效的文件路径: {:?}", path))?;
+    let temp_path = path.with_file_name(format!("{}.tmp", file_name.to_string_lossy()));
+
     // 写入临时文件
-    fs::write(&temp_path, content)
-        .map_err(|e| format!("写入临时索引文件失败: {}", e))?;
-        
+    {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&temp_path)
+            .map_err(|e| format!("创建临时索引文件失败: {}", e))?;
+        file.write_all(content)
+            .and_then(|_| file.flush())
+            .map_err(|e| format!("写入临时索引文件失败: {}", e))?;
+        if fsync {
+            file.sync_all()
+                .map_err(|e| format!("同步临时索引文件失败: {}", e))?;
+        }
+    }
+
     // 原子重命名
-    fs::rename(temp_path, index_path)
+    fs::rename(&temp_path, path)
         .map_err(|e| format!("替换索引文件失败: {}", e))
 }
 
@@ -112,6 +297,32 @@ pub fn save_account(account: &Account) -> Result<(), String> {
         .map_err(|e| format!("保存账号数据失败: {}", e))
 }
 
+/// 批量写回反代请求统计的账号健康度，返回实际写入的账号数
+/// 在 ACCOUNT_INDEX_LOCK 下完成读-改-写并原子替换文件；已删除的账号直接跳过，不会被重新创建
+pub fn save_accounts_health(updates: &[(String, AccountHealth)]) -> Result<usize, String> {
+    let _lock = ACCOUNT_INDEX_LOCK.lock().map_err(|e| format!("获取锁失败: {}", e))?;
+    let accounts_dir = get_accounts_dir()?;
+    let mut saved = 0;
+    for (account_id, health) in updates {
+        let account_path = accounts_dir.join(format!("{}.json", account_id));
+        if !account_path.exists() {
+            continue;
+        }
+        let mut account = load_account(account_id)?;
+        let previous = std::mem::replace(&mut account.health, health.clone());
+        // 反代内存中的健康度不含配额查询写入的错误信息: 之后没有成功请求时保留磁盘上的记录
+        if previous.last_error.is_some() && health.last_success_at <= previous.last_error_at {
+            account.health.last_error = previous.last_error;
+            account.health.last_error_at = account.health.last_error_at.max(previous.last_error_at);
+        }
+        let content = serde_json::to_string_pretty(&account)
+            .map_err(|e| format!("序列化账号数据失败: {}", e))?;
+        write_file_atomically(&account_path, content.as_bytes(), false)?;
+        saved += 1;
+    }
+    Ok(saved)
+}
+
 /// 列出所有账号
 /// 列出所有账号
 pub fn list_accounts() -> Result<Vec<Account>, String> {
@@ -181,6 +392,9 @@ pub fn add_account(email: String, name: Option<String>, token: TokenData) -> Res
         id: account_id.clone(),
         email: email.clone(),
         name: name.clone(),
+        upstream_proxy: None,
+        system_prompt_prefix: None,
+        priority: account.priority,
         created_at: account.created_at,
         last_used: account.last_used,
     });
@@ -195,8 +409,23 @@ pub fn add_account(email: String, name: Option<String>, token: TokenData) -> Res
     Ok(account)
 }
 
+/// 查找以其他邮箱持有同一 refresh_token 的账号
+fn find_refresh_token_owner<'a>(accounts: &'a [Account], email: &str, refresh_token: &str) -> Option<&'a Account> {
+    accounts
+        .iter()
+        .find(|a| a.email != email && a.token.refresh_token == refresh_token)
+}
+
 /// 添加或更新账号
-pub fn upsert_account(email: String, name: Option<String>, token: TokenData) -> Result<Account, String> {
+///
+/// `existing_refresh_token_hint`: 调用方认为当前已存储的 refresh_token (如 Token 刷新流程)。
+/// 同一邮箱的 refresh_token 发生变化且与该提示不符时，视为重新授权产生的新 grant 并记录警告
+pub fn upsert_account(
+    email: String,
+    name: Option<String>,
+    token: TokenData,
+    existing_refresh_token_hint: Option<&str>,
+) -> Result<Account, String> {
     let _lock = ACCOUNT_INDEX_LOCK.lock().map_err(|e| format!("获取锁失败: {}", e))?;
     let mut index = load_account_index()?;
     
@@ -204,13 +433,36 @@ pub fn upsert_account(email: String, name: Option<String>, token: TokenData) ->
     let existing_account_id = index.accounts.iter()
         .find(|s| s.email == email)
         .map(|s| s.id.clone());
+    let existing_account = existing_account_id.as_deref().map(load_account);
+
+    // refresh_token 与已存储的不同 (新账号或重新授权) 时，禁止与其他邮箱的账号共用同一 refresh_token
+    let stored_refresh_token = match &existing_account {
+        Some(Ok(account)) => Some(account.token.refresh_token.as_str()),
+        _ => None,
+    };
+    if !token.refresh_token.is_empty() && stored_refresh_token != Some(token.refresh_token.as_str()) {
+        let accounts: Vec<Account> = index.accounts.iter()
+            .filter_map(|s| load_account(&s.id).ok())
+            .collect();
+        if let Some(owner) = find_refresh_token_owner(&accounts, &email, &token.refresh_token) {
+            return Err(format!("该 refresh_token 已被账号 {} 使用", owner.email));
+        }
+    }
     
-    if let Some(account_id) = existing_account_id {
+    if let (Some(account_id), Some(existing)) = (existing_account_id, existing_account) {
         // 更新现有账号
-        match load_account(&account_id) {
+        match existing {
             Ok(mut account) => {
                 let old_access_token = account.token.access_token.clone();
                 let old_refresh_token = account.token.refresh_token.clone();
+                if token.refresh_token != old_refresh_token
+                    && existing_refresh_token_hint != Some(old_refresh_token.as_str())
+                {
+                    crate::modules::logger::log_warn(&format!(
+                        "账号 {} 的 refresh_token 已变化，可能是重新授权后签发的新 grant",
+                        email
+                    ));
+                }
                 account.token = token;
                 account.name = name.clone();
                 // If an account was previously disabled (e.g. invalid_grant), any explicit token upsert
@@ -226,9 +478,9 @@ pub fn upsert_account(email: String, name: Option<String>, token: TokenData) ->
                 account.update_last_used();
                 save_account(&account)?;
                 
-                // 同步更新索引中的 name
+                // 同步更新索引中的 name (自定义名称优先)
                 if let Some(idx_summary) = index.accounts.iter_mut().find(|s| s.id == account_id) {
-                    idx_summary.name = name;
+                    idx_summary.name = account.display_name().map(str::to_string);
                     save_account_index(&index)?;
                 }
                 
@@ -324,6 +576,28 @@ pub fn delete_accounts(account_ids: &[String]) -> Result<(), String> {
     save_account_index(&index)
 }
 
+/// 配额状态为 is_forbidden 的账号 ID (当前账号除外)
+fn forbidden_account_ids(accounts: &[Account], current_account_id: Option<&str>) -> Vec<String> {
+    accounts
+        .iter()
+        .filter(|a| a.quota.as_ref().is_some_and(|q| q.is_forbidden))
+        .filter(|a| current_account_id != Some(a.id.as_str()))
+        .map(|a| a.id.clone())
+        .collect()
+}
+
+/// 列出可清理的被禁止账号 (不包含当前账号)，按索引顺序返回
+pub fn list_forbidden_accounts() -> Result<Vec<AccountSummary>, String> {
+    let current_account_id = get_current_account_id()?;
+    let forbidden = forbidden_account_ids(&list_accounts()?, current_account_id.as_deref());
+    let index = load_account_index()?;
+    Ok(index
+        .accounts
+        .into_iter()
+        .filter(|s| forbidden.contains(&s.id))
+        .collect())
+}
+
 /// 重新排序账号列表
 /// 根据传入的账号ID顺序更新索引文件中的账号排列顺序
 pub fn reorder_accounts(account_ids: &[String]) -> Result<(), String> {
@@ -621,7 +895,11 @@ pub fn set_current_account_id(account_id: &str) -> Result<(), String> {
 /// 更新账号配额
 pub fn update_account_quota(account_id: &str, quota: QuotaData) -> Result<(), String> {
     let mut account = load_account(account_id)?;
-    account.update_quota(quota);
+    let max_history = crate::modules::config::load_app_config()
+        .map(|c| c.max_quota_history_entries)
+        .unwrap_or(crate::models::config::DEFAULT_MAX_QUOTA_HISTORY_ENTRIES);
+    account.update_quota_with_history(quota, max_history);
+    account.health.last_error = None;
 
     // --- 配额保护逻辑开始 ---
     if let Ok(config) = crate::modules::config::load_app_config() {
@@ -688,24 +966,792 @@ pub fn update_account_quota(account_id: &str, quota: QuotaData) -> Result<(), St
     save_account(&account)
 }
 
-/// 导出所有账号的 refresh_token
-#[allow(dead_code)]
-pub fn export_accounts() -> Result<Vec<(String, String)>, String> {
-    let accounts = list_accounts()?;
-    let mut exports = Vec::new();
-    
-    for account in accounts {
-        exports.push((account.email, account.token.refresh_token));
+const BACKUP_VERSION: u32 = 1;
+
+/// 备份文件中的单个账号 (不含会过期的 access_token)
+#[derive(Debug, Clone, Serialize, serde::Deserialize)]
+pub struct AccountBackupEntry {
+    pub email: String,
+    pub name: Option<String>,
+    pub refresh_token: String,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub device_profile: Option<DeviceProfile>,
+    #[serde(default)]
+    pub upstream_proxy: Option<String>,
+    pub created_at: i64,
+}
+
+/// 加密备份的密钥派生参数 (Argon2id)
+#[derive(Debug, Clone, Serialize, serde::Deserialize)]
+struct BackupKdf {
+    algorithm: String,
+    salt: String, // base64
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+}
+
+/// 备份文件格式: 明文时 `accounts` 有值，加密时 `kdf`/`nonce`/`ciphertext` 有值
+#[derive(Debug, Clone, Serialize, serde::Deserialize)]
+struct AccountBackupFile {
+    version: u32,
+    exported_at: i64,
+    encrypted: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    accounts: Option<Vec<AccountBackupEntry>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kdf: Option<BackupKdf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>, // base64
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ciphertext: Option<String>, // base64, 明文为 accounts 数组的 JSON
+}
+
+fn derive_backup_key(password: &str, kdf: &BackupKdf) -> Result<[u8; 32], String> {
+    use base64::{engine::general_purpose, Engine as _};
+
+    if kdf.algorithm != "argon2id" {
+        return Err(format!("不支持的密钥派生算法: {}", kdf.algorithm));
     }
-    
-    Ok(exports)
+    let salt = general_purpose::STANDARD
+        .decode(&kdf.salt)
+        .map_err(|e| format!("备份文件 salt 无效: {}", e))?;
+    let params = argon2::Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(32))
+        .map_err(|e| format!("备份文件 KDF 参数无效: {}", e))?;
+    let mut key = [0u8; 32];
+    argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), &salt, &mut key)
+        .map_err(|e| format!("派生密钥失败: {}", e))?;
+    Ok(key)
+}
+
+fn build_backup_file(entries: Vec<AccountBackupEntry>, password: Option<&str>) -> Result<AccountBackupFile, String> {
+    use aes_gcm::aead::{Aead, KeyInit};
+    use aes_gcm::{Aes256Gcm, Nonce};
+    use base64::{engine::general_purpose, Engine as _};
+    use rand::RngCore;
+
+    let exported_at = chrono::Utc::now().timestamp();
+    let Some(password) = password.filter(|p| !p.is_empty()) else {
+        return Ok(AccountBackupFile {
+            version: BACKUP_VERSION,
+            exported_at,
+            encrypted: false,
+            accounts: Some(entries),
+            kdf: None,
+            nonce: None,
+            ciphertext: None,
+        });
+    };
+
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let kdf = BackupKdf {
+        algorithm: "argon2id".to_string(),
+        salt: general_purpose::STANDARD.encode(salt),
+        m_cost: argon2::Params::DEFAULT_M_COST,
+        t_cost: argon2::Params::DEFAULT_T_COST,
+        p_cost: argon2::Params::DEFAULT_P_COST,
+    };
+    let key = derive_backup_key(password, &kdf)?;
+
+    let plaintext = serde_json::to_vec(&entries).map_err(|e| format!("序列化账号失败: {}", e))?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| format!("初始化加密失败: {}", e))?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|e| format!("加密失败: {}", e))?;
+
+    Ok(AccountBackupFile {
+        version: BACKUP_VERSION,
+        exported_at,
+        encrypted: true,
+        accounts: None,
+        kdf: Some(kdf),
+        nonce: Some(general_purpose::STANDARD.encode(nonce)),
+        ciphertext: Some(general_purpose::STANDARD.encode(ciphertext)),
+    })
+}
+
+fn read_backup_entries(file: AccountBackupFile, password: Option<&str>) -> Result<Vec<AccountBackupEntry>, String> {
+    use aes_gcm::aead::{Aead, KeyInit};
+    use aes_gcm::{Aes256Gcm, Nonce};
+    use base64::{engine::general_purpose, Engine as _};
+
+    if file.version > BACKUP_VERSION {
+        return Err(format!("不支持的备份文件版本: {}", file.version));
+    }
+    if !file.encrypted {
+        return file.accounts.ok_or_else(|| "备份文件缺少 accounts 字段".to_string());
+    }
+
+    let password = password
+        .filter(|p| !p.is_empty())
+        .ok_or("备份文件已加密，请提供密码")?;
+    let kdf = file.kdf.ok_or("备份文件缺少 kdf 字段")?;
+    let nonce = general_purpose::STANDARD
+        .decode(file.nonce.ok_or("备份文件缺少 nonce 字段")?)
+        .map_err(|e| format!("备份文件 nonce 无效: {}", e))?;
+    if nonce.len() != 12 {
+        return Err("备份文件 nonce 长度无效".to_string());
+    }
+    let ciphertext = general_purpose::STANDARD
+        .decode(file.ciphertext.ok_or("备份文件缺少 ciphertext 字段")?)
+        .map_err(|e| format!("备份文件密文无效: {}", e))?;
+
+    let key = derive_backup_key(password, &kdf)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| format!("初始化解密失败: {}", e))?;
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| "解密失败: 密码错误或文件已损坏".to_string())?;
+
+    serde_json::from_slice(&plaintext).map_err(|e| format!("解析备份账号失败: {}", e))
+}
+
+/// 导出所有账号到 JSON 备份文件 (可选密码加密: Argon2id + AES-256-GCM)
+pub fn export_accounts_to_file(path: &std::path::Path, password: Option<&str>) -> Result<(), String> {
+    let entries = list_accounts()?
+        .into_iter()
+        .map(|account| AccountBackupEntry {
+            email: account.email,
+            name: account.name,
+            refresh_token: account.token.refresh_token,
+            project_id: account.token.project_id,
+            device_profile: account.device_profile,
+            upstream_proxy: account.upstream_proxy,
+            created_at: account.created_at,
+        })
+        .collect();
+
+    let backup = build_backup_file(entries, password)?;
+    let content = serde_json::to_string_pretty(&backup).map_err(|e| format!("序列化备份失败: {}", e))?;
+    fs::write(path, content).map_err(|e| format!("写入备份文件失败: {}", e))
+}
+
+/// 从备份文件导入账号 (按邮箱合并，已存在的账号更新 refresh_token)
+/// 导入的 access_token 视为已过期，首次使用时会自动刷新
+pub fn import_accounts_from_file(path: &std::path::Path, password: Option<&str>) -> Result<Vec<Account>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("读取备份文件失败: {}", e))?;
+    let backup: AccountBackupFile =
+        serde_json::from_str(&content).map_err(|e| format!("解析备份文件失败: {}", e))?;
+    let entries = read_backup_entries(backup, password)?;
+    let existing = list_accounts()?;
+
+    let mut imported = Vec::new();
+    for entry in entries {
+        // refresh_token 未变化时保留现有 access_token，避免无谓的刷新
+        let token = match existing.iter().find(|a| a.email == entry.email) {
+            Some(acc) if acc.token.refresh_token == entry.refresh_token => acc.token.clone(),
+            _ => TokenData::new(
+                String::new(),
+                entry.refresh_token,
+                0,
+                Some(entry.email.clone()),
+                entry.project_id,
+                None,
+            ),
+        };
+        let mut account = upsert_account(entry.email, entry.name, token, None)?;
+        if entry.device_profile.is_some() {
+            account.device_profile = entry.device_profile;
+            save_account(&account)?;
+        }
+        if entry.upstream_proxy.is_some() {
+            account = update_account_proxy(&account.id, entry.upstream_proxy)?;
+        }
+        imported.push(account);
+    }
+
+    modules::logger::log_info(&format!("从备份文件导入 {} 个账号", imported.len()));
+    Ok(imported)
+}
+
+/// 账号归档中的单个账号文件
+#[derive(Debug)]
+struct ArchivedAccount {
+    account: Account,
+    content: Vec<u8>,
+}
+
+/// 打包账号目录与索引为 tar.gz 归档
+fn write_accounts_archive(data_dir: &std::path::Path, dest: &std::path::Path) -> Result<(), String> {
+    let file = fs::File::create(dest).map_err(|e| format!("创建归档文件失败: {}", e))?;
+    let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    let index_path = data_dir.join(ACCOUNTS_INDEX);
+    if index_path.exists() {
+        builder
+            .append_path_with_name(&index_path, ACCOUNTS_INDEX)
+            .map_err(|e| format!("写入归档失败: {}", e))?;
+    }
+    let accounts_dir = data_dir.join(ACCOUNTS_DIR);
+    if accounts_dir.exists() {
+        builder
+            .append_dir_all(ACCOUNTS_DIR, &accounts_dir)
+            .map_err(|e| format!("写入归档失败: {}", e))?;
+    }
+
+    builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map(|_| ())
+        .map_err(|e| format!("写入归档失败: {}", e))
+}
+
+/// 读取并校验归档内容 (不写入任何文件)
+/// 仅接受 accounts.json 与 accounts/<id>.json，每个账号文件必须能解析为 Account
+fn read_accounts_archive(archive: &std::path::Path) -> Result<(AccountIndex, Vec<ArchivedAccount>), String> {
+    use std::io::Read;
+
+    let file = fs::File::open(archive).map_err(|e| format!("读取归档文件失败: {}", e))?;
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    let mut index: Option<AccountIndex> = None;
+    let mut accounts = Vec::new();
+
+    let entries = tar.entries().map_err(|e| format!("归档文件已损坏: {}", e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("归档文件已损坏: {}", e))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path().map_err(|e| format!("归档文件已损坏: {}", e))?.into_owned();
+        let mut content = Vec::new();
+        entry
+            .read_to_end(&mut content)
+            .map_err(|e| format!("归档文件已损坏: {}", e))?;
+
+        let components: Vec<_> = path.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+        match components.as_slice() {
+            [name] if name == ACCOUNTS_INDEX => {
+                index = Some(
+                    serde_json::from_slice(&content).map_err(|e| format!("归档中的账号索引无效: {}", e))?,
+                );
+            }
+            [dir, name] if dir == ACCOUNTS_DIR && name.ends_with(".json") => {
+                let account: Account = serde_json::from_slice(&content)
+                    .map_err(|e| format!("归档中的账号文件 {} 无效: {}", name, e))?;
+                if format!("{}.json", account.id) != *name {
+                    return Err(format!("归档中的账号文件 {} 与账号 ID 不符", name));
+                }
+                accounts.push(ArchivedAccount { account, content });
+            }
+            _ => modules::logger::log_warn(&format!("跳过归档中的未知文件: {:?}", path)),
+        }
+    }
+
+    let index = index.ok_or("归档中缺少账号索引 accounts.json")?;
+    if let Some(missing) = index.accounts.iter().find(|s| !accounts.iter().any(|a| a.account.id == s.id)) {
+        return Err(format!("归档中缺少账号文件: {}", missing.id));
+    }
+    Ok((index, accounts))
+}
+
+/// 将归档账号合并进现有索引: 归档中的账号覆盖同 ID 记录，其余账号保持不变
+fn merge_account_index(current: &mut AccountIndex, archived: AccountIndex) {
+    for summary in archived.accounts {
+        match current.accounts.iter_mut().find(|s| s.id == summary.id) {
+            Some(existing) => *existing = summary,
+            None => current.accounts.push(summary),
+        }
+    }
+    if current.current_account_id.is_none() {
+        current.current_account_id = archived.current_account_id;
+    }
+}
+
+/// 备份账号目录与索引为 tar.gz 归档，dest 为目录时自动生成文件名，返回最终路径
+pub fn backup_accounts_archive(dest: &std::path::Path) -> Result<PathBuf, String> {
+    let dest = if dest.is_dir() {
+        dest.join(format!(
+            "antigravity_accounts_{}.tar.gz",
+            chrono::Local::now().format("%Y%m%d_%H%M%S")
+        ))
+    } else {
+        dest.to_path_buf()
+    };
+
+    let _lock = ACCOUNT_INDEX_LOCK.lock().map_err(|e| format!("获取锁失败: {}", e))?;
+    write_accounts_archive(&get_data_dir()?, &dest)?;
+    Ok(dest)
+}
+
+/// 从 tar.gz 归档恢复账号，返回恢复的账号数
+/// 写入前完整校验归档，归档外的现有账号不会被覆盖
+pub fn restore_accounts_archive(archive: &std::path::Path) -> Result<usize, String> {
+    let (archived_index, archived) = read_accounts_archive(archive)?;
+    let restored_ids: Vec<String> = archived.iter().map(|a| a.account.id.clone()).collect();
+
+    {
+        let _lock = ACCOUNT_INDEX_LOCK.lock().map_err(|e| format!("获取锁失败: {}", e))?;
+        let accounts_dir = get_accounts_dir()?;
+        for entry in &archived {
+            let path = accounts_dir.join(format!("{}.json", entry.account.id));
+            fs::write(&path, &entry.content).map_err(|e| format!("写入账号文件失败: {}", e))?;
+        }
+
+        let mut index = load_account_index()?;
+        merge_account_index(&mut index, archived_index);
+        // 归档中存在文件但索引未记录的账号同样加入索引
+        for entry in &archived {
+            if !index.accounts.iter().any(|s| s.id == entry.account.id) {
+                let account = &entry.account;
+                index.accounts.push(AccountSummary {
+                    id: account.id.clone(),
+                    email: account.email.clone(),
+                    name: account.name.clone(),
+                    upstream_proxy: account.upstream_proxy.clone(),
+                    system_prompt_prefix: account.system_prompt_prefix.clone(),
+                    priority: account.priority,
+                    created_at: account.created_at,
+                    last_used: account.last_used,
+                });
+            }
+        }
+        save_account_index(&index)?;
+    }
+
+    // 重新加载校验完整性
+    let restored = list_accounts()?
+        .iter()
+        .filter(|a| restored_ids.contains(&a.id))
+        .count();
+    if restored != restored_ids.len() {
+        return Err(format!(
+            "恢复后校验失败: 归档包含 {} 个账号，仅加载 {} 个",
+            restored_ids.len(),
+            restored
+        ));
+    }
+
+    modules::logger::log_info(&format!("从归档恢复 {} 个账号", restored));
+    Ok(restored)
+}
+
+/// 获取账号的每日配额快照 (按时间升序)
+pub fn get_quota_history(account_id: &str) -> Result<Vec<QuotaSnapshot>, String> {
+    let account = load_account(account_id)?;
+    let mut history: Vec<QuotaSnapshot> = account
+        .quota_history
+        .into_iter()
+        .map(|(timestamp, quota)| QuotaSnapshot { timestamp, quota })
+        .collect();
+    history.sort_by_key(|s| s.timestamp);
+    Ok(history)
+}
+
+/// 单个模型在所有账号上的剩余配额汇总
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ModelQuotaSummary {
+    pub name: String,
+    pub accounts: usize,
+    pub avg_remaining_percentage: f64,
+    /// 剩余 0% 的账号数
+    pub exhausted_accounts: usize,
+}
+
+/// 单个模型配额窗口的名义容量，用于把剩余百分比换算为剩余请求数 / token 数
+const NOMINAL_REQUESTS_PER_MODEL: u64 = 100;
+const NOMINAL_TOKENS_PER_MODEL: u64 = 1_000_000;
+
+/// 多账号配额汇总 (仅读取本地缓存的配额，不发起网络请求)
+///
+/// 上游配额接口只返回每个模型的剩余比例，不提供剩余请求数 / token 数。
+/// `*_remaining` 为估算值: 每个模型的剩余量 = 剩余百分比 / 100 × 名义容量
+/// (每个模型窗口按 100 次请求、1,000,000 token 计)，再对所有参与统计的账号与模型求和
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct QuotaSummary {
+    pub total_accounts: usize,
+    /// 已有配额数据 (且未被禁止访问) 的账号数，仅这些账号参与平均值计算
+    pub accounts_with_quota: usize,
+    pub forbidden_accounts: usize,
+    /// 各账号模型平均剩余百分比的平均值
+    pub avg_remaining_percentage: f64,
+    /// 估算的剩余请求总数 (见类型文档的换算方式)
+    pub total_requests_remaining: u64,
+    /// 估算的剩余 token 总数
+    pub total_tokens_remaining: u64,
+    /// 每个参与统计的账号平均剩余请求数 (无配额数据的账号不计入分母)
+    pub avg_requests_remaining: f64,
+    pub models: Vec<ModelQuotaSummary>,
+    /// 最近一次配额刷新时间 (Unix 秒)
+    pub last_refreshed_at: Option<i64>,
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0usize), |(s, c), v| (s + v, c + 1));
+    if count == 0 { 0.0 } else { sum / count as f64 }
+}
+
+pub fn summarize_quotas(accounts: &[Account]) -> QuotaSummary {
+    let quotas: Vec<&QuotaData> = accounts.iter().filter_map(|a| a.quota.as_ref()).collect();
+    let usable: Vec<&QuotaData> = quotas
+        .iter()
+        .copied()
+        .filter(|q| !q.is_forbidden && !q.models.is_empty())
+        .collect();
+
+    let mut per_model: std::collections::BTreeMap<&str, Vec<i32>> = std::collections::BTreeMap::new();
+    for quota in &usable {
+        for model in &quota.models {
+            per_model.entry(model.name.as_str()).or_default().push(model.percentage);
+        }
+    }
+
+    let remaining = |capacity: u64| -> u64 {
+        usable
+            .iter()
+            .flat_map(|q| q.models.iter())
+            .map(|m| m.percentage.clamp(0, 100) as u64 * capacity / 100)
+            .sum()
+    };
+    let total_requests_remaining = remaining(NOMINAL_REQUESTS_PER_MODEL);
+
+    QuotaSummary {
+        total_accounts: accounts.len(),
+        accounts_with_quota: usable.len(),
+        forbidden_accounts: quotas.iter().filter(|q| q.is_forbidden).count(),
+        avg_remaining_percentage: mean(
+            usable
+                .iter()
+                .map(|q| mean(q.models.iter().map(|m| m.percentage as f64))),
+        ),
+        total_requests_remaining,
+        total_tokens_remaining: remaining(NOMINAL_TOKENS_PER_MODEL),
+        avg_requests_remaining: if usable.is_empty() {
+            0.0
+        } else {
+            total_requests_remaining as f64 / usable.len() as f64
+        },
+        models: per_model
+            .into_iter()
+            .map(|(name, percentages)| ModelQuotaSummary {
+                name: name.to_string(),
+                accounts: percentages.len(),
+                avg_remaining_percentage: mean(percentages.iter().map(|p| *p as f64)),
+                exhausted_accounts: percentages.iter().filter(|p| **p <= 0).count(),
+            })
+            .collect(),
+        last_refreshed_at: quotas.iter().map(|q| q.last_updated).max(),
+    }
+}
+
+/// 汇总所有账号的配额状态
+pub fn get_quota_summary() -> Result<QuotaSummary, String> {
+    Ok(summarize_quotas(&list_accounts()?))
+}
+
+/// 记录账号健康状态并落盘 (与其他账号文件写入共用 ACCOUNT_INDEX_LOCK，避免并发读-改-写丢失更新)
+pub fn record_account_health(account_id: &str, success: bool, latency_ms: Option<f64>) -> Result<AccountHealth, String> {
+    let _lock = ACCOUNT_INDEX_LOCK.lock().map_err(|e| format!("获取锁失败: {}", e))?;
+    let mut account = load_account(account_id)?;
+    let now = chrono::Utc::now().timestamp();
+    if success {
+        account.health.record_success(latency_ms, now);
+    } else {
+        account.health.record_error(now);
+    }
+    save_account(&account)?;
+    Ok(account.health)
+}
+
+/// 记录账号最近一次配额查询错误信息 (时间由 record_account_health 写入 health.last_error_at，
+/// 下次成功时清除)
+pub fn update_account_error(account_id: &str, error_message: &str) -> Result<(), String> {
+    let _lock = ACCOUNT_INDEX_LOCK.lock().map_err(|e| format!("获取锁失败: {}", e))?;
+    let mut account = load_account(account_id)?;
+    account.health.last_error = Some(error_message.to_string());
+    save_account(&account)
+}
+
+/// 设置账号级上游代理 (None 或空字符串表示清除，回退到全局代理)
+pub fn update_account_proxy(account_id: &str, proxy_url: Option<String>) -> Result<Account, String> {
+    let proxy_url = proxy_url
+        .map(|u| u.trim().to_string())
+        .filter(|u| !u.is_empty());
+    if let Some(url) = &proxy_url {
+        crate::modules::config::validate_proxy_url(url)?;
+    }
+
+    let _lock = ACCOUNT_INDEX_LOCK.lock().map_err(|e| format!("获取锁失败: {}", e))?;
+    let mut account = load_account(account_id)?;
+    account.upstream_proxy = proxy_url.clone();
+    save_account(&account)?;
+
+    let mut index = load_account_index()?;
+    if let Some(summary) = index.accounts.iter_mut().find(|s| s.id == account_id) {
+        summary.upstream_proxy = proxy_url;
+        save_account_index(&index)?;
+    }
+
+    Ok(account)
+}
+
+/// 设置账号级系统提示词前缀 (None 或空字符串表示清除)
+pub fn update_account_system_prompt(account_id: &str, prefix: Option<String>) -> Result<Account, String> {
+    let prefix = prefix.filter(|p| !p.trim().is_empty());
+
+    let _lock = ACCOUNT_INDEX_LOCK.lock().map_err(|e| format!("获取锁失败: {}", e))?;
+    let mut account = load_account(account_id)?;
+    account.system_prompt_prefix = prefix.clone();
+    save_account(&account)?;
+
+    let mut index = load_account_index()?;
+    if let Some(summary) = index.accounts.iter_mut().find(|s| s.id == account_id) {
+        summary.system_prompt_prefix = prefix;
+        save_account_index(&index)?;
+    }
+
+    Ok(account)
+}
+
+/// 冻结 / 解冻账号 (冻结的账号不进入反代账号池，但仍可手动切换)
+pub fn set_account_frozen(account_id: &str, frozen: bool, reason: Option<String>) -> Result<Account, String> {
+    let _lock = ACCOUNT_INDEX_LOCK.lock().map_err(|e| format!("获取锁失败: {}", e))?;
+    let mut account = load_account(account_id)?;
+    account.frozen = frozen;
+    account.freeze_reason = if frozen {
+        reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty())
+    } else {
+        None
+    };
+    save_account(&account)?;
+    Ok(account)
+}
+
+/// 设置账号调度优先级 (1-10，反代加权轮询的权重)
+pub fn update_account_priority(account_id: &str, priority: u8) -> Result<Account, String> {
+    use crate::models::account::{MAX_ACCOUNT_PRIORITY, MIN_ACCOUNT_PRIORITY};
+    if !(MIN_ACCOUNT_PRIORITY..=MAX_ACCOUNT_PRIORITY).contains(&priority) {
+        return Err(format!(
+            "优先级必须在 {} 到 {} 之间",
+            MIN_ACCOUNT_PRIORITY, MAX_ACCOUNT_PRIORITY
+        ));
+    }
+
+    let _lock = ACCOUNT_INDEX_LOCK.lock().map_err(|e| format!("获取锁失败: {}", e))?;
+    let mut account = load_account(account_id)?;
+    account.priority = priority;
+    save_account(&account)?;
+
+    let mut index = load_account_index()?;
+    if let Some(summary) = index.accounts.iter_mut().find(|s| s.id == account_id) {
+        summary.priority = priority;
+        save_account_index(&index)?;
+    }
+
+    Ok(account)
+}
+
+/// 设置账号自定义名称 (为空时清除，恢复为 OAuth 名称)，同步更新索引中的 name
+pub fn rename_account(account_id: &str, display_name: &str) -> Result<Account, String> {
+    let _lock = ACCOUNT_INDEX_LOCK.lock().map_err(|e| format!("获取锁失败: {}", e))?;
+    let mut account = load_account(account_id)?;
+    account.custom_name = Some(display_name.trim().to_string()).filter(|n| !n.is_empty());
+    save_account(&account)?;
+
+    let mut index = load_account_index()?;
+    if let Some(summary) = index.accounts.iter_mut().find(|s| s.id == account_id) {
+        summary.name = account.display_name().map(str::to_string);
+        save_account_index(&index)?;
+    }
+
+    Ok(account)
+}
+
+/// 设置账号备注 (notes 为空时清除)，备注只保存在账号文件中
+pub fn set_account_notes(account_id: &str, notes: &str) -> Result<Account, String> {
+    let _lock = ACCOUNT_INDEX_LOCK.lock().map_err(|e| format!("获取锁失败: {}", e))?;
+    let mut account = load_account(account_id)?;
+    account.notes = Some(notes.trim().to_string()).filter(|n| !n.is_empty());
+    save_account(&account)?;
+    Ok(account)
+}
+
+/// 读取账号备注
+pub fn get_account_notes(account_id: &str) -> Result<Option<String>, String> {
+    Ok(load_account(account_id)?.notes)
+}
+
+/// 账号连通性测试使用的模型 (最便宜，与配置无关)
+const TOKEN_TEST_MODEL: &str = "gemini-2.5-flash";
+const TOKEN_TEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Refresh Token 预校验结果 (添加账号前预览)
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenValidationResult {
+    pub email: String,
+    pub display_name: Option<String>,
+    pub expires_in: u64,
+    /// 索引中已存在相同邮箱的账号 (添加时将覆盖其 Token)
+    pub is_duplicate: bool,
+}
+
+/// 校验 refresh_token: 换取 access_token 并获取用户信息，不写入任何账号数据
+/// 同时返回换取到的 Token，供添加账号时直接使用
+pub async fn validate_refresh_token(
+    refresh_token: &str,
+) -> Result<(TokenValidationResult, modules::oauth::TokenResponse), String> {
+    if refresh_token.trim().is_empty() {
+        return Err("Refresh Token 不能为空".to_string());
+    }
+    let token_res = modules::oauth::refresh_access_token(refresh_token).await?;
+    let user_info = modules::oauth::get_user_info(&token_res.access_token).await?;
+    check_email_domain_allowed(&user_info.email)?;
+    let is_duplicate = load_account_index()?
+        .accounts
+        .iter()
+        .any(|a| a.email.eq_ignore_ascii_case(&user_info.email));
+
+    let result = TokenValidationResult {
+        display_name: user_info.get_display_name(),
+        email: user_info.email,
+        expires_in: token_res.expires_in.max(0) as u64,
+        is_duplicate,
+    };
+    Ok((result, token_res))
+}
+
+/// 邮箱域名是否在允许列表中 (忽略大小写与前导 @，列表为空时不限制)
+fn is_email_domain_allowed(email: &str, allowed_domains: &[String]) -> bool {
+    if allowed_domains.is_empty() {
+        return true;
+    }
+    let Some((_, domain)) = email.rsplit_once('@') else {
+        return false;
+    };
+    allowed_domains
+        .iter()
+        .any(|d| d.trim().trim_start_matches('@').eq_ignore_ascii_case(domain))
+}
+
+/// 按 allowed_email_domains 配置检查账号邮箱域名 (添加 / 导入账号前调用)
+pub fn check_email_domain_allowed(email: &str) -> Result<(), String> {
+    let allowed_domains = crate::modules::config::load_app_config()
+        .map(|c| c.allowed_email_domains)
+        .unwrap_or_default();
+    if is_email_domain_allowed(email, &allowed_domains) {
+        Ok(())
+    } else {
+        Err(format!("Account {} is not in an allowed domain", email))
+    }
+}
+
+/// 账号连通性测试结果
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenTestResult {
+    pub valid: bool,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+    pub model_used: String,
+}
+
+/// 轻量检测账号是否可用: 刷新 Token 后发送一次 maxOutputTokens=1 的 generateContent 请求
+/// 结果写入账号健康度 (last_error / last_error_at / last_success_at)
+pub async fn test_account_token(account_id: &str) -> Result<TokenTestResult, String> {
+    let account = load_account(account_id)?;
+    let start = std::time::Instant::now();
+    let outcome = match modules::quota::get_valid_token_for_warmup(&account).await {
+        Ok((access_token, project_id)) => {
+            let proxy_config = crate::modules::config::load_app_config()
+                .map(|c| c.proxy)
+                .unwrap_or_default();
+            let client = crate::proxy::upstream::client::UpstreamClient::new(
+                Some(proxy_config.upstream_proxy.clone()),
+                crate::proxy::upstream::client::UpstreamClientConfig::from_proxy_config(&proxy_config),
+            );
+            let body = crate::proxy::mappers::gemini::wrap_request(
+                &serde_json::json!({
+                    "contents": [{"parts": [{"text": "Hi"}]}],
+                    "generationConfig": {"maxOutputTokens": 1}
+                }),
+                &project_id,
+                TOKEN_TEST_MODEL,
+            );
+            let request = async {
+                let resp = client
+                    .call_v1_internal("generateContent", &access_token, body, None, account.upstream_proxy.as_deref())
+                    .await?;
+                let status = resp.status();
+                if status.is_success() {
+                    Ok(())
+                } else {
+                    let text = resp.text().await.unwrap_or_default();
+                    Err(format!("HTTP {}: {}", status.as_u16(), text.chars().take(500).collect::<String>()))
+                }
+            };
+            match tokio::time::timeout(TOKEN_TEST_TIMEOUT, request).await {
+                Ok(r) => r,
+                Err(_) => Err(format!("Request timed out after {}s", TOKEN_TEST_TIMEOUT.as_secs())),
+            }
+        }
+        Err(e) => Err(format!("Token refresh failed: {}", e)),
+    };
+    let latency_ms = start.elapsed().as_millis() as u64;
+
+    // get_valid_token_for_warmup 可能已保存刷新后的 Token，在锁内重新加载后再写入
+    {
+        let _lock = ACCOUNT_INDEX_LOCK.lock().map_err(|e| format!("获取锁失败: {}", e))?;
+        let mut account = load_account(account_id)?;
+        let now = chrono::Utc::now().timestamp();
+        match &outcome {
+            Ok(()) => account.health.record_success(Some(latency_ms as f64), now),
+            Err(e) => {
+                account.health.record_error(now);
+                account.health.last_error = Some(e.clone());
+            }
+        }
+        save_account(&account)?;
+    }
+
+    modules::logger::log_info(&format!(
+        "账号连通性测试 {}: {} ({}ms)",
+        account.email,
+        if outcome.is_ok() { "OK" } else { "FAILED" },
+        latency_ms
+    ));
+    Ok(TokenTestResult {
+        valid: outcome.is_ok(),
+        latency_ms,
+        error: outcome.err(),
+        model_used: TOKEN_TEST_MODEL.to_string(),
+    })
 }
 
 /// 带有重试机制的配额查询 (从 commands 移动到 modules 以便共享)
+/// 查询结果会同步更新账号健康状态
 pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppResult<QuotaData> {
+    let start = std::time::Instant::now();
+    let result = fetch_quota_with_retry_inner(account).await;
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    match record_account_health(&account.id, result.is_ok(), Some(latency_ms)) {
+        Ok(health) => account.health = health,
+        Err(e) => modules::logger::log_warn(&format!("更新账号健康状态失败 ({}): {}", account.email, e)),
+    }
+
+    // 同步内存中的账号，避免调用方后续保存时覆盖磁盘上的错误记录
+    if let Err(e) = &result {
+        let message = e.to_string();
+        account.health.last_error = Some(message.clone());
+        if let Err(err) = update_account_error(&account.id, &message) {
+            modules::logger::log_warn(&format!("记录账号错误失败 ({}): {}", account.email, err));
+        }
+    }
+    result
+}
+
+async fn fetch_quota_with_retry_inner(account: &mut Account) -> crate::error::AppResult<QuotaData> {
     use crate::modules::oauth;
     use crate::error::AppError;
     use reqwest::StatusCode;
+
+    // Token 刷新不会签发新的 grant，以刷新前的 refresh_token 作为 upsert 提示
+    let stored_refresh_token = account.token.refresh_token.clone();
     
     // 1. 基于时间的检查 (Time-based check) - 先确保 Token 有效
     let token = match oauth::ensure_fresh_token(&account.token).await {
@@ -721,6 +1767,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
                 account.disabled_reason = Some(format!("invalid_grant: {}", e));
                 let _ = save_account(account);
             }
+            notify_token_refresh_failed(&account.id, &account.email, &e);
             return Err(AppError::OAuth(e));
         }
     };
@@ -740,7 +1787,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
         };
         
         account.name = name.clone();
-        upsert_account(account.email.clone(), name, token.clone()).map_err(AppError::Account)?;
+        upsert_account(account.email.clone(), name, token.clone(), Some(&stored_refresh_token)).map_err(AppError::Account)?;
     }
 
     // 0. 补充用户名 (如果 Token 没过期但也没用户名，或者上面没获取到)
@@ -753,7 +1800,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
                 modules::logger::log_info(&format!("成功获取用户名: {:?}", display_name));
                 account.name = display_name.clone();
                 // 立即保存
-                if let Err(e) = upsert_account(account.email.clone(), display_name, account.token.clone()) {
+                if let Err(e) = upsert_account(account.email.clone(), display_name, account.token.clone(), Some(&stored_refresh_token)) {
                      modules::logger::log_warn(&format!("保存用户名失败: {}", e));
                 }
             },
@@ -771,7 +1818,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
         if project_id.is_some() && *project_id != account.token.project_id {
             modules::logger::log_info(&format!("检测到 project_id 更新 ({}), 正在保存...", account.email));
             account.token.project_id = project_id.clone();
-            if let Err(e) = upsert_account(account.email.clone(), account.name.clone(), account.token.clone()) {
+            if let Err(e) = upsert_account(account.email.clone(), account.name.clone(), account.token.clone(), Some(&stored_refresh_token)) {
                 modules::logger::log_warn(&format!("同步保存 project_id 失败: {}", e));
             }
         }
@@ -797,6 +1844,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
                             account.disabled_reason = Some(format!("invalid_grant: {}", e));
                             let _ = save_account(account);
                         }
+                        notify_token_refresh_failed(&account.id, &account.email, &e);
                         return Err(AppError::OAuth(e));
                     }
                 };
@@ -822,7 +1870,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
                 
                 account.token = new_token.clone();
                 account.name = name.clone();
-                upsert_account(account.email.clone(), name, new_token.clone()).map_err(AppError::Account)?;
+                upsert_account(account.email.clone(), name, new_token.clone(), Some(&stored_refresh_token)).map_err(AppError::Account)?;
                 
                 // 重试查询
                 let retry_result: crate::error::AppResult<(QuotaData, Option<String>)> = modules::fetch_quota(&new_token.access_token, &account.email).await;
@@ -832,7 +1880,7 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
                     if project_id.is_some() && *project_id != account.token.project_id {
                         modules::logger::log_info(&format!("检测到重试后 project_id 更新 ({}), 正在保存...", account.email));
                         account.token.project_id = project_id.clone();
-                        let _ = upsert_account(account.email.clone(), account.name.clone(), account.token.clone());
+                        let _ = upsert_account(account.email.clone(), account.name.clone(), account.token.clone(), Some(&stored_refresh_token));
                     }
                 }
 
@@ -854,32 +1902,91 @@ pub async fn fetch_quota_with_retry(account: &mut Account) -> crate::error::AppR
     result.map(|(q, _)| q)
 }
 
-#[derive(Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RefreshStats {
     pub total: usize,
     pub success: usize,
     pub failed: usize,
     pub details: Vec<String>,
+    pub duration_ms: u64,
 }
 
-/// 批量刷新所有账号配额的核心逻辑 (不依赖 Tauri 状态)
-pub async fn refresh_all_quotas_logic() -> Result<RefreshStats, String> {
-    use futures::future::join_all;
-    use std::sync::Arc;
-    use tokio::sync::Semaphore;
+/// 以固定并发度执行刷新任务并汇总结果
+/// 网络 I/O 是瓶颈，落盘由 update_account_quota 自行串行化
+async fn run_refresh_tasks<F, Fut>(accounts: Vec<Account>, concurrency: usize, refresh: F) -> RefreshStats
+where
+    F: Fn(Account) -> Fut,
+    Fut: std::future::Future<Output = Result<(), String>>,
+{
+    use futures::stream::{self, StreamExt};
 
-    const MAX_CONCURRENT: usize = 5;
     let start = std::time::Instant::now();
+    let total = accounts.len();
+    let results: Vec<Result<(), String>> = stream::iter(accounts.into_iter().map(refresh))
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut success = 0;
+    let mut failed = 0;
+    let mut details = Vec::new();
+
+    for result in results {
+        match result {
+            Ok(()) => success += 1,
+            Err(msg) => {
+                failed += 1;
+                details.push(msg);
+            }
+        }
+    }
+
+    RefreshStats {
+        total,
+        success,
+        failed,
+        details,
+        duration_ms: start.elapsed().as_millis() as u64,
+    }
+}
+
+/// 正在进行的批量刷新数量 (手动 + 定时)
+static ACTIVE_REFRESHES: AtomicUsize = AtomicUsize::new(0);
+
+/// 批量刷新期间持有，结束 (含出错) 时自动释放
+struct RefreshInProgress;
+
+impl RefreshInProgress {
+    fn enter() -> Self {
+        ACTIVE_REFRESHES.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for RefreshInProgress {
+    fn drop(&mut self) {
+        ACTIVE_REFRESHES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 是否有批量刷新正在进行
+pub fn is_refresh_in_progress() -> bool {
+    ACTIVE_REFRESHES.load(Ordering::SeqCst) > 0
+}
+
+/// 批量刷新所有账号配额的核心逻辑 (不依赖 Tauri 状态)
+pub async fn refresh_all_quotas_logic() -> Result<RefreshStats, String> {
+    let _in_progress = RefreshInProgress::enter();
+    let concurrency = crate::modules::config::load_app_config()
+        .map(|c| c.refresh_concurrency)
+        .unwrap_or(crate::models::config::DEFAULT_REFRESH_CONCURRENCY)
+        .max(1);
 
     crate::modules::logger::log_info(&format!(
         "开始批量刷新所有账号配额 (并发模式, 最大并发: {})",
-        MAX_CONCURRENT
+        concurrency
     ));
-    let accounts = list_accounts()?;
-
-    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT));
-
-    let tasks: Vec<_> = accounts
+    let accounts: Vec<Account> = list_accounts()?
         .into_iter()
         .filter(|account| {
             if account.disabled {
@@ -894,63 +2001,413 @@ pub async fn refresh_all_quotas_logic() -> Result<RefreshStats, String> {
             }
             true
         })
-        .map(|mut account| {
-            let email = account.email.clone();
-            let account_id = account.id.clone();
-            let permit = semaphore.clone();
-            async move {
-                let _guard = permit.acquire().await.unwrap();
-                crate::modules::logger::log_info(&format!("  - Processing {}", email));
-                match fetch_quota_with_retry(&mut account).await {
-                    Ok(quota) => {
-                        if let Err(e) = update_account_quota(&account_id, quota) {
-                            let msg = format!("Account {}: Save quota failed - {}", email, e);
-                            crate::modules::logger::log_error(&msg);
-                            Err(msg)
-                        } else {
-                            crate::modules::logger::log_info(&format!("    ✅ {} Success", email));
-                            Ok(())
-                        }
-                    }
-                    Err(e) => {
-                        let msg = format!("Account {}: Fetch quota failed - {}", email, e);
-                        crate::modules::logger::log_error(&msg);
-                        Err(msg)
-                    }
+        .collect();
+
+    let stats = run_refresh_tasks(accounts, concurrency, |mut account| async move {
+        let email = account.email.clone();
+        let account_id = account.id.clone();
+        crate::modules::logger::log_info(&format!("  - Processing {}", email));
+        match fetch_quota_with_retry(&mut account).await {
+            Ok(quota) => {
+                if let Err(e) = update_account_quota(&account_id, quota) {
+                    let msg = format!("Account {}: Save quota failed - {}", email, e);
+                    crate::modules::logger::log_error(&msg);
+                    Err(msg)
+                } else {
+                    crate::modules::logger::log_info(&format!("    ✅ {} Success", email));
+                    Ok(())
                 }
             }
+            Err(e) => {
+                let msg = format!("Account {}: Fetch quota failed - {}", email, e);
+                crate::modules::logger::log_error(&msg);
+                Err(msg)
+            }
+        }
+    })
+    .await;
+
+    crate::modules::logger::log_info(&format!(
+        "批量刷新完成: {} 成功, {} 失败, 耗时: {}ms",
+        stats.success, stats.failed, stats.duration_ms
+    ));
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_write_file_atomically_replaces_via_temp_file() {
+        let dir = std::env::temp_dir().join(format!("atomic_write_test_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(ACCOUNTS_INDEX);
+        fs::write(&path, "old").unwrap();
+
+        for fsync in [true, false] {
+            let content = format!("{{\"fsync\": {}}}", fsync);
+            write_file_atomically(&path, content.as_bytes(), fsync).unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        }
+        // 临时文件已被重命名，目录中只剩目标文件
+        let entries: Vec<_> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(entries, vec![std::ffi::OsString::from(ACCOUNTS_INDEX)]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    fn make_accounts(n: usize) -> Vec<Account> {
+        (0..n)
+            .map(|i| {
+                let token = TokenData::new(
+                    format!("access-{}", i),
+                    format!("refresh-{}", i),
+                    3600,
+                    None,
+                    None,
+                    None,
+                );
+                Account::new(format!("acc-{}", i), format!("acc-{}@example.com", i), token)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_account_notes_serde() {
+        let mut account = make_accounts(1).remove(0);
+        let json = serde_json::to_value(&account).unwrap();
+        assert!(json.get("notes").is_none());
+
+        // 旧版本账号文件没有 notes 字段
+        let legacy: Account = serde_json::from_value(json).unwrap();
+        assert_eq!(legacy.notes, None);
+
+        account.notes = Some("VPN required".to_string());
+        let restored: Account = serde_json::from_str(&serde_json::to_string(&account).unwrap()).unwrap();
+        assert_eq!(restored.notes.as_deref(), Some("VPN required"));
+    }
+
+    #[test]
+    fn test_is_email_domain_allowed() {
+        assert!(is_email_domain_allowed("a@gmail.com", &[]));
+        let allowed = vec!["company.com".to_string(), "@Example.org".to_string()];
+        assert!(is_email_domain_allowed("alice@Company.com", &allowed));
+        assert!(is_email_domain_allowed("bob@example.org", &allowed));
+        assert!(!is_email_domain_allowed("eve@gmail.com", &allowed));
+        assert!(!is_email_domain_allowed("eve@sub.company.com", &allowed));
+        assert!(!is_email_domain_allowed("not-an-email", &allowed));
+    }
+
+    #[test]
+    fn test_find_refresh_token_owner() {
+        let accounts = make_accounts(3);
+        let owner = find_refresh_token_owner(&accounts, "new@example.com", "refresh-1").unwrap();
+        assert_eq!(owner.email, "acc-1@example.com");
+        // 同一邮箱重复添加 (更新 Token) 不算冲突
+        assert!(find_refresh_token_owner(&accounts, "acc-1@example.com", "refresh-1").is_none());
+        assert!(find_refresh_token_owner(&accounts, "new@example.com", "refresh-9").is_none());
+    }
+
+    #[test]
+    fn test_forbidden_account_ids_skip_current() {
+        let mut accounts = make_accounts(3);
+        for account in &mut accounts[..2] {
+            let mut quota = QuotaData::new();
+            quota.is_forbidden = true;
+            account.quota = Some(quota);
+        }
+        accounts[2].quota = Some(QuotaData::new());
+
+        assert_eq!(forbidden_account_ids(&accounts, None), vec!["acc-0", "acc-1"]);
+        assert_eq!(forbidden_account_ids(&accounts, Some("acc-0")), vec!["acc-1"]);
+    }
+
+    #[test]
+    fn test_summarize_quotas() {
+        let mut accounts = make_accounts(4);
+        let mut a = QuotaData::new();
+        a.last_updated = 100;
+        a.add_model("gemini-3-pro-high".to_string(), 80, String::new());
+        a.add_model("claude-sonnet-4-5".to_string(), 0, String::new());
+        let mut b = QuotaData::new();
+        b.last_updated = 200;
+        b.add_model("gemini-3-pro-high".to_string(), 20, String::new());
+        let mut forbidden = QuotaData::new();
+        forbidden.last_updated = 50;
+        forbidden.is_forbidden = true;
+        accounts[0].quota = Some(a);
+        accounts[1].quota = Some(b);
+        accounts[2].quota = Some(forbidden);
+
+        let summary = summarize_quotas(&accounts);
+        assert_eq!(summary.total_accounts, 4);
+        assert_eq!(summary.accounts_with_quota, 2);
+        assert_eq!(summary.forbidden_accounts, 1);
+        assert_eq!(summary.last_refreshed_at, Some(200));
+        assert!((summary.avg_remaining_percentage - 30.0).abs() < f64::EPSILON);
+        // 80% + 0% + 20% 的模型窗口; 无配额 / 被禁止的账号不计入平均值的分母
+        assert_eq!(summary.total_requests_remaining, 100);
+        assert_eq!(summary.total_tokens_remaining, 1_000_000);
+        assert!((summary.avg_requests_remaining - 50.0).abs() < f64::EPSILON);
+        assert_eq!(
+            summary.models,
+            vec![
+                ModelQuotaSummary {
+                    name: "claude-sonnet-4-5".to_string(),
+                    accounts: 1,
+                    avg_remaining_percentage: 0.0,
+                    exhausted_accounts: 1,
+                },
+                ModelQuotaSummary {
+                    name: "gemini-3-pro-high".to_string(),
+                    accounts: 2,
+                    avg_remaining_percentage: 50.0,
+                    exhausted_accounts: 0,
+                },
+            ]
+        );
+
+        let empty = summarize_quotas(&[]);
+        assert_eq!(empty.avg_remaining_percentage, 0.0);
+        assert_eq!(empty.total_requests_remaining, 0);
+        assert_eq!(empty.avg_requests_remaining, 0.0);
+        assert_eq!(empty.last_refreshed_at, None);
+    }
+
+    #[test]
+    fn test_account_display_name_prefers_custom_name() {
+        let mut account = make_accounts(1).remove(0);
+        assert_eq!(account.display_name(), None);
+        account.name = Some("Jane Doe".to_string());
+        assert_eq!(account.display_name(), Some("Jane Doe"));
+
+        account.custom_name = Some("Work".to_string());
+        let restored: Account = serde_json::from_str(&serde_json::to_string(&account).unwrap()).unwrap();
+        assert_eq!(restored.display_name(), Some("Work"));
+        assert_eq!(restored.name.as_deref(), Some("Jane Doe"));
+    }
+
+    #[test]
+    fn test_migrate_data_dir_between() {
+        let root = std::env::temp_dir().join(format!("data_dir_migrate_test_{}", Uuid::new_v4()));
+        let default_dir = root.join(".antigravity_tools");
+        fs::create_dir_all(default_dir.join("accounts")).unwrap();
+        fs::write(default_dir.join("accounts.json"), "{}").unwrap();
+        fs::write(default_dir.join("accounts").join("a.json"), "{\"id\":\"a\"}").unwrap();
+
+        // 非空目标目录被拒绝，原目录保持不变
+        let occupied = root.join("occupied");
+        fs::create_dir_all(&occupied).unwrap();
+        fs::write(occupied.join("other.txt"), "x").unwrap();
+        assert!(migrate_data_dir_between(&default_dir, &default_dir, &occupied).is_err());
+        assert!(migrate_data_dir_between(&default_dir, &default_dir, &default_dir.join("nested")).is_err());
+        assert!(migrate_data_dir_between(&default_dir, &default_dir, std::path::Path::new("relative")).is_err());
+        assert!(!default_dir.join(DATA_DIR_REDIRECT).exists());
+
+        let target = root.join("cloud").join("antigravity");
+        migrate_data_dir_between(&default_dir, &default_dir, &target).unwrap();
+        assert_eq!(fs::read_to_string(target.join("accounts").join("a.json")).unwrap(), "{\"id\":\"a\"}");
+        assert_eq!(read_data_dir_redirect(&default_dir), Some(target.clone()));
+        // 不残留临时目录
+        assert_eq!(fs::read_dir(root.join("cloud")).unwrap().count(), 1);
+
+        // 迁回默认目录后删除重定向
+        fs::write(target.join("accounts.json"), "{\"accounts\":[]}").unwrap();
+        migrate_data_dir_between(&target, &default_dir, &default_dir).unwrap();
+        assert_eq!(read_data_dir_redirect(&default_dir), None);
+        assert_eq!(fs::read_to_string(default_dir.join("accounts.json")).unwrap(), "{\"accounts\":[]}");
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_account_last_error_record_and_clear() {
+        let mut account = make_accounts(1).remove(0);
+        assert!(serde_json::to_value(&account).unwrap()["health"].get("last_error").is_none());
+
+        account.health.record_error(1_700_000_000);
+        account.health.last_error = Some("Network error: timeout".to_string());
+        let json = serde_json::to_value(&account).unwrap();
+        assert!(json.get("last_error").is_none());
+        assert_eq!(json["health"]["last_error"], "Network error: timeout");
+        assert_eq!(json["health"]["last_error_at"], 1_700_000_000);
+
+        account.health.record_success(None, 1_700_000_100);
+        assert_eq!(account.health.last_error, None);
+        assert_eq!(account.health.last_error_at, Some(1_700_000_000));
+        assert_eq!(account.health.last_success_at, Some(1_700_000_100));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_concurrency_one_is_serial() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+
+        let stats = run_refresh_tasks(make_accounts(4), 1, |account| {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Err(format!("Account {}: failed", account.email))
+            }
         })
-        .collect();
+        .await;
 
-    let total = tasks.len();
-    let results = join_all(tasks).await;
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 1);
+        assert_eq!(stats.total, 4);
+        assert_eq!(stats.failed, 4);
+        // 串行模式下结果顺序与账号顺序一致
+        let expected: Vec<String> = (0..4)
+            .map(|i| format!("Account acc-{}@example.com: failed", i))
+            .collect();
+        assert_eq!(stats.details, expected);
+    }
 
-    let mut success = 0;
-    let mut failed = 0;
-    let mut details = Vec::new();
+    #[tokio::test]
+    async fn test_refresh_concurrency_three_runs_in_batches() {
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let in_flight = Arc::new(AtomicUsize::new(0));
 
-    for result in results {
-        match result {
-            Ok(()) => success += 1,
-            Err(msg) => {
-                failed += 1;
-                details.push(msg);
+        let stats = run_refresh_tasks(make_accounts(6), 3, |_| {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(())
             }
+        })
+        .await;
+
+        assert_eq!(stats.success, 6);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+        // 6 个账号、并发 3: 约 2 批 (~200ms)，远小于串行的 ~600ms
+        assert!(stats.duration_ms >= 190, "duration {}ms", stats.duration_ms);
+        assert!(stats.duration_ms < 450, "duration {}ms", stats.duration_ms);
+    }
+
+    fn make_backup_entry() -> AccountBackupEntry {
+        AccountBackupEntry {
+            email: "a@example.com".to_string(),
+            name: Some("A".to_string()),
+            refresh_token: "1//refresh-a".to_string(),
+            project_id: Some("proj-a".to_string()),
+            device_profile: None,
+            upstream_proxy: Some("socks5://127.0.0.1:1080".to_string()),
+            created_at: 1,
         }
     }
 
-    let elapsed = start.elapsed();
-    crate::modules::logger::log_info(&format!(
-        "批量刷新完成: {} 成功, {} 失败, 耗时: {}ms",
-        success,
-        failed,
-        elapsed.as_millis()
-    ));
+    #[test]
+    fn test_backup_plaintext_roundtrip() {
+        let file = build_backup_file(vec![make_backup_entry()], None).unwrap();
+        assert!(!file.encrypted);
+        let json = serde_json::to_string(&file).unwrap();
+        assert!(!json.contains("access_token"));
 
-    Ok(RefreshStats {
-        total,
-        success,
-        failed,
-        details,
-    })
+        let parsed: AccountBackupFile = serde_json::from_str(&json).unwrap();
+        let entries = read_backup_entries(parsed, None).unwrap();
+        assert_eq!(entries[0].refresh_token, "1//refresh-a");
+    }
+
+    #[test]
+    fn test_backup_encrypted_roundtrip() {
+        let file = build_backup_file(vec![make_backup_entry()], Some("hunter2")).unwrap();
+        assert!(file.encrypted && file.accounts.is_none());
+        let json = serde_json::to_string(&file).unwrap();
+        assert!(!json.contains("1//refresh-a"));
+
+        let parsed: AccountBackupFile = serde_json::from_str(&json).unwrap();
+        assert!(read_backup_entries(parsed.clone(), None).is_err());
+        assert!(read_backup_entries(parsed.clone(), Some("wrong")).is_err());
+        let entries = read_backup_entries(parsed, Some("hunter2")).unwrap();
+        assert_eq!(entries[0].email, "a@example.com");
+        assert_eq!(entries[0].upstream_proxy.as_deref(), Some("socks5://127.0.0.1:1080"));
+    }
+
+    #[test]
+    fn test_accounts_archive_roundtrip_and_merge() {
+        let dir = std::env::temp_dir().join(format!("account_archive_test_{}", Uuid::new_v4()));
+        let data_dir = dir.join("data");
+        fs::create_dir_all(data_dir.join(ACCOUNTS_DIR)).unwrap();
+
+        let mut index = AccountIndex::new();
+        for account in make_accounts(2) {
+            fs::write(
+                data_dir.join(ACCOUNTS_DIR).join(format!("{}.json", account.id)),
+                serde_json::to_string(&account).unwrap(),
+            )
+            .unwrap();
+            index.accounts.push(AccountSummary {
+                id: account.id.clone(),
+                email: account.email.clone(),
+                name: None,
+                upstream_proxy: None,
+                system_prompt_prefix: None,
+                priority: account.priority,
+                created_at: account.created_at,
+                last_used: account.last_used,
+            });
+        }
+        fs::write(data_dir.join(ACCOUNTS_INDEX), serde_json::to_string(&index).unwrap()).unwrap();
+
+        let archive = dir.join("backup.tar.gz");
+        write_accounts_archive(&data_dir, &archive).unwrap();
+        let (archived_index, archived) = read_accounts_archive(&archive).unwrap();
+        assert_eq!(archived.len(), 2);
+
+        // 归档外的账号保留，同 ID 账号被覆盖
+        let mut current = AccountIndex::new();
+        current.accounts.push(AccountSummary {
+            id: "other".to_string(),
+            email: "other@example.com".to_string(),
+            ..index.accounts[0].clone()
+        });
+        current.accounts.push(AccountSummary {
+            email: "stale@example.com".to_string(),
+            ..index.accounts[0].clone()
+        });
+        merge_account_index(&mut current, archived_index);
+        let emails: Vec<_> = current.accounts.iter().map(|s| s.email.as_str()).collect();
+        assert_eq!(emails, vec!["other@example.com", "acc-0@example.com", "acc-1@example.com"]);
+
+        // 损坏的归档与无效的账号文件均应失败
+        fs::write(dir.join("corrupt.tar.gz"), b"not an archive").unwrap();
+        assert!(read_accounts_archive(&dir.join("corrupt.tar.gz")).is_err());
+        fs::write(data_dir.join(ACCOUNTS_DIR).join("acc-1.json"), r#"{"id":"acc-1"}"#).unwrap();
+        write_accounts_archive(&data_dir, &archive).unwrap();
+        assert!(read_accounts_archive(&archive).unwrap_err().contains("acc-1.json"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_quota_history_is_daily_and_capped() {
+        let mut account = make_accounts(1).remove(0);
+        let quota_at = |ts: i64| QuotaData {
+            last_updated: ts,
+            ..QuotaData::new()
+        };
+
+        let day = 24 * 60 * 60;
+        account.update_quota_with_history(quota_at(day), 2);
+        account.update_quota_with_history(quota_at(day + 60), 2); // 同一天仅保留最新
+        assert_eq!(account.quota_history.len(), 1);
+        assert_eq!(account.quota_history[0].0, day + 60);
+
+        account.update_quota_with_history(quota_at(2 * day), 2);
+        account.update_quota_with_history(quota_at(3 * day), 2);
+        let timestamps: Vec<i64> = account.quota_history.iter().map(|(ts, _)| *ts).collect();
+        assert_eq!(timestamps, vec![2 * day, 3 * day]);
+        assert_eq!(account.quota.as_ref().unwrap().last_updated, 3 * day);
+    }
 }
@@ -1,25 +1,35 @@
 use std::fs;
 use serde_json;
 
+use crate::error::{AppError, AppResult, ConfigError};
 use crate::models::AppConfig;
+use crate::proxy::config::ProxyAuthMode;
+use crate::proxy::ProxySecurityConfig;
 use super::account::get_data_dir;
 
 const CONFIG_FILE: &str = "gui_config.json";
 
 /// 加载应用配置
-pub fn load_app_config() -> Result<AppConfig, String> {
-    let data_dir = get_data_dir()?;
+/// 校验不通过时返回 AppError::Config (包含全部无效字段)，由调用方决定是否回退到默认配置
+pub fn load_app_config() -> AppResult<AppConfig> {
+    let config = read_app_config()?;
+    validate_app_config(&config).map_err(AppError::Config)?;
+    Ok(config)
+}
+
+/// 读取 (并迁移) 配置文件但不校验，供设置页在配置无效时仍能展示并修正配置
+pub fn read_app_config() -> AppResult<AppConfig> {
+    let data_dir = get_data_dir().map_err(AppError::Unknown)?;
     let config_path = data_dir.join(CONFIG_FILE);
     
     if !config_path.exists() {
         return Ok(AppConfig::new());
     }
     
-    let content = fs::read_to_string(&config_path)
-        .map_err(|e| format!("读取配置文件失败: {}", e))?;
+    let content = fs::read_to_string(&config_path)?;
     
     let mut v: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| format!("解析配置文件失败: {}", e))?;
+        .map_err(|e| AppError::Config(vec![ConfigError::new(CONFIG_FILE, format!("解析配置文件失败: {}", e))]))?;
     
     let mut modified = false;
 
@@ -65,24 +75,215 @@ pub fn load_app_config() -> Result<AppConfig, String> {
     }
 
     let config: AppConfig = serde_json::from_value(v)
-        .map_err(|e| format!("迁移后转换配置失败: {}", e))?;
-    
+        .map_err(|e| AppError::Config(vec![ConfigError::new(CONFIG_FILE, format!("迁移后转换配置失败: {}", e))]))?;
+
     // 如果发生了迁移，自动保存一次以清理文件
     if modified {
-        let _ = save_app_config(&config);
+        let _ = write_app_config(&config);
     }
 
     Ok(config)
 }
 
-/// 保存应用配置
-pub fn save_app_config(config: &AppConfig) -> Result<(), String> {
-    let data_dir = get_data_dir()?;
+/// 保存应用配置 (校验不通过时返回 AppError::Config，不写入文件)
+pub fn save_app_config(config: &AppConfig) -> AppResult<()> {
+    validate_app_config(config).map_err(AppError::Config)?;
+    write_app_config(config)
+}
+
+fn write_app_config(config: &AppConfig) -> AppResult<()> {
+    let data_dir = get_data_dir().map_err(AppError::Unknown)?;
     let config_path = data_dir.join(CONFIG_FILE);
     
     let content = serde_json::to_string_pretty(config)
-        .map_err(|e| format!("序列化配置失败: {}", e))?;
+        .map_err(|e| AppError::Unknown(format!("序列化配置失败: {}", e)))?;
     
     fs::write(&config_path, content)
-        .map_err(|e| format!("保存配置失败: {}", e))
+        .map_err(|e| AppError::io(format!("保存配置失败: {}", e)))
+}
+
+/// reqwest 支持的代理协议
+const PROXY_SCHEMES: [&str; 6] = ["http", "https", "socks4", "socks4a", "socks5", "socks5h"];
+
+/// 校验上游代理地址，与 reqwest 的解析规则保持一致 (省略协议时按 http 处理)
+pub fn validate_proxy_url(url: &str) -> Result<(), String> {
+    let url = url.trim();
+    let parsed = url::Url::parse(url)
+        .or_else(|_| url::Url::parse(&format!("http://{}", url)))
+        .map_err(|e| format!("代理地址无效: {}", e))?;
+    if !PROXY_SCHEMES.contains(&parsed.scheme()) {
+        return Err(format!(
+            "不支持的代理协议: {} (支持 {})",
+            parsed.scheme(),
+            PROXY_SCHEMES.join("/")
+        ));
+    }
+    if parsed.host_str().is_none_or(|h| h.is_empty()) {
+        return Err("代理地址缺少主机名".to_string());
+    }
+    Ok(())
+}
+
+/// 校验应用配置，返回全部不合法字段 (而非遇到第一个错误即停止)
+pub fn validate_app_config(config: &AppConfig) -> Result<(), Vec<ConfigError>> {
+    let mut errors = Vec::new();
+    let proxy = &config.proxy;
+
+    if proxy.port == 0 {
+        errors.push(ConfigError::new("proxy.port", "端口必须在 1-65535 之间"));
+    }
+    if let Some((start, end)) = proxy.port_fallback_range {
+        if start == 0 || start > end {
+            errors.push(ConfigError::new("proxy.port_fallback_range", "备用端口范围无效"));
+        }
+    }
+
+    if proxy.upstream_proxy.enabled {
+        if let Err(e) = validate_proxy_url(&proxy.upstream_proxy.url) {
+            errors.push(ConfigError::new("proxy.upstream_proxy.url", e));
+        }
+    }
+
+    if let Some(rps) = proxy.rate_limit_rps {
+        if !rps.is_finite() || rps < 0.0 {
+            errors.push(ConfigError::new("proxy.rate_limit_rps", "速率上限不能为负数"));
+        }
+    }
+    let mut account_keys: Vec<_> = proxy.account_rate_limit_rps.keys().collect();
+    account_keys.sort();
+    for key in account_keys {
+        let rps = proxy.account_rate_limit_rps[key];
+        if !rps.is_finite() || rps < 0.0 {
+            errors.push(ConfigError::new(
+                format!("proxy.account_rate_limit_rps.{}", key),
+                "速率上限不能为负数",
+            ));
+        }
+    }
+
+    let mut alias_keys: Vec<_> = proxy.model_aliases.keys().collect();
+    alias_keys.sort();
+    for key in alias_keys {
+        if key.trim().is_empty() || proxy.model_aliases[key].trim().is_empty() {
+            errors.push(ConfigError::new(
+                format!("proxy.model_aliases.{}", key),
+                "模型别名与目标模型不能为空",
+            ));
+        }
+    }
+
+    for (key, account_ids) in &proxy.api_key_routes {
+        if key.trim().is_empty() || account_ids.is_empty() {
+            errors.push(ConfigError::new(
+                format!("proxy.api_key_routes.{}", key),
+                "路由 API Key 不能为空且至少绑定一个账号",
+            ));
+        }
+    }
+
+    if config.refresh_interval < 0 {
+        errors.push(ConfigError::new("refresh_interval", "刷新间隔不能为负数"));
+    }
+    if config.sync_interval < 0 {
+        errors.push(ConfigError::new("sync_interval", "同步间隔不能为负数"));
+    }
+    if config.max_log_size_mb == 0 {
+        errors.push(ConfigError::new("max_log_size_mb", "日志文件大小上限至少为 1 MB"));
+    }
+
+    // 实际生效的鉴权模式 (Auto 在仅本机访问时等同于 Off)
+    let auth_mode = ProxySecurityConfig::from_proxy_config(proxy).effective_auth_mode();
+    if !matches!(auth_mode, ProxyAuthMode::Off) && proxy.api_key.trim().is_empty() {
+        errors.push(ConfigError::new("proxy.api_key", "启用鉴权时 API Key 不能为空"));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(config: &AppConfig) -> Vec<String> {
+        validate_app_config(config)
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| e.field)
+            .collect()
+    }
+
+    #[test]
+    fn test_default_config_is_valid() {
+        assert!(validate_app_config(&AppConfig::new()).is_ok());
+    }
+
+    #[test]
+    fn test_save_rejects_invalid_config() {
+        let mut config = AppConfig::new();
+        config.proxy.port = 0;
+        // 校验先于写入，不会触碰配置文件
+        match save_app_config(&config) {
+            Err(AppError::Config(errors)) => assert_eq!(errors[0].field, "proxy.port"),
+            other => panic!("expected config error, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_validate_collects_all_errors() {
+        let mut config = AppConfig::new();
+        config.proxy.port = 0;
+        config.proxy.port_fallback_range = Some((8090, 8080));
+        config.proxy.upstream_proxy.enabled = true;
+        config.proxy.upstream_proxy.url = "ftp://127.0.0.1:21".to_string();
+        config.proxy.rate_limit_rps = Some(-1.0);
+        config.proxy.account_rate_limit_rps.insert("a@example.com".to_string(), f64::NAN);
+        config.proxy.model_aliases.insert("gpt-4-turbo".to_string(), " ".to_string());
+        config.refresh_interval = -5;
+        config.proxy.auth_mode = ProxyAuthMode::Strict;
+        config.proxy.api_key = "  ".to_string();
+
+        assert_eq!(
+            fields(&config),
+            vec![
+                "proxy.port",
+                "proxy.port_fallback_range",
+                "proxy.upstream_proxy.url",
+                "proxy.rate_limit_rps",
+                "proxy.account_rate_limit_rps.a@example.com",
+                "proxy.model_aliases.gpt-4-turbo",
+                "refresh_interval",
+                "proxy.api_key",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_upstream_proxy_and_auth() {
+        let mut config = AppConfig::new();
+        config.proxy.upstream_proxy.enabled = true;
+        config.proxy.upstream_proxy.url = "socks5://127.0.0.1:1080".to_string();
+        config.proxy.auth_mode = ProxyAuthMode::Auto;
+        config.proxy.api_key = String::new();
+        // Auto + 仅本机访问 => 鉴权实际关闭，允许空 Key
+        assert!(validate_app_config(&config).is_ok());
+
+        config.proxy.allow_lan_access = true;
+        config.proxy.upstream_proxy.url = "not a url".to_string();
+        assert_eq!(fields(&config), vec!["proxy.upstream_proxy.url", "proxy.api_key"]);
+    }
+
+    #[test]
+    fn test_validate_proxy_url_matches_reqwest() {
+        for url in ["127.0.0.1:7890", "http://127.0.0.1:7890", "socks5h://127.0.0.1:1080", "socks4://proxy:1080"] {
+            assert!(validate_proxy_url(url).is_ok(), "{}", url);
+        }
+        for url in ["ftp://127.0.0.1:21", "not a url", ""] {
+            assert!(validate_proxy_url(url).is_err(), "{}", url);
+        }
+    }
 }
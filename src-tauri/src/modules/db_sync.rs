@@ -0,0 +1,108 @@
+// 后台账号同步
+// 按 db_sync_interval_secs 周期读取 Antigravity 数据库，检测到账号切换时自动导入并通知前端
+
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::time;
+
+use crate::modules::{config, db, logger, migration};
+
+/// 连续解析失败达到该次数后进入退避
+const MAX_PARSE_FAILURES: u32 = 3;
+/// 退避时的间隔倍数
+const BACKOFF_FACTOR: u32 = 10;
+/// 退避间隔上限
+const MAX_BACKOFF_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// 根据连续解析失败次数计算同步间隔
+fn sync_interval(base: Duration, parse_failures: u32) -> Duration {
+    if parse_failures < MAX_PARSE_FAILURES {
+        base
+    } else {
+        (base * BACKOFF_FACTOR).clamp(base, MAX_BACKOFF_INTERVAL.max(base))
+    }
+}
+
+fn new_interval(period: Duration) -> time::Interval {
+    let mut interval = time::interval_at(time::Instant::now() + period, period);
+    interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    interval
+}
+
+pub fn start_db_sync(app_handle: tauri::AppHandle) {
+    let base = match config::load_app_config().ok().and_then(|c| c.db_sync_interval_secs) {
+        Some(secs) if secs > 0 => Duration::from_secs(secs),
+        _ => return,
+    };
+
+    tauri::async_runtime::spawn(async move {
+        logger::log_info(&format!("[DbSync] Background account sync started, interval {}s", base.as_secs()));
+
+        let mut parse_failures = 0u32;
+        let mut period = base;
+        let mut interval = new_interval(period);
+
+        loop {
+            interval.tick().await;
+
+            // 未安装 Antigravity (数据库不存在) 时静默跳过
+            let installed = db::get_db_path().map(|p| p.exists()).unwrap_or(false);
+            if !installed {
+                continue;
+            }
+
+            match migration::get_refresh_token_from_db() {
+                Ok(token) => {
+                    if parse_failures >= MAX_PARSE_FAILURES {
+                        logger::log_info("[DbSync] Database readable again, restoring sync interval");
+                    }
+                    parse_failures = 0;
+                    match crate::commands::sync_account_with_refresh_token(app_handle.clone(), token).await {
+                        Ok(Some(account)) => {
+                            let _ = app_handle.emit("account://synced", &account);
+                        }
+                        Ok(None) => {}
+                        Err(e) => logger::log_warn(&format!("[DbSync] Account sync failed: {}", e)),
+                    }
+                }
+                Err(e) => {
+                    parse_failures += 1;
+                    if parse_failures <= MAX_PARSE_FAILURES {
+                        logger::log_warn(&format!(
+                            "[DbSync] Failed to read account from database ({}/{}): {}",
+                            parse_failures, MAX_PARSE_FAILURES, e
+                        ));
+                    }
+                    if parse_failures == MAX_PARSE_FAILURES {
+                        logger::log_warn(&format!(
+                            "[DbSync] Backing off to {}s after repeated failures",
+                            sync_interval(base, parse_failures).as_secs()
+                        ));
+                    }
+                }
+            }
+
+            let next = sync_interval(base, parse_failures);
+            if next != period {
+                period = next;
+                interval = new_interval(period);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_after_repeated_parse_failures() {
+        let base = Duration::from_secs(30);
+        assert_eq!(sync_interval(base, 0), base);
+        assert_eq!(sync_interval(base, 2), base);
+        assert_eq!(sync_interval(base, 3), Duration::from_secs(300));
+        assert_eq!(sync_interval(Duration::from_secs(600), 5), MAX_BACKOFF_INTERVAL);
+        // 基础间隔已超过上限时不缩短
+        assert_eq!(sync_interval(Duration::from_secs(7200), 5), Duration::from_secs(7200));
+    }
+}
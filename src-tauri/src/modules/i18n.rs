@@ -13,6 +13,7 @@ pub struct TrayTexts {
     pub no_account: String,
     pub unknown_quota: String,
     pub forbidden: String,
+    pub frozen: String,
 }
 
 /// Load translations from JSON
@@ -78,5 +79,9 @@ pub fn get_tray_texts(lang: &str) -> TrayTexts {
             .get("forbidden")
             .cloned()
             .unwrap_or_else(|| "Account Forbidden".to_string()),
+        frozen: t
+            .get("frozen")
+            .cloned()
+            .unwrap_or_else(|| "Frozen (not used by proxy)".to_string()),
     }
 }
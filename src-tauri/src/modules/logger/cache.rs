@@ -0,0 +1,142 @@
+// 内存日志缓存
+// 保留最近 N 条日志供前端检索，读多写少，使用 RwLock 保护有界队列
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::RwLock;
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use super::JsonFieldVisitor;
+
+pub const DEFAULT_LOG_CACHE_CAPACITY: usize = 10_000;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogPage {
+    pub entries: Vec<LogEntry>,
+    pub total: usize, // 过滤后的总条数
+}
+
+pub struct LogCache {
+    capacity: usize,
+    entries: RwLock<VecDeque<LogEntry>>,
+}
+
+impl LogCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: RwLock::new(VecDeque::with_capacity(capacity.min(1024))),
+        }
+    }
+
+    pub fn push(&self, entry: LogEntry) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        while entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    pub fn clear(&self) {
+        self.entries.write().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// 按级别与关键字 (均不区分大小写) 过滤，最新在前分页返回
+    pub fn query(&self, level: Option<&str>, search: Option<&str>, limit: usize, offset: usize) -> LogPage {
+        let level = level.map(str::trim).filter(|l| !l.is_empty());
+        let search = search
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty());
+
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        let matched: Vec<&LogEntry> = entries
+            .iter()
+            .rev()
+            .filter(|e| level.is_none_or(|l| e.level.eq_ignore_ascii_case(l)))
+            .filter(|e| {
+                search
+                    .as_deref()
+                    .is_none_or(|s| e.message.to_lowercase().contains(s))
+            })
+            .collect();
+
+        LogPage {
+            total: matched.len(),
+            entries: matched.into_iter().skip(offset).take(limit).cloned().collect(),
+        }
+    }
+}
+
+static LOG_CACHE: Lazy<LogCache> = Lazy::new(|| LogCache::new(DEFAULT_LOG_CACHE_CAPACITY));
+
+/// 全局日志缓存
+pub fn log_cache() -> &'static LogCache {
+    &LOG_CACHE
+}
+
+/// 将通过过滤的日志事件写入全局缓存
+pub struct LogCacheLayer;
+
+impl<S: Subscriber> Layer<S> for LogCacheLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = JsonFieldVisitor::default();
+        event.record(&mut visitor);
+
+        let mut message = visitor.message.unwrap_or_default();
+        for (key, value) in &visitor.fields {
+            message.push_str(&format!(" {}={}", key, value.as_str().unwrap_or_default()));
+        }
+
+        log_cache().push(LogEntry {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            level: event.metadata().level().to_string(),
+            message,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(level: &str, message: &str) -> LogEntry {
+        LogEntry {
+            timestamp: String::new(),
+            level: level.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_cache_is_bounded_and_filterable() {
+        let cache = LogCache::new(3);
+        cache.push(entry("INFO", "started proxy"));
+        cache.push(entry("WARN", "Proxy retrying"));
+        cache.push(entry("ERROR", "token refresh failed"));
+        cache.push(entry("INFO", "proxy request ok"));
+
+        // 容量 3，最旧的一条被淘汰
+        let all = cache.query(None, None, 10, 0);
+        assert_eq!(all.total, 3);
+        assert_eq!(all.entries[0].message, "proxy request ok");
+
+        let proxy = cache.query(None, Some("PROXY"), 10, 0);
+        assert_eq!(proxy.total, 2);
+
+        let warn = cache.query(Some("warn"), Some("proxy"), 10, 0);
+        assert_eq!(warn.entries, vec![entry("WARN", "Proxy retrying")]);
+
+        let page = cache.query(None, None, 1, 1);
+        assert_eq!(page.total, 3);
+        assert_eq!(page.entries[0].message, "token refresh failed");
+    }
+}
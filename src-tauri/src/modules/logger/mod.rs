@@ -0,0 +1,471 @@
+use tracing::{info, warn, error};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::{self, FormatEvent, FormatFields, Writer};
+use tracing_subscriber::fmt::FmtContext;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use std::fs;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use crate::models::LogFormat;
+use crate::modules::account::get_data_dir;
+
+mod cache;
+mod rotation;
+
+pub use cache::{log_cache, LogEntry, LogPage};
+use cache::LogCacheLayer;
+use rotation::{prune_archives, RotatingFileWriter, RotationPolicy};
+
+// 自定义本地时区时间格式化器
+#[derive(Clone, Copy)]
+struct LocalTimer;
+
+impl tracing_subscriber::fmt::time::FormatTime for LocalTimer {
+    fn format_time(&self, w: &mut tracing_subscriber::fmt::format::Writer<'_>) -> std::fmt::Result {
+        let now = chrono::Local::now();
+        write!(w, "{}", now.to_rfc3339())
+    }
+}
+
+/// 未显式配置时: stdout 不是终端 (服务化部署/重定向) 则使用 JSON
+fn resolve_log_format(configured: Option<LogFormat>, stdout_is_tty: bool) -> LogFormat {
+    configured.unwrap_or(if stdout_is_tty { LogFormat::Plain } else { LogFormat::Json })
+}
+
+/// 收集事件/Span 字段: message、account_id、request_id 单独提取，其余字段原样保留
+#[derive(Default)]
+struct JsonFieldVisitor {
+    message: Option<String>,
+    context: LogContext,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl JsonFieldVisitor {
+    fn record_value(&mut self, field: &Field, value: String) {
+        match field.name() {
+            "message" => self.message = Some(value),
+            "account_id" => self.context.account_id = Some(value),
+            "request_id" => self.context.request_id = Some(value),
+            name => {
+                self.fields.insert(name.to_string(), serde_json::Value::String(value));
+            }
+        }
+    }
+}
+
+impl Visit for JsonFieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_value(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record_value(field, format!("{:?}", value));
+    }
+}
+
+/// Span 上携带的日志上下文 (存放在 span extensions 中)
+#[derive(Debug, Clone, Default)]
+struct LogContext {
+    account_id: Option<String>,
+    request_id: Option<String>,
+}
+
+impl LogContext {
+    fn merge(&mut self, other: &LogContext) {
+        if other.account_id.is_some() {
+            self.account_id = other.account_id.clone();
+        }
+        if other.request_id.is_some() {
+            self.request_id = other.request_id.clone();
+        }
+    }
+}
+
+/// 记录 span 上的 account_id / request_id，供 JSON 格式化器读取
+struct LogContextLayer;
+
+impl LogContextLayer {
+    fn update<S>(id: &Id, ctx: &Context<'_, S>, visitor: JsonFieldVisitor)
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        if visitor.context.account_id.is_none() && visitor.context.request_id.is_none() {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            let mut extensions = span.extensions_mut();
+            match extensions.get_mut::<LogContext>() {
+                Some(existing) => existing.merge(&visitor.context),
+                None => extensions.insert(visitor.context),
+            }
+        }
+    }
+}
+
+impl<S> Layer<S> for LogContextLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = JsonFieldVisitor::default();
+        attrs.record(&mut visitor);
+        Self::update(id, &ctx, visitor);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let mut visitor = JsonFieldVisitor::default();
+        values.record(&mut visitor);
+        Self::update(id, &ctx, visitor);
+    }
+}
+
+/// 按配置在文本格式与 JSON 格式之间切换的事件格式化器
+enum EventFormatter {
+    Plain(format::Format<format::Full, LocalTimer>),
+    Json,
+}
+
+impl EventFormatter {
+    fn new(log_format: LogFormat, with_target: bool) -> Self {
+        match log_format {
+            LogFormat::Plain => Self::Plain(
+                format::format()
+                    .with_target(with_target)
+                    .with_thread_ids(false)
+                    .with_level(true)
+                    .with_timer(LocalTimer),
+            ),
+            LogFormat::Json => Self::Json,
+        }
+    }
+}
+
+impl<S, N> FormatEvent<S, N> for EventFormatter
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> std::fmt::Result {
+        let Self::Plain(inner) = self else {
+            return writeln!(writer, "{}", json_log_line(ctx, event));
+        };
+        inner.format_event(ctx, writer, event)
+    }
+}
+
+/// 构造单行 JSON 日志: timestamp / level / message / module，以及上下文中的 account_id / request_id
+fn json_log_line<S, N>(ctx: &FmtContext<'_, S, N>, event: &Event<'_>) -> String
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    let mut visitor = JsonFieldVisitor::default();
+    event.record(&mut visitor);
+
+    // 外层 span 的上下文先合并，事件自身字段优先
+    let mut context = LogContext::default();
+    if let Some(scope) = ctx.event_scope() {
+        for span in scope.from_root() {
+            if let Some(span_context) = span.extensions().get::<LogContext>() {
+                context.merge(span_context);
+            }
+        }
+    }
+    context.merge(&visitor.context);
+
+    let metadata = event.metadata();
+    let mut obj = serde_json::Map::new();
+    obj.insert("timestamp".into(), chrono::Local::now().to_rfc3339().into());
+    obj.insert("level".into(), metadata.level().as_str().into());
+    obj.insert("message".into(), visitor.message.unwrap_or_default().into());
+    obj.insert(
+        "module".into(),
+        metadata.module_path().unwrap_or_else(|| metadata.target()).into(),
+    );
+    if let Some(account_id) = context.account_id {
+        obj.insert("account_id".into(), account_id.into());
+    }
+    if let Some(request_id) = context.request_id {
+        obj.insert("request_id".into(), request_id.into());
+    }
+    if !visitor.fields.is_empty() {
+        obj.insert("fields".into(), serde_json::Value::Object(visitor.fields));
+    }
+    serde_json::Value::Object(obj).to_string()
+}
+
+pub fn get_log_dir() -> Result<PathBuf, String> {
+    let data_dir = get_data_dir()?;
+    let log_dir = data_dir.join("logs");
+    
+    if !log_dir.exists() {
+        fs::create_dir_all(&log_dir).map_err(|e| format!("创建日志目录失败: {}", e))?;
+    }
+    
+    Ok(log_dir)
+}
+
+/// 初始化日志系统
+pub fn init_logger() {
+    // 捕获 log 宏日志
+    let _ = tracing_log::LogTracer::init();
+    
+    let log_dir = match get_log_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("无法初始化日志目录: {}", e);
+            return;
+        }
+    };
+    
+    // 0. 确定输出格式 (配置优先，否则根据 stdout 是否为终端自动选择)
+    // 配置校验失败时仍沿用其中的日志选项；错误在订阅器初始化之后再输出，否则会被丢弃
+    let (app_config, config_error) = match crate::modules::config::load_app_config() {
+        Ok(config) => (Some(config), None),
+        Err(e) => (crate::modules::config::read_app_config().ok(), Some(e)),
+    };
+    let log_format = resolve_log_format(
+        app_config.as_ref().and_then(|c| c.log_format),
+        std::io::stdout().is_terminal(),
+    );
+    let rotation_policy = app_config
+        .as_ref()
+        .map(RotationPolicy::from_config)
+        .unwrap_or_default();
+
+    // 1. 设置文件 Appender (按大小轮转，保留有限数量的归档)
+    let file_appender = match RotatingFileWriter::new(log_dir.clone(), rotation_policy) {
+        Ok(writer) => writer,
+        Err(e) => {
+            eprintln!("无法打开日志文件: {}", e);
+            return;
+        }
+    };
+    let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
+    
+    // 2. 终端输出层（使用本地时区）
+    let console_layer = fmt::Layer::new()
+        .with_ansi(log_format == LogFormat::Plain)
+        .event_format(EventFormatter::new(log_format, false));
+        
+    // 3. 文件输出层 (关闭 ANSI 格式化，使用本地时区)
+    let file_layer = fmt::Layer::new()
+        .with_writer(non_blocking)
+        .with_ansi(false)
+        .event_format(EventFormatter::new(log_format, true));
+
+    // 4. 设置过滤层 (默认使用 INFO 级别以减少日志体积)
+    let filter_layer = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+
+    // 5. 初始化全局订阅器 (使用 try_init 避免重复初始化崩溃)
+    let _ = tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(LogContextLayer)
+        .with(LogCacheLayer)
+        .with(console_layer)
+        .with(file_layer)
+        .try_init();
+
+    // 泄漏 _guard 以确保其生命周期持续到程序退出
+    // 这是使用 tracing_appender::non_blocking 时的推荐做法（如果不需要手动刷盘）
+    std::mem::forget(_guard);
+    
+    info!("日志系统已完成初始化 (终端控制台 + 文件持久化, 格式: {:?})", log_format);
+
+    match config_error {
+        Some(crate::error::AppError::Config(errors)) => {
+            for e in &errors {
+                warn!("配置项 {} 无效: {}", e.field, e.message);
+            }
+        }
+        Some(e) => warn!("加载配置失败: {}", e),
+        None => {}
+    }
+    
+    // 清理超出保留数量的归档，以及 7 天前的旧日志
+    if let Err(e) = prune_archives(&log_dir, rotation_policy.max_files) {
+        warn!("清理归档日志失败: {}", e);
+    }
+    if let Err(e) = cleanup_old_logs(7) {
+        warn!("清理旧日志失败: {}", e);
+    }
+}
+
+/// 清理指定天数之前的旧日志文件
+pub fn cleanup_old_logs(days_to_keep: u64) -> Result<(), String> {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    
+    let log_dir = get_log_dir()?;
+    if !log_dir.exists() {
+        return Ok(());
+    }
+    
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| format!("获取系统时间失败: {}", e))?
+        .as_secs();
+    
+    let cutoff_time = now.saturating_sub(days_to_keep * 24 * 60 * 60);
+    let mut deleted_count = 0;
+    let mut total_size_freed = 0u64;
+    
+    let entries = fs::read_dir(&log_dir)
+        .map_err(|e| format!("读取日志目录失败: {}", e))?;
+    
+    for entry in entries {
+        if let Ok(entry) = entry {
+            let path = entry.path();
+            
+            // 只处理文件，跳过目录
+            if !path.is_file() {
+                continue;
+            }
+            
+            // 获取文件修改时间
+            if let Ok(metadata) = fs::metadata(&path) {
+                if let Ok(modified) = metadata.modified() {
+                    let modified_secs = modified
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0);
+                    
+                    // 如果文件早于截止时间，删除它
+                    if modified_secs < cutoff_time {
+                        let file_size = metadata.len();
+                        if let Err(e) = fs::remove_file(&path) {
+                            warn!("删除旧日志文件失败 {:?}: {}", path, e);
+                        } else {
+                            deleted_count += 1;
+                            total_size_freed += file_size;
+                            info!("已删除旧日志文件: {:?}", path.file_name());
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    if deleted_count > 0 {
+        let size_mb = total_size_freed as f64 / 1024.0 / 1024.0;
+        info!(
+            "日志清理完成: 删除了 {} 个文件，释放 {:.2} MB 空间",
+            deleted_count, size_mb
+        );
+    }
+    
+    Ok(())
+}
+
+/// 清理日志缓存 (采用截断模式以保持文件句柄有效)
+/// 同时删除超出保留数量的归档文件，并清空内存日志缓存
+pub fn clear_logs() -> Result<(), String> {
+    log_cache().clear();
+    let log_dir = get_log_dir()?;
+    if log_dir.exists() {
+        let policy = crate::modules::config::load_app_config()
+            .map(|c| RotationPolicy::from_config(&c))
+            .unwrap_or_default();
+        prune_archives(&log_dir, policy.max_files)?;
+
+        // 遍历目录下的所有文件并截断，而不是删除目录
+        let entries = fs::read_dir(&log_dir).map_err(|e| format!("读取日志目录失败: {}", e))?;
+        for entry in entries {
+            if let Ok(entry) = entry {
+                let path = entry.path();
+                if path.is_file() {
+                    // 使用截断模式打开文件，将大小设为 0
+                    let _ = fs::OpenOptions::new()
+                        .write(true)
+                        .truncate(true)
+                        .open(path);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 记录信息日志 (向后兼容接口)
+pub fn log_info(message: &str) {
+    info!("{}", message);
+}
+
+/// 记录警告日志 (向后兼容接口)
+pub fn log_warn(message: &str) {
+    warn!("{}", message);
+}
+
+/// 记录错误日志 (向后兼容接口)
+pub fn log_error(message: &str) {
+    error!("{}", message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct CaptureWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for CaptureWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_resolve_log_format() {
+        assert_eq!(resolve_log_format(None, true), LogFormat::Plain);
+        assert_eq!(resolve_log_format(None, false), LogFormat::Json);
+        assert_eq!(resolve_log_format(Some(LogFormat::Plain), false), LogFormat::Plain);
+    }
+
+    #[test]
+    fn test_json_format_includes_span_context() {
+        let capture = CaptureWriter::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::registry().with(LogContextLayer).with(
+            fmt::Layer::new()
+                .with_ansi(false)
+                .with_writer(move || writer.clone())
+                .event_format(EventFormatter::new(LogFormat::Json, true)),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request", request_id = "req-1");
+            let _enter = span.enter();
+            log_info("plain message");
+            warn!(account_id = "acc-1", attempt = 2, "retrying");
+        });
+
+        let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["level"], "INFO");
+        assert_eq!(lines[0]["message"], "plain message");
+        assert_eq!(lines[0]["request_id"], "req-1");
+        assert!(lines[0].get("account_id").is_none());
+        assert!(lines[0]["module"].as_str().unwrap().ends_with("logger"));
+
+        assert_eq!(lines[1]["level"], "WARN");
+        assert_eq!(lines[1]["account_id"], "acc-1");
+        assert_eq!(lines[1]["request_id"], "req-1");
+        assert_eq!(lines[1]["fields"]["attempt"], "2");
+    }
+}
@@ -0,0 +1,208 @@
+// 日志文件按大小轮转
+// 当前日志写入 antigravity_tools.log，超过上限后依次归档为 .log.1 ~ .log.N (数字越大越旧)
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::models::AppConfig;
+
+pub const LOG_FILE_NAME: &str = "antigravity_tools.log";
+
+/// 日志轮转策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotationPolicy {
+    /// 单个日志文件大小上限 (字节)
+    pub max_bytes: u64,
+    /// 最多保留的归档数量
+    pub max_files: u8,
+}
+
+impl RotationPolicy {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            max_bytes: config.max_log_size_mb.max(1) * 1024 * 1024,
+            max_files: config.max_log_files,
+        }
+    }
+}
+
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        Self::from_config(&AppConfig::default())
+    }
+}
+
+fn log_path(dir: &Path) -> PathBuf {
+    dir.join(LOG_FILE_NAME)
+}
+
+fn archive_path(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("{}.{}", LOG_FILE_NAME, index))
+}
+
+/// 执行一次轮转: .log.(N-1) -> .log.N ... .log -> .log.1
+/// 新日志文件先以 `.log.new` 创建再重命名为 `.log`，避免出现写了一半的文件
+fn rotate_files(dir: &Path, max_files: u8) -> io::Result<()> {
+    let current = log_path(dir);
+    let staged = dir.join(format!("{}.new", LOG_FILE_NAME));
+    File::create(&staged)?;
+
+    let max_files = max_files as usize;
+    if max_files > 0 {
+        for i in (1..max_files).rev() {
+            let from = archive_path(dir, i);
+            if from.exists() {
+                fs::rename(&from, archive_path(dir, i + 1))?;
+            }
+        }
+        if current.exists() {
+            fs::rename(&current, archive_path(dir, 1))?;
+        }
+    }
+
+    fs::rename(&staged, &current)
+}
+
+/// 删除超出保留数量的归档文件，返回删除的文件数
+pub fn prune_archives(dir: &Path, max_files: u8) -> Result<usize, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("读取日志目录失败: {}", e))?;
+    let prefix = format!("{}.", LOG_FILE_NAME);
+    let mut removed = 0;
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(index) = name
+            .to_str()
+            .and_then(|n| n.strip_prefix(&prefix))
+            .and_then(|suffix| suffix.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        if index > max_files as usize {
+            fs::remove_file(entry.path()).map_err(|e| format!("删除归档日志失败: {}", e))?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// 按大小自动轮转的日志文件写入器 (配合 tracing_appender::non_blocking 使用)
+pub struct RotatingFileWriter {
+    dir: PathBuf,
+    policy: RotationPolicy,
+    // 轮转期间先关闭旧句柄 (Windows 下无法重命名已打开的文件)
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFileWriter {
+    pub fn new(dir: PathBuf, policy: RotationPolicy) -> io::Result<Self> {
+        let file = Self::open(&dir)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            dir,
+            policy,
+            file: Some(file),
+            size,
+        })
+    }
+
+    fn open(dir: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(log_path(dir))
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        let result = rotate_files(&self.dir, self.policy.max_files);
+        self.file = Some(Self::open(&self.dir)?);
+        self.size = 0;
+        result
+    }
+
+    fn needs_rotation(&mut self, incoming: usize) -> bool {
+        if self.size == 0 || self.size + incoming as u64 <= self.policy.max_bytes {
+            return false;
+        }
+        // 文件可能已被 clear_logs 截断，以实际大小为准
+        if let Some(actual) = self.file.as_ref().and_then(|f| f.metadata().ok()).map(|m| m.len()) {
+            self.size = actual;
+        }
+        self.size > 0 && self.size + incoming as u64 > self.policy.max_bytes
+    }
+}
+
+impl Write for RotatingFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.needs_rotation(buf.len()) {
+            if let Err(e) = self.rotate() {
+                eprintln!("日志轮转失败: {}", e);
+            }
+        }
+        let file = match self.file.as_mut() {
+            Some(f) => f,
+            None => self.file.insert(Self::open(&self.dir)?),
+        };
+        let written = file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file.as_mut() {
+            Some(f) => f.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("log_rotation_test_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_rotation_keeps_max_files() {
+        let dir = temp_dir();
+        let policy = RotationPolicy { max_bytes: 10, max_files: 2 };
+        let mut writer = RotatingFileWriter::new(dir.clone(), policy).unwrap();
+
+        for line in ["aaaaaaaa\n", "bbbbbbbb\n", "cccccccc\n", "dddddddd\n"] {
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+        writer.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(log_path(&dir)).unwrap(), "dddddddd\n");
+        assert_eq!(fs::read_to_string(archive_path(&dir, 1)).unwrap(), "cccccccc\n");
+        assert_eq!(fs::read_to_string(archive_path(&dir, 2)).unwrap(), "bbbbbbbb\n");
+        assert!(!archive_path(&dir, 3).exists());
+        assert!(!dir.join(format!("{}.new", LOG_FILE_NAME)).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_prune_archives_beyond_limit() {
+        let dir = temp_dir();
+        for i in 1..=4 {
+            fs::write(archive_path(&dir, i), "x").unwrap();
+        }
+        fs::write(log_path(&dir), "current").unwrap();
+        fs::write(dir.join("other.log.9"), "unrelated").unwrap();
+
+        assert_eq!(prune_archives(&dir, 2).unwrap(), 2);
+        assert!(archive_path(&dir, 2).exists());
+        assert!(!archive_path(&dir, 3).exists());
+        assert!(!archive_path(&dir, 4).exists());
+        assert!(log_path(&dir).exists());
+        assert!(dir.join("other.log.9").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
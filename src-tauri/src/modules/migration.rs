@@ -2,12 +2,81 @@ use crate::models::{Account, TokenData};
 use crate::modules::{account, db};
 use crate::utils::protobuf;
 use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
 use serde_json::Value;
 use std::fs;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// An account found in V1 data that would be (or was) imported
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AccountPreview {
+    pub email: String,
+    pub name: Option<String>,
+    pub source_file: String,
+    pub token_verified: bool, // false when the refresh token could not be refreshed
+}
+
+/// Result of a V1 import (or dry-run preview)
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ImportResult {
+    pub accounts: Vec<AccountPreview>,
+    pub already_exists: Vec<String>, // emails that are already in the account index
+    pub would_fail: Vec<ImportError>,
+    /// Saved accounts (always empty in dry-run mode)
+    #[serde(skip)]
+    pub imported: Vec<Account>,
+}
+
+/// Resolve a V1 backup file path, which may be stale or relative to the V1 directory
+fn resolve_v1_backup_path(v1_dir: &Path, target_file: &str) -> Option<PathBuf> {
+    let backup_path = PathBuf::from(target_file);
+    if backup_path.exists() {
+        return Some(backup_path);
+    }
+    let file_name = backup_path.file_name().unwrap_or_default();
+    [
+        v1_dir.join(file_name),
+        v1_dir.join("backups").join(file_name),
+        v1_dir.join("accounts").join(file_name),
+    ]
+    .into_iter()
+    .find(|p| p.exists())
+}
+
+/// Read the refresh token from a V1 backup file
+fn read_v1_refresh_token(backup_path: &Path) -> Result<String, String> {
+    let backup_content =
+        fs::read_to_string(backup_path).map_err(|e| format!("Failed to read data file: {}", e))?;
+    let backup_json: Value = serde_json::from_str(&backup_content)
+        .map_err(|e| format!("Failed to parse data file: {}", e))?;
+
+    // Compatible with two formats:
+    // 1. V2/Script data: JSON containing "token" field
+    if let Some(rt) = backup_json
+        .get("token")
+        .and_then(|t| t.get("refresh_token"))
+        .and_then(|v| v.as_str())
+    {
+        return Ok(rt.to_string());
+    }
+
+    // 2. V1 backup: jetskiStateSync.agentManagerInitState -> Protobuf
+    backup_json
+        .get("jetskiStateSync.agentManagerInitState")
+        .and_then(|v| v.as_str())
+        .and_then(|state_b64| general_purpose::STANDARD.decode(state_b64).ok())
+        .and_then(|blob| protobuf::find_field(&blob, 6).ok().flatten())
+        .and_then(|oauth_data| protobuf::find_field(&oauth_data, 3).ok().flatten())
+        .and_then(|refresh_bytes| String::from_utf8(refresh_bytes).ok())
+        .ok_or_else(|| "Refresh Token not found in data file".to_string())
+}
 
 /// Scan and import V1 data
-pub async fn import_from_v1() -> Result<Vec<Account>, String> {
+///
+/// With `dry_run` the V1 data is scanned and each refresh token is verified,
+/// but nothing is written: the account index is only read to report duplicates.
+pub async fn import_from_v1(dry_run: bool) -> Result<ImportResult, String> {
     use crate::modules::oauth;
 
     let home = dirs::home_dir().ok_or("Failed to get home directory")?;
@@ -15,7 +84,13 @@ pub async fn import_from_v1() -> Result<Vec<Account>, String> {
     // V1 data directory (consistent across platforms based on utils.py)
     let v1_dir = home.join(".antigravity-agent");
 
-    let mut imported_accounts = Vec::new();
+    let existing_emails: HashSet<String> = account::load_account_index()?
+        .accounts
+        .into_iter()
+        .map(|a| a.email)
+        .collect();
+    let mut seen_emails = HashSet::new();
+    let mut result = ImportResult::default();
 
     // Try multiple possible filenames
     let index_files = vec![
@@ -62,191 +137,273 @@ pub async fn import_from_v1() -> Result<Vec<Account>, String> {
             continue;
         };
 
-        for (id, acc_info) in accounts_map {
+        // Skip non-account keys (e.g., "current_account_id")
+        let entries = accounts_map.iter().filter(|(_, info)| info.is_object());
+        for (position, (id, acc_info)) in entries.enumerate() {
             let email_placeholder = acc_info
                 .get("email")
                 .and_then(|v| v.as_str())
                 .unwrap_or("Unknown")
                 .to_string();
+            let fail = |message: String| {
+                crate::modules::logger::log_warn(&format!(
+                    "Account {} ({}): {}",
+                    id, email_placeholder, message
+                ));
+                ImportError {
+                    line: position + 1,
+                    email: Some(email_placeholder.clone()),
+                    message,
+                }
+            };
 
-            // Skip non-account keys (e.g., "current_account_id")
-            if !acc_info.is_object() {
+            // Prefer backup_file, then data_file
+            let Some(target_file) = acc_info
+                .get("backup_file")
+                .or_else(|| acc_info.get("data_file"))
+                .and_then(|v| v.as_str())
+            else {
+                result.would_fail.push(fail("Missing data file path".to_string()));
                 continue;
-            }
+            };
 
-            let backup_file_str = acc_info.get("backup_file").and_then(|v| v.as_str());
-            let data_file_str = acc_info.get("data_file").and_then(|v| v.as_str());
+            let Some(backup_path) = resolve_v1_backup_path(&v1_dir, target_file) else {
+                result
+                    .would_fail
+                    .push(fail(format!("Backup file not found: {}", target_file)));
+                continue;
+            };
 
-            // Prefer backup_file, then data_file
-            let target_file = backup_file_str.or(data_file_str);
+            let refresh_token = match read_v1_refresh_token(&backup_path) {
+                Ok(rt) => rt,
+                Err(e) => {
+                    result.would_fail.push(fail(e));
+                    continue;
+                }
+            };
+
+            crate::modules::logger::log_info(&format!(
+                "{} account: {}",
+                if dry_run { "Checking" } else { "Importing" },
+                email_placeholder
+            ));
+
+            let (email, name, access_token, expires_in, token_verified) =
+                match oauth::refresh_access_token(&refresh_token).await {
+                    Ok(token_resp) => match oauth::get_user_info(&token_resp.access_token).await {
+                        Ok(user_info) => {
+                            let name = user_info.get_display_name();
+                            (user_info.email, name, token_resp.access_token, token_resp.expires_in, true)
+                        }
+                        Err(_) => (
+                            email_placeholder.clone(),
+                            None,
+                            token_resp.access_token,
+                            token_resp.expires_in,
+                            true,
+                        ),
+                    },
+                    Err(e) => {
+                        // A dry run reports this; a real import keeps the account so it can be re-authorized later
+                        let error = fail(format!("Token refresh failed (possibly expired): {}", e));
+                        if dry_run {
+                            result.would_fail.push(error);
+                            continue;
+                        }
+                        (
+                            email_placeholder.clone(),
+                            None,
+                            "imported_access_token".to_string(),
+                            0,
+                            false,
+                        )
+                    }
+                };
 
-            if target_file.is_none() {
-                crate::modules::logger::log_warn(&format!(
-                    "Account {} ({}) missing data file path",
-                    id, email_placeholder
-                ));
+            // The same account may appear in both index files
+            if !seen_emails.insert(email.clone()) {
                 continue;
             }
+            if let Err(e) = account::check_email_domain_allowed(&email) {
+                result.would_fail.push(fail(e));
+                continue;
+            }
+            if existing_emails.contains(&email) {
+                result.already_exists.push(email.clone());
+            }
 
-            let mut backup_path = PathBuf::from(target_file.unwrap());
+            let preview = AccountPreview {
+                email: email.clone(),
+                name: name.clone(),
+                source_file: backup_path.to_string_lossy().to_string(),
+                token_verified,
+            };
 
-            // If relative path, try to join
-            if !backup_path.exists() {
-                backup_path = v1_dir.join(backup_path.file_name().unwrap_or_default());
+            if dry_run {
+                result.accounts.push(preview);
+                continue;
             }
 
-            // Try joining data/ or backups/ subdirectories again
-            if !backup_path.exists() {
-                let file_name = backup_path.file_name().unwrap_or_default();
-                let try_backups = v1_dir.join("backups").join(file_name);
-                if try_backups.exists() {
-                    backup_path = try_backups;
-                } else {
-                    let try_accounts = v1_dir.join("accounts").join(file_name);
-                    if try_accounts.exists() {
-                        backup_path = try_accounts;
-                    }
+            let token_data = TokenData::new(
+                access_token,
+                refresh_token,
+                expires_in,
+                Some(email.clone()),
+                None, // project_id will be fetched when needed
+                None, // session_id
+            );
+
+            match account::upsert_account(email.clone(), name, token_data, None) {
+                Ok(acc) => {
+                    crate::modules::logger::log_info(&format!("Import successful: {}", email));
+                    result.accounts.push(preview);
+                    result.imported.push(acc);
                 }
+                Err(e) => result
+                    .would_fail
+                    .push(fail(format!("Import save failed {}: {}", email, e))),
             }
+        }
+    }
 
-            if !backup_path.exists() {
-                crate::modules::logger::log_warn(&format!(
-                    "Account {} ({}) backup file not found: {:?}",
-                    id, email_placeholder, backup_path
-                ));
-                continue;
+    if !found_index {
+        return Err("V1 account data file not found".to_string());
+    }
+
+    Ok(result)
+}
+
+/// A line of a token list that could not be imported
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ImportError {
+    pub line: usize, // 1-based line number (entry position for V1 imports)
+    pub email: Option<String>,
+    pub message: String,
+}
+
+/// Result of a token list import: successful accounts plus per-line failures
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenListImport {
+    pub accounts: Vec<Account>,
+    pub errors: Vec<ImportError>,
+}
+
+/// Parse `email:refresh_token` lines, skipping blank lines and `#` comments
+fn parse_token_list(content: &str) -> Vec<Result<(usize, String, String), ImportError>> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(idx, raw)| (idx + 1, raw.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_no, line)| {
+            let parsed = line
+                .split_once(':')
+                .map(|(email, token)| (email.trim(), token.trim()))
+                .filter(|(email, token)| !email.is_empty() && !token.is_empty());
+            match parsed {
+                Some((email, token)) => Ok((line_no, email.to_string(), token.to_string())),
+                None => Err(ImportError {
+                    line: line_no,
+                    email: None,
+                    message: "Expected format: email:refresh_token".to_string(),
+                }),
             }
+        })
+        .collect()
+}
 
-            // Read backup file
-            if let Ok(backup_content) = fs::read_to_string(&backup_path) {
-                if let Ok(backup_json) = serde_json::from_str::<Value>(&backup_content) {
-                    // Compatible with two formats:
-                    // 1. V1 backup: jetskiStateSync.agentManagerInitState -> Protobuf
-                    // 2. V2/Script data: JSON containing "token" field
+/// Import accounts from a plain-text file of `email:refresh_token` pairs
+pub async fn import_from_token_list(path: &str) -> Result<TokenListImport, String> {
+    use crate::modules::oauth;
 
-                    let mut refresh_token_opt = None;
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read token list {}: {}", path, e))?;
 
-                    // Try format 2
-                    if let Some(token_data) = backup_json.get("token") {
-                        if let Some(rt) = token_data.get("refresh_token").and_then(|v| v.as_str()) {
-                            refresh_token_opt = Some(rt.to_string());
-                        }
-                    }
+    let mut accounts = Vec::new();
+    let mut errors = Vec::new();
 
-                    // Try format 1
-                    if refresh_token_opt.is_none() {
-                        if let Some(state_b64) = backup_json
-                            .get("jetskiStateSync.agentManagerInitState")
-                            .and_then(|v| v.as_str())
-                        {
-                            // Parse Protobuf
-                            if let Ok(blob) = general_purpose::STANDARD.decode(state_b64) {
-                                if let Ok(Some(oauth_data)) = protobuf::find_field(&blob, 6) {
-                                    if let Ok(Some(refresh_bytes)) =
-                                        protobuf::find_field(&oauth_data, 3)
-                                    {
-                                        if let Ok(rt) = String::from_utf8(refresh_bytes) {
-                                            refresh_token_opt = Some(rt);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+    for entry in parse_token_list(&content) {
+        let (line, listed_email, refresh_token) = match entry {
+            Ok(v) => v,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        let fail = |message: String| ImportError {
+            line,
+            email: Some(listed_email.clone()),
+            message,
+        };
 
-                    if let Some(refresh_token) = refresh_token_opt {
-                        crate::modules::logger::log_info(&format!(
-                            "Importing account: {}",
-                            email_placeholder
-                        ));
-
-                        let (email, access_token, expires_in) =
-                            match oauth::refresh_access_token(&refresh_token).await {
-                                Ok(token_resp) => {
-                                    match oauth::get_user_info(&token_resp.access_token).await {
-                                        Ok(user_info) => (
-                                            user_info.email,
-                                            token_resp.access_token,
-                                            token_resp.expires_in,
-                                        ),
-                                        Err(_) => (
-                                            email_placeholder.clone(),
-                                            token_resp.access_token,
-                                            token_resp.expires_in,
-                                        ),
-                                    }
-                                }
-                                Err(e) => {
-                                    crate::modules::logger::log_warn(&format!(
-                                        "Token refresh failed (possibly expired): {}",
-                                        e
-                                    ));
-                                    (
-                                        email_placeholder.clone(),
-                                        "imported_access_token".to_string(),
-                                        0,
-                                    )
-                                }
-                            };
-
-                        let token_data = TokenData::new(
-                            access_token,
-                            refresh_token,
-                            expires_in,
-                            Some(email.clone()),
-                            None, // project_id will be fetched when needed
-                            None, // session_id
-                        );
-
-                        // name was already fetched in get_user_info at line 153, but here it is outside the match statement, we use None for safety
-                        match account::upsert_account(email.clone(), None, token_data) {
-                            Ok(acc) => {
-                                crate::modules::logger::log_info(&format!(
-                                    "Import successful: {}",
-                                    email
-                                ));
-                                imported_accounts.push(acc);
-                            }
-                            Err(e) => crate::modules::logger::log_error(&format!(
-                                "Import save failed {}: {}",
-                                email, e
-                            )),
-                        }
-                    } else {
-                        crate::modules::logger::log_warn(&format!(
-                            "Refresh Token not found in account {} data file",
-                            email_placeholder
-                        ));
-                    }
-                }
+        let token_resp = match oauth::refresh_access_token(&refresh_token).await {
+            Ok(t) => t,
+            Err(e) => {
+                crate::modules::logger::log_warn(&format!(
+                    "Token list line {}: refresh failed for {}: {}",
+                    line, listed_email, e
+                ));
+                errors.push(fail(format!("Token refresh failed: {}", e)));
+                continue;
             }
+        };
+
+        // Prefer the email reported by Google; fall back to the one in the file
+        let (email, name) = match oauth::get_user_info(&token_resp.access_token).await {
+            Ok(user_info) => {
+                let name = user_info.get_display_name();
+                (user_info.email, name)
+            }
+            Err(_) => (listed_email.clone(), None),
+        };
+        if let Err(e) = account::check_email_domain_allowed(&email) {
+            errors.push(fail(e));
+            continue;
         }
-    }
 
-    if !found_index {
-        return Err("V1 account data file not found".to_string());
+        let token_data = TokenData::new(
+            token_resp.access_token,
+            refresh_token,
+            token_resp.expires_in,
+            Some(email.clone()),
+            None, // project_id will be fetched when needed
+            None, // session_id
+        );
+
+        match account::upsert_account(email.clone(), name, token_data, None) {
+            Ok(acc) => {
+                crate::modules::logger::log_info(&format!("Import successful: {}", email));
+                accounts.push(acc);
+            }
+            Err(e) => errors.push(fail(format!("Failed to save account: {}", e))),
+        }
     }
 
-    Ok(imported_accounts)
+    Ok(TokenListImport { accounts, errors })
 }
 
 /// Import account from custom database path
 pub async fn import_from_custom_db_path(path_str: String) -> Result<Account, String> {
-    use crate::modules::oauth;
-
     let path = PathBuf::from(path_str);
     if !path.exists() {
         return Err(format!("File does not exist: {:?}", path));
     }
 
     let refresh_token = extract_refresh_token_from_file(&path)?;
+    import_with_refresh_token(refresh_token).await
+}
+
+/// Refresh the token, fetch user info and add (or update) the account
+async fn import_with_refresh_token(refresh_token: String) -> Result<Account, String> {
+    use crate::modules::oauth;
 
-    // 3. Use Refresh Token to get latest Access Token and user info
+    // Use Refresh Token to get latest Access Token and user info
     crate::modules::logger::log_info("Using Refresh Token to get user info...");
     let token_resp = oauth::refresh_access_token(&refresh_token).await?;
     let user_info = oauth::get_user_info(&token_resp.access_token).await?;
 
     let email = user_info.email;
+    account::check_email_domain_allowed(&email)?;
 
     crate::modules::logger::log_info(&format!("Successfully retrieved account info: {}", email));
 
@@ -259,8 +416,61 @@ pub async fn import_from_custom_db_path(path_str: String) -> Result<Account, Str
         None, // session_id will be generated in token_manager
     );
 
-    // 4. Add or update account
-    account::upsert_account(email.clone(), user_info.name, token_data)
+    // Add or update account
+    account::upsert_account(email.clone(), user_info.name, token_data, None)
+}
+
+/// Cursor auth state file (globalStorage/storage.json, cross-platform)
+fn get_cursor_storage_path() -> Result<PathBuf, String> {
+    #[cfg(target_os = "macos")]
+    {
+        let home = dirs::home_dir().ok_or("Failed to get home directory")?;
+        Ok(home.join("Library/Application Support/Cursor/User/globalStorage/storage.json"))
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let appdata =
+            std::env::var("APPDATA").map_err(|_| "Failed to get APPDATA environment variable".to_string())?;
+        Ok(PathBuf::from(appdata).join("Cursor\\User\\globalStorage\\storage.json"))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let home = dirs::home_dir().ok_or("Failed to get home directory")?;
+        Ok(home.join(".config/Cursor/User/globalStorage/storage.json"))
+    }
+}
+
+/// Extract the Cursor token, preferring the refresh token over the access token
+fn extract_cursor_token(storage: &Value) -> Result<String, String> {
+    ["cursorAuth/refreshToken", "cursorAuth/accessToken"]
+        .iter()
+        .find_map(|key| {
+            storage
+                .get(*key)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+        })
+        .map(str::to_string)
+        .ok_or_else(|| "Cursor auth token not found (cursorAuth/refreshToken)".to_string())
+}
+
+/// Import the account currently logged in to Cursor IDE
+pub async fn import_from_cursor() -> Result<Account, String> {
+    let path = get_cursor_storage_path()?;
+    if !path.exists() {
+        return Err(format!("Cursor storage file not found: {:?}", path));
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read Cursor storage: {}", e))?;
+    let storage: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse Cursor storage: {}", e))?;
+
+    let token = extract_cursor_token(&storage)?;
+    import_with_refresh_token(token).await
 }
 
 /// Import current logged-in account from default IDE database
@@ -313,3 +523,69 @@ pub fn get_refresh_token_from_db() -> Result<String, String> {
     let db_path = db::get_db_path()?;
     extract_refresh_token_from_file(&db_path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_cursor_token() {
+        let both = serde_json::json!({
+            "cursorAuth/accessToken": "access",
+            "cursorAuth/refreshToken": "refresh",
+            "telemetry.machineId": "abc"
+        });
+        assert_eq!(extract_cursor_token(&both).unwrap(), "refresh");
+
+        let access_only = serde_json::json!({"cursorAuth/refreshToken": " ", "cursorAuth/accessToken": "access"});
+        assert_eq!(extract_cursor_token(&access_only).unwrap(), "access");
+
+        assert!(extract_cursor_token(&serde_json::json!({})).is_err());
+    }
+
+    #[test]
+    fn test_parse_token_list() {
+        let content = "# exported tokens\n\
+            a@example.com:1//token-a\n\
+            \n\
+            \x20 b@example.com : 1//token-b \n\
+            missing-separator\n\
+            c@example.com:\n";
+        let parsed = parse_token_list(content);
+
+        assert_eq!(parsed.len(), 4);
+        assert_eq!(
+            parsed[0],
+            Ok((2, "a@example.com".to_string(), "1//token-a".to_string()))
+        );
+        assert_eq!(
+            parsed[1],
+            Ok((4, "b@example.com".to_string(), "1//token-b".to_string()))
+        );
+        assert_eq!(parsed[2].as_ref().unwrap_err().line, 5);
+        assert_eq!(parsed[3].as_ref().unwrap_err().line, 6);
+    }
+
+    #[test]
+    fn test_resolve_and_read_v1_backup() {
+        let dir = std::env::temp_dir().join(format!("v1_import_test_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("backups")).unwrap();
+        fs::write(
+            dir.join("backups").join("a.json"),
+            r#"{"token":{"refresh_token":"1//token-a"}}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("b.json"), r#"{"other":true}"#).unwrap();
+
+        // Stale absolute paths fall back to the V1 directory by file name
+        let a = resolve_v1_backup_path(&dir, "/old/machine/a.json").unwrap();
+        assert_eq!(a, dir.join("backups").join("a.json"));
+        assert_eq!(read_v1_refresh_token(&a).unwrap(), "1//token-a");
+
+        let b = resolve_v1_backup_path(&dir, "b.json").unwrap();
+        assert!(read_v1_refresh_token(&b).is_err());
+        assert!(resolve_v1_backup_path(&dir, "missing.json").is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -13,6 +13,10 @@ pub mod proxy_db;
 pub mod device;
 pub mod update_checker;
 pub mod scheduler;
+pub mod db_sync;
+pub mod version;
+pub mod process_watch;
+pub mod diagnostics;
 
 use crate::models;
 
@@ -7,6 +7,11 @@ const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
 const USERINFO_URL: &str = "https://www.googleapis.com/oauth2/v2/userinfo";
 
 const AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const DEVICE_CODE_URL: &str = "https://oauth2.googleapis.com/device/code";
+const DEVICE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Extra delay added to the polling interval on `slow_down` (RFC 8628 §3.5)
+const SLOW_DOWN_INCREMENT_SECS: u64 = 5;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenResponse {
@@ -18,6 +23,29 @@ pub struct TokenResponse {
     pub refresh_token: Option<String>,
 }
 
+/// Device authorization response (RFC 8628)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCodeResponse {
+    pub device_code: String,
+    pub user_code: String,
+    #[serde(alias = "verification_uri")]
+    pub verification_url: String,
+    pub expires_in: u64,
+    #[serde(default = "default_device_poll_interval")]
+    pub interval: u64,
+}
+
+fn default_device_poll_interval() -> u64 {
+    5
+}
+
+#[derive(Debug, Deserialize)]
+struct OAuthErrorResponse {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserInfo {
     pub email: String,
@@ -47,8 +75,29 @@ impl UserInfo {
     }
 }
 
+/// PKCE code_verifier length (RFC 7636 allows 43-128)
+const CODE_VERIFIER_LEN: usize = 64;
+
+/// Generate a random PKCE code_verifier from the unreserved character set
+pub fn generate_code_verifier() -> String {
+    use rand::Rng;
+    const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~";
+    let mut rng = rand::thread_rng();
+    (0..CODE_VERIFIER_LEN)
+        .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+        .collect()
+}
+
+/// Compute PKCE code_challenge = BASE64URL(SHA256(code_verifier)) without padding
+pub fn code_challenge_s256(code_verifier: &str) -> String {
+    use base64::Engine as _;
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(code_verifier.as_bytes());
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(digest)
+}
+
 /// Generate OAuth Authorization URL
-pub fn get_auth_url(redirect_uri: &str) -> String {
+pub fn get_auth_url(redirect_uri: &str, code_challenge: &str) -> String {
     let scopes = vec![
         "https://www.googleapis.com/auth/cloud-platform",
         "https://www.googleapis.com/auth/userinfo.email",
@@ -66,6 +115,8 @@ pub fn get_auth_url(redirect_uri: &str) -> String {
         ("access_type", "offline"),
         ("prompt", "consent"),
         ("include_granted_scopes", "true"),
+        ("code_challenge", code_challenge),
+        ("code_challenge_method", "S256"),
     ];
 
     let url = url::Url::parse_with_params(AUTH_URL, &params).expect("Invalid Auth URL");
@@ -73,7 +124,7 @@ pub fn get_auth_url(redirect_uri: &str) -> String {
 }
 
 /// Exchange Authorization Code for Token
-pub async fn exchange_code(code: &str, redirect_uri: &str) -> Result<TokenResponse, String> {
+pub async fn exchange_code(code: &str, redirect_uri: &str, code_verifier: &str) -> Result<TokenResponse, String> {
     let client = crate::utils::http::create_client(15);
 
     let params = [
@@ -82,6 +133,7 @@ pub async fn exchange_code(code: &str, redirect_uri: &str) -> Result<TokenRespon
         ("code", code),
         ("redirect_uri", redirect_uri),
         ("grant_type", "authorization_code"),
+        ("code_verifier", code_verifier),
     ];
 
     let response = client
@@ -125,6 +177,92 @@ pub async fn exchange_code(code: &str, redirect_uri: &str) -> Result<TokenRespon
     }
 }
 
+/// Request a device code for the device authorization flow
+pub async fn request_device_code() -> Result<DeviceCodeResponse, String> {
+    let client = crate::utils::http::create_client(15);
+    let scopes = [
+        "https://www.googleapis.com/auth/cloud-platform",
+        "https://www.googleapis.com/auth/userinfo.email",
+        "https://www.googleapis.com/auth/userinfo.profile",
+    ]
+    .join(" ");
+    let params = [("client_id", CLIENT_ID), ("scope", scopes.as_str())];
+
+    let response = client
+        .post(DEVICE_CODE_URL)
+        .form(&params)
+        .send()
+        .await
+        .map_err(|e| format!("Device code request failed: {}", e))?;
+
+    if response.status().is_success() {
+        response
+            .json::<DeviceCodeResponse>()
+            .await
+            .map_err(|e| format!("Device code parsing failed: {}", e))
+    } else {
+        let error_text = response.text().await.unwrap_or_default();
+        Err(format!("Device code request failed: {}", error_text))
+    }
+}
+
+/// Outcome of a single device token poll
+pub enum DevicePollResult {
+    Complete(TokenResponse),
+    Pending,
+    SlowDown,
+}
+
+/// Poll the token endpoint once for a device code
+pub async fn poll_device_token(device_code: &str) -> Result<DevicePollResult, String> {
+    let client = crate::utils::http::create_client(15);
+    let params = [
+        ("client_id", CLIENT_ID),
+        ("client_secret", CLIENT_SECRET),
+        ("device_code", device_code),
+        ("grant_type", DEVICE_GRANT_TYPE),
+    ];
+
+    let response = client
+        .post(TOKEN_URL)
+        .form(&params)
+        .send()
+        .await
+        .map_err(|e| format!("Device token request failed: {}", e))?;
+
+    if response.status().is_success() {
+        return response
+            .json::<TokenResponse>()
+            .await
+            .map(DevicePollResult::Complete)
+            .map_err(|e| format!("Token parsing failed: {}", e));
+    }
+
+    let error_text = response.text().await.unwrap_or_default();
+    match serde_json::from_str::<OAuthErrorResponse>(&error_text) {
+        Ok(err) => match err.error.as_str() {
+            "authorization_pending" => Ok(DevicePollResult::Pending),
+            "slow_down" => Ok(DevicePollResult::SlowDown),
+            "access_denied" => Err("Authorization was denied by the user".to_string()),
+            "expired_token" => Err("Device code expired, please restart login".to_string()),
+            _ => Err(format!(
+                "Device token exchange failed: {} {}",
+                err.error,
+                err.error_description.unwrap_or_default()
+            )),
+        },
+        Err(_) => Err(format!("Device token exchange failed: {}", error_text)),
+    }
+}
+
+/// Next polling interval after a poll result
+pub fn next_device_poll_interval(current_secs: u64, result: &DevicePollResult) -> u64 {
+    match result {
+        DevicePollResult::SlowDown => current_secs + SLOW_DOWN_INCREMENT_SECS,
+        _ => current_secs,
+    }
+}
+
 /// Refresh access_token using refresh_token
 pub async fn refresh_access_token(refresh_token: &str) -> Result<TokenResponse, String> {
     let client = crate::utils::http::create_client(15);
@@ -189,15 +327,16 @@ pub async fn get_user_info(access_token: &str) -> Result<UserInfo, String> {
 pub async fn ensure_fresh_token(
     current_token: &crate::models::TokenData,
 ) -> Result<crate::models::TokenData, String> {
-    let now = chrono::Local::now().timestamp();
-
-    // If no expiry time, or still has more than 5 minutes validity, return directly
-    if current_token.expiry_timestamp > now + 300 {
+    // Still has more than 5 minutes validity, return directly
+    if !current_token.is_expired() {
         return Ok(current_token.clone());
     }
 
     // Need refresh
-    crate::modules::logger::log_info("Token is about to expire, refreshing...");
+    crate::modules::logger::log_info(&format!(
+        "Token is about to expire ({}s left), refreshing...",
+        current_token.seconds_until_expiry()
+    ));
     let response = refresh_access_token(&current_token.refresh_token).await?;
 
     // Construct new TokenData
@@ -210,3 +349,33 @@ pub async fn ensure_fresh_token(
         None,                             // session_id will be generated in token_manager
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_challenge_rfc7636_vector() {
+        // RFC 7636 Appendix B
+        assert_eq!(
+            code_challenge_s256("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn test_slow_down_increases_interval() {
+        assert_eq!(next_device_poll_interval(5, &DevicePollResult::Pending), 5);
+        assert_eq!(next_device_poll_interval(5, &DevicePollResult::SlowDown), 10);
+    }
+
+    #[test]
+    fn test_generate_code_verifier() {
+        let verifier = generate_code_verifier();
+        assert!((43..=128).contains(&verifier.len()));
+        assert!(verifier
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-._~".contains(c)));
+        assert_ne!(verifier, generate_code_verifier());
+    }
+}
@@ -9,12 +9,20 @@ use tokio::sync::watch;
 struct OAuthFlowState {
     auth_url: String,
     redirect_uri: String,
+    code_verifier: String, // PKCE verifier, sent with the code exchange
     cancel_tx: watch::Sender<bool>,
     code_rx: Option<oneshot::Receiver<Result<String, String>>>,
 }
 
 static OAUTH_FLOW_STATE: OnceLock<Mutex<Option<OAuthFlowState>>> = OnceLock::new();
 
+/// Cancel signal for the in-progress device flow (if any)
+static DEVICE_FLOW_CANCEL: OnceLock<Mutex<Option<watch::Sender<bool>>>> = OnceLock::new();
+
+fn get_device_flow_cancel() -> &'static Mutex<Option<watch::Sender<bool>>> {
+    DEVICE_FLOW_CANCEL.get_or_init(|| Mutex::new(None))
+}
+
 fn get_oauth_flow_state() -> &'static Mutex<Option<OAuthFlowState>> {
     OAUTH_FLOW_STATE.get_or_init(|| Mutex::new(None))
 }
@@ -111,7 +119,10 @@ async fn ensure_oauth_flow_prepared(app_handle: &tauri::AppHandle) -> Result<Str
         format!("http://[::1]:{}/oauth-callback", port)
     };
 
-    let auth_url = oauth::get_auth_url(&redirect_uri);
+    // PKCE: keep the verifier locally, only the challenge goes into the auth URL
+    let code_verifier = oauth::generate_code_verifier();
+    let code_challenge = oauth::code_challenge_s256(&code_verifier);
+    let auth_url = oauth::get_auth_url(&redirect_uri, &code_challenge);
 
     // Cancel signal (supports multiple consumers)
     let (cancel_tx, cancel_rx) = watch::channel(false);
@@ -212,6 +223,7 @@ async fn ensure_oauth_flow_prepared(app_handle: &tauri::AppHandle) -> Result<Str
         *state = Some(OAuthFlowState {
             auth_url: auth_url.clone(),
             redirect_uri,
+            code_verifier,
             cancel_tx,
             code_rx: Some(code_rx),
         });
@@ -236,6 +248,79 @@ pub fn cancel_oauth_flow() {
             crate::modules::logger::log_info("OAuth cancel signal sent");
         }
     }
+    if let Ok(mut cancel) = get_device_flow_cancel().lock() {
+        if let Some(tx) = cancel.take() {
+            let _ = tx.send(true);
+            crate::modules::logger::log_info("Device flow cancel signal sent");
+        }
+    }
+}
+
+/// Device authorization flow for environments where no browser can be opened.
+/// Emits `device-flow-code` with `{user_code, verification_url, expires_in}`,
+/// then polls the token endpoint every `interval` seconds (longer after `slow_down`)
+/// until the user approves, denies, or the code expires.
+/// Emits `device-flow-result` with `{success, error}` when the flow ends.
+pub async fn start_device_flow(
+    app_handle: tauri::AppHandle,
+) -> Result<oauth::TokenResponse, String> {
+    use tauri::Emitter;
+
+    let device = oauth::request_device_code().await?;
+    crate::modules::logger::log_info(&format!(
+        "Device flow started, user_code: {}, verification_url: {}",
+        device.user_code, device.verification_url
+    ));
+
+    let _ = app_handle.emit(
+        "device-flow-code",
+        serde_json::json!({
+            "user_code": device.user_code,
+            "verification_url": device.verification_url,
+            "expires_in": device.expires_in,
+        }),
+    );
+
+    let (cancel_tx, mut cancel_rx) = watch::channel(false);
+    if let Ok(mut cancel) = get_device_flow_cancel().lock() {
+        *cancel = Some(cancel_tx);
+    }
+
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(device.expires_in);
+    let mut interval = device.interval.max(1);
+
+    let result = loop {
+        // Never sleep past the code's expiry
+        let wake_at = (tokio::time::Instant::now() + std::time::Duration::from_secs(interval)).min(deadline);
+        tokio::select! {
+            _ = tokio::time::sleep_until(wake_at) => {}
+            _ = cancel_rx.changed() => break Err("OAuth authorization cancelled".to_string()),
+        }
+
+        if tokio::time::Instant::now() >= deadline {
+            break Err("Device code expired, please restart login".to_string());
+        }
+
+        match oauth::poll_device_token(&device.device_code).await {
+            Ok(oauth::DevicePollResult::Complete(token)) => break Ok(token),
+            Ok(pending) => {
+                interval = oauth::next_device_poll_interval(interval, &pending);
+            }
+            Err(e) => break Err(e),
+        }
+    };
+
+    if let Ok(mut cancel) = get_device_flow_cancel().lock() {
+        *cancel = None;
+    }
+    let _ = app_handle.emit(
+        "device-flow-result",
+        serde_json::json!({
+            "success": result.is_ok(),
+            "error": result.as_ref().err(),
+        }),
+    );
+    result
 }
 
 /// Start OAuth flow and wait for callback, then exchange token
@@ -253,7 +338,7 @@ pub async fn start_oauth_flow(
         .map_err(|e| format!("Failed to open browser: {}", e))?;
 
     // Take code_rx for waiting
-    let (code_rx, redirect_uri) = {
+    let (code_rx, redirect_uri, code_verifier) = {
         let mut lock = get_oauth_flow_state()
             .lock()
             .map_err(|_| "OAuth state lock poisoned".to_string())?;
@@ -264,7 +349,7 @@ pub async fn start_oauth_flow(
             .code_rx
             .take()
             .ok_or_else(|| "OAuth authorization already in progress".to_string())?;
-        (rx, state.redirect_uri.clone())
+        (rx, state.redirect_uri.clone(), state.code_verifier.clone())
     };
 
     // Wait for code (if user already authorized, this returns immediately)
@@ -279,7 +364,7 @@ pub async fn start_oauth_flow(
         *lock = None;
     }
 
-    oauth::exchange_code(&code, &redirect_uri).await
+    oauth::exchange_code(&code, &redirect_uri, &code_verifier).await
 }
 
 /// Complete OAuth flow without opening browser.
@@ -292,7 +377,7 @@ pub async fn complete_oauth_flow(
     let _ = ensure_oauth_flow_prepared(&app_handle).await?;
 
     // Take receiver to wait for code
-    let (code_rx, redirect_uri) = {
+    let (code_rx, redirect_uri, code_verifier) = {
         let mut lock = get_oauth_flow_state()
             .lock()
             .map_err(|_| "OAuth state lock poisoned".to_string())?;
@@ -303,7 +388,7 @@ pub async fn complete_oauth_flow(
             .code_rx
             .take()
             .ok_or_else(|| "OAuth authorization already in progress".to_string())?;
-        (rx, state.redirect_uri.clone())
+        (rx, state.redirect_uri.clone(), state.code_verifier.clone())
     };
 
     let code = match code_rx.await {
@@ -316,5 +401,5 @@ pub async fn complete_oauth_flow(
         *lock = None;
     }
 
-    oauth::exchange_code(&code, &redirect_uri).await
+    oauth::exchange_code(&code, &redirect_uri, &code_verifier).await
 }
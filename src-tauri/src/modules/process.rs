@@ -858,6 +858,63 @@ pub fn start_antigravity() -> Result<(), String> {
     Ok(())
 }
 
+/// Antigravity 主进程资源占用
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub cpu_usage_percent: f32,
+    pub memory_mb: f64,
+    /// 进程启动时间 (Unix 秒)
+    pub start_time: i64,
+    pub status: String,
+}
+
+/// 从候选 `(pid, parent_pid, start_time)` 中选出主进程:
+/// 父进程不在候选集合内的进程优先，其次启动最早
+fn select_main_pid(candidates: &[(u32, Option<u32>, u64)]) -> Option<u32> {
+    candidates
+        .iter()
+        .min_by_key(|(_, parent, start)| {
+            let child_of_candidate =
+                parent.is_some_and(|p| candidates.iter().any(|(pid, _, _)| *pid == p));
+            (child_of_candidate, *start)
+        })
+        .map(|(pid, _, _)| *pid)
+}
+
+/// 获取运行中的 Antigravity 主进程的 CPU / 内存占用 (未运行时返回 None)
+///
+/// CPU 占用需要两次采样，函数会阻塞约 200ms
+pub fn get_antigravity_process_info() -> Option<ProcessInfo> {
+    let pids = get_antigravity_pids();
+    if pids.is_empty() {
+        return None;
+    }
+
+    let sys_pids: Vec<sysinfo::Pid> = pids.iter().map(|p| sysinfo::Pid::from_u32(*p)).collect();
+    let mut system = System::new();
+    system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&sys_pids));
+    let candidates: Vec<(u32, Option<u32>, u64)> = sys_pids
+        .iter()
+        .filter_map(|pid| system.process(*pid))
+        .map(|p| (p.pid().as_u32(), p.parent().map(|pp| pp.as_u32()), p.start_time()))
+        .collect();
+    let main_pid = sysinfo::Pid::from_u32(select_main_pid(&candidates)?);
+
+    // 第二次采样后 cpu_usage 才有意义
+    thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+    system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[main_pid]));
+
+    let process = system.process(main_pid)?;
+    Some(ProcessInfo {
+        pid: main_pid.as_u32(),
+        cpu_usage_percent: process.cpu_usage(),
+        memory_mb: process.memory() as f64 / (1024.0 * 1024.0),
+        start_time: process.start_time() as i64,
+        status: process.status().to_string(),
+    })
+}
+
 /// 从运行中的进程获取 Antigravity 可执行文件路径和启动参数
 ///
 /// 这是最可靠的方法，可以找到任意位置的安装和启动参数
@@ -1122,3 +1179,16 @@ fn check_standard_locations() -> Option<std::path::PathBuf> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_main_pid_prefers_root_process() {
+        // 200 是 100 的子进程，即使启动更早也不应被选中
+        let candidates = [(200, Some(100), 5), (100, Some(1), 10), (300, Some(1), 20)];
+        assert_eq!(select_main_pid(&candidates), Some(100));
+        assert_eq!(select_main_pid(&[]), None);
+    }
+}
@@ -0,0 +1,102 @@
+// Antigravity 进程监听
+// 每 2 秒检测一次 Antigravity 是否在运行，状态变化时通知前端并更新托盘图标
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Runtime};
+use tokio_util::sync::CancellationToken;
+
+use crate::modules::{logger, process, tray};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+pub const STATUS_CHANGED_EVENT: &str = "antigravity://status-changed";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessStatusPayload {
+    pub running: bool,
+}
+
+/// 监听任务状态 (由 Tauri 托管)
+#[derive(Default)]
+pub struct ProcessWatchState {
+    cancel: Mutex<Option<CancellationToken>>,
+}
+
+impl ProcessWatchState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 启动监听，已在运行时返回 false
+    pub fn start<R: Runtime>(&self, app: tauri::AppHandle<R>) -> bool {
+        let mut cancel = self.cancel.lock().unwrap_or_else(|e| e.into_inner());
+        if cancel.as_ref().is_some_and(|t| !t.is_cancelled()) {
+            return false;
+        }
+        let token = CancellationToken::new();
+        *cancel = Some(token.clone());
+        tauri::async_runtime::spawn(watch_loop(app, token));
+        true
+    }
+
+    /// 停止监听，未在运行时返回 false
+    pub fn stop(&self) -> bool {
+        match self.cancel.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// 仅在状态变化 (或首次检测) 时返回新状态
+fn transition(previous: Option<bool>, current: bool) -> Option<bool> {
+    (previous != Some(current)).then_some(current)
+}
+
+async fn watch_loop<R: Runtime>(app: tauri::AppHandle<R>, token: CancellationToken) {
+    logger::log_info("[ProcessWatch] Antigravity process watch started");
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut last: Option<bool> = None;
+
+    loop {
+        tokio::select! {
+            _ = token.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+
+        // sysinfo 枚举进程为阻塞操作
+        let running = match tokio::task::spawn_blocking(process::is_antigravity_running).await {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+        if let Some(running) = transition(last, running) {
+            last = Some(running);
+            logger::log_info(&format!("[ProcessWatch] Antigravity running: {}", running));
+            let _ = app.emit(STATUS_CHANGED_EVENT, ProcessStatusPayload { running });
+            tray::set_antigravity_running(&app, running);
+        }
+    }
+
+    logger::log_info("[ProcessWatch] Antigravity process watch stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transition_only_on_change() {
+        assert_eq!(transition(None, false), Some(false));
+        assert_eq!(transition(Some(false), false), None);
+        assert_eq!(transition(Some(false), true), Some(true));
+        assert_eq!(transition(Some(true), true), None);
+
+        let state = ProcessWatchState::new();
+        assert!(!state.stop());
+    }
+}
@@ -120,7 +120,8 @@ pub fn get_logs(limit: usize) -> Result<Vec<ProxyRequestLog>, String> {
     get_logs_summary(limit, 0)
 }
 
-pub fn get_stats() -> Result<crate::proxy::monitor::ProxyStats, String> {
+/// 统计请求总数 / 成功数 / 失败数，`since_ms` 为 Some 时只统计该时间 (毫秒) 之后的请求
+pub fn get_stats(since_ms: Option<i64>) -> Result<crate::proxy::monitor::ProxyStats, String> {
     let db_path = get_proxy_db_path()?;
     let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
 
@@ -128,10 +129,11 @@ pub fn get_stats() -> Result<crate::proxy::monitor::ProxyStats, String> {
     let (total_requests, success_count, error_count): (u64, u64, u64) = conn.query_row(
         "SELECT 
             COUNT(*) as total,
-            SUM(CASE WHEN status >= 200 AND status < 400 THEN 1 ELSE 0 END) as success,
-            SUM(CASE WHEN status < 200 OR status >= 400 THEN 1 ELSE 0 END) as error
-         FROM request_logs",
-        [],
+            COALESCE(SUM(CASE WHEN status >= 200 AND status < 400 THEN 1 ELSE 0 END), 0) as success,
+            COALESCE(SUM(CASE WHEN status < 200 OR status >= 400 THEN 1 ELSE 0 END), 0) as error
+         FROM request_logs
+         WHERE timestamp >= ?1",
+        params![since_ms.unwrap_or(i64::MIN)],
         |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
     ).map_err(|e| e.to_string())?;
 
@@ -139,6 +141,7 @@ pub fn get_stats() -> Result<crate::proxy::monitor::ProxyStats, String> {
         total_requests,
         success_count,
         error_count,
+        ..Default::default()
     })
 }
 
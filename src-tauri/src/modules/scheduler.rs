@@ -3,10 +3,14 @@ use once_cell::sync::Lazy;
 use std::collections::HashMap;
 use std::sync::Mutex;
 use tokio::time::{self, Duration};
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 use crate::modules::{config, logger, quota, account};
 use crate::models::Account;
 
+/// 定时刷新配额的最小间隔，防止频繁调用上游接口
+pub const MIN_QUOTA_REFRESH_INTERVAL_SECS: u64 = 60;
+pub const QUOTA_REFRESHED_EVENT: &str = "quota://refreshed";
+
 // 预热历史记录：key = "email:model_name:100", value = 预热时间戳
 static WARMUP_HISTORY: Lazy<Mutex<HashMap<String, i64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
 
@@ -229,3 +233,87 @@ pub async fn trigger_warmup_for_account(account: &Account) {
         }
     }
 }
+
+/// 定时配额刷新任务 (由 Tauri 托管)
+#[derive(Default)]
+pub struct QuotaRefreshState {
+    handle: Mutex<Option<tauri::async_runtime::JoinHandle<()>>>,
+}
+
+impl QuotaRefreshState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 启动定时刷新，替换已有任务；返回实际使用的间隔 (秒)
+    pub fn schedule(&self, app: tauri::AppHandle, interval_secs: u64) -> u64 {
+        let interval_secs = clamp_refresh_interval(interval_secs);
+        let task = tauri::async_runtime::spawn(quota_refresh_loop(app, interval_secs));
+        if let Some(old) = self.handle.lock().unwrap_or_else(|e| e.into_inner()).replace(task) {
+            old.abort();
+        }
+        interval_secs
+    }
+
+    /// 取消定时刷新，未在运行时返回 false
+    pub fn cancel(&self) -> bool {
+        match self.handle.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            Some(task) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn clamp_refresh_interval(interval_secs: u64) -> u64 {
+    interval_secs.max(MIN_QUOTA_REFRESH_INTERVAL_SECS)
+}
+
+async fn quota_refresh_loop(app: tauri::AppHandle, interval_secs: u64) {
+    logger::log_info(&format!("[QuotaRefresh] Scheduled every {}s", interval_secs));
+    let period = Duration::from_secs(interval_secs);
+    // 首次在一个周期后执行 (调用方通常刚刷新过)
+    let mut interval = time::interval_at(time::Instant::now() + period, period);
+    interval.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+        if account::is_refresh_in_progress() {
+            logger::log_info("[QuotaRefresh] skipped: previous refresh still in progress");
+            continue;
+        }
+
+        // 单独的任务中执行，使刷新耗时超过间隔时下一次触发能被记录为跳过
+        let handle = app.clone();
+        tokio::spawn(async move {
+            let state = handle.state::<crate::commands::proxy::ProxyServiceState>();
+            match crate::commands::refresh_all_quotas(state).await {
+                Ok(stats) => {
+                    logger::log_info(&format!(
+                        "[QuotaRefresh] Completed: {}/{} successful",
+                        stats.success, stats.total
+                    ));
+                    let _ = handle.emit(QUOTA_REFRESHED_EVENT, &stats);
+                }
+                Err(e) => logger::log_error(&format!("[QuotaRefresh] Refresh failed: {}", e)),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_interval_is_clamped() {
+        assert_eq!(clamp_refresh_interval(0), MIN_QUOTA_REFRESH_INTERVAL_SECS);
+        assert_eq!(clamp_refresh_interval(59), MIN_QUOTA_REFRESH_INTERVAL_SECS);
+        assert_eq!(clamp_refresh_interval(300), 300);
+
+        let state = QuotaRefreshState::new();
+        assert!(!state.cancel());
+    }
+}
@@ -6,14 +6,10 @@ use tauri::{
     Emitter, Listener, Manager, Runtime,
 };
 
-pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
-    // 1. Load config to get language settings
-    let config = modules::load_app_config().unwrap_or_default();
-    let texts = modules::i18n::get_tray_texts(&config.language);
-
-    // 2. Load icon (macOS uses Template Image)
+/// Load tray icon; `dimmed` halves the alpha channel (used when Antigravity is not running)
+fn load_tray_icon(dimmed: bool) -> tauri::Result<Image<'static>> {
     let icon_bytes = include_bytes!("../../icons/tray-icon.png");
-    let img = image::load_from_memory(icon_bytes)
+    let mut img = image::load_from_memory(icon_bytes)
         .map_err(|e| {
             tauri::Error::Io(std::io::Error::new(
                 std::io::ErrorKind::Other,
@@ -21,8 +17,34 @@ pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
             ))
         })?
         .to_rgba8();
+    if dimmed {
+        for pixel in img.pixels_mut() {
+            pixel[3] /= 2;
+        }
+    }
     let (width, height) = img.dimensions();
-    let icon = Image::new_owned(img.into_raw(), width, height);
+    Ok(Image::new_owned(img.into_raw(), width, height))
+}
+
+/// Show whether Antigravity is running via tray icon opacity and tooltip
+pub fn set_antigravity_running<R: Runtime>(app: &tauri::AppHandle<R>, running: bool) {
+    let Some(tray) = app.tray_by_id("main") else {
+        return;
+    };
+    if let Ok(icon) = load_tray_icon(!running) {
+        let _ = tray.set_icon(Some(icon));
+    }
+    let tooltip = if running { "Antigravity: running" } else { "Antigravity: stopped" };
+    let _ = tray.set_tooltip(Some(tooltip));
+}
+
+pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
+    // 1. Load config to get language settings
+    let config = modules::load_app_config().unwrap_or_default();
+    let texts = modules::i18n::get_tray_texts(&config.language);
+
+    // 2. Load icon (macOS uses Template Image)
+    let icon = load_tray_icon(false)?;
 
     // 3. Define menu items (use translated text)
     // Status area
@@ -196,7 +218,14 @@ pub fn update_tray_menus<R: Runtime>(app: &tauri::AppHandle<R>) {
 
         if let Some(id) = current {
             if let Ok(account) = modules::load_account(&id) {
-                user_text = format!("{}: {}", texts.current, account.email);
+                user_text = match account.custom_name.as_deref() {
+                    Some(custom) => format!("{}: {} ({})", texts.current, custom, account.email),
+                    None => format!("{}: {}", texts.current, account.email),
+                };
+                if account.frozen {
+                    user_text = format!("❄️ {}", user_text);
+                    menu_lines.push(format!("❄️ {}", texts.frozen));
+                }
 
                 if let Some(q) = account.quota {
                     if q.is_forbidden {
@@ -236,8 +265,24 @@ pub fn update_tray_menus<R: Runtime>(app: &tauri::AppHandle<R>) {
             menu_lines.push(texts.unknown_quota.clone());
         };
 
+        // Installed Antigravity version (cached, may spawn a process)
+        let antigravity_version = tauri::async_runtime::spawn_blocking(modules::version::get_antigravity_version)
+            .await
+            .ok()
+            .flatten();
+
         // Rebuild menu items
         let info_user = MenuItem::with_id(&app_clone, "info_user", &user_text, false, None::<&str>);
+        let info_version = antigravity_version.and_then(|v| {
+            MenuItem::with_id(
+                &app_clone,
+                "info_version",
+                format!("Antigravity v{}", v),
+                false,
+                None::<&str>,
+            )
+            .ok()
+        });
 
         // Dynamically create quota items
         let mut quota_items = Vec::new();
@@ -284,6 +329,9 @@ pub fn update_tray_menus<R: Runtime>(app: &tauri::AppHandle<R>) {
             for item in &quota_items {
                 items.push(item);
             }
+            if let Some(ref v) = info_version {
+                items.push(v);
+            }
 
             if let Some(ref s) = sep1 {
                 items.push(s);
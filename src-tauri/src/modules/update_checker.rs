@@ -83,7 +83,8 @@ pub async fn check_for_updates() -> Result<UpdateInfo, String> {
     let latest_version = release.tag_name.trim_start_matches('v').to_string();
     let current_version = CURRENT_VERSION.to_string();
 
-    let has_update = compare_versions(&latest_version, &current_version);
+    let has_update = compare_versions(&latest_version, &current_version)
+        .inspect_err(|e| logger::log_error(e))?;
 
     if has_update {
         logger::log_info(&format!("发现新版本: {} (当前版本: {})", latest_version, current_version));
@@ -101,29 +102,17 @@ pub async fn check_for_updates() -> Result<UpdateInfo, String> {
     })
 }
 
-/// Compare two semantic versions (e.g., "3.3.24" vs "3.3.23")
-fn compare_versions(latest: &str, current: &str) -> bool {
-    let parse_version = |v: &str| -> Vec<u32> {
-        v.split('.')
-            .filter_map(|s| s.parse::<u32>().ok())
-            .collect()
-    };
-
-    let latest_parts = parse_version(latest);
-    let current_parts = parse_version(current);
-
-    for i in 0..latest_parts.len().max(current_parts.len()) {
-        let latest_part = latest_parts.get(i).unwrap_or(&0);
-        let current_part = current_parts.get(i).unwrap_or(&0);
-
-        if latest_part > current_part {
-            return true;
-        } else if latest_part < current_part {
-            return false;
-        }
-    }
+/// Parse a release version, tolerating a leading `v` (e.g. "v3.3.24")
+pub fn parse_version(s: &str) -> Option<semver::Version> {
+    let s = s.trim();
+    semver::Version::parse(s.strip_prefix(['v', 'V']).unwrap_or(s)).ok()
+}
 
-    false
+/// Whether `latest` is newer than `current` (SemVer precedence, build metadata ignored)
+fn compare_versions(latest: &str, current: &str) -> Result<bool, String> {
+    let latest_v = parse_version(latest).ok_or_else(|| format!("Invalid latest version: {:?}", latest))?;
+    let current_v = parse_version(current).ok_or_else(|| format!("Invalid current version: {:?}", current))?;
+    Ok(latest_v.cmp_precedence(&current_v).is_gt())
 }
 
 /// Check if enough time has passed since last check
@@ -192,11 +181,40 @@ mod tests {
 
     #[test]
     fn test_compare_versions() {
-        assert!(compare_versions("3.3.24", "3.3.23"));
-        assert!(compare_versions("3.4.0", "3.3.24"));
-        assert!(compare_versions("4.0.0", "3.3.24"));
-        assert!(!compare_versions("3.3.23", "3.3.24"));
-        assert!(!compare_versions("3.3.24", "3.3.24"));
+        assert_eq!(compare_versions("3.3.24", "3.3.23"), Ok(true));
+        assert_eq!(compare_versions("3.4.0", "3.3.24"), Ok(true));
+        assert_eq!(compare_versions("4.0.0", "3.3.24"), Ok(true));
+        assert_eq!(compare_versions("3.3.23", "3.3.24"), Ok(false));
+        assert_eq!(compare_versions("3.3.24", "3.3.24"), Ok(false));
+    }
+
+    #[test]
+    fn test_compare_versions_prerelease_build_and_malformed() {
+        // 预发布版本按 SemVer 规则比较
+        assert_eq!(compare_versions("1.2.3-beta.2", "1.2.3-beta.1"), Ok(true));
+        assert_eq!(compare_versions("1.2.3-beta.1", "1.2.3-beta.2"), Ok(false));
+        assert_eq!(compare_versions("1.2.3-beta.10", "1.2.3-beta.2"), Ok(true));
+        // 当前为预发布版本，上游为同版本正式版 -> 有更新
+        assert_eq!(compare_versions("1.2.3", "1.2.3-rc.1"), Ok(true));
+        assert_eq!(compare_versions("1.2.3-rc.1", "1.2.3"), Ok(false));
+        // 构建元数据不参与比较
+        assert_eq!(compare_versions("1.2.3+build.2", "1.2.3+build.1"), Ok(false));
+        assert_eq!(compare_versions("1.2.4+build.1", "1.2.3"), Ok(true));
+        // 格式错误返回明确的错误信息
+        assert!(compare_versions("latest", "1.2.3").unwrap_err().contains("latest"));
+        assert!(compare_versions("1.2.3", "").is_err());
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("v3.3.24"), Some(semver::Version::new(3, 3, 24)));
+        assert_eq!(parse_version(" 3.3.24 "), Some(semver::Version::new(3, 3, 24)));
+        let v = parse_version("1.2.3-beta.1+build.5").unwrap();
+        assert_eq!(v.pre.as_str(), "beta.1");
+        assert_eq!(v.build.as_str(), "build.5");
+        assert_eq!(parse_version("1.2"), None);
+        assert_eq!(parse_version("1.2.x"), None);
+        assert_eq!(parse_version(""), None);
     }
 
     #[test]
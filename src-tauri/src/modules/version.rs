@@ -0,0 +1,254 @@
+// Antigravity 版本检测
+// 依次尝试: `antigravity --version` -> 可执行文件旁的 package.json -> 平台相关的二进制资源
+// 结果 (包括未检测到) 缓存 30 秒，避免 UI 轮询时频繁启动进程
+
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+const CACHE_TTL: Duration = Duration::from_secs(30);
+const VERSION_COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+type CachedVersion = (Option<String>, Instant);
+
+fn version_cache() -> &'static Mutex<Option<CachedVersion>> {
+    static CACHE: OnceLock<Mutex<Option<CachedVersion>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(None))
+}
+
+/// 获取已安装 / 运行中的 Antigravity 版本 (阻塞调用)
+pub fn get_antigravity_version() -> Option<String> {
+    {
+        let cache = version_cache().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((version, at)) = cache.as_ref() {
+            if at.elapsed() < CACHE_TTL {
+                return version.clone();
+            }
+        }
+    }
+
+    // 检测可能启动子进程 (最长 5 秒)，不持有锁，避免阻塞其他调用方
+    let version = detect_version();
+    *version_cache().lock().unwrap_or_else(|e| e.into_inner()) = Some((version.clone(), Instant::now()));
+    version
+}
+
+fn detect_version() -> Option<String> {
+    let exe = crate::modules::process::get_antigravity_executable_path();
+
+    if let Some(v) = version_from_command(exe.as_deref()) {
+        return Some(v);
+    }
+    let exe = exe?;
+    if let Some(v) = package_json_candidates(&exe)
+        .iter()
+        .find_map(|p| std::fs::read_to_string(p).ok().and_then(|c| version_from_package_json(&c)))
+    {
+        return Some(v);
+    }
+    version_from_binary(&exe)
+}
+
+/// 从文本中提取第一个 x.y.z 形式的版本号
+pub fn parse_version(text: &str) -> Option<String> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"\b(\d+\.\d+\.\d+(?:-[0-9A-Za-z.]+)?)\b").unwrap());
+    re.captures(text).map(|c| c[1].to_string())
+}
+
+fn version_from_package_json(content: &str) -> Option<String> {
+    let json: serde_json::Value = serde_json::from_str(content).ok()?;
+    json.get("version")?.as_str().and_then(parse_version)
+}
+
+/// 1. 执行 `antigravity --version` (PATH 中的命令优先，其次是发现的可执行文件)
+fn version_from_command(exe: Option<&Path>) -> Option<String> {
+    let mut programs = vec![PathBuf::from("antigravity")];
+    programs.extend(exe.filter(|p| p.is_file()).map(Path::to_path_buf));
+    programs.iter().find_map(|program| run_version_command(program))
+}
+
+fn run_version_command(program: &Path) -> Option<String> {
+    let mut command = Command::new(program);
+    command
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let mut child = command.spawn().ok()?;
+
+    // 防止命令意外启动 GUI 后一直不退出
+    let deadline = Instant::now() + VERSION_COMMAND_TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(50)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+
+    let output = child.wait_with_output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().find(|l| !l.trim().is_empty()).and_then(parse_version)
+}
+
+/// 2. 可执行文件旁的 package.json
+fn package_json_candidates(exe: &Path) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(bundle) = app_bundle(exe) {
+        candidates.push(bundle.join("Contents/Resources/app/package.json"));
+    }
+    if let Some(dir) = exe.parent() {
+        candidates.push(dir.join("package.json"));
+        candidates.push(dir.join("resources/app/package.json"));
+    }
+    candidates
+}
+
+/// macOS: 找到包含该路径的 .app 目录
+fn app_bundle(path: &Path) -> Option<&Path> {
+    path.ancestors()
+        .find(|p| p.extension().is_some_and(|ext| ext == "app"))
+}
+
+/// 3. 平台相关的二进制资源
+#[cfg(target_os = "macos")]
+fn version_from_binary(exe: &Path) -> Option<String> {
+    let plist = app_bundle(exe)?.join("Contents/Info.plist");
+    let output = Command::new("plutil")
+        .args(["-extract", "CFBundleShortVersionString", "raw", "-o", "-"])
+        .arg(&plist)
+        .output()
+        .ok()?;
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(target_os = "windows")]
+fn version_from_binary(exe: &Path) -> Option<String> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::winver::{
+        GetFileVersionInfoSizeW, GetFileVersionInfoW, VerQueryValueW, VS_FIXEDFILEINFO,
+    };
+
+    let path: Vec<u16> = exe.as_os_str().encode_wide().chain(Some(0)).collect();
+    let root: Vec<u16> = "\\".encode_utf16().chain(Some(0)).collect();
+    // SAFETY: 缓冲区大小由 GetFileVersionInfoSizeW 给出，VerQueryValueW 返回的指针指向该缓冲区内部
+    unsafe {
+        let size = GetFileVersionInfoSizeW(path.as_ptr(), std::ptr::null_mut());
+        if size == 0 {
+            return None;
+        }
+        let mut buf = vec![0u8; size as usize];
+        if GetFileVersionInfoW(path.as_ptr(), 0, size, buf.as_mut_ptr().cast()) == 0 {
+            return None;
+        }
+        let mut info: *mut winapi::ctypes::c_void = std::ptr::null_mut();
+        let mut len: u32 = 0;
+        if VerQueryValueW(buf.as_ptr().cast(), root.as_ptr(), &mut info, &mut len) == 0
+            || info.is_null()
+            || (len as usize) < std::mem::size_of::<VS_FIXEDFILEINFO>()
+        {
+            return None;
+        }
+        let fixed = &*(info as *const VS_FIXEDFILEINFO);
+        Some(format!(
+            "{}.{}.{}",
+            fixed.dwProductVersionMS >> 16,
+            fixed.dwProductVersionMS & 0xFFFF,
+            fixed.dwProductVersionLS >> 16
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn version_from_binary(exe: &Path) -> Option<String> {
+    let mut file = std::fs::File::open(exe).ok()?;
+    elf_note_version(&mut file)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn version_from_binary(_exe: &Path) -> Option<String> {
+    None
+}
+
+/// 在 ELF64 (小端) 的 .note* 段中查找版本号
+#[cfg(any(target_os = "linux", test))]
+fn elf_note_version<R: std::io::Read + std::io::Seek>(reader: &mut R) -> Option<String> {
+    use std::io::SeekFrom;
+
+    let mut read_at = |offset: u64, len: usize| -> Option<Vec<u8>> {
+        reader.seek(SeekFrom::Start(offset)).ok()?;
+        let mut buf = vec![0u8; len];
+        reader.read_exact(&mut buf).ok()?;
+        Some(buf)
+    };
+    let u16_at = |b: &[u8], o: usize| u16::from_le_bytes([b[o], b[o + 1]]);
+    let u32_at = |b: &[u8], o: usize| u32::from_le_bytes(b[o..o + 4].try_into().unwrap());
+    let u64_at = |b: &[u8], o: usize| u64::from_le_bytes(b[o..o + 8].try_into().unwrap());
+
+    let header = read_at(0, 64)?;
+    // 魔数 + ELFCLASS64 + 小端
+    if &header[..4] != b"\x7fELF" || header[4] != 2 || header[5] != 1 {
+        return None;
+    }
+    let sh_off = u64_at(&header, 0x28);
+    let sh_entsize = u16_at(&header, 0x3A) as usize;
+    let sh_num = u16_at(&header, 0x3C) as usize;
+    let sh_strndx = u16_at(&header, 0x3E) as usize;
+    if sh_entsize < 64 || sh_num == 0 || sh_strndx >= sh_num || sh_num > 4096 {
+        return None;
+    }
+
+    let sections = read_at(sh_off, sh_entsize * sh_num)?;
+    let section = |i: usize| &sections[i * sh_entsize..(i + 1) * sh_entsize];
+    let strtab_hdr = section(sh_strndx);
+    let names = read_at(u64_at(strtab_hdr, 0x18), u64_at(strtab_hdr, 0x20).min(1 << 20) as usize)?;
+
+    (0..sh_num).find_map(|i| {
+        let hdr = section(i);
+        let name_off = u32_at(hdr, 0) as usize;
+        let name = names.get(name_off..)?.split(|b| *b == 0).next()?;
+        if !name.starts_with(b".note") {
+            return None;
+        }
+        let data = read_at(u64_at(hdr, 0x18), u64_at(hdr, 0x20).min(1 << 20) as usize)?;
+        parse_version(&String::from_utf8_lossy(&data))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version_sources() {
+        assert_eq!(parse_version("1.11.9\nabc123\nx64"), Some("1.11.9".to_string()));
+        assert_eq!(parse_version("Antigravity 1.2.3-beta.1"), Some("1.2.3-beta.1".to_string()));
+        assert_eq!(parse_version("no version here"), None);
+
+        assert_eq!(
+            version_from_package_json(r#"{"name":"antigravity","version":"1.11.9"}"#),
+            Some("1.11.9".to_string())
+        );
+        assert_eq!(version_from_package_json("{}"), None);
+
+        let exe = Path::new("/Applications/Antigravity.app/Contents/MacOS/Electron");
+        assert_eq!(app_bundle(exe), Some(Path::new("/Applications/Antigravity.app")));
+        assert!(package_json_candidates(exe)
+            .contains(&PathBuf::from("/Applications/Antigravity.app/Contents/Resources/app/package.json")));
+
+        let mut not_elf = std::io::Cursor::new(vec![0u8; 128]);
+        assert_eq!(elf_note_version(&mut not_elf), None);
+    }
+}
@@ -0,0 +1,108 @@
+// 活跃流式连接跟踪
+// 流式响应 (SSE / NDJSON) 打开时登记，响应体传输完毕或客户端断开后随守卫释放
+
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveStreamStats {
+    pub count: usize,
+    /// 最早仍在进行的流的开始时间 (RFC 3339)
+    pub oldest_started_at: Option<String>,
+}
+
+/// 活跃流计数与开始时间表 (流 ID -> 开始时间，ID 单调递增，首项即最早的流)
+#[derive(Clone, Default)]
+pub struct ActiveStreams {
+    count: Arc<AtomicUsize>,
+    started: Arc<Mutex<BTreeMap<u64, Instant>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+struct ActiveStreamGuard {
+    streams: ActiveStreams,
+    id: u64,
+}
+
+impl Drop for ActiveStreamGuard {
+    fn drop(&mut self) {
+        self.streams.count.fetch_sub(1, Ordering::SeqCst);
+        self.streams
+            .started
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.id);
+    }
+}
+
+impl ActiveStreams {
+    fn open(&self) -> ActiveStreamGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.started
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id, Instant::now());
+        self.count.fetch_add(1, Ordering::SeqCst);
+        ActiveStreamGuard {
+            streams: self.clone(),
+            id,
+        }
+    }
+
+    /// 将响应流登记为活跃流，流被丢弃时自动注销
+    pub fn track<S>(&self, stream: S) -> impl Stream<Item = S::Item> + Send + 'static
+    where
+        S: Stream + Send + 'static,
+    {
+        let guard = self.open();
+        stream.map(move |chunk| {
+            let _ = &guard;
+            chunk
+        })
+    }
+
+    pub fn stats(&self) -> ActiveStreamStats {
+        let oldest = self
+            .started
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .next()
+            .copied();
+        ActiveStreamStats {
+            count: self.count.load(Ordering::SeqCst),
+            oldest_started_at: oldest.map(|started| {
+                let elapsed = chrono::Duration::from_std(started.elapsed()).unwrap_or_default();
+                (chrono::Utc::now() - elapsed).to_rfc3339()
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_active_streams_released_on_drop() {
+        let streams = ActiveStreams::default();
+        assert_eq!(streams.stats().count, 0);
+        assert!(streams.stats().oldest_started_at.is_none());
+
+        let first = Box::pin(streams.track(futures::stream::iter(vec![1, 2])));
+        let second = streams.track(futures::stream::iter(vec![3]));
+        let stats = streams.stats();
+        assert_eq!(stats.count, 2);
+        assert!(stats.oldest_started_at.is_some());
+
+        drop(second);
+        let collected: Vec<i32> = first.collect().await;
+        assert_eq!(collected, vec![1, 2]);
+        assert_eq!(streams.stats().count, 0);
+        assert!(streams.stats().oldest_started_at.is_none());
+    }
+}
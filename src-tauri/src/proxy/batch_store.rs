@@ -0,0 +1,123 @@
+// OpenAI Batch API (同步实现)
+// 上游没有等价的批处理接口: 创建批次时逐条调用 chat completions，结果写入本地 JSONL 文件后
+// 直接返回 completed 状态的批次对象，仅适合小批量 (批次记录仅内存，重启后失效)
+// 批次与文件一样按创建请求的 API key / 账号范围隔离
+
+use crate::proxy::file_store::FileOwner;
+use dashmap::DashMap;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::OnceLock;
+
+/// 目前唯一支持的批处理端点
+pub const BATCH_ENDPOINT_CHAT: &str = "/v1/chat/completions";
+
+/// 批处理输入文件中的单条请求
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BatchRequestLine {
+    pub custom_id: String,
+    pub url: String,
+    pub body: Value,
+}
+
+/// 解析 JSONL 输入文件 (每行一个请求，忽略空行)
+pub fn parse_batch_input(content: &str) -> Result<Vec<BatchRequestLine>, String> {
+    let requests = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|e| format!("Invalid batch input on line {}: {}", i + 1, e))
+        })
+        .collect::<Result<Vec<BatchRequestLine>, String>>()?;
+    if requests.is_empty() {
+        return Err("Batch input file contains no requests".to_string());
+    }
+    Ok(requests)
+}
+
+/// 输出文件中的单条结果
+pub fn batch_output_line(custom_id: &str, status_code: u16, body: Value) -> Value {
+    json!({
+        "id": format!("batch_req_{}", uuid::Uuid::new_v4().simple()),
+        "custom_id": custom_id,
+        "response": {
+            "status_code": status_code,
+            "request_id": format!("req_{}", uuid::Uuid::new_v4().simple()),
+            "body": body
+        },
+        "error": null
+    })
+}
+
+pub struct BatchStore {
+    batches: DashMap<String, (FileOwner, Value)>,
+}
+
+impl BatchStore {
+    fn new() -> Self {
+        Self { batches: DashMap::new() }
+    }
+
+    /// Global singleton instance
+    pub fn global() -> &'static BatchStore {
+        static INSTANCE: OnceLock<BatchStore> = OnceLock::new();
+        INSTANCE.get_or_init(BatchStore::new)
+    }
+
+    /// 保存批次对象 (以其 id 字段为键)
+    pub fn insert(&self, batch: Value, owner: FileOwner) {
+        if let Some(id) = batch.get("id").and_then(|v| v.as_str()) {
+            self.batches.insert(id.to_string(), (owner, batch.clone()));
+        }
+    }
+
+    /// 按归属查找批次，其他请求方创建的批次视为不存在
+    pub fn get(&self, id: &str, owner: &FileOwner) -> Option<Value> {
+        self.batches
+            .get(id)
+            .filter(|entry| &entry.0 == owner)
+            .map(|entry| entry.1.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_batch_input() {
+        let content = concat!(
+            r#"{"custom_id":"a","method":"POST","url":"/v1/chat/completions","body":{"model":"gpt-4o","messages":[]}}"#,
+            "\n\n",
+            r#"{"custom_id":"b","method":"POST","url":"/v1/embeddings","body":{}}"#,
+            "\n"
+        );
+        let requests = parse_batch_input(content).unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].custom_id, "a");
+        assert_eq!(requests[0].body["model"], "gpt-4o");
+        assert_eq!(requests[1].url, "/v1/embeddings");
+
+        assert!(parse_batch_input("\n").is_err());
+        let err = parse_batch_input("{\"custom_id\":\"a\"}\nnot json").unwrap_err();
+        assert!(err.contains("line 1"), "{}", err);
+    }
+
+    #[test]
+    fn test_batch_store_and_output_line() {
+        let store = BatchStore::new();
+        let owner = FileOwner::new(Some("sk-a"), None);
+        store.insert(json!({"id": "batch_1", "status": "completed"}), owner.clone());
+        assert_eq!(store.get("batch_1", &owner).unwrap()["status"], "completed");
+        assert!(store.get("batch_2", &owner).is_none());
+        // 其他 key 看不到该批次
+        assert!(store.get("batch_1", &FileOwner::new(Some("sk-b"), None)).is_none());
+        assert!(store.get("batch_1", &FileOwner::default()).is_none());
+
+        let line = batch_output_line("req-1", 200, json!({"object": "chat.completion"}));
+        assert_eq!(line["custom_id"], "req-1");
+        assert_eq!(line["response"]["status_code"], 200);
+        assert!(line["id"].as_str().unwrap().starts_with("batch_req_"));
+    }
+}
@@ -5,3 +5,4 @@
 pub mod json_schema;
 pub mod model_mapping;
 pub mod utils;
+pub mod usage_headers;
@@ -1,6 +1,7 @@
 // 模型名称映射
 use std::collections::HashMap;
 use once_cell::sync::Lazy;
+use serde::Serialize;
 
 static CLAUDE_TO_GEMINI: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
     let mut m = HashMap::new();
@@ -16,9 +17,10 @@ static CLAUDE_TO_GEMINI: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|
     m.insert("claude-3-5-sonnet-20240620", "claude-sonnet-4-5");
     m.insert("claude-opus-4", "claude-opus-4-5-thinking");
     m.insert("claude-opus-4-5-20251101", "claude-opus-4-5-thinking");
-    m.insert("claude-haiku-4", "claude-sonnet-4-5");
-    m.insert("claude-3-haiku-20240307", "claude-sonnet-4-5");
-    m.insert("claude-haiku-4-5-20251001", "claude-sonnet-4-5");
+    // Haiku 走快速通道: 映射到 Flash 以降低延迟
+    m.insert("claude-haiku-4", "gemini-2.5-flash");
+    m.insert("claude-3-haiku-20240307", "gemini-2.5-flash");
+    m.insert("claude-haiku-4-5-20251001", "gemini-2.5-flash");
     // OpenAI 协议映射表
     m.insert("gpt-4", "gemini-2.5-pro");
     m.insert("gpt-4-turbo", "gemini-2.5-pro");
@@ -55,6 +57,27 @@ static CLAUDE_TO_GEMINI: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|
     m
 });
 
+// Mistral 协议映射表 (Mistral 兼容层走 OpenAI 转换路径，由 resolve_model_route 查询)
+static MISTRAL_TO_GEMINI: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
+    let mut m = HashMap::new();
+    m.insert("mistral-large-latest", "gemini-2.5-pro");
+    m.insert("mistral-large-2411", "gemini-2.5-pro");
+    m.insert("mistral-medium-latest", "gemini-2.5-pro");
+    m.insert("mistral-small-latest", "gemini-2.5-flash");
+    m.insert("open-mistral-nemo", "gemini-2.5-flash");
+    m.insert("ministral-8b-latest", "gemini-2.5-flash-lite");
+    m.insert("ministral-3b-latest", "gemini-2.5-flash-lite");
+    m.insert("pixtral-large-latest", "gemini-2.5-pro");
+    m.insert("codestral-latest", "gemini-2.5-pro");
+    m.insert("codestral-2501", "gemini-2.5-pro");
+    m
+});
+
+/// Mistral 模型 ID 对应的内置 Gemini 模型，不在映射表中时返回 None
+pub fn map_mistral_model_to_gemini(input: &str) -> Option<String> {
+    MISTRAL_TO_GEMINI.get(input).map(|m| m.to_string())
+}
+
 pub fn map_claude_model_to_gemini(input: &str) -> String {
     // 1. Check exact match in map
     if let Some(mapped) = CLAUDE_TO_GEMINI.get(input) {
@@ -66,17 +89,113 @@ pub fn map_claude_model_to_gemini(input: &str) -> String {
         return input.to_string();
     }
 
-    // 3. Fallback to default
+    // 3. 其他 Haiku 版本同样走快速通道
+    if input.contains("haiku") {
+        return "gemini-2.5-flash".to_string();
+    }
+
+    // 4. Fallback to default
     "claude-sonnet-4-5".to_string()
 }
 
+/// 模型能力元数据 (GET /v1/models/{id} 等端点返回)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ModelCapabilities {
+    pub context_window: u32,
+    pub max_output_tokens: u32,
+    pub supports_vision: bool,
+    pub supports_function_calling: bool,
+    pub supports_streaming: bool,
+    pub supports_thinking: bool,
+}
+
+const fn caps(context_window: u32, max_output_tokens: u32, supports_vision: bool, supports_function_calling: bool, supports_thinking: bool) -> ModelCapabilities {
+    ModelCapabilities {
+        context_window,
+        max_output_tokens,
+        supports_vision,
+        supports_function_calling,
+        supports_streaming: true,
+        supports_thinking,
+    }
+}
+
+/// 按映射后的上游模型名索引的能力表
+static MODEL_CAPABILITIES: Lazy<HashMap<&'static str, ModelCapabilities>> = Lazy::new(|| {
+    let mut m = HashMap::new();
+
+    // Claude (经 Antigravity 转发)
+    m.insert("claude-opus-4-5-thinking", caps(200_000, 64_000, true, true, true));
+    m.insert("claude-sonnet-4-5", caps(200_000, 64_000, true, true, false));
+    m.insert("claude-sonnet-4-5-thinking", caps(200_000, 64_000, true, true, true));
+
+    // Gemini 2.x
+    m.insert("gemini-2.0-flash-exp", caps(1_048_576, 8_192, true, true, false));
+    m.insert("gemini-2.5-pro", caps(1_048_576, 65_536, true, true, true));
+    m.insert("gemini-2.5-flash", caps(1_048_576, 65_536, true, true, true));
+    m.insert("gemini-2.5-flash-thinking", caps(1_048_576, 65_536, true, true, true));
+    m.insert("gemini-2.5-flash-lite", caps(1_048_576, 65_536, true, true, false));
+
+    // Gemini 3
+    m.insert("gemini-3-pro", caps(1_048_576, 65_536, true, true, true));
+    m.insert("gemini-3-pro-preview", caps(1_048_576, 65_536, true, true, true));
+    m.insert("gemini-3-pro-low", caps(1_048_576, 65_536, true, true, true));
+    m.insert("gemini-3-pro-high", caps(1_048_576, 65_536, true, true, true));
+    m.insert("gemini-3-flash", caps(1_048_576, 65_536, true, true, true));
+    m.insert("gemini-3-pro-image", caps(65_536, 32_768, true, false, false));
+
+    m
+});
+
+/// 查询映射后模型的能力；画图模型的分辨率 / 比例变体 (如 gemini-3-pro-image-4k-16x9) 按基础模型处理
+pub fn model_capabilities(mapped_model: &str) -> Option<ModelCapabilities> {
+    if let Some(caps) = MODEL_CAPABILITIES.get(mapped_model) {
+        return Some(*caps);
+    }
+    if mapped_model.starts_with("gemini-3-pro-image") {
+        return MODEL_CAPABILITIES.get("gemini-3-pro-image").copied();
+    }
+    None
+}
+
+/// 请求调度优先级: High 表示延迟敏感，账号选择时优先近期延迟最低的账号
+/// Low 表示可以容忍延迟的后台请求，目前与 Normal 一样按加权轮询选择账号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RequestPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// 根据客户端请求的模型推断调度优先级 (Haiku -> High，Sonnet / Opus 等 -> Normal)
+pub fn request_priority_for_model(model: &str) -> RequestPriority {
+    if model.to_lowercase().contains("haiku") {
+        RequestPriority::High
+    } else {
+        RequestPriority::Normal
+    }
+}
+
+/// 是否为 Mistral 模型 ID (用于 Mistral 兼容层的响应整形)
+pub fn is_mistral_model(model: &str) -> bool {
+    ["mistral-", "open-mistral-", "codestral-", "ministral-", "pixtral-"]
+        .iter()
+        .any(|prefix| model.starts_with(prefix))
+}
+
 /// 获取所有内置支持的模型列表关键字
 pub fn get_supported_models() -> Vec<String> {
-    CLAUDE_TO_GEMINI.keys().map(|s| s.to_string()).collect()
+    CLAUDE_TO_GEMINI
+        .keys()
+        .chain(MISTRAL_TO_GEMINI.keys())
+        .map(|s| s.to_string())
+        .collect()
 }
 
 /// 动态获取所有可用模型列表 (包含内置与用户自定义)
 pub async fn get_all_dynamic_models(
+    model_aliases: &tokio::sync::RwLock<std::collections::HashMap<String, String>>,
     custom_mapping: &tokio::sync::RwLock<std::collections::HashMap<String, String>>,
 ) -> Vec<String> {
     use std::collections::HashSet;
@@ -95,6 +214,12 @@ pub async fn get_all_dynamic_models(
         }
     }
 
+    // 3. 模型别名
+    {
+        let aliases = model_aliases.read().await;
+        model_ids.extend(aliases.keys().cloned());
+    }
+
     // 5. 确保包含常用的 Gemini/画画模型 ID
     model_ids.insert("gemini-3-pro-low".to_string());
     
@@ -143,19 +268,27 @@ fn wildcard_match(pattern: &str, text: &str) -> bool {
 }
 
 /// 核心模型路由解析引擎
-/// 优先级：精确匹配 > 通配符匹配 > 系统默认映射
+/// 优先级：模型别名 > 精确匹配 > 通配符匹配 > 系统默认映射
 /// 
 /// # 参数
 /// - `original_model`: 原始模型名称
+/// - `model_aliases`: 模型别名表 (`ProxyConfig::model_aliases`)
 /// - `custom_mapping`: 用户自定义映射表
 /// 
 /// # 返回
 /// 映射后的目标模型名称
 pub fn resolve_model_route(
     original_model: &str,
+    model_aliases: &std::collections::HashMap<String, String>,
     custom_mapping: &std::collections::HashMap<String, String>,
 ) -> String {
-    // 1. 精确匹配 (最高优先级)
+    // 0. 模型别名 (最高优先级)
+    if let Some(target) = model_aliases.get(original_model) {
+        crate::modules::logger::log_info(&format!("[Router] 别名映射: {} -> {}", original_model, target));
+        return target.clone();
+    }
+
+    // 1. 精确匹配
     if let Some(target) = custom_mapping.get(original_model) {
         crate::modules::logger::log_info(&format!("[Router] 精确映射: {} -> {}", original_model, target));
         return target.clone();
@@ -169,14 +302,32 @@ pub fn resolve_model_route(
         }
     }
     
-    // 3. 系统默认映射
-    let result = map_claude_model_to_gemini(original_model);
+    // 3. 系统默认映射 (Mistral 模型使用独立的映射表)
+    let result = map_mistral_model_to_gemini(original_model)
+        .unwrap_or_else(|| map_claude_model_to_gemini(original_model));
     if result != original_model {
         crate::modules::logger::log_info(&format!("[Router] 系统默认映射: {} -> {}", original_model, result));
     }
     result
 }
 
+/// 合并后的有效映射表 (供 UI 展示)
+///
+/// 以内置映射为基础，依次叠加自定义映射 (含通配符规则，原样保留) 与模型别名，后者覆盖前者。
+pub fn effective_model_mapping(
+    model_aliases: &HashMap<String, String>,
+    custom_mapping: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    let mut merged: HashMap<String, String> = CLAUDE_TO_GEMINI
+        .iter()
+        .chain(MISTRAL_TO_GEMINI.iter())
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    merged.extend(custom_mapping.iter().map(|(k, v)| (k.clone(), v.clone())));
+    merged.extend(model_aliases.iter().map(|(k, v)| (k.clone(), v.clone())));
+    merged
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -196,9 +347,58 @@ mod tests {
             map_claude_model_to_gemini("gemini-2.5-flash-mini-test"),
             "gemini-2.5-flash-mini-test"
         );
+        assert_eq!(
+            map_mistral_model_to_gemini("codestral-latest").as_deref(),
+            Some("gemini-2.5-pro")
+        );
+        assert!(map_mistral_model_to_gemini("gpt-4o").is_none());
+        assert_eq!(
+            resolve_model_route("ministral-8b-latest", &HashMap::new(), &HashMap::new()),
+            "gemini-2.5-flash-lite"
+        );
+        assert!(is_mistral_model("mistral-large-latest"));
+        assert!(!is_mistral_model("gpt-4o"));
         assert_eq!(
             map_claude_model_to_gemini("unknown-model"),
             "claude-sonnet-4-5"
         );
     }
+
+    #[test]
+    fn test_model_capabilities_cover_builtin_mappings() {
+        for target in CLAUDE_TO_GEMINI.values().chain(MISTRAL_TO_GEMINI.values()) {
+            assert!(model_capabilities(target).is_some(), "missing capabilities for {}", target);
+        }
+        let image = model_capabilities("gemini-3-pro-image-4k-16x9").unwrap();
+        assert!(!image.supports_function_calling);
+        assert!(model_capabilities("claude-opus-4-5-thinking").unwrap().supports_thinking);
+        assert!(model_capabilities("unknown-model").is_none());
+    }
+
+    #[test]
+    fn test_haiku_fast_path() {
+        assert_eq!(map_claude_model_to_gemini("claude-3-haiku-20240307"), "gemini-2.5-flash");
+        assert_eq!(map_claude_model_to_gemini("claude-3-5-haiku-20241022"), "gemini-2.5-flash");
+        assert_eq!(request_priority_for_model("claude-3-haiku-20240307"), RequestPriority::High);
+        assert_eq!(request_priority_for_model("claude-sonnet-4-5"), RequestPriority::Normal);
+        assert_eq!(request_priority_for_model("claude-opus-4"), RequestPriority::Normal);
+    }
+
+    #[test]
+    fn test_model_aliases_take_priority() {
+        let aliases = HashMap::from([("gpt-4-turbo".to_string(), "gemini-3-pro-high".to_string())]);
+        let custom = HashMap::from([
+            ("gpt-4-turbo".to_string(), "gemini-2.5-flash".to_string()),
+            ("gpt-4*".to_string(), "gemini-2.5-flash".to_string()),
+        ]);
+
+        assert_eq!(resolve_model_route("gpt-4-turbo", &aliases, &custom), "gemini-3-pro-high");
+        assert_eq!(resolve_model_route("gpt-4o", &aliases, &custom), "gemini-2.5-flash");
+        assert_eq!(resolve_model_route("gpt-3.5-turbo", &aliases, &custom), "gemini-2.5-flash");
+
+        let merged = effective_model_mapping(&aliases, &custom);
+        assert_eq!(merged["gpt-4-turbo"], "gemini-3-pro-high");
+        assert_eq!(merged["gpt-4*"], "gemini-2.5-flash");
+        assert_eq!(merged["claude-opus-4"], "claude-opus-4-5-thinking");
+    }
 }
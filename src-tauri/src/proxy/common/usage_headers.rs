@@ -0,0 +1,56 @@
+// 用量响应头
+// 将上游 usageMetadata 以响应头形式返回，客户端无需解析响应体即可统计 token 用量
+// 不输出 X-RateLimit-Remaining-Tokens: 上游配额接口只提供剩余百分比，没有可用的剩余 token 数，
+// 以百分比填充该头会被客户端当作 token 数误用
+
+use axum::http::{HeaderMap, HeaderValue};
+use axum::response::Response;
+
+use crate::proxy::model_stats::TokenUsage;
+
+pub const HEADER_TOKENS_INPUT: &str = "X-Tokens-Input";
+pub const HEADER_TOKENS_OUTPUT: &str = "X-Tokens-Output";
+
+/// 写入用量响应头，缺失的字段不输出
+pub fn insert_usage_headers(headers: &mut HeaderMap, usage: Option<TokenUsage>) {
+    let usage = usage.unwrap_or_default();
+    let values = [
+        (HEADER_TOKENS_INPUT, usage.input),
+        (HEADER_TOKENS_OUTPUT, usage.output),
+    ];
+    for (name, value) in values {
+        if let Some(value) = value {
+            headers.insert(name, HeaderValue::from(value));
+        }
+    }
+}
+
+/// 为非流式响应附加用量响应头 (include_usage_headers 关闭时原样返回)
+/// 流式响应的响应头在用量可知前已发送，因此不附加
+pub fn with_usage_headers(
+    mut response: Response,
+    enabled: bool,
+    usage: Option<TokenUsage>,
+) -> Response {
+    if enabled {
+        insert_usage_headers(response.headers_mut(), usage);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_headers_omit_missing_values() {
+        let mut headers = HeaderMap::new();
+        insert_usage_headers(&mut headers, Some(TokenUsage { input: Some(12), output: None }));
+        assert_eq!(headers.get(HEADER_TOKENS_INPUT).unwrap(), "12");
+        assert!(headers.get(HEADER_TOKENS_OUTPUT).is_none());
+        assert!(headers.get("X-RateLimit-Remaining-Tokens").is_none());
+
+        let disabled = with_usage_headers(Response::default(), false, Some(TokenUsage { input: Some(1), output: Some(2) }));
+        assert!(disabled.headers().get(HEADER_TOKENS_INPUT).is_none());
+    }
+}
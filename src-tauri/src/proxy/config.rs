@@ -166,9 +166,17 @@ pub struct ProxyConfig {
     
     /// 监听端口
     pub port: u16,
+
+    /// 主端口被占用时依次尝试的备用端口范围 (闭区间，如 [8080, 8090])
+    #[serde(default)]
+    pub port_fallback_range: Option<(u16, u16)>,
     
     /// API 密钥
     pub api_key: String,
+
+    /// 按 API Key 路由账号 (key: 额外的 API Key, value: 该 Key 可使用的账号 ID 列表)
+    #[serde(default)]
+    pub api_key_routes: std::collections::HashMap<String, Vec<String>>,
     
 
     /// 是否自动启动
@@ -178,6 +186,10 @@ pub struct ProxyConfig {
     #[serde(default)]
     pub custom_mapping: std::collections::HashMap<String, String>,
 
+    /// 模型别名 (key: 客户端请求的模型名, value: 上游 Gemini 模型名)，优先于其它映射
+    #[serde(default)]
+    pub model_aliases: std::collections::HashMap<String, String>,
+
     /// API 请求超时时间(秒)
     #[serde(default = "default_request_timeout")]
     pub request_timeout: u64,
@@ -201,6 +213,136 @@ pub struct ProxyConfig {
     /// 实验性功能配置
     #[serde(default)]
     pub experimental: ExperimentalConfig,
+
+    /// 单账号请求速率上限 (令牌桶补充速率, 请求/秒)
+    /// - None: 不限速 (默认)
+    #[serde(default)]
+    pub rate_limit_rps: Option<f64>,
+
+    /// 单账号突发请求上限 (令牌桶容量)
+    /// - None: 取 `rate_limit_rps` 向上取整 (至少 1)
+    #[serde(default)]
+    pub rate_limit_burst: Option<u32>,
+
+    /// 按账号覆盖速率上限 (key: 账号 ID 或邮箱, value: 请求/秒)
+    #[serde(default)]
+    pub account_rate_limit_rps: HashMap<String, f64>,
+
+    /// 重试退避基础延迟 (毫秒)，第 n 次重试等待 base * 2^n
+    #[serde(default = "default_retry_base_delay_ms")]
+    pub retry_base_delay_ms: u64,
+
+    /// 重试退避最大延迟 (毫秒)
+    #[serde(default = "default_retry_max_delay_ms")]
+    pub retry_max_delay_ms: u64,
+
+    /// 重试退避随机抖动上限 (毫秒)
+    #[serde(default = "default_retry_jitter_ms")]
+    pub retry_jitter_ms: u64,
+
+    /// 账号熔断冷却时间 (秒)，401/硬 403 后账号在此期间不参与调度
+    #[serde(default = "default_circuit_breaker_cooldown_secs")]
+    pub circuit_breaker_cooldown_secs: u64,
+
+    /// Mistral 兼容模式: 启用后 /v1/chat/completions 对 Mistral 模型返回 Mistral 形态的响应
+    #[serde(default)]
+    pub mistral_compat: bool,
+
+    /// 挂载 POST /v1/moderations 桩端点 (始终返回 flagged: false)，关闭时该端点返回 404
+    #[serde(default = "default_mock_moderation_endpoint")]
+    pub mock_moderation_endpoint: bool,
+
+    /// 调试用: 设置后将每个 SSE 响应原样写入该目录 ({timestamp}_{protocol}_{model}.sse，保留最近 100 个)
+    #[serde(default)]
+    pub record_streams: Option<std::path::PathBuf>,
+
+    /// 相同 generateContent 请求的去重窗口 (秒)，0 表示关闭
+    #[serde(default = "default_dedup_window_secs")]
+    pub dedup_window_secs: u64,
+
+    /// 停止服务时等待进行中请求完成的最长时间 (秒)，超时后强制关闭连接
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+
+    /// 管理端点 (/admin/*) 专用 Key，未配置时启动服务会随机生成并打印到日志
+    #[serde(default)]
+    pub admin_api_key: Option<String>,
+
+    /// 非流式响应附加 X-Tokens-Input / X-Tokens-Output 响应头
+    #[serde(default = "default_include_usage_headers")]
+    pub include_usage_headers: bool,
+
+    /// Claude 请求在所有账号上均返回 503 时，改用该模型再轮询一遍账号池 (未配置则不降级)
+    #[serde(default)]
+    pub fallback_model_on_503: Option<String>,
+
+    /// 响应体小于该字节数时不压缩 (gzip / br 按客户端 Accept-Encoding 协商)
+    #[serde(default = "default_compression_threshold_bytes")]
+    pub compression_threshold_bytes: usize,
+
+    /// 请求体大小上限 (MB)，超过时返回 413 (/healthz 与 /metrics 除外)
+    #[serde(default = "default_max_request_body_mb")]
+    pub max_request_body_mb: usize,
+
+    /// 将 X-Request-ID 作为 X-Goog-Request-Id 转发给上游 (关闭后仍会记录日志并回显)
+    #[serde(default = "default_propagate_request_id")]
+    pub propagate_request_id: bool,
+
+    /// 允许的浏览器来源 (CORS)，`*` 表示任意来源
+    #[serde(default = "default_allowed_origins")]
+    pub allowed_origins: Vec<String>,
+
+    /// CORS 响应是否允许携带凭证 (Cookie / Authorization)
+    #[serde(default)]
+    pub allow_credentials: bool,
+
+    /// 流式响应中两个 chunk 之间的最长等待时间 (秒)，超时后结束流并发送错误事件
+    #[serde(default = "default_stream_inactivity_timeout_secs")]
+    pub stream_inactivity_timeout_secs: u64,
+
+    /// 流式响应从首个字节到结束的总时长上限 (秒)，0 表示不限制
+    #[serde(default)]
+    pub stream_total_timeout_secs: u64,
+
+    /// Claude 请求的输入 token 估算上限，超过时从最早的消息开始丢弃 (None 表示不限制)
+    #[serde(default)]
+    pub max_input_tokens: Option<u32>,
+
+    /// 上游连接池每个主机保留的空闲连接数
+    #[serde(default = "default_upstream_pool_max_idle_per_host")]
+    pub upstream_pool_max_idle_per_host: usize,
+
+    /// 上游空闲连接保留时间 (秒)
+    #[serde(default = "default_upstream_pool_idle_timeout_secs")]
+    pub upstream_pool_idle_timeout_secs: u64,
+
+    /// 上游 TCP keepalive 探测间隔 (秒)
+    #[serde(default = "default_upstream_tcp_keepalive_secs")]
+    pub upstream_tcp_keepalive_secs: u64,
+
+    /// 上游 HTTP/2 PING 保活间隔 (秒)
+    #[serde(default = "default_upstream_http2_keep_alive_interval_secs")]
+    pub upstream_http2_keep_alive_interval_secs: u64,
+
+    /// 跳过 ALPN 协商直接使用 HTTP/2 (默认关闭)
+    #[serde(default)]
+    pub upstream_http2_prior_knowledge: bool,
+
+    /// 上游建立连接超时 (秒)
+    #[serde(default = "default_upstream_connect_timeout_secs")]
+    pub upstream_connect_timeout_secs: u64,
+
+    /// 上游响应读取超时 (秒)，两次读取之间超过该时间视为失败
+    #[serde(default = "default_upstream_read_timeout_secs")]
+    pub upstream_read_timeout_secs: u64,
+
+    /// 非流式响应缓存的有效期 (秒)，None 表示不启用缓存
+    #[serde(default)]
+    pub response_cache_ttl_secs: Option<u64>,
+
+    /// 响应缓存的最大条目数 (LRU 淘汰)
+    #[serde(default = "default_response_cache_max_entries")]
+    pub response_cache_max_entries: usize,
 }
 
 /// 上游代理配置
@@ -219,15 +361,50 @@ impl Default for ProxyConfig {
             allow_lan_access: false, // 默认仅本机访问，隐私优先
             auth_mode: ProxyAuthMode::default(),
             port: 8045,
+            port_fallback_range: None,
             api_key: format!("sk-{}", uuid::Uuid::new_v4().simple()),
+            api_key_routes: std::collections::HashMap::new(),
             auto_start: false,
             custom_mapping: std::collections::HashMap::new(),
+            model_aliases: std::collections::HashMap::new(),
             request_timeout: default_request_timeout(),
             enable_logging: false, // 默认关闭，节省性能
             upstream_proxy: UpstreamProxyConfig::default(),
             zai: ZaiConfig::default(),
             scheduling: crate::proxy::sticky_config::StickySessionConfig::default(),
             experimental: ExperimentalConfig::default(),
+            rate_limit_rps: None,
+            rate_limit_burst: None,
+            account_rate_limit_rps: HashMap::new(),
+            retry_base_delay_ms: default_retry_base_delay_ms(),
+            retry_max_delay_ms: default_retry_max_delay_ms(),
+            retry_jitter_ms: default_retry_jitter_ms(),
+            circuit_breaker_cooldown_secs: default_circuit_breaker_cooldown_secs(),
+            mistral_compat: false,
+            mock_moderation_endpoint: default_mock_moderation_endpoint(),
+            record_streams: None,
+            dedup_window_secs: default_dedup_window_secs(),
+            shutdown_timeout_secs: default_shutdown_timeout_secs(),
+            include_usage_headers: default_include_usage_headers(),
+            admin_api_key: None,
+            fallback_model_on_503: None,
+            compression_threshold_bytes: default_compression_threshold_bytes(),
+            max_request_body_mb: default_max_request_body_mb(),
+            propagate_request_id: default_propagate_request_id(),
+            allowed_origins: default_allowed_origins(),
+            allow_credentials: false,
+            stream_inactivity_timeout_secs: default_stream_inactivity_timeout_secs(),
+            stream_total_timeout_secs: 0,
+            max_input_tokens: None,
+            upstream_pool_max_idle_per_host: default_upstream_pool_max_idle_per_host(),
+            upstream_pool_idle_timeout_secs: default_upstream_pool_idle_timeout_secs(),
+            upstream_tcp_keepalive_secs: default_upstream_tcp_keepalive_secs(),
+            upstream_http2_keep_alive_interval_secs: default_upstream_http2_keep_alive_interval_secs(),
+            upstream_http2_prior_knowledge: false,
+            upstream_connect_timeout_secs: default_upstream_connect_timeout_secs(),
+            upstream_read_timeout_secs: default_upstream_read_timeout_secs(),
+            response_cache_ttl_secs: None,
+            response_cache_max_entries: default_response_cache_max_entries(),
         }
     }
 }
@@ -236,6 +413,86 @@ fn default_request_timeout() -> u64 {
     120  // 默认 120 秒,原来 60 秒太短
 }
 
+fn default_retry_base_delay_ms() -> u64 {
+    500
+}
+
+fn default_retry_max_delay_ms() -> u64 {
+    8000
+}
+
+fn default_retry_jitter_ms() -> u64 {
+    200
+}
+
+fn default_circuit_breaker_cooldown_secs() -> u64 {
+    300
+}
+
+fn default_dedup_window_secs() -> u64 {
+    5
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+
+fn default_include_usage_headers() -> bool {
+    true
+}
+
+fn default_mock_moderation_endpoint() -> bool {
+    true
+}
+
+fn default_compression_threshold_bytes() -> usize {
+    1024
+}
+
+fn default_max_request_body_mb() -> usize {
+    50
+}
+
+fn default_response_cache_max_entries() -> usize {
+    100
+}
+
+fn default_upstream_pool_max_idle_per_host() -> usize {
+    10
+}
+
+fn default_upstream_pool_idle_timeout_secs() -> u64 {
+    90
+}
+
+fn default_upstream_tcp_keepalive_secs() -> u64 {
+    30
+}
+
+fn default_upstream_http2_keep_alive_interval_secs() -> u64 {
+    20
+}
+
+fn default_upstream_connect_timeout_secs() -> u64 {
+    10
+}
+
+fn default_upstream_read_timeout_secs() -> u64 {
+    120
+}
+
+fn default_propagate_request_id() -> bool {
+    true
+}
+
+fn default_stream_inactivity_timeout_secs() -> u64 {
+    60
+}
+
+fn default_allowed_origins() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
 fn default_zai_base_url() -> String {
     "https://api.z.ai/api/anthropic".to_string()
 }
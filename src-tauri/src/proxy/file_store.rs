@@ -0,0 +1,278 @@
+// OpenAI Files API 映射表
+// 记录 OpenAI 风格 file ID 与 Google File API 文件的对应关系 (仅内存，重启后失效)
+// Batch API 的输入 / 输出文件不上传到 Google，保存在本地临时目录
+// 文件按上传请求的 API key 与账号范围隔离，其他 key 查询时视为不存在 (404)
+
+use crate::proxy::token_manager::AccountAllowlist;
+use dashmap::DashMap;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+// Google File API 上传的文件 48 小时后自动删除
+const FILE_TTL_SECS: i64 = 48 * 60 * 60;
+
+/// 文件归属: 上传请求的 API key (仅保存 SHA-256 摘要) 及该 key 可用的账号范围
+/// 未启用鉴权时两者均为 None，此时所有请求共享同一归属
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileOwner {
+    key_hash: Option<[u8; 32]>,
+    scope: Option<Vec<String>>,
+}
+
+impl FileOwner {
+    pub fn new(api_key: Option<&str>, allowlist: Option<&AccountAllowlist>) -> Self {
+        Self {
+            key_hash: api_key.map(|key| Sha256::digest(key.as_bytes()).into()),
+            scope: allowlist.map(|a| a.account_ids()),
+        }
+    }
+
+    pub fn from_request(headers: &axum::http::HeaderMap, allowlist: Option<&AccountAllowlist>) -> Self {
+        Self::new(crate::proxy::middleware::request_api_key(headers), allowlist)
+    }
+}
+
+/// 已上传到 Google File API 的文件
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredFile {
+    pub id: String,          // OpenAI 风格 ID (file-...)
+    pub google_name: String, // files/xxx
+    pub uri: String,
+    pub mime_type: String,
+    pub filename: String,
+    pub bytes: u64,
+    pub purpose: String,
+    pub created_at: i64,
+    /// 上传使用的账号 (文件归属于该账号，检索/删除时必须使用同一账号)
+    pub account_email: String,
+    /// 本地保存的文件 (Batch API)，为 Some 时不对应任何 Google 文件
+    pub local_path: Option<PathBuf>,
+    /// 上传者，只有同一 API key 且账号范围一致的请求可以访问
+    pub owner: FileOwner,
+}
+
+impl StoredFile {
+    /// 由 Google File API 返回的文件对象构建
+    pub fn from_google(
+        file: &Value,
+        filename: &str,
+        purpose: &str,
+        account_email: &str,
+        owner: FileOwner,
+    ) -> Result<Self, String> {
+        let google_name = file
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or("Upstream file response missing name")?;
+        Ok(Self {
+            id: format!("file-{}", uuid::Uuid::new_v4().simple()),
+            google_name: google_name.to_string(),
+            uri: file.get("uri").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+            mime_type: file
+                .get("mimeType")
+                .and_then(|v| v.as_str())
+                .unwrap_or("application/octet-stream")
+                .to_string(),
+            filename: filename.to_string(),
+            // sizeBytes 为 int64 字符串
+            bytes: file
+                .get("sizeBytes")
+                .and_then(|v| v.as_str().and_then(|s| s.parse().ok()).or_else(|| v.as_u64()))
+                .unwrap_or(0),
+            purpose: purpose.to_string(),
+            created_at: chrono::Utc::now().timestamp(),
+            account_email: account_email.to_string(),
+            local_path: None,
+            owner,
+        })
+    }
+
+    pub fn is_local(&self) -> bool {
+        self.local_path.is_some()
+    }
+
+    /// 读取本地文件内容 (Google File API 不支持下载用户上传的文件)
+    pub fn read_local(&self) -> Result<Vec<u8>, String> {
+        let path = self
+            .local_path
+            .as_ref()
+            .ok_or_else(|| format!("File {} content is not available for download", self.id))?;
+        std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", self.id, e))
+    }
+
+    /// 转换为 OpenAI File 对象
+    pub fn to_openai(&self) -> Value {
+        json!({
+            "id": self.id,
+            "object": "file",
+            "bytes": self.bytes,
+            "created_at": self.created_at,
+            "filename": self.filename,
+            "purpose": self.purpose,
+            "status": "processed",
+        })
+    }
+
+    fn is_expired(&self, now: i64) -> bool {
+        now - self.created_at > FILE_TTL_SECS
+    }
+}
+
+pub struct FileStore {
+    files: DashMap<String, StoredFile>,
+}
+
+impl FileStore {
+    fn new() -> Self {
+        Self { files: DashMap::new() }
+    }
+
+    /// Global singleton instance
+    pub fn global() -> &'static FileStore {
+        static INSTANCE: OnceLock<FileStore> = OnceLock::new();
+        INSTANCE.get_or_init(FileStore::new)
+    }
+
+    /// 保存文件记录，同时清理已过期的记录
+    pub fn insert(&self, file: StoredFile) {
+        let now = chrono::Utc::now().timestamp();
+        self.files.retain(|_, f| {
+            let expired = f.is_expired(now);
+            if expired {
+                remove_local_file(f);
+            }
+            !expired
+        });
+        self.files.insert(file.id.clone(), file);
+    }
+
+    /// 将内容写入本地临时目录并登记为文件 (Batch API 输入 / 输出)
+    pub fn save_local(
+        &self,
+        filename: &str,
+        purpose: &str,
+        content: &[u8],
+        owner: FileOwner,
+    ) -> Result<StoredFile, String> {
+        let id = format!("file-{}", uuid::Uuid::new_v4().simple());
+        let dir = local_file_dir();
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+        let path = dir.join(format!("{}.jsonl", id));
+        std::fs::write(&path, content).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+
+        let file = StoredFile {
+            id,
+            google_name: String::new(),
+            uri: String::new(),
+            mime_type: "application/jsonl".to_string(),
+            filename: filename.to_string(),
+            bytes: content.len() as u64,
+            purpose: purpose.to_string(),
+            created_at: chrono::Utc::now().timestamp(),
+            account_email: String::new(),
+            local_path: Some(path),
+            owner,
+        };
+        self.insert(file.clone());
+        Ok(file)
+    }
+
+    pub fn get(&self, id: &str) -> Option<StoredFile> {
+        let now = chrono::Utc::now().timestamp();
+        self.files
+            .get(id)
+            .map(|f| f.clone())
+            .filter(|f| !f.is_expired(now))
+    }
+
+    /// 按归属查找文件，不属于该请求方的文件视为不存在
+    pub fn get_owned(&self, id: &str, owner: &FileOwner) -> Option<StoredFile> {
+        self.get(id).filter(|f| &f.owner == owner)
+    }
+
+    /// 移除文件记录，本地文件一并删除
+    pub fn remove(&self, id: &str) -> Option<StoredFile> {
+        let (_, file) = self.files.remove(id)?;
+        remove_local_file(&file);
+        Some(file)
+    }
+}
+
+fn local_file_dir() -> PathBuf {
+    std::env::temp_dir().join("antigravity_files")
+}
+
+fn remove_local_file(file: &StoredFile) {
+    if let Some(path) = &file.local_path {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_from_google_and_expiry() {
+        let google = json!({
+            "name": "files/abc123",
+            "uri": "https://generativelanguage.googleapis.com/v1beta/files/abc123",
+            "mimeType": "application/pdf",
+            "sizeBytes": "2048"
+        });
+        let file =
+            StoredFile::from_google(&google, "report.pdf", "assistants", "a@example.com", FileOwner::default()).unwrap();
+        assert!(file.id.starts_with("file-"));
+        assert_eq!(file.bytes, 2048);
+        assert_eq!(file.to_openai()["filename"], "report.pdf");
+
+        let store = FileStore::new();
+        let mut expired = file.clone();
+        expired.id = "file-old".to_string();
+        expired.created_at -= FILE_TTL_SECS + 1;
+        store.files.insert(expired.id.clone(), expired);
+        assert!(store.get("file-old").is_none());
+
+        store.insert(file.clone());
+        assert!(!store.files.contains_key("file-old"));
+        assert_eq!(store.get(&file.id), Some(file.clone()));
+        assert_eq!(store.remove(&file.id).map(|f| f.google_name), Some("files/abc123".to_string()));
+
+        assert!(StoredFile::from_google(&json!({}), "x", "y", "z", FileOwner::default()).is_err());
+    }
+
+    #[test]
+    fn test_save_local_file() {
+        let store = FileStore::new();
+        let file = store
+            .save_local("input.jsonl", "batch", b"{\"custom_id\":\"1\"}\n", FileOwner::default())
+            .unwrap();
+        assert!(file.is_local());
+        assert_eq!(file.bytes, 18);
+        assert_eq!(file.read_local().unwrap(), b"{\"custom_id\":\"1\"}\n");
+
+        let path = file.local_path.clone().unwrap();
+        store.remove(&file.id);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_get_owned_rejects_other_keys_and_scopes() {
+        let store = FileStore::new();
+        let scope = AccountAllowlist::new(["acc-1".to_string()]);
+        let owner = FileOwner::new(Some("sk-a"), Some(&scope));
+        let file = store.save_local("input.jsonl", "batch", b"{}\n", owner.clone()).unwrap();
+
+        assert_eq!(store.get_owned(&file.id, &owner).map(|f| f.id), Some(file.id.clone()));
+        // 其他 key、无 key 或同一 key 但账号范围已变化均不可见
+        assert!(store.get_owned(&file.id, &FileOwner::new(Some("sk-b"), Some(&scope))).is_none());
+        assert!(store.get_owned(&file.id, &FileOwner::default()).is_none());
+        let widened = AccountAllowlist::new(["acc-1".to_string(), "acc-2".to_string()]);
+        assert!(store.get_owned(&file.id, &FileOwner::new(Some("sk-a"), Some(&widened))).is_none());
+        assert!(store.get_owned(&file.id, &FileOwner::new(Some("sk-a"), None)).is_none());
+
+        store.remove(&file.id);
+    }
+}
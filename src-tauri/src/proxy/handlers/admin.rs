@@ -0,0 +1,54 @@
+// 管理端点处理器
+// 供无界面运行时通过脚本触发热重载 (账号 + 配置)，无需重启反代服务
+
+use axum::{extract::State, response::IntoResponse, Json};
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::proxy::{AxumServer, TokenManager};
+
+#[derive(Clone)]
+pub struct AdminState {
+    pub server: AxumServer,
+    pub token_manager: Arc<TokenManager>,
+}
+
+/// POST /admin/reload: 重新加载账号并从磁盘重新读取配置
+pub async fn handle_reload(State(admin): State<AdminState>) -> impl IntoResponse {
+    let mut errors = Vec::new();
+
+    let accounts_loaded = match admin.token_manager.load_accounts().await {
+        Ok(count) => count,
+        Err(e) => {
+            errors.push(format!("重新加载账号失败: {}", e));
+            0
+        }
+    };
+
+    let config_reloaded = match crate::modules::config::load_app_config() {
+        Ok(config) => {
+            admin.server.update_mapping(&config.proxy).await;
+            admin.server.update_proxy(config.proxy.upstream_proxy.clone()).await;
+            admin.server.update_security(&config.proxy).await;
+            admin.server.update_zai(&config.proxy).await;
+            true
+        }
+        Err(e) => {
+            errors.push(format!("读取配置失败: {}", e));
+            false
+        }
+    };
+
+    tracing::info!(
+        "[Admin] Reload finished: {} accounts loaded, config reloaded: {}, {} errors",
+        accounts_loaded,
+        config_reloaded,
+        errors.len()
+    );
+
+    Json(json!({
+        "accounts_loaded": accounts_loaded,
+        "config_reloaded": config_reloaded,
+        "errors": errors,
+    }))
+}
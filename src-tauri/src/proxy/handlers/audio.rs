@@ -2,7 +2,7 @@ use axum::{
     extract::{Multipart, State},
     http::StatusCode,
     response::IntoResponse,
-    Json,
+    Extension, Json,
 };
 use serde_json::{json, Value};
 use tracing::{debug, info};
@@ -11,11 +11,13 @@ use uuid::Uuid;
 use crate::proxy::{
     audio::AudioProcessor,
     server::AppState,
+    AccountAllowlist,
 };
 
 /// 处理音频转录请求 (OpenAI Whisper API 兼容)
 pub async fn handle_audio_transcription(
     State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
     mut multipart: Multipart,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     let mut audio_data: Option<Vec<u8>> = None;
@@ -100,8 +102,8 @@ pub async fn handle_audio_transcription(
 
     // 6. 获取 Token 和上游客户端
     let token_manager = state.token_manager;
-    let (access_token, project_id, email) = token_manager
-        .get_token("text", false, None)
+    let (access_token, project_id, email, account_proxy) = token_manager
+        .get_token("text", false, None, allowlist.as_deref())
         .await
         .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e))?;
 
@@ -120,7 +122,7 @@ pub async fn handle_audio_transcription(
     // 8. 发送请求到 Gemini
     let upstream = state.upstream.clone();
     let response = upstream
-        .call_v1_internal("generateContent", &access_token, wrapped_body, None)
+        .call_v1_internal("generateContent", &access_token, wrapped_body, None, account_proxy.as_deref())
         .await
         .map_err(|e| (StatusCode::BAD_GATEWAY, format!("上游请求失败: {}", e)))?;
 
@@ -2,9 +2,10 @@
 
 use axum::{
     body::Body,
-    extract::{Json, State},
+    extract::{Json, Path, State},
     http::{header, StatusCode},
     response::{IntoResponse, Response},
+    Extension,
 };
 use bytes::Bytes;
 use futures::StreamExt;
@@ -16,7 +17,13 @@ use crate::proxy::mappers::claude::{
     transform_claude_request_in, transform_response, create_claude_sse_stream, ClaudeRequest,
     close_tool_loop_for_thinking,
 };
+use crate::proxy::common::usage_headers::with_usage_headers;
+use crate::proxy::model_stats::{estimate_request_tokens, track_stream_usage, TokenUsage, UsageRecorder};
 use crate::proxy::server::AppState;
+use crate::proxy::session_manager::{SessionManager, ANTHROPIC_SESSION_HEADER};
+use crate::proxy::upstream::stream_timeout::{inject_timeout_event, with_stream_timeouts};
+use crate::proxy::AccountAllowlist;
+use crate::proxy::upstream::backoff::ExponentialBackoff;
 use axum::http::HeaderMap;
 use std::sync::atomic::Ordering;
 
@@ -28,9 +35,14 @@ const MIN_SIGNATURE_LENGTH: usize = 10;  // 最小有效签名长度
 const BACKGROUND_MODEL_LITE: &str = "gemini-2.5-flash-lite";  // For simple/lightweight tasks
 const BACKGROUND_MODEL_STANDARD: &str = "gemini-2.5-flash";   // For complex background tasks
 
-// ===== Jitter Configuration (REMOVED) =====
-// Jitter was causing connection instability, reverted to fixed delays
-// const JITTER_FACTOR: f64 = 0.2;
+// ===== Anthropic 协议头 =====
+const MIN_ANTHROPIC_VERSION: &str = "2023-06-01";
+const INTERLEAVED_THINKING_BETA_PREFIX: &str = "interleaved-thinking";
+/// 上游 Gemini 接口版本 (仅用于日志)
+const UPSTREAM_API_VERSION: &str = "v1internal";
+
+// ===== Jitter Configuration =====
+// Jitter is now bounded and configurable via ProxyConfig.retry_jitter_ms (0 disables it)
 
 // ===== Thinking 块处理辅助函数 =====
 
@@ -162,8 +174,7 @@ fn remove_trailing_unsigned_thinking(blocks: &mut Vec<ContentBlock>) {
 
 // ===== 统一退避策略模块 =====
 
-// [REMOVED] apply_jitter function
-// Jitter logic removed to restore stability (v3.3.16 fix)
+// 退避参数统一由 upstream::backoff::ExponentialBackoff 计算
 
 /// 重试策略枚举
 #[derive(Debug, Clone)]
@@ -172,10 +183,8 @@ enum RetryStrategy {
     NoRetry,
     /// 固定延迟
     FixedDelay(Duration),
-    /// 线性退避：base_ms * (attempt + 1)
-    LinearBackoff { base_ms: u64 },
-    /// 指数退避：base_ms * 2^attempt，上限 max_ms
-    ExponentialBackoff { base_ms: u64, max_ms: u64 },
+    /// 指数退避 + 抖动：base * 2^attempt + jitter，上限 max (参数来自 ProxyConfig)
+    ExponentialBackoff(ExponentialBackoff),
 }
 
 /// 根据错误状态码和错误信息确定重试策略
@@ -183,6 +192,7 @@ fn determine_retry_strategy(
     status_code: u16,
     error_text: &str,
     retried_without_thinking: bool,
+    backoff: &ExponentialBackoff,
 ) -> RetryStrategy {
     match status_code {
         // 400 错误：Thinking 签名失败
@@ -202,25 +212,13 @@ fn determine_retry_strategy(
                 let actual_delay = delay_ms.saturating_add(200).min(10_000);
                 RetryStrategy::FixedDelay(Duration::from_millis(actual_delay))
             } else {
-                // 否则使用线性退避：1s, 2s, 3s
-                RetryStrategy::LinearBackoff { base_ms: 1000 }
+                // 否则使用指数退避
+                RetryStrategy::ExponentialBackoff(*backoff)
             }
         }
 
-        // 503 服务不可用 / 529 服务器过载
-        503 | 529 => {
-            // 指数退避：1s, 2s, 4s, 8s
-            RetryStrategy::ExponentialBackoff {
-                base_ms: 1000,
-                max_ms: 8000,
-            }
-        }
-
-        // 500 服务器内部错误
-        500 => {
-            // 线性退避：500ms, 1s, 1.5s
-            RetryStrategy::LinearBackoff { base_ms: 500 }
-        }
+        // 5xx 服务器错误 / 529 服务器过载：指数退避
+        500 | 502 | 503 | 529 => RetryStrategy::ExponentialBackoff(*backoff),
 
         // 401/403 认证/权限错误：可重试（轮换账号）
         401 | 403 => RetryStrategy::FixedDelay(Duration::from_millis(100)),
@@ -257,31 +255,17 @@ async fn apply_retry_strategy(
             true
         }
 
-        RetryStrategy::LinearBackoff { base_ms } => {
-            let calculated_ms = base_ms * (attempt as u64 + 1);
-            info!(
-                "[{}] ⏱️  Retry with linear backoff: status={}, attempt={}/{}, base={}ms",
-                trace_id,
-                status_code,
-                attempt + 1,
-                MAX_RETRY_ATTEMPTS,
-                calculated_ms
-            );
-            sleep(Duration::from_millis(calculated_ms)).await;
-            true
-        }
-
-        RetryStrategy::ExponentialBackoff { base_ms, max_ms } => {
-            let calculated_ms = (base_ms * 2_u64.pow(attempt as u32)).min(max_ms);
+        RetryStrategy::ExponentialBackoff(backoff) => {
+            let delay = backoff.delay_for(attempt);
             info!(
                 "[{}] ⏱️  Retry with exponential backoff: status={}, attempt={}/{}, base={}ms",
                 trace_id,
                 status_code,
                 attempt + 1,
                 MAX_RETRY_ATTEMPTS,
-                calculated_ms
+                delay.as_millis()
             );
-            sleep(Duration::from_millis(calculated_ms)).await;
+            sleep(delay).await;
             true
         }
     }
@@ -293,7 +277,7 @@ fn should_rotate_account(status_code: u16) -> bool {
         // 这些错误是账号级别的，需要轮换
         429 | 401 | 403 | 500 => true,
         // 这些错误是服务端级别的，轮换账号无意义
-        400 | 503 | 529 => false,
+        400 | 502 | 503 | 529 => false,
         // 其他错误默认不轮换
         _ => false,
     }
@@ -306,6 +290,7 @@ fn should_rotate_account(status_code: u16) -> bool {
 /// 处理 Chat 消息请求流程
 pub async fn handle_messages(
     State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
     headers: HeaderMap,
     Json(body): Json<Value>,
 ) -> Response {
@@ -396,9 +381,78 @@ pub async fn handle_messages(
         .await;
     }
     
+    // 为对话分配 anthropic-session-id 并在响应中回显 (客户端携带时沿用)
+    let anthropic_session_id = state.anthropic_sessions.resolve(
+        headers.get(ANTHROPIC_SESSION_HEADER).and_then(|v| v.to_str().ok()),
+        &SessionManager::extract_session_id(&request),
+    );
+    let response = handle_google_messages(state, allowlist, headers, request, trace_id, &anthropic_session_id).await;
+    with_anthropic_session_header(response, &anthropic_session_id)
+}
+
+/// Google Flow: 模型路由、账号选择与上游请求 (含重试)
+async fn handle_google_messages(
+    state: AppState,
+    allowlist: Option<Extension<AccountAllowlist>>,
+    headers: HeaderMap,
+    mut request: crate::proxy::mappers::claude::models::ClaudeRequest,
+    trace_id: String,
+    anthropic_session_id: &str,
+) -> Response {
     // Google Flow 继续使用 request 对象
     // (后续代码不需要再次 filter_invalid_thinking_blocks)
 
+    // 解析 anthropic-version / anthropic-beta 请求头
+    let anthropic = AnthropicHeaders::from_headers(&headers);
+    if let Some(version) = anthropic.version.as_deref() {
+        if !is_supported_anthropic_version(version) {
+            tracing::warn!(
+                "[{}] anthropic-version '{}' is below minimum supported '{}'",
+                trace_id, version, MIN_ANTHROPIC_VERSION
+            );
+        }
+    }
+    debug!(
+        "[{}] anthropic-version: {:?}, anthropic-beta: {:?}, upstream API: {}",
+        trace_id, anthropic.version, anthropic.betas, UPSTREAM_API_VERSION
+    );
+    // interleaved-thinking beta 作为开启 Thinking 的额外信号 (仅在请求未显式配置 thinking 时生效)
+    if request.thinking.is_none() && anthropic.requests_interleaved_thinking() {
+        debug!("[{}] Enabling thinking from anthropic-beta header", trace_id);
+        request.thinking = Some(crate::proxy::mappers::claude::models::ThinkingConfig {
+            type_: "enabled".to_string(),
+            budget_tokens: None,
+        });
+    }
+
+    // 输入 token 超出 max_input_tokens 时丢弃最早的消息 (保留 system 与最近的消息)
+    let context_truncated = match state.max_input_tokens {
+        Some(limit) => match truncate_oldest_messages(&mut request, limit as u64) {
+            Some((before, after)) => {
+                tracing::warn!(
+                    "[{}] Context truncated: ~{} -> ~{} input tokens (limit {}), {} messages kept",
+                    trace_id, before, after, limit, request.messages.len()
+                );
+                true
+            }
+            None => false,
+        },
+        None => false,
+    };
+
+    // 非流式请求优先查询响应缓存 (按 API Key 隔离，完整请求计入缓存键)
+    let cache_key = if request.stream {
+        None
+    } else {
+        state
+            .response_cache
+            .key_for(crate::proxy::middleware::request_api_key(&headers), &request)
+    };
+    if let Some(cached) = cache_key.as_ref().and_then(|k| state.response_cache.lookup(k)) {
+        info!("[{}] Response cache hit for model {}", trace_id, request.model);
+        return with_context_truncated_header(cached, context_truncated);
+    }
+
     // 获取最新一条“有意义”的消息内容（用于日志记录和后台任务检测）
     // 策略：反向遍历，首先筛选出所有角色为 "user" 的消息，然后从中找到第一条非 "Warmup" 且非空的文本消息
     // 获取最新一条“有意义”的消息内容（用于日志记录和后台任务检测）
@@ -507,13 +561,36 @@ pub async fn handle_messages(
     let mut last_error = String::new();
     let mut retried_without_thinking = false;
     let mut last_email: Option<String> = None;
+
+    // 主模型所有尝试均返回 503 时，使用降级模型再轮询一遍账号池 (需配置 fallback_model_on_503)
+    let fallback_model = state.fallback_model_on_503.clone();
+    let total_attempts = if fallback_model.is_some() { max_attempts * 2 } else { max_attempts };
+    let mut primary_503_count = 0;
+    let mut using_fallback = false;
     
-    for attempt in 0..max_attempts {
+    for attempt in 0..total_attempts {
+        if attempt == max_attempts {
+            if primary_503_count < max_attempts {
+                break;
+            }
+            using_fallback = true;
+            tracing::warn!(
+                "[{}] All {} attempts returned 503, retrying with fallback model: {:?}",
+                trace_id, max_attempts, fallback_model
+            );
+        }
+
         // 2. 模型路由解析
         let mut mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
             &request_for_body.model,
+            &*state.model_aliases.read().await,
             &*state.custom_mapping.read().await,
         );
+        if using_fallback {
+            if let Some(fallback) = &fallback_model {
+                mapped_model = fallback.clone();
+            }
+        }
         
         // 将 Claude 工具转为 Value 数组以便探测联网
         let tools_val: Option<Vec<Value>> = request_for_body.tools.as_ref().map(|list| {
@@ -524,11 +601,19 @@ pub async fn handle_messages(
 
         // 0. 尝试提取 session_id 用于粘性调度 (Phase 2/3)
         // 使用 SessionManager 生成稳定的会话指纹
-        let session_id_str = crate::proxy::session_manager::SessionManager::extract_session_id(&request_for_body);
+        // 请求头显式指定的会话 ID 优先，其次为 metadata.user_id / 内容指纹
+        let session_id_str = SessionManager::extract_header_session_id(&headers)
+            .unwrap_or_else(|| anthropic_session_id.to_string());
         let session_id = Some(session_id_str.as_str());
+        // 粘性绑定已失效时，按会话缓存中记录的账号恢复
+        if let Some(account) = state.anthropic_sessions.last_account(&session_id_str) {
+            token_manager.restore_session_binding(&session_id_str, &account);
+        }
 
         let force_rotate_token = attempt > 0;
-        let (access_token, project_id, email) = match token_manager.get_token(&config.request_type, force_rotate_token, session_id).await {
+        // Haiku 等延迟敏感请求优先选择近期响应最快的账号
+        let priority = crate::proxy::common::model_mapping::request_priority_for_model(&request_for_body.model);
+        let (access_token, project_id, email, account_proxy) = match token_manager.get_token_with_priority(&config.request_type, force_rotate_token, session_id, allowlist.as_deref(), priority).await {
             Ok(t) => t,
             Err(e) => {
                 let safe_message = if e.contains("invalid_grant") {
@@ -550,6 +635,7 @@ pub async fn handle_messages(
         };
 
         last_email = Some(email.clone());
+        state.anthropic_sessions.record_account(anthropic_session_id, &email);
         info!("✓ Using account: {} (type: {})", email, config.request_type);
         
         
@@ -616,7 +702,11 @@ pub async fn handle_messages(
         // 生成 Trace ID (简单用时间戳后缀)
         // let _trace_id = format!("req_{}", chrono::Utc::now().timestamp_subsec_millis());
 
-        let gemini_body = match transform_claude_request_in(&request_with_mapped, &project_id) {
+        let gemini_body = match transform_claude_request_in(
+            &request_with_mapped,
+            &project_id,
+            token_manager.get_system_prompt_prefix(&email).as_deref(),
+        ) {
             Ok(b) => {
                 debug!("[{}] Transformed Gemini Body: {}", trace_id, serde_json::to_string_pretty(&b).unwrap_or_default());
                 b
@@ -648,14 +738,23 @@ pub async fn handle_messages(
     let method = if actual_stream { "streamGenerateContent" } else { "generateContent" };
     let query = if actual_stream { Some("alt=sse") } else { None };
 
+    let usage = UsageRecorder::new(
+        &state.model_stats,
+        &state.account_stats,
+        &request_with_mapped.model,
+        &email,
+        estimate_request_tokens(&gemini_body),
+    );
     let response = match upstream.call_v1_internal(
         method,
         &access_token,
         gemini_body,
-        query
+        query,
+        account_proxy.as_deref(),
     ).await {
             Ok(r) => r,
             Err(e) => {
+                usage.record_response(false);
                 last_error = e.clone();
                 debug!("Request failed on attempt {}/{}: {}", attempt + 1, max_attempts, e);
                 continue;
@@ -663,6 +762,7 @@ pub async fn handle_messages(
         };
         
         let status = response.status();
+        usage.record_response(status.is_success());
         
         // 成功
         if status.is_success() {
@@ -671,8 +771,8 @@ pub async fn handle_messages(
             
             // 处理流式响应
             if actual_stream {
-                let stream = response.bytes_stream();
-                let gemini_stream = Box::pin(stream);
+                let stream = track_stream_usage(response.bytes_stream(), usage.clone());
+                let (gemini_stream, timeout_signal) = with_stream_timeouts(stream, state.stream_timeouts);
                 let mut claude_stream = create_claude_sse_stream(gemini_stream, trace_id.clone(), email.clone());
 
                 // [FIX #530/#529] Peek first chunk to detect empty response and allow retry
@@ -700,15 +800,20 @@ pub async fn handle_messages(
                         // 判断客户端期望的格式
                         if client_wants_stream {
                             // 客户端本就要 Stream，直接返回 SSE
-                            return Response::builder()
+                            let response = Response::builder()
                                 .status(StatusCode::OK)
                                 .header(header::CONTENT_TYPE, "text/event-stream")
                                 .header(header::CACHE_CONTROL, "no-cache")
                                 .header(header::CONNECTION, "keep-alive")
                                 .header("X-Account-Email", &email)
                                 .header("X-Mapped-Model", &request_with_mapped.model)
-                                .body(Body::from_stream(combined_stream))
+                                .body(Body::from_stream(state.active_streams.track(state.stream_recorder.record(
+                                    "claude",
+                                    &request_for_body.model,
+                                    inject_timeout_event(combined_stream, timeout_signal),
+                                ))))
                                 .unwrap();
+                            return with_context_truncated_header(with_model_fallback_header(response, using_fallback), context_truncated);
                         } else {
                             // 客户端要非 Stream，需要收集完整响应并转换为 JSON
                             use crate::proxy::mappers::claude::collect_stream_to_json;
@@ -716,13 +821,20 @@ pub async fn handle_messages(
                             match collect_stream_to_json(combined_stream).await {
                                 Ok(full_response) => {
                                     info!("[{}] ✓ Stream collected and converted to JSON", trace_id);
-                                    return Response::builder()
+                                    state.response_cache.store_json(cache_key, &full_response);
+                                    let response = Response::builder()
                                         .status(StatusCode::OK)
                                         .header(header::CONTENT_TYPE, "application/json")
                                         .header("X-Account-Email", &email)
                                         .header("X-Mapped-Model", &request_with_mapped.model)
                                         .body(Body::from(serde_json::to_string(&full_response).unwrap()))
                                         .unwrap();
+                                    let response = with_usage_headers(
+                                        response,
+                                        state.include_usage_headers,
+                                        usage.observed(),
+                                    );
+                                    return with_context_truncated_header(with_model_fallback_header(response, using_fallback), context_truncated);
                                 }
                                 Err(e) => {
                                     return (StatusCode::INTERNAL_SERVER_ERROR, format!("Stream collection error: {}", e)).into_response();
@@ -757,6 +869,8 @@ pub async fn handle_messages(
                     Ok(v) => v,
                     Err(e) => return (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)).into_response(),
                 };
+                let token_usage = TokenUsage::from_response(&gemini_resp);
+                usage.record_tokens(token_usage);
 
                 // 解包 response 字段（v1internal 格式）
                 let raw = gemini_resp.get("response").unwrap_or(&gemini_resp);
@@ -789,7 +903,14 @@ pub async fn handle_messages(
                     cache_info
                 );
 
-                return (StatusCode::OK, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", request_with_mapped.model.as_str())], Json(claude_response)).into_response();
+                state.response_cache.store_json(cache_key, &claude_response);
+                let response = (StatusCode::OK, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", request_with_mapped.model.as_str())], Json(claude_response)).into_response();
+                let response = with_usage_headers(
+                    response,
+                    state.include_usage_headers,
+                    Some(token_usage),
+                );
+                return with_context_truncated_header(with_model_fallback_header(response, using_fallback), context_truncated);
             }
         }
         
@@ -801,12 +922,18 @@ pub async fn handle_messages(
         let error_text = response.text().await.unwrap_or_else(|_| format!("HTTP {}", status));
         last_error = format!("HTTP {}: {}", status_code, error_text);
         debug!("[{}] Upstream Error Response: {}", trace_id, error_text);
+        if status_code == 503 && !using_fallback {
+            primary_503_count += 1;
+        }
         
         // 3. 标记限流状态(用于 UI 显示) - 使用异步版本以支持实时配额刷新
         // 🆕 传入实际使用的模型,实现模型级别限流,避免不同模型配额互相影响
         if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 500 {
             token_manager.mark_rate_limited_async(&email, status_code, retry_after.as_deref(), &error_text, Some(&request_with_mapped.model)).await;
         }
+        if status_code == 401 || status_code == 403 {
+            token_manager.mark_circuit_failure(&email, status_code, &error_text);
+        }
 
         // 4. 处理 400 错误 (Thinking 签名失效)
         // 由于已经主动过滤,这个错误应该很少发生
@@ -857,7 +984,7 @@ pub async fn handle_messages(
             }
             
             // 使用统一退避策略
-            let strategy = determine_retry_strategy(status_code, &error_text, retried_without_thinking);
+            let strategy = determine_retry_strategy(status_code, &error_text, retried_without_thinking, &state.retry_backoff);
             if apply_retry_strategy(strategy, attempt, status_code, &trace_id).await {
                 continue;
             }
@@ -869,7 +996,7 @@ pub async fn handle_messages(
         
         
         // 确定重试策略
-        let strategy = determine_retry_strategy(status_code, &error_text, retried_without_thinking);
+        let strategy = determine_retry_strategy(status_code, &error_text, retried_without_thinking, &state.retry_backoff);
         
         // 执行退避
         if apply_retry_strategy(strategy, attempt, status_code, &trace_id).await {
@@ -885,12 +1012,13 @@ pub async fn handle_messages(
         }
     }
     
+    let attempts_made = if using_fallback { total_attempts } else { max_attempts };
     if let Some(email) = last_email {
         (StatusCode::TOO_MANY_REQUESTS, [("X-Account-Email", email)], Json(json!({
             "type": "error",
             "error": {
                 "type": "overloaded_error",
-                "message": format!("All {} attempts failed. Last error: {}", max_attempts, last_error)
+                "message": format!("All {} attempts failed. Last error: {}", attempts_made, last_error)
             }
         }))).into_response()
     } else {
@@ -898,7 +1026,7 @@ pub async fn handle_messages(
             "type": "error",
             "error": {
                 "type": "overloaded_error",
-                "message": format!("All {} attempts failed. Last error: {}", max_attempts, last_error)
+                "message": format!("All {} attempts failed. Last error: {}", attempts_made, last_error)
             }
         }))).into_response()
     }
@@ -909,6 +1037,7 @@ pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoRespo
     use crate::proxy::common::model_mapping::get_all_dynamic_models;
 
     let model_ids = get_all_dynamic_models(
+        &state.model_aliases,
         &state.custom_mapping,
     ).await;
 
@@ -927,9 +1056,40 @@ pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoRespo
     }))
 }
 
-/// 计算 tokens (占位符)
+/// 单个模型的信息与能力 (Anthropic 格式，GET /v1/models/{model_id} 携带 anthropic-version 时)
+pub async fn handle_get_model(State(state): State<AppState>, Path(model_id): Path<String>) -> Response {
+    let Some((mapped_model, capabilities)) = crate::proxy::handlers::common::resolve_model_info(&state, &model_id).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "type": "error",
+                "error": {
+                    "type": "not_found_error",
+                    "message": format!("model: {}", model_id)
+                }
+            })),
+        )
+            .into_response();
+    };
+    Json(crate::proxy::handlers::common::with_model_capabilities(
+        json!({
+            "type": "model",
+            "id": model_id,
+            "display_name": model_id,
+            "created_at": "2024-02-01T00:00:00Z",
+            "mapped_model": mapped_model,
+        }),
+        &capabilities,
+    ))
+    .into_response()
+}
+
+/// 计算 tokens
+///
+/// 将 Claude 请求转换为 Gemini 格式后调用上游 countTokens，失败时回退到本地估算
 pub async fn handle_count_tokens(
     State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
     headers: HeaderMap,
     Json(body): Json<Value>,
 ) -> Response {
@@ -947,9 +1107,63 @@ pub async fn handle_count_tokens(
         .await;
     }
 
+    let mut request: ClaudeRequest = match serde_json::from_value(body) {
+        Ok(r) => r,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "type": "error",
+                    "error": {
+                        "type": "invalid_request_error",
+                        "message": format!("Invalid request body: {}", e)
+                    }
+                }))
+            ).into_response();
+        }
+    };
+    filter_invalid_thinking_blocks(&mut request.messages);
+
+    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        &request.model,
+        &*state.model_aliases.read().await,
+        &*state.custom_mapping.read().await,
+    );
+    let tools_val: Option<Vec<Value>> = request.tools.as_ref().map(|list| {
+        list.iter().map(|t| serde_json::to_value(t).unwrap_or(json!({}))).collect()
+    });
+    let config = crate::proxy::mappers::common_utils::resolve_request_config(&request.model, &mapped_model, &tools_val);
+    request.model = mapped_model;
+
+    // project_id 仅用于包装，countTokens 不需要
+    let gemini_body = match transform_claude_request_in(&request, "", None) {
+        Ok(b) => b,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "type": "error",
+                    "error": {
+                        "type": "api_error",
+                        "message": format!("Transform error: {}", e)
+                    }
+                }))
+            ).into_response();
+        }
+    };
+    let inner_request = gemini_body.get("request").unwrap_or(&gemini_body);
+
+    let input_tokens = crate::proxy::handlers::common::count_tokens_with_fallback(
+        &state,
+        &config.final_model,
+        &config.request_type,
+        inner_request,
+        allowlist.as_deref(),
+    )
+    .await;
+
     Json(json!({
-        "input_tokens": 0,
-        "output_tokens": 0
+        "input_tokens": input_tokens
     }))
     .into_response()
 }
@@ -967,6 +1181,133 @@ mod tests {
 }
 */
 
+/// 使用了 503 降级模型时附加 X-Model-Fallback 响应头
+fn with_model_fallback_header(mut response: Response, used_fallback: bool) -> Response {
+    if used_fallback {
+        response
+            .headers_mut()
+            .insert("X-Model-Fallback", header::HeaderValue::from_static("true"));
+    }
+    response
+}
+
+/// 回显 anthropic-session-id 响应头
+fn with_anthropic_session_header(mut response: Response, session_id: &str) -> Response {
+    if let Ok(value) = header::HeaderValue::from_str(session_id) {
+        response.headers_mut().insert(ANTHROPIC_SESSION_HEADER, value);
+    }
+    response
+}
+
+/// 请求上下文被截断时附加 X-Context-Truncated 响应头
+fn with_context_truncated_header(mut response: Response, truncated: bool) -> Response {
+    if truncated {
+        response
+            .headers_mut()
+            .insert("X-Context-Truncated", header::HeaderValue::from_static("true"));
+    }
+    response
+}
+
+/// 估算单个 Claude 字段 (system / message / tools) 的 token 数，口径与上游请求估算一致
+fn estimate_claude_tokens<T: serde::Serialize>(key: &str, value: &T) -> u64 {
+    let mut inner = serde_json::Map::new();
+    inner.insert(key.to_string(), serde_json::to_value(value).unwrap_or(Value::Null));
+    crate::proxy::mappers::gemini::estimate_tokens(&Value::Object(inner))
+}
+
+/// 消息是否可以作为截断后的第一条 (user 角色且不含孤立的 tool_result)
+fn is_clean_user_turn(message: &crate::proxy::mappers::claude::models::Message) -> bool {
+    use crate::proxy::mappers::claude::models::{ContentBlock, MessageContent};
+    message.role == "user"
+        && match &message.content {
+            MessageContent::String(_) => true,
+            MessageContent::Array(blocks) => !blocks
+                .iter()
+                .any(|b| matches!(b, ContentBlock::ToolResult { .. })),
+        }
+}
+
+/// 输入 token 估算超过上限时按 FIFO 丢弃最早的消息，system 与最后一条消息始终保留。
+/// 截断边界会继续后移到下一条干净的 user 消息，避免留下孤立的 tool_result；找不到时不截断。
+/// 发生截断时返回 (截断前估算, 截断后估算)
+fn truncate_oldest_messages(request: &mut ClaudeRequest, max_input_tokens: u64) -> Option<(u64, u64)> {
+    let fixed = estimate_claude_tokens("systemInstruction", &request.system)
+        + estimate_claude_tokens("tools", &request.tools);
+    let per_message: Vec<u64> = request
+        .messages
+        .iter()
+        .map(|m| estimate_claude_tokens("contents", &[m]))
+        .collect();
+    let original = fixed + per_message.iter().sum::<u64>();
+    if original <= max_input_tokens || request.messages.len() <= 1 {
+        return None;
+    }
+
+    let last = request.messages.len() - 1;
+    let mut current = original;
+    let mut drop = 0;
+    while drop < last && current > max_input_tokens {
+        current -= per_message[drop];
+        drop += 1;
+    }
+    while drop < last && !is_clean_user_turn(&request.messages[drop]) {
+        current -= per_message[drop];
+        drop += 1;
+    }
+    // 找不到干净的 user 起点时放弃截断，避免留下缺少 tool_use 的 tool_result
+    if !is_clean_user_turn(&request.messages[drop]) {
+        return None;
+    }
+
+    request.messages.drain(..drop);
+    Some((original, current))
+}
+
+// ===== Anthropic 协议头辅助函数 =====
+
+/// 客户端发送的 Anthropic 协议头
+#[derive(Debug, Default, PartialEq)]
+struct AnthropicHeaders {
+    version: Option<String>,
+    betas: Vec<String>,
+}
+
+impl AnthropicHeaders {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let version = headers
+            .get("anthropic-version")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        // anthropic-beta 可多次出现或以逗号分隔
+        let betas = headers
+            .get_all("anthropic-beta")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|b| b.trim().to_string())
+            .filter(|b| !b.is_empty())
+            .collect();
+        Self { version, betas }
+    }
+
+    fn requests_interleaved_thinking(&self) -> bool {
+        self.betas
+            .iter()
+            .any(|b| b.starts_with(INTERLEAVED_THINKING_BETA_PREFIX))
+    }
+}
+
+/// anthropic-version 为 YYYY-MM-DD 格式，按日期比较
+fn is_supported_anthropic_version(version: &str) -> bool {
+    let parse = |v: &str| chrono::NaiveDate::parse_from_str(v, "%Y-%m-%d").ok();
+    match (parse(version), parse(MIN_ANTHROPIC_VERSION)) {
+        (Some(v), Some(min)) => v >= min,
+        _ => false,
+    }
+}
+
 // ===== 后台任务检测辅助函数 =====
 
 /// 后台任务类型
@@ -1246,3 +1587,79 @@ fn create_warmup_response(request: &ClaudeRequest, is_stream: bool) -> Response
         ).into_response()
     }
 }
+
+#[cfg(test)]
+mod anthropic_header_tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_model_fallback_header() {
+        let marked = with_model_fallback_header(Response::new(Body::empty()), true);
+        assert_eq!(marked.headers().get("X-Model-Fallback").unwrap(), "true");
+        let plain = with_model_fallback_header(Response::new(Body::empty()), false);
+        assert!(plain.headers().get("X-Model-Fallback").is_none());
+    }
+
+    #[test]
+    fn test_truncate_oldest_messages() {
+        let long = "x".repeat(4000);
+        let mut request: ClaudeRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "system": "keep me",
+            "messages": [
+                {"role": "user", "content": long},
+                {"role": "assistant", "content": [{"type": "tool_use", "id": "t1", "name": "ls", "input": {}}]},
+                {"role": "user", "content": [{"type": "tool_result", "tool_use_id": "t1", "content": long}]},
+                {"role": "assistant", "content": long},
+                {"role": "user", "content": "latest question"}
+            ]
+        }))
+        .unwrap();
+
+        assert!(truncate_oldest_messages(&mut request.clone(), 1_000_000).is_none());
+
+        let (before, after) = truncate_oldest_messages(&mut request, 1500).unwrap();
+        assert!(before > after);
+        // 丢弃到 tool_result 之后，首条消息必须是干净的 user 消息
+        assert_eq!(request.messages.len(), 1);
+        assert_eq!(request.messages[0].role, "user");
+        assert!(request.system.is_some());
+
+        // 最后一条是 tool_result 且之前没有干净的 user 消息时不截断
+        let mut tool_tail: ClaudeRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [
+                {"role": "user", "content": long},
+                {"role": "assistant", "content": [{"type": "tool_use", "id": "t1", "name": "ls", "input": {}}]},
+                {"role": "user", "content": [{"type": "tool_result", "tool_use_id": "t1", "content": long}]}
+            ]
+        }))
+        .unwrap();
+        assert!(truncate_oldest_messages(&mut tool_tail, 1500).is_none());
+        assert_eq!(tool_tail.messages.len(), 3);
+
+        let marked = with_context_truncated_header(Response::new(Body::empty()), true);
+        assert_eq!(marked.headers().get("X-Context-Truncated").unwrap(), "true");
+    }
+
+    #[test]
+    fn test_anthropic_headers_parsing() {
+        let mut headers = HeaderMap::new();
+        headers.insert("anthropic-version", HeaderValue::from_static("2023-06-01"));
+        headers.append(
+            "anthropic-beta",
+            HeaderValue::from_static("prompt-caching-2024-07-31, interleaved-thinking-2025-05-14"),
+        );
+        let parsed = AnthropicHeaders::from_headers(&headers);
+        assert_eq!(parsed.version.as_deref(), Some("2023-06-01"));
+        assert_eq!(parsed.betas.len(), 2);
+        assert!(parsed.requests_interleaved_thinking());
+
+        assert!(!AnthropicHeaders::from_headers(&HeaderMap::new()).requests_interleaved_thinking());
+
+        assert!(is_supported_anthropic_version("2023-06-01"));
+        assert!(!is_supported_anthropic_version("2023-01-01"));
+        assert!(!is_supported_anthropic_version("latest"));
+    }
+}
@@ -1,6 +1,37 @@
 use axum::{extract::State, extract::Json, http::StatusCode, response::IntoResponse};
 use serde_json::{json, Value};
 use crate::proxy::server::AppState;
+use crate::proxy::AccountAllowlist;
+
+const COUNT_TOKENS_MAX_ATTEMPTS: usize = 3;
+
+/// 解析客户端模型 ID 对应的上游模型与能力，未知模型返回 None
+pub async fn resolve_model_info(
+    state: &AppState,
+    model_id: &str,
+) -> Option<(String, crate::proxy::common::model_mapping::ModelCapabilities)> {
+    use crate::proxy::common::model_mapping::{get_all_dynamic_models, model_capabilities, resolve_model_route};
+
+    let known = get_all_dynamic_models(&state.model_aliases, &state.custom_mapping).await;
+    if !known.iter().any(|id| id == model_id) {
+        return None;
+    }
+    let mapped_model = resolve_model_route(
+        model_id,
+        &*state.model_aliases.read().await,
+        &*state.custom_mapping.read().await,
+    );
+    let capabilities = model_capabilities(&mapped_model)?;
+    Some((mapped_model, capabilities))
+}
+
+/// 将能力字段合并到模型信息 JSON 中
+pub fn with_model_capabilities(mut body: Value, capabilities: &crate::proxy::common::model_mapping::ModelCapabilities) -> Value {
+    if let (Some(obj), Ok(Value::Object(extra))) = (body.as_object_mut(), serde_json::to_value(capabilities)) {
+        obj.extend(extra);
+    }
+    body
+}
 
 /// Detects model capabilities and configuration
 /// POST /v1/models/detect
@@ -17,6 +48,7 @@ pub async fn handle_detect_model(
     // 1. Resolve mapping
     let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
         model_name,
+        &*state.model_aliases.read().await,
         &*state.custom_mapping.read().await,
     );
 
@@ -46,3 +78,74 @@ pub async fn handle_detect_model(
 
     Json(response).into_response()
 }
+
+/// 调用上游 countTokens 计算 token 数，失败时回退到本地估算
+///
+/// 与 `handle_generate` 相同，在 429/5xx/401/403 时轮换账号重试；
+/// 所有尝试失败后返回 `estimate_tokens` 的估算值，避免客户端拿到 0。
+pub async fn count_tokens_with_fallback(
+    state: &AppState,
+    mapped_model: &str,
+    request_type: &str,
+    inner_request: &Value,
+    allowlist: Option<&AccountAllowlist>,
+) -> u64 {
+    use crate::proxy::mappers::gemini::{estimate_tokens, extract_total_tokens, wrap_count_tokens_request};
+
+    let token_manager = &state.token_manager;
+    let max_attempts = COUNT_TOKENS_MAX_ATTEMPTS.min(token_manager.len()).max(1);
+    let count_body = wrap_count_tokens_request(inner_request, mapped_model);
+
+    for attempt in 0..max_attempts {
+        let (access_token, _project_id, email, account_proxy) = match token_manager.get_token(request_type, attempt > 0, None, allowlist).await {
+            Ok(t) => t,
+            Err(e) => {
+                tracing::warn!("[CountTokens] Token error: {}, falling back to estimate", e);
+                break;
+            }
+        };
+
+        let response = match state
+            .upstream
+            .call_v1_internal("countTokens", &access_token, count_body.clone(), None, account_proxy.as_deref())
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                tracing::debug!("[CountTokens] Request failed on attempt {}/{}: {}", attempt + 1, max_attempts, e);
+                continue;
+            }
+        };
+
+        let status = response.status();
+        if status.is_success() {
+            match response.json::<Value>().await {
+                Ok(v) => {
+                    if let Some(total) = extract_total_tokens(&v) {
+                        return total;
+                    }
+                    tracing::warn!("[CountTokens] Upstream response missing totalTokens: {}", v);
+                }
+                Err(e) => tracing::warn!("[CountTokens] Parse error: {}", e),
+            }
+            break;
+        }
+
+        let status_code = status.as_u16();
+        let retry_after = response.headers().get("Retry-After").and_then(|h| h.to_str().ok()).map(|s| s.to_string());
+        let error_text = response.text().await.unwrap_or_else(|_| format!("HTTP {}", status_code));
+
+        if matches!(status_code, 429 | 529 | 503 | 500 | 403 | 401) {
+            token_manager.mark_rate_limited(&email, status_code, retry_after.as_deref(), &error_text);
+            tracing::warn!("[CountTokens] Upstream {} on account {} attempt {}/{}, rotating account", status_code, email, attempt + 1, max_attempts);
+            continue;
+        }
+
+        tracing::warn!("[CountTokens] Upstream non-retryable error {}: {}", status_code, error_text);
+        break;
+    }
+
+    let estimate = estimate_tokens(inner_request);
+    tracing::info!("[CountTokens] Using local estimate: {} tokens (model: {})", estimate, mapped_model);
+    estimate
+}
@@ -1,11 +1,15 @@
 // Gemini Handler
-use axum::{extract::State, extract::{Json, Path}, http::StatusCode, response::IntoResponse};
+use axum::{extract::State, extract::{Json, Path}, http::{HeaderMap, StatusCode}, response::IntoResponse, Extension};
 use serde_json::{json, Value};
 use tracing::{debug, error, info};
 
-use crate::proxy::mappers::gemini::{wrap_request, unwrap_response};
+use crate::proxy::mappers::gemini::{grounding_citations, wrap_request, unwrap_response};
+use crate::proxy::common::usage_headers::with_usage_headers;
+use crate::proxy::model_stats::{estimate_request_tokens, track_stream_usage, TokenUsage, UsageRecorder};
 use crate::proxy::server::AppState;
+use crate::proxy::AccountAllowlist;
 use crate::proxy::session_manager::SessionManager;
+use crate::proxy::upstream::backoff::ExponentialBackoff;
  
 const MAX_RETRY_ATTEMPTS: usize = 3;
  
@@ -13,7 +17,9 @@ const MAX_RETRY_ATTEMPTS: usize = 3;
 /// 路径参数: model_name, method (e.g. "gemini-pro", "generateContent")
 pub async fn handle_generate(
     State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
     Path(model_action): Path<String>,
+    headers: HeaderMap,
     Json(body): Json<Value>
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // 解析 model:method
@@ -25,7 +31,12 @@ pub async fn handle_generate(
 
     crate::modules::logger::log_info(&format!("Received Gemini request: {}/{}", model_name, method));
 
-    // 1. 验证方法
+    // 1. 验证方法 (countTokens 以 "model:countTokens" 形式命中同一路由)
+    if method == "countTokens" {
+        return handle_count_tokens(State(state), allowlist, Path(model_name), Json(body))
+            .await
+            .map(|r| r.into_response());
+    }
     if method != "generateContent" && method != "streamGenerateContent" {
         return Err((StatusCode::BAD_REQUEST, format!("Unsupported method: {}", method)));
     }
@@ -44,6 +55,7 @@ pub async fn handle_generate(
         // 3. 模型路由解析
         let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
             &model_name,
+            &*state.model_aliases.read().await,
             &*state.custom_mapping.read().await,
         );
         // 提取 tools 列表以进行联网探测 (Gemini 风格可能是嵌套的)
@@ -63,10 +75,11 @@ pub async fn handle_generate(
 
         // 4. 获取 Token (使用准确的 request_type)
         // 提取 SessionId (粘性指纹)
-        let session_id = SessionManager::extract_gemini_session_id(&body, &model_name);
+        let session_id = SessionManager::extract_header_session_id(&headers)
+            .unwrap_or_else(|| SessionManager::extract_gemini_session_id(&body, &model_name));
 
         // 关键：在重试尝试 (attempt > 0) 时强制轮换账号
-        let (access_token, project_id, email) = match token_manager.get_token(&config.request_type, attempt > 0, Some(&session_id)).await {
+        let (access_token, project_id, email, account_proxy) = match token_manager.get_token(&config.request_type, attempt > 0, Some(&session_id), allowlist.as_deref()).await {
             Ok(t) => t,
             Err(e) => {
                 return Err((StatusCode::SERVICE_UNAVAILABLE, format!("Token error: {}", e)));
@@ -83,11 +96,19 @@ pub async fn handle_generate(
         let query_string = if is_stream { Some("alt=sse") } else { None };
         let upstream_method = if is_stream { "streamGenerateContent" } else { "generateContent" };
 
+        let usage = UsageRecorder::new(
+            &state.model_stats,
+            &state.account_stats,
+            &mapped_model,
+            &email,
+            estimate_request_tokens(&wrapped_body),
+        );
         let response = match upstream
-            .call_v1_internal(upstream_method, &access_token, wrapped_body, query_string)
+            .call_v1_internal(upstream_method, &access_token, wrapped_body, query_string, account_proxy.as_deref())
             .await {
                 Ok(r) => r,
                 Err(e) => {
+                    usage.record_response(false);
                     last_error = e.clone();
                     debug!("Gemini Request failed on attempt {}/{}: {}", attempt + 1, max_attempts, e);
                     continue;
@@ -95,59 +116,54 @@ pub async fn handle_generate(
             };
 
         let status = response.status();
+        usage.record_response(status.is_success());
         if status.is_success() {
+            token_manager.mark_account_success(&email);
+
             // 6. 响应处理
             if is_stream {
                 use axum::body::Body;
                 use axum::response::Response;
-                use bytes::{Bytes, BytesMut};
+                use bytes::Bytes;
                 use futures::StreamExt;
-                
-                let mut response_stream = response.bytes_stream();
-                let mut buffer = BytesMut::new();
+                use crate::proxy::upstream::sse_parser::SseParser;
+
+                let (upstream_stream, _) = crate::proxy::upstream::stream_timeout::with_stream_timeouts(
+                    track_stream_usage(response.bytes_stream(), usage),
+                    state.stream_timeouts,
+                );
+                let mut events = SseParser::new(upstream_stream);
 
                 let stream = async_stream::stream! {
-                    while let Some(item) = response_stream.next().await {
+                    while let Some(item) = events.next().await {
                         match item {
-                            Ok(bytes) => {
-                                debug!("[Gemini-SSE] Received chunk: {} bytes", bytes.len());
-                                buffer.extend_from_slice(&bytes);
-                                while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
-                                    let line_raw = buffer.split_to(pos + 1);
-                                    if let Ok(line_str) = std::str::from_utf8(&line_raw) {
-                                        let line = line_str.trim();
-                                        if line.is_empty() { continue; }
-                                        
-                                        if line.starts_with("data: ") {
-                                            let json_part = line.trim_start_matches("data: ").trim();
-                                            if json_part == "[DONE]" {
-                                                yield Ok::<Bytes, String>(Bytes::from("data: [DONE]\n\n"));
-                                                continue;
-                                            }
-                                            
-                                            match serde_json::from_str::<Value>(json_part) {
-                                                Ok(mut json) => {
-                                                    // Unwrap v1internal response wrapper
-                                                    if let Some(inner) = json.get_mut("response").map(|v| v.take()) {
-                                                        let new_line = format!("data: {}\n\n", serde_json::to_string(&inner).unwrap_or_default());
-                                                        yield Ok::<Bytes, String>(Bytes::from(new_line));
-                                                    } else {
-                                                        yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&json).unwrap_or_default())));
-                                                    }
-                                                }
-                                                Err(e) => {
-                                                    debug!("[Gemini-SSE] JSON parse error: {}, passing raw line", e);
-                                                    yield Ok::<Bytes, String>(Bytes::from(format!("{}\n\n", line)));
-                                                }
-                                            }
-                                        } else {
-                                            // Non-data lines (comments, etc.)
-                                            yield Ok::<Bytes, String>(Bytes::from(format!("{}\n\n", line)));
+                            Ok(event) => {
+                                let json_part = event.data.trim();
+                                if json_part.is_empty() { continue; }
+                                if json_part == "[DONE]" {
+                                    yield Ok::<Bytes, String>(Bytes::from("data: [DONE]\n\n"));
+                                    continue;
+                                }
+
+                                match serde_json::from_str::<Value>(json_part) {
+                                    Ok(json) => {
+                                        // Unwrap v1internal response wrapper
+                                        let inner = unwrap_response(&json);
+                                        yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&inner).unwrap_or_default())));
+
+                                        // 联网搜索来源: 额外发送 citations 事件，客户端可选择处理
+                                        let citations = grounding_citations(&inner);
+                                        if !citations.is_empty() {
+                                            let event = json!({
+                                                "type": "content_block_delta",
+                                                "delta": {"type": "citations", "citations": citations}
+                                            });
+                                            yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", event)));
                                         }
-                                    } else {
-                                        // Non-UTF8 data? Just pass it through or skip
-                                        debug!("[Gemini-SSE] Non-UTF8 line encountered");
-                                        yield Ok::<Bytes, String>(line_raw.freeze());
+                                    }
+                                    Err(e) => {
+                                        debug!("[Gemini-SSE] JSON parse error: {}, passing raw event", e);
+                                        yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", json_part)));
                                     }
                                 }
                             }
@@ -159,7 +175,7 @@ pub async fn handle_generate(
                     }
                 };
                 
-                let body = Body::from_stream(stream);
+                let body = Body::from_stream(state.active_streams.track(state.stream_recorder.record("gemini", &model_name, stream)));
                 return Ok(Response::builder()
                     .header("Content-Type", "text/event-stream")
                     .header("Cache-Control", "no-cache")
@@ -175,9 +191,16 @@ pub async fn handle_generate(
                 .json()
                 .await
                 .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
+            let token_usage = TokenUsage::from_response(&gemini_resp);
+            usage.record_tokens(token_usage);
 
             let unwrapped = unwrap_response(&gemini_resp);
-            return Ok((StatusCode::OK, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())], Json(unwrapped)).into_response());
+            let response = (StatusCode::OK, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())], Json(unwrapped)).into_response();
+            return Ok(with_usage_headers(
+                response,
+                state.include_usage_headers,
+                Some(token_usage),
+            ));
         }
 
         // 处理错误并重试
@@ -186,10 +209,11 @@ pub async fn handle_generate(
         let error_text = response.text().await.unwrap_or_else(|_| format!("HTTP {}", status_code));
         last_error = format!("HTTP {}: {}", status_code, error_text);
  
-        // 只有 429 (限流), 529 (过载), 503, 403 (权限) 和 401 (认证失效) 触发账号轮换
-        if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 500 || status_code == 403 || status_code == 401 {
+        // 只有 429 (限流), 529 (过载), 5xx, 403 (权限) 和 401 (认证失效) 触发账号轮换
+        if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 502 || status_code == 500 || status_code == 403 || status_code == 401 {
             // 记录限流信息 (全局同步)
             token_manager.mark_rate_limited(&email, status_code, retry_after.as_deref(), &error_text);
+            token_manager.mark_circuit_failure(&email, status_code, &error_text);
 
             // 只有明确包含 "QUOTA_EXHAUSTED" 才停止，避免误判上游的频率限制提示 (如 "check quota")
             if status_code == 429 && error_text.contains("QUOTA_EXHAUSTED") {
@@ -198,6 +222,7 @@ pub async fn handle_generate(
             }
 
             tracing::warn!("Gemini Upstream {} on account {} attempt {}/{}, rotating account", status_code, email, attempt + 1, max_attempts);
+            wait_before_retry(&state.retry_backoff, status_code, &error_text, attempt, max_attempts).await;
             continue;
         }
  
@@ -213,11 +238,33 @@ pub async fn handle_generate(
     }
 }
 
+/// 重试前退避: 优先使用上游 RetryInfo，否则按指数退避等待 (最后一次尝试不等待)
+async fn wait_before_retry(
+    backoff: &ExponentialBackoff,
+    status_code: u16,
+    error_text: &str,
+    attempt: usize,
+    max_attempts: usize,
+) {
+    if attempt + 1 >= max_attempts || !ExponentialBackoff::is_retryable_status(status_code) {
+        return;
+    }
+    if let Some(delay_ms) = crate::proxy::upstream::retry::parse_retry_delay(error_text) {
+        let actual_delay = delay_ms.saturating_add(200).min(10_000);
+        debug!("Gemini retry after upstream RetryInfo delay: {}ms", actual_delay);
+        tokio::time::sleep(tokio::time::Duration::from_millis(actual_delay)).await;
+    } else {
+        let delay = backoff.wait(attempt).await;
+        debug!("Gemini retry after exponential backoff: {}ms", delay.as_millis());
+    }
+}
+
 pub async fn handle_list_models(State(state): State<AppState>) -> Result<impl IntoResponse, (StatusCode, String)> {
     use crate::proxy::common::model_mapping::get_all_dynamic_models;
 
     // 获取所有动态模型列表（与 /v1/models 一致）
     let model_ids = get_all_dynamic_models(
+        &state.model_aliases,
         &state.custom_mapping,
     ).await;
 
@@ -240,17 +287,54 @@ pub async fn handle_list_models(State(state): State<AppState>) -> Result<impl In
     Ok(Json(json!({ "models": models })))
 }
 
-pub async fn handle_get_model(Path(model_name): Path<String>) -> impl IntoResponse {
-    Json(json!({
+pub async fn handle_get_model(State(state): State<AppState>, Path(model_name): Path<String>) -> impl IntoResponse {
+    let mut body = json!({
         "name": format!("models/{}", model_name),
         "displayName": model_name
-    }))
+    });
+
+    // Gemini 原生客户端直接使用上游模型名，未知模型仍返回基础信息以保持兼容
+    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        &model_name,
+        &*state.model_aliases.read().await,
+        &*state.custom_mapping.read().await,
+    );
+    if let Some(capabilities) = crate::proxy::common::model_mapping::model_capabilities(&mapped_model) {
+        body["inputTokenLimit"] = json!(capabilities.context_window);
+        body["outputTokenLimit"] = json!(capabilities.max_output_tokens);
+        body["mapped_model"] = json!(mapped_model);
+        body = crate::proxy::handlers::common::with_model_capabilities(body, &capabilities);
+    }
+    Json(body)
 }
 
-pub async fn handle_count_tokens(State(state): State<AppState>, Path(_model_name): Path<String>, Json(_body): Json<Value>) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let model_group = "gemini";
-    let (_access_token, _project_id, _) = state.token_manager.get_token(model_group, false, None).await
-        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, format!("Token error: {}", e)))?;
-    
-    Ok(Json(json!({"totalTokens": 0})))
+pub async fn handle_count_tokens(
+    State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
+    Path(model_action): Path<String>,
+    Json(body): Json<Value>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    // 路由可能以 "model:countTokens" 或 "model" 形式传入
+    let model_name = model_action
+        .rsplit_once(':')
+        .map(|(m, _)| m.to_string())
+        .unwrap_or(model_action);
+
+    let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+        &model_name,
+        &*state.model_aliases.read().await,
+        &*state.custom_mapping.read().await,
+    );
+    let config = crate::proxy::mappers::common_utils::resolve_request_config(&model_name, &mapped_model, &None);
+
+    let total_tokens = crate::proxy::handlers::common::count_tokens_with_fallback(
+        &state,
+        &config.final_model,
+        &config.request_type,
+        &body,
+        allowlist.as_deref(),
+    )
+    .await;
+
+    Ok(Json(json!({"totalTokens": total_tokens})))
 }
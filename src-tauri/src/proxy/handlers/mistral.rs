@@ -0,0 +1,204 @@
+// Mistral Handler
+// Mistral 兼容层: 复用 OpenAI Chat 转换路径，仅对 Mistral 模型的响应做字段整形
+use axum::{
+    body::Body,
+    extract::{Json, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use bytes::{Bytes, BytesMut};
+use futures::StreamExt;
+use serde_json::Value;
+use tracing::debug;
+
+use crate::proxy::common::model_mapping::is_mistral_model;
+use crate::proxy::server::AppState;
+use crate::proxy::AccountAllowlist;
+
+/// 处理 Chat Completions (Mistral 兼容模式下替代 OpenAI 端点)
+///
+/// 非 Mistral 模型原样走 OpenAI 逻辑；Mistral 模型的响应会移除不支持的字段，
+/// 并将 `tool_calls[].function.arguments` 还原为 JSON 对象。
+pub async fn handle_chat_completions(
+    State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
+    compat: Query<super::openai::CompatQuery>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Result<Response, (StatusCode, String)> {
+    let requested_model = body
+        .get("model")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    let response = super::openai::handle_chat_completions(State(state), allowlist, compat, headers, Json(body))
+        .await?
+        .into_response();
+
+    if !is_mistral_model(&requested_model) || !response.status().is_success() {
+        return Ok(response);
+    }
+
+    debug!("[Mistral] Shaping response for model: {}", requested_model);
+
+    let is_stream = response
+        .headers()
+        .get("Content-Type")
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| ct.starts_with("text/event-stream"))
+        .unwrap_or(false);
+
+    let (mut parts, body) = response.into_parts();
+
+    if is_stream {
+        let mut data_stream = body.into_data_stream();
+        let stream = async_stream::stream! {
+            let mut buffer = BytesMut::new();
+            while let Some(item) = data_stream.next().await {
+                match item {
+                    Ok(bytes) => {
+                        buffer.extend_from_slice(&bytes);
+                        while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+                            let line_raw = buffer.split_to(pos + 1);
+                            yield Ok::<Bytes, String>(shape_sse_line(&line_raw, &requested_model));
+                        }
+                    }
+                    Err(e) => yield Err(format!("Stream error: {}", e)),
+                }
+            }
+            if !buffer.is_empty() {
+                yield Ok::<Bytes, String>(shape_sse_line(&buffer, &requested_model));
+            }
+        };
+        return Ok(Response::from_parts(parts, Body::from_stream(stream)));
+    }
+
+    let bytes = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Read response error: {}", e)))?;
+    let mut json: Value = match serde_json::from_slice(&bytes) {
+        Ok(v) => v,
+        // 非 JSON 响应原样返回
+        Err(_) => return Ok(Response::from_parts(parts, Body::from(bytes))),
+    };
+    shape_mistral_response(&mut json, &requested_model);
+
+    let out = serde_json::to_vec(&json).unwrap_or_default();
+    parts.headers.remove(axum::http::header::CONTENT_LENGTH);
+    Ok(Response::from_parts(parts, Body::from(out)))
+}
+
+/// 整形单行 SSE，非 `data:` JSON 行原样透传
+fn shape_sse_line(line_raw: &[u8], model: &str) -> Bytes {
+    let Ok(line) = std::str::from_utf8(line_raw) else {
+        return Bytes::copy_from_slice(line_raw);
+    };
+    let Some(payload) = line.trim_end().strip_prefix("data: ") else {
+        return Bytes::copy_from_slice(line_raw);
+    };
+    match serde_json::from_str::<Value>(payload) {
+        Ok(mut chunk) => {
+            shape_mistral_response(&mut chunk, model);
+            Bytes::from(format!("data: {}\n", chunk))
+        }
+        Err(_) => Bytes::copy_from_slice(line_raw),
+    }
+}
+
+/// 将 OpenAI 形态的响应 (完整响应或流式分片) 整形为 Mistral 形态
+pub fn shape_mistral_response(resp: &mut Value, model: &str) {
+    let Some(obj) = resp.as_object_mut() else {
+        return;
+    };
+    obj.insert("model".to_string(), Value::String(model.to_string()));
+    obj.remove("system_fingerprint");
+
+    let Some(choices) = obj.get_mut("choices").and_then(|c| c.as_array_mut()) else {
+        return;
+    };
+    for choice in choices {
+        let Some(choice_obj) = choice.as_object_mut() else {
+            continue;
+        };
+        // Mistral 不支持 logprobs
+        choice_obj.remove("logprobs");
+
+        for key in ["message", "delta"] {
+            let Some(msg) = choice_obj.get_mut(key).and_then(|m| m.as_object_mut()) else {
+                continue;
+            };
+            // 思维链字段不属于 Mistral 协议
+            msg.remove("reasoning_content");
+            let Some(tool_calls) = msg.get_mut("tool_calls").and_then(|t| t.as_array_mut()) else {
+                continue;
+            };
+            for call in tool_calls {
+                let Some(args) = call.get_mut("function").and_then(|f| f.get_mut("arguments")) else {
+                    continue;
+                };
+                // 仅在参数为完整 JSON 时还原为对象，流式增量片段保持字符串
+                if let Some(parsed) = args.as_str().and_then(|s| serde_json::from_str::<Value>(s).ok()) {
+                    if parsed.is_object() {
+                        *args = parsed;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_shape_mistral_response_parses_arguments() {
+        let mut resp = json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "model": "gemini-2.5-pro",
+            "system_fingerprint": "fp",
+            "choices": [{
+                "index": 0,
+                "logprobs": null,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "get_weather", "arguments": "{\"city\":\"Paris\"}" }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]
+        });
+
+        shape_mistral_response(&mut resp, "mistral-large-latest");
+
+        assert_eq!(resp["model"], "mistral-large-latest");
+        assert!(resp.get("system_fingerprint").is_none());
+        assert!(resp["choices"][0].get("logprobs").is_none());
+        assert_eq!(
+            resp["choices"][0]["message"]["tool_calls"][0]["function"]["arguments"]["city"],
+            "Paris"
+        );
+    }
+
+    #[test]
+    fn test_shape_sse_line_passthrough() {
+        let done = shape_sse_line(b"data: [DONE]\n", "codestral-latest");
+        assert_eq!(&done[..], b"data: [DONE]\n");
+
+        let chunk = shape_sse_line(
+            b"data: {\"model\":\"x\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"hi\"}}]}\n",
+            "codestral-latest",
+        );
+        let text = std::str::from_utf8(&chunk).unwrap();
+        assert!(text.starts_with("data: "));
+        assert!(text.contains("codestral-latest"));
+    }
+}
@@ -8,4 +8,8 @@ pub mod mcp;
 pub mod common;
 pub mod audio;  // 音频转录处理器 (PR #311)
 pub mod warmup; // 预热处理器
+pub mod ollama; // Ollama 兼容处理器
+pub mod mistral; // Mistral 兼容层
+pub mod admin; // 管理端点 (热重载)
+pub mod response_cache; // 非流式响应缓存
 
@@ -0,0 +1,559 @@
+// Ollama Handler
+// 兼容 Ollama REST 协议 (/api/generate, /api/chat)，复用 OpenAI → Gemini 转换路径
+use axum::{
+    body::Body,
+    extract::{Json, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use bytes::{Bytes, BytesMut};
+use chrono::Utc;
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::pin::Pin;
+use tracing::{debug, error, info};
+
+use crate::proxy::mappers::openai::{
+    transform_openai_request, transform_openai_response, OpenAIContent, OpenAIContentBlock,
+    OpenAIImageUrl, OpenAIMessage, OpenAIRequest, ResponseFormat,
+};
+use crate::proxy::server::AppState;
+use crate::proxy::AccountAllowlist;
+
+const MAX_RETRY_ATTEMPTS: usize = 3;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OllamaOptions {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    /// 最大生成 token 数，-1/-2 表示不限制
+    pub num_predict: Option<i64>,
+    pub stop: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: String,
+    /// Base64 编码的图片 (不带 data: 前缀)
+    #[serde(default)]
+    pub images: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaChatRequest {
+    pub model: String,
+    #[serde(default)]
+    pub messages: Vec<OllamaMessage>,
+    /// Ollama 默认开启流式
+    #[serde(default = "default_stream")]
+    pub stream: bool,
+    #[serde(default)]
+    pub format: Option<Value>,
+    #[serde(default)]
+    pub options: Option<OllamaOptions>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaGenerateRequest {
+    pub model: String,
+    #[serde(default)]
+    pub prompt: String,
+    #[serde(default)]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub images: Option<Vec<String>>,
+    #[serde(default = "default_stream")]
+    pub stream: bool,
+    #[serde(default)]
+    pub format: Option<Value>,
+    #[serde(default)]
+    pub options: Option<OllamaOptions>,
+}
+
+fn default_stream() -> bool {
+    true
+}
+
+/// 响应形态: /api/chat 返回 message，/api/generate 返回 response
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OllamaKind {
+    Chat,
+    Generate,
+}
+
+/// 处理 Ollama Chat API (/api/chat)
+pub async fn handle_ollama_chat(
+    State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
+    Json(body): Json<Value>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let req: OllamaChatRequest = serde_json::from_value(body)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)))?;
+    debug!("Received Ollama chat request for model: {}", req.model);
+
+    let openai_req = chat_to_openai_request(&req);
+    forward_ollama_request(state, allowlist.map(|Extension(a)| a), openai_req, OllamaKind::Chat).await
+}
+
+/// 处理 Ollama Generate API (/api/generate)
+pub async fn handle_ollama_generate(
+    State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
+    Json(body): Json<Value>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let req: OllamaGenerateRequest = serde_json::from_value(body)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)))?;
+    debug!("Received Ollama generate request for model: {}", req.model);
+
+    let openai_req = generate_to_openai_request(&req);
+    forward_ollama_request(state, allowlist.map(|Extension(a)| a), openai_req, OllamaKind::Generate).await
+}
+
+fn to_openai_message(role: &str, text: &str, images: Option<&Vec<String>>) -> OpenAIMessage {
+    let content = match images {
+        Some(images) if !images.is_empty() => {
+            let mut blocks = Vec::with_capacity(images.len() + 1);
+            if !text.is_empty() {
+                blocks.push(OpenAIContentBlock::Text { text: text.to_string() });
+            }
+            for img in images {
+                let url = if img.starts_with("data:") {
+                    img.clone()
+                } else {
+                    format!("data:image/png;base64,{}", img)
+                };
+                blocks.push(OpenAIContentBlock::ImageUrl {
+                    image_url: OpenAIImageUrl { url, detail: None },
+                });
+            }
+            OpenAIContent::Array(blocks)
+        }
+        _ => OpenAIContent::String(text.to_string()),
+    };
+
+    OpenAIMessage {
+        role: role.to_string(),
+        content: Some(content),
+        reasoning_content: None,
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    }
+}
+
+fn build_openai_request(
+    model: &str,
+    messages: Vec<OpenAIMessage>,
+    stream: bool,
+    format: Option<&Value>,
+    options: Option<&OllamaOptions>,
+) -> OpenAIRequest {
+    let options = options.cloned().unwrap_or_default();
+    // num_predict <= 0 (-1 无限 / -2 填满上下文) 交由上游默认值处理
+    let max_tokens = options
+        .num_predict
+        .filter(|n| *n > 0)
+        .map(|n| n.min(u32::MAX as i64) as u32);
+    // format: "json" 或 JSON Schema 均按 JSON 输出处理
+    let response_format = format
+        .filter(|f| !f.is_null() && f.as_str() != Some(""))
+        .map(|_| ResponseFormat { r#type: "json_object".to_string() });
+
+    OpenAIRequest {
+        model: model.to_string(),
+        messages,
+        prompt: None,
+        stream,
+        stream_options: None,
+        n: None,
+        max_tokens,
+        temperature: options.temperature,
+        top_p: options.top_p,
+        stop: options.stop.map(|s| json!(s)),
+        response_format,
+        tools: None,
+        tool_choice: None,
+        parallel_tool_calls: None,
+        instructions: None,
+        input: None,
+        x_gemini_config: None,
+    }
+}
+
+fn chat_to_openai_request(req: &OllamaChatRequest) -> OpenAIRequest {
+    let mut messages: Vec<OpenAIMessage> = req
+        .messages
+        .iter()
+        .map(|m| to_openai_message(&m.role, &m.content, m.images.as_ref()))
+        .collect();
+    if messages.is_empty() {
+        messages.push(to_openai_message("user", " ", None));
+    }
+    build_openai_request(&req.model, messages, req.stream, req.format.as_ref(), req.options.as_ref())
+}
+
+fn generate_to_openai_request(req: &OllamaGenerateRequest) -> OpenAIRequest {
+    let mut messages = Vec::new();
+    if let Some(system) = req.system.as_deref().filter(|s| !s.is_empty()) {
+        messages.push(to_openai_message("system", system, None));
+    }
+    let prompt = if req.prompt.is_empty() { " " } else { req.prompt.as_str() };
+    messages.push(to_openai_message("user", prompt, req.images.as_ref()));
+    build_openai_request(&req.model, messages, req.stream, req.format.as_ref(), req.options.as_ref())
+}
+
+/// 将 Gemini finishReason 映射为 Ollama done_reason
+fn map_done_reason(finish_reason: Option<&str>) -> &'static str {
+    match finish_reason {
+        Some("MAX_TOKENS") => "length",
+        _ => "stop",
+    }
+}
+
+/// 构造 Ollama 响应对象 (流式分片或最终结果)
+fn build_ollama_chunk(
+    kind: OllamaKind,
+    model: &str,
+    text: &str,
+    done: Option<(&str, &Value)>,
+) -> Value {
+    let mut chunk = json!({
+        "model": model,
+        "created_at": Utc::now().to_rfc3339(),
+    });
+    match kind {
+        OllamaKind::Chat => {
+            chunk["message"] = json!({ "role": "assistant", "content": text });
+        }
+        OllamaKind::Generate => {
+            chunk["response"] = json!(text);
+        }
+    }
+    match done {
+        Some((reason, usage)) => {
+            chunk["done"] = json!(true);
+            chunk["done_reason"] = json!(reason);
+            if let Some(n) = usage.get("promptTokenCount").and_then(|v| v.as_u64()) {
+                chunk["prompt_eval_count"] = json!(n);
+            }
+            if let Some(n) = usage.get("candidatesTokenCount").and_then(|v| v.as_u64()) {
+                chunk["eval_count"] = json!(n);
+            }
+        }
+        None => {
+            chunk["done"] = json!(false);
+        }
+    }
+    chunk
+}
+
+/// Gemini SSE → Ollama NDJSON (每行一个 JSON 对象，最后一行 done: true)
+fn create_ollama_ndjson_stream(
+    mut gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
+    model: String,
+    kind: OllamaKind,
+) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> {
+    let mut buffer = BytesMut::new();
+
+    let stream = async_stream::stream! {
+        let mut finish_reason: Option<String> = None;
+        let mut usage = json!({});
+
+        while let Some(item) = gemini_stream.next().await {
+            match item {
+                Ok(bytes) => {
+                    buffer.extend_from_slice(&bytes);
+                    while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+                        let line_raw = buffer.split_to(pos + 1);
+                        let Ok(line_str) = std::str::from_utf8(&line_raw) else { continue };
+                        let line = line_str.trim();
+                        let Some(json_part) = line.strip_prefix("data: ") else { continue };
+                        let Ok(mut json) = serde_json::from_str::<Value>(json_part.trim()) else { continue };
+                        let actual_data = json.get_mut("response").map(|v| v.take()).unwrap_or(json);
+
+                        if let Some(u) = actual_data.get("usageMetadata") {
+                            usage = u.clone();
+                        }
+                        let candidate = actual_data.get("candidates").and_then(|c| c.get(0));
+                        if let Some(f) = candidate.and_then(|c| c.get("finishReason")).and_then(|f| f.as_str()) {
+                            finish_reason = Some(f.to_string());
+                        }
+
+                        let mut content_out = String::new();
+                        if let Some(parts) = candidate
+                            .and_then(|c| c.get("content"))
+                            .and_then(|c| c.get("parts"))
+                            .and_then(|p| p.as_array())
+                        {
+                            for part in parts {
+                                // 思维链不输出到正文
+                                if part.get("thought").and_then(|v| v.as_bool()).unwrap_or(false) {
+                                    continue;
+                                }
+                                if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                                    content_out.push_str(text);
+                                }
+                            }
+                        }
+
+                        if !content_out.is_empty() {
+                            let chunk = build_ollama_chunk(kind, &model, &content_out, None);
+                            yield Ok::<Bytes, String>(Bytes::from(format!("{}\n", chunk)));
+                        }
+                    }
+                }
+                Err(e) => yield Err(format!("Upstream error: {}", e)),
+            }
+        }
+
+        let done_reason = map_done_reason(finish_reason.as_deref());
+        let final_chunk = build_ollama_chunk(kind, &model, "", Some((done_reason, &usage)));
+        yield Ok::<Bytes, String>(Bytes::from(format!("{}\n", final_chunk)));
+    };
+
+    Box::pin(stream)
+}
+
+/// 非流式: Gemini 完整响应 → Ollama 单个 JSON 对象
+fn transform_to_ollama_response(gemini_resp: &Value, model: &str, kind: OllamaKind) -> Value {
+    let openai_resp = transform_openai_response(gemini_resp);
+    let text = openai_resp
+        .choices
+        .first()
+        .and_then(|c| match &c.message.content {
+            Some(OpenAIContent::String(s)) => Some(s.clone()),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let raw = gemini_resp.get("response").unwrap_or(gemini_resp);
+    let finish_reason = raw
+        .get("candidates")
+        .and_then(|c| c.get(0))
+        .and_then(|c| c.get("finishReason"))
+        .and_then(|f| f.as_str());
+    let usage = raw.get("usageMetadata").cloned().unwrap_or_else(|| json!({}));
+
+    build_ollama_chunk(kind, model, &text, Some((map_done_reason(finish_reason), &usage)))
+}
+
+async fn forward_ollama_request(
+    state: AppState,
+    allowlist: Option<AccountAllowlist>,
+    openai_req: OpenAIRequest,
+    kind: OllamaKind,
+) -> Result<Response, (StatusCode, String)> {
+    let upstream = state.upstream.clone();
+    let token_manager = state.token_manager.clone();
+    let pool_size = token_manager.len();
+    let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size).max(1);
+
+    let mut last_error = String::new();
+
+    for attempt in 0..max_attempts {
+        // 1. 模型路由解析
+        let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
+            &openai_req.model,
+            &*state.model_aliases.read().await,
+            &*state.custom_mapping.read().await,
+        );
+        let config = crate::proxy::mappers::common_utils::resolve_request_config(
+            &openai_req.model,
+            &mapped_model,
+            &None,
+        );
+
+        // 2. 获取 Token (重试时强制轮换账号)
+        let (access_token, project_id, email, account_proxy) = match token_manager
+            .get_token(&config.request_type, attempt > 0, None, allowlist.as_ref())
+            .await
+        {
+            Ok(t) => t,
+            Err(e) => {
+                return Err((
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    format!("Token error: {}", e),
+                ));
+            }
+        };
+
+        info!("✓ Using account: {} (type: {})", email, config.request_type);
+
+        // 3. 转换请求 (复用 OpenAI 映射)
+        let gemini_body = transform_openai_request(
+            &openai_req,
+            &project_id,
+            &mapped_model,
+            token_manager.get_system_prompt_prefix(&email).as_deref(),
+        );
+
+        let method = if openai_req.stream {
+            "streamGenerateContent"
+        } else {
+            "generateContent"
+        };
+        let query_string = if openai_req.stream { Some("alt=sse") } else { None };
+
+        let response = match upstream
+            .call_v1_internal(method, &access_token, gemini_body, query_string, account_proxy.as_deref())
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                last_error = e.clone();
+                debug!(
+                    "Ollama Request failed on attempt {}/{}: {}",
+                    attempt + 1,
+                    max_attempts,
+                    e
+                );
+                continue;
+            }
+        };
+
+        let status = response.status();
+        if status.is_success() {
+            token_manager.mark_account_success(&email);
+
+            // 4. 流式: NDJSON
+            if openai_req.stream {
+                let (upstream_stream, _) = crate::proxy::upstream::stream_timeout::with_stream_timeouts(
+                    response.bytes_stream(),
+                    state.stream_timeouts,
+                );
+                let ollama_stream = create_ollama_ndjson_stream(
+                    upstream_stream,
+                    openai_req.model.clone(),
+                    kind,
+                );
+                return Ok(Response::builder()
+                    .header("Content-Type", "application/x-ndjson")
+                    .header("Cache-Control", "no-cache")
+                    .header("X-Account-Email", &email)
+                    .header("X-Mapped-Model", &mapped_model)
+                    .body(Body::from_stream(state.active_streams.track(ollama_stream)))
+                    .unwrap()
+                    .into_response());
+            }
+
+            let gemini_resp: Value = response
+                .json()
+                .await
+                .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
+
+            let ollama_resp = transform_to_ollama_response(&gemini_resp, &openai_req.model, kind);
+            return Ok((
+                StatusCode::OK,
+                [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())],
+                Json(ollama_resp),
+            )
+                .into_response());
+        }
+
+        // 5. 错误处理与重试
+        let status_code = status.as_u16();
+        let retry_after = response.headers().get("Retry-After").and_then(|h| h.to_str().ok()).map(|s| s.to_string());
+        let error_text = response.text().await.unwrap_or_else(|_| format!("HTTP {}", status_code));
+        last_error = format!("HTTP {}: {}", status_code, error_text);
+        error!("[Ollama-Upstream] Error Response {}: {}", status_code, error_text);
+
+        if matches!(status_code, 429 | 529 | 503 | 502 | 500) {
+            token_manager.mark_rate_limited(&email, status_code, retry_after.as_deref(), &error_text);
+            if error_text.contains("QUOTA_EXHAUSTED") {
+                return Ok((status, [("X-Account-Email", email.as_str())], error_text).into_response());
+            }
+            if attempt + 1 < max_attempts
+                && crate::proxy::upstream::backoff::ExponentialBackoff::is_retryable_status(status_code)
+            {
+                let delay = state.retry_backoff.wait(attempt).await;
+                debug!("Ollama retry after exponential backoff: {}ms", delay.as_millis());
+            }
+            continue;
+        }
+
+        if status_code == 403 || status_code == 401 {
+            token_manager.mark_circuit_failure(&email, status_code, &error_text);
+            continue;
+        }
+
+        return Ok((status, [("X-Account-Email", email.as_str())], error_text).into_response());
+    }
+
+    Ok((
+        StatusCode::TOO_MANY_REQUESTS,
+        format!("All accounts exhausted. Last error: {}", last_error),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_request_maps_options() {
+        let req: OllamaChatRequest = serde_json::from_value(json!({
+            "model": "gemini-2.5-flash",
+            "messages": [
+                { "role": "system", "content": "be brief" },
+                { "role": "user", "content": "hi", "images": ["iVBORw0KGgo="] }
+            ],
+            "options": { "temperature": 0.2, "num_predict": 128 }
+        }))
+        .unwrap();
+        assert!(req.stream);
+
+        let openai_req = chat_to_openai_request(&req);
+        assert_eq!(openai_req.messages.len(), 2);
+        assert_eq!(openai_req.temperature, Some(0.2));
+        assert_eq!(openai_req.max_tokens, Some(128));
+        match &openai_req.messages[1].content {
+            Some(OpenAIContent::Array(blocks)) => assert_eq!(blocks.len(), 2),
+            other => panic!("unexpected content: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_generate_request_unlimited_num_predict() {
+        let req: OllamaGenerateRequest = serde_json::from_value(json!({
+            "model": "gemini-2.5-flash",
+            "prompt": "hello",
+            "system": "sys",
+            "stream": false,
+            "options": { "num_predict": -1 }
+        }))
+        .unwrap();
+        let openai_req = generate_to_openai_request(&req);
+        assert!(!openai_req.stream);
+        assert_eq!(openai_req.max_tokens, None);
+        assert_eq!(openai_req.messages[0].role, "system");
+        assert_eq!(openai_req.messages[1].role, "user");
+    }
+
+    #[test]
+    fn test_transform_to_ollama_chat_response() {
+        let gemini_resp = json!({
+            "response": {
+                "candidates": [{
+                    "content": { "parts": [{ "text": "Hello!" }] },
+                    "finishReason": "STOP"
+                }],
+                "usageMetadata": { "promptTokenCount": 5, "candidatesTokenCount": 2 }
+            }
+        });
+        let chat = transform_to_ollama_response(&gemini_resp, "m", OllamaKind::Chat);
+        assert_eq!(chat["message"]["role"], "assistant");
+        assert_eq!(chat["message"]["content"], "Hello!");
+        assert_eq!(chat["done"], true);
+        assert_eq!(chat["prompt_eval_count"], 5);
+        assert_eq!(chat["eval_count"], 2);
+
+        let gen = transform_to_ollama_response(&gemini_resp, "m", OllamaKind::Generate);
+        assert_eq!(gen["response"], "Hello!");
+        assert_eq!(gen["done_reason"], "stop");
+    }
+}
@@ -1,26 +1,59 @@
 // OpenAI Handler
-use axum::{extract::Json, extract::State, http::StatusCode, response::IntoResponse};
+use axum::{extract::Json, extract::Path, extract::Query, extract::State, http::{HeaderMap, StatusCode}, response::IntoResponse, Extension};
 use base64::Engine as _; 
 use bytes::Bytes;
 use serde_json::{json, Value};
 use tracing::{debug, error, info}; // Import Engine trait for encode method
 
 use crate::proxy::mappers::openai::{
-    transform_openai_request, transform_openai_response, OpenAIRequest,
+    strip_lmstudio_prefix, to_lmstudio_model_id, transform_openai_request, transform_openai_response,
+    OpenAIRequest,
 };
+use crate::proxy::common::usage_headers::with_usage_headers;
+use crate::proxy::model_stats::{estimate_request_tokens, track_stream_usage, TokenUsage, UsageRecorder};
 // use crate::proxy::upstream::client::UpstreamClient; // 通过 state 获取
 use crate::proxy::server::AppState;
+use crate::proxy::upstream::stream_timeout::{inject_timeout_event, with_stream_timeouts};
+use crate::proxy::AccountAllowlist;
 
 const MAX_RETRY_ATTEMPTS: usize = 3;
 use crate::proxy::session_manager::SessionManager;
 
+/// OpenAI 路由的兼容模式查询参数
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct CompatQuery {
+    /// ?lmstudio=1: 模型 ID 使用 lm-studio/ 前缀 (LM Studio 客户端)
+    #[serde(default)]
+    lmstudio: Option<String>,
+}
+
+impl CompatQuery {
+    fn lmstudio(&self) -> bool {
+        matches!(self.lmstudio.as_deref(), Some("1") | Some("true"))
+    }
+}
+
 pub async fn handle_chat_completions(
     State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
+    Query(compat): Query<CompatQuery>,
+    headers: HeaderMap,
     Json(body): Json<Value>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     let mut openai_req: OpenAIRequest = serde_json::from_value(body)
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)))?;
 
+    // LM Studio 模式: 请求中的模型 ID 带 lm-studio/ 前缀，响应中同样带回
+    let lmstudio = compat.lmstudio();
+    if lmstudio {
+        openai_req.model = strip_lmstudio_prefix(&openai_req.model).to_string();
+    }
+    let response_model = if lmstudio {
+        to_lmstudio_model_id(&openai_req.model)
+    } else {
+        openai_req.model.clone()
+    };
+
     // Safety: Ensure messages is not empty
     if openai_req.messages.is_empty() {
         debug!("Received request with empty messages, injecting fallback...");
@@ -40,6 +73,19 @@ pub async fn handle_chat_completions(
 
     debug!("Received OpenAI request for model: {}", openai_req.model);
 
+    // 非流式请求优先查询响应缓存 (按 API Key 隔离，完整请求计入缓存键)
+    let cache_key = if openai_req.stream {
+        None
+    } else {
+        state
+            .response_cache
+            .key_for(crate::proxy::middleware::request_api_key(&headers), &openai_req)
+    };
+    if let Some(cached) = cache_key.as_ref().and_then(|k| state.response_cache.lookup(k)) {
+        info!("[OpenAI] Response cache hit for model {}", openai_req.model);
+        return Ok(cached);
+    }
+
     // 1. 获取 UpstreamClient (Clone handle)
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
@@ -53,6 +99,7 @@ pub async fn handle_chat_completions(
         // 2. 模型路由解析
         let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
             &openai_req.model,
+            &*state.model_aliases.read().await,
             &*state.custom_mapping.read().await,
         );
         // 将 OpenAI 工具转为 Value 数组以便探测联网
@@ -67,12 +114,13 @@ pub async fn handle_chat_completions(
         );
 
         // 3. 提取 SessionId (粘性指纹)
-        let session_id = SessionManager::extract_openai_session_id(&openai_req);
+        let session_id = SessionManager::extract_header_session_id(&headers)
+            .unwrap_or_else(|| SessionManager::extract_openai_session_id(&openai_req));
 
         // 4. 获取 Token (使用准确的 request_type)
         // 关键：在重试尝试 (attempt > 0) 时强制轮换账号
-        let (access_token, project_id, email) = match token_manager
-            .get_token(&config.request_type, attempt > 0, Some(&session_id))
+        let (access_token, project_id, email, account_proxy) = match token_manager
+            .get_token(&config.request_type, attempt > 0, Some(&session_id), allowlist.as_deref())
             .await
         {
             Ok(t) => t,
@@ -88,7 +136,12 @@ pub async fn handle_chat_completions(
         info!("✓ Using account: {} (type: {})", email, config.request_type);
 
         // 4. 转换请求
-        let gemini_body = transform_openai_request(&openai_req, &project_id, &mapped_model);
+        let gemini_body = transform_openai_request(
+            &openai_req,
+            &project_id,
+            &mapped_model,
+            token_manager.get_system_prompt_prefix(&email).as_deref(),
+        );
 
         // [New] 打印转换后的报文 (Gemini Body) 供调试
         if let Ok(body_json) = serde_json::to_string_pretty(&gemini_body) {
@@ -112,12 +165,20 @@ pub async fn handle_chat_completions(
         };
         let query_string = if actual_stream { Some("alt=sse") } else { None };
 
+        let usage = UsageRecorder::new(
+            &state.model_stats,
+            &state.account_stats,
+            &mapped_model,
+            &email,
+            estimate_request_tokens(&gemini_body),
+        );
         let response = match upstream
-            .call_v1_internal(method, &access_token, gemini_body, query_string)
+            .call_v1_internal(method, &access_token, gemini_body, query_string, account_proxy.as_deref())
             .await
         {
             Ok(r) => r,
             Err(e) => {
+                usage.record_response(false);
                 last_error = e.clone();
                 debug!(
                     "OpenAI Request failed on attempt {}/{}: {}",
@@ -130,21 +191,37 @@ pub async fn handle_chat_completions(
         };
 
         let status = response.status();
+        usage.record_response(status.is_success());
         if status.is_success() {
+            token_manager.mark_account_success(&email);
+
             // 5. 处理流式 vs 非流式
             if actual_stream {
                 use crate::proxy::mappers::openai::streaming::create_openai_sse_stream;
                 use axum::body::Body;
                 use axum::response::Response;
 
-                let gemini_stream = response.bytes_stream();
+                let (gemini_stream, timeout_signal) = with_stream_timeouts(
+                    track_stream_usage(response.bytes_stream(), usage.clone()),
+                    state.stream_timeouts,
+                );
+                // stream_options.include_usage 仅对客户端真正的流式请求生效
+                let include_usage = client_wants_stream
+                    && openai_req
+                        .stream_options
+                        .as_ref()
+                        .is_some_and(|o| o.include_usage);
                 let openai_stream =
-                    create_openai_sse_stream(Box::pin(gemini_stream), openai_req.model.clone());
+                    create_openai_sse_stream(gemini_stream, response_model.clone(), include_usage);
                 
                 // 判断客户端期望的格式
                 if client_wants_stream {
                     // 客户端本就要 Stream，直接返回 SSE
-                    let body = Body::from_stream(openai_stream);
+                    let body = Body::from_stream(state.active_streams.track(state.stream_recorder.record(
+                        "openai",
+                        &openai_req.model,
+                        inject_timeout_event(openai_stream, timeout_signal),
+                    )));
                     return Ok(Response::builder()
                         .header("Content-Type", "text/event-stream")
                         .header("Cache-Control", "no-cache")
@@ -168,9 +245,18 @@ pub async fn handle_chat_completions(
                     });
                     
                     match collect_openai_stream_to_json(sse_stream).await {
-                        Ok(full_response) => {
+                        Ok(mut full_response) => {
+                            if lmstudio {
+                                full_response.model = response_model.clone();
+                            }
                             info!("[OpenAI] ✓ Stream collected and converted to JSON");
-                            return Ok((StatusCode::OK, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())], Json(full_response)).into_response());
+                            state.response_cache.store_json(cache_key, &full_response);
+                            let response = (StatusCode::OK, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())], Json(full_response)).into_response();
+                            return Ok(with_usage_headers(
+                                response,
+                                state.include_usage_headers,
+                                usage.observed(),
+                            ));
                         }
                         Err(e) => {
                             return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Stream collection error: {}", e)));
@@ -183,9 +269,20 @@ pub async fn handle_chat_completions(
                 .json()
                 .await
                 .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
+            let token_usage = TokenUsage::from_response(&gemini_resp);
+            usage.record_tokens(token_usage);
 
-            let openai_response = transform_openai_response(&gemini_resp);
-            return Ok((StatusCode::OK, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())], Json(openai_response)).into_response());
+            let mut openai_response = transform_openai_response(&gemini_resp);
+            if lmstudio {
+                openai_response.model = response_model.clone();
+            }
+            state.response_cache.store_json(cache_key, &openai_response);
+            let response = (StatusCode::OK, [("X-Account-Email", email.as_str()), ("X-Mapped-Model", mapped_model.as_str())], Json(openai_response)).into_response();
+            return Ok(with_usage_headers(
+                response,
+                state.include_usage_headers,
+                Some(token_usage),
+            ));
         }
 
         // 处理特定错误并重试
@@ -201,8 +298,8 @@ pub async fn handle_chat_completions(
             error_text
         );
 
-        // 429/529/503 智能处理
-        if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 500 {
+        // 429/529/5xx 智能处理
+        if status_code == 429 || status_code == 529 || status_code == 503 || status_code == 502 || status_code == 500 {
             // 记录限流信息 (全局同步)
             token_manager.mark_rate_limited(&email, status_code, retry_after.as_deref(), &error_text);
 
@@ -232,7 +329,7 @@ pub async fn handle_chat_completions(
                 return Ok((status, [("X-Account-Email", email.as_str())], error_text).into_response());
             }
 
-            // 3. 其他限流或服务器过载情况，轮换账号
+            // 3. 其他限流或服务器过载情况，指数退避后轮换账号
             tracing::warn!(
                 "OpenAI Upstream {} on {} attempt {}/{}, rotating account",
                 status_code,
@@ -240,11 +337,18 @@ pub async fn handle_chat_completions(
                 attempt + 1,
                 max_attempts
             );
+            if attempt + 1 < max_attempts
+                && crate::proxy::upstream::backoff::ExponentialBackoff::is_retryable_status(status_code)
+            {
+                let delay = state.retry_backoff.wait(attempt).await;
+                debug!("OpenAI retry after exponential backoff: {}ms", delay.as_millis());
+            }
             continue;
         }
 
         // 只有 403 (权限/地区限制) 和 401 (认证失效) 触发账号轮换
         if status_code == 403 || status_code == 401 {
+            token_manager.mark_circuit_failure(&email, status_code, &error_text);
             tracing::warn!(
                 "OpenAI Upstream {} on account {} attempt {}/{}, rotating account",
                 status_code,
@@ -282,6 +386,7 @@ pub async fn handle_chat_completions(
 /// 将 Prompt 转换为 Chat Message 格式，复用 handle_chat_completions
 pub async fn handle_completions(
     State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
     Json(mut body): Json<Value>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     info!(
@@ -568,6 +673,7 @@ pub async fn handle_completions(
         // 1. 模型路由解析
         let mapped_model = crate::proxy::common::model_mapping::resolve_model_route(
             &openai_req.model,
+            &*state.model_aliases.read().await,
             &*state.custom_mapping.read().await,
         );
         // 将 OpenAI 工具转为 Value 数组以便探测联网
@@ -581,8 +687,8 @@ pub async fn handle_completions(
             &tools_val,
         );
 
-        let (access_token, project_id, email) =
-            match token_manager.get_token(&config.request_type, false, None).await {
+        let (access_token, project_id, email, account_proxy) =
+            match token_manager.get_token(&config.request_type, false, None, allowlist.as_deref()).await {
                 Ok(t) => t,
                 Err(e) => {
                     return Err((
@@ -594,7 +700,12 @@ pub async fn handle_completions(
 
         info!("✓ Using account: {} (type: {})", email, config.request_type);
 
-        let gemini_body = transform_openai_request(&openai_req, &project_id, &mapped_model);
+        let gemini_body = transform_openai_request(
+            &openai_req,
+            &project_id,
+            &mapped_model,
+            token_manager.get_system_prompt_prefix(&email).as_deref(),
+        );
 
         // [New] 打印转换后的报文 (Gemini Body) 供调试 (Codex 路径)
         if let Ok(body_json) = serde_json::to_string_pretty(&gemini_body) {
@@ -609,34 +720,52 @@ pub async fn handle_completions(
         };
         let query_string = if list_response { Some("alt=sse") } else { None };
 
+        let usage = UsageRecorder::new(
+            &state.model_stats,
+            &state.account_stats,
+            &mapped_model,
+            &email,
+            estimate_request_tokens(&gemini_body),
+        );
         let response = match upstream
-            .call_v1_internal(method, &access_token, gemini_body, query_string)
+            .call_v1_internal(method, &access_token, gemini_body, query_string, account_proxy.as_deref())
             .await
         {
             Ok(r) => r,
             Err(e) => {
+                usage.record_response(false);
                 last_error = e.clone();
                 continue;
             }
         };
 
         let status = response.status();
+        usage.record_response(status.is_success());
         if status.is_success() {
             if list_response {
                 use axum::body::Body;
                 use axum::response::Response;
 
-                let gemini_stream = response.bytes_stream();
+                let (gemini_stream, timeout_signal) = with_stream_timeouts(
+                    track_stream_usage(response.bytes_stream(), usage),
+                    state.stream_timeouts,
+                );
                 let body = if is_codex_style {
                     use crate::proxy::mappers::openai::streaming::create_codex_sse_stream;
-                    let s =
-                        create_codex_sse_stream(Box::pin(gemini_stream), openai_req.model.clone());
-                    Body::from_stream(s)
+                    let s = create_codex_sse_stream(gemini_stream, openai_req.model.clone());
+                    Body::from_stream(state.active_streams.track(state.stream_recorder.record(
+                        "openai",
+                        &openai_req.model,
+                        inject_timeout_event(s, timeout_signal),
+                    )))
                 } else {
                     use crate::proxy::mappers::openai::streaming::create_legacy_sse_stream;
-                    let s =
-                        create_legacy_sse_stream(Box::pin(gemini_stream), openai_req.model.clone());
-                    Body::from_stream(s)
+                    let s = create_legacy_sse_stream(gemini_stream, openai_req.model.clone());
+                    Body::from_stream(state.active_streams.track(state.stream_recorder.record(
+                        "openai",
+                        &openai_req.model,
+                        inject_timeout_event(s, timeout_signal),
+                    )))
                 };
 
                 return Ok(Response::builder()
@@ -654,6 +783,7 @@ pub async fn handle_completions(
                 .json()
                 .await
                 .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
+            usage.record_tokens(TokenUsage::from_response(&gemini_resp));
 
             let chat_resp = transform_openai_response(&gemini_resp);
 
@@ -698,14 +828,20 @@ pub async fn handle_completions(
     ))
 }
 
-pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoResponse {
+pub async fn handle_list_models(
+    State(state): State<AppState>,
+    Query(compat): Query<CompatQuery>,
+) -> impl IntoResponse {
     use crate::proxy::common::model_mapping::get_all_dynamic_models;
 
     let model_ids = get_all_dynamic_models(
+        &state.model_aliases,
         &state.custom_mapping,
     ).await;
 
+    let lmstudio = compat.lmstudio();
     let data: Vec<_> = model_ids.into_iter().map(|id| {
+        let id = if lmstudio { to_lmstudio_model_id(&id) } else { id };
         json!({
             "id": id,
             "object": "model",
@@ -720,10 +856,77 @@ pub async fn handle_list_models(State(state): State<AppState>) -> impl IntoRespo
     }))
 }
 
+/// 单个模型的信息与能力 (GET /v1/models/{model_id})
+/// 携带 anthropic-version 头的请求 (Claude SDK) 返回 Anthropic 格式
+pub async fn handle_get_model(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(model_id): Path<String>,
+) -> axum::response::Response {
+    if headers.contains_key("anthropic-version") {
+        return crate::proxy::handlers::claude::handle_get_model(State(state), Path(model_id)).await;
+    }
+
+    let Some((mapped_model, capabilities)) = crate::proxy::handlers::common::resolve_model_info(&state, &model_id).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": {
+                    "message": format!("The model '{}' does not exist", model_id),
+                    "type": "invalid_request_error",
+                    "param": "model",
+                    "code": "model_not_found"
+                }
+            })),
+        )
+            .into_response();
+    };
+    Json(crate::proxy::handlers::common::with_model_capabilities(
+        json!({
+            "id": model_id,
+            "object": "model",
+            "created": 1706745600,
+            "owned_by": "antigravity",
+            "mapped_model": mapped_model,
+        }),
+        &capabilities,
+    ))
+    .into_response()
+}
+
+/// 不支持的音频端点 (POST /v1/audio/translations, /v1/audio/speech)
+/// 返回 501 而非 404，避免客户端将其视为网络错误而无限重试
+pub async fn handle_audio_transcriptions_stub() -> impl IntoResponse {
+    (
+        StatusCode::NOT_IMPLEMENTED,
+        Json(json!({
+            "error": {
+                "type": "not_supported_error",
+                "message": "Audio transcription is not supported by this proxy. Use a direct OpenAI endpoint for audio."
+            }
+        })),
+    )
+}
+
+/// 内容审核桩端点: POST /v1/moderations
+/// 忽略请求体、不调用上游，始终返回未命中，避免客户端在发送消息前的审核调用因 404 失败
+pub async fn handle_moderations() -> impl IntoResponse {
+    Json(json!({
+        "id": format!("modr-{}", uuid::Uuid::new_v4().simple()),
+        "model": "text-moderation-latest",
+        "results": [{
+            "flagged": false,
+            "categories": {},
+            "category_scores": {}
+        }]
+    }))
+}
+
 /// OpenAI Images API: POST /v1/images/generations
 /// 处理图像生成请求，转换为 Gemini API 格式
 pub async fn handle_images_generations(
     State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
     Json(body): Json<Value>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // 1. 解析请求参数
@@ -793,7 +996,7 @@ pub async fn handle_images_generations(
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
 
-    let (access_token, project_id, email) = match token_manager.get_token("image_gen", false, None).await
+    let (access_token, project_id, email, account_proxy) = match token_manager.get_token("image_gen", false, None, allowlist.as_deref()).await
     {
         Ok(t) => t,
         Err(e) => {
@@ -812,6 +1015,7 @@ pub async fn handle_images_generations(
     for _ in 0..n {
         let upstream = upstream.clone();
         let access_token = access_token.clone();
+        let account_proxy = account_proxy.clone();
         let project_id = project_id.clone();
         let final_prompt = final_prompt.clone();
         let aspect_ratio = aspect_ratio.to_string();
@@ -846,7 +1050,7 @@ pub async fn handle_images_generations(
             });
 
             match upstream
-                .call_v1_internal("generateContent", &access_token, gemini_body, None)
+                .call_v1_internal("generateContent", &access_token, gemini_body, None, account_proxy.as_deref())
                 .await
             {
                 Ok(response) => {
@@ -954,6 +1158,7 @@ pub async fn handle_images_generations(
 
 pub async fn handle_images_edits(
     State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
     mut multipart: axum::extract::Multipart,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     tracing::info!("[Images] Received edit request");
@@ -1043,7 +1248,7 @@ pub async fn handle_images_edits(
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
     // Fix: Proper get_token call with correct signature and unwrap (using image_gen quota)
-    let (access_token, project_id, _email) = match token_manager.get_token("image_gen", false, None).await
+    let (access_token, project_id, _email, account_proxy) = match token_manager.get_token("image_gen", false, None, allowlist.as_deref()).await
     {
         Ok(t) => t,
         Err(e) => {
@@ -1113,11 +1318,12 @@ pub async fn handle_images_edits(
     for _ in 0..n {
         let upstream = upstream.clone();
         let access_token = access_token.clone();
+        let account_proxy = account_proxy.clone();
         let body = gemini_body.clone();
 
         tasks.push(tokio::spawn(async move {
             match upstream
-                .call_v1_internal("generateContent", &access_token, body, None)
+                .call_v1_internal("generateContent", &access_token, body, None, account_proxy.as_deref())
                 .await
             {
                 Ok(response) => {
@@ -1223,3 +1429,429 @@ pub async fn handle_images_edits(
 
     Ok(Json(openai_response))
 }
+
+/// OpenAI Embeddings API: POST /v1/embeddings
+/// 多个输入合并为一次 batchEmbedContents 调用
+pub async fn handle_embeddings(
+    State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
+    Json(body): Json<Value>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    use crate::proxy::mappers::openai::embeddings::{
+        build_batch_embed_request, resolve_embedding_model, transform_embed_response, OpenAIEmbeddingRequest,
+    };
+
+    let req: OpenAIEmbeddingRequest = serde_json::from_value(body)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)))?;
+    let inputs = req.input.into_vec();
+    if inputs.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "input must not be empty".to_string()));
+    }
+
+    let model = resolve_embedding_model(&req.model, &*state.model_aliases.read().await);
+    info!("[Embeddings] {} input(s), model: {} -> {}", inputs.len(), req.model, model);
+
+    let (access_token, _project_id, email, account_proxy) = state
+        .token_manager
+        .get_token("text", false, None, allowlist.as_deref())
+        .await
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, format!("Token error: {}", e)))?;
+
+    let upstream_body = build_batch_embed_request(&model, &inputs, req.dimensions);
+    let response = state
+        .upstream
+        .batch_embed_contents(&access_token, account_proxy.as_deref(), &model, &upstream_body)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        error!("[Embeddings] Upstream error {}: {}", status, error_text);
+        return Err((status, error_text));
+    }
+
+    let gemini_resp: Value = response
+        .json()
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
+    let openai_response =
+        transform_embed_response(&gemini_resp, &req.model, &inputs).map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+
+    Ok(([("X-Account-Email", email)], Json(openai_response)))
+}
+
+/// OpenAI Files API: POST /v1/files
+/// 将 multipart 上传的文件转存到 Google File API，返回 OpenAI File 对象
+pub async fn handle_file_upload(
+    State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
+    headers: HeaderMap,
+    mut multipart: axum::extract::Multipart,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    use crate::proxy::file_store::{FileOwner, FileStore, StoredFile};
+
+    let owner = FileOwner::from_request(&headers, allowlist.as_deref());
+
+    let mut file_data = None;
+    let mut filename = "upload".to_string();
+    let mut mime_type = "application/octet-stream".to_string();
+    let mut purpose = "user_data".to_string();
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Multipart error: {}", e)))?
+    {
+        match field.name().unwrap_or("") {
+            "file" => {
+                if let Some(name) = field.file_name() {
+                    filename = name.to_string();
+                }
+                if let Some(ct) = field.content_type() {
+                    mime_type = ct.to_string();
+                }
+                file_data = Some(
+                    field
+                        .bytes()
+                        .await
+                        .map_err(|e| (StatusCode::BAD_REQUEST, format!("File read error: {}", e)))?,
+                );
+            }
+            "purpose" => {
+                if let Ok(val) = field.text().await {
+                    purpose = val;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let data = file_data.ok_or((StatusCode::BAD_REQUEST, "Missing file".to_string()))?;
+
+    // Batch 输入文件只在本地使用，不上传到 Google
+    if purpose == "batch" {
+        let stored = FileStore::global()
+            .save_local(&filename, &purpose, &data, owner)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        info!("[Files] Stored batch input {} ({} bytes) as {}", filename, data.len(), stored.id);
+        return Ok(Json(stored.to_openai()).into_response());
+    }
+
+    info!("[Files] Uploading {} ({} bytes, {})", filename, data.len(), mime_type);
+
+    // 文件上传不是延迟敏感请求，以 Low 优先级选择账号
+    let (access_token, _project_id, email, account_proxy) = state
+        .token_manager
+        .get_token_with_priority(
+            "agent",
+            false,
+            None,
+            allowlist.as_deref(),
+            crate::proxy::common::model_mapping::RequestPriority::Low,
+        )
+        .await
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, format!("Token error: {}", e)))?;
+
+    let response = state
+        .upstream
+        .upload_file(&access_token, account_proxy.as_deref(), &filename, &mime_type, data)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        error!("[Files] Upload failed {}: {}", status, error_text);
+        return Err((status, error_text));
+    }
+
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Parse error: {}", e)))?;
+    let stored = StoredFile::from_google(body.get("file").unwrap_or(&body), &filename, &purpose, &email, owner)
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+    let openai_file = stored.to_openai();
+    debug!("[Files] Stored {} -> {}", stored.id, stored.google_name);
+    FileStore::global().insert(stored);
+
+    Ok(([("X-Account-Email", email)], Json(openai_file)).into_response())
+}
+
+/// 查找当前请求方可见的文件 (其他 API key 上传的文件返回 404)
+fn find_owned_file(
+    file_id: &str,
+    owner: &crate::proxy::file_store::FileOwner,
+) -> Result<crate::proxy::file_store::StoredFile, (StatusCode, String)> {
+    crate::proxy::file_store::FileStore::global()
+        .get_owned(file_id, owner)
+        .ok_or((StatusCode::NOT_FOUND, format!("No such File object: {}", file_id)))
+}
+
+/// 查找 file ID 对应的文件及其归属账号的凭证
+/// 归属校验保证账号在上传时的 allowlist 范围内，因此可以直接按邮箱取 token
+async fn resolve_stored_file(
+    state: &AppState,
+    file: crate::proxy::file_store::StoredFile,
+) -> Result<(crate::proxy::file_store::StoredFile, String, Option<String>), (StatusCode, String)> {
+    let (access_token, _project_id, _email, account_proxy) = state
+        .token_manager
+        .get_token_by_email(&file.account_email)
+        .await
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, format!("Token error: {}", e)))?;
+    Ok((file, access_token, account_proxy))
+}
+
+/// OpenAI Files API: GET /v1/files/{file_id}
+pub async fn handle_file_retrieve(
+    State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
+    headers: HeaderMap,
+    axum::extract::Path(file_id): axum::extract::Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let owner = crate::proxy::file_store::FileOwner::from_request(&headers, allowlist.as_deref());
+    let file = find_owned_file(&file_id, &owner)?;
+    if file.is_local() {
+        return Ok(Json(file.to_openai()));
+    }
+    let (mut file, access_token, account_proxy) = resolve_stored_file(&state, file).await?;
+
+    let response = state
+        .upstream
+        .get_file(&access_token, account_proxy.as_deref(), &file.google_name)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+    let status = response.status();
+    if !status.is_success() {
+        if status == StatusCode::NOT_FOUND {
+            // 上游文件已过期或被删除
+            crate::proxy::file_store::FileStore::global().remove(&file_id);
+        }
+        return Err((status, response.text().await.unwrap_or_default()));
+    }
+
+    if let Ok(google_file) = response.json::<Value>().await {
+        if let Some(size) = google_file
+            .get("sizeBytes")
+            .and_then(|v| v.as_str())
+            .and_then(|s| s.parse().ok())
+        {
+            file.bytes = size;
+        }
+    }
+    Ok(Json(file.to_openai()))
+}
+
+/// OpenAI Files API: DELETE /v1/files/{file_id}
+pub async fn handle_file_delete(
+    State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
+    headers: HeaderMap,
+    axum::extract::Path(file_id): axum::extract::Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let owner = crate::proxy::file_store::FileOwner::from_request(&headers, allowlist.as_deref());
+    let file = find_owned_file(&file_id, &owner)?;
+    if file.is_local() {
+        crate::proxy::file_store::FileStore::global().remove(&file_id);
+        return Ok(Json(json!({ "id": file_id, "object": "file", "deleted": true })));
+    }
+    let (file, access_token, account_proxy) = resolve_stored_file(&state, file).await?;
+
+    let response = state
+        .upstream
+        .delete_file(&access_token, account_proxy.as_deref(), &file.google_name)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+    let status = response.status();
+    // 上游 404 视为已删除
+    if !status.is_success() && status != StatusCode::NOT_FOUND {
+        return Err((status, response.text().await.unwrap_or_default()));
+    }
+
+    crate::proxy::file_store::FileStore::global().remove(&file_id);
+    Ok(Json(json!({
+        "id": file_id,
+        "object": "file",
+        "deleted": true
+    })))
+}
+
+/// OpenAI Files API: GET /v1/files/{file_id}/content
+/// 仅支持本地保存的 Batch 输入 / 输出文件 (Google File API 不提供上传文件的下载)
+pub async fn handle_file_content(
+    allowlist: Option<Extension<AccountAllowlist>>,
+    headers: HeaderMap,
+    axum::extract::Path(file_id): axum::extract::Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let owner = crate::proxy::file_store::FileOwner::from_request(&headers, allowlist.as_deref());
+    let file = find_owned_file(&file_id, &owner)?;
+    let content = file.read_local().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(([(axum::http::header::CONTENT_TYPE, file.mime_type)], content))
+}
+
+/// OpenAI Batch API: POST /v1/batches
+/// 同步处理: 逐条调用 chat completions，全部完成后返回 completed 状态的批次对象
+pub async fn handle_batch_create(
+    State(state): State<AppState>,
+    allowlist: Option<Extension<AccountAllowlist>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    use crate::proxy::batch_store::{batch_output_line, parse_batch_input, BatchStore, BATCH_ENDPOINT_CHAT};
+    use crate::proxy::file_store::{FileOwner, FileStore};
+
+    let owner = FileOwner::from_request(&headers, allowlist.as_deref());
+
+    let input_file_id = body
+        .get("input_file_id")
+        .and_then(|v| v.as_str())
+        .ok_or((StatusCode::BAD_REQUEST, "Missing input_file_id".to_string()))?;
+    let endpoint = body.get("endpoint").and_then(|v| v.as_str()).unwrap_or(BATCH_ENDPOINT_CHAT);
+    if endpoint != BATCH_ENDPOINT_CHAT {
+        return Err((StatusCode::BAD_REQUEST, format!("Unsupported batch endpoint: {}", endpoint)));
+    }
+
+    let input_file = find_owned_file(input_file_id, &owner)?;
+    let content = input_file.read_local().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let requests =
+        parse_batch_input(&String::from_utf8_lossy(&content)).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let batch_id = format!("batch_{}", uuid::Uuid::new_v4().simple());
+    let created_at = chrono::Utc::now().timestamp();
+    info!("[Batch] {} processing {} request(s) from {}", batch_id, requests.len(), input_file_id);
+
+    let mut output = String::new();
+    let mut failed = 0;
+    for request in &requests {
+        let (status, response_body) = if request.url != BATCH_ENDPOINT_CHAT {
+            (
+                StatusCode::BAD_REQUEST,
+                json!({
+                    "error": {
+                        "message": format!("Unsupported url: {}", request.url),
+                        "type": "invalid_request_error"
+                    }
+                }),
+            )
+        } else {
+            let mut chat_body = request.body.clone();
+            if let Some(obj) = chat_body.as_object_mut() {
+                obj.insert("stream".to_string(), json!(false));
+            }
+            let response = handle_chat_completions(
+                State(state.clone()),
+                allowlist.clone(),
+                Query(CompatQuery::default()),
+                headers.clone(),
+                Json(chat_body),
+            )
+            .await
+            .into_response();
+            let status = response.status();
+            // 单条响应同样受 max_request_body_mb 限制，超限或读取失败记为该条请求失败
+            match axum::body::to_bytes(response.into_body(), state.max_body_bytes).await {
+                Ok(bytes) => {
+                    let response_body = serde_json::from_slice(&bytes).unwrap_or_else(|_| {
+                        json!({
+                            "error": {
+                                "message": String::from_utf8_lossy(&bytes),
+                                "type": "upstream_error"
+                            }
+                        })
+                    });
+                    (status, response_body)
+                }
+                Err(e) => (
+                    StatusCode::BAD_GATEWAY,
+                    json!({
+                        "error": {
+                            "message": format!("Failed to read response: {}", e),
+                            "type": "upstream_error"
+                        }
+                    }),
+                ),
+            }
+        };
+
+        if !status.is_success() {
+            failed += 1;
+            debug!("[Batch] {} request {} failed with {}", batch_id, request.custom_id, status);
+        }
+        output.push_str(&batch_output_line(&request.custom_id, status.as_u16(), response_body).to_string());
+        output.push('\n');
+    }
+
+    let output_file = FileStore::global()
+        .save_local(&format!("{}_output.jsonl", batch_id), "batch_output", output.as_bytes(), owner.clone())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let completed_at = chrono::Utc::now().timestamp();
+    info!(
+        "[Batch] {} completed: {} succeeded, {} failed -> {}",
+        batch_id,
+        requests.len() - failed,
+        failed,
+        output_file.id
+    );
+
+    let batch = json!({
+        "id": batch_id,
+        "object": "batch",
+        "endpoint": endpoint,
+        "errors": null,
+        "input_file_id": input_file_id,
+        "completion_window": body.get("completion_window").cloned().unwrap_or(json!("24h")),
+        "status": "completed",
+        "output_file_id": output_file.id,
+        "error_file_id": null,
+        "created_at": created_at,
+        "in_progress_at": created_at,
+        "expires_at": null,
+        "finalizing_at": completed_at,
+        "completed_at": completed_at,
+        "failed_at": null,
+        "expired_at": null,
+        "cancelling_at": null,
+        "cancelled_at": null,
+        "request_counts": {
+            "total": requests.len(),
+            "completed": requests.len() - failed,
+            "failed": failed
+        },
+        "metadata": body.get("metadata").cloned().unwrap_or(Value::Null)
+    });
+    BatchStore::global().insert(batch.clone(), owner);
+    Ok(Json(batch))
+}
+
+/// OpenAI Batch API: GET /v1/batches/{batch_id}
+pub async fn handle_batch_retrieve(
+    allowlist: Option<Extension<AccountAllowlist>>,
+    headers: HeaderMap,
+    axum::extract::Path(batch_id): axum::extract::Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let owner = crate::proxy::file_store::FileOwner::from_request(&headers, allowlist.as_deref());
+    crate::proxy::batch_store::BatchStore::global()
+        .get(&batch_id, &owner)
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, format!("No such Batch object: {}", batch_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_moderations_stub_never_flags() {
+        let response = handle_moderations().await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), 64 * 1024)
+            .await
+            .expect("moderation body within limit");
+        let value: Value = serde_json::from_slice(&body).unwrap();
+        assert!(value["id"].as_str().unwrap().starts_with("modr-"));
+        assert_eq!(value["model"], "text-moderation-latest");
+        assert_eq!(
+            value["results"],
+            json!([{"flagged": false, "categories": {}, "category_scores": {}}])
+        );
+    }
+}
@@ -0,0 +1,173 @@
+// 非流式响应缓存
+// IDE 插件等后台轮询会在短时间内反复发送完全相同的问题，
+// 命中缓存时直接返回上一次的完整响应，避免重复消耗上游配额
+
+use axum::{
+    body::Body,
+    http::{header, HeaderValue, StatusCode},
+    response::Response,
+};
+use bytes::Bytes;
+use lru::LruCache;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub type CacheKey = [u8; 32];
+
+/// 缓存的完整响应体 (JSON)
+#[derive(Clone)]
+pub struct CachedResponse {
+    pub bytes: Bytes,
+    pub expires_at: Instant,
+}
+
+/// 按 (API Key, 请求) 哈希索引的 LRU 响应缓存，未配置 TTL 时不启用
+#[derive(Clone)]
+pub struct ResponseCache {
+    ttl: Duration,
+    entries: Option<Arc<Mutex<LruCache<CacheKey, CachedResponse>>>>,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Option<Duration>, max_entries: usize) -> Self {
+        let ttl = ttl.filter(|t| !t.is_zero());
+        let entries = match (ttl, NonZeroUsize::new(max_entries)) {
+            (Some(_), Some(cap)) => Some(Arc::new(Mutex::new(LruCache::new(cap)))),
+            _ => None,
+        };
+        Self {
+            ttl: ttl.unwrap_or_default(),
+            entries,
+        }
+    }
+
+    pub fn from_proxy_config(config: &crate::proxy::config::ProxyConfig) -> Self {
+        Self::new(
+            config.response_cache_ttl_secs.map(Duration::from_secs),
+            config.response_cache_max_entries,
+        )
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.entries.is_some()
+    }
+
+    /// 缓存键: SHA-256(api_key + 去掉 stream 相关字段后的完整请求 JSON)
+    /// 采样参数、stop、tool_choice、thinking 等任一字段不同都视为不同请求，且不同 API Key 之间不共享
+    pub fn key<T: Serialize + ?Sized>(api_key: Option<&str>, request: &T) -> CacheKey {
+        let mut request = serde_json::to_value(request).unwrap_or_default();
+        if let Some(obj) = request.as_object_mut() {
+            obj.remove("stream");
+            obj.remove("stream_options");
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(api_key.unwrap_or_default().as_bytes());
+        hasher.update([0u8]);
+        hasher.update(serde_json::to_vec(&request).unwrap_or_default());
+        hasher.finalize().into()
+    }
+
+    /// 启用缓存时返回请求对应的缓存键，否则返回 None (调用方据此跳过写入)
+    pub fn key_for<T: Serialize + ?Sized>(&self, api_key: Option<&str>, request: &T) -> Option<CacheKey> {
+        self.is_enabled().then(|| Self::key(api_key, request))
+    }
+
+    /// 命中且未过期时返回带 X-Cache: HIT 与 Age 头的响应，过期条目顺带移除
+    pub fn lookup(&self, key: &CacheKey) -> Option<Response> {
+        let entries = self.entries.as_ref()?;
+        let mut entries = entries.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let cached = match entries.get(key) {
+            Some(c) if c.expires_at > now => c.clone(),
+            Some(_) => {
+                entries.pop(key);
+                return None;
+            }
+            None => return None,
+        };
+        drop(entries);
+
+        let age = self.ttl.saturating_sub(cached.expires_at - now).as_secs();
+        Some(
+            Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, "application/json")
+                .header("X-Cache", HeaderValue::from_static("HIT"))
+                .header(header::AGE, age)
+                .body(Body::from(cached.bytes))
+                .unwrap(),
+        )
+    }
+
+    pub fn store(&self, key: CacheKey, bytes: Bytes) {
+        if let Some(entries) = &self.entries {
+            let expires_at = Instant::now() + self.ttl;
+            entries
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .put(key, CachedResponse { bytes, expires_at });
+        }
+    }
+
+    /// 上游成功返回后写入缓存 (key 为 None 时不做任何事)
+    pub fn store_json<T: Serialize>(&self, key: Option<CacheKey>, value: &T) {
+        if let Some(key) = key {
+            if let Ok(bytes) = serde_json::to_vec(value) {
+                self.store(key, Bytes::from(bytes));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cache_hit_and_expiry() {
+        let cache = ResponseCache::new(Some(Duration::from_millis(50)), 10);
+        let request = json!({"model": "gemini-2.5-flash", "messages": [{"role": "user", "content": "hi"}], "stream": false});
+        let key = ResponseCache::key(Some("sk-a"), &request);
+        // stream 字段不影响缓存键
+        let mut streamed = request.clone();
+        streamed["stream"] = json!(true);
+        assert_eq!(key, ResponseCache::key(Some("sk-a"), &streamed));
+        // 模型、采样参数或 API Key 不同均不命中
+        let mut other_model = request.clone();
+        other_model["model"] = json!("gemini-2.5-pro");
+        assert_ne!(key, ResponseCache::key(Some("sk-a"), &other_model));
+        let mut other_temperature = request.clone();
+        other_temperature["temperature"] = json!(0.2);
+        assert_ne!(key, ResponseCache::key(Some("sk-a"), &other_temperature));
+        assert_ne!(key, ResponseCache::key(Some("sk-b"), &request));
+        assert!(cache.lookup(&key).is_none());
+
+        cache.store_json(Some(key), &json!({"answer": 42}));
+        let hit = cache.lookup(&key).unwrap();
+        assert_eq!(hit.headers().get("X-Cache").unwrap(), "HIT");
+        assert_eq!(hit.headers().get(header::AGE).unwrap(), "0");
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(cache.lookup(&key).is_none());
+    }
+
+    #[test]
+    fn test_cache_lru_eviction_and_disabled() {
+        let cache = ResponseCache::new(Some(Duration::from_secs(60)), 1);
+        let a = ResponseCache::key(None, "a");
+        let b = ResponseCache::key(None, "b");
+        cache.store(a, Bytes::from_static(b"a"));
+        cache.store(b, Bytes::from_static(b"b"));
+        assert!(cache.lookup(&a).is_none());
+        assert!(cache.lookup(&b).is_some());
+
+        let disabled = ResponseCache::new(None, 100);
+        assert!(disabled.key_for(None, "a").is_none());
+        disabled.store(a, Bytes::from_static(b"a"));
+        assert!(disabled.lookup(&a).is_none());
+    }
+}
@@ -50,11 +50,11 @@ pub async fn handle_warmup(
     );
 
     // ===== 步骤 1: 获取 Token =====
-    let (access_token, project_id) = if let (Some(at), Some(pid)) = (&req.access_token, &req.project_id) {
-        (at.clone(), pid.clone())
+    let (access_token, project_id, account_proxy) = if let (Some(at), Some(pid)) = (&req.access_token, &req.project_id) {
+        (at.clone(), pid.clone(), state.token_manager.get_account_proxy(&req.email))
     } else {
         match state.token_manager.get_token_by_email(&req.email).await {
-            Ok((at, pid, _)) => (at, pid),
+            Ok((at, pid, _, proxy)) => (at, pid, proxy),
             Err(e) => {
                 warn!(
                     "[Warmup-API] Step 1 FAILED: Token error for {}: {}",
@@ -97,11 +97,14 @@ pub async fn handle_warmup(
             metadata: None,
             thinking: None,
             output_config: None,
+            tool_choice: None,
+            stop_sequences: None,
         };
 
         match crate::proxy::mappers::claude::transform_claude_request_in(
             &claude_request,
             &project_id,
+            None,
         ) {
             Ok(transformed) => transformed,
             Err(e) => {
@@ -150,14 +153,14 @@ pub async fn handle_warmup(
 
     let mut result = state
         .upstream
-        .call_v1_internal(method, &access_token, body.clone(), query)
+        .call_v1_internal(method, &access_token, body.clone(), query, account_proxy.as_deref())
         .await;
 
     // 如果流式请求失败，尝试非流式请求
     if result.is_err() && !prefer_non_stream {
         result = state
             .upstream
-            .call_v1_internal("generateContent", &access_token, body, None)
+            .call_v1_internal("generateContent", &access_token, body, None, account_proxy.as_deref())
             .await;
     }
 
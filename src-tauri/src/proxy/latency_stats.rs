@@ -0,0 +1,75 @@
+// 按账号的请求延迟统计
+// 每次请求完成后以指数平滑方式更新平均值与 P50 / P95 估计，供延迟敏感请求优先选择快速账号
+
+use serde::Serialize;
+
+/// 平滑系数: 越大越偏向最近的请求
+const EMA_ALPHA: f64 = 0.2;
+
+/// 单账号延迟估计 (毫秒)
+///
+/// 分位数采用随机逼近: 样本高于估计值时上调 q 步，否则下调 (1 - q) 步，
+/// 步长与当前平均延迟成比例，收敛后估计值落在对应分位上
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq)]
+pub struct LatencyStats {
+    pub ema_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub samples: u64,
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, latency_ms: f64) {
+        let latency_ms = latency_ms.max(0.0);
+        if self.samples == 0 {
+            self.ema_ms = latency_ms;
+            self.p50_ms = latency_ms;
+            self.p95_ms = latency_ms;
+        } else {
+            self.ema_ms += EMA_ALPHA * (latency_ms - self.ema_ms);
+            let step = EMA_ALPHA * self.ema_ms.max(1.0);
+            self.p50_ms = update_quantile(self.p50_ms, latency_ms, 0.5, step);
+            self.p95_ms = update_quantile(self.p95_ms, latency_ms, 0.95, step).max(self.p50_ms);
+        }
+        self.samples += 1;
+    }
+}
+
+fn update_quantile(estimate: f64, sample: f64, q: f64, step: f64) -> f64 {
+    let adjusted = if sample > estimate {
+        estimate + step * q
+    } else {
+        estimate - step * (1.0 - q)
+    };
+    adjusted.max(0.0)
+}
+
+/// 账号延迟快照 (供前端展示)
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountLatencyInfo {
+    pub account_id: String,
+    pub email: String,
+    #[serde(flatten)]
+    pub stats: LatencyStats,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_stats_converge() {
+        let mut stats = LatencyStats::default();
+        stats.record(200.0);
+        assert_eq!(stats.p50_ms, 200.0);
+        assert_eq!(stats.samples, 1);
+
+        // 90% 的请求约 100ms，10% 约 1000ms
+        for i in 0..2000 {
+            stats.record(if i % 10 == 0 { 1000.0 } else { 100.0 });
+        }
+        assert!(stats.p50_ms < 200.0, "p50 = {}", stats.p50_ms);
+        assert!(stats.p95_ms > 300.0, "p95 = {}", stats.p95_ms);
+        assert!(stats.p95_ms >= stats.p50_ms);
+    }
+}
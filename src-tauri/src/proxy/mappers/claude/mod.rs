@@ -20,37 +20,72 @@ use bytes::Bytes;
 use futures::Stream;
 use std::pin::Pin;
 
+/// 流处理发生 panic 时在终止事件之后发送的错误事件
+pub(crate) const STREAM_PROCESSING_ERROR: &str =
+    "{\"type\":\"error\",\"error\":{\"type\":\"internal\",\"message\":\"Stream processing error\"}}";
+
+/// Gemini 因版权原因拒绝生成 (finishReason: RECITATION) 时返回给客户端的提示文本
+/// 上游此时通常没有任何内容，空 content 会导致部分客户端崩溃
+pub(crate) const RECITATION_BLOCKED_TEXT: &str = "[Content blocked by safety filter: RECITATION]";
+
 /// 创建从 Gemini SSE 流到 Claude SSE 流的转换
 pub fn create_claude_sse_stream(
-    mut gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
+    gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
     trace_id: String,
     email: String,
 ) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> {
+    claude_sse_stream_with(gemini_stream, move |data, state| {
+        process_sse_data(data, state, &trace_id, &email)
+    })
+}
+
+/// 以指定的事件处理函数驱动 Claude SSE 流
+/// 处理函数 panic 时补发终止事件 (message_stop) 与 error 事件后结束流，避免客户端无限等待
+fn claude_sse_stream_with<F>(
+    gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
+    mut process: F,
+) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>>
+where
+    F: FnMut(&str, &mut StreamingState) -> Option<Vec<Bytes>> + Send + 'static,
+{
     use async_stream::stream;
-    use bytes::BytesMut;
+    use crate::proxy::upstream::sse_parser::SseParser;
     use futures::StreamExt;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
 
     Box::pin(stream! {
         let mut state = StreamingState::new();
-        let mut buffer = BytesMut::new();
-
-        while let Some(chunk_result) = gemini_stream.next().await {
-            match chunk_result {
-                Ok(chunk) => {
-                    buffer.extend_from_slice(&chunk);
-
-                    // Process complete lines
-                    while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
-                        let line_raw = buffer.split_to(pos + 1);
-                        if let Ok(line_str) = std::str::from_utf8(&line_raw) {
-                            let line = line_str.trim();
-                            if line.is_empty() { continue; }
-
-                            if let Some(sse_chunks) = process_sse_line(line, &mut state, &trace_id, &email) {
-                                for sse_chunk in sse_chunks {
-                                    yield Ok(sse_chunk);
-                                }
+        let mut events = SseParser::new(gemini_stream);
+
+        while let Some(event_result) = events.next().await {
+            match event_result {
+                Ok(event) => {
+                    match catch_unwind(AssertUnwindSafe(|| process(&event.data, &mut state))) {
+                        Ok(Some(sse_chunks)) => {
+                            for sse_chunk in sse_chunks {
+                                yield Ok(sse_chunk);
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(_) => {
+                            tracing::error!("[Claude-Stream] 流处理发生 panic，发送终止事件后结束流");
+                            // 状态可能已不一致，force stop 本身失败时直接补发 message_stop
+                            let stop_chunks = catch_unwind(AssertUnwindSafe(|| emit_force_stop(&mut state)))
+                                .unwrap_or_default();
+                            for chunk in stop_chunks {
+                                yield Ok(chunk);
                             }
+                            if !state.message_stop_sent {
+                                state.message_stop_sent = true;
+                                yield Ok(Bytes::from(
+                                    "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+                                ));
+                            }
+                            yield Ok(Bytes::from(format!(
+                                "event: error\ndata: {}\n\n",
+                                STREAM_PROCESSING_ERROR
+                            )));
+                            return;
                         }
                     }
                 }
@@ -68,13 +103,9 @@ pub fn create_claude_sse_stream(
     })
 }
 
-/// 处理单行 SSE 数据
-fn process_sse_line(line: &str, state: &mut StreamingState, trace_id: &str, email: &str) -> Option<Vec<Bytes>> {
-    if !line.starts_with("data: ") {
-        return None;
-    }
-
-    let data_str = line[6..].trim();
+/// 处理单个 SSE 事件的 data 字段
+fn process_sse_data(data: &str, state: &mut StreamingState, trace_id: &str, email: &str) -> Option<Vec<Bytes>> {
+    let data_str = data.trim();
     if data_str.is_empty() {
         return None;
     }
@@ -338,9 +369,9 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_process_sse_line_done() {
+    fn test_process_sse_data_done() {
         let mut state = StreamingState::new();
-        let result = process_sse_line("data: [DONE]", &mut state, "test_id", "test@example.com");
+        let result = process_sse_data("[DONE]", &mut state, "test_id", "test@example.com");
         assert!(result.is_some());
         let chunks = result.unwrap();
         assert!(!chunks.is_empty());
@@ -352,13 +383,47 @@ mod tests {
         assert!(all_text.contains("message_stop"));
     }
 
+    #[tokio::test]
+    async fn test_panic_mid_stream_emits_terminal_events() {
+        use futures::StreamExt;
+
+        let upstream = concat!(
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hello\"}]}}]}\n\n",
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"boom\"}]}}]}\n\n",
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"never\"}]}}]}\n\n",
+        );
+        let gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>> =
+            Box::pin(futures::stream::iter(vec![Ok(Bytes::from(upstream))]));
+
+        let stream = claude_sse_stream_with(gemini_stream, |data, state| {
+            if data.contains("boom") {
+                panic!("simulated processing panic");
+            }
+            process_sse_data(data, state, "test_id", "test@example.com")
+        });
+        let output: String = stream
+            .map(|c| String::from_utf8(c.unwrap().to_vec()).unwrap())
+            .collect::<Vec<_>>()
+            .await
+            .concat();
+
+        assert!(output.contains("Hello"));
+        assert!(!output.contains("never"));
+        assert!(output.contains("content_block_stop"));
+        let stop = output.find("event: message_stop").unwrap();
+        let error = output.find("event: error").unwrap();
+        assert!(stop < error);
+        assert!(output.trim_end().ends_with(STREAM_PROCESSING_ERROR));
+        assert_eq!(output.matches("event: message_stop").count(), 1);
+    }
+
     #[test]
-    fn test_process_sse_line_with_text() {
+    fn test_process_sse_data_with_text() {
         let mut state = StreamingState::new();
 
-        let test_data = r#"data: {"candidates":[{"content":{"parts":[{"text":"Hello"}]}}],"usageMetadata":{},"modelVersion":"test","responseId":"123"}"#;
+        let test_data = r#"{"candidates":[{"content":{"parts":[{"text":"Hello"}]}}],"usageMetadata":{},"modelVersion":"test","responseId":"123"}"#;
         
-        let result = process_sse_line(test_data, &mut state, "test_id", "test@example.com");
+        let result = process_sse_data(test_data, &mut state, "test_id", "test@example.com");
         assert!(result.is_some());
 
         let chunks = result.unwrap();
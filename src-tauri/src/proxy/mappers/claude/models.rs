@@ -29,6 +29,10 @@ pub struct ClaudeRequest {
     /// Output configuration for effort level (Claude API v2.0.67+)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub output_config: Option<OutputConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
 }
 
 /// Thinking 配置
@@ -135,6 +139,13 @@ pub enum ContentBlock {
         tool_use_id: String,
         content: serde_json::Value,
     },
+
+    /// 联网搜索来源 (由 Gemini groundingMetadata 转换而来)
+    #[serde(rename = "source")]
+    Source {
+        url: String,
+        title: String,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -219,6 +230,15 @@ pub struct OutputConfig {
     pub effort: Option<String>,
 }
 
+/// 工具选择策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolChoice {
+    #[serde(rename = "type")]
+    pub type_: String, // "auto" | "any" | "tool" | "none"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>, // type 为 "tool" 时指定的工具名
+}
+
 /// Claude API 响应
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClaudeResponse {
@@ -280,6 +300,21 @@ pub struct GeminiPart {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "inlineData")]
     pub inline_data: Option<InlineData>,
+
+    /// 图像生成结果，以原生 image 内容块返回 (inlineData 仍以 Markdown 文本返回)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "imageGenerationOutput")]
+    pub image_generation_output: Option<ImageGenerationOutput>,
+
+    /// codeExecution 工具生成的代码
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "executableCode")]
+    pub executable_code: Option<ExecutableCode>,
+
+    /// codeExecution 工具的执行结果
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "codeExecutionResult")]
+    pub code_execution_result: Option<CodeExecutionResult>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -306,6 +341,30 @@ pub struct InlineData {
     pub data: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageGenerationOutput {
+    #[serde(rename = "mimeType", default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(alias = "bytesBase64Encoded")]
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutableCode {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeExecutionResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
 /// Gemini 完整响应
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeminiResponse {
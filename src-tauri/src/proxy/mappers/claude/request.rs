@@ -168,6 +168,7 @@ fn sort_thinking_blocks_first(messages: &mut [Message]) {
 pub fn transform_claude_request_in(
     claude_req: &ClaudeRequest,
     project_id: &str,
+    system_prompt_prefix: Option<&str>,
 ) -> Result<Value, String> {
     // [CRITICAL FIX] 预先清理所有消息中的 cache_control 字段
     // 这解决了 VS Code 插件等客户端在多轮对话中将历史消息的 cache_control 字段
@@ -199,7 +200,7 @@ pub fn transform_claude_request_in(
     let mut tool_id_to_name: HashMap<String, String> = HashMap::new();
 
     // 1. System Instruction (注入动态身份防护)
-    let system_instruction = build_system_instruction(&claude_req.system, &claude_req.model);
+    let system_instruction = build_system_instruction(&claude_req.system, &claude_req.model, system_prompt_prefix);
 
     //  Map model name (Use standard mapping)
     // [IMPROVED] 提取 web search 模型为常量，便于维护
@@ -351,12 +352,8 @@ pub fn transform_claude_request_in(
 
     if let Some(tools_val) = tools {
         inner_request["tools"] = tools_val;
-        // 显式设置工具配置模式为 VALIDATED
-        inner_request["toolConfig"] = json!({
-            "functionCallingConfig": {
-                "mode": "VALIDATED"
-            }
-        });
+        // 未指定 tool_choice 时显式设置工具配置模式为 VALIDATED
+        inner_request["toolConfig"] = build_tool_config_from_choice(claude_req.tool_choice.as_ref());
     }
 
     // Inject googleSearch tool if needed (and not already done by build_tools)
@@ -499,7 +496,11 @@ fn has_valid_signature_for_function_calls(
 }
 
 /// 构建 System Instruction (支持动态身份映射与 Prompt 隔离)
-fn build_system_instruction(system: &Option<SystemPrompt>, _model_name: &str) -> Option<Value> {
+fn build_system_instruction(
+    system: &Option<SystemPrompt>,
+    _model_name: &str,
+    system_prompt_prefix: Option<&str>,
+) -> Option<Value> {
     let mut parts = Vec::new();
 
     // [NEW] Antigravity 身份指令 (原始简化版)
@@ -533,6 +534,11 @@ fn build_system_instruction(system: &Option<SystemPrompt>, _model_name: &str) ->
         parts.push(json!({"text": antigravity_identity}));
     }
 
+    // 账号级系统提示词前缀 (位于用户系统提示词之前)
+    if let Some(prefix) = system_prompt_prefix {
+        parts.push(json!({"text": prefix}));
+    }
+
     // 添加用户的系统提示词
     if let Some(sys) = system {
         match sys {
@@ -705,10 +711,19 @@ fn build_contents(
                             parts.push(part);
                         }
                         ContentBlock::RedactedThinking { data } => {
-                            // [FIX] 将 RedactedThinking 作为普通文本处理，保留上下文
-                            tracing::debug!("[Claude-Request] Degrade RedactedThinking to text");
+                            // 与 Thinking 相同的约束: 必须是首个 part 且思维模式开启，否则降级为文本保留上下文
+                            if !is_thinking_enabled || !parts.is_empty() {
+                                tracing::debug!("[Claude-Request] Degrade RedactedThinking to text");
+                                parts.push(json!({
+                                    "text": format!("[Redacted Thinking: {}]", data)
+                                }));
+                                continue;
+                            }
+                            // Gemini 的等价形式: 无文本的 thought part，加密内容放入 thoughtSignature (已是 base64，原样传递)
                             parts.push(json!({
-                                "text": format!("[Redacted Thinking: {}]", data)
+                                "text": "",
+                                "thought": true,
+                                "thoughtSignature": data,
                             }));
                             continue;
                         }
@@ -863,7 +878,9 @@ fn build_contents(
                             parts.push(part);
                         }
                         // ContentBlock::RedactedThinking handled above at line 583
-                        ContentBlock::ServerToolUse { .. } | ContentBlock::WebSearchToolResult { .. } => {
+                        ContentBlock::ServerToolUse { .. }
+                        | ContentBlock::WebSearchToolResult { .. }
+                        | ContentBlock::Source { .. } => {
                             // 搜索结果 block 不应由客户端发回给上游 (已由 tool_result 替代)
                             continue;
                         }
@@ -1013,10 +1030,26 @@ fn merge_adjacent_roles(mut contents: Vec<Value>) -> Vec<Value> {
 }
 
 /// 构建 Tools
+/// 将 Claude tool_choice 映射为 Gemini toolConfig
+fn build_tool_config_from_choice(tool_choice: Option<&ToolChoice>) -> Value {
+    use crate::proxy::mappers::common_utils::build_tool_config;
+
+    match tool_choice {
+        None => build_tool_config("VALIDATED", None),
+        Some(choice) => match choice.type_.as_str() {
+            "any" => build_tool_config("ANY", None),
+            "none" => build_tool_config("NONE", None),
+            "tool" => build_tool_config("ANY", choice.name.as_deref()),
+            _ => build_tool_config("AUTO", None),
+        },
+    }
+}
+
 fn build_tools(tools: &Option<Vec<Tool>>, has_web_search: bool) -> Result<Option<Value>, String> {
     if let Some(tools_list) = tools {
         let mut function_declarations: Vec<Value> = Vec::new();
         let mut has_google_search = has_web_search;
+        let mut has_code_execution = false;
 
         for tool in tools_list {
             // 1. Detect server tools / built-in tools like web_search
@@ -1030,6 +1063,11 @@ fn build_tools(tools: &Option<Vec<Tool>>, has_web_search: bool) -> Result<Option
                     has_google_search = true;
                     continue;
                 }
+                // Anthropic code_execution_YYYYMMDD 服务端工具
+                if t_type.starts_with("code_execution") {
+                    has_code_execution = true;
+                    continue;
+                }
             }
 
             // 2. Detect by name
@@ -1038,8 +1076,19 @@ fn build_tools(tools: &Option<Vec<Tool>>, has_web_search: bool) -> Result<Option
                     has_google_search = true;
                     continue;
                 }
+                // 代码解释器映射为 Gemini 内置 codeExecution
+                if crate::proxy::mappers::common_utils::is_code_execution_tool_name(name) {
+                    has_code_execution = true;
+                    continue;
+                }
 
-                // 3. Client tools require input_schema
+                // 3. Anthropic 内置客户端工具 (computer / text_editor / bash) 没有 input_schema，使用手写 schema
+                if let Some(declaration) = builtin_tool_declaration(tool, name) {
+                    function_declarations.push(declaration);
+                    continue;
+                }
+
+                // 4. Client tools require input_schema
                 let mut input_schema = tool.input_schema.clone().unwrap_or(json!({
                     "type": "object",
                     "properties": {}
@@ -1076,14 +1125,110 @@ fn build_tools(tools: &Option<Vec<Tool>>, has_web_search: bool) -> Result<Option
             tool_obj.insert("googleSearch".to_string(), json!({}));
         }
 
+        let mut tools_out = Vec::new();
         if !tool_obj.is_empty() {
-            return Ok(Some(json!([tool_obj])));
+            tools_out.push(Value::Object(tool_obj));
+        }
+        if has_code_execution {
+            tools_out.push(json!({ "codeExecution": {} }));
+        }
+        if !tools_out.is_empty() {
+            return Ok(Some(Value::Array(tools_out)));
         }
     }
 
     Ok(None)
 }
 
+/// Anthropic 内置客户端工具 (computer_use / text_editor / bash) 映射为 Gemini functionDeclaration。
+/// 按 type 前缀识别；未带 type 时仅在没有 input_schema 的情况下按名称识别，避免覆盖同名自定义工具。
+fn builtin_tool_declaration(tool: &Tool, name: &str) -> Option<Value> {
+    let by_type = |prefix: &str| tool.type_.as_deref().is_some_and(|t| t.starts_with(prefix));
+    let by_name = |names: &[&str]| tool.input_schema.is_none() && names.contains(&name);
+
+    let (description, parameters) = if by_type("computer_") || by_name(&["computer", "computer_use"]) {
+        (
+            "Control the computer's mouse and keyboard and take screenshots of the screen.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": [
+                            "screenshot", "left_click", "right_click", "middle_click", "double_click",
+                            "left_click_drag", "type", "key", "scroll", "mouse_move", "cursor_position"
+                        ],
+                        "description": "The action to perform."
+                    },
+                    "coordinate": {
+                        "type": "array",
+                        "items": { "type": "integer" },
+                        "description": "[x, y] pixel position for click, drag, scroll and mouse_move actions."
+                    },
+                    "text": {
+                        "type": "string",
+                        "description": "Text to type, or the key combination for the key action (e.g. \"ctrl+s\")."
+                    },
+                    "scroll_direction": {
+                        "type": "string",
+                        "enum": ["up", "down", "left", "right"]
+                    },
+                    "scroll_amount": {
+                        "type": "integer",
+                        "description": "Number of scroll wheel clicks."
+                    }
+                },
+                "required": ["action"]
+            }),
+        )
+    } else if by_type("text_editor_")
+        || by_name(&["str_replace_editor", "str_replace_based_edit_tool", "text_editor"])
+    {
+        (
+            "View, create and edit files.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "command": {
+                        "type": "string",
+                        "enum": ["view", "create", "str_replace", "insert", "undo_edit"]
+                    },
+                    "path": { "type": "string", "description": "Absolute path of the file or directory." },
+                    "file_text": { "type": "string", "description": "Content of the file for the create command." },
+                    "old_str": { "type": "string", "description": "Exact text to replace for str_replace." },
+                    "new_str": { "type": "string", "description": "Replacement text for str_replace, or text to insert." },
+                    "insert_line": { "type": "integer", "description": "Line number after which new_str is inserted." },
+                    "view_range": {
+                        "type": "array",
+                        "items": { "type": "integer" },
+                        "description": "[start, end] line range for the view command."
+                    }
+                },
+                "required": ["command", "path"]
+            }),
+        )
+    } else if by_type("bash_") || by_name(&["bash", "bash_tool"]) {
+        (
+            "Run a command in a persistent bash shell.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string", "description": "The bash command to run." },
+                    "restart": { "type": "boolean", "description": "Restart the shell session." }
+                }
+            }),
+        )
+    } else {
+        return None;
+    };
+
+    Some(json!({
+        "name": name,
+        "description": tool.description.as_deref().unwrap_or(description),
+        "parameters": parameters
+    }))
+}
+
 /// 构建 Generation Config
 fn build_generation_config(
     claude_req: &ClaudeRequest,
@@ -1150,14 +1295,10 @@ fn build_generation_config(
     // max_tokens 映射为 maxOutputTokens
     config["maxOutputTokens"] = json!(64000);
 
-    // [优化] 设置全局停止序列,防止流式输出冗余
-    config["stopSequences"] = json!([
-        "<|user|>",
-        "<|endoftext|>",
-        "<|end_of_turn|>",
-        "[DONE]",
-        "\n\nHuman:"
-    ]);
+    // [优化] 设置全局停止序列,防止流式输出冗余 (与客户端 stop_sequences 合并)
+    config["stopSequences"] = json!(crate::proxy::mappers::common_utils::merge_stop_sequences(
+        claude_req.stop_sequences.as_deref().unwrap_or_default()
+    ));
 
     config
 }
@@ -1228,9 +1369,11 @@ mod tests {
             thinking: None,
             metadata: None,
             output_config: None,
+            tool_choice: None,
+            stop_sequences: None,
         };
 
-        let result = transform_claude_request_in(&req, "test-project");
+        let result = transform_claude_request_in(&req, "test-project", None);
         assert!(result.is_ok());
 
         let body = result.unwrap();
@@ -1282,6 +1425,50 @@ mod tests {
         assert_eq!(schema["properties"]["date"]["type"], "string");
     }
 
+    #[test]
+    fn test_builtin_client_tools_get_gemini_schemas() {
+        let tools: Vec<Tool> = serde_json::from_value(json!([
+            {"type": "computer_20250124", "name": "computer", "display_width_px": 1024, "display_height_px": 768},
+            {"type": "text_editor_20250124", "name": "str_replace_editor"},
+            {"type": "bash_20250124", "name": "bash"},
+            {"name": "bash", "input_schema": {"type": "object", "properties": {"cmd": {"type": "string"}}}}
+        ]))
+        .unwrap();
+
+        let out = build_tools(&Some(tools), false).unwrap().unwrap();
+        let decls = out[0]["functionDeclarations"].as_array().unwrap();
+        assert_eq!(decls.len(), 4);
+
+        let computer = &decls[0]["parameters"];
+        assert_eq!(computer["type"], "object");
+        assert_eq!(computer["required"], json!(["action"]));
+        let actions: Vec<&str> = computer["properties"]["action"]["enum"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|v| v.as_str())
+            .collect();
+        for action in ["screenshot", "left_click", "type", "key", "scroll", "mouse_move"] {
+            assert!(actions.contains(&action), "missing action {}", action);
+        }
+        // Gemini 要求数组类型声明 items
+        assert_eq!(computer["properties"]["coordinate"]["items"]["type"], "integer");
+
+        assert_eq!(decls[1]["name"], "str_replace_editor");
+        assert_eq!(decls[1]["parameters"]["required"], json!(["command", "path"]));
+        assert!(decls[2]["parameters"]["properties"]["command"].is_object());
+        assert!(decls[2]["description"].is_string());
+
+        // 自带 input_schema 的同名自定义工具保持原样
+        assert!(decls[3]["parameters"]["properties"]["cmd"].is_object());
+
+        // Gemini 不接受的 schema 关键字不应出现
+        let serialized = out.to_string();
+        for keyword in ["additionalProperties", "$schema", "display_width_px"] {
+            assert!(!serialized.contains(keyword), "unexpected {}", keyword);
+        }
+    }
+
     #[test]
     fn test_complex_tool_result() {
         let req = ClaudeRequest {
@@ -1325,9 +1512,11 @@ mod tests {
             thinking: None,
             metadata: None,
             output_config: None,
+            tool_choice: None,
+            stop_sequences: None,
         };
 
-        let result = transform_claude_request_in(&req, "test-project");
+        let result = transform_claude_request_in(&req, "test-project", None);
         assert!(result.is_ok());
 
         let body = result.unwrap();
@@ -1395,9 +1584,11 @@ mod tests {
             thinking: None,
             metadata: None,
             output_config: None,
+            tool_choice: None,
+            stop_sequences: None,
         };
 
-        let result = transform_claude_request_in(&req, "test-project");
+        let result = transform_claude_request_in(&req, "test-project", None);
         assert!(result.is_ok());
 
         // 验证请求成功转换
@@ -1470,9 +1661,11 @@ mod tests {
             }),
             metadata: None,
             output_config: None,
+            tool_choice: None,
+            stop_sequences: None,
         };
 
-        let result = transform_claude_request_in(&req, "test-project");
+        let result = transform_claude_request_in(&req, "test-project", None);
         assert!(result.is_ok());
 
         let body = result.unwrap();
@@ -1519,9 +1712,11 @@ mod tests {
             thinking: None, // 未启用 thinking
             metadata: None,
             output_config: None,
+            tool_choice: None,
+            stop_sequences: None,
         };
 
-        let result = transform_claude_request_in(&req, "test-project");
+        let result = transform_claude_request_in(&req, "test-project", None);
         assert!(result.is_ok());
 
         let body = result.unwrap();
@@ -1572,9 +1767,11 @@ mod tests {
             }),
             metadata: None,
             output_config: None,
+            tool_choice: None,
+            stop_sequences: None,
         };
 
-        let result = transform_claude_request_in(&req, "test-project");
+        let result = transform_claude_request_in(&req, "test-project", None);
         assert!(result.is_ok(), "Transformation failed");
         let body = result.unwrap();
         let contents = body["request"]["contents"].as_array().unwrap();
@@ -1612,9 +1809,11 @@ mod tests {
             thinking: None,
             metadata: None,
             output_config: None,
+            tool_choice: None,
+            stop_sequences: None,
         };
 
-        let result = transform_claude_request_in(&req, "test-project");
+        let result = transform_claude_request_in(&req, "test-project", None);
         assert!(result.is_ok());
         let body = result.unwrap();
         let parts = body["request"]["contents"][0]["parts"].as_array().unwrap();
@@ -1625,6 +1824,65 @@ mod tests {
         assert!(parts[0].get("thought").is_none(), "Redacted thinking should NOT have thought: true");
     }
 
+    #[test]
+    fn test_redacted_thinking_round_trip() {
+        use crate::proxy::mappers::claude::transform_response;
+
+        let data = "RW5jcnlwdGVkVGhvdWdodA==";
+        let req = ClaudeRequest {
+            model: "claude-sonnet-4-5".to_string(),
+            messages: vec![
+                Message {
+                    role: "user".to_string(),
+                    content: MessageContent::String("Hello".to_string()),
+                },
+                Message {
+                    role: "assistant".to_string(),
+                    content: MessageContent::Array(vec![
+                        ContentBlock::RedactedThinking { data: data.to_string() },
+                        ContentBlock::Text { text: "Hi".to_string() },
+                    ]),
+                },
+            ],
+            system: None,
+            tools: None,
+            stream: false,
+            max_tokens: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
+            thinking: Some(ThinkingConfig {
+                type_: "enabled".to_string(),
+                budget_tokens: Some(1024),
+            }),
+            metadata: None,
+            output_config: None,
+            tool_choice: None,
+            stop_sequences: None,
+        };
+
+        let body = transform_claude_request_in(&req, "test-project", None).unwrap();
+        let part = body["request"]["contents"][1]["parts"][0].clone();
+        assert_eq!(part["thought"], true);
+        assert_eq!(part["text"], "");
+        assert_eq!(part["thoughtSignature"], data);
+
+        // 上游原样返回该 part 时映射回 RedactedThinking
+        let gemini_resp: GeminiResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "content": { "role": "model", "parts": [part, {"text": "Answer"}] },
+                "finishReason": "STOP"
+            }]
+        }))
+        .unwrap();
+        let resp = transform_response(&gemini_resp).unwrap();
+        assert!(matches!(
+            &resp.content[0],
+            ContentBlock::RedactedThinking { data: d } if d == data
+        ));
+        assert!(matches!(&resp.content[1], ContentBlock::Text { text } if text == "Answer"));
+    }
+
     // ==================================================================================
     // [FIX #564] Test: Thinking blocks are sorted to be first after context compression
     // ==================================================================================
@@ -1692,5 +1950,92 @@ mod tests {
             assert!(matches!(blocks[1], ContentBlock::Text { .. }), "Text should still be second");
         }
     }
-}
 
+    #[test]
+    fn test_system_prompt_prefix_precedes_user_system() {
+        let system = Some(SystemPrompt::String("Be concise.".to_string()));
+        let instruction =
+            build_system_instruction(&system, "claude-sonnet-4-5", Some("Only discuss project X.")).unwrap();
+        let texts: Vec<&str> = instruction["parts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|p| p["text"].as_str())
+            .collect();
+
+        let prefix_pos = texts.iter().position(|t| *t == "Only discuss project X.").unwrap();
+        let user_pos = texts.iter().position(|t| *t == "Be concise.").unwrap();
+        assert!(texts[0].contains("You are Antigravity"));
+        assert!(prefix_pos < user_pos);
+    }
+
+    #[test]
+    fn test_tool_choice_maps_to_function_calling_config() {
+        let cases = [
+            (None, json!({"mode": "VALIDATED"})),
+            (Some(json!({"type": "auto"})), json!({"mode": "AUTO"})),
+            (Some(json!({"type": "any"})), json!({"mode": "ANY"})),
+            (Some(json!({"type": "none"})), json!({"mode": "NONE"})),
+            (
+                Some(json!({"type": "tool", "name": "get_weather"})),
+                json!({"mode": "ANY", "allowedFunctionNames": ["get_weather"]}),
+            ),
+        ];
+
+        for (tool_choice, expected) in cases {
+            let mut req_json = json!({
+                "model": "claude-sonnet-4-5",
+                "messages": [{"role": "user", "content": "Weather in Paris?"}],
+                "tools": [{
+                    "name": "get_weather",
+                    "description": "Get weather",
+                    "input_schema": {"type": "object", "properties": {"city": {"type": "string"}}}
+                }]
+            });
+            if let Some(choice) = tool_choice {
+                req_json["tool_choice"] = choice;
+            }
+            let req: ClaudeRequest = serde_json::from_value(req_json).unwrap();
+
+            let body = transform_claude_request_in(&req, "test-project", None).unwrap();
+            assert_eq!(body["request"]["toolConfig"]["functionCallingConfig"], expected);
+        }
+    }
+
+    #[test]
+    fn test_stop_sequences_merged_with_defaults() {
+        let req: ClaudeRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [{"role": "user", "content": "hi"}],
+            "stop_sequences": ["a", "b", "\n\nHuman:", "c", "d"]
+        }))
+        .unwrap();
+
+        let body = transform_claude_request_in(&req, "test-project", None).unwrap();
+        let stops = body["request"]["generationConfig"]["stopSequences"].as_array().unwrap().clone();
+        assert_eq!(stops[..4], [json!("a"), json!("b"), json!("\n\nHuman:"), json!("c")]);
+        assert!(!stops.contains(&json!("d")));
+        assert!(stops.contains(&json!("<|user|>")));
+        assert_eq!(stops.len(), 8);
+    }
+
+    #[test]
+    fn test_code_interpreter_maps_to_code_execution() {
+        let req: ClaudeRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [{"role": "user", "content": "Compute 2**100"}],
+            "tools": [
+                {"name": "code_interpreter", "input_schema": {"type": "object", "properties": {}}},
+                {"name": "get_weather", "input_schema": {"type": "object", "properties": {}}}
+            ]
+        }))
+        .unwrap();
+
+        let body = transform_claude_request_in(&req, "test-project", None).unwrap();
+        let tools = body["request"]["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[0]["functionDeclarations"].as_array().unwrap().len(), 1);
+        assert_eq!(tools[0]["functionDeclarations"][0]["name"], "get_weather");
+        assert_eq!(tools[1], json!({"codeExecution": {}}));
+    }
+}
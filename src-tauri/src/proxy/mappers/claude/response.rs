@@ -3,6 +3,9 @@
 
 use super::models::*;
 use super::utils::to_claude_usage;
+use crate::proxy::mappers::common_utils::{
+    format_code_execution_result, format_executable_code, image_media_type,
+};
 
 /// Known parameter remappings for Gemini → Claude compatibility
 /// [FIX] Gemini sometimes uses different parameter names than specified in tool schema
@@ -229,6 +232,16 @@ impl NonStreamingProcessor {
 
         // 2. Text 处理
         if let Some(text) = &part.text {
+            // 无文本、仅带签名的独立 thought part 对应 Claude 的 redacted_thinking (data 为原始 base64 签名)
+            if part.thought.unwrap_or(false) && text.is_empty() && self.thinking_builder.is_empty() {
+                if let Some(data) = &part.thought_signature {
+                    self.flush_text();
+                    self.flush_thinking();
+                    self.content_blocks.push(ContentBlock::RedactedThinking { data: data.clone() });
+                    return;
+                }
+            }
+
             if part.thought.unwrap_or(false) {
                 // Thinking part
                 self.flush_text();
@@ -295,6 +308,36 @@ impl NonStreamingProcessor {
                 self.flush_text();
             }
         }
+
+        // 4. imageGenerationOutput: 以原生 image 内容块返回
+        if let Some(img) = &part.image_generation_output {
+            self.flush_thinking();
+            self.flush_text();
+            if !img.data.is_empty() {
+                self.content_blocks.push(ContentBlock::Image {
+                    source: ImageSource {
+                        source_type: "base64".to_string(),
+                        media_type: image_media_type(img.mime_type.as_deref()).to_string(),
+                        data: img.data.clone(),
+                    },
+                    cache_control: None,
+                });
+            }
+        }
+
+        // 5. codeExecution: 代码与执行结果以 Markdown 文本返回
+        if let Some(code) = &part.executable_code {
+            self.flush_thinking();
+            self.text_builder
+                .push_str(&format_executable_code(code.language.as_deref(), &code.code));
+        }
+        if let Some(result) = &part.code_execution_result {
+            self.flush_thinking();
+            self.text_builder.push_str(&format_code_execution_result(
+                result.outcome.as_deref(),
+                result.output.as_deref(),
+            ));
+        }
     }
 
     /// 处理 Grounding 元数据 (Web Search 结果)
@@ -333,6 +376,18 @@ impl NonStreamingProcessor {
             self.text_builder.push_str(&grounding_text);
             self.flush_text();
         }
+
+        // 3. 来源链接同时以 source block 输出 (按 URL 去重)，便于客户端结构化展示
+        let mut seen = std::collections::HashSet::new();
+        for web in grounding.grounding_chunks.iter().flatten().filter_map(|c| c.web.as_ref()) {
+            let Some(url) = web.uri.as_deref() else { continue };
+            if seen.insert(url) {
+                self.content_blocks.push(ContentBlock::Source {
+                    url: url.to_string(),
+                    title: web.title.clone().unwrap_or_else(|| url.to_string()),
+                });
+            }
+        }
     }
 
     /// 刷新 text builder
@@ -373,7 +428,10 @@ impl NonStreamingProcessor {
             .and_then(|c| c.get(0))
             .and_then(|candidate| candidate.finish_reason.as_deref());
 
-        let stop_reason = if self.has_tool_call {
+        let recitation = finish_reason == Some("RECITATION");
+        let stop_reason = if recitation {
+            "content_filter"
+        } else if self.has_tool_call {
             "tool_use"
         } else if finish_reason == Some("MAX_TOKENS") {
             "max_tokens"
@@ -381,6 +439,13 @@ impl NonStreamingProcessor {
             "end_turn"
         };
 
+        let mut content = self.content_blocks.clone();
+        if recitation {
+            content.push(ContentBlock::Text {
+                text: super::RECITATION_BLOCKED_TEXT.to_string(),
+            });
+        }
+
         let usage = gemini_response
             .usage_metadata
             .as_ref()
@@ -400,7 +465,7 @@ impl NonStreamingProcessor {
             type_: "message".to_string(),
             role: "assistant".to_string(),
             model: gemini_response.model_version.clone().unwrap_or_default(),
-            content: self.content_blocks.clone(),
+            content,
             stop_reason: stop_reason.to_string(),
             stop_sequence: None,
             usage,
@@ -431,6 +496,9 @@ mod tests {
                         function_call: None,
                         function_response: None,
                         inline_data: None,
+                        image_generation_output: None,
+                        executable_code: None,
+                        code_execution_result: None,
                     }],
                 }),
                 finish_reason: Some("STOP".to_string()),
@@ -477,6 +545,9 @@ mod tests {
                             function_call: None,
                             function_response: None,
                             inline_data: None,
+                            image_generation_output: None,
+                            executable_code: None,
+                            code_execution_result: None,
                         },
                         GeminiPart {
                             text: Some("The answer is 42".to_string()),
@@ -485,6 +556,9 @@ mod tests {
                             function_call: None,
                             function_response: None,
                             inline_data: None,
+                            image_generation_output: None,
+                            executable_code: None,
+                            code_execution_result: None,
                         },
                     ],
                 }),
@@ -522,4 +596,80 @@ mod tests {
             _ => panic!("Expected Text block"),
         }
     }
+
+    #[test]
+    fn test_image_generation_output_as_image_block() {
+        let gemini_resp: GeminiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [
+                        {"text": "Generated:"},
+                        {"imageGenerationOutput": {"mimeType": "image/webp", "bytesBase64Encoded": "AAAA"}}
+                    ]
+                },
+                "finishReason": "STOP"
+            }]
+        }))
+        .unwrap();
+
+        let claude_resp = transform_response(&gemini_resp).unwrap();
+        assert_eq!(claude_resp.content.len(), 2);
+        match &claude_resp.content[1] {
+            ContentBlock::Image { source, .. } => {
+                assert_eq!(source.source_type, "base64");
+                assert_eq!(source.media_type, "image/webp");
+                assert_eq!(source.data, "AAAA");
+            }
+            _ => panic!("Expected Image block"),
+        }
+    }
+
+    #[test]
+    fn test_grounding_to_source_blocks() {
+        let gemini_resp: GeminiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": "Rust 1.0 was released in 2015."}]},
+                "finishReason": "STOP",
+                "groundingMetadata": {
+                    "webSearchQueries": ["rust 1.0 release"],
+                    "groundingChunks": [
+                        {"web": {"uri": "https://blog.rust-lang.org", "title": "Rust Blog"}},
+                        {"web": {"uri": "https://blog.rust-lang.org", "title": "Rust Blog"}}
+                    ]
+                }
+            }]
+        }))
+        .unwrap();
+
+        let claude_resp = transform_response(&gemini_resp).unwrap();
+        let sources: Vec<_> = claude_resp
+            .content
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::Source { url, title } => Some((url.as_str(), title.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sources, vec![("https://blog.rust-lang.org", "Rust Blog")]);
+        assert!(matches!(claude_resp.content.last(), Some(ContentBlock::Source { .. })));
+    }
+
+    #[test]
+    fn test_recitation_returns_notice_text() {
+        let gemini_resp: GeminiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": []},
+                "finishReason": "RECITATION"
+            }]
+        }))
+        .unwrap();
+
+        let claude_resp = transform_response(&gemini_resp).unwrap();
+        assert_eq!(claude_resp.stop_reason, "content_filter");
+        match claude_resp.content.as_slice() {
+            [ContentBlock::Text { text }] => assert_eq!(text, crate::proxy::mappers::claude::RECITATION_BLOCKED_TEXT),
+            other => panic!("Expected single Text block, got {:?}", other),
+        }
+    }
 }
@@ -3,6 +3,9 @@
 
 use super::models::*;
 use super::utils::to_claude_usage;
+use crate::proxy::mappers::common_utils::{
+    format_code_execution_result, format_executable_code, image_media_type,
+};
 // use crate::proxy::mappers::signature_store::store_thought_signature; // Deprecated
 use crate::proxy::SignatureCache;
 use bytes::Bytes;
@@ -113,6 +116,7 @@ pub enum BlockType {
     Text,
     Thinking,
     Function,
+    Image,
 }
 
 /// 签名管理器
@@ -382,8 +386,23 @@ impl StreamingState {
             }
         }
 
+        // RECITATION: 上游拒绝输出，补发提示文本块
+        let recitation = finish_reason == Some("RECITATION");
+        if recitation {
+            chunks.push(self.emit("content_block_start", json!({
+                "type": "content_block_start",
+                "index": self.block_index,
+                "content_block": { "type": "text", "text": "" }
+            })));
+            chunks.push(self.emit_delta("text_delta", json!({ "text": super::RECITATION_BLOCKED_TEXT })));
+            chunks.push(self.emit("content_block_stop", json!({ "type": "content_block_stop", "index": self.block_index })));
+            self.block_index += 1;
+        }
+
         // 确定 stop_reason
-        let stop_reason = if self.used_tool {
+        let stop_reason = if recitation {
+            "content_filter"
+        } else if self.used_tool {
             "tool_use"
         } else if finish_reason == Some("MAX_TOKENS") {
             "max_tokens"
@@ -602,6 +621,34 @@ impl<'a> PartProcessor<'a> {
             }
         }
 
+        // 4. imageGenerationOutput: 以完整 image 内容块发送 (无 delta)
+        if let Some(img) = &part.image_generation_output {
+            if !img.data.is_empty() {
+                chunks.extend(self.state.start_block(
+                    BlockType::Image,
+                    json!({
+                        "type": "image",
+                        "source": {
+                            "type": "base64",
+                            "media_type": image_media_type(img.mime_type.as_deref()),
+                            "data": img.data,
+                        }
+                    }),
+                ));
+                chunks.extend(self.state.end_block());
+            }
+        }
+
+        // 5. codeExecution: 代码与执行结果作为文本发送
+        if let Some(code) = &part.executable_code {
+            let text = format_executable_code(code.language.as_deref(), &code.code);
+            chunks.extend(self.process_text(&text, None));
+        }
+        if let Some(result) = &part.code_execution_result {
+            let text = format_code_execution_result(result.outcome.as_deref(), result.output.as_deref());
+            chunks.extend(self.process_text(&text, None));
+        }
+
         chunks
     }
 
@@ -851,6 +898,9 @@ mod tests {
             text: None,
             function_call: Some(fc),
             inline_data: None,
+            image_generation_output: None,
+            executable_code: None,
+            code_execution_result: None,
             thought: None,
             thought_signature: None,
             function_response: None,
@@ -878,4 +928,19 @@ mod tests {
         // 3. content_block_stop
         assert!(output.contains(r#""type":"content_block_stop""#));
     }
+
+    #[test]
+    fn test_emit_finish_recitation() {
+        let mut state = StreamingState::new();
+        let output = state
+            .emit_finish(Some("RECITATION"), None)
+            .iter()
+            .map(|b| String::from_utf8(b.to_vec()).unwrap())
+            .collect::<Vec<_>>()
+            .join("");
+
+        assert!(output.contains(r#""type":"text_delta""#));
+        assert!(output.contains(super::super::RECITATION_BLOCKED_TEXT));
+        assert!(output.contains(r#""stop_reason":"content_filter""#));
+    }
 }
@@ -117,6 +117,101 @@ fn parse_image_config(model_name: &str) -> (Value, String) {
 }
 
 /// Inject current googleSearch tool and ensure no duplicate legacy search tools
+/// 构建 Gemini toolConfig (mode: AUTO / ANY / NONE / VALIDATED)
+/// 指定函数名时强制调用该函数 (ANY + allowedFunctionNames)
+pub fn build_tool_config(mode: &str, allowed_function_name: Option<&str>) -> Value {
+    match allowed_function_name {
+        Some(name) => json!({
+            "functionCallingConfig": {
+                "mode": "ANY",
+                "allowedFunctionNames": [name]
+            }
+        }),
+        None => json!({
+            "functionCallingConfig": { "mode": mode }
+        }),
+    }
+}
+
+/// 生成图片的 media_type: Claude/OpenAI 支持 png/jpeg/gif/webp，其余按 PNG 处理
+pub fn image_media_type(mime_type: Option<&str>) -> &'static str {
+    match mime_type.map(|m| m.trim().to_ascii_lowercase()).as_deref() {
+        Some("image/jpeg") | Some("image/jpg") => "image/jpeg",
+        Some("image/gif") => "image/gif",
+        Some("image/webp") => "image/webp",
+        _ => "image/png",
+    }
+}
+
+/// 默认注入的停止序列，防止流式输出冗余
+const DEFAULT_STOP_SEQUENCES: [&str; 5] = [
+    "<|user|>",
+    "<|endoftext|>",
+    "<|end_of_turn|>",
+    "[DONE]",
+    "\n\nHuman:",
+];
+
+/// 客户端可指定的停止序列上限 (与 OpenAI 的 stop 限制一致)
+const MAX_CLIENT_STOP_SEQUENCES: usize = 4;
+
+/// 合并客户端停止序列与默认停止序列 (客户端在前，去重)；客户端超过 4 个时只保留前 4 个
+pub fn merge_stop_sequences(client: &[String]) -> Vec<String> {
+    if client.len() > MAX_CLIENT_STOP_SEQUENCES {
+        tracing::warn!(
+            "[Stop-Sequences] {} stop sequences supplied, keeping the first {}",
+            client.len(),
+            MAX_CLIENT_STOP_SEQUENCES
+        );
+    }
+    let mut merged: Vec<String> = Vec::new();
+    let client = client.iter().filter(|s| !s.is_empty()).take(MAX_CLIENT_STOP_SEQUENCES);
+    for seq in client.map(String::as_str).chain(DEFAULT_STOP_SEQUENCES) {
+        if !merged.iter().any(|s| s == seq) {
+            merged.push(seq.to_string());
+        }
+    }
+    merged
+}
+
+/// 映射为 Gemini 内置 codeExecution 的客户端工具名
+const CODE_EXECUTION_TOOL_NAMES: [&str; 2] = ["code_interpreter", "python_interpreter"];
+
+pub fn is_code_execution_tool_name(name: &str) -> bool {
+    CODE_EXECUTION_TOOL_NAMES.contains(&name)
+}
+
+/// executableCode part 转为 Markdown 代码块
+pub fn format_executable_code(language: Option<&str>, code: &str) -> String {
+    let language = language.unwrap_or("python").to_ascii_lowercase();
+    format!("\n```{}\n{}\n```\n", language, code.trim_end())
+}
+
+/// codeExecutionResult part 转为 Markdown 输出块，执行失败时附带 outcome
+pub fn format_code_execution_result(outcome: Option<&str>, output: Option<&str>) -> String {
+    let output = output.unwrap_or_default().trim_end();
+    match outcome {
+        None | Some("OUTCOME_OK") => format!("\n```\n{}\n```\n", output),
+        Some(outcome) => format!("\n**Code execution failed ({})**\n```\n{}\n```\n", outcome, output),
+    }
+}
+
+/// 从原始 Gemini part 中提取代码执行内容 (executableCode / codeExecutionResult)
+pub fn code_execution_part_text(part: &Value) -> Option<String> {
+    if let Some(code) = part.get("executableCode") {
+        return Some(format_executable_code(
+            code.get("language").and_then(|v| v.as_str()),
+            code.get("code").and_then(|v| v.as_str()).unwrap_or_default(),
+        ));
+    }
+    part.get("codeExecutionResult").map(|result| {
+        format_code_execution_result(
+            result.get("outcome").and_then(|v| v.as_str()),
+            result.get("output").and_then(|v| v.as_str()),
+        )
+    })
+}
+
 pub fn inject_google_search_tool(body: &mut Value) {
     if let Some(obj) = body.as_object_mut() {
         let tools_entry = obj.entry("tools").or_insert_with(|| json!([]));
@@ -267,6 +362,17 @@ pub fn contains_non_networking_tool(tools: &Option<Vec<Value>>) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_merge_stop_sequences() {
+        let defaults = merge_stop_sequences(&[]);
+        assert_eq!(defaults.len(), DEFAULT_STOP_SEQUENCES.len());
+
+        let client: Vec<String> = ["1", "2", "", "3", "4", "5", "6"].iter().map(|s| s.to_string()).collect();
+        let merged = merge_stop_sequences(&client);
+        assert_eq!(merged[..4], ["1", "2", "3", "4"]);
+        assert_eq!(merged.len(), 4 + DEFAULT_STOP_SEQUENCES.len());
+    }
+
     #[test]
     fn test_high_quality_model_auto_grounding() {
         // Auto-grounding is currently disabled by default due to conflict with image gen
@@ -138,8 +138,125 @@ pub fn wrap_request(body: &Value, project_id: &str, mapped_model: &str) -> Value
 }
 
 /// 解包响应（提取 response 字段）
+///
+/// v1internal 偶尔将 groundingMetadata 放在 response 同级，解包时并入首个候选，避免联网搜索来源丢失
 pub fn unwrap_response(response: &Value) -> Value {
-    response.get("response").unwrap_or(response).clone()
+    let mut inner = response.get("response").unwrap_or(response).clone();
+    if let Some(grounding) = response.get("groundingMetadata").filter(|_| response.get("response").is_some()) {
+        if let Some(candidate) = inner
+            .get_mut("candidates")
+            .and_then(|c| c.get_mut(0))
+            .and_then(|c| c.as_object_mut())
+        {
+            candidate
+                .entry("groundingMetadata")
+                .or_insert_with(|| grounding.clone());
+        }
+    }
+    inner
+}
+
+/// 从 (已解包的) 响应中提取联网搜索来源，按 URL 去重: [{"url": "...", "title": "..."}]
+pub fn grounding_citations(response: &Value) -> Vec<Value> {
+    let mut seen = std::collections::HashSet::new();
+    response
+        .get("candidates")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|cand| cand.get("groundingMetadata"))
+        .filter_map(|g| g.get("groundingChunks").and_then(|c| c.as_array()))
+        .flatten()
+        .filter_map(|chunk| chunk.get("web"))
+        .filter_map(|web| {
+            let url = web.get("uri").and_then(|v| v.as_str())?;
+            let title = web.get("title").and_then(|v| v.as_str()).unwrap_or(url);
+            seen.insert(url.to_string())
+                .then(|| json!({"url": url, "title": title}))
+        })
+        .collect()
+}
+
+/// 单张图片/文件的估算 token 数 (与 Gemini 对图像的固定计费一致)
+const INLINE_DATA_TOKEN_ESTIMATE: u64 = 258;
+
+/// 包装 countTokens 请求为 v1internal 格式
+///
+/// `inner_request` 可以是原生 Gemini 请求 (`contents` / `systemInstruction`)，
+/// 也可以是 `{"generateContentRequest": {...}}` 形式。
+/// systemInstruction 会作为首条 user 消息并入 contents，保证计数完整。
+pub fn wrap_count_tokens_request(inner_request: &Value, mapped_model: &str) -> Value {
+    let inner = inner_request
+        .get("generateContentRequest")
+        .unwrap_or(inner_request);
+
+    let mut contents: Vec<Value> = Vec::new();
+    if let Some(parts) = inner
+        .get("systemInstruction")
+        .and_then(|s| s.get("parts"))
+        .and_then(|p| p.as_array())
+    {
+        if !parts.is_empty() {
+            contents.push(json!({"role": "user", "parts": parts}));
+        }
+    }
+    if let Some(arr) = inner.get("contents").and_then(|c| c.as_array()) {
+        contents.extend(arr.iter().cloned());
+    }
+
+    let mut request = json!({
+        "model": format!("models/{}", mapped_model),
+        "contents": contents
+    });
+    crate::proxy::mappers::common_utils::deep_clean_undefined(&mut request);
+
+    json!({ "request": request })
+}
+
+/// 从 countTokens 响应中提取 totalTokens (兼容 v1internal 包装)
+pub fn extract_total_tokens(response: &Value) -> Option<u64> {
+    let inner = response.get("response").unwrap_or(response);
+    inner.get("totalTokens").and_then(|v| {
+        v.as_u64()
+            .or_else(|| v.as_str().and_then(|s| s.parse::<u64>().ok()))
+    })
+}
+
+/// 本地估算 token 数 (上游 countTokens 失败时的兜底)
+///
+/// 按约 4 字符 / token 估算文本，inlineData/fileData 按固定值计。
+pub fn estimate_tokens(inner_request: &Value) -> u64 {
+    fn walk(value: &Value, chars: &mut u64, media: &mut u64) {
+        match value {
+            Value::String(s) => *chars += s.chars().count() as u64,
+            Value::Array(arr) => arr.iter().for_each(|v| walk(v, chars, media)),
+            Value::Object(obj) => {
+                for (key, v) in obj {
+                    match key.as_str() {
+                        "inlineData" | "fileData" => *media += 1,
+                        // 元数据字段不计入
+                        "role" | "mimeType" | "thoughtSignature" => {}
+                        _ => walk(v, chars, media),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let inner = inner_request
+        .get("generateContentRequest")
+        .unwrap_or(inner_request);
+
+    let mut chars = 0u64;
+    let mut media = 0u64;
+    for key in ["systemInstruction", "contents", "tools"] {
+        if let Some(v) = inner.get(key) {
+            walk(v, &mut chars, &mut media);
+        }
+    }
+
+    chars.div_ceil(4) + media * INLINE_DATA_TOKEN_ESTIMATE
 }
 
 #[cfg(test)]
@@ -173,6 +290,71 @@ mod tests {
         assert!(result.get("response").is_none());
     }
 
+    #[test]
+    fn test_unwrap_response_preserves_grounding() {
+        let chunks = json!([
+            {"web": {"uri": "https://a.example", "title": "A"}},
+            {"web": {"uri": "https://a.example", "title": "A again"}},
+            {"web": {"uri": "https://b.example"}}
+        ]);
+        let wrapped = json!({
+            "response": {"candidates": [{"content": {"parts": [{"text": "Hi"}]}}]},
+            "groundingMetadata": {"groundingChunks": chunks}
+        });
+
+        let result = unwrap_response(&wrapped);
+        assert_eq!(result["candidates"][0]["groundingMetadata"]["groundingChunks"], chunks);
+        assert_eq!(
+            grounding_citations(&result),
+            vec![
+                json!({"url": "https://a.example", "title": "A"}),
+                json!({"url": "https://b.example", "title": "https://b.example"}),
+            ]
+        );
+        assert!(grounding_citations(&json!({"candidates": [{}]})).is_empty());
+    }
+
+    #[test]
+    fn test_wrap_count_tokens_request() {
+        let body = json!({
+            "systemInstruction": {"parts": [{"text": "Be brief"}]},
+            "contents": [{"role": "user", "parts": [{"text": "Hi"}]}]
+        });
+
+        let result = wrap_count_tokens_request(&body, "gemini-2.5-flash");
+        assert_eq!(result["request"]["model"], "models/gemini-2.5-flash");
+        let contents = result["request"]["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 2);
+        assert_eq!(contents[0]["parts"][0]["text"], "Be brief");
+
+        // generateContentRequest 形式
+        let nested = json!({"generateContentRequest": {"contents": [{"parts": [{"text": "Hi"}]}]}});
+        let result = wrap_count_tokens_request(&nested, "gemini-2.5-flash");
+        assert_eq!(result["request"]["contents"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_extract_total_tokens() {
+        assert_eq!(extract_total_tokens(&json!({"response": {"totalTokens": 42}})), Some(42));
+        assert_eq!(extract_total_tokens(&json!({"totalTokens": "7"})), Some(7));
+        assert_eq!(extract_total_tokens(&json!({"error": {}})), None);
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        let body = json!({
+            "contents": [{
+                "role": "user",
+                "parts": [
+                    {"text": "abcdefgh"},
+                    {"inlineData": {"mimeType": "image/png", "data": "AAAA"}}
+                ]
+            }]
+        });
+        assert_eq!(estimate_tokens(&body), 2 + INLINE_DATA_TOKEN_ESTIMATE);
+        assert_eq!(estimate_tokens(&json!({})), 0);
+    }
+
     #[test]
     fn test_antigravity_identity_injection_with_role() {
         let body = json!({
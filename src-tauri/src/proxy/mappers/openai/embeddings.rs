@@ -0,0 +1,136 @@
+// OpenAI Embeddings ↔ Gemini batchEmbedContents 转换
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// OpenAI embedding 模型默认映射到的 Gemini 模型
+pub const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-004";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    Single(String),
+    Batch(Vec<String>),
+}
+
+impl EmbeddingInput {
+    pub fn into_vec(self) -> Vec<String> {
+        match self {
+            EmbeddingInput::Single(s) => vec![s],
+            EmbeddingInput::Batch(v) => v,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenAIEmbeddingRequest {
+    pub model: String,
+    pub input: EmbeddingInput,
+    #[serde(default)]
+    pub dimensions: Option<u32>,
+}
+
+/// 解析目标 Gemini embedding 模型: 别名优先，Gemini 原生模型名直通，其余使用默认模型
+pub fn resolve_embedding_model(model: &str, model_aliases: &HashMap<String, String>) -> String {
+    if let Some(target) = model_aliases.get(model) {
+        return target.clone();
+    }
+    let model = model.trim_start_matches("models/");
+    if model.starts_with("text-embedding-0")
+        || model.starts_with("gemini-embedding")
+        || model.starts_with("embedding-")
+    {
+        return model.to_string();
+    }
+    DEFAULT_EMBEDDING_MODEL.to_string()
+}
+
+/// 构建 batchEmbedContents 请求体 (多个输入合并为一次上游调用)
+pub fn build_batch_embed_request(model: &str, inputs: &[String], dimensions: Option<u32>) -> Value {
+    let requests: Vec<Value> = inputs
+        .iter()
+        .map(|text| {
+            let mut req = json!({
+                "model": format!("models/{}", model),
+                "content": { "parts": [{ "text": text }] }
+            });
+            if let Some(dim) = dimensions {
+                req["outputDimensionality"] = json!(dim);
+            }
+            req
+        })
+        .collect();
+    json!({ "requests": requests })
+}
+
+/// 转换为 OpenAI embeddings 响应 (上游不返回 token 用量，按字符数估算)
+pub fn transform_embed_response(
+    gemini_resp: &Value,
+    requested_model: &str,
+    inputs: &[String],
+) -> Result<Value, String> {
+    let embeddings = gemini_resp
+        .get("embeddings")
+        .and_then(|v| v.as_array())
+        .ok_or("Upstream response missing embeddings")?;
+
+    let data: Vec<Value> = embeddings
+        .iter()
+        .enumerate()
+        .map(|(index, e)| {
+            json!({
+                "object": "embedding",
+                "embedding": e.get("values").cloned().unwrap_or_else(|| json!([])),
+                "index": index,
+            })
+        })
+        .collect();
+
+    let prompt_tokens: usize = inputs.iter().map(|s| s.chars().count().div_ceil(4)).sum();
+    Ok(json!({
+        "object": "list",
+        "data": data,
+        "model": requested_model,
+        "usage": {
+            "prompt_tokens": prompt_tokens,
+            "total_tokens": prompt_tokens,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_request_and_response() {
+        let req: OpenAIEmbeddingRequest =
+            serde_json::from_value(json!({"model": "text-embedding-ada-002", "input": ["a", "bb"]})).unwrap();
+        let inputs = req.input.into_vec();
+        assert_eq!(inputs.len(), 2);
+
+        let single: OpenAIEmbeddingRequest =
+            serde_json::from_value(json!({"model": "x", "input": "hello"})).unwrap();
+        assert_eq!(single.input.into_vec(), vec!["hello".to_string()]);
+
+        let mut aliases = HashMap::new();
+        assert_eq!(resolve_embedding_model("text-embedding-ada-002", &aliases), DEFAULT_EMBEDDING_MODEL);
+        assert_eq!(resolve_embedding_model("gemini-embedding-001", &aliases), "gemini-embedding-001");
+        aliases.insert("text-embedding-3-large".to_string(), "gemini-embedding-001".to_string());
+        assert_eq!(resolve_embedding_model("text-embedding-3-large", &aliases), "gemini-embedding-001");
+
+        let body = build_batch_embed_request(DEFAULT_EMBEDDING_MODEL, &inputs, Some(256));
+        assert_eq!(body["requests"][1]["content"]["parts"][0]["text"], "bb");
+        assert_eq!(body["requests"][0]["model"], "models/text-embedding-004");
+        assert_eq!(body["requests"][0]["outputDimensionality"], 256);
+
+        let upstream = json!({"embeddings": [{"values": [0.1, 0.2]}, {"values": [0.3]}]});
+        let resp = transform_embed_response(&upstream, "text-embedding-ada-002", &inputs).unwrap();
+        assert_eq!(resp["object"], "list");
+        assert_eq!(resp["data"][1]["index"], 1);
+        assert_eq!(resp["data"][0]["embedding"], json!([0.1, 0.2]));
+        assert_eq!(resp["usage"]["prompt_tokens"], 2);
+        assert!(transform_embed_response(&json!({}), "m", &inputs).is_err());
+    }
+}
@@ -13,6 +13,8 @@ pub struct OpenAIRequest {
     #[serde(default)]
     pub stream: bool,
     #[serde(default)]
+    pub stream_options: Option<StreamOptions>,
+    #[serde(default)]
     pub n: Option<u32>, // [NEW] 支持多候选结果数量
     #[serde(rename = "max_tokens")]
     pub max_tokens: Option<u32>,
@@ -30,6 +32,16 @@ pub struct OpenAIRequest {
     // Codex proprietary fields
     pub instructions: Option<String>,
     pub input: Option<Value>,
+    /// 厂商扩展: 原样合并进 Gemini generationConfig (如 seed / responseLogprobs)，同名字段覆盖默认值
+    #[serde(default)]
+    pub x_gemini_config: Option<Value>,
+}
+
+/// 流式选项 (include_usage: 在 [DONE] 前追加一个携带 usage 的空 choices chunk)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamOptions {
+    #[serde(default)]
+    pub include_usage: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,6 +71,21 @@ pub enum OpenAIContentBlock {
     AudioUrl {
         audio_url: AudioUrlContent,
     },
+    #[serde(rename = "file")]
+    File {
+        file: OpenAIFileContent,
+    },
+}
+
+/// 文件引用: 通过 /v1/files 上传得到的 file_id，或内联的 data URI
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OpenAIFileContent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_data: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -3,7 +3,12 @@ use super::models::*;
 use serde_json::{json, Value};
 use super::streaming::get_thought_signature;
 
-pub fn transform_openai_request(request: &OpenAIRequest, project_id: &str, mapped_model: &str) -> Value {
+pub fn transform_openai_request(
+    request: &OpenAIRequest,
+    project_id: &str,
+    mapped_model: &str,
+    system_prompt_prefix: Option<&str>,
+) -> Value {
     // 将 OpenAI 工具转为 Value 数组以便探测
     let tools_val = request.tools.as_ref().map(|list| {
         list.iter().map(|v| v.clone()).collect::<Vec<_>>()
@@ -97,6 +102,9 @@ pub fn transform_openai_request(request: &OpenAIRequest, project_id: &str, mappe
                                         parts.push(json!({
                                             "fileData": { "fileUri": &image_url.url, "mimeType": "image/jpeg" }
                                         }));
+                                    } else if let Some(part) = bare_base64_image_part(&image_url.url) {
+                                        // 部分客户端直接发送不带 data: 前缀的 base64
+                                        parts.push(part);
                                     } else {
                                         // [NEW] 处理本地文件路径 (file:// 或 Windows/Unix 路径)
                                         let file_path = if image_url.url.starts_with("file://") {
@@ -142,6 +150,11 @@ pub fn transform_openai_request(request: &OpenAIRequest, project_id: &str, mappe
                                     // 这会与 v3.3.16 的 thinkingConfig 逻辑冲突，留待后续版本实现
                                     tracing::debug!("[OpenAI-Request] Skipping audio_url (not yet implemented in v3.3.16)");
                                 }
+                                OpenAIContentBlock::File { file } => {
+                                    if let Some(part) = file_content_to_part(file) {
+                                        parts.push(part);
+                                    }
+                                }
                             }
                         }
                     }
@@ -243,10 +256,13 @@ pub fn transform_openai_request(request: &OpenAIRequest, project_id: &str, mappe
     }
 
 
-    if let Some(stop) = &request.stop {
-        if stop.is_string() { gen_config["stopSequences"] = json!([stop]); }
-        else if stop.is_array() { gen_config["stopSequences"] = stop.clone(); }
-    }
+    // stop 可为字符串或数组，与默认停止序列合并
+    let client_stops: Vec<String> = match &request.stop {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Array(items)) => items.iter().filter_map(|v| v.as_str().map(str::to_string)).collect(),
+        _ => Vec::new(),
+    };
+    gen_config["stopSequences"] = json!(crate::proxy::mappers::common_utils::merge_stop_sequences(&client_stops));
 
     if let Some(fmt) = &request.response_format {
         if fmt.r#type == "json_object" {
@@ -254,6 +270,16 @@ pub fn transform_openai_request(request: &OpenAIRequest, project_id: &str, mappe
         }
     }
 
+    // 厂商扩展字段 x_gemini_config 最后合并，覆盖上面计算出的默认值
+    if let (Some(extra), Some(gen_obj)) = (
+        request.x_gemini_config.as_ref().and_then(|v| v.as_object()),
+        gen_config.as_object_mut(),
+    ) {
+        for (key, value) in extra {
+            gen_obj.insert(key.clone(), value.clone());
+        }
+    }
+
     let mut inner_request = json!({
         "contents": contents,
         "generationConfig": gen_config,
@@ -272,7 +298,21 @@ pub fn transform_openai_request(request: &OpenAIRequest, project_id: &str, mappe
     // 4. Handle Tools (Merged Cleaning)
     if let Some(tools) = &request.tools {
         let mut function_declarations: Vec<Value> = Vec::new();
+        let mut has_code_execution = false;
         for tool in tools.iter() {
+            // OpenAI code_interpreter 工具映射为 Gemini 内置 codeExecution
+            let tool_name = tool
+                .get("function")
+                .unwrap_or(tool)
+                .get("name")
+                .and_then(|v| v.as_str());
+            if tool.get("type").and_then(|v| v.as_str()) == Some("code_interpreter")
+                || tool_name.is_some_and(crate::proxy::mappers::common_utils::is_code_execution_tool_name)
+            {
+                has_code_execution = true;
+                continue;
+            }
+
             let mut gemini_func = if let Some(func) = tool.get("function") {
                 func.clone()
             } else {
@@ -325,8 +365,18 @@ pub fn transform_openai_request(request: &OpenAIRequest, project_id: &str, mappe
             function_declarations.push(gemini_func);
         }
         
+        let mut gemini_tools = Vec::new();
         if !function_declarations.is_empty() {
-            inner_request["tools"] = json!([{ "functionDeclarations": function_declarations }]);
+            gemini_tools.push(json!({ "functionDeclarations": function_declarations }));
+            if let Some(tool_config) = request.tool_choice.as_ref().and_then(build_tool_config_from_choice) {
+                inner_request["toolConfig"] = tool_config;
+            }
+        }
+        if has_code_execution {
+            gemini_tools.push(json!({ "codeExecution": {} }));
+        }
+        if !gemini_tools.is_empty() {
+            inner_request["tools"] = Value::Array(gemini_tools);
         }
     }
     
@@ -347,7 +397,10 @@ pub fn transform_openai_request(request: &OpenAIRequest, project_id: &str, mappe
         parts.push(json!({"text": antigravity_identity}));
     }
 
-    // 2. 追加用户指令 (作为独立 Parts)
+    // 2. 账号级系统提示词前缀 + 用户指令 (作为独立 Parts)
+    if let Some(prefix) = system_prompt_prefix {
+        parts.push(json!({"text": prefix}));
+    }
     for inst in system_instructions {
         parts.push(json!({"text": inst}));
     }
@@ -385,6 +438,94 @@ pub fn transform_openai_request(request: &OpenAIRequest, project_id: &str, mappe
     })
 }
 
+/// 将 OpenAI 文件引用转换为 Gemini part
+/// - `file_data` (data URI) 直接内联
+/// - `file_id` 查找 /v1/files 上传记录，转为 fileData 引用
+fn file_content_to_part(file: &OpenAIFileContent) -> Option<Value> {
+    if let Some(data_uri) = file.file_data.as_deref().and_then(|d| d.strip_prefix("data:")) {
+        let (meta, data) = data_uri.split_once(',')?;
+        let mime_type = meta.split(';').next().filter(|m| !m.is_empty()).unwrap_or("application/octet-stream");
+        return Some(json!({ "inlineData": { "mimeType": mime_type, "data": data } }));
+    }
+
+    let file_id = file.file_id.as_deref()?;
+    // 本地文件 (Batch API) 没有可供上游引用的 URI
+    match crate::proxy::file_store::FileStore::global().get(file_id).filter(|f| !f.is_local()) {
+        Some(stored) => Some(json!({
+            "fileData": { "fileUri": stored.uri, "mimeType": stored.mime_type }
+        })),
+        None => {
+            tracing::warn!("[OpenAI-Request] Unknown or expired file_id: {}", file_id);
+            None
+        }
+    }
+}
+
+/// 识别不带 `data:` 前缀的裸 base64 图片 (长度 > 100、无空白、仅含 base64 字符)，
+/// 解码后按文件头推断 MIME 类型，无法识别时按 image/jpeg 处理
+fn bare_base64_image_part(url: &str) -> Option<Value> {
+    use base64::Engine as _;
+    use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+
+    let trimmed = url.trim_end_matches('=');
+    let looks_like_base64 = url.len() > 100
+        && trimmed
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/');
+    if !looks_like_base64 {
+        return None;
+    }
+
+    let bytes = STANDARD
+        .decode(url)
+        .or_else(|_| STANDARD_NO_PAD.decode(trimmed))
+        .ok()?;
+    Some(json!({
+        "inlineData": { "mimeType": sniff_image_mime(&bytes), "data": STANDARD.encode(&bytes) }
+    }))
+}
+
+/// 根据文件头 (magic bytes) 推断图片 MIME 类型
+fn sniff_image_mime(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "image/jpeg"
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        "image/gif"
+    } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        "image/webp"
+    } else {
+        "image/jpeg"
+    }
+}
+
+/// 将 OpenAI tool_choice 映射为 Gemini toolConfig
+/// 支持 "auto" / "none" / "required" 及 {"type":"function","function":{"name":...}}
+fn build_tool_config_from_choice(tool_choice: &Value) -> Option<Value> {
+    use crate::proxy::mappers::common_utils::build_tool_config;
+
+    match tool_choice {
+        Value::String(mode) => match mode.as_str() {
+            "auto" => Some(build_tool_config("AUTO", None)),
+            "none" => Some(build_tool_config("NONE", None)),
+            "required" => Some(build_tool_config("ANY", None)),
+            _ => None,
+        },
+        Value::Object(obj) => {
+            let name = obj
+                .get("function")
+                .and_then(|f| f.get("name"))
+                .or(obj.get("name"))
+                .and_then(|v| v.as_str())?;
+            // 与工具声明保持一致的重命名
+            let name = if name == "local_shell_call" { "shell" } else { name };
+            Some(build_tool_config("ANY", Some(name)))
+        }
+        _ => None,
+    }
+}
+
 fn enforce_uppercase_types(value: &mut Value) {
     if let Value::Object(map) = value {
         if let Some(type_val) = map.get_mut("type") {
@@ -432,6 +573,7 @@ mod tests {
                 name: None,
             }],
             stream: false,
+            stream_options: None,
             n: None,
             max_tokens: None,
             temperature: None,
@@ -444,12 +586,165 @@ mod tests {
             instructions: None,
             input: None,
             prompt: None,
+            x_gemini_config: None,
         };
 
-        let result = transform_openai_request(&req, "test-v", "gemini-1.5-flash");
+        let result = transform_openai_request(&req, "test-v", "gemini-1.5-flash", None);
         let parts = &result["request"]["contents"][0]["parts"];
         assert_eq!(parts.as_array().unwrap().len(), 2);
         assert_eq!(parts[0]["text"].as_str().unwrap(), "What is in this image?");
         assert_eq!(parts[1]["inlineData"]["mimeType"].as_str().unwrap(), "image/png");
     }
+
+    #[test]
+    fn test_bare_base64_image_part() {
+        use base64::Engine as _;
+        let encode = |header: &[u8]| {
+            let mut bytes = header.to_vec();
+            bytes.resize(120, 0x42);
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        };
+
+        let png = bare_base64_image_part(&encode(b"\x89PNG\r\n\x1a\n")).unwrap();
+        assert_eq!(png["inlineData"]["mimeType"], "image/png");
+        assert_eq!(png["inlineData"]["data"], encode(b"\x89PNG\r\n\x1a\n"));
+
+        let jpeg = bare_base64_image_part(&encode(&[0xFF, 0xD8, 0xFF, 0xE0])).unwrap();
+        assert_eq!(jpeg["inlineData"]["mimeType"], "image/jpeg");
+
+        // 无法识别的格式默认按 JPEG 处理；缺少填充同样可解码
+        let unknown = encode(b"not-an-image");
+        let part = bare_base64_image_part(unknown.trim_end_matches('=')).unwrap();
+        assert_eq!(part["inlineData"]["mimeType"], "image/jpeg");
+
+        // 过短、含空白或非 base64 字符 (如本地路径) 均不视为 base64
+        assert!(bare_base64_image_part("aGVsbG8=").is_none());
+        assert!(bare_base64_image_part(&format!("{} {}", unknown, unknown)).is_none());
+        assert!(bare_base64_image_part(&format!("/home/user/{}.png", "a".repeat(100))).is_none());
+    }
+
+    #[test]
+    fn test_file_content_to_part() {
+        use crate::proxy::file_store::{FileOwner, FileStore, StoredFile};
+
+        let inline = OpenAIFileContent {
+            file_id: None,
+            filename: Some("a.pdf".to_string()),
+            file_data: Some("data:application/pdf;base64,JVBERi0=".to_string()),
+        };
+        let part = file_content_to_part(&inline).unwrap();
+        assert_eq!(part["inlineData"]["mimeType"], "application/pdf");
+        assert_eq!(part["inlineData"]["data"], "JVBERi0=");
+
+        let stored = StoredFile::from_google(
+            &json!({ "name": "files/xyz", "uri": "https://example.com/files/xyz", "mimeType": "text/plain" }),
+            "notes.txt",
+            "user_data",
+            "a@example.com",
+            FileOwner::default(),
+        )
+        .unwrap();
+        let by_id = OpenAIFileContent { file_id: Some(stored.id.clone()), filename: None, file_data: None };
+        FileStore::global().insert(stored);
+        let part = file_content_to_part(&by_id).unwrap();
+        assert_eq!(part["fileData"]["fileUri"], "https://example.com/files/xyz");
+
+        let missing = OpenAIFileContent { file_id: Some("file-missing".to_string()), filename: None, file_data: None };
+        assert!(file_content_to_part(&missing).is_none());
+    }
+
+    #[test]
+    fn test_x_gemini_config_merged_into_generation_config() {
+        let req: OpenAIRequest = serde_json::from_value(json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "hi"}],
+            "temperature": 0.2,
+            "x_gemini_config": {"seed": 123, "responseLogprobs": true, "temperature": 0.7}
+        }))
+        .unwrap();
+
+        let result = transform_openai_request(&req, "test-v", "gemini-2.5-flash", None);
+        let gen_config = &result["request"]["generationConfig"];
+        assert_eq!(gen_config["seed"], 123);
+        assert_eq!(gen_config["responseLogprobs"], true);
+        assert_eq!(gen_config["temperature"], 0.7);
+        assert_eq!(gen_config["topP"], 1.0);
+    }
+
+    #[test]
+    fn test_stop_sequences_truncated_and_merged() {
+        let req: OpenAIRequest = serde_json::from_value(json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "hi"}],
+            "stop": ["END", "[DONE]", "STOP", "END", "extra-1", "extra-2"]
+        }))
+        .unwrap();
+
+        let result = transform_openai_request(&req, "test-v", "gemini-2.5-flash", None);
+        let stops = result["request"]["generationConfig"]["stopSequences"].as_array().unwrap().clone();
+        // 只保留前 4 个 (去重后为 END / [DONE] / STOP)，其后追加默认停止序列
+        assert_eq!(stops[..3], [json!("END"), json!("[DONE]"), json!("STOP")]);
+        assert!(!stops.contains(&json!("extra-1")));
+        assert!(stops.contains(&json!("<|endoftext|>")));
+        assert_eq!(stops.iter().filter(|s| *s == "[DONE]").count(), 1);
+
+        let req: OpenAIRequest = serde_json::from_value(json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "hi"}],
+            "stop": "END"
+        }))
+        .unwrap();
+        let result = transform_openai_request(&req, "test-v", "gemini-2.5-flash", None);
+        assert_eq!(result["request"]["generationConfig"]["stopSequences"][0], "END");
+    }
+
+    #[test]
+    fn test_tool_choice_maps_to_function_calling_config() {
+        let cases = [
+            (json!("auto"), Some(json!({"mode": "AUTO"}))),
+            (json!("none"), Some(json!({"mode": "NONE"}))),
+            (json!("required"), Some(json!({"mode": "ANY"}))),
+            (
+                json!({"type": "function", "function": {"name": "get_weather"}}),
+                Some(json!({"mode": "ANY", "allowedFunctionNames": ["get_weather"]})),
+            ),
+            (Value::Null, None),
+        ];
+
+        for (tool_choice, expected) in cases {
+            let req: OpenAIRequest = serde_json::from_value(json!({
+                "model": "gpt-4o",
+                "messages": [{"role": "user", "content": "Weather in Paris?"}],
+                "tools": [{
+                    "type": "function",
+                    "function": {
+                        "name": "get_weather",
+                        "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+                    }
+                }],
+                "tool_choice": tool_choice
+            }))
+            .unwrap();
+
+            let result = transform_openai_request(&req, "test-v", "gemini-2.5-flash", None);
+            assert_eq!(
+                result["request"]["toolConfig"].get("functionCallingConfig").cloned(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_code_interpreter_tool_maps_to_code_execution() {
+        let req: OpenAIRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "Plot a sine wave"}],
+            "tools": [{"type": "code_interpreter"}]
+        }))
+        .unwrap();
+
+        let result = transform_openai_request(&req, "test-v", "gemini-2.5-flash", None);
+        assert_eq!(result["request"]["tools"], json!([{"codeExecution": {}}]));
+        assert!(result["request"].get("toolConfig").is_none());
+    }
 }
@@ -1,7 +1,37 @@
 // OpenAI 协议响应转换模块
 use super::models::*;
+use crate::proxy::mappers::common_utils::{code_execution_part_text, image_media_type};
 use serde_json::Value;
 
+/// LM Studio 兼容模式 (?lmstudio=1) 下模型 ID 的前缀
+pub const LMSTUDIO_MODEL_PREFIX: &str = "lm-studio/";
+
+/// 将 Gemini finishReason (或已是 OpenAI 形式的值) 规范化为 OpenAI finish_reason
+pub fn normalize_finish_reason(reason: &str) -> &'static str {
+    match reason.to_ascii_uppercase().as_str() {
+        "MAX_TOKENS" | "LENGTH" => "length",
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" | "CONTENT_FILTER" => {
+            "content_filter"
+        }
+        "TOOL_CALLS" => "tool_calls",
+        _ => "stop",
+    }
+}
+
+/// LM Studio 模式下返回给客户端的模型 ID (lm-studio/<model>)
+pub fn to_lmstudio_model_id(model: &str) -> String {
+    if model.starts_with(LMSTUDIO_MODEL_PREFIX) {
+        model.to_string()
+    } else {
+        format!("{}{}", LMSTUDIO_MODEL_PREFIX, model)
+    }
+}
+
+/// 去除客户端请求中的 lm-studio/ 前缀
+pub fn strip_lmstudio_prefix(model: &str) -> &str {
+    model.strip_prefix(LMSTUDIO_MODEL_PREFIX).unwrap_or(model)
+}
+
 pub fn transform_openai_response(gemini_response: &Value) -> OpenAIResponse {
     // 解包 response 字段
     let raw = gemini_response.get("response").unwrap_or(gemini_response);
@@ -14,6 +44,7 @@ pub fn transform_openai_response(gemini_response: &Value) -> OpenAIResponse {
             let mut content_out = String::new();
             let mut thought_out = String::new();
             let mut tool_calls = Vec::new();
+            let mut images = Vec::new();
 
             // 提取 content 和 tool_calls
             if let Some(parts) = candidate
@@ -50,10 +81,12 @@ pub fn transform_openai_response(gemini_response: &Value) -> OpenAIResponse {
                     // 工具调用部分
                     if let Some(fc) = part.get("functionCall") {
                         let name = fc.get("name").and_then(|v| v.as_str()).unwrap_or("unknown");
-                        let args = fc
-                            .get("args")
-                            .map(|v| v.to_string())
-                            .unwrap_or_else(|| "{}".to_string());
+                        // arguments 为 JSON 字符串；上游已是字符串时不再二次编码
+                        let args = match fc.get("args") {
+                            Some(Value::String(s)) => s.clone(),
+                            Some(v) if !v.is_null() => v.to_string(),
+                            _ => "{}".to_string(),
+                        };
                         let id = fc
                             .get("id")
                             .and_then(|v| v.as_str())
@@ -81,6 +114,29 @@ pub fn transform_openai_response(gemini_response: &Value) -> OpenAIResponse {
                             content_out.push_str(&format!("![image](data:{};base64,{})", mime_type, data));
                         }
                     }
+
+                    // 代码执行 (executableCode / codeExecutionResult)
+                    if let Some(text) = code_execution_part_text(part) {
+                        content_out.push_str(&text);
+                    }
+
+                    // 图像生成结果 (imageGenerationOutput)，以 image_url 内容块返回
+                    if let Some(img) = part.get("imageGenerationOutput") {
+                        let data = img
+                            .get("data")
+                            .or(img.get("bytesBase64Encoded"))
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("");
+                        if !data.is_empty() {
+                            let mime_type = image_media_type(img.get("mimeType").and_then(|v| v.as_str()));
+                            images.push(OpenAIContentBlock::ImageUrl {
+                                image_url: OpenAIImageUrl {
+                                    url: format!("data:{};base64,{}", mime_type, data),
+                                    detail: None,
+                                },
+                            });
+                        }
+                    }
                 }
             }
 
@@ -123,23 +179,30 @@ pub fn transform_openai_response(gemini_response: &Value) -> OpenAIResponse {
             }
 
             // 提取该候选结果的 finish_reason
-            let finish_reason = candidate
+            // Gemini 返回 functionCall 时 finishReason 仍为 STOP，按 OpenAI 规范改为 tool_calls
+            let finish_reason = match candidate
                 .get("finishReason")
                 .and_then(|f| f.as_str())
-                .map(|f| match f {
-                    "STOP" => "stop",
-                    "MAX_TOKENS" => "length",
-                    "SAFETY" => "content_filter",
-                    "RECITATION" => "content_filter",
-                    _ => "stop",
-                })
-                .unwrap_or("stop");
+                .map(normalize_finish_reason)
+                .unwrap_or("stop")
+            {
+                "stop" if !tool_calls.is_empty() => "tool_calls",
+                reason => reason,
+            };
 
             choices.push(Choice {
                 index: idx as u32,
                 message: OpenAIMessage {
                     role: "assistant".to_string(),
-                    content: if content_out.is_empty() {
+                    content: if !images.is_empty() {
+                        // 含生成图片时使用数组形式: 文本在前，图片在后
+                        let mut blocks = Vec::new();
+                        if !content_out.is_empty() {
+                            blocks.push(OpenAIContentBlock::Text { text: content_out });
+                        }
+                        blocks.extend(images);
+                        Some(OpenAIContent::Array(blocks))
+                    } else if content_out.is_empty() {
                         None
                     } else {
                         Some(OpenAIContent::String(content_out))
@@ -206,4 +269,109 @@ mod tests {
         assert_eq!(content, "Hello!");
         assert_eq!(result.choices[0].finish_reason, Some("stop".to_string()));
     }
+
+    #[test]
+    fn test_function_call_sets_tool_calls_finish_reason() {
+        let gemini_resp = json!({
+            "candidates": [{
+                "content": {
+                    "parts": [
+                        {"functionCall": {"name": "get_weather", "args": {"city": "Paris"}, "id": "call_1"}},
+                        {"functionCall": {"name": "get_time", "args": "{\"tz\":\"CET\"}"}}
+                    ]
+                },
+                "finishReason": "STOP"
+            }]
+        });
+
+        let result = transform_openai_response(&gemini_resp);
+        let choice = &result.choices[0];
+        assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+        let tool_calls = choice.message.tool_calls.as_ref().unwrap();
+        assert_eq!(tool_calls.len(), 2);
+        assert_eq!(tool_calls[0].id, "call_1");
+        assert_eq!(tool_calls[0].function.name, "get_weather");
+        let args: Value = serde_json::from_str(&tool_calls[0].function.arguments).unwrap();
+        assert_eq!(args, json!({"city": "Paris"}));
+        assert_eq!(tool_calls[1].function.arguments, "{\"tz\":\"CET\"}");
+
+        // MAX_TOKENS / SAFETY 优先于 tool_calls
+        for (reason, expected) in [("MAX_TOKENS", "length"), ("SAFETY", "content_filter")] {
+            let mut resp = gemini_resp.clone();
+            resp["candidates"][0]["finishReason"] = json!(reason);
+            let result = transform_openai_response(&resp);
+            assert_eq!(result.choices[0].finish_reason.as_deref(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn test_image_generation_output_as_image_url() {
+        let gemini_resp = json!({
+            "candidates": [{
+                "content": {
+                    "parts": [
+                        {"text": "Here you go"},
+                        {"imageGenerationOutput": {"mimeType": "image/webp", "data": "AAAA"}}
+                    ]
+                },
+                "finishReason": "STOP"
+            }]
+        });
+
+        let result = transform_openai_response(&gemini_resp);
+        match result.choices[0].message.content.as_ref().unwrap() {
+            OpenAIContent::Array(blocks) => {
+                assert_eq!(blocks[0], OpenAIContentBlock::Text { text: "Here you go".to_string() });
+                assert_eq!(
+                    blocks[1],
+                    OpenAIContentBlock::ImageUrl {
+                        image_url: OpenAIImageUrl {
+                            url: "data:image/webp;base64,AAAA".to_string(),
+                            detail: None,
+                        }
+                    }
+                );
+            }
+            _ => panic!("Expected array content"),
+        }
+    }
+
+    #[test]
+    fn test_finish_reason_and_lmstudio_model_id() {
+        assert_eq!(normalize_finish_reason("STOP"), "stop");
+        assert_eq!(normalize_finish_reason("stop"), "stop");
+        assert_eq!(normalize_finish_reason("MAX_TOKENS"), "length");
+        assert_eq!(normalize_finish_reason("PROHIBITED_CONTENT"), "content_filter");
+        assert_eq!(normalize_finish_reason("RECITATION"), "content_filter");
+        assert_eq!(normalize_finish_reason("FINISH_REASON_UNSPECIFIED"), "stop");
+
+        assert_eq!(to_lmstudio_model_id("gemini-2.5-pro"), "lm-studio/gemini-2.5-pro");
+        assert_eq!(to_lmstudio_model_id("lm-studio/gemini-2.5-pro"), "lm-studio/gemini-2.5-pro");
+        assert_eq!(strip_lmstudio_prefix("lm-studio/gemini-2.5-pro"), "gemini-2.5-pro");
+        assert_eq!(strip_lmstudio_prefix("gemini-2.5-pro"), "gemini-2.5-pro");
+    }
+
+    #[test]
+    fn test_code_execution_parts_as_text() {
+        let gemini_resp = json!({
+            "candidates": [{
+                "content": {
+                    "parts": [
+                        {"executableCode": {"language": "PYTHON", "code": "print(2 ** 10)"}},
+                        {"codeExecutionResult": {"outcome": "OUTCOME_OK", "output": "1024\n"}}
+                    ]
+                },
+                "finishReason": "STOP"
+            }]
+        });
+
+        let result = transform_openai_response(&gemini_resp);
+        match result.choices[0].message.content.as_ref().unwrap() {
+            OpenAIContent::String(text) => {
+                assert!(text.contains("```python\nprint(2 ** 10)\n```"));
+                assert!(text.contains("```\n1024\n```"));
+            }
+            _ => panic!("Expected string content"),
+        }
+    }
 }
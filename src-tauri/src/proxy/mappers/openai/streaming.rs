@@ -1,13 +1,14 @@
 // OpenAI 流式转换
-use bytes::{Bytes, BytesMut};
+use bytes::Bytes;
 use futures::{Stream, StreamExt};
 use serde_json::{json, Value};
 use std::pin::Pin;
 use std::sync::{Mutex, OnceLock};
 use chrono::Utc;
 use uuid::Uuid;
-use tracing::debug;
 use rand::Rng;
+use super::response::normalize_finish_reason;
+use crate::proxy::upstream::sse_parser::SseParser;
 
 // === 全局 ThoughtSignature 存储 ===
 // 用于在流式响应和后续请求之间传递签名，避免嵌入到用户可见的文本中
@@ -50,188 +51,271 @@ pub fn get_thought_signature() -> Option<String> {
     }
 }
 
-pub fn create_openai_sse_stream(
-    mut gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
+/// OpenAI 流式转换状态 (所有 chunk 共用流开始时生成的 id 与 timestamp)
+struct OpenAIStreamState {
+    stream_id: String,
+    created_ts: i64,
     model: String,
-) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> {
-    let mut buffer = BytesMut::new();
-    
-    // 在流开始时生成固定的 ID 和 timestamp，所有 chunk 共用
-    let stream_id = format!("chatcmpl-{}", Uuid::new_v4());
-    let created_ts = Utc::now().timestamp();
-    
-    let stream = async_stream::stream! {
-        while let Some(item) = gemini_stream.next().await {
-            match item {
-                Ok(bytes) => {
-                    // Verbose logging for debugging image fragmentation
-                    debug!("[OpenAI-SSE] Received chunk: {} bytes", bytes.len());
-                    buffer.extend_from_slice(&bytes);
-                    
-                    // Process complete lines from buffer
-                    while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
-                        let line_raw = buffer.split_to(pos + 1);
-                        if let Ok(line_str) = std::str::from_utf8(&line_raw) {
-                            let line = line_str.trim();
-                            if line.is_empty() { continue; }
-
-                            if line.starts_with("data: ") {
-                                let json_part = line.trim_start_matches("data: ").trim();
-                                if json_part == "[DONE]" {
-                                    continue;
+    include_usage: bool,
+    /// 最近一次上游事件携带的 usageMetadata (stream_options.include_usage 时在结尾输出)
+    last_usage: Option<Value>,
+}
+
+impl OpenAIStreamState {
+    fn new(model: String, include_usage: bool) -> Self {
+        Self {
+            stream_id: format!("chatcmpl-{}", Uuid::new_v4()),
+            created_ts: Utc::now().timestamp(),
+            model,
+            include_usage,
+            last_usage: None,
+        }
+    }
+
+    /// 处理单个上游 SSE 事件的 data 字段，返回需要发送的 OpenAI chunk
+    fn process_event(&mut self, data: &str) -> Vec<Bytes> {
+        let mut out = Vec::new();
+        let json_part = data.trim();
+        if json_part.is_empty() || json_part == "[DONE]" {
+            return out;
+        }
+        let stream_id = &self.stream_id;
+        let created_ts = self.created_ts;
+        let model = &self.model;
+        let include_usage = self.include_usage;
+
+        if let Ok(mut json) = serde_json::from_str::<Value>(json_part) {
+            // Log raw chunk for debugging gemini-3 thoughts
+            tracing::debug!("Gemini SSE Chunk: {}", json_part);
+
+            // Handle v1internal wrapper if present
+            let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) {
+                inner
+            } else {
+                json
+            };
+
+            if let Some(usage) = actual_data.get("usageMetadata") {
+                self.last_usage = Some(usage.clone());
+            }
+
+            // Extract candidates
+            if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
+                for (idx, candidate) in candidates.iter().enumerate() {
+                    let parts = candidate.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array());
+
+                    let mut content_out = String::new();
+                    let mut thought_out = String::new();
+
+                    if let Some(parts_list) = parts {
+                        for part in parts_list {
+                            let is_thought_part = part.get("thought")
+                                .and_then(|v| v.as_bool())
+                                .unwrap_or(false);
+
+                            if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                                if is_thought_part {
+                                    thought_out.push_str(text);
+                                } else {
+                                    content_out.push_str(text);
                                 }
+                            }
+                            // 捕获 thoughtSignature (Gemini 3 工具调用必需)
+                            if let Some(sig) = part.get("thoughtSignature").or(part.get("thought_signature")).and_then(|s| s.as_str()) {
+                                store_thought_signature(sig);
+                            }
 
-                                if let Ok(mut json) = serde_json::from_str::<Value>(json_part) {
-                                    // Log raw chunk for debugging gemini-3 thoughts
-                                    tracing::debug!("Gemini SSE Chunk: {}", json_part);
-
-                                    // Handle v1internal wrapper if present
-                                    let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) {
-                                        inner
-                                    } else {
-                                        json
-                                    };
-
-                                    // Extract candidates
-                                    if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
-                                        for (idx, candidate) in candidates.iter().enumerate() {
-                                            let parts = candidate.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array());
-
-                                            let mut content_out = String::new();
-                                            let mut thought_out = String::new();
-                                            
-                                            if let Some(parts_list) = parts {
-                                                for part in parts_list {
-                                                    let is_thought_part = part.get("thought")
-                                                        .and_then(|v| v.as_bool())
-                                                        .unwrap_or(false);
-                                                    
-                                                    if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
-                                                        if is_thought_part {
-                                                            thought_out.push_str(text);
-                                                        } else {
-                                                            content_out.push_str(text);
-                                                        }
-                                                    }
-                                                    // 捕获 thoughtSignature (Gemini 3 工具调用必需)
-                                                    if let Some(sig) = part.get("thoughtSignature").or(part.get("thought_signature")).and_then(|s| s.as_str()) {
-                                                        store_thought_signature(sig);
-                                                    }
+                            if let Some(img) = part.get("inlineData") {
+                                let mime_type = img.get("mimeType").and_then(|v| v.as_str()).unwrap_or("image/png");
+                                let data = img.get("data").and_then(|v| v.as_str()).unwrap_or("");
+                                if !data.is_empty() {
+                                    content_out.push_str(&format!("![image](data:{};base64,{})", mime_type, data));
+                                }
+                            }
 
-                                                    if let Some(img) = part.get("inlineData") {
-                                                        let mime_type = img.get("mimeType").and_then(|v| v.as_str()).unwrap_or("image/png");
-                                                        let data = img.get("data").and_then(|v| v.as_str()).unwrap_or("");
-                                                        if !data.is_empty() {
-                                                            content_out.push_str(&format!("![image](data:{};base64,{})", mime_type, data));
-                                                        }
-                                                    }
-                                                }
-                                            }
+                            if let Some(text) = crate::proxy::mappers::common_utils::code_execution_part_text(part) {
+                                content_out.push_str(&text);
+                            }
+                        }
+                    }
 
 
-                                            // 处理联网搜索引文 (Grounding Metadata) - 流式
-                                            if let Some(grounding) = candidate.get("groundingMetadata") {
-                                                let mut grounding_text = String::new();
-                                                
-                                                // 1. 处理搜索词
-                                                if let Some(queries) = grounding.get("webSearchQueries").and_then(|q| q.as_array()) {
-                                                    let query_list: Vec<&str> = queries.iter().filter_map(|v| v.as_str()).collect();
-                                                    if !query_list.is_empty() {
-                                                        grounding_text.push_str("\n\n---\n**🔍 已为您搜索：** ");
-                                                        grounding_text.push_str(&query_list.join(", "));
-                                                    }
-                                                }
+                    // 处理联网搜索引文 (Grounding Metadata) - 流式
+                    if let Some(grounding) = candidate.get("groundingMetadata") {
+                        let mut grounding_text = String::new();
 
-                                                // 2. 处理来源链接 (Chunks)
-                                                if let Some(chunks) = grounding.get("groundingChunks").and_then(|c| c.as_array()) {
-                                                    let mut links = Vec::new();
-                                                    for (i, chunk) in chunks.iter().enumerate() {
-                                                        if let Some(web) = chunk.get("web") {
-                                                            let title = web.get("title").and_then(|v| v.as_str()).unwrap_or("网页来源");
-                                                            let uri = web.get("uri").and_then(|v| v.as_str()).unwrap_or("#");
-                                                            links.push(format!("[{}] [{}]({})", i + 1, title, uri));
-                                                        }
-                                                    }
-                                                    if !links.is_empty() {
-                                                        grounding_text.push_str("\n\n**🌐 来源引文：**\n");
-                                                        grounding_text.push_str(&links.join("\n"));
-                                                    }
-                                                }
-                                                
-                                                if !grounding_text.is_empty() {
-                                                    content_out.push_str(&grounding_text);
-                                                }
-                                            }
+                        // 1. 处理搜索词
+                        if let Some(queries) = grounding.get("webSearchQueries").and_then(|q| q.as_array()) {
+                            let query_list: Vec<&str> = queries.iter().filter_map(|v| v.as_str()).collect();
+                            if !query_list.is_empty() {
+                                grounding_text.push_str("\n\n---\n**🔍 已为您搜索：** ");
+                                grounding_text.push_str(&query_list.join(", "));
+                            }
+                        }
 
-                                            // 只有当 content 和 thought 都为空时才跳过
-                                            if content_out.is_empty() && thought_out.is_empty() {
-                                                // Skip empty chunks if no text/grounding/thought was found
-                                                if candidate.get("finishReason").is_none() {
-                                                    continue;
-                                                }
-                                            }
-                                                
-                                            // Extract finish reason
-                                            let finish_reason = candidate.get("finishReason")
-                                                .and_then(|f| f.as_str())
-                                                .map(|f| match f {
-                                                    "STOP" => "stop",
-                                                    "MAX_TOKENS" => "length",
-                                                    "SAFETY" => "content_filter",
-                                                    "RECITATION" => "content_filter",
-                                                    _ => f,
-                                                });
-
-                                            // Construct OpenAI SSE chunk
-                                            // 如果有思考内容，先发送 reasoning_content chunk
-                                            if !thought_out.is_empty() {
-                                                let reasoning_chunk = json!({
-                                                    "id": &stream_id,
-                                                    "object": "chat.completion.chunk",
-                                                    "created": created_ts,
-                                                    "model": model,
-                                                    "choices": [
-                                                        {
-                                                            "index": idx as u32,
-                                                            "delta": {
-                                                                "role": "assistant",
-                                                                "content": serde_json::Value::Null,
-                                                                "reasoning_content": thought_out
-                                                            },
-                                                            "finish_reason": serde_json::Value::Null
-                                                        }
-                                                    ]
-                                                });
-                                                let sse_out = format!("data: {}\n\n", serde_json::to_string(&reasoning_chunk).unwrap_or_default());
-                                                yield Ok::<Bytes, String>(Bytes::from(sse_out));
-                                            }
+                        // 2. 处理来源链接 (Chunks)
+                        if let Some(chunks) = grounding.get("groundingChunks").and_then(|c| c.as_array()) {
+                            let mut links = Vec::new();
+                            for (i, chunk) in chunks.iter().enumerate() {
+                                if let Some(web) = chunk.get("web") {
+                                    let title = web.get("title").and_then(|v| v.as_str()).unwrap_or("网页来源");
+                                    let uri = web.get("uri").and_then(|v| v.as_str()).unwrap_or("#");
+                                    links.push(format!("[{}] [{}]({})", i + 1, title, uri));
+                                }
+                            }
+                            if !links.is_empty() {
+                                grounding_text.push_str("\n\n**🌐 来源引文：**\n");
+                                grounding_text.push_str(&links.join("\n"));
+                            }
+                        }
 
-                                            // 发送正常 content chunk
-                                            if !content_out.is_empty() || finish_reason.is_some() {
-                                                let openai_chunk = json!({
-                                                    "id": &stream_id,
-                                                    "object": "chat.completion.chunk",
-                                                    "created": created_ts,
-                                                    "model": model,
-                                                    "choices": [
-                                                        {
-                                                            "index": idx as u32,
-                                                            "delta": {
-                                                                "content": content_out
-                                                            },
-                                                            "finish_reason": finish_reason
-                                                        }
-                                                    ]
-                                                });
+                        if !grounding_text.is_empty() {
+                            content_out.push_str(&grounding_text);
+                        }
+                    }
 
-                                                let sse_out = format!("data: {}\n\n", serde_json::to_string(&openai_chunk).unwrap_or_default());
-                                                yield Ok::<Bytes, String>(Bytes::from(sse_out));
-                                            }
-                                        }
-                                    }
+                    // 只有当 content 和 thought 都为空时才跳过
+                    if content_out.is_empty() && thought_out.is_empty() {
+                        // Skip empty chunks if no text/grounding/thought was found
+                        if candidate.get("finishReason").is_none() {
+                            continue;
+                        }
+                    }
+
+                    // Extract finish reason
+                    let finish_reason = candidate.get("finishReason")
+                        .and_then(|f| f.as_str())
+                        .map(normalize_finish_reason);
+
+                    // Construct OpenAI SSE chunk
+                    // 如果有思考内容，先发送 reasoning_content chunk
+                    if !thought_out.is_empty() {
+                        let mut reasoning_chunk = json!({
+                            "id": &stream_id,
+                            "object": "chat.completion.chunk",
+                            "created": created_ts,
+                            "model": model,
+                            "choices": [
+                                {
+                                    "index": idx as u32,
+                                    "delta": {
+                                        "role": "assistant",
+                                        "content": serde_json::Value::Null,
+                                        "reasoning_content": thought_out
+                                    },
+                                    "finish_reason": serde_json::Value::Null
                                 }
+                            ]
+                        });
+                        if include_usage {
+                            reasoning_chunk["usage"] = Value::Null;
+                        }
+                        let sse_out = format!("data: {}\n\n", serde_json::to_string(&reasoning_chunk).unwrap_or_default());
+                        out.push(Bytes::from(sse_out));
+                    }
+
+                    // 发送正常 content chunk
+                    if !content_out.is_empty() || finish_reason.is_some() {
+                        let mut openai_chunk = json!({
+                            "id": &stream_id,
+                            "object": "chat.completion.chunk",
+                            "created": created_ts,
+                            "model": model,
+                            "choices": [
+                                {
+                                    "index": idx as u32,
+                                    "delta": {
+                                        "content": content_out
+                                    },
+                                    "finish_reason": finish_reason
+                                }
+                            ]
+                        });
+                        if include_usage {
+                            openai_chunk["usage"] = Value::Null;
+                        }
+
+                        let sse_out = format!("data: {}\n\n", serde_json::to_string(&openai_chunk).unwrap_or_default());
+                        out.push(Bytes::from(sse_out));
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// 结尾事件: usage chunk (include_usage 时) 与 [DONE]
+    fn finish(&self) -> Vec<Bytes> {
+        let mut out = Vec::new();
+        if self.include_usage {
+            let usage_chunk = json!({
+                "id": &self.stream_id,
+                "object": "chat.completion.chunk",
+                "created": self.created_ts,
+                "model": self.model,
+                "choices": [],
+                "usage": openai_usage(self.last_usage.as_ref())
+            });
+            let sse_out = format!("data: {}\n\n", serde_json::to_string(&usage_chunk).unwrap_or_default());
+            out.push(Bytes::from(sse_out));
+        }
+        // End of stream signal for OpenAI
+        out.push(Bytes::from("data: [DONE]\n\n"));
+        out
+    }
+}
+
+pub fn create_openai_sse_stream(
+    gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
+    model: String,
+    include_usage: bool,
+) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> {
+    openai_sse_stream_with(
+        gemini_stream,
+        OpenAIStreamState::new(model, include_usage),
+        OpenAIStreamState::process_event,
+    )
+}
+
+/// 以指定的事件处理函数驱动 OpenAI SSE 流
+/// 处理函数 panic 时补发结尾事件 (usage / error / [DONE]) 后结束流，避免客户端无限等待
+fn openai_sse_stream_with<F>(
+    gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
+    mut state: OpenAIStreamState,
+    mut process: F,
+) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>>
+where
+    F: FnMut(&mut OpenAIStreamState, &str) -> Vec<Bytes> + Send + 'static,
+{
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let stream = async_stream::stream! {
+        let mut events = SseParser::new(gemini_stream);
+        while let Some(item) = events.next().await {
+            match item {
+                Ok(event) => {
+                    match catch_unwind(AssertUnwindSafe(|| process(&mut state, &event.data))) {
+                        Ok(chunks) => {
+                            for chunk in chunks {
+                                yield Ok::<Bytes, String>(chunk);
                             }
                         }
+                        Err(_) => {
+                            tracing::error!("[OpenAI-Stream] 流处理发生 panic，发送结尾事件后结束流");
+                            let mut terminal = state.finish();
+                            let done = terminal.pop();
+                            for chunk in terminal {
+                                yield Ok::<Bytes, String>(chunk);
+                            }
+                            yield Ok::<Bytes, String>(Bytes::from(format!(
+                                "data: {}\n\n",
+                                crate::proxy::mappers::claude::STREAM_PROCESSING_ERROR
+                            )));
+                            if let Some(done) = done {
+                                yield Ok::<Bytes, String>(done);
+                            }
+                            return;
+                        }
                     }
                 }
                 Err(e) => {
@@ -239,19 +323,39 @@ pub fn create_openai_sse_stream(
                 }
             }
         }
-        // End of stream signal for OpenAI
-        yield Ok::<Bytes, String>(Bytes::from("data: [DONE]\n\n"));
+        for chunk in state.finish() {
+            yield Ok::<Bytes, String>(chunk);
+        }
     };
 
     Box::pin(stream)
 }
 
+/// Gemini usageMetadata -> OpenAI usage (缺失字段按 0 计)
+fn openai_usage(usage: Option<&Value>) -> Value {
+    let count = |key: &str| {
+        usage
+            .and_then(|u| u.get(key))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+    };
+    let prompt_tokens = count("promptTokenCount");
+    let completion_tokens = count("candidatesTokenCount");
+    let total_tokens = usage
+        .and_then(|u| u.get("totalTokenCount"))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(prompt_tokens + completion_tokens);
+    json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": total_tokens
+    })
+}
+
 pub fn create_legacy_sse_stream(
-    mut gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
+    gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
     model: String,
 ) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> {
-    let mut buffer = BytesMut::new();
-    
     // Generate constant alphanumeric ID (mimics OpenAI base62 format)
     let charset = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
     let mut rng = rand::thread_rng();
@@ -265,79 +369,64 @@ pub fn create_legacy_sse_stream(
     let created_ts = Utc::now().timestamp(); 
     
     let stream = async_stream::stream! {
-        while let Some(item) = gemini_stream.next().await {
+        let mut events = SseParser::new(gemini_stream);
+        while let Some(item) = events.next().await {
             match item {
-                Ok(bytes) => {
-                    buffer.extend_from_slice(&bytes);
-                    while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
-                        let line_raw = buffer.split_to(pos + 1);
-                        if let Ok(line_str) = std::str::from_utf8(&line_raw) {
-                            let line = line_str.trim();
-                            if line.is_empty() { continue; }
-
-                            if line.starts_with("data: ") {
-                                let json_part = line.trim_start_matches("data: ").trim();
-                                if json_part == "[DONE]" { continue; }
-
-                                if let Ok(mut json) = serde_json::from_str::<Value>(json_part) {
-                                    let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) { inner } else { json };
-                                    
-                                    let mut content_out = String::new();
-                                    if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
-                                        if let Some(parts) = candidates.get(0).and_then(|c| c.get("content")).and_then(|c| c.get("parts")).and_then(|p| p.as_array()) {
-                                            for part in parts {
-                                                if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
-                                                    content_out.push_str(text);
-                                                }
-                                                /* 禁用思维链输出到正文
-                                                if let Some(thought_text) = part.get("thought").and_then(|t| t.as_str()) {
-                                                    // // content_out.push_str(thought_text);
-                                                }
-                                                */
-                                                // 捕获 thoughtSignature
-                                                // 捕获 thoughtSignature 到全局存储
-                                                if let Some(sig) = part.get("thoughtSignature").or(part.get("thought_signature")).and_then(|s| s.as_str()) {
-                                                    store_thought_signature(sig);
-                                                }
-                                            }
-                                        }
+                Ok(event) => {
+                    let json_part = event.data.trim();
+                    if json_part.is_empty() || json_part == "[DONE]" { continue; }
+
+                    if let Ok(mut json) = serde_json::from_str::<Value>(json_part) {
+                        let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) { inner } else { json };
+
+                        let mut content_out = String::new();
+                        if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
+                            if let Some(parts) = candidates.get(0).and_then(|c| c.get("content")).and_then(|c| c.get("parts")).and_then(|p| p.as_array()) {
+                                for part in parts {
+                                    if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                                        content_out.push_str(text);
+                                    }
+                                    /* 禁用思维链输出到正文
+                                    if let Some(thought_text) = part.get("thought").and_then(|t| t.as_str()) {
+                                        // // content_out.push_str(thought_text);
+                                    }
+                                    */
+                                    // 捕获 thoughtSignature
+                                    // 捕获 thoughtSignature 到全局存储
+                                    if let Some(sig) = part.get("thoughtSignature").or(part.get("thought_signature")).and_then(|s| s.as_str()) {
+                                        store_thought_signature(sig);
                                     }
-
-                                    let finish_reason = actual_data.get("candidates")
-                                        .and_then(|c| c.as_array())
-                                        .and_then(|c| c.get(0))
-                                        .and_then(|c| c.get("finishReason"))
-                                        .and_then(|f| f.as_str())
-                                        .map(|f| match f {
-                                            "STOP" => "stop",
-                                            "MAX_TOKENS" => "length",
-                                            "SAFETY" => "content_filter",
-                                            _ => f,
-                                        });
-
-                                    // Construct LEGACY completion chunk - STRICT VERSION
-                                    let legacy_chunk = json!({
-                                        "id": &stream_id,
-                                        "object": "text_completion",
-                                        "created": created_ts,
-                                        "model": &model,
-                                        "choices": [
-                                            {
-                                                "text": content_out,
-                                                "index": 0,
-                                                "logprobs": null,
-                                                "finish_reason": finish_reason // Will be null if None
-                                            }
-                                        ]
-                                    });
-
-                                    let json_str = serde_json::to_string(&legacy_chunk).unwrap_or_default();
-                                    tracing::debug!("Legacy Stream Chunk: {}", json_str); 
-                                    let sse_out = format!("data: {}\n\n", json_str);
-                                    yield Ok::<Bytes, String>(Bytes::from(sse_out));
                                 }
                             }
                         }
+
+                        let finish_reason = actual_data.get("candidates")
+                            .and_then(|c| c.as_array())
+                            .and_then(|c| c.get(0))
+                            .and_then(|c| c.get("finishReason"))
+                            .and_then(|f| f.as_str())
+                            .map(normalize_finish_reason);
+
+                        // Construct LEGACY completion chunk - STRICT VERSION
+                        let legacy_chunk = json!({
+                            "id": &stream_id,
+                            "object": "text_completion",
+                            "created": created_ts,
+                            "model": &model,
+                            "choices": [
+                                {
+                                    "text": content_out,
+                                    "index": 0,
+                                    "logprobs": null,
+                                    "finish_reason": finish_reason // Will be null if None
+                                }
+                            ]
+                        });
+
+                        let json_str = serde_json::to_string(&legacy_chunk).unwrap_or_default();
+                        tracing::debug!("Legacy Stream Chunk: {}", json_str); 
+                        let sse_out = format!("data: {}\n\n", json_str);
+                        yield Ok::<Bytes, String>(Bytes::from(sse_out));
                     }
                 }
                 Err(e) => yield Err(format!("Upstream error: {}", e)),
@@ -353,11 +442,9 @@ pub fn create_legacy_sse_stream(
 }
 
 pub fn create_codex_sse_stream(
-    mut gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
+    gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
     _model: String,
 ) -> Pin<Box<dyn Stream<Item = Result<Bytes, String>> + Send>> {
-    let mut buffer = BytesMut::new();
-    
     // Generate alphanumeric ID
     let charset = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
     let mut rng = rand::thread_rng();
@@ -384,228 +471,216 @@ pub fn create_codex_sse_stream(
         let mut emitted_tool_calls = std::collections::HashSet::new();
         let mut last_finish_reason = "stop".to_string();
 
-        while let Some(item) = gemini_stream.next().await {
+        let mut events = SseParser::new(gemini_stream);
+        while let Some(item) = events.next().await {
             match item {
-                Ok(bytes) => {
-                    buffer.extend_from_slice(&bytes);
-                    while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
-                        let line_raw = buffer.split_to(pos + 1);
-                        if let Ok(line_str) = std::str::from_utf8(&line_raw) {
-                            let line = line_str.trim();
-                            if line.is_empty() || !line.starts_with("data: ") { continue; }
-                            
-                            let json_part = line.trim_start_matches("data: ").trim();
-                            if json_part == "[DONE]" { continue; }
-
-                            if let Ok(mut json) = serde_json::from_str::<Value>(json_part) {
-                                let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) { inner } else { json };
-                                
-                                // Capture finish reason
-                                if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
-                                    if let Some(candidate) = candidates.get(0) {
-                                        if let Some(reason) = candidate.get("finishReason").and_then(|r| r.as_str()) {
-                                            last_finish_reason = match reason {
-                                                "STOP" => "stop".to_string(),
-                                                "MAX_TOKENS" => "length".to_string(),
-                                                _ => "stop".to_string(),
-                                            };
-                                        }
-                                    }
+                Ok(event) => {
+                    let json_part = event.data.trim();
+                    if json_part.is_empty() || json_part == "[DONE]" { continue; }
+
+                    if let Ok(mut json) = serde_json::from_str::<Value>(json_part) {
+                        let actual_data = if let Some(inner) = json.get_mut("response").map(|v| v.take()) { inner } else { json };
+
+                        // Capture finish reason
+                        if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
+                            if let Some(candidate) = candidates.get(0) {
+                                if let Some(reason) = candidate.get("finishReason").and_then(|r| r.as_str()) {
+                                    last_finish_reason = normalize_finish_reason(reason).to_string();
                                 }
+                            }
+                        }
 
-                                // text delta
-                                let mut delta_text = String::new();
-                                if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
-                                    if let Some(candidate) = candidates.get(0) {
-                                        if let Some(parts) = candidate.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array()) {
-                                            for part in parts {
-                                                if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
-                                                    // Sanitize smart quotes to standard quotes for JSON compatibility
-                                                    let clean_text = text.replace('“', "\"").replace('”', "\"");
-                                                    delta_text.push_str(&clean_text);
-                                                }
-                                                /* 禁用思维链输出到正文
-                                                if let Some(thought_text) = part.get("thought").and_then(|t| t.as_str()) {
-                                                    let clean_thought = thought_text.replace('"', "\"").replace('"', "\"");
-                                                    // delta_text.push_str(&clean_thought);
-                                                }
-                                                */
-                                                // 捕获 thoughtSignature (Gemini 3 工具调用必需)
-                                                // 存储到全局状态，不再嵌入到用户可见的文本中
-                                                if let Some(sig) = part.get("thoughtSignature").or(part.get("thought_signature")).and_then(|s| s.as_str()) {
-                                                    tracing::debug!("[Codex-SSE] 捕获 thoughtSignature (长度: {})", sig.len());
-                                                    store_thought_signature(sig);
-                                                }
-                                                // Handle function call in chunk with deduplication
-                                                if let Some(func_call) = part.get("functionCall") {
-                                                    let call_key = serde_json::to_string(func_call).unwrap_or_default();
-                                                    if !emitted_tool_calls.contains(&call_key) {
-                                                        emitted_tool_calls.insert(call_key);
-
-                                                                                let name = func_call.get("name").and_then(|v| v.as_str()).unwrap_or("unknown");
-                                                                                let _args = func_call.get("args").unwrap_or(&json!({})).to_string();                                                        
-                                                        // Stable ID generation based on hashed content to be consistent
-                                                        let mut hasher = std::collections::hash_map::DefaultHasher::new();
-                                                        use std::hash::{Hash, Hasher};
-                                                        serde_json::to_string(func_call).unwrap_or_default().hash(&mut hasher);
-                                                        let call_id = format!("call_{:x}", hasher.finish());
-                                                        
-                                                        // Parse args once
-                                                        let fallback_args = json!({});
-                                                        let args_obj = func_call.get("args").unwrap_or(&fallback_args);
-                                                        // Fallback for function_call arguments string
-                                                        let args_str = args_obj.to_string();
-
-                                                        let name_str = name.to_string();
-                                                        
-                                                        // Determine event type based on tool name
-                                                        // 使用 Option 来允许某些情况跳过工具调用
-                                                        let maybe_item_added_ev: Option<Value> = if name_str == "shell" || name_str == "local_shell" {
-                                                            // Map to local_shell_call
-                                                            tracing::debug!("[Debug] func_call: {}", serde_json::to_string(&func_call).unwrap_or_default());
-                                                            tracing::debug!("[Debug] args_obj: {}", serde_json::to_string(&args_obj).unwrap_or_default());
-                                                            
-                                                            // 解析命令：支持数组格式、字符串格式，以及空 args 情况
-                                                            let cmd_vec: Vec<String> = if args_obj.as_object().map(|o| o.is_empty()).unwrap_or(true) {
-                                                                // args 为空时使用静默成功命令，避免任务中断
-                                                                tracing::debug!("shell command args 为空，使用静默成功命令继续流程");
-                                                                vec!["powershell.exe".to_string(), "-Command".to_string(), "exit 0".to_string()]
-                                                            } else if let Some(arr) = args_obj.get("command").and_then(|v| v.as_array()) {
-                                                                // 数组格式
-                                                                arr.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect()
-                                                            } else if let Some(cmd_str) = args_obj.get("command").and_then(|v| v.as_str()) {
-                                                                // 字符串格式
-                                                                if cmd_str.contains(' ') {
-                                                                    vec!["powershell.exe".to_string(), "-Command".to_string(), cmd_str.to_string()]
-                                                                } else {
-                                                                    vec![cmd_str.to_string()]
-                                                                }
-                                                            } else {
-                                                                // command 字段缺失，使用静默成功命令
-                                                                tracing::debug!("shell command 缺少 command 字段，使用静默成功命令");
-                                                                vec!["powershell.exe".to_string(), "-Command".to_string(), "exit 0".to_string()]
-                                                            };
-                                                            
-                                                            tracing::debug!("Shell 命令解析: {:?}", cmd_vec);
-                                                            Some(json!({
-                                                                "type": "response.output_item.added",
-                                                                "item": {
-                                                                    "type": "local_shell_call",
-                                                                    "status": "in_progress",
-                                                                    "call_id": &call_id,
-                                                                    "action": {
-                                                                        "type": "exec",
-                                                                        "command": cmd_vec
-                                                                    }
-                                                                }
-                                                            }))
-                                                        } else if name_str == "googleSearch" || name_str == "web_search" || name_str == "google_search" {
-                                                            // Map to web_search_call
-                                                            let query_val = args_obj.get("query").and_then(|v| v.as_str()).unwrap_or("");
-                                                            Some(json!({
-                                                                "type": "response.output_item.added",
-                                                                "item": {
-                                                                    "type": "web_search_call",
-                                                                    "status": "in_progress",
-                                                                    "call_id": &call_id,
-                                                                    "action": {
-                                                                        "type": "search",
-                                                                        "query": query_val
-                                                                    }
-                                                                }
-                                                            }))
+                        // text delta
+                        let mut delta_text = String::new();
+                        if let Some(candidates) = actual_data.get("candidates").and_then(|c| c.as_array()) {
+                            if let Some(candidate) = candidates.get(0) {
+                                if let Some(parts) = candidate.get("content").and_then(|c| c.get("parts")).and_then(|p| p.as_array()) {
+                                    for part in parts {
+                                        if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                                            // Sanitize smart quotes to standard quotes for JSON compatibility
+                                            let clean_text = text.replace('“', "\"").replace('”', "\"");
+                                            delta_text.push_str(&clean_text);
+                                        }
+                                        /* 禁用思维链输出到正文
+                                        if let Some(thought_text) = part.get("thought").and_then(|t| t.as_str()) {
+                                            let clean_thought = thought_text.replace('"', "\"").replace('"', "\"");
+                                            // delta_text.push_str(&clean_thought);
+                                        }
+                                        */
+                                        // 捕获 thoughtSignature (Gemini 3 工具调用必需)
+                                        // 存储到全局状态，不再嵌入到用户可见的文本中
+                                        if let Some(sig) = part.get("thoughtSignature").or(part.get("thought_signature")).and_then(|s| s.as_str()) {
+                                            tracing::debug!("[Codex-SSE] 捕获 thoughtSignature (长度: {})", sig.len());
+                                            store_thought_signature(sig);
+                                        }
+                                        // Handle function call in chunk with deduplication
+                                        if let Some(func_call) = part.get("functionCall") {
+                                            let call_key = serde_json::to_string(func_call).unwrap_or_default();
+                                            if !emitted_tool_calls.contains(&call_key) {
+                                                emitted_tool_calls.insert(call_key);
+
+                                                                        let name = func_call.get("name").and_then(|v| v.as_str()).unwrap_or("unknown");
+                                                                        let _args = func_call.get("args").unwrap_or(&json!({})).to_string();                                                        
+                                                // Stable ID generation based on hashed content to be consistent
+                                                let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                                                use std::hash::{Hash, Hasher};
+                                                serde_json::to_string(func_call).unwrap_or_default().hash(&mut hasher);
+                                                let call_id = format!("call_{:x}", hasher.finish());
+
+                                                // Parse args once
+                                                let fallback_args = json!({});
+                                                let args_obj = func_call.get("args").unwrap_or(&fallback_args);
+                                                // Fallback for function_call arguments string
+                                                let args_str = args_obj.to_string();
+
+                                                let name_str = name.to_string();
+
+                                                // Determine event type based on tool name
+                                                // 使用 Option 来允许某些情况跳过工具调用
+                                                let maybe_item_added_ev: Option<Value> = if name_str == "shell" || name_str == "local_shell" {
+                                                    // Map to local_shell_call
+                                                    tracing::debug!("[Debug] func_call: {}", serde_json::to_string(&func_call).unwrap_or_default());
+                                                    tracing::debug!("[Debug] args_obj: {}", serde_json::to_string(&args_obj).unwrap_or_default());
+
+                                                    // 解析命令：支持数组格式、字符串格式，以及空 args 情况
+                                                    let cmd_vec: Vec<String> = if args_obj.as_object().map(|o| o.is_empty()).unwrap_or(true) {
+                                                        // args 为空时使用静默成功命令，避免任务中断
+                                                        tracing::debug!("shell command args 为空，使用静默成功命令继续流程");
+                                                        vec!["powershell.exe".to_string(), "-Command".to_string(), "exit 0".to_string()]
+                                                    } else if let Some(arr) = args_obj.get("command").and_then(|v| v.as_array()) {
+                                                        // 数组格式
+                                                        arr.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect()
+                                                    } else if let Some(cmd_str) = args_obj.get("command").and_then(|v| v.as_str()) {
+                                                        // 字符串格式
+                                                        if cmd_str.contains(' ') {
+                                                            vec!["powershell.exe".to_string(), "-Command".to_string(), cmd_str.to_string()]
                                                         } else {
-                                                            // Default function_call
-                                                            Some(json!({
-                                                                "type": "response.output_item.added",
-                                                                "item": {
-                                                                    "type": "function_call",
-                                                                    "name": name,
-                                                                    "arguments": args_str,
-                                                                    "call_id": &call_id
-                                                                }
-                                                            }))
-                                                        };
-
-                                                        // 只有在有事件时才发送
-                                                        if let Some(item_added_ev) = maybe_item_added_ev {
-                                                            yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&item_added_ev).unwrap())));
-
-                                                        // Emit response.output_item.done (matching the added event)
-                                                        // 复用相同的 cmd_vec 逻辑
-                                                        let item_done_ev = if name_str == "shell" || name_str == "local_shell" {
-                                                            let cmd_vec_done: Vec<String> = if let Some(arr) = args_obj.get("command").and_then(|v| v.as_array()) {
-                                                                arr.iter()
-                                                                    .filter_map(|v| v.as_str())
-                                                                    .map(|s| s.to_string())
-                                                                    .collect()
-                                                            } else if let Some(cmd_str) = args_obj.get("command").and_then(|v| v.as_str()) {
-                                                                if cmd_str.contains(' ') {
-                                                                    vec!["powershell.exe".to_string(), "-Command".to_string(), cmd_str.to_string()]
-                                                                } else {
-                                                                    vec![cmd_str.to_string()]
-                                                                }
-                                                            } else {
-                                                                vec!["powershell.exe".to_string(), "-Command".to_string(), "echo 'Invalid command'".to_string()]
-                                                            };
-                                                            json!({
-                                                                "type": "response.output_item.done",
-                                                                "item": {
-                                                                    "type": "local_shell_call",
-                                                                    "status": "in_progress",
-                                                                    "call_id": call_id,
-                                                                     "action": {
-                                                                        "type": "exec",
-                                                                        "command": cmd_vec_done
-                                                                    }
-                                                                }
-                                                            })
-                                                        } else if name_str == "googleSearch" || name_str == "web_search" || name_str == "google_search" {
-                                                            let query_val = args_obj.get("query").and_then(|v| v.as_str()).unwrap_or("");
-                                                             json!({
-                                                                "type": "response.output_item.done",
-                                                                "item": {
-                                                                    "type": "web_search_call",
-                                                                    "status": "in_progress",
-                                                                    "call_id": call_id,
-                                                                    "action": {
-                                                                        "type": "search",
-                                                                        "query": query_val
-                                                                    }
-                                                                }
-                                                            })
+                                                            vec![cmd_str.to_string()]
+                                                        }
+                                                    } else {
+                                                        // command 字段缺失，使用静默成功命令
+                                                        tracing::debug!("shell command 缺少 command 字段，使用静默成功命令");
+                                                        vec!["powershell.exe".to_string(), "-Command".to_string(), "exit 0".to_string()]
+                                                    };
+
+                                                    tracing::debug!("Shell 命令解析: {:?}", cmd_vec);
+                                                    Some(json!({
+                                                        "type": "response.output_item.added",
+                                                        "item": {
+                                                            "type": "local_shell_call",
+                                                            "status": "in_progress",
+                                                            "call_id": &call_id,
+                                                            "action": {
+                                                                "type": "exec",
+                                                                "command": cmd_vec
+                                                            }
+                                                        }
+                                                    }))
+                                                } else if name_str == "googleSearch" || name_str == "web_search" || name_str == "google_search" {
+                                                    // Map to web_search_call
+                                                    let query_val = args_obj.get("query").and_then(|v| v.as_str()).unwrap_or("");
+                                                    Some(json!({
+                                                        "type": "response.output_item.added",
+                                                        "item": {
+                                                            "type": "web_search_call",
+                                                            "status": "in_progress",
+                                                            "call_id": &call_id,
+                                                            "action": {
+                                                                "type": "search",
+                                                                "query": query_val
+                                                            }
+                                                        }
+                                                    }))
+                                                } else {
+                                                    // Default function_call
+                                                    Some(json!({
+                                                        "type": "response.output_item.added",
+                                                        "item": {
+                                                            "type": "function_call",
+                                                            "name": name,
+                                                            "arguments": args_str,
+                                                            "call_id": &call_id
+                                                        }
+                                                    }))
+                                                };
+
+                                                // 只有在有事件时才发送
+                                                if let Some(item_added_ev) = maybe_item_added_ev {
+                                                    yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&item_added_ev).unwrap())));
+
+                                                // Emit response.output_item.done (matching the added event)
+                                                // 复用相同的 cmd_vec 逻辑
+                                                let item_done_ev = if name_str == "shell" || name_str == "local_shell" {
+                                                    let cmd_vec_done: Vec<String> = if let Some(arr) = args_obj.get("command").and_then(|v| v.as_array()) {
+                                                        arr.iter()
+                                                            .filter_map(|v| v.as_str())
+                                                            .map(|s| s.to_string())
+                                                            .collect()
+                                                    } else if let Some(cmd_str) = args_obj.get("command").and_then(|v| v.as_str()) {
+                                                        if cmd_str.contains(' ') {
+                                                            vec!["powershell.exe".to_string(), "-Command".to_string(), cmd_str.to_string()]
                                                         } else {
-                                                            json!({
-                                                                "type": "response.output_item.done",
-                                                                "item": {
-                                                                    "type": "function_call",
-                                                                    "name": name,
-                                                                    "arguments": args_str,
-                                                                    "call_id": call_id
-                                                                }
-                                                            })
-                                                        };
-
-                                                        yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&item_done_ev).unwrap())));
-                                                        } // 关闭 if let Some(item_added_ev)
-                                                    }
-                                                }
+                                                            vec![cmd_str.to_string()]
+                                                        }
+                                                    } else {
+                                                        vec!["powershell.exe".to_string(), "-Command".to_string(), "echo 'Invalid command'".to_string()]
+                                                    };
+                                                    json!({
+                                                        "type": "response.output_item.done",
+                                                        "item": {
+                                                            "type": "local_shell_call",
+                                                            "status": "in_progress",
+                                                            "call_id": call_id,
+                                                             "action": {
+                                                                "type": "exec",
+                                                                "command": cmd_vec_done
+                                                            }
+                                                        }
+                                                    })
+                                                } else if name_str == "googleSearch" || name_str == "web_search" || name_str == "google_search" {
+                                                    let query_val = args_obj.get("query").and_then(|v| v.as_str()).unwrap_or("");
+                                                     json!({
+                                                        "type": "response.output_item.done",
+                                                        "item": {
+                                                            "type": "web_search_call",
+                                                            "status": "in_progress",
+                                                            "call_id": call_id,
+                                                            "action": {
+                                                                "type": "search",
+                                                                "query": query_val
+                                                            }
+                                                        }
+                                                    })
+                                                } else {
+                                                    json!({
+                                                        "type": "response.output_item.done",
+                                                        "item": {
+                                                            "type": "function_call",
+                                                            "name": name,
+                                                            "arguments": args_str,
+                                                            "call_id": call_id
+                                                        }
+                                                    })
+                                                };
+
+                                                yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&item_done_ev).unwrap())));
+                                                } // 关闭 if let Some(item_added_ev)
                                             }
                                         }
                                     }
                                 }
-
-                                if !delta_text.is_empty() {
-                                    full_content.push_str(&delta_text);
-                                    // 2. Emit response.output_text.delta
-                                    let delta_ev = json!({
-                                        "type": "response.output_text.delta",
-                                        "delta": delta_text
-                                    });
-                                    yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&delta_ev).unwrap())));
-                                }
                             }
                         }
+
+                        if !delta_text.is_empty() {
+                            full_content.push_str(&delta_text);
+                            // 2. Emit response.output_text.delta
+                            let delta_ev = json!({
+                                "type": "response.output_text.delta",
+                                "delta": delta_text
+                            });
+                            yield Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&delta_ev).unwrap())));
+                        }
                     }
                 }
                 Err(e) => yield Err(format!("Upstream error: {}", e)),
@@ -817,3 +892,65 @@ pub fn create_codex_sse_stream(
 
     Box::pin(stream)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_include_usage_emits_final_usage_chunk() {
+        let upstream = concat!(
+            "data: {\"response\":{\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hi\"}]}}],\"usageMetadata\":{\"promptTokenCount\":5}}}\n\n",
+            "data: {\"response\":{\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"!\"}]},\"finishReason\":\"STOP\"}],\"usageMetadata\":{\"promptTokenCount\":5,\"candidatesTokenCount\":2,\"totalTokenCount\":7}}}\n\n",
+        );
+        let gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>> =
+            Box::pin(futures::stream::iter(vec![Ok(Bytes::from(upstream))]));
+
+        let chunks: Vec<String> = create_openai_sse_stream(gemini_stream, "gpt-4o".to_string(), true)
+            .map(|c| String::from_utf8(c.unwrap().to_vec()).unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(chunks.last().unwrap(), "data: [DONE]\n\n");
+        let parse = |s: &str| serde_json::from_str::<Value>(s.trim().trim_start_matches("data: ")).unwrap();
+        let usage_chunk = parse(&chunks[chunks.len() - 2]);
+        assert_eq!(usage_chunk["choices"], json!([]));
+        assert_eq!(
+            usage_chunk["usage"],
+            json!({"prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7})
+        );
+        let first = parse(&chunks[0]);
+        assert!(first.get("usage").is_some_and(|u| u.is_null()));
+    }
+
+    #[tokio::test]
+    async fn test_panic_mid_stream_emits_terminal_events() {
+        let upstream = concat!(
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hi\"}]}}],\"usageMetadata\":{\"promptTokenCount\":5}}\n\n",
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"boom\"}]}}]}\n\n",
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"never\"}]}}]}\n\n",
+        );
+        let gemini_stream: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>> =
+            Box::pin(futures::stream::iter(vec![Ok(Bytes::from(upstream))]));
+
+        let state = OpenAIStreamState::new("gpt-4o".to_string(), true);
+        let chunks: Vec<String> = openai_sse_stream_with(gemini_stream, state, |state, data| {
+            if data.contains("boom") {
+                panic!("simulated processing panic");
+            }
+            state.process_event(data)
+        })
+        .map(|c| String::from_utf8(c.unwrap().to_vec()).unwrap())
+        .collect()
+        .await;
+
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks[0].contains("Hi"));
+        assert!(chunks[1].contains("\"prompt_tokens\":5"));
+        assert_eq!(
+            chunks[2],
+            format!("data: {}\n\n", crate::proxy::mappers::claude::STREAM_PROCESSING_ERROR)
+        );
+        assert_eq!(chunks[3], "data: [DONE]\n\n");
+    }
+}
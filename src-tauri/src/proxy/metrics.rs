@@ -0,0 +1,220 @@
+// Prometheus 指标
+// 计数器使用 Arc<AtomicU64>，按标签组合懒创建，跨请求持续累积
+
+use dashmap::DashMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::proxy::TokenManager;
+
+/// 请求耗时直方图分桶 (秒)
+const DURATION_BUCKETS: [f64; 10] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [AtomicU64; DURATION_BUCKETS.len()],
+    sum_micros: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        for (i, bound) in DURATION_BUCKETS.iter().enumerate() {
+            if secs <= *bound {
+                self.buckets[i].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.sum_micros.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        for bucket in &self.buckets {
+            bucket.store(0, Ordering::SeqCst);
+        }
+        self.sum_micros.store(0, Ordering::SeqCst);
+        self.count.store(0, Ordering::SeqCst);
+    }
+}
+
+/// 反代服务指标
+#[derive(Default)]
+pub struct ProxyMetrics {
+    requests: DashMap<(String, String, u16), Arc<AtomicU64>>,
+    durations: DashMap<(String, String), Arc<Histogram>>,
+}
+
+impl ProxyMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次反代请求
+    pub fn record_request(&self, protocol: &str, model: &str, status: u16, duration: Duration) {
+        let counter = self
+            .requests
+            .entry((protocol.to_string(), model.to_string(), status))
+            .or_default()
+            .clone();
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        let histogram = self
+            .durations
+            .entry((protocol.to_string(), model.to_string()))
+            .or_default()
+            .clone();
+        histogram.observe(duration);
+    }
+
+    /// 清零所有计数器与直方图 (保留已出现的标签组合，避免 Prometheus 序列消失)
+    pub fn reset(&self) {
+        for counter in self.requests.iter() {
+            counter.value().store(0, Ordering::SeqCst);
+        }
+        for histogram in self.durations.iter() {
+            histogram.value().reset();
+        }
+    }
+
+    /// 渲染为 Prometheus 文本格式
+    pub fn render(&self, token_manager: &TokenManager) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP agtools_requests_total Total proxied requests.\n");
+        out.push_str("# TYPE agtools_requests_total counter\n");
+        let mut requests: Vec<_> = self
+            .requests
+            .iter()
+            .map(|e| (e.key().clone(), e.value().load(Ordering::Relaxed)))
+            .collect();
+        requests.sort();
+        for ((protocol, model, status), value) in requests {
+            let _ = writeln!(
+                out,
+                "agtools_requests_total{{protocol=\"{}\",model=\"{}\",status=\"{}\"}} {}",
+                escape_label(&protocol),
+                escape_label(&model),
+                status,
+                value
+            );
+        }
+
+        out.push_str("# HELP agtools_request_duration_seconds Proxied request latency.\n");
+        out.push_str("# TYPE agtools_request_duration_seconds histogram\n");
+        let mut durations: Vec<_> = self
+            .durations
+            .iter()
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect();
+        durations.sort_by(|a, b| a.0.cmp(&b.0));
+        for ((protocol, model), histogram) in durations {
+            let labels = format!(
+                "protocol=\"{}\",model=\"{}\"",
+                escape_label(&protocol),
+                escape_label(&model)
+            );
+            for (i, bound) in DURATION_BUCKETS.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "agtools_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels,
+                    bound,
+                    histogram.buckets[i].load(Ordering::Relaxed)
+                );
+            }
+            let count = histogram.count.load(Ordering::Relaxed);
+            let _ = writeln!(
+                out,
+                "agtools_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, count
+            );
+            let _ = writeln!(
+                out,
+                "agtools_request_duration_seconds_sum{{{}}} {}",
+                labels,
+                histogram.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0
+            );
+            let _ = writeln!(out, "agtools_request_duration_seconds_count{{{}}} {}", labels, count);
+        }
+
+        let circuits = token_manager.get_circuit_states();
+        let open_circuits = circuits.iter().filter(|c| c.state == "open").count();
+
+        out.push_str("# HELP agtools_accounts_total Accounts loaded in the proxy pool.\n");
+        out.push_str("# TYPE agtools_accounts_total gauge\n");
+        let _ = writeln!(out, "agtools_accounts_total {}", token_manager.len());
+
+        out.push_str("# HELP agtools_accounts_open_circuit Accounts with an open circuit breaker.\n");
+        out.push_str("# TYPE agtools_accounts_open_circuit gauge\n");
+        let _ = writeln!(out, "agtools_accounts_open_circuit {}", open_circuits);
+
+        let (refresh_success, refresh_failure) = token_manager.token_refresh_counts();
+        out.push_str("# HELP agtools_token_refreshes_total OAuth token refresh attempts.\n");
+        out.push_str("# TYPE agtools_token_refreshes_total counter\n");
+        let _ = writeln!(out, "agtools_token_refreshes_total{{result=\"success\"}} {}", refresh_success);
+        let _ = writeln!(out, "agtools_token_refreshes_total{{result=\"failure\"}} {}", refresh_failure);
+
+        out.push_str("# HELP agtools_quota_remaining Remaining quota percentage per account.\n");
+        out.push_str("# TYPE agtools_quota_remaining gauge\n");
+        for (email, quota) in token_manager.remaining_quotas() {
+            let _ = writeln!(
+                out,
+                "agtools_quota_remaining{{account=\"{}\"}} {}",
+                escape_label(&email),
+                quota
+            );
+        }
+
+        out
+    }
+}
+
+/// Prometheus 标签值转义
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_requests_and_histogram() {
+        let metrics = ProxyMetrics::new();
+        metrics.record_request("openai", "gpt-4o", 200, Duration::from_millis(300));
+        metrics.record_request("openai", "gpt-4o", 200, Duration::from_secs(3));
+        metrics.record_request("claude", "claude-\"x\"", 429, Duration::from_millis(50));
+
+        let manager = TokenManager::new(std::env::temp_dir());
+        let text = metrics.render(&manager);
+
+        assert!(text.contains(
+            "agtools_requests_total{protocol=\"openai\",model=\"gpt-4o\",status=\"200\"} 2"
+        ));
+        assert!(text.contains("model=\"claude-\\\"x\\\"\""));
+        assert!(text.contains(
+            "agtools_request_duration_seconds_bucket{protocol=\"openai\",model=\"gpt-4o\",le=\"0.5\"} 1"
+        ));
+        assert!(text.contains(
+            "agtools_request_duration_seconds_bucket{protocol=\"openai\",model=\"gpt-4o\",le=\"+Inf\"} 2"
+        ));
+        assert!(text.contains("agtools_accounts_total 0"));
+        assert!(text.contains("agtools_token_refreshes_total{result=\"success\"} 0"));
+
+        metrics.reset();
+        let text = metrics.render(&manager);
+        assert!(text.contains(
+            "agtools_requests_total{protocol=\"openai\",model=\"gpt-4o\",status=\"200\"} 0"
+        ));
+        assert!(text.contains(
+            "agtools_request_duration_seconds_bucket{protocol=\"openai\",model=\"gpt-4o\",le=\"+Inf\"} 0"
+        ));
+        assert!(text.contains("agtools_request_duration_seconds_sum{protocol=\"openai\",model=\"gpt-4o\"} 0"));
+    }
+}
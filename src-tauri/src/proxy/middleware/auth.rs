@@ -2,7 +2,7 @@
 use axum::{
     extract::State,
     extract::Request,
-    http::{header, StatusCode},
+    http::{header, HeaderMap, StatusCode},
     middleware::Next,
     response::Response,
 };
@@ -11,17 +11,26 @@ use tokio::sync::RwLock;
 
 use crate::proxy::{ProxyAuthMode, ProxySecurityConfig};
 
+/// 从请求头中提取 API key (Authorization: Bearer 或 x-api-key)
+pub fn request_api_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer ").or(Some(s)))
+        .or_else(|| headers.get("x-api-key").and_then(|h| h.to_str().ok()))
+}
+
 /// API Key 认证中间件
 pub async fn auth_middleware(
     State(security): State<Arc<RwLock<ProxySecurityConfig>>>,
-    request: Request,
+    mut request: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
     let method = request.method().clone();
     let path = request.uri().path().to_string();
 
     // 过滤心跳和健康检查请求,避免日志噪音
-    if !path.contains("event_logging") && path != "/healthz" {
+    if !path.contains("event_logging") && !matches!(path.as_str(), "/healthz" | "/health" | "/metrics") {
         tracing::info!("Request: {} {}", method, path);
     } else {
         tracing::trace!("Heartbeat: {} {}", method, path);
@@ -32,9 +41,34 @@ pub async fn auth_middleware(
         return Ok(next.run(request).await);
     }
 
+    // Prometheus 抓取端点与 /health 状态端点无需鉴权
+    if path == "/metrics" || path == "/health" {
+        return Ok(next.run(request).await);
+    }
+
     let security = security.read().await.clone();
     let effective_mode = security.effective_auth_mode();
 
+    // 从 header 中提取 API key
+    let api_key = request_api_key(request.headers());
+
+    // 管理端点始终要求 admin_api_key，不受 auth_mode 影响
+    if path.starts_with("/admin/") {
+        return if api_key.is_some_and(|k| security.authorize_admin(k)) {
+            Ok(next.run(request).await)
+        } else {
+            Err(StatusCode::UNAUTHORIZED)
+        };
+    }
+
+    // Constant-time compare is unnecessary here, but keep strict equality and avoid leaking values.
+    let authorization = api_key.and_then(|k| security.authorize(k));
+
+    // 路由 Key 即使在免鉴权模式下也限定账号，便于本机多客户端隔离
+    if let Some(Some(allowlist)) = &authorization {
+        request.extensions_mut().insert(allowlist.clone());
+    }
+
     if matches!(effective_mode, ProxyAuthMode::Off) {
         return Ok(next.run(request).await);
     }
@@ -42,29 +76,13 @@ pub async fn auth_middleware(
     if matches!(effective_mode, ProxyAuthMode::AllExceptHealth) && path == "/healthz" {
         return Ok(next.run(request).await);
     }
-    
-    // 从 header 中提取 API key
-    let api_key = request
-        .headers()
-        .get(header::AUTHORIZATION)
-        .and_then(|h| h.to_str().ok())
-        .and_then(|s| s.strip_prefix("Bearer ").or(Some(s)))
-        .or_else(|| {
-            request
-                .headers()
-                .get("x-api-key")
-                .and_then(|h| h.to_str().ok())
-        });
-
-    if security.api_key.is_empty() {
+
+    if security.api_key.is_empty() && security.api_key_routes.is_empty() {
         tracing::error!("Proxy auth is enabled but api_key is empty; denying request");
         return Err(StatusCode::UNAUTHORIZED);
     }
 
-    // Constant-time compare is unnecessary here, but keep strict equality and avoid leaking values.
-    let authorized = api_key.map(|k| k == security.api_key).unwrap_or(false);
-
-    if authorized {
+    if authorization.is_some() {
         Ok(next.run(request).await)
     } else {
         Err(StatusCode::UNAUTHORIZED)
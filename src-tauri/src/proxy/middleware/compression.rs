@@ -0,0 +1,97 @@
+// 请求 / 响应体压缩
+// 请求体: 按 Content-Encoding (gzip / deflate / br) 解压后再交给处理器
+// 响应体: 按 Accept-Encoding 压缩；SSE 与 NDJSON 流式响应不压缩，避免输出被缓冲
+
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use tower_http::decompression::RequestDecompressionLayer;
+
+/// 响应压缩层，小于 `threshold_bytes` 的响应不压缩 (未知长度的响应始终视为超过阈值)
+pub fn compression_layer(threshold_bytes: usize) -> CompressionLayer<impl Predicate> {
+    let threshold = u16::try_from(threshold_bytes).unwrap_or(u16::MAX);
+    let predicate = SizeAbove::new(threshold)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::IMAGES)
+        .and(NotForContentType::SSE)
+        .and(NotForContentType::const_new("application/x-ndjson"));
+    CompressionLayer::new().compress_when(predicate)
+}
+
+/// 请求体解压层
+pub fn request_decompression_layer() -> RequestDecompressionLayer {
+    RequestDecompressionLayer::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::header,
+        routing::{get, post},
+        Router,
+    };
+    use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+    use std::io::{Read, Write};
+    use tower::Service;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/echo", post(|body: String| async move { body }))
+            .route("/large", get(|| async { "x".repeat(4096) }))
+            .route("/small", get(|| async { "ok" }))
+            .route(
+                "/sse",
+                get(|| async { ([(header::CONTENT_TYPE, "text/event-stream")], "data: x\n\n".repeat(512)) }),
+            )
+            .layer(compression_layer(1024))
+            .layer(request_decompression_layer())
+    }
+
+    async fn get_with_gzip(app: &mut Router, path: &str) -> axum::response::Response {
+        app.call(
+            Request::get(path)
+                .header(header::ACCEPT_ENCODING, "gzip")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_gzip_request_is_decompressed() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(br#"{"contents":[]}"#).unwrap();
+        let resp = app()
+            .call(
+                Request::post("/echo")
+                    .header(header::CONTENT_ENCODING, "gzip")
+                    .body(Body::from(encoder.finish().unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], br#"{"contents":[]}"#);
+    }
+
+    #[tokio::test]
+    async fn test_response_compression_respects_threshold_and_sse() {
+        let mut app = app();
+
+        let resp = get_with_gzip(&mut app, "/large").await;
+        assert_eq!(resp.headers()[header::CONTENT_ENCODING], "gzip");
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let mut text = String::new();
+        GzDecoder::new(&body[..]).read_to_string(&mut text).unwrap();
+        assert_eq!(text.len(), 4096);
+
+        let resp = get_with_gzip(&mut app, "/small").await;
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+
+        let resp = get_with_gzip(&mut app, "/sse").await;
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+    }
+}
@@ -1,33 +1,167 @@
 // CORS 中间件
-use tower_http::cors::{CorsLayer, Any};
-use axum::http::Method;
+// 按 ProxyConfig.allowed_origins 限制浏览器来源；`*` 表示允许任意来源 (默认，兼容旧配置)
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use tower_http::cors::{AllowHeaders, AllowOrigin, Any, CorsLayer};
+
+/// CORS 来源白名单配置
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    allowed_origins: Arc<Vec<String>>,
+    allow_credentials: bool,
+}
+
+impl CorsConfig {
+    pub fn new(allowed_origins: Vec<String>, allow_credentials: bool) -> Self {
+        let allowed_origins = allowed_origins
+            .into_iter()
+            .map(|o| o.trim().trim_end_matches('/').to_string())
+            .filter(|o| !o.is_empty())
+            .collect();
+        Self {
+            allowed_origins: Arc::new(allowed_origins),
+            allow_credentials,
+        }
+    }
+
+    pub fn from_proxy_config(config: &crate::proxy::config::ProxyConfig) -> Self {
+        Self::new(config.allowed_origins.clone(), config.allow_credentials)
+    }
+
+    fn allows_any(&self) -> bool {
+        self.allowed_origins.iter().any(|o| o == "*")
+    }
+
+    fn allows(&self, origin: &str) -> bool {
+        self.allows_any() || self.allowed_origins.iter().any(|o| o.eq_ignore_ascii_case(origin))
+    }
+}
 
 /// 创建 CORS layer
-pub fn cors_layer() -> CorsLayer {
+///
+/// 启用 allow_credentials 时规范禁止返回 `*`，此时通配来源改为回显请求的 Origin
+pub fn cors_layer(config: &CorsConfig) -> CorsLayer {
+    let allow_origin = if config.allows_any() {
+        if config.allow_credentials {
+            AllowOrigin::mirror_request()
+        } else {
+            AllowOrigin::any()
+        }
+    } else {
+        AllowOrigin::list(
+            config
+                .allowed_origins
+                .iter()
+                .filter_map(|o| HeaderValue::from_str(o).ok()),
+        )
+    };
+    let allow_headers = if config.allow_credentials {
+        AllowHeaders::mirror_request()
+    } else {
+        AllowHeaders::from(Any)
+    };
+
     CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::DELETE,
-            Method::HEAD,
-            Method::OPTIONS,
-            Method::PATCH,
-        ])
-        .allow_headers(Any)
-        .allow_credentials(false)
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::OPTIONS])
+        .allow_headers(allow_headers)
+        .allow_credentials(config.allow_credentials)
         .max_age(std::time::Duration::from_secs(3600))
 }
 
+/// 位于 CORS layer 外层: 拒绝白名单外的来源 (403，不转发)，预检响应统一返回 204
+pub async fn cors_guard_middleware(
+    State(config): State<CorsConfig>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let origin = request
+        .headers()
+        .get(header::ORIGIN)
+        .map(|v| v.to_str().unwrap_or_default().trim_end_matches('/').to_string());
+    if let Some(origin) = origin.filter(|o| !config.allows(o)) {
+        tracing::warn!("[CORS] Rejected request from disallowed origin: {}", origin);
+        return (StatusCode::FORBIDDEN, "Origin not allowed").into_response();
+    }
+
+    let is_preflight = request.method() == Method::OPTIONS;
+    let mut response = next.run(request).await;
+    if is_preflight && response.status() == StatusCode::OK {
+        *response.status_mut() = StatusCode::NO_CONTENT;
+    }
+    response
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axum::{body::Body, routing::post, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::Service;
+
+    fn app(config: CorsConfig, hits: Arc<AtomicUsize>) -> Router {
+        Router::new()
+            .route(
+                "/v1/messages",
+                post(move || async move {
+                    hits.fetch_add(1, Ordering::SeqCst);
+                    "ok"
+                }),
+            )
+            .layer(cors_layer(&config))
+            .layer(axum::middleware::from_fn_with_state(config, cors_guard_middleware))
+    }
+
+    fn request(method: Method, origin: &str) -> Request {
+        Request::builder()
+            .method(method)
+            .uri("/v1/messages")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_disallowed_origin_is_rejected() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let config = CorsConfig::new(vec!["https://app.example.com/".to_string()], true);
+        let mut app = app(config, hits.clone());
+
+        let resp = app.call(request(Method::POST, "https://evil.example.com")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+
+        let resp = app.call(request(Method::OPTIONS, "https://app.example.com")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let headers = resp.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example.com");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert!(headers[header::ACCESS_CONTROL_ALLOW_METHODS].to_str().unwrap().contains("POST"));
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+
+        let resp = app.call(request(Method::POST, "https://app.example.com")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_wildcard_allows_any_origin() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let mut wildcard = app(CorsConfig::new(vec!["*".to_string()], false), hits.clone());
+
+        let resp = wildcard.call(request(Method::POST, "https://anything.example")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
 
-    #[test]
-    fn test_cors_layer_creation() {
-        let _layer = cors_layer();
-        // Layer 创建成功
-        assert!(true);
+        // 通配 + 凭证时回显来源而非 `*`
+        let mut credentialed = app(CorsConfig::new(vec!["*".to_string()], true), hits);
+        let resp = credentialed.call(request(Method::OPTIONS, "https://anything.example")).await.unwrap();
+        assert_eq!(resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://anything.example");
     }
 }
@@ -0,0 +1,268 @@
+// 请求去重中间件
+// 客户端重传 (如网络超时后重试) 的相同非流式 generateContent 请求，
+// 在首个请求仍在进行时直接复用其结果，避免重复消耗上游配额
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use bytes::Bytes;
+use dashmap::{mapref::entry::Entry, DashMap};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+const MAX_DEDUP_BODY_SIZE: usize = 100 * 1024 * 1024;
+
+/// 计算哈希时忽略的字段 (客户端重传时可能变化)
+const VOLATILE_FIELDS: [&str; 5] = ["timestamp", "created", "created_at", "requestId", "request_id"];
+
+type RequestHash = [u8; 32];
+type ResultReceiver = Arc<watch::Receiver<Option<SharedResponse>>>;
+
+/// 首个请求的完整响应，供重复请求复用
+#[derive(Clone)]
+struct SharedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl SharedResponse {
+    fn to_response(&self) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+            .headers_mut()
+            .insert("X-Deduplicated", HeaderValue::from_static("true"));
+        response
+    }
+}
+
+struct InFlight {
+    started: Instant,
+    receiver: ResultReceiver,
+}
+
+/// 进行中请求表 (按请求体哈希)
+#[derive(Clone)]
+pub struct DeduplicateLayer {
+    window: Duration,
+    in_flight: Arc<DashMap<RequestHash, InFlight>>,
+}
+
+impl DeduplicateLayer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            in_flight: Arc::new(DashMap::new()),
+        }
+    }
+
+    pub fn from_proxy_config(config: &crate::proxy::config::ProxyConfig) -> Self {
+        Self::new(Duration::from_secs(config.dedup_window_secs))
+    }
+
+    /// 窗口内存在相同的进行中请求时返回其结果通道，否则登记为首个请求并返回发送端
+    fn join_or_lead(
+        &self,
+        hash: RequestHash,
+    ) -> Result<ResultReceiver, (Instant, watch::Sender<Option<SharedResponse>>)> {
+        match self.in_flight.entry(hash) {
+            Entry::Occupied(e) if e.get().started.elapsed() <= self.window => Ok(e.get().receiver.clone()),
+            entry => {
+                let (sender, receiver) = watch::channel(None);
+                let started = Instant::now();
+                entry.insert(InFlight {
+                    started,
+                    receiver: Arc::new(receiver),
+                });
+                Err((started, sender))
+            }
+        }
+    }
+
+    fn finish(&self, hash: &RequestHash, started: Instant) {
+        self.in_flight.remove_if(hash, |_, e| e.started == started);
+    }
+}
+
+/// 首个请求结束 (含被取消) 时移除登记
+struct LeaderGuard<'a> {
+    dedup: &'a DeduplicateLayer,
+    hash: RequestHash,
+    started: Instant,
+}
+
+impl Drop for LeaderGuard<'_> {
+    fn drop(&mut self) {
+        self.dedup.finish(&self.hash, self.started);
+    }
+}
+
+/// 仅对非流式 Gemini generateContent 请求去重
+pub fn is_dedup_request(method: &Method, path: &str) -> bool {
+    method == Method::POST && path.starts_with("/v1beta/models/") && path.ends_with(":generateContent")
+}
+
+fn strip_volatile_fields(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|k, _| !VOLATILE_FIELDS.contains(&k.as_str()));
+            map.values_mut().for_each(strip_volatile_fields);
+        }
+        Value::Array(arr) => arr.iter_mut().for_each(strip_volatile_fields),
+        _ => {}
+    }
+}
+
+/// 请求哈希: SHA-256(API Key + 路径 + 去除易变字段后的请求体)
+/// 包含 API Key，不同客户端 (及不同 api_key_routes 账号范围) 的请求不会共享响应
+pub fn request_hash(api_key: Option<&str>, path: &str, body: &[u8]) -> RequestHash {
+    let mut hasher = Sha256::new();
+    hasher.update(api_key.unwrap_or_default().as_bytes());
+    hasher.update([0u8]);
+    hasher.update(path.as_bytes());
+    hasher.update([0u8]);
+    match serde_json::from_slice::<Value>(body) {
+        Ok(mut json) => {
+            strip_volatile_fields(&mut json);
+            hasher.update(json.to_string().as_bytes());
+        }
+        Err(_) => hasher.update(body),
+    }
+    hasher.finalize().into()
+}
+
+pub async fn dedup_middleware(
+    State(dedup): State<DeduplicateLayer>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if dedup.window.is_zero() || !is_dedup_request(request.method(), request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_DEDUP_BODY_SIZE).await {
+        Ok(b) => b,
+        Err(e) => return super::body_read_error(&e, MAX_DEDUP_BODY_SIZE),
+    };
+    let hash = request_hash(super::request_api_key(&parts.headers), parts.uri.path(), &bytes);
+    let request = Request::from_parts(parts, Body::from(bytes));
+
+    let (started, sender) = match dedup.join_or_lead(hash) {
+        Ok(receiver) => {
+            let mut receiver = (*receiver).clone();
+            let shared = receiver.wait_for(|r| r.is_some()).await.ok().and_then(|r| r.clone());
+            return match shared {
+                Some(shared) => {
+                    tracing::info!("[Dedup] Reusing in-flight response for identical request");
+                    shared.to_response()
+                }
+                // 首个请求被取消或失败，自行发起请求
+                None => next.run(request).await,
+            };
+        }
+        Err(leader) => leader,
+    };
+    let _guard = LeaderGuard { dedup: &dedup, hash, started };
+
+    let response = next.run(request).await;
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(b) => b,
+        Err(e) => {
+            return Response::builder()
+                .status(StatusCode::BAD_GATEWAY)
+                .body(Body::from(format!("Read response error: {}", e)))
+                .unwrap();
+        }
+    };
+
+    let _ = sender.send(Some(SharedResponse {
+        status: parts.status,
+        headers: parts.headers.clone(),
+        body: body.clone(),
+    }));
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::Service;
+
+    #[test]
+    fn test_request_hash_ignores_volatile_fields() {
+        let path = "/v1beta/models/gemini-2.5-pro:generateContent";
+        let a = br#"{"contents":[{"parts":[{"text":"hi"}]}],"timestamp":1}"#;
+        let b = br#"{"contents":[{"parts":[{"text":"hi"}]}],"timestamp":2}"#;
+        let c = br#"{"contents":[{"parts":[{"text":"bye"}]}]}"#;
+
+        assert_eq!(request_hash(None, path, a), request_hash(None, path, b));
+        assert_ne!(request_hash(None, path, a), request_hash(None, path, c));
+        assert_ne!(
+            request_hash(None, path, a),
+            request_hash(None, "/v1beta/models/gemini-2.5-flash:generateContent", a)
+        );
+        // 不同 API Key 的相同请求不共享结果
+        assert_ne!(request_hash(Some("key-a"), path, a), request_hash(Some("key-b"), path, a));
+        assert!(is_dedup_request(&Method::POST, path));
+        assert!(!is_dedup_request(
+            &Method::POST,
+            "/v1beta/models/gemini-2.5-pro:streamGenerateContent"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_identical_in_flight_requests_share_upstream_call() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let app = Router::new()
+            .route(
+                "/v1beta/models/:model",
+                post(move || {
+                    let counter = counter.clone();
+                    async move {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        "result"
+                    }
+                }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                DeduplicateLayer::new(Duration::from_secs(5)),
+                dedup_middleware,
+            ));
+
+        let request = || {
+            Request::post("/v1beta/models/gemini-2.5-pro:generateContent")
+                .body(Body::from(r#"{"contents":[]}"#))
+                .unwrap()
+        };
+        let (first, second) = tokio::join!(app.clone().call(request()), async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            app.clone().call(request()).await
+        });
+        let (first, second) = (first.unwrap(), second.unwrap());
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(first.headers().get("X-Deduplicated").is_none());
+        assert_eq!(second.headers().get("X-Deduplicated").unwrap(), "true");
+        let body = axum::body::to_bytes(second.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"result");
+
+        // 首个请求完成后不再复用
+        app.clone().call(request()).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,70 @@
+// 进行中请求计数中间件
+// 供优雅停机时判断是否仍有请求未完成；流式响应在响应体传输完毕 (或客户端断开) 后才计为完成
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use futures::StreamExt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+pub type InFlightCounter = Arc<AtomicUsize>;
+
+struct InFlightGuard(InFlightCounter);
+
+impl InFlightGuard {
+    fn new(counter: InFlightCounter) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter)
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub async fn in_flight_middleware(
+    State(counter): State<InFlightCounter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let guard = InFlightGuard::new(counter);
+    let (parts, body) = next.run(request).await.into_parts();
+
+    // 计数守卫随响应体一起释放
+    let body = body.into_data_stream().map(move |chunk| {
+        let _ = &guard;
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use tower::Service;
+
+    #[tokio::test]
+    async fn test_request_counted_until_body_consumed() {
+        let counter = InFlightCounter::default();
+        let mut app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(counter.clone(), in_flight_middleware));
+
+        let response = app
+            .call(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"ok");
+        assert_eq!(counter.load(Ordering::SeqCst), 0);
+    }
+}
@@ -1,11 +1,360 @@
 // Logging middleware
-// Directly use tower_http::trace::TraceLayer::new_for_http() in routes
+// HTTP 追踪直接使用 tower_http::trace::TraceLayer::new_for_http()；
+// 本模块提供审计日志中间件，记录每个反代请求的元信息 (不含 token 与请求体)
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+const AUDIT_LOG_FILE: &str = "proxy_audit.log";
+const MAX_AUDIT_LOG_BYTES: u64 = 10 * 1024 * 1024; // 10MB 轮转
+const MAX_AUDIT_ARCHIVES: usize = 5;
+const MAX_REQUEST_PEEK_SIZE: usize = 1024 * 1024; // 仅 Content-Length 不超过该值时读取请求体提取 model 字段
+
+/// 审计日志条目
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditLogEntry {
+    pub timestamp: i64, // 毫秒时间戳
+    pub source_ip: Option<String>,
+    pub protocol: String, // claude | openai | gemini | ollama
+    pub method: String,
+    pub path: String, // 不含 query (Gemini 协议可能在 query 中携带 key)
+    pub model: Option<String>,
+    pub mapped_model: Option<String>,
+    pub account_email: Option<String>,
+    pub status: u16,
+    pub latency_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// 追加写入的审计日志文件，超过大小上限时轮转为 `.1` ~ `.N`
+pub struct AuditLogger {
+    path: PathBuf,
+    max_bytes: u64,
+    max_archives: usize,
+    lock: Mutex<()>,
+}
+
+impl AuditLogger {
+    pub fn new(path: PathBuf) -> Self {
+        Self::with_limits(path, MAX_AUDIT_LOG_BYTES, MAX_AUDIT_ARCHIVES)
+    }
+
+    pub fn with_limits(path: PathBuf, max_bytes: u64, max_archives: usize) -> Self {
+        Self {
+            path,
+            max_bytes,
+            max_archives,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn archive_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    /// 轮转: log.(N-1) -> log.N ... log -> log.1，最旧的归档被覆盖
+    fn rotate(&self) -> Result<(), String> {
+        if self.max_archives == 0 {
+            return fs::remove_file(&self.path).map_err(|e| format!("删除审计日志失败: {}", e));
+        }
+        for i in (1..self.max_archives).rev() {
+            let from = self.archive_path(i);
+            if from.exists() {
+                fs::rename(&from, self.archive_path(i + 1))
+                    .map_err(|e| format!("轮转审计日志失败: {}", e))?;
+            }
+        }
+        fs::rename(&self.path, self.archive_path(1)).map_err(|e| format!("轮转审计日志失败: {}", e))
+    }
+
+    pub fn append(&self, entry: &AuditLogEntry) -> Result<(), String> {
+        let mut line = serde_json::to_string(entry).map_err(|e| format!("序列化审计日志失败: {}", e))?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let current_size = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        if current_size > 0 && current_size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("打开审计日志失败: {}", e))?;
+        file.write_all(line.as_bytes())
+            .map_err(|e| format!("写入审计日志失败: {}", e))
+    }
+
+    /// 按时间倒序读取日志条目 (最新在前)，跨越归档文件
+    pub fn read_entries(&self, limit: usize, offset: usize) -> Result<Vec<AuditLogEntry>, String> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let wanted = offset.saturating_add(limit);
+        let mut entries = Vec::new();
+
+        let files = std::iter::once(self.path.clone())
+            .chain((1..=self.max_archives).map(|i| self.archive_path(i)));
+        for path in files {
+            if entries.len() >= wanted {
+                break;
+            }
+            let mut file_entries = read_log_file(&path)?;
+            file_entries.reverse();
+            entries.extend(file_entries);
+        }
+
+        Ok(entries.into_iter().skip(offset).take(limit).collect())
+    }
+}
+
+fn read_log_file(path: &Path) -> Result<Vec<AuditLogEntry>, String> {
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("读取审计日志失败: {}", e)),
+    };
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<AuditLogEntry>(&line).ok())
+        .collect())
+}
+
+/// 外层中间件已解析出的请求模型名 (通过 request extensions 传递)
+#[derive(Debug, Clone)]
+pub struct RequestedModel(pub Option<String>);
+
+/// 请求体中只反序列化 model 字段，其余字段直接跳过
+#[derive(Deserialize)]
+struct ModelField {
+    model: Option<String>,
+}
+
+/// 提取请求的模型名: Gemini 取自路径，其余协议从请求体中只读取 model 字段
+/// 只缓冲声明了 Content-Length 且不超过 MAX_REQUEST_PEEK_SIZE 的请求体，更大的请求不记录模型名；
+/// 读取失败时返回 400/413 响应，不再把空请求体转发给下游
+pub async fn peek_request_model(request: Request) -> Result<(Request, Option<String>), Response> {
+    if let Some(RequestedModel(model)) = request.extensions().get::<RequestedModel>() {
+        let model = model.clone();
+        return Ok((request, model));
+    }
+
+    let path_model = request
+        .uri()
+        .path()
+        .strip_prefix("/v1beta/models/")
+        .and_then(|s| s.split(':').next())
+        .map(|s| s.to_string());
+    if path_model.is_some() || request.method() != axum::http::Method::POST {
+        return Ok((request, path_model));
+    }
+
+    let content_length = request
+        .headers()
+        .get(axum::http::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if content_length.is_none_or(|len| len > MAX_REQUEST_PEEK_SIZE) {
+        return Ok((request, None));
+    }
+
+    let (parts, body) = request.into_parts();
+    let bytes = axum::body::to_bytes(body, MAX_REQUEST_PEEK_SIZE)
+        .await
+        .map_err(|e| super::body_read_error(&e, MAX_REQUEST_PEEK_SIZE))?;
+    let model = serde_json::from_slice::<ModelField>(&bytes)
+        .ok()
+        .and_then(|f| f.model);
+    Ok((Request::from_parts(parts, Body::from(bytes)), model))
+}
+
+static AUDIT_LOGGER: OnceLock<Option<AuditLogger>> = OnceLock::new();
+
+/// 全局审计日志 (位于数据目录 `.antigravity_tools/proxy_audit.log`)
+pub fn audit_logger() -> Option<&'static AuditLogger> {
+    AUDIT_LOGGER
+        .get_or_init(|| match crate::modules::account::get_data_dir() {
+            Ok(dir) => Some(AuditLogger::new(dir.join(AUDIT_LOG_FILE))),
+            Err(e) => {
+                tracing::warn!("审计日志不可用: {}", e);
+                None
+            }
+        })
+        .as_ref()
+}
+
+/// 根据路径识别 API 协议，非反代端点返回 None (不记录)
+pub fn detect_protocol(path: &str) -> Option<&'static str> {
+    if path.starts_with("/v1/messages") {
+        Some("claude")
+    } else if path.starts_with("/v1beta/models/") {
+        Some("gemini")
+    } else if path.starts_with("/api/") {
+        Some("ollama")
+    } else if path.starts_with("/v1/chat/completions")
+        || path.starts_with("/v1/completions")
+        || path.starts_with("/v1/responses")
+        || path.starts_with("/v1/images/")
+        || path.starts_with("/v1/audio/")
+        || path.starts_with("/v1/files")
+        || path.starts_with("/v1/embeddings")
+    {
+        Some("openai")
+    } else {
+        None
+    }
+}
+
+fn header_string(response: &Response, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+}
+
+pub async fn audit_middleware(request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    let Some(protocol) = detect_protocol(&path) else {
+        return next.run(request).await;
+    };
+    let Some(logger) = audit_logger() else {
+        return next.run(request).await;
+    };
+
+    let start = Instant::now();
+    let method = request.method().to_string();
+    let source_ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ci| ci.0.ip().to_string());
+    let request_id = request
+        .extensions()
+        .get::<super::RequestId>()
+        .map(|id| id.0.clone());
+
+    let (request, model) = match peek_request_model(request).await {
+        Ok(peeked) => peeked,
+        Err(response) => return response,
+    };
+
+    let response = next.run(request).await;
+
+    let entry = AuditLogEntry {
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        source_ip,
+        protocol: protocol.to_string(),
+        method,
+        path,
+        model,
+        mapped_model: header_string(&response, "X-Mapped-Model"),
+        account_email: header_string(&response, "X-Account-Email"),
+        status: response.status().as_u16(),
+        latency_ms: start.elapsed().as_millis() as u64,
+        request_id,
+    };
+
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = logger.append(&entry) {
+            tracing::warn!("{}", e);
+        }
+    });
+
+    response
+}
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    fn make_entry(ts: i64) -> AuditLogEntry {
+        AuditLogEntry {
+            timestamp: ts,
+            source_ip: Some("127.0.0.1".to_string()),
+            protocol: "openai".to_string(),
+            method: "POST".to_string(),
+            path: "/v1/chat/completions".to_string(),
+            model: Some("gpt-4o".to_string()),
+            mapped_model: Some("gemini-2.5-pro".to_string()),
+            account_email: Some("a@example.com".to_string()),
+            status: 200,
+            latency_ms: 12,
+            request_id: None,
+        }
+    }
+
+    #[test]
+    fn test_audit_log_rotation_and_read() {
+        let dir = std::env::temp_dir().join(format!("audit_log_test_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let line_len = serde_json::to_string(&make_entry(0)).unwrap().len() as u64 + 1;
+        // 每个文件最多容纳 2 条，最多 2 个归档
+        let logger = AuditLogger::with_limits(dir.join(AUDIT_LOG_FILE), line_len * 2, 2);
+
+        for ts in 0..8 {
+            logger.append(&make_entry(ts)).unwrap();
+        }
+
+        assert!(logger.archive_path(2).exists());
+        assert!(!logger.archive_path(3).exists());
+
+        // 当前文件 + 2 个归档共保留最近 6 条，最新在前
+        let all = logger.read_entries(100, 0).unwrap();
+        let timestamps: Vec<i64> = all.iter().map(|e| e.timestamp).collect();
+        assert_eq!(timestamps, vec![7, 6, 5, 4, 3, 2]);
+
+        let page = logger.read_entries(2, 3).unwrap();
+        assert_eq!(page.iter().map(|e| e.timestamp).collect::<Vec<_>>(), vec![4, 3]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
     #[test]
-    fn test_logging_middleware() {
-        // Logging middleware is used directly via tower_http::trace::TraceLayer::new_for_http()
-        assert!(true);
+    fn test_detect_protocol() {
+        assert_eq!(detect_protocol("/v1/messages"), Some("claude"));
+        assert_eq!(detect_protocol("/v1beta/models/gemini-2.5-pro:generateContent"), Some("gemini"));
+        assert_eq!(detect_protocol("/v1/chat/completions"), Some("openai"));
+        assert_eq!(detect_protocol("/api/chat"), Some("ollama"));
+        assert_eq!(detect_protocol("/healthz"), None);
+    }
+
+    #[tokio::test]
+    async fn test_peek_request_model_only_buffers_small_bodies() {
+        let body = r#"{"model":"claude-sonnet-4-5","messages":[]}"#;
+        let request = Request::post("/v1/messages")
+            .header("content-length", body.len())
+            .body(Body::from(body))
+            .unwrap();
+        let (request, model) = peek_request_model(request).await.unwrap();
+        assert_eq!(model.as_deref(), Some("claude-sonnet-4-5"));
+        let forwarded = axum::body::to_bytes(request.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(forwarded, body.as_bytes());
+
+        // 未声明长度的请求体不读取
+        let request = Request::post("/v1/messages").body(Body::from(body)).unwrap();
+        let (request, model) = peek_request_model(request).await.unwrap();
+        assert!(model.is_none());
+        let forwarded = axum::body::to_bytes(request.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(forwarded, body.as_bytes());
+
+        let request = Request::get("/v1beta/models/gemini-2.5-pro:generateContent")
+            .body(Body::empty())
+            .unwrap();
+        let (_, model) = peek_request_model(request).await.unwrap();
+        assert_eq!(model.as_deref(), Some("gemini-2.5-pro"));
     }
 }
@@ -0,0 +1,58 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::time::Instant;
+
+use crate::proxy::middleware::logging::{detect_protocol, peek_request_model, RequestedModel};
+use crate::proxy::server::AppState;
+
+/// 记录 Prometheus 请求指标 (仅统计反代端点)
+pub async fn metrics_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    let Some(protocol) = detect_protocol(&path) else {
+        return next.run(request).await;
+    };
+
+    let start = Instant::now();
+    let (mut request, model) = match peek_request_model(request).await {
+        Ok(peeked) => peeked,
+        Err(response) => return response,
+    };
+    // 共享给内层审计中间件，避免重复读取请求体
+    request.extensions_mut().insert(RequestedModel(model.clone()));
+
+    let response = next.run(request).await;
+    let status = response.status().as_u16();
+    let elapsed = start.elapsed();
+
+    state.metrics.record_request(
+        protocol,
+        model.as_deref().unwrap_or("unknown"),
+        status,
+        elapsed,
+    );
+
+    // 更新账号健康度: 仅统计与账号相关的结果，客户端错误 (400/404 等) 不计入
+    // 去重复用的响应没有产生新的上游调用，同样不计入
+    let deduplicated = response.headers().contains_key("X-Deduplicated");
+    if let Some(email) = response
+        .headers()
+        .get("X-Account-Email")
+        .and_then(|v| v.to_str().ok())
+        .filter(|_| !deduplicated)
+    {
+        let success = status < 400;
+        if success || matches!(status, 401 | 403 | 429) || status >= 500 {
+            state
+                .token_manager
+                .record_request_health(email, success, elapsed.as_secs_f64() * 1000.0);
+        }
+    }
+    response
+}
@@ -1,9 +1,24 @@
 // Middleware module - Axum middleware
 
 pub mod auth;
+pub mod compression;
 pub mod cors;
+pub mod dedup;
+pub mod in_flight;
 pub mod logging;
+pub mod metrics;
 pub mod monitor;
+pub mod request_id;
+pub mod security_headers;
+pub mod size_limit;
 
-pub use auth::auth_middleware;
-pub use cors::cors_layer;
+pub use auth::{auth_middleware, request_api_key};
+pub use compression::{compression_layer, request_decompression_layer};
+pub use cors::{cors_guard_middleware, cors_layer, CorsConfig};
+pub use dedup::{dedup_middleware, DeduplicateLayer};
+pub use in_flight::{in_flight_middleware, InFlightCounter};
+pub use logging::audit_middleware;
+pub use metrics::metrics_middleware;
+pub use request_id::{request_id_middleware, RequestId};
+pub use security_headers::security_headers_middleware;
+pub use size_limit::{body_read_error, request_size_limit_middleware, RequestSizeLimitLayer};
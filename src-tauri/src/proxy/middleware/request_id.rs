@@ -0,0 +1,101 @@
+// 请求 ID 中间件
+// 读取客户端的 X-Request-ID (缺失时生成 UUID v4)，写入 request extensions 与当前 tracing span，
+// 在响应中回显，并 (可选) 作为 X-Goog-Request-Id 转发给上游，便于客户端将自身链路与反代日志关联
+
+use axum::{
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-ID";
+pub const UPSTREAM_REQUEST_ID_HEADER: &str = "X-Goog-Request-Id";
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// 当前请求的 ID (通过 request extensions 传递)
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+tokio::task_local! {
+    /// 需要转发给上游的请求 ID (propagate_request_id 关闭时为 None)
+    static UPSTREAM_REQUEST_ID: Option<String>;
+}
+
+/// 当前请求需要转发给上游的请求 ID，不在请求上下文中时返回 None
+pub fn upstream_request_id() -> Option<String> {
+    UPSTREAM_REQUEST_ID.try_with(|id| id.clone()).ok().flatten()
+}
+
+/// 客户端提供的 ID 仅接受可见 ASCII 且长度受限，否则重新生成
+fn resolve_request_id(request: &Request) -> String {
+    request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic()))
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+pub async fn request_id_middleware(
+    State(propagate): State<bool>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let request_id = resolve_request_id(&request);
+    // TraceLayer 创建的 span 预留了 request_id 字段，处理器内的日志均携带该 ID
+    tracing::Span::current().record("request_id", request_id.as_str());
+    request.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let upstream_id = propagate.then(|| request_id.clone());
+    let mut response = UPSTREAM_REQUEST_ID.scope(upstream_id, next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Extension, Router};
+    use tower::Service;
+
+    fn app(propagate: bool) -> Router {
+        Router::new()
+            .route(
+                "/",
+                get(|Extension(RequestId(id)): Extension<RequestId>| async move {
+                    format!("{}|{}", id, upstream_request_id().unwrap_or_default())
+                }),
+            )
+            .layer(axum::middleware::from_fn_with_state(propagate, request_id_middleware))
+    }
+
+    async fn body_string(resp: Response) -> String {
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request_id_echoed_and_propagated() {
+        let resp = app(true)
+            .call(Request::get("/").header(REQUEST_ID_HEADER, "trace-123").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()[REQUEST_ID_HEADER], "trace-123");
+        assert_eq!(body_string(resp).await, "trace-123|trace-123");
+
+        // 缺失时生成 UUID；关闭转发时上游 ID 为空
+        let resp = app(false)
+            .call(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let id = resp.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        assert!(uuid::Uuid::parse_str(&id).is_ok());
+        assert_eq!(body_string(resp).await, format!("{}|", id));
+        assert_eq!(upstream_request_id(), None);
+    }
+}
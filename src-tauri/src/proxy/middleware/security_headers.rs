@@ -0,0 +1,71 @@
+// 安全响应头中间件
+// 为所有响应 (包括鉴权失败的 401) 添加防点击劫持 / 内容嗅探相关的响应头
+
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+const DEFAULT_CSP: &str = "default-src 'none'";
+/// SSE 响应使用宽松策略，避免阻断事件流
+const SSE_CSP: &str = "connect-src 'self'";
+
+fn is_event_stream(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/event-stream"))
+}
+
+pub async fn security_headers_middleware(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    let csp = if is_event_stream(&response) { SSE_CSP } else { DEFAULT_CSP };
+
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_SECURITY_POLICY, HeaderValue::from_static(csp));
+    headers.insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+    headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    headers.insert(header::REFERRER_POLICY, HeaderValue::from_static("no-referrer"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, routing::get, Router};
+    use tower::Service;
+
+    #[tokio::test]
+    async fn test_security_headers_on_json_sse_and_rejected_responses() {
+        let mut app = Router::new()
+            .route("/json", get(|| async { "{}" }))
+            .route(
+                "/sse",
+                get(|| async { ([(header::CONTENT_TYPE, "text/event-stream")], "data: {}\n\n") }),
+            )
+            .route("/denied", get(|| async { StatusCode::UNAUTHORIZED }))
+            .layer(axum::middleware::from_fn(security_headers_middleware));
+
+        for path in ["/json", "/denied"] {
+            let resp = app
+                .call(Request::get(path).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let headers = resp.headers();
+            assert_eq!(headers[header::CONTENT_SECURITY_POLICY], DEFAULT_CSP);
+            assert_eq!(headers[header::X_FRAME_OPTIONS], "DENY");
+            assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+            assert_eq!(headers[header::REFERRER_POLICY], "no-referrer");
+        }
+
+        let resp = app
+            .call(Request::get("/sse").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()[header::CONTENT_SECURITY_POLICY], SSE_CSP);
+        assert_eq!(resp.headers()[header::X_FRAME_OPTIONS], "DENY");
+    }
+}
@@ -0,0 +1,167 @@
+// 请求体大小限制中间件
+// 超过 max_request_body_mb 的请求直接返回 413，避免超大 inline data (如 base64 视频) 被整体缓冲导致内存耗尽
+// 仅限制请求体，上游的流式响应不受影响
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+/// 不受限制的端点 (无请求体的监控接口)
+const EXEMPT_PATHS: [&str; 3] = ["/healthz", "/health", "/metrics"];
+
+#[derive(Clone, Copy)]
+pub struct RequestSizeLimitLayer {
+    max_bytes: usize,
+}
+
+impl RequestSizeLimitLayer {
+    pub fn new(max_bytes: usize) -> Self {
+        Self { max_bytes }
+    }
+
+    pub fn from_proxy_config(config: &crate::proxy::config::ProxyConfig) -> Self {
+        Self::new(config.max_request_body_mb.saturating_mul(1024 * 1024))
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+}
+
+fn payload_too_large(max_bytes: usize) -> Response {
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(json!({
+            "error": {
+                "type": "request_too_large",
+                "message": format!("Request body exceeds the limit of {} MB", max_bytes / (1024 * 1024)),
+            }
+        })),
+    )
+        .into_response()
+}
+
+/// 中间件缓冲请求体失败时的响应: 超过长度上限返回 413，其余 (客户端断开 / 解压失败等) 返回 400
+/// 不能以空请求体继续转发，否则处理器会收到被静默丢弃的请求
+pub fn body_read_error(err: &axum::Error, max_bytes: usize) -> Response {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(err);
+    while let Some(e) = source {
+        if e.is::<http_body_util::LengthLimitError>() {
+            return payload_too_large(max_bytes);
+        }
+        source = e.source();
+    }
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({
+            "error": {
+                "type": "invalid_request_error",
+                "message": format!("Failed to read request body: {}", err),
+            }
+        })),
+    )
+        .into_response()
+}
+
+pub async fn request_size_limit_middleware(
+    State(limit): State<RequestSizeLimitLayer>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if EXEMPT_PATHS.contains(&request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let content_length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    match content_length {
+        Some(len) if len > limit.max_bytes as u64 => {
+            tracing::warn!("[SizeLimit] Rejected request body of {} bytes", len);
+            payload_too_large(limit.max_bytes)
+        }
+        Some(_) => next.run(request).await,
+        // 无 Content-Length (分块传输 / 解压后的请求体): 最多缓冲 max_bytes
+        None => {
+            let (parts, body) = request.into_parts();
+            match axum::body::to_bytes(body, limit.max_bytes).await {
+                Ok(bytes) => next.run(Request::from_parts(parts, Body::from(bytes))).await,
+                Err(_) => {
+                    tracing::warn!("[SizeLimit] Rejected streamed request body over {} bytes", limit.max_bytes);
+                    payload_too_large(limit.max_bytes)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        routing::{get, post},
+        Router,
+    };
+    use tower::Service;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/v1/messages", post(|body: String| async move { body.len().to_string() }))
+            .route("/metrics", get(|| async { "ok" }).post(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                RequestSizeLimitLayer::new(16),
+                request_size_limit_middleware,
+            ))
+    }
+
+    #[tokio::test]
+    async fn test_rejects_oversized_content_length_and_stream() {
+        let mut app = app();
+
+        let resp = app
+            .call(Request::post("/v1/messages").body(Body::from("x".repeat(16))).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = app
+            .call(Request::post("/v1/messages").body(Body::from("x".repeat(17))).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // 无 Content-Length 的分块请求体
+        let chunks = futures::stream::iter(
+            ["0123456789", "0123456789"].map(|c| Ok::<_, std::io::Error>(bytes::Bytes::from_static(c.as_bytes()))),
+        );
+        let resp = app
+            .call(Request::post("/v1/messages").body(Body::from_stream(chunks)).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let resp = app
+            .call(Request::post("/metrics").body(Body::from("x".repeat(64))).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_body_read_error_status() {
+        let err = axum::body::to_bytes(Body::from("x".repeat(32)), 16).await.unwrap_err();
+        assert_eq!(body_read_error(&err, 16).status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let broken = futures::stream::iter([Err::<bytes::Bytes, _>(std::io::Error::other("reset"))]);
+        let err = axum::body::to_bytes(Body::from_stream(broken), 16).await.unwrap_err();
+        assert_eq!(body_read_error(&err, 16).status(), StatusCode::BAD_REQUEST);
+    }
+}
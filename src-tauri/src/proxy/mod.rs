@@ -17,18 +17,26 @@ pub mod providers;         // Extra upstream providers (z.ai, etc.)
 pub mod zai_vision_mcp;    // Built-in Vision MCP server state
 pub mod zai_vision_tools;  // Built-in Vision MCP tools (z.ai vision API)
 pub mod monitor;           // 监控
+pub mod metrics;           // Prometheus 指标
+pub mod model_stats;       // 按模型 / 账号的用量统计
+pub mod latency_stats;     // 按账号的请求延迟统计
+pub mod active_streams;    // 活跃流式连接跟踪
+pub mod stream_recorder;   // SSE 响应录制与回放 (调试)
 pub mod rate_limit;        // 限流跟踪
 pub mod sticky_config;     // 粘性调度配置
 pub mod session_manager;   // 会话指纹管理
 pub mod audio;             // 音频处理模块 (PR #311)
 pub mod signature_cache;   // Signature Cache (v3.3.16)
+pub mod file_store;        // OpenAI Files API -> Google File API 映射
+pub mod batch_store;       // OpenAI Batch API (同步处理)
+pub mod token_state;       // TokenManager 熔断状态持久化
 
 
 pub use config::ProxyConfig;
 pub use config::ProxyAuthMode;
 pub use config::ZaiConfig;
 pub use config::ZaiDispatchMode;
-pub use token_manager::TokenManager;
+pub use token_manager::{AccountAllowlist, TokenManager};
 pub use server::AxumServer;
 pub use security::ProxySecurityConfig;
 pub use signature_cache::SignatureCache;
@@ -0,0 +1,306 @@
+// 按模型 / 账号统计上游用量
+// 每次上游响应后累加请求数与错误数；token 数优先取上游 usageMetadata，缺失时按请求体本地估算
+
+use bytes::Bytes;
+use dashmap::DashMap;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// 流式扫描时保留的上一分片尾部长度，防止字段被分片截断
+const SCAN_TAIL_BYTES: usize = 64;
+
+pub type UsageStatsMap = Arc<DashMap<String, ModelStats>>;
+
+/// 单个模型 (或账号) 的累计用量
+#[derive(Debug, Default)]
+pub struct ModelStats {
+    pub request_count: AtomicU64,
+    pub estimated_input_tokens: AtomicU64,
+    pub estimated_output_tokens: AtomicU64,
+    pub error_count: AtomicU64,
+}
+
+impl ModelStats {
+    fn reset(&self) {
+        for counter in [
+            &self.request_count,
+            &self.estimated_input_tokens,
+            &self.estimated_output_tokens,
+            &self.error_count,
+        ] {
+            counter.store(0, Ordering::SeqCst);
+        }
+    }
+}
+
+/// 用量快照 (返回给前端)
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ModelStatsSnapshot {
+    pub name: String,
+    pub request_count: u64,
+    pub estimated_input_tokens: u64,
+    pub estimated_output_tokens: u64,
+    pub error_count: u64,
+}
+
+/// 导出快照，按请求数降序
+pub fn snapshot(stats: &DashMap<String, ModelStats>) -> Vec<ModelStatsSnapshot> {
+    let mut list: Vec<ModelStatsSnapshot> = stats
+        .iter()
+        .map(|e| ModelStatsSnapshot {
+            name: e.key().clone(),
+            request_count: e.request_count.load(Ordering::Relaxed),
+            estimated_input_tokens: e.estimated_input_tokens.load(Ordering::Relaxed),
+            estimated_output_tokens: e.estimated_output_tokens.load(Ordering::Relaxed),
+            error_count: e.error_count.load(Ordering::Relaxed),
+        })
+        .collect();
+    list.sort_by(|a, b| b.request_count.cmp(&a.request_count).then_with(|| a.name.cmp(&b.name)));
+    list
+}
+
+/// 将所有计数器清零 (保留已出现的模型 / 账号条目)
+pub fn reset(stats: &DashMap<String, ModelStats>) {
+    for entry in stats.iter() {
+        entry.reset();
+    }
+}
+
+/// 上游返回的 token 用量 (usageMetadata)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub input: Option<u64>,
+    pub output: Option<u64>,
+}
+
+impl TokenUsage {
+    /// 从 Gemini 响应 (含 v1internal 包装) 中读取 usageMetadata
+    pub fn from_response(resp: &Value) -> Self {
+        let usage = resp
+            .get("response")
+            .unwrap_or(resp)
+            .get("usageMetadata");
+        Self {
+            input: usage.and_then(|u| u.get("promptTokenCount")).and_then(|v| v.as_u64()),
+            output: usage.and_then(|u| u.get("candidatesTokenCount")).and_then(|v| v.as_u64()),
+        }
+    }
+}
+
+/// 本地估算 v1internal 请求体的输入 token 数
+pub fn estimate_request_tokens(wrapped_body: &Value) -> u64 {
+    crate::proxy::mappers::gemini::estimate_tokens(wrapped_body.get("request").unwrap_or(wrapped_body))
+}
+
+/// 绑定到一次上游调用 (模型 + 账号) 的用量记录器
+#[derive(Clone)]
+pub struct UsageRecorder {
+    model_stats: UsageStatsMap,
+    account_stats: UsageStatsMap,
+    model: String,
+    email: String,
+    estimated_input: u64,
+    /// 最近一次记录的上游用量 (供收集完整流后读取)
+    observed: Arc<std::sync::Mutex<Option<TokenUsage>>>,
+}
+
+impl UsageRecorder {
+    pub fn new(
+        model_stats: &UsageStatsMap,
+        account_stats: &UsageStatsMap,
+        model: &str,
+        email: &str,
+        estimated_input: u64,
+    ) -> Self {
+        Self {
+            model_stats: model_stats.clone(),
+            account_stats: account_stats.clone(),
+            model: model.to_string(),
+            email: email.to_string(),
+            estimated_input,
+            observed: Arc::new(std::sync::Mutex::new(None)),
+        }
+    }
+
+    /// 已记录的上游用量 (流被完整消费后可用)
+    pub fn observed(&self) -> Option<TokenUsage> {
+        *self.observed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn update(&self, f: impl Fn(&ModelStats)) {
+        f(&self.model_stats.entry(self.model.clone()).or_default());
+        f(&self.account_stats.entry(self.email.clone()).or_default());
+    }
+
+    /// 记录一次上游响应 (或连接失败)
+    pub fn record_response(&self, success: bool) {
+        self.update(|s| {
+            s.request_count.fetch_add(1, Ordering::Relaxed);
+            if !success {
+                s.error_count.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+    }
+
+    /// 记录成功响应的 token 用量，上游未返回输入 token 时使用本地估算值
+    pub fn record_tokens(&self, usage: TokenUsage) {
+        *self.observed.lock().unwrap_or_else(|e| e.into_inner()) = Some(usage);
+        let input = usage.input.unwrap_or(self.estimated_input);
+        let output = usage.output.unwrap_or(0);
+        self.update(|s| {
+            s.estimated_input_tokens.fetch_add(input, Ordering::Relaxed);
+            s.estimated_output_tokens.fetch_add(output, Ordering::Relaxed);
+        });
+    }
+}
+
+/// 在 SSE 字节流中查找用量字段，保留最后一次出现的值 (Gemini 每个分片携带累计用量)
+#[derive(Default)]
+struct UsageScanner {
+    tail: Vec<u8>,
+    usage: TokenUsage,
+}
+
+impl UsageScanner {
+    fn feed(&mut self, chunk: &[u8]) {
+        let mut window = std::mem::take(&mut self.tail);
+        window.extend_from_slice(chunk);
+        let text = String::from_utf8_lossy(&window);
+        if let Some(v) = last_number_after(&text, "\"promptTokenCount\"") {
+            self.usage.input = Some(v);
+        }
+        if let Some(v) = last_number_after(&text, "\"candidatesTokenCount\"") {
+            self.usage.output = Some(v);
+        }
+        let keep = window.len().min(SCAN_TAIL_BYTES);
+        self.tail = window.split_off(window.len() - keep);
+    }
+}
+
+/// 查找 `key: <数字>` 最后一次完整出现的数值
+fn last_number_after(text: &str, key: &str) -> Option<u64> {
+    text.rmatch_indices(key).find_map(|(idx, _)| {
+        let rest = text[idx + key.len()..].trim_start().strip_prefix(':')?.trim_start();
+        let end = rest.find(|c: char| !c.is_ascii_digit())?;
+        rest[..end].parse().ok()
+    })
+}
+
+/// 流结束 (或被客户端提前丢弃) 时写入 token 用量
+struct StreamUsage {
+    recorder: UsageRecorder,
+    scanner: UsageScanner,
+}
+
+impl Drop for StreamUsage {
+    fn drop(&mut self) {
+        self.recorder.record_tokens(self.scanner.usage);
+    }
+}
+
+/// 包装上游字节流，透传数据并统计 token 用量
+pub fn track_stream_usage<S, E>(
+    stream: S,
+    recorder: UsageRecorder,
+) -> impl Stream<Item = Result<Bytes, E>> + Send
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: Send + 'static,
+{
+    async_stream::stream! {
+        let mut stream = Box::pin(stream);
+        let mut usage = StreamUsage { recorder, scanner: UsageScanner::default() };
+        while let Some(item) = stream.next().await {
+            if let Ok(bytes) = &item {
+                usage.scanner.feed(bytes);
+            }
+            yield item;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn maps() -> (UsageStatsMap, UsageStatsMap) {
+        (Arc::new(DashMap::new()), Arc::new(DashMap::new()))
+    }
+
+    #[test]
+    fn test_recorder_counts_per_model_and_account() {
+        let (models, accounts) = maps();
+        let pro = UsageRecorder::new(&models, &accounts, "gemini-2.5-pro", "a@example.com", 7);
+        pro.record_response(true);
+        pro.record_tokens(TokenUsage { input: None, output: Some(5) });
+        pro.record_response(false);
+
+        let flash = UsageRecorder::new(&models, &accounts, "gemini-2.5-flash", "a@example.com", 3);
+        flash.record_response(true);
+        flash.record_tokens(TokenUsage { input: Some(10), output: Some(2) });
+
+        let by_model = snapshot(&models);
+        assert_eq!(
+            by_model[0],
+            ModelStatsSnapshot {
+                name: "gemini-2.5-pro".to_string(),
+                request_count: 2,
+                estimated_input_tokens: 7,
+                estimated_output_tokens: 5,
+                error_count: 1,
+            }
+        );
+        assert_eq!(by_model[1].estimated_input_tokens, 10);
+
+        let by_account = snapshot(&accounts);
+        assert_eq!(by_account.len(), 1);
+        assert_eq!(by_account[0].request_count, 3);
+        assert_eq!(by_account[0].estimated_output_tokens, 7);
+
+        reset(&models);
+        assert!(snapshot(&models).iter().all(|s| s.request_count == 0
+            && s.estimated_input_tokens == 0
+            && s.estimated_output_tokens == 0
+            && s.error_count == 0));
+        assert_eq!(snapshot(&accounts)[0].request_count, 3);
+    }
+
+    #[test]
+    fn test_usage_from_wrapped_response() {
+        let resp = json!({
+            "response": {
+                "candidates": [],
+                "usageMetadata": { "promptTokenCount": 12, "candidatesTokenCount": 34 }
+            }
+        });
+        assert_eq!(
+            TokenUsage::from_response(&resp),
+            TokenUsage { input: Some(12), output: Some(34) }
+        );
+        assert_eq!(TokenUsage::from_response(&json!({})), TokenUsage::default());
+    }
+
+    #[tokio::test]
+    async fn test_track_stream_usage_across_chunks() {
+        let (models, accounts) = maps();
+        let recorder = UsageRecorder::new(&models, &accounts, "gemini-2.5-flash", "b@example.com", 1);
+        let chunks: Vec<Result<Bytes, String>> = vec![
+            Ok(Bytes::from("data: {\"usageMetadata\": {\"promptTokenCount\": 8, \"candidatesTokenCount\": 1}}\n\n")),
+            Ok(Bytes::from("data: {\"usageMetadata\": {\"promptTokenCount\": 8, \"candidatesTok")),
+            Ok(Bytes::from("enCount\": 42}}\n\n")),
+        ];
+
+        let observer = recorder.clone();
+        let out: Vec<_> = track_stream_usage(futures::stream::iter(chunks), recorder).collect().await;
+        assert_eq!(out.len(), 3);
+
+        assert_eq!(observer.observed(), Some(TokenUsage { input: Some(8), output: Some(42) }));
+        let stats = snapshot(&models);
+        assert_eq!(stats[0].estimated_input_tokens, 8);
+        assert_eq!(stats[0].estimated_output_tokens, 42);
+    }
+}
@@ -27,6 +27,18 @@ pub struct ProxyStats {
     pub total_requests: u64,
     pub success_count: u64,
     pub error_count: u64,
+    /// 按模型的上游用量 (仅反代运行期间累计)
+    #[serde(default)]
+    pub model_stats: Vec<crate::proxy::model_stats::ModelStatsSnapshot>,
+    /// 按账号的上游用量
+    #[serde(default)]
+    pub account_stats: Vec<crate::proxy::model_stats::ModelStatsSnapshot>,
+    /// 反代实际监听的端口 (未运行时为 0)
+    #[serde(default)]
+    pub actual_port: u16,
+    /// 上次清零用量统计的时间 (RFC 3339，反代未运行时为空)
+    #[serde(default)]
+    pub last_stats_reset_at: Option<String>,
 }
 
 pub struct ProxyMonitor {
@@ -127,8 +139,9 @@ impl ProxyMonitor {
         }
     }
 
-    pub async fn get_stats(&self) -> ProxyStats {
-        match crate::modules::proxy_db::get_stats() {
+    /// `since_ms` 为 Some 时只统计该时间 (毫秒) 之后的请求 (数据库不可用时回退到内存统计，不按时间过滤)
+    pub async fn get_stats(&self, since_ms: Option<i64>) -> ProxyStats {
+        match crate::modules::proxy_db::get_stats(since_ms) {
             Ok(stats) => stats,
             Err(e) => {
                 tracing::error!("Failed to get stats from DB: {}", e);
@@ -1,5 +1,5 @@
 use dashmap::DashMap;
-use std::time::{SystemTime, Duration};
+use std::time::{SystemTime, Duration, Instant};
 use regex::Regex;
 
 /// 限流原因类型
@@ -507,6 +507,67 @@ impl Default for RateLimitTracker {
     }
 }
 
+/// 令牌桶限流器 (本地主动限速)
+///
+/// 按 `refill_per_sec` 的速率补充令牌，最多累积 `capacity` 个 (突发上限)。
+/// 与 `RateLimitTracker` 不同，它在请求发出前限速，避免单账号被高频请求打出 429。
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    refill_per_sec: f64,
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// 创建令牌桶，初始为满桶
+    pub fn new(refill_per_sec: f64, burst: u32) -> Self {
+        let capacity = burst.max(1) as f64;
+        Self {
+            refill_per_sec: refill_per_sec.max(0.0),
+            capacity,
+            tokens: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// 尝试消耗一个令牌，桶空时立即返回 false (不阻塞)
+    pub fn try_acquire(&mut self) -> bool {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 更新速率与突发上限，保留当前令牌数 (不超过新上限)
+    pub fn reconfigure(&mut self, refill_per_sec: f64, burst: u32) {
+        self.refill();
+        self.refill_per_sec = refill_per_sec.max(0.0);
+        self.capacity = burst.max(1) as f64;
+        self.tokens = self.tokens.min(self.capacity);
+    }
+
+    /// 当前速率 (令牌/秒)
+    pub fn refill_per_sec(&self) -> f64 {
+        self.refill_per_sec
+    }
+
+    /// 当前突发上限
+    pub fn burst(&self) -> u32 {
+        self.capacity as u32
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -572,4 +633,28 @@ mod tests {
         // 应该被识别为 RateLimitExceeded，而不是 QuotaExhausted
         assert_eq!(reason, RateLimitReason::RateLimitExceeded);
     }
+
+    #[test]
+    fn test_token_bucket_burst_and_refill() {
+        let mut limiter = RateLimiter::new(2.0, 2);
+        assert!(limiter.try_acquire());
+        assert!(limiter.try_acquire());
+        // 突发额度用尽
+        assert!(!limiter.try_acquire());
+
+        // 模拟经过 1 秒，补充 2 个令牌
+        limiter.last_refill -= Duration::from_secs(1);
+        assert!(limiter.try_acquire());
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+    }
+
+    #[test]
+    fn test_token_bucket_reconfigure_clamps_tokens() {
+        let mut limiter = RateLimiter::new(1.0, 5);
+        limiter.reconfigure(1.0, 1);
+        assert_eq!(limiter.burst(), 1);
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+    }
 }
@@ -1,10 +1,16 @@
 use crate::proxy::config::{ProxyAuthMode, ProxyConfig};
+use crate::proxy::token_manager::AccountAllowlist;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
 pub struct ProxySecurityConfig {
     pub auth_mode: ProxyAuthMode,
     pub api_key: String,
     pub allow_lan_access: bool,
+    /// 额外的 API Key -> 该 Key 可使用的账号
+    pub api_key_routes: HashMap<String, AccountAllowlist>,
+    /// 管理端点 (/admin/*) 专用 Key，与 api_key 相互独立
+    pub admin_api_key: String,
 }
 
 impl ProxySecurityConfig {
@@ -13,9 +19,28 @@ impl ProxySecurityConfig {
             auth_mode: config.auth_mode.clone(),
             api_key: config.api_key.clone(),
             allow_lan_access: config.allow_lan_access,
+            api_key_routes: config
+                .api_key_routes
+                .iter()
+                .map(|(key, ids)| (key.clone(), AccountAllowlist::new(ids.iter().cloned())))
+                .collect(),
+            admin_api_key: config.admin_api_key.clone().unwrap_or_default(),
         }
     }
 
+    /// 校验管理 Key (普通 API Key 无权访问管理端点)
+    pub fn authorize_admin(&self, api_key: &str) -> bool {
+        !self.admin_api_key.is_empty() && api_key == self.admin_api_key
+    }
+
+    /// 校验 API Key: 主 Key 不限账号，路由 Key 返回其账号白名单
+    pub fn authorize(&self, api_key: &str) -> Option<Option<AccountAllowlist>> {
+        if !self.api_key.is_empty() && api_key == self.api_key {
+            return Some(None);
+        }
+        self.api_key_routes.get(api_key).map(|allowlist| Some(allowlist.clone()))
+    }
+
     pub fn effective_auth_mode(&self) -> ProxyAuthMode {
         match self.auth_mode {
             ProxyAuthMode::Auto => {
@@ -40,6 +65,8 @@ mod tests {
             auth_mode: ProxyAuthMode::Auto,
             api_key: "sk-test".to_string(),
             allow_lan_access: false,
+            api_key_routes: HashMap::new(),
+            admin_api_key: String::new(),
         };
         assert!(matches!(s.effective_auth_mode(), ProxyAuthMode::Off));
     }
@@ -50,11 +77,43 @@ mod tests {
             auth_mode: ProxyAuthMode::Auto,
             api_key: "sk-test".to_string(),
             allow_lan_access: true,
+            api_key_routes: HashMap::new(),
+            admin_api_key: String::new(),
         };
         assert!(matches!(
             s.effective_auth_mode(),
             ProxyAuthMode::AllExceptHealth
         ));
     }
-}
 
+    #[test]
+    fn routed_key_carries_account_allowlist() {
+        let config = ProxyConfig {
+            api_key: "sk-main".to_string(),
+            api_key_routes: HashMap::from([("sk-client-a".to_string(), vec!["acc-x".to_string()])]),
+            ..Default::default()
+        };
+        let s = ProxySecurityConfig::from_proxy_config(&config);
+
+        assert!(matches!(s.authorize("sk-main"), Some(None)));
+        let allowlist = s.authorize("sk-client-a").flatten().unwrap();
+        assert!(allowlist.contains("acc-x"));
+        assert!(!allowlist.contains("acc-y"));
+        assert!(s.authorize("sk-unknown").is_none());
+    }
+
+    #[test]
+    fn admin_key_is_separate_from_api_key() {
+        let config = ProxyConfig {
+            api_key: "sk-main".to_string(),
+            admin_api_key: Some("sk-admin".to_string()),
+            ..Default::default()
+        };
+        let s = ProxySecurityConfig::from_proxy_config(&config);
+
+        assert!(s.authorize_admin("sk-admin"));
+        assert!(!s.authorize_admin("sk-main"));
+        assert!(s.authorize("sk-admin").is_none());
+        assert!(!ProxySecurityConfig::from_proxy_config(&ProxyConfig::default()).authorize_admin(""));
+    }
+}
@@ -1,23 +1,25 @@
 use crate::proxy::TokenManager;
 use axum::{
-    extract::DefaultBodyLimit,
+    extract::{DefaultBodyLimit, State},
     http::StatusCode,
     response::{IntoResponse, Json, Response},
     routing::{any, get, post},
     Router,
 };
 use std::sync::Arc;
-use tokio::sync::oneshot;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tower_http::trace::TraceLayer;
 use tracing::{debug, error};
 use tokio::sync::RwLock;
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// Axum 应用状态
 #[derive(Clone)]
 pub struct AppState {
     pub token_manager: Arc<TokenManager>,
     pub custom_mapping: Arc<tokio::sync::RwLock<std::collections::HashMap<String, String>>>,
+    pub model_aliases: Arc<tokio::sync::RwLock<std::collections::HashMap<String, String>>>, // 模型别名
     #[allow(dead_code)]
     pub request_timeout: u64, // API 请求超时(秒)
     #[allow(dead_code)]
@@ -30,15 +32,44 @@ pub struct AppState {
     pub zai_vision_mcp: Arc<crate::proxy::zai_vision_mcp::ZaiVisionMcpState>,
     pub monitor: Arc<crate::proxy::monitor::ProxyMonitor>,
     pub experimental: Arc<RwLock<crate::proxy::config::ExperimentalConfig>>,
+    pub retry_backoff: crate::proxy::upstream::backoff::ExponentialBackoff, // 重试退避参数
+    pub metrics: Arc<crate::proxy::metrics::ProxyMetrics>, // Prometheus 指标
+    pub model_stats: crate::proxy::model_stats::UsageStatsMap, // 按模型的用量统计
+    pub account_stats: crate::proxy::model_stats::UsageStatsMap, // 按账号的用量统计
+    pub include_usage_headers: bool, // 非流式响应附加用量响应头
+    pub fallback_model_on_503: Option<String>, // 503 全部失败后的降级模型
+    pub started_at: Arc<std::time::Instant>, // 服务启动时间 (/health 计算 uptime)
+    pub stream_timeouts: crate::proxy::upstream::stream_timeout::StreamTimeouts, // 流式响应空闲 / 总时长超时
+    pub max_input_tokens: Option<u32>, // Claude 请求输入 token 上限 (超出时截断最早的消息)
+    pub response_cache: crate::proxy::handlers::response_cache::ResponseCache, // 非流式响应缓存
+    pub active_streams: crate::proxy::active_streams::ActiveStreams, // 当前打开的流式响应
+    pub anthropic_sessions: crate::proxy::session_manager::AnthropicSessionCache, // anthropic-session-id 缓存
+    pub stream_recorder: crate::proxy::stream_recorder::StreamRecorder, // SSE 响应录制 / 回放 (调试)
+    pub max_body_bytes: usize, // 请求体上限 (max_request_body_mb)，处理器内部缓冲响应体时同样使用
 }
 
 /// Axum 服务器实例
+#[derive(Clone)]
 pub struct AxumServer {
-    shutdown_tx: Option<oneshot::Sender<()>>,
+    /// 停止接收新连接，并通知已有连接在当前请求结束后关闭
+    shutdown: CancellationToken,
+    /// 等待超时后强制断开剩余连接
+    force_close: CancellationToken,
+    in_flight: crate::proxy::middleware::InFlightCounter,
     custom_mapping: Arc<tokio::sync::RwLock<std::collections::HashMap<String, String>>>,
+    model_aliases: Arc<tokio::sync::RwLock<std::collections::HashMap<String, String>>>,
     proxy_state: Arc<tokio::sync::RwLock<crate::proxy::config::UpstreamProxyConfig>>,
     security_state: Arc<RwLock<crate::proxy::ProxySecurityConfig>>,
     zai_state: Arc<RwLock<crate::proxy::ZaiConfig>>,
+    model_stats: crate::proxy::model_stats::UsageStatsMap,
+    account_stats: crate::proxy::model_stats::UsageStatsMap,
+    active_streams: crate::proxy::active_streams::ActiveStreams,
+    stream_recorder: crate::proxy::stream_recorder::StreamRecorder,
+    metrics: Arc<crate::proxy::metrics::ProxyMetrics>,
+    /// 实际监听的端口 (可能为备用端口)
+    port: u16,
+    /// 上次清零用量统计的时间 (未清零过时为服务启动时间)
+    last_stats_reset_at: Arc<std::sync::Mutex<std::time::Instant>>,
 }
 
 impl AxumServer {
@@ -47,6 +78,10 @@ impl AxumServer {
             let mut m = self.custom_mapping.write().await;
             *m = config.custom_mapping.clone();
         }
+        {
+            let mut aliases = self.model_aliases.write().await;
+            *aliases = config.model_aliases.clone();
+        }
         tracing::debug!("模型映射 (Custom) 已全量热更新");
     }
 
@@ -59,7 +94,12 @@ impl AxumServer {
 
     pub async fn update_security(&self, config: &crate::proxy::config::ProxyConfig) {
         let mut sec = self.security_state.write().await;
-        *sec = crate::proxy::ProxySecurityConfig::from_proxy_config(config);
+        let mut new_sec = crate::proxy::ProxySecurityConfig::from_proxy_config(config);
+        // 未配置管理 Key 时沿用启动时生成的临时 Key
+        if new_sec.admin_api_key.is_empty() {
+            new_sec.admin_api_key = sec.admin_api_key.clone();
+        }
+        *sec = new_sec;
         tracing::info!("反代服务安全配置已热更新");
     }
 
@@ -68,22 +108,86 @@ impl AxumServer {
         *zai = config.zai.clone();
         tracing::info!("z.ai 配置已热更新");
     }
+
+    /// 当前打开的流式连接数与最早的开始时间
+    pub fn active_stream_stats(&self) -> crate::proxy::active_streams::ActiveStreamStats {
+        self.active_streams.stats()
+    }
+
+    /// 加载录制的 SSE 内容，供 GET /debug/replay 回放
+    pub fn load_stream_replay(&self, content: bytes::Bytes) {
+        self.stream_recorder.load_replay(content);
+    }
+
+    /// 实际监听的端口
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// 清零按模型 / 按账号的用量计数器与 Prometheus 指标
+    pub fn reset_stats(&self) {
+        crate::proxy::model_stats::reset(&self.model_stats);
+        crate::proxy::model_stats::reset(&self.account_stats);
+        self.metrics.reset();
+        *self.last_stats_reset_at.lock().unwrap_or_else(|e| e.into_inner()) = std::time::Instant::now();
+        tracing::info!("反代用量统计已清零");
+    }
+
+    pub fn last_stats_reset_at(&self) -> std::time::Instant {
+        *self.last_stats_reset_at.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 按模型与按账号的用量快照
+    pub fn usage_snapshot(
+        &self,
+    ) -> (
+        Vec<crate::proxy::model_stats::ModelStatsSnapshot>,
+        Vec<crate::proxy::model_stats::ModelStatsSnapshot>,
+    ) {
+        (
+            crate::proxy::model_stats::snapshot(&self.model_stats),
+            crate::proxy::model_stats::snapshot(&self.account_stats),
+        )
+    }
     /// 启动 Axum 服务器
     pub async fn start(
         host: String,
         port: u16,
+        port_fallback_range: Option<(u16, u16)>,
         token_manager: Arc<TokenManager>,
         custom_mapping: std::collections::HashMap<String, String>,
+        model_aliases: std::collections::HashMap<String, String>,
         _request_timeout: u64,
         upstream_proxy: crate::proxy::config::UpstreamProxyConfig,
         security_config: crate::proxy::ProxySecurityConfig,
         zai_config: crate::proxy::ZaiConfig,
         monitor: Arc<crate::proxy::monitor::ProxyMonitor>,
         experimental_config: crate::proxy::config::ExperimentalConfig,
-
+        retry_backoff: crate::proxy::upstream::backoff::ExponentialBackoff,
+        mistral_compat: bool,
+        mock_moderation_endpoint: bool,
+        dedup: crate::proxy::middleware::DeduplicateLayer,
+        include_usage_headers: bool,
+        fallback_model_on_503: Option<String>,
+        compression_threshold_bytes: usize,
+        size_limit: crate::proxy::middleware::RequestSizeLimitLayer,
+        propagate_request_id: bool,
+        cors: crate::proxy::middleware::CorsConfig,
+        stream_timeouts: crate::proxy::upstream::stream_timeout::StreamTimeouts,
+        max_input_tokens: Option<u32>,
+        response_cache: crate::proxy::handlers::response_cache::ResponseCache,
+        upstream_client_config: crate::proxy::upstream::client::UpstreamClientConfig,
+        stream_recorder: crate::proxy::stream_recorder::StreamRecorder,
     ) -> Result<(Self, tokio::task::JoinHandle<()>), String> {
         let custom_mapping_state = Arc::new(tokio::sync::RwLock::new(custom_mapping));
+        let model_aliases_state = Arc::new(tokio::sync::RwLock::new(model_aliases));
 	        let proxy_state = Arc::new(tokio::sync::RwLock::new(upstream_proxy.clone()));
+	        // 未配置管理 Key 时随机生成 (仅本次运行有效)
+	        let mut security_config = security_config;
+	        if security_config.admin_api_key.is_empty() {
+	            security_config.admin_api_key = format!("sk-admin-{}", uuid::Uuid::new_v4().simple());
+	            tracing::info!("未配置 admin_api_key，已生成临时管理 Key: {}", security_config.admin_api_key);
+	        }
 	        let security_state = Arc::new(RwLock::new(security_config));
 	        let zai_state = Arc::new(RwLock::new(zai_config));
 	        let provider_rr = Arc::new(AtomicUsize::new(0));
@@ -91,35 +195,101 @@ impl AxumServer {
 	            Arc::new(crate::proxy::zai_vision_mcp::ZaiVisionMcpState::new());
 	        let experimental_state = Arc::new(RwLock::new(experimental_config));
 
+	        let model_stats: crate::proxy::model_stats::UsageStatsMap = Arc::new(dashmap::DashMap::new());
+	        let account_stats: crate::proxy::model_stats::UsageStatsMap = Arc::new(dashmap::DashMap::new());
+	        let in_flight = crate::proxy::middleware::InFlightCounter::default();
+	        let active_streams = crate::proxy::active_streams::ActiveStreams::default();
+	        let metrics = Arc::new(crate::proxy::metrics::ProxyMetrics::new());
+
 	        let state = AppState {
 	            token_manager: token_manager.clone(),
 	            custom_mapping: custom_mapping_state.clone(),
+	            model_aliases: model_aliases_state.clone(),
 	            request_timeout: 300, // 5分钟超时
             thought_signature_map: Arc::new(tokio::sync::Mutex::new(
                 std::collections::HashMap::new(),
             )),
             upstream_proxy: proxy_state.clone(),
-            upstream: Arc::new(crate::proxy::upstream::client::UpstreamClient::new(Some(
-                upstream_proxy.clone(),
-            ))),
+            upstream: Arc::new(crate::proxy::upstream::client::UpstreamClient::new(
+                Some(upstream_proxy.clone()),
+                upstream_client_config,
+            )),
             zai: zai_state.clone(),
             provider_rr: provider_rr.clone(),
             zai_vision_mcp: zai_vision_mcp_state,
             monitor: monitor.clone(),
             experimental: experimental_state,
+            retry_backoff,
+            metrics: metrics.clone(),
+            model_stats: model_stats.clone(),
+            account_stats: account_stats.clone(),
+            include_usage_headers,
+            fallback_model_on_503: fallback_model_on_503.filter(|m| !m.trim().is_empty()),
+            started_at: Arc::new(std::time::Instant::now()),
+            stream_timeouts,
+            max_input_tokens: max_input_tokens.filter(|limit| *limit > 0),
+            response_cache,
+            active_streams: active_streams.clone(),
+            anthropic_sessions: Default::default(),
+            stream_recorder: stream_recorder.clone(),
+            max_body_bytes: size_limit.max_bytes(),
         };
 
 
+        // 绑定地址 (主端口被占用时依次尝试备用端口)
+        let (listener, port) = bind_listener(&host, port, port_fallback_range).await?;
+        tracing::info!("反代服务器启动在 http://{}:{}", host, port);
+
+        let shutdown = CancellationToken::new();
+        let force_close = CancellationToken::new();
+
+        let server_instance = Self {
+            port,
+            shutdown: shutdown.clone(),
+            force_close: force_close.clone(),
+            in_flight: in_flight.clone(),
+            custom_mapping: custom_mapping_state.clone(),
+            model_aliases: model_aliases_state,
+            proxy_state,
+            security_state: security_state.clone(),
+            zai_state,
+            model_stats,
+            account_stats,
+            active_streams,
+            stream_recorder,
+            metrics,
+            last_stats_reset_at: Arc::new(std::sync::Mutex::new(std::time::Instant::now())),
+        };
+
         // 构建路由 - 使用新架构的 handlers！
         use crate::proxy::handlers;
+        // Mistral 兼容模式下由 Mistral 层接管 Chat Completions
+        let chat_completions = if mistral_compat {
+            tracing::info!("Mistral 兼容层已启用");
+            post(handlers::mistral::handle_chat_completions)
+        } else {
+            post(handlers::openai::handle_chat_completions)
+        };
+
+        // Ollama Protocol (/api/*)
+        let ollama_routes = Router::new()
+            .route("/generate", post(handlers::ollama::handle_ollama_generate))
+            .route("/chat", post(handlers::ollama::handle_ollama_chat));
+
+        // 内容审核桩端点 (关闭时不挂载，客户端收到 404)
+        let mut moderation_routes = Router::new();
+        if mock_moderation_endpoint {
+            moderation_routes = moderation_routes
+                .route("/v1/moderations", post(handlers::openai::handle_moderations));
+        }
+
         // 构建路由
         let app = Router::new()
             // OpenAI Protocol
             .route("/v1/models", get(handlers::openai::handle_list_models))
-            .route(
-                "/v1/chat/completions",
-                post(handlers::openai::handle_chat_completions),
-            )
+            // 单模型信息 (含能力元数据)；携带 anthropic-version 时返回 Claude 格式
+            .route("/v1/models/:model_id", get(handlers::openai::handle_get_model))
+            .route("/v1/chat/completions", chat_completions)
             .route(
                 "/v1/completions",
                 post(handlers::openai::handle_completions),
@@ -137,6 +307,20 @@ impl AxumServer {
                 "/v1/audio/transcriptions",
                 post(handlers::audio::handle_audio_transcription),
             ) // 音频转录 API (PR #311)
+            // 未实现的音频端点返回 501
+            .route("/v1/audio/translations", post(handlers::openai::handle_audio_transcriptions_stub))
+            .route("/v1/audio/speech", post(handlers::openai::handle_audio_transcriptions_stub))
+            .route("/v1/embeddings", post(handlers::openai::handle_embeddings)) // 向量嵌入 (Gemini batchEmbedContents)
+            .route("/v1/files", post(handlers::openai::handle_file_upload)) // 文件上传 (Google File API)
+            .route(
+                "/v1/files/:file_id",
+                get(handlers::openai::handle_file_retrieve).delete(handlers::openai::handle_file_delete),
+            )
+            .route("/v1/files/:file_id/content", get(handlers::openai::handle_file_content))
+            // Batch API (同步处理，仅适合小批量)
+            .route("/v1/batches", post(handlers::openai::handle_batch_create))
+            .route("/v1/batch", post(handlers::openai::handle_batch_create))
+            .route("/v1/batches/:batch_id", get(handlers::openai::handle_batch_retrieve))
             // Claude Protocol
             .route("/v1/messages", post(handlers::claude::handle_messages))
             .route(
@@ -176,34 +360,53 @@ impl AxumServer {
             .route("/v1/api/event_logging/batch", post(silent_ok_handler))
             .route("/v1/api/event_logging", post(silent_ok_handler))
             .route("/healthz", get(health_check_handler))
-            .layer(DefaultBodyLimit::max(100 * 1024 * 1024))
+            .route("/health", get(health_status_handler))
+            .route("/metrics", get(metrics_handler))
+            .route("/debug/replay", get(replay_handler))
+            // 管理端点 (使用独立的 admin_api_key 鉴权)
+            .route(
+                "/admin/reload",
+                post(handlers::admin::handle_reload).with_state(handlers::admin::AdminState {
+                    server: server_instance.clone(),
+                    token_manager: token_manager.clone(),
+                }),
+            )
+            .nest("/api", ollama_routes)
+            .merge(moderation_routes)
+            .layer(DefaultBodyLimit::max(size_limit.max_bytes()))
+            // 相同的进行中 generateContent 请求复用同一次上游调用 (位于鉴权之后)
+            .layer(axum::middleware::from_fn_with_state(dedup, crate::proxy::middleware::dedup_middleware))
             .layer(axum::middleware::from_fn_with_state(state.clone(), crate::proxy::middleware::monitor::monitor_middleware))
-            .layer(TraceLayer::new_for_http())
+            .layer(axum::middleware::from_fn(crate::proxy::middleware::audit_middleware))
+            .layer(axum::middleware::from_fn_with_state(state.clone(), crate::proxy::middleware::metrics_middleware))
+            .layer(axum::middleware::from_fn_with_state(propagate_request_id, crate::proxy::middleware::request_id_middleware))
+            .layer(TraceLayer::new_for_http().make_span_with(|request: &axum::extract::Request| {
+                tracing::info_span!(
+                    "request",
+                    method = %request.method(),
+                    uri = %request.uri().path(),
+                    request_id = tracing::field::Empty,
+                )
+            }))
+            // 位于解压层内侧，按解压后的大小限制
+            .layer(axum::middleware::from_fn_with_state(size_limit, crate::proxy::middleware::request_size_limit_middleware))
+            // 请求体解压位于去重 / 监控之外，使其读取到的是原始 JSON
+            .layer(crate::proxy::middleware::compression_layer(compression_threshold_bytes))
+            .layer(crate::proxy::middleware::request_decompression_layer())
             .layer(axum::middleware::from_fn_with_state(
                 security_state.clone(),
                 crate::proxy::middleware::auth_middleware,
             ))
-            .layer(crate::proxy::middleware::cors_layer())
+            // 安全响应头 (包裹鉴权层，401 响应同样生效)
+            .layer(axum::middleware::from_fn(crate::proxy::middleware::security_headers_middleware))
+            .layer(crate::proxy::middleware::cors_layer(&cors))
+            .layer(axum::middleware::from_fn_with_state(cors, crate::proxy::middleware::cors_guard_middleware))
+            .layer(axum::middleware::from_fn_with_state(
+                in_flight.clone(),
+                crate::proxy::middleware::in_flight_middleware,
+            ))
             .with_state(state);
 
-        // 绑定地址
-        let addr = format!("{}:{}", host, port);
-        let listener = tokio::net::TcpListener::bind(&addr)
-            .await
-            .map_err(|e| format!("地址 {} 绑定失败: {}", addr, e))?;
-
-        tracing::info!("反代服务器启动在 http://{}", addr);
-
-        // 创建关闭通道
-        let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
-
-        let server_instance = Self {
-            shutdown_tx: Some(shutdown_tx),
-            custom_mapping: custom_mapping_state.clone(),
-            proxy_state,
-            security_state,
-            zai_state,
-        };
 
         // 在新任务中启动服务器
         let handle = tokio::spawn(async move {
@@ -215,16 +418,33 @@ impl AxumServer {
                 tokio::select! {
                     res = listener.accept() => {
                         match res {
-                            Ok((stream, _)) => {
+                            Ok((stream, remote_addr)) => {
                                 let io = TokioIo::new(stream);
-                                let service = TowerToHyperService::new(app.clone());
+                                // 注入客户端地址供审计日志使用
+                                let service = TowerToHyperService::new(
+                                    app.clone().layer(axum::Extension(axum::extract::ConnectInfo(remote_addr))),
+                                );
 
+                                let shutdown = shutdown.clone();
+                                let force_close = force_close.clone();
                                 tokio::task::spawn(async move {
-                                    if let Err(err) = http1::Builder::new()
+                                    let conn = http1::Builder::new()
                                         .serve_connection(io, service)
-                                        .with_upgrades() // 支持 WebSocket (如果以后需要)
-                                        .await
-                                    {
+                                        .with_upgrades(); // 支持 WebSocket (如果以后需要)
+                                    tokio::pin!(conn);
+
+                                    let result = tokio::select! {
+                                        res = conn.as_mut() => res,
+                                        _ = shutdown.cancelled() => {
+                                            // 处理完当前请求后关闭连接，超时则直接断开
+                                            conn.as_mut().graceful_shutdown();
+                                            tokio::select! {
+                                                res = conn.as_mut() => res,
+                                                _ = force_close.cancelled() => Ok(()),
+                                            }
+                                        }
+                                    };
+                                    if let Err(err) = result {
                                         debug!("连接处理结束或出错: {:?}", err);
                                     }
                                 });
@@ -234,7 +454,7 @@ impl AxumServer {
                             }
                         }
                     }
-                    _ = &mut shutdown_rx => {
+                    _ = shutdown.cancelled() => {
                         tracing::info!("反代服务器停止监听");
                         break;
                     }
@@ -245,12 +465,62 @@ impl AxumServer {
         Ok((server_instance, handle))
     }
 
-    /// 停止服务器
-    pub fn stop(mut self) {
-        if let Some(tx) = self.shutdown_tx.take() {
-            let _ = tx.send(());
+    /// 优雅停止服务器: 停止接收新连接，最多等待 `timeout` 让进行中的请求完成，随后强制关闭
+    /// 返回强制关闭时仍未完成的请求数
+    pub async fn stop(self, timeout: Duration) -> usize {
+        self.shutdown.cancel();
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        while self.in_flight.load(Ordering::SeqCst) > 0 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        let remaining = self.in_flight.load(Ordering::SeqCst);
+        if remaining > 0 {
+            tracing::warn!("反代服务停止超时，强制关闭 {} 个进行中的请求", remaining);
+        }
+        self.force_close.cancel();
+        remaining
+    }
+}
+
+/// 监听端口的尝试顺序: 主端口，随后为备用范围内的其余端口
+fn candidate_ports(port: u16, fallback_range: Option<(u16, u16)>) -> Vec<u16> {
+    let mut ports = vec![port];
+    if let Some((start, end)) = fallback_range {
+        ports.extend((start..=end).filter(|p| *p != port));
+    }
+    ports
+}
+
+/// 依次尝试绑定候选端口，返回监听器与实际端口
+async fn bind_listener(
+    host: &str,
+    port: u16,
+    fallback_range: Option<(u16, u16)>,
+) -> Result<(tokio::net::TcpListener, u16), String> {
+    let mut first_error = None;
+    for candidate in candidate_ports(port, fallback_range) {
+        match tokio::net::TcpListener::bind((host, candidate)).await {
+            Ok(listener) => {
+                if candidate != port {
+                    tracing::warn!("端口 {} 不可用，已改用备用端口 {}", port, candidate);
+                }
+                return Ok((listener, candidate));
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
         }
     }
+    let e = first_error.map(|e| e.to_string()).unwrap_or_default();
+    Err(match fallback_range {
+        Some((start, end)) => format!(
+            "地址 {}:{} 绑定失败: {} (备用端口 {}-{} 均不可用)",
+            host, port, e, start, end
+        ),
+        None => format!("地址 {}:{} 绑定失败: {}", host, port, e),
+    })
 }
 
 // ===== API 处理器 (旧代码已移除，由 src/proxy/handlers/* 接管) =====
@@ -263,7 +533,111 @@ async fn health_check_handler() -> Response {
     .into_response()
 }
 
+/// 服务状态 JSON: 所有账号均不可用 (熔断或账号池为空) 时返回 503
+fn health_report(total: usize, open_circuit: usize, uptime_secs: u64) -> (StatusCode, serde_json::Value) {
+    let available = total.saturating_sub(open_circuit);
+    if available == 0 {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            serde_json::json!({
+                "status": "degraded",
+                "reason": "no available accounts",
+            }),
+        );
+    }
+    (
+        StatusCode::OK,
+        serde_json::json!({
+            "status": "ok",
+            "uptime_secs": uptime_secs,
+            "accounts": {
+                "total": total,
+                "available": available,
+                "open_circuit": open_circuit,
+            },
+            "version": env!("CARGO_PKG_VERSION"),
+        }),
+    )
+}
+
+/// 服务状态端点 (无需鉴权，供负载均衡 / 监控脚本使用)
+async fn health_status_handler(State(state): State<AppState>) -> Response {
+    let circuits = state.token_manager.get_circuit_states();
+    let open_circuit = circuits.iter().filter(|c| c.state == "open").count();
+    let (status, body) = health_report(circuits.len(), open_circuit, state.started_at.elapsed().as_secs());
+    (status, Json(body)).into_response()
+}
+
 /// 静默成功处理器 (用于拦截遥测日志等)
+/// Prometheus 指标端点 (无需鉴权)
+async fn metrics_handler(State(state): State<AppState>) -> Response {
+    let body = state.metrics.render(&state.token_manager);
+    (
+        StatusCode::OK,
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        body,
+    )
+        .into_response()
+}
+
+/// 以 SSE 形式回放 replay_stream 加载的录制内容
+async fn replay_handler(State(state): State<AppState>) -> Response {
+    let Some(events) = state.stream_recorder.replay_events() else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": {
+                    "message": "No recording loaded; call replay_stream first",
+                    "type": "not_found"
+                }
+            })),
+        )
+            .into_response();
+    };
+    let stream = futures::stream::iter(events.into_iter().map(Ok::<_, std::convert::Infallible>));
+    Response::builder()
+        .header(axum::http::header::CONTENT_TYPE, "text/event-stream")
+        .header(axum::http::header::CACHE_CONTROL, "no-cache")
+        .body(axum::body::Body::from_stream(stream))
+        .unwrap()
+}
+
 async fn silent_ok_handler() -> Response {
     StatusCode::OK.into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_report() {
+        let (status, body) = health_report(3, 1, 42);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["uptime_secs"], 42);
+        assert_eq!(body["accounts"]["available"], 2);
+        assert_eq!(body["accounts"]["open_circuit"], 1);
+
+        let (status, body) = health_report(2, 2, 42);
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "degraded");
+        assert_eq!(body["reason"], "no available accounts");
+    }
+
+    #[tokio::test]
+    async fn test_bind_listener_falls_back_to_range() {
+        assert_eq!(candidate_ports(8045, None), vec![8045]);
+        assert_eq!(candidate_ports(8045, Some((8044, 8046))), vec![8045, 8044, 8046]);
+
+        let occupied = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = occupied.local_addr().unwrap().port();
+        assert!(bind_listener("127.0.0.1", port, None).await.is_err());
+
+        let (listener, bound) = bind_listener("127.0.0.1", port, Some((port, port.saturating_add(10))))
+            .await
+            .unwrap();
+        assert_ne!(bound, port);
+        assert_eq!(listener.local_addr().unwrap().port(), bound);
+    }
+}
@@ -2,11 +2,103 @@ use sha2::{Sha256, Digest};
 use crate::proxy::mappers::claude::models::{ClaudeRequest, MessageContent};
 use crate::proxy::mappers::openai::models::{OpenAIRequest, OpenAIContent};
 use serde_json::Value;
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// 会话管理器工具
 pub struct SessionManager;
 
+/// Anthropic 会话 ID 请求 / 响应头
+pub const ANTHROPIC_SESSION_HEADER: &str = "anthropic-session-id";
+
+/// 客户端显式指定会话 ID 的请求头 (按优先级)
+const SESSION_ID_HEADERS: [&str; 2] = [ANTHROPIC_SESSION_HEADER, "x-session-id"];
+
+/// anthropic-session-id 闲置超过该时长后从缓存中移除
+const ANTHROPIC_SESSION_IDLE_TTL: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone)]
+struct AnthropicSession {
+    /// 最近一次服务该会话的账号 (邮箱)
+    account: Option<String>,
+    last_seen: Instant,
+}
+
+/// anthropic-session-id 缓存
+///
+/// 未携带会话头的请求按首条用户消息指纹分配会话 ID (由指纹派生的 UUID v4)，
+/// 并记录每个会话最近使用的账号，供粘性调度在绑定失效后恢复
+#[derive(Clone, Default)]
+pub struct AnthropicSessionCache {
+    /// 内容指纹 -> 会话 ID
+    by_fingerprint: Arc<DashMap<String, String>>,
+    /// 会话 ID -> 会话状态
+    sessions: Arc<DashMap<String, AnthropicSession>>,
+}
+
+impl AnthropicSessionCache {
+    /// 解析本次请求的会话 ID: 优先使用客户端携带的会话头，否则按内容指纹查找或生成
+    pub fn resolve(&self, header_id: Option<&str>, fingerprint: &str) -> String {
+        self.purge_expired(ANTHROPIC_SESSION_IDLE_TTL);
+
+        let session_id = match header_id.map(str::trim).filter(|id| !id.is_empty()) {
+            Some(id) => id.to_string(),
+            None => self
+                .by_fingerprint
+                .entry(fingerprint.to_string())
+                .or_insert_with(|| session_uuid_from_fingerprint(fingerprint))
+                .clone(),
+        };
+
+        let now = Instant::now();
+        self.sessions
+            .entry(session_id.clone())
+            .and_modify(|s| s.last_seen = now)
+            .or_insert(AnthropicSession { account: None, last_seen: now });
+        session_id
+    }
+
+    /// 会话最近使用的账号
+    pub fn last_account(&self, session_id: &str) -> Option<String> {
+        self.sessions.get(session_id).and_then(|s| s.account.clone())
+    }
+
+    pub fn record_account(&self, session_id: &str, account: &str) {
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
+            session.account = Some(account.to_string());
+            session.last_seen = Instant::now();
+        }
+    }
+
+    fn purge_expired(&self, ttl: Duration) {
+        self.sessions.retain(|_, s| s.last_seen.elapsed() <= ttl);
+        let sessions = &self.sessions;
+        self.by_fingerprint.retain(|_, id| sessions.contains_key(id));
+    }
+}
+
+/// 由内容指纹派生 UUID v4 (同一对话在重启后仍得到相同的会话 ID)
+fn session_uuid_from_fingerprint(fingerprint: &str) -> String {
+    let digest = Sha256::digest(fingerprint.as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    uuid::Builder::from_random_bytes(bytes).into_uuid().to_string()
+}
+
 impl SessionManager {
+    /// 从请求头读取客户端显式指定的会话 ID，优先于内容指纹
+    pub fn extract_header_session_id(headers: &axum::http::HeaderMap) -> Option<String> {
+        SESSION_ID_HEADERS.iter().find_map(|name| {
+            headers
+                .get(*name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        })
+    }
+
     /// 根据 Claude 请求生成稳定的会话指纹 (Session Fingerprint)
     pub fn extract_session_id(request: &ClaudeRequest) -> String {
         // 1. 优先使用 metadata 中的 user_id
@@ -148,3 +240,36 @@ impl SessionManager {
         sid
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anthropic_session_resolve() {
+        let cache = AnthropicSessionCache::default();
+        let first = cache.resolve(None, "sid-abc");
+        assert_eq!(uuid::Uuid::parse_str(&first).unwrap().get_version_num(), 4);
+        assert_eq!(cache.resolve(None, "sid-abc"), first);
+        assert_ne!(cache.resolve(None, "sid-other"), first);
+
+        // 客户端携带的会话头优先，并可查回最近使用的账号
+        cache.record_account(&first, "a@example.com");
+        assert_eq!(cache.resolve(Some(&first), "sid-ignored"), first);
+        assert_eq!(cache.last_account(&first).as_deref(), Some("a@example.com"));
+        assert_eq!(cache.resolve(Some("client-session"), "sid-abc"), "client-session");
+        assert_eq!(cache.last_account("client-session"), None);
+    }
+
+    #[test]
+    fn test_anthropic_session_expires() {
+        let cache = AnthropicSessionCache::default();
+        let id = cache.resolve(None, "sid-abc");
+        cache.record_account(&id, "a@example.com");
+
+        std::thread::sleep(Duration::from_millis(5));
+        cache.purge_expired(Duration::ZERO);
+        assert_eq!(cache.last_account(&id), None);
+        assert!(cache.by_fingerprint.is_empty());
+    }
+}
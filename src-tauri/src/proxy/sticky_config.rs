@@ -24,6 +24,13 @@ pub struct StickySessionConfig {
     pub mode: SchedulingMode,
     /// 缓存优先模式下的最大等待时间 (秒)
     pub max_wait_seconds: u64,
+    /// 会话与账号绑定的有效期 (秒)，超过后重新分配账号
+    #[serde(default = "default_session_ttl_secs")]
+    pub session_ttl_secs: u64,
+}
+
+fn default_session_ttl_secs() -> u64 {
+    3600
 }
 
 impl Default for StickySessionConfig {
@@ -31,6 +38,7 @@ impl Default for StickySessionConfig {
         Self {
             mode: SchedulingMode::Balance,
             max_wait_seconds: 60,
+            session_ttl_secs: default_session_ttl_secs(),
         }
     }
 }
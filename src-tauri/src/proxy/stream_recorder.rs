@@ -0,0 +1,215 @@
+// 流式响应录制与回放 (调试用)
+// 配置 record_streams 后，发给客户端的每个 SSE 响应体原样写入 {dir}/{timestamp}_{protocol}_{model}.sse；
+// replay_stream 命令加载录制文件后，可通过 GET /debug/replay 以 SSE 形式重新输出，用于验证客户端解析
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// 录制目录中最多保留的录制文件数量
+const MAX_RECORDINGS: usize = 100;
+
+const RECORDING_EXTENSION: &str = "sse";
+
+#[derive(Clone, Default)]
+pub struct StreamRecorder {
+    dir: Option<PathBuf>,
+    /// replay_stream 加载的录制内容
+    replay: Arc<Mutex<Option<Bytes>>>,
+}
+
+/// 单个流的录制缓冲，流结束或客户端断开 (被丢弃) 时写入磁盘
+struct Recording {
+    dir: PathBuf,
+    file_name: String,
+    buf: Vec<u8>,
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        let dir = self.dir.clone();
+        let path = dir.join(&self.file_name);
+        let content = std::mem::take(&mut self.buf);
+        let write = move || {
+            let result = std::fs::create_dir_all(&dir)
+                .and_then(|_| std::fs::write(&path, content))
+                .map_err(|e| e.to_string())
+                .and_then(|_| cleanup_recordings(&dir, MAX_RECORDINGS));
+            if let Err(e) = result {
+                tracing::warn!("[Stream-Recorder] 写入录制文件失败 ({:?}): {}", path, e);
+            }
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(write);
+            }
+            Err(_) => write(),
+        }
+    }
+}
+
+impl StreamRecorder {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            dir: dir.filter(|d| !d.as_os_str().is_empty()),
+            replay: Arc::default(),
+        }
+    }
+
+    pub fn from_proxy_config(config: &crate::proxy::config::ProxyConfig) -> Self {
+        Self::new(config.record_streams.clone())
+    }
+
+    /// 未配置录制目录时原样返回流，否则在转发的同时缓存响应内容
+    pub fn record<S, E>(&self, protocol: &str, model: &str, stream: S) -> impl Stream<Item = Result<Bytes, E>> + Send + 'static
+    where
+        S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+        E: Send + 'static,
+    {
+        let Some(dir) = self.dir.clone() else {
+            return stream.left_stream();
+        };
+        let mut recording = Recording {
+            dir,
+            file_name: recording_file_name(protocol, model),
+            buf: Vec::new(),
+        };
+        stream
+            .map(move |chunk| {
+                if let Ok(bytes) = &chunk {
+                    recording.buf.extend_from_slice(bytes);
+                }
+                chunk
+            })
+            .right_stream()
+    }
+
+    /// 加载录制文件供 GET /debug/replay 输出
+    pub fn load_replay(&self, content: Bytes) {
+        *self.replay.lock().unwrap_or_else(|e| e.into_inner()) = Some(content);
+    }
+
+    /// 当前加载的录制内容，按 SSE 事件 (空行分隔) 切分
+    pub fn replay_events(&self) -> Option<Vec<Bytes>> {
+        let content = self.replay.lock().unwrap_or_else(|e| e.into_inner()).clone()?;
+        Some(split_sse_events(&content))
+    }
+}
+
+/// 录制文件名: 时间戳在前，按文件名排序即为录制顺序
+fn recording_file_name(protocol: &str, model: &str) -> String {
+    let sanitize = |s: &str| -> String {
+        s.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+            .collect()
+    };
+    format!(
+        "{}_{}_{}.{}",
+        chrono::Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+        sanitize(protocol),
+        sanitize(model),
+        RECORDING_EXTENSION
+    )
+}
+
+/// 按事件边界切分，保留每个事件末尾的空行
+fn split_sse_events(content: &[u8]) -> Vec<Bytes> {
+    let mut events = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i + 1 < content.len() {
+        if content[i] == b'\n' && content[i + 1] == b'\n' {
+            events.push(Bytes::copy_from_slice(&content[start..i + 2]));
+            start = i + 2;
+            i = start;
+        } else {
+            i += 1;
+        }
+    }
+    if start < content.len() {
+        events.push(Bytes::copy_from_slice(&content[start..]));
+    }
+    events
+}
+
+/// 只保留最新的 `keep` 个录制文件
+fn cleanup_recordings(dir: &Path, keep: usize) -> Result<(), String> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some(RECORDING_EXTENSION))
+        .collect();
+    if files.len() <= keep {
+        return Ok(());
+    }
+    files.sort();
+    for path in &files[..files.len() - keep] {
+        let _ = std::fs::remove_file(path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_and_replay_stream() {
+        let dir = std::env::temp_dir().join(format!("stream_recorder_test_{}", uuid::Uuid::new_v4()));
+        let recorder = StreamRecorder::new(Some(dir.clone()));
+        let chunks = vec![
+            Ok::<_, String>(Bytes::from("data: {\"a\":1}\n\n")),
+            Ok(Bytes::from("data: [DONE]\n\n")),
+        ];
+        let forwarded: Vec<_> = recorder
+            .record("openai", "gpt-4o/mini", futures::stream::iter(chunks))
+            .collect()
+            .await;
+        assert_eq!(forwarded.len(), 2);
+
+        // 录制在后台线程写入
+        let mut files = Vec::new();
+        for _ in 0..50 {
+            files = std::fs::read_dir(&dir).map(|d| d.filter_map(|e| e.ok()).map(|e| e.path()).collect()).unwrap_or_default();
+            if !files.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(files.len(), 1);
+        let name = files[0].file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.ends_with("_openai_gpt-4o_mini.sse"), "{}", name);
+        let content = std::fs::read(&files[0]).unwrap();
+        assert_eq!(content, b"data: {\"a\":1}\n\ndata: [DONE]\n\n");
+
+        assert!(recorder.replay_events().is_none());
+        recorder.load_replay(Bytes::from(content));
+        assert_eq!(
+            recorder.replay_events().unwrap(),
+            vec![Bytes::from("data: {\"a\":1}\n\n"), Bytes::from("data: [DONE]\n\n")]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_cleanup_keeps_latest_recordings() {
+        let dir = std::env::temp_dir().join(format!("stream_cleanup_test_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for i in 0..5 {
+            std::fs::write(dir.join(format!("2026010{}_claude_m.sse", i)), "").unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+
+        cleanup_recordings(&dir, 2).unwrap();
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["20260103_claude_m.sse", "20260104_claude_m.sse", "notes.txt"]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
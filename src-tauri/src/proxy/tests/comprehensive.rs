@@ -36,11 +36,13 @@ mod tests {
             }),
             metadata: None,
             output_config: None,
+            tool_choice: None,
+            stop_sequences: None,
         };
 
         // 2. 执行转换
         // 如果修复生效，这里应该成功返回，且 thinkingConfig 被保留
-        let result = transform_claude_request_in(&req, "test-project");
+        let result = transform_claude_request_in(&req, "test-project", None);
         assert!(result.is_ok(), "First thinking request should be allowed");
 
         let body = result.unwrap();
@@ -1,12 +1,60 @@
 // 移除冗余的顶层导入，因为这些在代码中已由 full path 或局部导入处理
 use dashmap::DashMap;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
 
-use crate::proxy::rate_limit::RateLimitTracker;
+use crate::proxy::common::model_mapping::RequestPriority;
+use crate::proxy::latency_stats::{AccountLatencyInfo, LatencyStats};
+use crate::proxy::rate_limit::{RateLimitTracker, RateLimiter};
 use crate::proxy::sticky_config::StickySessionConfig;
+use crate::proxy::token_state::{self, PersistedState, PersistedToken};
+
+/// 账号熔断状态
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CircuitState {
+    /// 正常，可参与调度
+    Closed,
+    /// 熔断中，冷却结束前不参与调度
+    Open { until: std::time::Instant },
+    /// 冷却结束，允许试探请求；成功则恢复 Closed，失败则重新 Open
+    HalfOpen,
+}
+
+/// 账号熔断状态快照 (供前端展示)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AccountCircuitInfo {
+    pub account_id: String,
+    pub email: String,
+    /// "closed" | "open" | "half_open"
+    pub state: String,
+    pub consecutive_failures: u8,
+    /// 熔断剩余秒数 (仅 open 状态)
+    pub open_remaining_secs: Option<u64>,
+}
+
+/// 按 API Key 限定的可用账号 ID 集合 (由鉴权中间件写入请求扩展)
+#[derive(Debug, Clone, Default)]
+pub struct AccountAllowlist(Arc<HashSet<String>>);
+
+impl AccountAllowlist {
+    pub fn new(account_ids: impl IntoIterator<Item = String>) -> Self {
+        Self(Arc::new(account_ids.into_iter().collect()))
+    }
+
+    pub fn contains(&self, account_id: &str) -> bool {
+        self.0.contains(account_id)
+    }
+
+    /// 排序后的账号 ID 列表 (用于比较两个范围是否一致)
+    pub fn account_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.0.iter().cloned().collect();
+        ids.sort();
+        ids
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ProxyToken {
@@ -20,17 +68,85 @@ pub struct ProxyToken {
     pub project_id: Option<String>,
     pub subscription_tier: Option<String>, // "FREE" | "PRO" | "ULTRA"
     pub remaining_quota: Option<i32>, // [FIX #563] Remaining quota for priority sorting
+    pub circuit_state: CircuitState, // 熔断状态 (401/硬 403 后暂时移出调度)
+    pub consecutive_failures: u8,    // 连续硬失败次数
+    pub health: crate::models::AccountHealth, // 账号健康度 (同 tier/配额时作为调度 tie-breaker)
+    pub upstream_proxy: Option<String>, // 账号级上游代理 (覆盖全局 upstream_proxy)
+    pub system_prompt_prefix: Option<String>, // 账号级系统提示词前缀
+    pub last_proactive_refresh: Option<std::time::Instant>, // 最近一次刷新时间 (后台任务或请求路径)
+    pub priority: u8, // 加权轮询权重 (1-10，加载时归一化)
+}
+
+impl ProxyToken {
+    /// 转换为 TokenData (用于过期判断与刷新)
+    pub fn token_data(&self) -> crate::models::TokenData {
+        crate::models::TokenData {
+            access_token: self.access_token.clone(),
+            refresh_token: self.refresh_token.clone(),
+            expires_in: self.expires_in,
+            expiry_timestamp: self.timestamp,
+            token_type: "Bearer".to_string(),
+            email: Some(self.email.clone()),
+            project_id: self.project_id.clone(),
+            session_id: None,
+        }
+    }
+}
+
+/// 单账号令牌桶限速配置
+#[derive(Debug, Clone, Default)]
+pub struct AccountRateLimitConfig {
+    /// 全局补充速率 (请求/秒)，None 表示不限速
+    pub rps: Option<f64>,
+    /// 突发上限，None 时取速率向上取整
+    pub burst: Option<u32>,
+    /// 按账号覆盖速率 (key: 账号 ID 或邮箱)
+    pub per_account_rps: HashMap<String, f64>,
 }
 
+impl AccountRateLimitConfig {
+    pub fn from_proxy_config(config: &crate::proxy::config::ProxyConfig) -> Self {
+        Self {
+            rps: config.rate_limit_rps,
+            burst: config.rate_limit_burst,
+            per_account_rps: config.account_rate_limit_rps.clone(),
+        }
+    }
+
+    /// 解析账号的 (速率, 突发上限)，未配置或速率非正时返回 None
+    fn resolve(&self, account_id: &str, email: &str) -> Option<(f64, u32)> {
+        let rps = self
+            .per_account_rps
+            .get(account_id)
+            .or_else(|| self.per_account_rps.get(email))
+            .copied()
+            .or(self.rps)
+            .filter(|r| *r > 0.0)?;
+        let burst = self.burst.unwrap_or_else(|| rps.ceil() as u32).max(1);
+        Some((rps, burst))
+    }
+}
 
+/// 所有字段均为共享状态，clone 后与原实例指向同一账号池 (供后台任务使用)
+#[derive(Clone)]
 pub struct TokenManager {
     tokens: Arc<DashMap<String, ProxyToken>>,  // account_id -> ProxyToken
     current_index: Arc<AtomicUsize>,
+    wrr_current_weights: Arc<std::sync::Mutex<HashMap<String, i64>>>, // 平滑加权轮询的当前权重 (AccountID -> weight)
     last_used_account: Arc<tokio::sync::Mutex<Option<(String, std::time::Instant)>>>,
     data_dir: PathBuf,
     rate_limit_tracker: Arc<RateLimitTracker>,  // 新增: 限流跟踪器
     sticky_config: Arc<tokio::sync::RwLock<StickySessionConfig>>, // 新增：调度配置
-    session_accounts: Arc<DashMap<String, String>>, // 新增：会话与账号映射 (SessionID -> AccountID)
+    session_accounts: Arc<DashMap<String, (String, std::time::Instant)>>, // 会话与账号映射 (SessionID -> (AccountID, 最近使用时间))
+    rate_limiters: Arc<DashMap<String, RateLimiter>>, // 单账号令牌桶 (AccountID -> RateLimiter)
+    rate_limit_config: Arc<tokio::sync::RwLock<AccountRateLimitConfig>>,
+    circuit_cooldown_secs: Arc<AtomicU64>, // 熔断冷却时间 (秒)
+    refresh_success: Arc<AtomicU64>, // Token 刷新成功次数 (指标)
+    refresh_failure: Arc<AtomicU64>, // Token 刷新失败次数 (指标)
+    refresh_task: Arc<std::sync::Mutex<Option<CancellationToken>>>, // 后台主动刷新任务
+    latency_stats: Arc<DashMap<String, LatencyStats>>, // 按账号的请求延迟 (AccountID -> 估计值)
+    dirty_health: Arc<std::sync::Mutex<HashSet<String>>>, // 健康度待写回磁盘的账号 (AccountID)
+    health_flush_pending: Arc<AtomicBool>, // 是否已有延迟写回任务在等待
 }
 
 impl TokenManager {
@@ -39,11 +155,21 @@ impl TokenManager {
         Self {
             tokens: Arc::new(DashMap::new()),
             current_index: Arc::new(AtomicUsize::new(0)),
+            wrr_current_weights: Arc::new(std::sync::Mutex::new(HashMap::new())),
             last_used_account: Arc::new(tokio::sync::Mutex::new(None)),
             data_dir,
             rate_limit_tracker: Arc::new(RateLimitTracker::new()),
             sticky_config: Arc::new(tokio::sync::RwLock::new(StickySessionConfig::default())),
             session_accounts: Arc::new(DashMap::new()),
+            rate_limiters: Arc::new(DashMap::new()),
+            rate_limit_config: Arc::new(tokio::sync::RwLock::new(AccountRateLimitConfig::default())),
+            circuit_cooldown_secs: Arc::new(AtomicU64::new(DEFAULT_CIRCUIT_COOLDOWN_SECS)),
+            refresh_success: Arc::new(AtomicU64::new(0)),
+            refresh_failure: Arc::new(AtomicU64::new(0)),
+            refresh_task: Arc::new(std::sync::Mutex::new(None)),
+            latency_stats: Arc::new(DashMap::new()),
+            dirty_health: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            health_flush_pending: Arc::new(AtomicBool::new(false)),
         }
     }
     
@@ -55,9 +181,21 @@ impl TokenManager {
             return Err(format!("账号目录不存在: {:?}", accounts_dir));
         }
 
+        // 熔断状态在重新加载后保留，避免坏账号因 reload 立即重回调度池
+        let mut previous_circuits: HashMap<String, (CircuitState, u8)> = self
+            .tokens
+            .iter()
+            .map(|e| (e.key().clone(), (e.circuit_state, e.consecutive_failures)))
+            .collect();
+        // 首次加载 (服务重启) 时从磁盘恢复上次停止前的熔断状态
+        if previous_circuits.is_empty() {
+            previous_circuits = self.load_persisted_circuits();
+        }
+
         // Reload should reflect current on-disk state (accounts can be added/removed/disabled).
         self.tokens.clear();
         self.current_index.store(0, Ordering::SeqCst);
+        self.wrr_current_weights.lock().unwrap_or_else(|e| e.into_inner()).clear();
         {
             let mut last_used = self.last_used_account.lock().await;
             *last_used = None;
@@ -78,8 +216,12 @@ impl TokenManager {
             
             // 尝试加载账号
             match self.load_single_account(&path).await {
-                Ok(Some(token)) => {
+                Ok(Some(mut token)) => {
                     let account_id = token.account_id.clone();
+                    if let Some((circuit_state, failures)) = previous_circuits.get(&account_id) {
+                        token.circuit_state = *circuit_state;
+                        token.consecutive_failures = *failures;
+                    }
                     self.tokens.insert(account_id, token);
                     count += 1;
                 },
@@ -91,10 +233,80 @@ impl TokenManager {
                 }
             }
         }
-        
+
+        self.start_proactive_refresh();
         Ok(count)
     }
 
+    /// 启动后台主动刷新任务 (已在运行时不重复启动)
+    pub fn start_proactive_refresh(&self) {
+        let mut task = self.refresh_task.lock().unwrap_or_else(|e| e.into_inner());
+        if task.as_ref().is_some_and(|t| !t.is_cancelled()) {
+            return;
+        }
+        let cancel = CancellationToken::new();
+        *task = Some(cancel.clone());
+
+        let manager = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = tokio::time::sleep(PROACTIVE_REFRESH_INTERVAL) => {}
+                }
+                manager.refresh_expiring_tokens().await;
+            }
+            tracing::debug!("后台 Token 主动刷新任务已停止");
+        });
+    }
+
+    /// 停止后台主动刷新任务 (停止反代服务时调用)
+    pub fn stop_proactive_refresh(&self) {
+        if let Some(cancel) = self.refresh_task.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            cancel.cancel();
+        }
+    }
+
+    /// 刷新即将过期的 token，失败仅记录警告 (请求路径仍会按需刷新)
+    async fn refresh_expiring_tokens(&self) {
+        let now = chrono::Utc::now().timestamp();
+        let expiring: Vec<ProxyToken> = self
+            .tokens
+            .iter()
+            .filter(|e| needs_proactive_refresh(e.value(), now))
+            .map(|e| e.value().clone())
+            .collect();
+
+        for token in expiring {
+            match crate::modules::oauth::ensure_fresh_token(&token.token_data()).await {
+                Ok(fresh) => {
+                    self.refresh_success.fetch_add(1, Ordering::Relaxed);
+                    if let Some(mut entry) = self.tokens.get_mut(&token.account_id) {
+                        entry.access_token = fresh.access_token.clone();
+                        entry.expires_in = fresh.expires_in;
+                        entry.timestamp = fresh.expiry_timestamp;
+                        entry.last_proactive_refresh = Some(std::time::Instant::now());
+                    }
+                    let response = crate::modules::oauth::TokenResponse {
+                        access_token: fresh.access_token,
+                        expires_in: fresh.expires_in,
+                        token_type: fresh.token_type,
+                        refresh_token: None,
+                    };
+                    if let Err(e) = self.save_refreshed_token(&token.account_id, &response).await {
+                        tracing::debug!("保存刷新后的 token 失败 ({}): {}", token.email, e);
+                    }
+                    tracing::debug!("后台主动刷新 token 成功: {}", token.email);
+                }
+                Err(e) => {
+                    self.refresh_failure.fetch_add(1, Ordering::Relaxed);
+                    tracing::warn!("后台主动刷新 token 失败 ({}): {}", token.email, e);
+                    crate::modules::account::notify_token_refresh_failed(&token.account_id, &token.email, &e);
+                }
+            }
+        }
+    }
+
     /// 重新加载指定账号（用于配额更新后的实时同步）
     pub async fn reload_account(&self, account_id: &str) -> Result<(), String> {
         let path = self.data_dir.join("accounts").join(format!("{}.json", account_id));
@@ -103,7 +315,11 @@ impl TokenManager {
         }
 
         match self.load_single_account(&path).await {
-            Ok(Some(token)) => {
+            Ok(Some(mut token)) => {
+                if let Some(existing) = self.tokens.get(account_id) {
+                    token.circuit_state = existing.circuit_state;
+                    token.consecutive_failures = existing.consecutive_failures;
+                }
                 self.tokens.insert(account_id.to_string(), token);
                 Ok(())
             }
@@ -138,6 +354,15 @@ impl TokenManager {
             return Ok(None);
         }
 
+        if is_frozen_account(&account) {
+            tracing::debug!(
+                "Skipping frozen account file: {:?} (email={})",
+                path,
+                account.get("email").and_then(|v| v.as_str()).unwrap_or("<unknown>")
+            );
+            return Ok(None);
+        }
+
         // 【新增】配额保护检查 - 在检查 proxy_disabled 之前执行
         // 这样可以在加载时自动恢复配额已恢复的账号
         if self.check_and_protect_quota(&account, path).await {
@@ -171,27 +396,20 @@ impl TokenManager {
             .ok_or("缺少 email 字段")?
             .to_string();
         
-        let token_obj = account["token"].as_object()
-            .ok_or("缺少 token 字段")?;
-        
-        let access_token = token_obj["access_token"].as_str()
-            .ok_or("缺少 access_token")?
-            .to_string();
-        
-        let refresh_token = token_obj["refresh_token"].as_str()
-            .ok_or("缺少 refresh_token")?
-            .to_string();
-        
-        let expires_in = token_obj["expires_in"].as_i64()
-            .ok_or("缺少 expires_in")?;
-        
-        let timestamp = token_obj["expiry_timestamp"].as_i64()
-            .ok_or("缺少 expiry_timestamp")?;
-        
-        // project_id 是可选的
-        let project_id = token_obj.get("project_id")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
+        let token: crate::models::TokenData = account
+            .get("token")
+            .cloned()
+            .ok_or("缺少 token 字段")
+            .and_then(|t| serde_json::from_value(t).map_err(|_| "token 字段无效"))?;
+
+        // 过期的 token 仍然加载，首次使用时 (或后台主动刷新) 再刷新
+        if token.is_expired() {
+            tracing::debug!(
+                "账号 {} 的 token 已过期或即将过期 (剩余 {}s)",
+                email,
+                token.seconds_until_expiry()
+            );
+        }
         
         
         // 【新增】提取订阅等级 (subscription_tier 为 "FREE" | "PRO" | "ULTRA")
@@ -200,6 +418,25 @@ impl TokenManager {
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
         
+        let health = account
+            .get("health")
+            .and_then(|h| serde_json::from_value::<crate::models::AccountHealth>(h.clone()).ok())
+            .unwrap_or_default();
+
+        let upstream_proxy = account
+            .get("upstream_proxy")
+            .and_then(|v| v.as_str())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+
+        let system_prompt_prefix = account
+            .get("system_prompt_prefix")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| s.to_string());
+
+        let priority = normalize_priority(account.get("priority").and_then(|v| v.as_u64()));
+
         // [FIX #563] 提取剩余配额用于优先级排序
         let remaining_quota = account.get("quota")
             .map(|q| self.calculate_quota_stats(q).1) // (total, remaining) -> remaining
@@ -207,15 +444,22 @@ impl TokenManager {
         
         Ok(Some(ProxyToken {
             account_id,
-            access_token,
-            refresh_token,
-            expires_in,
-            timestamp,
+            access_token: token.access_token.clone(),
+            refresh_token: token.refresh_token.clone(),
+            expires_in: token.expires_in,
+            timestamp: token.expires_at(),
             email,
             account_path: path.clone(),
-            project_id,
+            project_id: token.project_id,
             subscription_tier,
             remaining_quota,
+            circuit_state: CircuitState::Closed,
+            consecutive_failures: 0,
+            health,
+            upstream_proxy,
+            system_prompt_prefix,
+            last_proactive_refresh: None,
+            priority,
         }))
     }
 
@@ -394,20 +638,58 @@ impl TokenManager {
     /// 参数 `quota_group` 用于区分 "claude" vs "gemini" 组
     /// 参数 `force_rotate` 为 true 时将忽略锁定，强制切换账号
     /// 参数 `session_id` 用于跨请求维持会话粘性
-    pub async fn get_token(&self, quota_group: &str, force_rotate: bool, session_id: Option<&str>) -> Result<(String, String, String), String> {
+    ///
+    /// 返回 `(access_token, project_id, email, upstream_proxy)`，
+    /// `upstream_proxy` 为账号级代理覆盖，None 时使用全局上游代理
+    /// `allowlist` 不为空时仅在其中的账号间选择，其余账号完全跳过
+    pub async fn get_token(
+        &self,
+        quota_group: &str,
+        force_rotate: bool,
+        session_id: Option<&str>,
+        allowlist: Option<&AccountAllowlist>,
+    ) -> Result<(String, String, String, Option<String>), String> {
+        self.get_token_with_priority(quota_group, force_rotate, session_id, allowlist, RequestPriority::Normal)
+            .await
+    }
+
+    /// 同 `get_token`，`priority` 为 High 时 (如 Haiku 等延迟敏感请求) 优先选择近期 P50 延迟最低的账号
+    pub async fn get_token_with_priority(
+        &self,
+        quota_group: &str,
+        force_rotate: bool,
+        session_id: Option<&str>,
+        allowlist: Option<&AccountAllowlist>,
+        priority: RequestPriority,
+    ) -> Result<(String, String, String, Option<String>), String> {
         // 【优化 Issue #284】添加 5 秒超时，防止死锁
         let timeout_duration = std::time::Duration::from_secs(5);
-        match tokio::time::timeout(timeout_duration, self.get_token_internal(quota_group, force_rotate, session_id)).await {
+        match tokio::time::timeout(timeout_duration, self.get_token_internal(quota_group, force_rotate, session_id, allowlist, priority)).await {
             Ok(result) => result,
             Err(_) => Err("Token acquisition timeout (5s) - system too busy or deadlock detected".to_string()),
         }
     }
 
     /// 内部实现：获取 Token 的核心逻辑
-    async fn get_token_internal(&self, quota_group: &str, force_rotate: bool, session_id: Option<&str>) -> Result<(String, String, String), String> {
-        let mut tokens_snapshot: Vec<ProxyToken> = self.tokens.iter().map(|e| e.value().clone()).collect();
+    async fn get_token_internal(
+        &self,
+        quota_group: &str,
+        force_rotate: bool,
+        session_id: Option<&str>,
+        allowlist: Option<&AccountAllowlist>,
+        priority: RequestPriority,
+    ) -> Result<(String, String, String, Option<String>), String> {
+        let mut tokens_snapshot: Vec<ProxyToken> = self
+            .tokens
+            .iter()
+            .filter(|e| allowlist.is_none_or(|a| a.contains(&e.value().account_id)))
+            .map(|e| e.value().clone())
+            .collect();
         let total = tokens_snapshot.len();
         if total == 0 {
+            if allowlist.is_some() {
+                return Err("No accounts available for this API key".to_string());
+            }
             return Err("Token pool is empty".to_string());
         }
 
@@ -436,16 +718,28 @@ impl TokenManager {
             let quota_a = a.remaining_quota.unwrap_or(0);
             let quota_b = b.remaining_quota.unwrap_or(0);
             quota_b.cmp(&quota_a)  // Descending: higher quota first
+                // Third: lower 7-day error rate first (health tie-breaker)
+                .then_with(|| {
+                    a.health
+                        .error_rate_7d
+                        .partial_cmp(&b.health.error_rate_7d)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
         });
 
+        // 延迟敏感请求: 从近期 P50 最快的几个账号中加权轮询选择起点 (见 fastest_start_index)
+        let prefer_fastest = priority == RequestPriority::High;
+
 
         // 0. 读取当前调度配置
         let scheduling = self.sticky_config.read().await.clone();
         use crate::proxy::sticky_config::SchedulingMode;
+        let session_ttl = std::time::Duration::from_secs(scheduling.session_ttl_secs);
+        let rate_limit_config = self.rate_limit_config.read().await.clone();
 
         // 【优化 Issue #284】将锁操作移到循环外，避免重复获取锁
         // 预先获取 last_used_account 的快照，避免在循环中多次加锁
-        let last_used_account_id = if quota_group != "image_gen" {
+        let last_used_account_id = if quota_group != "image_gen" && !prefer_fastest {
             let last_used = self.last_used_account.lock().await;
             last_used.clone()
         } else {
@@ -455,6 +749,8 @@ impl TokenManager {
         let mut attempted: HashSet<String> = HashSet::new();
         let mut last_error: Option<String> = None;
         let mut need_update_last_used: Option<(String, std::time::Instant)> = None;
+        // 是否有账号因本地令牌桶耗尽被跳过
+        let mut throttled = false;
 
         for attempt in 0..total {
             let rotate = force_rotate || attempt > 0;
@@ -467,7 +763,7 @@ impl TokenManager {
                 let sid = session_id.unwrap();
                 
                 // 1. 检查会话是否已绑定账号
-                if let Some(bound_id) = self.session_accounts.get(sid).map(|v| v.clone()) {
+                if let Some(bound_id) = self.session_binding(sid, session_ttl) {
                     // 【修复】先通过 account_id 找到对应的账号，获取其 email
                     // 因为限流记录是以 email 为 key 存储的
                     if let Some(bound_token) = tokens_snapshot.iter().find(|t| t.account_id == bound_id) {
@@ -481,10 +777,20 @@ impl TokenManager {
                                 sid, bound_token.email, reset_sec
                             );
                             self.session_accounts.remove(sid);
+                        } else if !self.circuit_allows(&bound_id) {
+                            // 绑定账号处于熔断状态，解绑并切换
+                            tracing::warn!("Session {} bound account {} circuit is open, unbinding.", sid, bound_token.email);
+                            self.session_accounts.remove(sid);
                         } else if !attempted.contains(&bound_id) {
-                            // 3. 账号可用且未被标记为尝试失败，优先复用
-                            tracing::debug!("Sticky Session: Successfully reusing bound account {} for session {}", bound_token.email, sid);
-                            target_token = Some(bound_token.clone());
+                            // 3. 账号可用且未被标记为尝试失败，优先复用 (令牌桶耗尽时跳过，但保留绑定)
+                            if self.try_acquire_rate_budget(bound_token, &rate_limit_config) {
+                                tracing::debug!("Sticky Session: Successfully reusing bound account {} for session {}", bound_token.email, sid);
+                                self.bind_session(sid, &bound_id, session_ttl);
+                                target_token = Some(bound_token.clone());
+                            } else {
+                                tracing::debug!("Sticky Session: Bound account {} hit local rate limit, skipping", bound_token.email);
+                                throttled = true;
+                            }
                         }
                     } else {
                         // 绑定的账号已不存在（可能被删除），解绑
@@ -501,9 +807,16 @@ impl TokenManager {
                     if last_time.elapsed().as_secs() < 60 && !attempted.contains(account_id) {
                         if let Some(found) = tokens_snapshot.iter().find(|t| &t.account_id == account_id) {
                             // 【修复】检查限流状态，避免复用已被锁定的账号
-                            if !self.is_rate_limited(&found.email) {
-                                tracing::debug!("60s Window: Force reusing last account: {}", found.email);
-                                target_token = Some(found.clone());
+                            if !self.circuit_allows(&found.account_id) {
+                                tracing::debug!("60s Window: Last account {} circuit is open, skipping", found.email);
+                            } else if !self.is_rate_limited(&found.email) {
+                                if self.try_acquire_rate_budget(found, &rate_limit_config) {
+                                    tracing::debug!("60s Window: Force reusing last account: {}", found.email);
+                                    target_token = Some(found.clone());
+                                } else {
+                                    tracing::debug!("60s Window: Last account {} hit local rate limit, skipping", found.email);
+                                    throttled = true;
+                                }
                             } else {
                                 tracing::debug!("60s Window: Last account {} is rate-limited, skipping", found.email);
                             }
@@ -513,7 +826,11 @@ impl TokenManager {
                 
                 // 若无锁定，则轮询选择新账号
                 if target_token.is_none() {
-                    let start_idx = self.current_index.fetch_add(1, Ordering::SeqCst) % total;
+                    let start_idx = if prefer_fastest {
+                        self.fastest_start_index(&tokens_snapshot)
+                    } else {
+                        self.weighted_start_index(&tokens_snapshot)
+                    };
                     for offset in 0..total {
                         let idx = (start_idx + offset) % total;
                         let candidate = &tokens_snapshot[idx];
@@ -526,6 +843,17 @@ impl TokenManager {
                             continue;
                         }
 
+                        // 熔断中的账号 (401/硬 403) 在冷却期内不参与调度
+                        if !self.circuit_allows(&candidate.account_id) {
+                            continue;
+                        }
+
+                        // 本地令牌桶耗尽，跳到下一个账号而不是阻塞等待
+                        if !self.try_acquire_rate_budget(candidate, &rate_limit_config) {
+                            throttled = true;
+                            continue;
+                        }
+
                         target_token = Some(candidate.clone());
                         // 【优化】标记需要更新，稍后统一写回
                         need_update_last_used = Some((candidate.account_id.clone(), std::time::Instant::now()));
@@ -533,7 +861,7 @@ impl TokenManager {
                         // 如果是会话首次分配且需要粘性，在此建立绑定
                         if let Some(sid) = session_id {
                             if scheduling.mode != SchedulingMode::PerformanceFirst {
-                                self.session_accounts.insert(sid.to_string(), candidate.account_id.clone());
+                                self.bind_session(sid, &candidate.account_id, session_ttl);
                                 tracing::debug!("Sticky Session: Bound new account {} to session {}", candidate.email, sid);
                             }
                         }
@@ -542,7 +870,11 @@ impl TokenManager {
                 }
             } else if target_token.is_none() {
                 // 模式 C: 纯轮询模式 (Round-robin) 或强制轮换
-                let start_idx = self.current_index.fetch_add(1, Ordering::SeqCst) % total;
+                let start_idx = if prefer_fastest {
+                    self.fastest_start_index(&tokens_snapshot)
+                } else {
+                    self.weighted_start_index(&tokens_snapshot)
+                };
                 for offset in 0..total {
                     let idx = (start_idx + offset) % total;
                     let candidate = &tokens_snapshot[idx];
@@ -555,6 +887,15 @@ impl TokenManager {
                         continue;
                     }
 
+                    if !self.circuit_allows(&candidate.account_id) {
+                        continue;
+                    }
+
+                    if !self.try_acquire_rate_budget(candidate, &rate_limit_config) {
+                        throttled = true;
+                        continue;
+                    }
+
                     target_token = Some(candidate.clone());
                     
                     if rotate {
@@ -567,6 +908,11 @@ impl TokenManager {
             let mut token = match target_token {
                 Some(t) => t,
                 None => {
+                    // 账号因本地限速被跳过，直接返回，不触发乐观重置
+                    if throttled {
+                        return Err("All available accounts reached the local per-account rate limit. Please retry shortly.".to_string());
+                    }
+
                     // 乐观重置策略: 双层防护机制
                     // 当所有账号都无法选择时,可能是时序竞争导致的状态不同步
                     
@@ -588,7 +934,7 @@ impl TokenManager {
                             
                             // 重新尝试选择账号
                             let retry_token = tokens_snapshot.iter()
-                                .find(|t| !attempted.contains(&t.account_id) && !self.is_rate_limited(&t.account_id));
+                                .find(|t| !attempted.contains(&t.account_id) && !self.is_rate_limited(&t.account_id) && self.circuit_allows(&t.account_id));
                             
                             if let Some(t) = retry_token {
                                 tracing::info!("✅ Buffer delay successful! Found available account: {}", t.email);
@@ -605,7 +951,7 @@ impl TokenManager {
                                 
                                 // 再次尝试选择账号
                                 let final_token = tokens_snapshot.iter()
-                                    .find(|t| !attempted.contains(&t.account_id));
+                                    .find(|t| !attempted.contains(&t.account_id) && self.circuit_allows(&t.account_id));
                                 
                                 if let Some(t) = final_token {
                                     tracing::info!("✅ Optimistic reset successful! Using account: {}", t.email);
@@ -630,25 +976,26 @@ impl TokenManager {
 
         
             // 3. 检查 token 是否过期（提前5分钟刷新）
-            let now = chrono::Utc::now().timestamp();
-            if now >= token.timestamp - 300 {
+            if token.token_data().is_expired() {
                 tracing::debug!("账号 {} 的 token 即将过期，正在刷新...", token.email);
 
                 // 调用 OAuth 刷新 token
                 match crate::modules::oauth::refresh_access_token(&token.refresh_token).await {
                     Ok(token_response) => {
                         tracing::debug!("Token 刷新成功！");
+                        self.refresh_success.fetch_add(1, Ordering::Relaxed);
 
                         // 更新本地内存对象供后续使用
                         token.access_token = token_response.access_token.clone();
                         token.expires_in = token_response.expires_in;
-                        token.timestamp = now + token_response.expires_in;
+                        token.timestamp = chrono::Utc::now().timestamp() + token_response.expires_in;
 
                         // 同步更新跨线程共享的 DashMap
                         if let Some(mut entry) = self.tokens.get_mut(&token.account_id) {
                             entry.access_token = token.access_token.clone();
                             entry.expires_in = token.expires_in;
                             entry.timestamp = token.timestamp;
+                            entry.last_proactive_refresh = Some(std::time::Instant::now());
                         }
 
                         // 同步落盘（避免重启后继续使用过期 timestamp 导致频繁刷新）
@@ -658,6 +1005,7 @@ impl TokenManager {
                     }
                     Err(e) => {
                         tracing::error!("Token 刷新失败 ({}): {}，尝试下一个账号", token.email, e);
+                        self.refresh_failure.fetch_add(1, Ordering::Relaxed);
                         if e.contains("\"invalid_grant\"") || e.contains("invalid_grant") {
                             tracing::error!(
                                 "Disabling account due to invalid_grant ({}): refresh_token likely revoked/expired",
@@ -725,7 +1073,7 @@ impl TokenManager {
                 }
             }
 
-            return Ok((token.access_token, project_id, token.email));
+            return Ok((token.access_token, project_id, token.email, token.upstream_proxy));
         }
 
         Err(last_error.unwrap_or_else(|| "All accounts failed".to_string()))
@@ -805,9 +1153,74 @@ impl TokenManager {
         self.tokens.len()
     }
 
+    /// 获取账号级上游代理 (未设置或账号不在池中时返回 None)
+    pub fn get_account_proxy(&self, email: &str) -> Option<String> {
+        self.tokens
+            .iter()
+            .find(|entry| entry.value().email == email)
+            .and_then(|entry| entry.value().upstream_proxy.clone())
+    }
+
+    /// 保存账号熔断状态到 proxy_state.json (不含 access/refresh token)，返回保存的账号数
+    pub fn save_state(&self) -> Result<usize, String> {
+        let now = chrono::Utc::now().timestamp();
+        let tokens: Vec<PersistedToken> = self
+            .tokens
+            .iter()
+            .map(|e| PersistedToken::from_token(e.value(), now))
+            .collect();
+        let count = tokens.len();
+        token_state::save_state(&self.data_dir, &PersistedState { saved_at: now, tokens })?;
+        Ok(count)
+    }
+
+    /// 丢弃超过 `max_age_secs` 未刷新 token 的持久化条目，返回丢弃数量
+    pub fn cleanup_stale_state(&self, max_age_secs: u64) -> Result<usize, String> {
+        let Some(mut state) = token_state::load_state(&self.data_dir)? else {
+            return Ok(0);
+        };
+        let now = chrono::Utc::now().timestamp();
+        let before = state.tokens.len();
+        state.tokens.retain(|t| !t.is_stale(now, max_age_secs));
+        let removed = before - state.tokens.len();
+        if removed > 0 {
+            token_state::save_state(&self.data_dir, &state)?;
+        }
+        Ok(removed)
+    }
+
+    /// 读取持久化的熔断状态 (account_id -> (状态, 连续失败次数))，失败时仅记录警告
+    fn load_persisted_circuits(&self) -> HashMap<String, (CircuitState, u8)> {
+        if let Err(e) = self.cleanup_stale_state(token_state::PROXY_STATE_MAX_AGE_SECS) {
+            tracing::warn!("清理过期反代状态失败: {}", e);
+        }
+        let state = match token_state::load_state(&self.data_dir) {
+            Ok(Some(state)) => state,
+            Ok(None) => return HashMap::new(),
+            Err(e) => {
+                tracing::warn!("{}", e);
+                return HashMap::new();
+            }
+        };
+        let now = chrono::Utc::now().timestamp();
+        state
+            .tokens
+            .into_iter()
+            .map(|t| (t.account_id.clone(), (t.restore_circuit(now), t.consecutive_failures)))
+            .collect()
+    }
+
+    /// 获取账号级系统提示词前缀 (未设置或账号不在池中时返回 None)
+    pub fn get_system_prompt_prefix(&self, email: &str) -> Option<String> {
+        self.tokens
+            .iter()
+            .find(|entry| entry.value().email == email)
+            .and_then(|entry| entry.value().system_prompt_prefix.clone())
+    }
+
     /// 通过 email 获取指定账号的 Token（用于预热等需要指定账号的场景）
     /// 此方法会自动刷新过期的 token
-    pub async fn get_token_by_email(&self, email: &str) -> Result<(String, String, String), String> {
+    pub async fn get_token_by_email(&self, email: &str) -> Result<(String, String, String, Option<String>), String> {
         // 查找账号信息
         let token_info = {
             let mut found = None;
@@ -816,12 +1229,8 @@ impl TokenManager {
                 if token.email == email {
                     found = Some((
                         token.account_id.clone(),
-                        token.access_token.clone(),
-                        token.refresh_token.clone(),
-                        token.timestamp,
-                        token.expires_in,
-                        chrono::Utc::now().timestamp(),
-                        token.project_id.clone(),
+                        token.token_data(),
+                        token.upstream_proxy.clone(),
                     ));
                     break;
                 }
@@ -829,47 +1238,45 @@ impl TokenManager {
             found
         };
 
-        let (
-            account_id,
-            current_access_token,
-            refresh_token,
-            timestamp,
-            expires_in,
-            now,
-            project_id_opt,
-        ) = match token_info {
+        let (account_id, token_data, upstream_proxy) = match token_info {
             Some(info) => info,
             None => return Err(format!("未找到账号: {}", email)),
         };
 
-        let project_id = project_id_opt.unwrap_or_else(|| "bamboo-precept-lgxtn".to_string());
+        let project_id = token_data
+            .project_id
+            .clone()
+            .unwrap_or_else(|| "bamboo-precept-lgxtn".to_string());
         
         // 检查是否过期 (提前5分钟)
-        if now < timestamp + expires_in - 300 {
-            return Ok((current_access_token, project_id, email.to_string()));
+        if !token_data.is_expired() {
+            return Ok((token_data.access_token, project_id, email.to_string(), upstream_proxy));
         }
 
         tracing::info!("[Warmup] Token for {} is expiring, refreshing...", email);
 
         // 调用 OAuth 刷新 token
-        match crate::modules::oauth::refresh_access_token(&refresh_token).await {
+        match crate::modules::oauth::refresh_access_token(&token_data.refresh_token).await {
             Ok(token_response) => {
                 tracing::info!("[Warmup] Token refresh successful for {}", email);
-                let new_now = chrono::Utc::now().timestamp();
+                self.refresh_success.fetch_add(1, Ordering::Relaxed);
                 
-                // 更新缓存
+                // 更新缓存 (timestamp 为过期时间)
                 if let Some(mut entry) = self.tokens.get_mut(&account_id) {
                     entry.access_token = token_response.access_token.clone();
                     entry.expires_in = token_response.expires_in;
-                    entry.timestamp = new_now;
+                    entry.timestamp = chrono::Utc::now().timestamp() + token_response.expires_in;
                 }
 
                 // 保存到磁盘
                 let _ = self.save_refreshed_token(&account_id, &token_response).await;
 
-                Ok((token_response.access_token, project_id, email.to_string()))
+                Ok((token_response.access_token, project_id, email.to_string(), upstream_proxy))
+            }
+            Err(e) => {
+                self.refresh_failure.fetch_add(1, Ordering::Relaxed);
+                Err(format!("[Warmup] Token refresh failed for {}: {}", email, e))
             }
-            Err(e) => Err(format!("[Warmup] Token refresh failed for {}: {}", email, e)),
         }
     }
     
@@ -921,6 +1328,256 @@ impl TokenManager {
     /// 下次失败时从最短的锁定时间开始（智能限流）。
     pub fn mark_account_success(&self, account_id: &str) {
         self.rate_limit_tracker.mark_success(account_id);
+        self.close_circuit(account_id);
+    }
+
+    /// 记录反代请求结果，更新账号健康度并异步落盘
+    ///
+    /// `account_id` 可以是账号 ID 或邮箱
+    pub fn record_request_health(&self, account_id: &str, success: bool, latency_ms: f64) {
+        let now = chrono::Utc::now().timestamp();
+        let mut updated = None;
+        for mut entry in self.tokens.iter_mut() {
+            if entry.account_id == account_id || entry.email == account_id {
+                if success {
+                    entry.health.record_success(Some(latency_ms), now);
+                    self.latency_stats
+                        .entry(entry.account_id.clone())
+                        .or_default()
+                        .record(latency_ms);
+                } else {
+                    entry.health.record_error(now);
+                }
+                updated = Some(entry.account_id.clone());
+                break;
+            }
+        }
+
+        let Some(account_id) = updated else {
+            return;
+        };
+        self.dirty_health
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(account_id);
+
+        // 防抖: 窗口期内的多次请求合并为一次写回
+        if self.health_flush_pending.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let manager = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(HEALTH_FLUSH_DELAY).await;
+            manager.health_flush_pending.store(false, Ordering::Release);
+            manager.flush_health().await;
+        });
+    }
+
+    /// 将待写回的账号健康度批量写入账号文件 (停止服务时也会调用)
+    pub async fn flush_health(&self) {
+        let updates: Vec<(String, crate::models::AccountHealth)> = {
+            let mut dirty = self.dirty_health.lock().unwrap_or_else(|e| e.into_inner());
+            dirty
+                .drain()
+                .filter_map(|id| self.tokens.get(&id).map(|t| (id, t.health.clone())))
+                .collect()
+        };
+        if updates.is_empty() {
+            return;
+        }
+        match tokio::task::spawn_blocking(move || crate::modules::account::save_accounts_health(&updates)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => tracing::debug!("保存账号健康度失败: {}", e),
+            Err(e) => tracing::debug!("保存账号健康度任务失败: {}", e),
+        }
+    }
+
+    /// Token 刷新统计 (成功, 失败)
+    pub fn token_refresh_counts(&self) -> (u64, u64) {
+        (
+            self.refresh_success.load(Ordering::Relaxed),
+            self.refresh_failure.load(Ordering::Relaxed),
+        )
+    }
+
+    /// 各账号剩余配额 (邮箱, 剩余百分比)，未知配额的账号不返回
+    pub fn remaining_quotas(&self) -> Vec<(String, i32)> {
+        let mut quotas: Vec<(String, i32)> = self
+            .tokens
+            .iter()
+            .filter_map(|e| e.remaining_quota.map(|q| (e.email.clone(), q)))
+            .collect();
+        quotas.sort();
+        quotas
+    }
+
+    // ===== 熔断器 =====
+
+    /// 设置熔断冷却时间 (秒)
+    pub fn set_circuit_cooldown(&self, secs: u64) {
+        self.circuit_cooldown_secs.store(secs, Ordering::Relaxed);
+    }
+
+    /// 记录硬失败 (401 / 非限流类 403)，打开账号熔断
+    ///
+    /// `account_id` 可以是账号 ID 或邮箱 (handler 中通常只有邮箱)
+    pub fn mark_circuit_failure(&self, account_id: &str, status: u16, error_body: &str) {
+        if !is_hard_failure(status, error_body) {
+            return;
+        }
+        let cooldown = std::time::Duration::from_secs(self.circuit_cooldown_secs.load(Ordering::Relaxed));
+        for mut entry in self.tokens.iter_mut() {
+            if entry.account_id == account_id || entry.email == account_id {
+                entry.consecutive_failures = entry.consecutive_failures.saturating_add(1);
+                entry.circuit_state = CircuitState::Open {
+                    until: std::time::Instant::now() + cooldown,
+                };
+                tracing::warn!(
+                    "Circuit opened for account {} after HTTP {} ({} consecutive failure(s), cooldown {}s)",
+                    entry.email,
+                    status,
+                    entry.consecutive_failures,
+                    cooldown.as_secs()
+                );
+            }
+        }
+    }
+
+    /// 请求成功后关闭熔断并清零失败计数
+    fn close_circuit(&self, account_id: &str) {
+        for mut entry in self.tokens.iter_mut() {
+            if (entry.account_id == account_id || entry.email == account_id)
+                && (entry.circuit_state != CircuitState::Closed || entry.consecutive_failures > 0)
+            {
+                entry.circuit_state = CircuitState::Closed;
+                entry.consecutive_failures = 0;
+                tracing::info!("Circuit closed for account {}", entry.email);
+            }
+        }
+    }
+
+    /// 平滑加权轮询 (同 nginx smooth WRR) 选出本轮的起始账号
+    ///
+    /// 每轮所有账号的当前权重加上各自 priority，选出当前权重最大者并减去总权重，
+    /// 使高权重账号被更频繁选中且与其他账号交错；熔断中的账号权重为 0
+    fn weighted_start_index(&self, tokens: &[ProxyToken]) -> usize {
+        let now = std::time::Instant::now();
+        let mut current = self.wrr_current_weights.lock().unwrap_or_else(|e| e.into_inner());
+        let mut total_weight = 0i64;
+        let mut best: Option<(usize, i64)> = None;
+
+        for (idx, token) in tokens.iter().enumerate() {
+            let circuit_open = self
+                .tokens
+                .get(&token.account_id)
+                .is_some_and(|t| matches!(t.circuit_state, CircuitState::Open { until } if now < until));
+            if circuit_open {
+                continue;
+            }
+            let weight = token.priority as i64;
+            let current_weight = current.entry(token.account_id.clone()).or_insert(0);
+            *current_weight += weight;
+            total_weight += weight;
+            if best.is_none_or(|(_, w)| *current_weight > w) {
+                best = Some((idx, *current_weight));
+            }
+        }
+
+        match best {
+            Some((idx, _)) => {
+                if let Some(w) = current.get_mut(&tokens[idx].account_id) {
+                    *w -= total_weight;
+                }
+                idx
+            }
+            // 全部熔断时退化为普通轮询 (候选循环中仍会跳过)
+            None => self.current_index.fetch_add(1, Ordering::SeqCst) % tokens.len(),
+        }
+    }
+
+    /// 延迟敏感请求的起始位置: 在近期 P50 最快的 FASTEST_POOL_SIZE 个账号中按优先级加权轮询，
+    /// 避免流量全部压到单个最快账号；候选保持 tier/配额排序，没有延迟样本时退化为普通加权轮询
+    fn fastest_start_index(&self, tokens: &[ProxyToken]) -> usize {
+        let mut measured: Vec<(usize, f64)> = tokens
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, t)| self.latency_stats.get(&t.account_id).map(|s| (idx, s.p50_ms)))
+            .collect();
+        if measured.is_empty() {
+            return self.weighted_start_index(tokens);
+        }
+        measured.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        measured.truncate(FASTEST_POOL_SIZE);
+        measured.sort_by_key(|(idx, _)| *idx);
+
+        let pool: Vec<ProxyToken> = measured.iter().map(|(idx, _)| tokens[*idx].clone()).collect();
+        measured[self.weighted_start_index(&pool)].0
+    }
+
+    /// 检查账号熔断状态是否允许调度；冷却结束的 Open 状态转为 HalfOpen
+    fn circuit_allows(&self, account_id: &str) -> bool {
+        let Some(mut entry) = self.tokens.get_mut(account_id) else {
+            return false;
+        };
+        match entry.circuit_state {
+            CircuitState::Closed | CircuitState::HalfOpen => true,
+            CircuitState::Open { until } => {
+                if std::time::Instant::now() >= until {
+                    entry.circuit_state = CircuitState::HalfOpen;
+                    tracing::info!("Circuit half-open for account {}, allowing trial request", entry.email);
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    /// 各账号的延迟统计 (按邮箱排序，无样本的账号不返回)
+    pub fn get_latency_stats(&self) -> Vec<AccountLatencyInfo> {
+        let mut stats: Vec<AccountLatencyInfo> = self
+            .latency_stats
+            .iter()
+            .filter_map(|entry| {
+                let token = self.tokens.get(entry.key())?;
+                Some(AccountLatencyInfo {
+                    account_id: entry.key().clone(),
+                    email: token.email.clone(),
+                    stats: *entry.value(),
+                })
+            })
+            .collect();
+        stats.sort_by(|a, b| a.email.cmp(&b.email));
+        stats
+    }
+
+    /// 获取所有账号的熔断状态
+    pub fn get_circuit_states(&self) -> Vec<AccountCircuitInfo> {
+        let now = std::time::Instant::now();
+        let mut states: Vec<AccountCircuitInfo> = self
+            .tokens
+            .iter()
+            .map(|entry| {
+                let (state, open_remaining_secs) = match entry.circuit_state {
+                    CircuitState::Closed => ("closed", None),
+                    CircuitState::HalfOpen => ("half_open", None),
+                    CircuitState::Open { until } if until > now => {
+                        ("open", Some(until.duration_since(now).as_secs()))
+                    }
+                    // 冷却已结束，下次调度时会转为 half_open
+                    CircuitState::Open { .. } => ("half_open", None),
+                };
+                AccountCircuitInfo {
+                    account_id: entry.account_id.clone(),
+                    email: entry.email.clone(),
+                    state: state.to_string(),
+                    consecutive_failures: entry.consecutive_failures,
+                    open_remaining_secs,
+                }
+            })
+            .collect();
+        states.sort_by(|a, b| a.email.cmp(&b.email));
+        states
     }
     
     /// 从账号文件获取配额刷新时间
@@ -1141,6 +1798,64 @@ impl TokenManager {
         tracing::debug!("Scheduling configuration updated: {:?}", *config);
     }
 
+    // ===== 单账号令牌桶限速 =====
+
+    /// 更新单账号限速配置
+    pub async fn update_rate_limit_config(&self, new_config: AccountRateLimitConfig) {
+        let mut config = self.rate_limit_config.write().await;
+        *config = new_config;
+        tracing::debug!("Per-account rate limit configuration updated: {:?}", *config);
+    }
+
+    /// 尝试消耗账号的令牌桶额度，未配置限速时始终返回 true
+    fn try_acquire_rate_budget(&self, token: &ProxyToken, config: &AccountRateLimitConfig) -> bool {
+        let Some((rps, burst)) = config.resolve(&token.account_id, &token.email) else {
+            return true;
+        };
+
+        let mut limiter = self
+            .rate_limiters
+            .entry(token.account_id.clone())
+            .or_insert_with(|| RateLimiter::new(rps, burst));
+        if limiter.refill_per_sec() != rps || limiter.burst() != burst {
+            limiter.reconfigure(rps, burst);
+        }
+        limiter.try_acquire()
+    }
+
+    /// 查询会话绑定的账号，超过 TTL 未使用的绑定视为失效并移除
+    fn session_binding(&self, session_id: &str, ttl: std::time::Duration) -> Option<String> {
+        let (account_id, last_used) = self.session_accounts.get(session_id).map(|v| v.clone())?;
+        if last_used.elapsed() > ttl {
+            self.session_accounts.remove(session_id);
+            return None;
+        }
+        Some(account_id)
+    }
+
+    /// 绑定 (或续期) 会话与账号，顺带清理已过期的绑定
+    fn bind_session(&self, session_id: &str, account_id: &str, ttl: std::time::Duration) {
+        self.session_accounts.retain(|_, (_, last_used)| last_used.elapsed() <= ttl);
+        self.session_accounts
+            .insert(session_id.to_string(), (account_id.to_string(), std::time::Instant::now()));
+    }
+
+    /// 会话当前没有绑定时，将其绑定到指定账号 (账号 ID 或邮箱)，用于恢复客户端会话的粘性
+    pub fn restore_session_binding(&self, session_id: &str, account: &str) {
+        if self.session_accounts.contains_key(session_id) {
+            return;
+        }
+        let account_id = self
+            .tokens
+            .iter()
+            .find(|t| t.account_id == account || t.email == account)
+            .map(|t| t.account_id.clone());
+        if let Some(account_id) = account_id {
+            self.session_accounts
+                .insert(session_id.to_string(), (account_id, std::time::Instant::now()));
+        }
+    }
+
     /// 清除特定会话的粘性映射
     #[allow(dead_code)]
     pub fn clear_session_binding(&self, session_id: &str) {
@@ -1153,6 +1868,64 @@ impl TokenManager {
     }
 }
 
+/// 冻结的账号保留给手动使用，不进入反代账号池
+fn is_frozen_account(account: &serde_json::Value) -> bool {
+    account.get("frozen").and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+/// 默认熔断冷却时间 (秒)
+const DEFAULT_CIRCUIT_COOLDOWN_SECS: u64 = 300;
+
+/// 延迟敏感请求在近期最快的多少个账号之间分摊
+const FASTEST_POOL_SIZE: usize = 3;
+
+/// 账号健康度写回磁盘的防抖间隔
+const HEALTH_FLUSH_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// 后台主动刷新间隔
+const PROACTIVE_REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+/// 距过期不足该时长 (秒) 的 token 会被主动刷新
+const PROACTIVE_REFRESH_WINDOW_SECS: i64 = 300;
+
+/// 将账号文件中的 priority 归一化到 1-10，缺失时取默认值
+fn normalize_priority(value: Option<u64>) -> u8 {
+    use crate::models::account::{DEFAULT_ACCOUNT_PRIORITY, MAX_ACCOUNT_PRIORITY, MIN_ACCOUNT_PRIORITY};
+    value
+        .map(|p| p.clamp(MIN_ACCOUNT_PRIORITY as u64, MAX_ACCOUNT_PRIORITY as u64) as u8)
+        .unwrap_or(DEFAULT_ACCOUNT_PRIORITY)
+}
+
+/// 是否需要后台主动刷新: 即将过期，且最近一个刷新间隔内未被刷新过 (避免与请求路径重复刷新)
+fn needs_proactive_refresh(token: &ProxyToken, now: i64) -> bool {
+    token.timestamp - now <= PROACTIVE_REFRESH_WINDOW_SECS
+        && token
+            .last_proactive_refresh
+            .is_none_or(|t| t.elapsed() >= PROACTIVE_REFRESH_INTERVAL)
+}
+
+/// 403 响应体中表示限流 / 配额耗尽 (而非权限问题) 的标记
+const RATE_LIMIT_MARKERS: [&str; 6] = [
+    "rate limit",
+    "rate_limit_exceeded",
+    "ratelimitexceeded",
+    "resource_exhausted",
+    "too many requests",
+    "quota",
+];
+
+/// 是否为需要熔断的硬失败: 401 认证失效，或非限流类的 403 (权限/地区限制)
+fn is_hard_failure(status: u16, error_body: &str) -> bool {
+    match status {
+        401 => true,
+        403 => {
+            // 只匹配完整的限流标记，避免 "generateContent" 等单词中的 "rate" 被误判为限流
+            let lower = error_body.to_lowercase();
+            !RATE_LIMIT_MARKERS.iter().any(|m| lower.contains(m))
+        }
+        _ => false,
+    }
+}
+
 fn truncate_reason(reason: &str, max_len: usize) -> String {
     if reason.chars().count() <= max_len {
         return reason.to_string();
@@ -1161,3 +1934,292 @@ fn truncate_reason(reason: &str, max_len: usize) -> String {
     s.push('…');
     s
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_token(account_id: &str) -> ProxyToken {
+        ProxyToken {
+            account_id: account_id.to_string(),
+            access_token: format!("access-{}", account_id),
+            refresh_token: format!("refresh-{}", account_id),
+            expires_in: 3600,
+            timestamp: chrono::Utc::now().timestamp() + 3600,
+            email: format!("{}@example.com", account_id),
+            account_path: PathBuf::from(format!("{}.json", account_id)),
+            project_id: Some("test-project".to_string()),
+            subscription_tier: None,
+            remaining_quota: None,
+            circuit_state: CircuitState::Closed,
+            consecutive_failures: 0,
+            health: crate::models::AccountHealth::default(),
+            upstream_proxy: None,
+            system_prompt_prefix: None,
+            last_proactive_refresh: None,
+            priority: crate::models::account::DEFAULT_ACCOUNT_PRIORITY,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_binding_sticks_until_ttl() {
+        let manager = make_manager(&["a", "b", "c"]);
+        let (_, _, first, _) = manager.get_token("gemini", false, Some("conv-1"), None).await.unwrap();
+        *manager.last_used_account.lock().await = None;
+        let (_, _, again, _) = manager.get_token("gemini", false, Some("conv-1"), None).await.unwrap();
+        assert_eq!(first, again);
+        assert!(manager.session_binding("conv-1", std::time::Duration::from_secs(3600)).is_some());
+
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert!(manager.session_binding("conv-1", std::time::Duration::ZERO).is_none());
+        assert!(manager.session_accounts.is_empty());
+    }
+
+    fn make_manager(ids: &[&str]) -> TokenManager {
+        let manager = TokenManager::new(std::env::temp_dir());
+        for id in ids {
+            manager.tokens.insert(id.to_string(), make_token(id));
+        }
+        manager
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_account_is_skipped() {
+        let manager = make_manager(&["acc-a", "acc-b"]);
+        manager
+            .update_rate_limit_config(AccountRateLimitConfig {
+                rps: Some(0.001),
+                burst: Some(1),
+                per_account_rps: HashMap::new(),
+            })
+            .await;
+
+        let (_, _, first, _) = manager.get_token("gemini", false, None, None).await.unwrap();
+        // 60s 窗口会优先复用上一个账号，但其令牌桶已空，应跳到下一个账号
+        let (_, _, second, _) = manager.get_token("gemini", false, None, None).await.unwrap();
+        assert_ne!(first, second);
+
+        // 所有账号额度均已耗尽
+        assert!(manager.get_token("gemini", false, None, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_token_returns_account_proxy() {
+        let manager = make_manager(&["acc-a"]);
+        let (_, _, _, proxy) = manager.get_token("gemini", false, None, None).await.unwrap();
+        assert_eq!(proxy, None);
+
+        manager.tokens.get_mut("acc-a").unwrap().upstream_proxy =
+            Some("socks5://127.0.0.1:1080".to_string());
+        let (_, _, _, proxy) = manager.get_token("gemini", false, None, None).await.unwrap();
+        assert_eq!(proxy.as_deref(), Some("socks5://127.0.0.1:1080"));
+        assert_eq!(
+            manager.get_account_proxy("acc-a@example.com").as_deref(),
+            Some("socks5://127.0.0.1:1080")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_per_account_rate_limit_override() {
+        let manager = make_manager(&["acc-a", "acc-b"]);
+        let mut per_account_rps = HashMap::new();
+        per_account_rps.insert("acc-a@example.com".to_string(), 0.001);
+        manager
+            .update_rate_limit_config(AccountRateLimitConfig {
+                rps: None,
+                burst: Some(1),
+                per_account_rps,
+            })
+            .await;
+
+        // acc-a 仅有 1 次额度，之后的请求都应落到未限速的 acc-b
+        let mut seen_a = 0;
+        for _ in 0..4 {
+            let (_, _, email, _) = manager.get_token("gemini", true, None, None).await.unwrap();
+            if email == "acc-a@example.com" {
+                seen_a += 1;
+            }
+        }
+        assert!(seen_a <= 1);
+    }
+
+    #[tokio::test]
+    async fn test_open_circuit_account_is_skipped() {
+        let manager = make_manager(&["acc-a", "acc-b"]);
+        manager.mark_circuit_failure("acc-a@example.com", 401, "UNAUTHENTICATED");
+
+        for _ in 0..4 {
+            let (_, _, email, _) = manager.get_token("gemini", true, None, None).await.unwrap();
+            assert_eq!(email, "acc-b@example.com");
+        }
+
+        let states = manager.get_circuit_states();
+        assert_eq!(states[0].state, "open");
+        assert_eq!(states[0].consecutive_failures, 1);
+        assert_eq!(states[1].state, "closed");
+    }
+
+    #[tokio::test]
+    async fn test_circuit_half_open_then_closed_on_success() {
+        let manager = make_manager(&["acc-a"]);
+        manager.set_circuit_cooldown(0);
+        manager.mark_circuit_failure("acc-a", 401, "");
+
+        // 冷却结束后允许试探请求 (HalfOpen)
+        let (_, _, email, _) = manager.get_token("gemini", false, None, None).await.unwrap();
+        assert_eq!(email, "acc-a@example.com");
+        assert_eq!(manager.tokens.get("acc-a").unwrap().circuit_state, CircuitState::HalfOpen);
+
+        manager.mark_account_success(&email);
+        let entry = manager.tokens.get("acc-a").unwrap();
+        assert_eq!(entry.circuit_state, CircuitState::Closed);
+        assert_eq!(entry.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_rate_limited_403_is_not_hard_failure() {
+        assert!(is_hard_failure(401, ""));
+        assert!(is_hard_failure(403, "PERMISSION_DENIED"));
+        assert!(!is_hard_failure(403, "RESOURCE_EXHAUSTED: rate limit"));
+        assert!(!is_hard_failure(403, r#"{"error":{"status":"RATE_LIMIT_EXCEEDED"}}"#));
+        // 权限错误的响应体通常包含 generateContent，不能因其中的 "rate" 被当作限流
+        assert!(is_hard_failure(
+            403,
+            r#"{"error":{"code":403,"message":"Permission denied on resource for method generateContent","status":"PERMISSION_DENIED"}}"#
+        ));
+        assert!(!is_hard_failure(429, ""));
+    }
+
+    #[tokio::test]
+    async fn test_health_breaks_ties_by_error_rate() {
+        let manager = make_manager(&["acc-a", "acc-b"]);
+        manager.tokens.get_mut("acc-a").unwrap().health.error_rate_7d = 0.5;
+
+        let (_, _, email, _) = manager.get_token("gemini", false, None, None).await.unwrap();
+        assert_eq!(email, "acc-b@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_high_priority_prefers_lowest_latency() {
+        let manager = make_manager(&["acc-a", "acc-b", "acc-c", "acc-d", "acc-e"]);
+        manager.record_request_health("acc-a", true, 800.0);
+        manager.record_request_health("acc-b@example.com", true, 120.0);
+        manager.record_request_health("acc-c", true, 150.0);
+        manager.record_request_health("acc-d", true, 200.0);
+        // 健康度只标记待写回，由防抖任务批量落盘
+        assert_eq!(manager.dirty_health.lock().unwrap().len(), 4);
+
+        // 只在最快的 3 个账号之间分摊，最慢与无样本的账号不参与
+        let mut picked = HashSet::new();
+        for _ in 0..6 {
+            let (_, _, email, _) = manager
+                .get_token_with_priority("gemini", false, None, None, RequestPriority::High)
+                .await
+                .unwrap();
+            assert!(["acc-b", "acc-c", "acc-d"].iter().any(|id| email.starts_with(id)), "{}", email);
+            picked.insert(email);
+        }
+        assert_eq!(picked.len(), 3);
+
+        let stats = manager.get_latency_stats();
+        assert_eq!(stats.len(), 4);
+        assert_eq!(stats[0].account_id, "acc-a");
+        assert_eq!(stats[1].stats.p50_ms, 120.0);
+    }
+
+    #[tokio::test]
+    async fn test_allowlist_restricts_selection() {
+        let manager = make_manager(&["acc-a", "acc-b", "acc-c"]);
+        let allowlist = AccountAllowlist::new(["acc-c".to_string()]);
+
+        for _ in 0..3 {
+            let (_, _, email, _) = manager.get_token("gemini", true, None, Some(&allowlist)).await.unwrap();
+            assert_eq!(email, "acc-c@example.com");
+        }
+
+        // 白名单中的账号全部不可用时直接失败，不回退到其它账号
+        manager.mark_circuit_failure("acc-c", 401, "");
+        assert!(manager.get_token("gemini", true, None, Some(&allowlist)).await.is_err());
+        let empty = AccountAllowlist::new(["missing".to_string()]);
+        assert!(manager.get_token("gemini", false, None, Some(&empty)).await.is_err());
+    }
+
+    #[test]
+    fn test_frozen_account_detection() {
+        let token = crate::models::TokenData::new("access".to_string(), "refresh".to_string(), 3600, None, None, None);
+        let mut account = crate::models::Account::new("acc-a".to_string(), "a@example.com".to_string(), token);
+        assert!(!is_frozen_account(&serde_json::to_value(&account).unwrap()));
+
+        account.frozen = true;
+        assert!(is_frozen_account(&serde_json::to_value(&account).unwrap()));
+        // 旧版本账号文件没有 frozen 字段
+        assert!(!is_frozen_account(&serde_json::json!({"id": "acc-a", "email": "a@example.com"})));
+    }
+
+    #[test]
+    fn test_circuit_state_survives_restart() {
+        let dir = std::env::temp_dir().join(format!("token_state_test_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let manager = TokenManager::new(dir.clone());
+        manager.tokens.insert("acc-a".to_string(), make_token("acc-a"));
+        manager.mark_circuit_failure("acc-a", 401, "");
+        assert_eq!(manager.save_state().unwrap(), 1);
+
+        let restarted = TokenManager::new(dir.clone());
+        let circuits = restarted.load_persisted_circuits();
+        let (state, failures) = circuits.get("acc-a").unwrap();
+        assert!(matches!(state, CircuitState::Open { .. }));
+        assert_eq!(*failures, 1);
+
+        // 很久未刷新 token 的条目被清理
+        assert_eq!(restarted.cleanup_stale_state(0).unwrap(), 0);
+        let mut stale = token_state::load_state(&dir).unwrap().unwrap();
+        stale.tokens[0].refreshed_at -= 10;
+        token_state::save_state(&dir, &stale).unwrap();
+        assert_eq!(restarted.cleanup_stale_state(5).unwrap(), 1);
+        assert!(restarted.load_persisted_circuits().is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_weighted_round_robin_by_priority() {
+        let manager = make_manager(&["acc-a", "acc-b"]);
+        manager.tokens.get_mut("acc-a").unwrap().priority = 10;
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..30 {
+            let (_, _, email, _) = manager.get_token("gemini", true, None, None).await.unwrap();
+            *counts.entry(email).or_default() += 1;
+        }
+        assert_eq!(counts["acc-a@example.com"], 20);
+        assert_eq!(counts["acc-b@example.com"], 10);
+
+        // 熔断中的账号权重为 0
+        manager.mark_circuit_failure("acc-a", 401, "");
+        for _ in 0..5 {
+            let (_, _, email, _) = manager.get_token("gemini", true, None, None).await.unwrap();
+            assert_eq!(email, "acc-b@example.com");
+        }
+
+        assert_eq!(normalize_priority(None), 5);
+        assert_eq!(normalize_priority(Some(0)), 1);
+        assert_eq!(normalize_priority(Some(42)), 10);
+    }
+
+    #[test]
+    fn test_needs_proactive_refresh() {
+        let now = chrono::Utc::now().timestamp();
+        let mut token = make_token("acc-a");
+        token.timestamp = now + 3600;
+        assert!(!needs_proactive_refresh(&token, now));
+
+        token.timestamp = now + 120;
+        assert!(needs_proactive_refresh(&token, now));
+
+        // 刚被刷新过 (例如请求路径) 时跳过
+        token.last_proactive_refresh = Some(std::time::Instant::now());
+        assert!(!needs_proactive_refresh(&token, now));
+    }
+}
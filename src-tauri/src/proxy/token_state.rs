@@ -0,0 +1,149 @@
+// TokenManager 状态持久化
+// 停止反代服务时保存账号熔断状态 (不含 access/refresh token)，重启后恢复，避免坏账号立即重回调度池
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::proxy::token_manager::{CircuitState, ProxyToken};
+
+pub const PROXY_STATE_FILE: &str = "proxy_state.json";
+const PROXY_STATE_TMP_FILE: &str = ".proxy_state.json.tmp";
+/// 加载账号时丢弃超过该时长未刷新 token 的持久化条目
+pub const PROXY_STATE_MAX_AGE_SECS: u64 = 24 * 60 * 60;
+
+/// 单个账号的持久化状态
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PersistedToken {
+    pub account_id: String,
+    pub email: String,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub subscription_tier: Option<String>,
+    /// 最近一次刷新 token 的时间 (Unix 秒)
+    pub refreshed_at: i64,
+    pub consecutive_failures: u8,
+    /// "closed" | "open" | "half_open"
+    pub circuit_state: String,
+    /// 熔断结束时间 (Unix 秒，仅 open 状态)
+    #[serde(default)]
+    pub open_until: Option<i64>,
+}
+
+impl PersistedToken {
+    pub fn from_token(token: &ProxyToken, now: i64) -> Self {
+        let (circuit_state, open_until) = match token.circuit_state {
+            CircuitState::Closed => ("closed", None),
+            CircuitState::Open { until } => (
+                "open",
+                Some(now + until.saturating_duration_since(std::time::Instant::now()).as_secs() as i64),
+            ),
+            CircuitState::HalfOpen => ("half_open", None),
+        };
+        Self {
+            account_id: token.account_id.clone(),
+            email: token.email.clone(),
+            project_id: token.project_id.clone(),
+            subscription_tier: token.subscription_tier.clone(),
+            refreshed_at: token.timestamp - token.expires_in,
+            consecutive_failures: token.consecutive_failures,
+            circuit_state: circuit_state.to_string(),
+            open_until,
+        }
+    }
+
+    /// 还原熔断状态: 冷却期已过的 open 账号以 half_open 恢复，只允许试探请求
+    pub fn restore_circuit(&self, now: i64) -> CircuitState {
+        match self.circuit_state.as_str() {
+            "open" => match self.open_until {
+                Some(until) if until > now => CircuitState::Open {
+                    until: std::time::Instant::now() + std::time::Duration::from_secs((until - now) as u64),
+                },
+                _ => CircuitState::HalfOpen,
+            },
+            "half_open" => CircuitState::HalfOpen,
+            _ => CircuitState::Closed,
+        }
+    }
+
+    pub fn is_stale(&self, now: i64, max_age_secs: u64) -> bool {
+        now - self.refreshed_at > max_age_secs as i64
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PersistedState {
+    pub saved_at: i64,
+    pub tokens: Vec<PersistedToken>,
+}
+
+pub fn state_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(PROXY_STATE_FILE)
+}
+
+/// 读取持久化状态，文件不存在时返回 None
+pub fn load_state(data_dir: &Path) -> Result<Option<PersistedState>, String> {
+    let path = state_path(data_dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("读取反代状态失败: {}", e))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("解析反代状态失败: {}", e))
+}
+
+/// 原子写入: 先写临时文件再重命名
+pub fn save_state(data_dir: &Path, state: &PersistedState) -> Result<(), String> {
+    let tmp = data_dir.join(PROXY_STATE_TMP_FILE);
+    let content = serde_json::to_string_pretty(state).map_err(|e| format!("序列化反代状态失败: {}", e))?;
+    fs::write(&tmp, content).map_err(|e| format!("写入反代状态失败: {}", e))?;
+    fs::rename(&tmp, state_path(data_dir)).map_err(|e| format!("保存反代状态失败: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(circuit_state: &str, open_until: Option<i64>, refreshed_at: i64) -> PersistedToken {
+        PersistedToken {
+            account_id: "acc-a".to_string(),
+            email: "a@example.com".to_string(),
+            project_id: None,
+            subscription_tier: None,
+            refreshed_at,
+            consecutive_failures: 2,
+            circuit_state: circuit_state.to_string(),
+            open_until,
+        }
+    }
+
+    #[test]
+    fn test_restore_circuit_and_staleness() {
+        let now = 1_000_000;
+        assert!(matches!(entry("open", Some(now + 60), now).restore_circuit(now), CircuitState::Open { .. }));
+        assert_eq!(entry("open", Some(now - 1), now).restore_circuit(now), CircuitState::HalfOpen);
+        assert_eq!(entry("closed", None, now).restore_circuit(now), CircuitState::Closed);
+
+        assert!(entry("closed", None, now - 100).is_stale(now, 50));
+        assert!(!entry("closed", None, now - 10).is_stale(now, 50));
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("proxy_state_test_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        assert!(load_state(&dir).unwrap().is_none());
+
+        let state = PersistedState {
+            saved_at: 1,
+            tokens: vec![entry("open", Some(5), 0)],
+        };
+        save_state(&dir, &state).unwrap();
+        assert!(!dir.join(PROXY_STATE_TMP_FILE).exists());
+        assert_eq!(load_state(&dir).unwrap().unwrap().tokens, state.tokens);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,108 @@
+// Exponential Backoff
+// 重试退避: base_delay_ms * 2^attempt + jitter，上限 max_delay_ms
+
+use rand::Rng;
+use tokio::time::Duration;
+
+/// 指数退避参数 (由 ProxyConfig 的 retry_* 字段配置)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExponentialBackoff {
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub jitter_ms: u64,
+}
+
+impl ExponentialBackoff {
+    pub fn new(base_delay_ms: u64, max_delay_ms: u64, jitter_ms: u64) -> Self {
+        Self {
+            base_delay_ms,
+            max_delay_ms,
+            jitter_ms,
+        }
+    }
+
+    pub fn from_proxy_config(config: &crate::proxy::config::ProxyConfig) -> Self {
+        Self::new(
+            config.retry_base_delay_ms,
+            config.retry_max_delay_ms,
+            config.retry_jitter_ms,
+        )
+    }
+
+    /// 是否为需要退避重试的状态码
+    pub fn is_retryable_status(status: u16) -> bool {
+        matches!(status, 429 | 500 | 502 | 503)
+    }
+
+    /// 不含抖动的延迟: base * 2^attempt，上限 max
+    pub fn base_delay_ms_for(&self, attempt: usize) -> u64 {
+        let factor = 1u64.checked_shl(attempt.min(63) as u32).unwrap_or(u64::MAX);
+        self.base_delay_ms
+            .saturating_mul(factor)
+            .min(self.max_delay_ms)
+    }
+
+    /// 第 `attempt` 次重试前的等待时间 (含随机抖动，上限 max)
+    pub fn delay_for(&self, attempt: usize) -> Duration {
+        let jitter = if self.jitter_ms > 0 {
+            rand::thread_rng().gen_range(0..=self.jitter_ms)
+        } else {
+            0
+        };
+        let delay_ms = self
+            .base_delay_ms_for(attempt)
+            .saturating_add(jitter)
+            .min(self.max_delay_ms);
+        Duration::from_millis(delay_ms)
+    }
+
+    /// 按第 `attempt` 次重试的退避时间等待，返回实际等待时长
+    pub async fn wait(&self, attempt: usize) -> Duration {
+        let delay = self.delay_for(attempt);
+        tokio::time::sleep(delay).await;
+        delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delays_increase_monotonically_and_cap() {
+        let backoff = ExponentialBackoff::new(100, 1000, 0);
+        let delays: Vec<u64> = (0..8).map(|a| backoff.delay_for(a).as_millis() as u64).collect();
+
+        assert_eq!(delays[..5], [100, 200, 400, 800, 1000]);
+        assert!(delays.windows(2).all(|w| w[0] <= w[1]));
+        assert!(delays.iter().all(|d| *d <= 1000));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let backoff = ExponentialBackoff::new(100, 10_000, 50);
+        for attempt in 0..4 {
+            let base = backoff.base_delay_ms_for(attempt);
+            let delay = backoff.delay_for(attempt).as_millis() as u64;
+            assert!(delay >= base && delay <= base + 50);
+        }
+        // 抖动也不能突破上限
+        let capped = ExponentialBackoff::new(1000, 1000, 500);
+        assert_eq!(capped.delay_for(3).as_millis(), 1000);
+    }
+
+    #[test]
+    fn test_large_attempt_does_not_overflow() {
+        let backoff = ExponentialBackoff::new(u64::MAX / 2, u64::MAX, 0);
+        assert_eq!(backoff.base_delay_ms_for(100), u64::MAX);
+    }
+
+    #[test]
+    fn test_retryable_status() {
+        for status in [429, 500, 502, 503] {
+            assert!(ExponentialBackoff::is_retryable_status(status));
+        }
+        assert!(!ExponentialBackoff::is_retryable_status(400));
+        assert!(!ExponentialBackoff::is_retryable_status(404));
+    }
+}
@@ -1,46 +1,133 @@
 // 上游客户端实现
 // 基于高性能通讯接口封装
 
+use dashmap::DashMap;
 use reqwest::{header, Client, Response, StatusCode};
 use serde_json::Value;
 use tokio::time::Duration;
 
 // Cloud Code v1internal endpoints (fallback order: prod → daily)
 // 优先使用稳定的 prod 端点，避免影响缓存命中率
-const V1_INTERNAL_BASE_URL_PROD: &str = "https://cloudcode-pa.googleapis.com/v1internal";
+pub(crate) const V1_INTERNAL_BASE_URL_PROD: &str = "https://cloudcode-pa.googleapis.com/v1internal";
 const V1_INTERNAL_BASE_URL_DAILY: &str = "https://daily-cloudcode-pa.sandbox.googleapis.com/v1internal";
 const V1_INTERNAL_BASE_URL_FALLBACKS: [&str; 2] = [
     V1_INTERNAL_BASE_URL_PROD,   // 优先使用生产环境（稳定）
     V1_INTERNAL_BASE_URL_DAILY,  // 备用测试环境（新功能）
 ];
 
+// Google File API (OpenAI /v1/files 兼容)
+const GENERATIVE_LANGUAGE_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+const FILE_API_UPLOAD_URL: &str = "https://generativelanguage.googleapis.com/upload/v1beta/files";
+
+/// HTTP 连接池 / 保活 / 超时参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpstreamClientConfig {
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout: Duration,
+    pub tcp_keepalive: Duration,
+    pub http2_keep_alive_interval: Duration,
+    /// 为 false 时由 TLS ALPN 协商 HTTP/2
+    pub http2_prior_knowledge: bool,
+    pub connect_timeout: Duration,
+    /// 两次读取之间的最长等待时间 (对流式响应同样生效)
+    pub read_timeout: Duration,
+}
+
+impl Default for UpstreamClientConfig {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: 10,
+            pool_idle_timeout: Duration::from_secs(90),
+            tcp_keepalive: Duration::from_secs(30),
+            http2_keep_alive_interval: Duration::from_secs(20),
+            http2_prior_knowledge: false,
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(120),
+        }
+    }
+}
+
+impl UpstreamClientConfig {
+    pub fn from_proxy_config(config: &crate::proxy::config::ProxyConfig) -> Self {
+        Self {
+            pool_max_idle_per_host: config.upstream_pool_max_idle_per_host,
+            pool_idle_timeout: Duration::from_secs(config.upstream_pool_idle_timeout_secs),
+            tcp_keepalive: Duration::from_secs(config.upstream_tcp_keepalive_secs),
+            http2_keep_alive_interval: Duration::from_secs(config.upstream_http2_keep_alive_interval_secs),
+            http2_prior_knowledge: config.upstream_http2_prior_knowledge,
+            connect_timeout: Duration::from_secs(config.upstream_connect_timeout_secs),
+            read_timeout: Duration::from_secs(config.upstream_read_timeout_secs),
+        }
+    }
+}
+
 pub struct UpstreamClient {
     http_client: Client,
+    // 账号级代理对应的客户端缓存 (key: 代理 URL)，避免每次请求重建连接池
+    proxy_clients: DashMap<String, Client>,
+    config: UpstreamClientConfig,
 }
 
 impl UpstreamClient {
-    pub fn new(proxy_config: Option<crate::proxy::config::UpstreamProxyConfig>) -> Self {
+    pub fn new(
+        proxy_config: Option<crate::proxy::config::UpstreamProxyConfig>,
+        config: UpstreamClientConfig,
+    ) -> Self {
+        let proxy_url = proxy_config
+            .filter(|c| c.enabled && !c.url.is_empty())
+            .map(|c| c.url);
+
+        Self {
+            http_client: Self::build_client(proxy_url.as_deref(), &config),
+            proxy_clients: DashMap::new(),
+            config,
+        }
+    }
+
+    fn client_builder(config: &UpstreamClientConfig) -> reqwest::ClientBuilder {
         let mut builder = Client::builder()
             // Connection settings (优化连接复用，减少建立开销)
-            .connect_timeout(Duration::from_secs(20))
-            .pool_max_idle_per_host(16)                  // 每主机最多 16 个空闲连接
-            .pool_idle_timeout(Duration::from_secs(90))  // 空闲连接保持 90 秒
-            .tcp_keepalive(Duration::from_secs(60))      // TCP 保活探测 60 秒
+            .connect_timeout(config.connect_timeout)
+            .read_timeout(config.read_timeout)
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .pool_idle_timeout(config.pool_idle_timeout)
+            .tcp_keepalive(config.tcp_keepalive)
+            // HTTP/2 PING 保活，空闲连接同样发送，避免被中间设备静默断开
+            .http2_keep_alive_interval(config.http2_keep_alive_interval)
+            .http2_keep_alive_while_idle(true)
             .timeout(Duration::from_secs(600))
             .user_agent("antigravity/1.11.9 windows/amd64");
+        if config.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        builder
+    }
 
-        if let Some(config) = proxy_config {
-            if config.enabled && !config.url.is_empty() {
-                if let Ok(proxy) = reqwest::Proxy::all(&config.url) {
-                    builder = builder.proxy(proxy);
-                    tracing::info!("UpstreamClient enabled proxy: {}", config.url);
-                }
+    fn build_client(proxy_url: Option<&str>, config: &UpstreamClientConfig) -> Client {
+        let mut builder = Self::client_builder(config);
+
+        if let Some(url) = proxy_url {
+            if let Ok(proxy) = reqwest::Proxy::all(url) {
+                builder = builder.proxy(proxy);
+                tracing::info!("UpstreamClient enabled proxy: {}", url);
             }
         }
 
-        let http_client = builder.build().expect("Failed to create HTTP client");
+        builder.build().expect("Failed to create HTTP client")
+    }
 
-        Self { http_client }
+    /// 选择请求使用的 HTTP 客户端
+    ///
+    /// 账号配置了独立代理时使用 (并缓存) 对应的客户端，否则使用全局代理客户端
+    fn client_for(&self, account_proxy: Option<&str>) -> Client {
+        match account_proxy.map(str::trim).filter(|u| !u.is_empty()) {
+            Some(url) => self
+                .proxy_clients
+                .entry(url.to_string())
+                .or_insert_with(|| Self::build_client(Some(url), &self.config))
+                .clone(),
+            None => self.http_client.clone(),
+        }
     }
 
     /// 构建 v1internal URL
@@ -71,13 +158,17 @@ impl UpstreamClient {
     /// 调用 v1internal API（基础方法）
     /// 
     /// 发起基础网络请求，支持多端点自动 Fallback
+    /// `account_proxy` 为账号级上游代理，None 时使用全局代理
     pub async fn call_v1_internal(
         &self,
         method: &str,
         access_token: &str,
         body: Value,
         query_string: Option<&str>,
+        account_proxy: Option<&str>,
     ) -> Result<Response, String> {
+        let http_client = self.client_for(account_proxy);
+
         // 构建 Headers (所有端点复用)
         let mut headers = header::HeaderMap::new();
         headers.insert(
@@ -93,6 +184,11 @@ impl UpstreamClient {
             header::USER_AGENT,
             header::HeaderValue::from_static("antigravity/1.11.9 windows/amd64"),
         );
+        if let Some(request_id) = crate::proxy::middleware::request_id::upstream_request_id() {
+            if let Ok(value) = header::HeaderValue::from_str(&request_id) {
+                headers.insert(crate::proxy::middleware::request_id::UPSTREAM_REQUEST_ID_HEADER, value);
+            }
+        }
 
         let mut last_err: Option<String> = None;
 
@@ -101,8 +197,7 @@ impl UpstreamClient {
             let url = Self::build_url(base_url, method, query_string);
             let has_next = idx + 1 < V1_INTERNAL_BASE_URL_FALLBACKS.len();
 
-            let response = self
-                .http_client
+            let response = http_client
                 .post(&url)
                 .headers(headers.clone())
                 .json(&body)
@@ -263,6 +358,99 @@ impl UpstreamClient {
 
         Err(last_err.unwrap_or_else(|| "All endpoints failed".to_string()))
     }
+
+    /// 上传文件到 Google File API (resumable 协议: 先申请上传地址，再一次性上传并 finalize)
+    ///
+    /// 返回上游响应，成功时响应体为 `{"file": {...}}`
+    pub async fn upload_file(
+        &self,
+        access_token: &str,
+        account_proxy: Option<&str>,
+        display_name: &str,
+        mime_type: &str,
+        data: bytes::Bytes,
+    ) -> Result<Response, String> {
+        let http_client = self.client_for(account_proxy);
+
+        let start = http_client
+            .post(FILE_API_UPLOAD_URL)
+            .bearer_auth(access_token)
+            .header("X-Goog-Upload-Protocol", "resumable")
+            .header("X-Goog-Upload-Command", "start")
+            .header("X-Goog-Upload-Header-Content-Length", data.len().to_string())
+            .header("X-Goog-Upload-Header-Content-Type", mime_type)
+            .json(&serde_json::json!({ "file": { "display_name": display_name } }))
+            .send()
+            .await
+            .map_err(|e| format!("File upload start failed: {}", e))?;
+
+        if !start.status().is_success() {
+            return Ok(start);
+        }
+        let upload_url = start
+            .headers()
+            .get("x-goog-upload-url")
+            .and_then(|v| v.to_str().ok())
+            .ok_or("Upstream did not return an upload URL")?
+            .to_string();
+
+        http_client
+            .post(&upload_url)
+            .header("X-Goog-Upload-Offset", "0")
+            .header("X-Goog-Upload-Command", "upload, finalize")
+            .header(header::CONTENT_LENGTH, data.len())
+            .body(data)
+            .send()
+            .await
+            .map_err(|e| format!("File upload failed: {}", e))
+    }
+
+    /// 查询 Google File API 文件 (`name` 形如 `files/xxx`)
+    pub async fn get_file(
+        &self,
+        access_token: &str,
+        account_proxy: Option<&str>,
+        name: &str,
+    ) -> Result<Response, String> {
+        self.client_for(account_proxy)
+            .get(format!("{}/{}", GENERATIVE_LANGUAGE_BASE_URL, name))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .map_err(|e| format!("File lookup failed: {}", e))
+    }
+
+    /// 调用 Gemini API batchEmbedContents (一次请求生成多个输入的向量)
+    pub async fn batch_embed_contents(
+        &self,
+        access_token: &str,
+        account_proxy: Option<&str>,
+        model: &str,
+        body: &Value,
+    ) -> Result<Response, String> {
+        self.client_for(account_proxy)
+            .post(format!("{}/models/{}:batchEmbedContents", GENERATIVE_LANGUAGE_BASE_URL, model))
+            .bearer_auth(access_token)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| format!("Embedding request failed: {}", e))
+    }
+
+    /// 删除 Google File API 文件
+    pub async fn delete_file(
+        &self,
+        access_token: &str,
+        account_proxy: Option<&str>,
+        name: &str,
+    ) -> Result<Response, String> {
+        self.client_for(account_proxy)
+            .delete(format!("{}/{}", GENERATIVE_LANGUAGE_BASE_URL, name))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .map_err(|e| format!("File delete failed: {}", e))
+    }
 }
 
 #[cfg(test)]
@@ -286,4 +474,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_upstream_client_config_from_proxy_config() {
+        let mut proxy = crate::proxy::config::ProxyConfig::default();
+        assert_eq!(UpstreamClientConfig::from_proxy_config(&proxy), UpstreamClientConfig::default());
+
+        proxy.upstream_pool_max_idle_per_host = 4;
+        proxy.upstream_read_timeout_secs = 30;
+        proxy.upstream_http2_prior_knowledge = true;
+        let config = UpstreamClientConfig::from_proxy_config(&proxy);
+        assert_eq!(config.pool_max_idle_per_host, 4);
+        assert_eq!(config.read_timeout, Duration::from_secs(30));
+        assert!(config.http2_prior_knowledge);
+        assert_eq!(config.connect_timeout, Duration::from_secs(10));
+        assert!(UpstreamClient::client_builder(&config).build().is_ok());
+    }
+
+    #[test]
+    fn test_client_for_caches_account_proxy_clients() {
+        let client = UpstreamClient::new(None, UpstreamClientConfig::default());
+        client.client_for(None);
+        client.client_for(Some("  "));
+        assert!(client.proxy_clients.is_empty());
+
+        client.client_for(Some("socks5://127.0.0.1:1080"));
+        client.client_for(Some("socks5://127.0.0.1:1080"));
+        client.client_for(Some("http://10.0.0.1:3128"));
+        assert_eq!(client.proxy_clients.len(), 2);
+    }
 }
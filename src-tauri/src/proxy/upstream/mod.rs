@@ -1,6 +1,9 @@
 // Upstream Module - Upstream Client
 // Corresponds to upstream communication interface
 
+pub mod backoff;
 pub mod client;
 pub mod models;
 pub mod retry;
+pub mod sse_parser;
+pub mod stream_timeout;
@@ -0,0 +1,180 @@
+// SSE 事件解析
+// 按空行 (\r\n\r\n 或 \n\n) 切分完整事件后再解析，避免多行 data 字段或被 TCP 分片截断的 JSON 解析失败
+
+use bytes::{Bytes, BytesMut};
+use futures::Stream;
+use pin_project::pin_project;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// 一个完整的 SSE 事件
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    /// 多个 data 行按规范以 \n 连接
+    pub data: String,
+    pub id: Option<String>,
+}
+
+impl SseEvent {
+    /// 解析单个事件块 (不含结尾空行)，没有任何字段时返回 None
+    fn parse(block: &[u8]) -> Option<Self> {
+        let text = String::from_utf8_lossy(block);
+        let mut event = SseEvent::default();
+        let mut data_lines: Vec<&str> = Vec::new();
+
+        for line in text.split(['\n', '\r']) {
+            // 空行与注释行 (: keep-alive)
+            if line.is_empty() || line.starts_with(':') {
+                continue;
+            }
+            let (field, value) = match line.split_once(':') {
+                Some((f, v)) => (f, v.strip_prefix(' ').unwrap_or(v)),
+                None => (line, ""),
+            };
+            match field {
+                "data" => data_lines.push(value),
+                "event" => event.event = Some(value.to_string()),
+                "id" => event.id = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        if data_lines.is_empty() && event.event.is_none() && event.id.is_none() {
+            return None;
+        }
+        event.data = data_lines.join("\n");
+        Some(event)
+    }
+}
+
+/// 查找第一个事件边界，返回 (事件结束位置, 边界长度)
+fn find_event_boundary(buf: &[u8]) -> Option<(usize, usize)> {
+    const BOUNDARIES: [&[u8]; 4] = [b"\r\n\r\n", b"\n\r\n", b"\r\n\n", b"\n\n"];
+    (0..buf.len()).find_map(|i| {
+        BOUNDARIES
+            .iter()
+            .find(|b| buf[i..].starts_with(b))
+            .map(|b| (i, b.len()))
+    })
+}
+
+/// 将字节流转换为 SSE 事件流，上游错误原样透传
+#[pin_project]
+pub struct SseParser<S> {
+    #[pin]
+    inner: S,
+    buffer: BytesMut,
+    pending: VecDeque<SseEvent>,
+    finished: bool,
+}
+
+impl<S> SseParser<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            buffer: BytesMut::new(),
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+}
+
+/// 从缓冲区中取出所有完整事件
+fn drain_events(buffer: &mut BytesMut, pending: &mut VecDeque<SseEvent>) {
+    while let Some((end, len)) = find_event_boundary(buffer) {
+        let block = buffer.split_to(end + len);
+        pending.extend(SseEvent::parse(&block[..end]));
+    }
+}
+
+impl<S, E> Stream for SseParser<S>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    type Item = Result<SseEvent, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            if let Some(event) = this.pending.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            if *this.finished {
+                return Poll::Ready(None);
+            }
+            match this.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(bytes))) => {
+                    this.buffer.extend_from_slice(&bytes);
+                    drain_events(this.buffer, this.pending);
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => {
+                    // 上游可能省略最后一个事件的结尾空行
+                    *this.finished = true;
+                    let rest = this.buffer.split();
+                    this.pending.extend(SseEvent::parse(&rest));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    async fn parse_chunks(chunks: &[&'static str]) -> Vec<SseEvent> {
+        let stream = futures::stream::iter(
+            chunks
+                .iter()
+                .map(|c| Ok::<Bytes, String>(Bytes::from_static(c.as_bytes())))
+                .collect::<Vec<_>>(),
+        );
+        SseParser::new(stream)
+            .map(|e| e.unwrap())
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_event_split_across_chunks() {
+        let events = parse_chunks(&[
+            "data: {\"candidates\":[{\"content\":",
+            "{\"parts\":[{\"text\":\"hi\"}]}}]}\r\n\r",
+            "\ndata: [DONE]\r\n\r\n",
+        ])
+        .await;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].data, "{\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"hi\"}]}}]}");
+        assert!(serde_json::from_str::<serde_json::Value>(&events[0].data).is_ok());
+        assert_eq!(events[1].data, "[DONE]");
+    }
+
+    #[tokio::test]
+    async fn test_multiline_data_and_fields() {
+        let events = parse_chunks(&[
+            ": keep-alive\n\n",
+            "event: message\nid: 7\ndata: {\"a\":\ndata: 1}\n\n",
+            "data: tail-without-blank-line\n",
+        ])
+        .await;
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("message".to_string()),
+                    data: "{\"a\":\n1}".to_string(),
+                    id: Some("7".to_string()),
+                },
+                SseEvent {
+                    data: "tail-without-blank-line".to_string(),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+}
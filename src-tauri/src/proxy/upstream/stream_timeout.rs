@@ -0,0 +1,166 @@
+// 上游流式响应超时
+// 上游发送部分数据后静默 (TCP 半开、网络分区) 时，流式处理器会永久挂起。
+// 每收到一个 chunk 重置空闲计时；超时或超过总时长后结束上游流，并由调用方向客户端发送 SSE 错误事件
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Claude / OpenAI 客户端在超时后收到的 SSE 错误事件
+pub const STREAM_TIMEOUT_EVENT: &str =
+    "data: {\"type\":\"error\",\"error\":{\"type\":\"timeout\",\"message\":\"Upstream connection timed out\"}}\n\n";
+
+/// 流式响应超时参数
+#[derive(Debug, Clone, Copy)]
+pub struct StreamTimeouts {
+    /// 两个 chunk 之间的最长间隔
+    pub inactivity: Duration,
+    /// 从首个字节到响应结束的总时长上限，None 表示不限制
+    pub total: Option<Duration>,
+}
+
+impl StreamTimeouts {
+    pub fn from_proxy_config(config: &crate::proxy::config::ProxyConfig) -> Self {
+        Self {
+            inactivity: Duration::from_secs(config.stream_inactivity_timeout_secs.max(1)),
+            total: Some(config.stream_total_timeout_secs)
+                .filter(|s| *s > 0)
+                .map(Duration::from_secs),
+        }
+    }
+}
+
+impl Default for StreamTimeouts {
+    fn default() -> Self {
+        Self {
+            inactivity: Duration::from_secs(60),
+            total: None,
+        }
+    }
+}
+
+/// 上游流是否因超时被结束
+#[derive(Debug, Clone, Default)]
+pub struct TimeoutSignal(Arc<AtomicBool>);
+
+impl TimeoutSignal {
+    pub fn timed_out(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// 为上游字节流加上空闲超时与总时长限制，超时后流正常结束并置位 [`TimeoutSignal`]
+pub fn with_stream_timeouts<S, E>(
+    stream: S,
+    timeouts: StreamTimeouts,
+) -> (Pin<Box<dyn Stream<Item = Result<Bytes, E>> + Send>>, TimeoutSignal)
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: Send + 'static,
+{
+    let signal = TimeoutSignal::default();
+    let flag = signal.0.clone();
+    let wrapped = async_stream::stream! {
+        let mut stream = Box::pin(stream);
+        let deadline = timeouts.total.map(|d| tokio::time::Instant::now() + d);
+        loop {
+            let wait = match deadline {
+                Some(d) => timeouts.inactivity.min(d.saturating_duration_since(tokio::time::Instant::now())),
+                None => timeouts.inactivity,
+            };
+            match tokio::time::timeout(wait, stream.next()).await {
+                Ok(Some(item)) => yield item,
+                Ok(None) => break,
+                Err(_) => {
+                    tracing::warn!("[Stream] Upstream stream timed out after {:?} without data", wait);
+                    flag.store(true, Ordering::SeqCst);
+                    break;
+                }
+            }
+        }
+    };
+    (Box::pin(wrapped), signal)
+}
+
+/// 超时后在下一个输出 chunk (通常是 [DONE] / message_stop 等结束事件) 之前插入错误事件
+pub fn inject_timeout_event<S, E>(stream: S, signal: TimeoutSignal) -> impl Stream<Item = Result<Bytes, E>> + Send
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: Send + 'static,
+{
+    async_stream::stream! {
+        let mut stream = Box::pin(stream);
+        let mut emitted = false;
+        while let Some(item) = stream.next().await {
+            if !emitted && signal.timed_out() {
+                emitted = true;
+                yield Ok(Bytes::from_static(STREAM_TIMEOUT_EVENT.as_bytes()));
+            }
+            yield item;
+        }
+        if !emitted && signal.timed_out() {
+            yield Ok(Bytes::from_static(STREAM_TIMEOUT_EVENT.as_bytes()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_inactivity_timeout_ends_stream_and_injects_error() {
+        let upstream = async_stream::stream! {
+            yield Ok::<Bytes, String>(Bytes::from_static(b"a"));
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            yield Ok(Bytes::from_static(b"b"));
+            // 之后上游静默
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+            yield Ok(Bytes::from_static(b"never"));
+        };
+        let timeouts = StreamTimeouts { inactivity: Duration::from_millis(200), total: None };
+        let (stream, signal) = with_stream_timeouts(upstream, timeouts);
+
+        // 模拟映射层: 上游结束后输出结束事件
+        let mapped = stream.chain(futures::stream::once(async { Ok(Bytes::from_static(b"[DONE]")) }));
+        let out: Vec<Bytes> = inject_timeout_event(mapped, signal.clone())
+            .map(|r| r.unwrap())
+            .collect()
+            .await;
+
+        assert!(signal.timed_out());
+        assert_eq!(
+            out,
+            vec![
+                Bytes::from_static(b"a"),
+                Bytes::from_static(b"b"),
+                Bytes::from_static(STREAM_TIMEOUT_EVENT.as_bytes()),
+                Bytes::from_static(b"[DONE]"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_total_timeout_and_normal_completion() {
+        // 持续有数据但超过总时长
+        let chatty = async_stream::stream! {
+            loop {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                yield Ok::<Bytes, String>(Bytes::from_static(b"x"));
+            }
+        };
+        let timeouts = StreamTimeouts { inactivity: Duration::from_secs(60), total: Some(Duration::from_millis(100)) };
+        let (stream, signal) = with_stream_timeouts(chatty, timeouts);
+        assert!(stream.count().await > 0);
+        assert!(signal.timed_out());
+
+        let finite = futures::stream::iter(vec![Ok::<Bytes, String>(Bytes::from_static(b"x"))]);
+        let (stream, signal) = with_stream_timeouts(finite, StreamTimeouts::default());
+        let out: Vec<_> = inject_timeout_event(stream, signal.clone()).collect().await;
+        assert_eq!(out.len(), 1);
+        assert!(!signal.timed_out());
+    }
+}
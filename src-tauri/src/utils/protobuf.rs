@@ -9,103 +9,175 @@ pub fn encode_varint(mut value: u64) -> Vec<u8> {
     buf
 }
 
-/// Read Protobuf Varint
-pub fn read_varint(data: &[u8], offset: usize) -> Result<(u64, usize), String> {
-    let mut result = 0u64;
-    let mut shift = 0;
-    let mut pos = offset;
+/// Maximum encoded length of a varint (64-bit value)
+const MAX_VARINT_BYTES: usize = 10;
+/// Maximum accepted length of a length-delimited field
+const MAX_LENGTH_DELIMITED: u64 = 64 * 1024 * 1024;
+/// Maximum nested message depth for `find_nested_field`
+pub const MAX_NESTING_DEPTH: usize = 16;
+
+/// Protobuf parse error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtobufError {
+    /// Data ended before the value at `offset` was complete
+    UnexpectedEof { offset: usize },
+    /// Varint at `offset` is longer than 10 bytes
+    VarintOverflow { offset: usize },
+    /// Length-delimited field at `offset` exceeds the 64 MB limit
+    LengthTooLarge { offset: usize, length: u64 },
+    /// Unsupported wire type (including deprecated groups)
+    InvalidWireType { offset: usize, wire_type: u8 },
+    /// Field path is deeper than `MAX_NESTING_DEPTH`
+    DepthExceeded { depth: usize },
+}
 
-    loop {
-        if pos >= data.len() {
-            return Err("Incomplete data".to_string());
+impl std::fmt::Display for ProtobufError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedEof { offset } => write!(f, "Incomplete data at offset {}", offset),
+            Self::VarintOverflow { offset } => write!(f, "Varint too long at offset {}", offset),
+            Self::LengthTooLarge { offset, length } => {
+                write!(f, "Field length {} too large at offset {}", length, offset)
+            }
+            Self::InvalidWireType { offset, wire_type } => {
+                write!(f, "Unknown wire_type {} at offset {}", wire_type, offset)
+            }
+            Self::DepthExceeded { depth } => {
+                write!(f, "Nesting depth {} exceeds limit {}", depth, MAX_NESTING_DEPTH)
+            }
         }
-        let byte = data[pos];
-        result |= ((byte & 0x7F) as u64) << shift;
-        pos += 1;
+    }
+}
+
+impl std::error::Error for ProtobufError {}
+
+impl From<ProtobufError> for String {
+    fn from(e: ProtobufError) -> Self {
+        e.to_string()
+    }
+}
+
+/// Read Protobuf Varint (up to 10 bytes)
+pub fn read_varint(data: &[u8], offset: usize) -> Result<(u64, usize), ProtobufError> {
+    let mut result = 0u64;
+    for i in 0..MAX_VARINT_BYTES {
+        let byte = *data
+            .get(offset + i)
+            .ok_or(ProtobufError::UnexpectedEof { offset })?;
+        result |= ((byte & 0x7F) as u64) << (7 * i);
         if byte & 0x80 == 0 {
-            break;
+            return Ok((result, offset + i + 1));
         }
-        shift += 7;
     }
+    Err(ProtobufError::VarintOverflow { offset })
+}
 
-    Ok((result, pos))
+/// Return the end offset of `len` bytes starting at `offset`
+fn checked_end(data: &[u8], offset: usize, len: usize) -> Result<usize, ProtobufError> {
+    offset
+        .checked_add(len)
+        .filter(|end| *end <= data.len())
+        .ok_or(ProtobufError::UnexpectedEof { offset })
+}
+
+/// Read a length-delimited field body, returning `(content_range_start, end)`
+fn read_length_delimited(data: &[u8], offset: usize) -> Result<(usize, usize), ProtobufError> {
+    let (length, content_offset) = read_varint(data, offset)?;
+    if length > MAX_LENGTH_DELIMITED {
+        return Err(ProtobufError::LengthTooLarge { offset, length });
+    }
+    let end = checked_end(data, content_offset, length as usize)?;
+    Ok((content_offset, end))
 }
 
 /// Skip Protobuf field
-pub fn skip_field(data: &[u8], offset: usize, wire_type: u8) -> Result<usize, String> {
+pub fn skip_field(data: &[u8], offset: usize, wire_type: u8) -> Result<usize, ProtobufError> {
     match wire_type {
-        0 => {
-            // Varint
-            let (_, new_offset) = read_varint(data, offset)?;
-            Ok(new_offset)
-        }
-        1 => {
-            // 64-bit
-            Ok(offset + 8)
-        }
-        2 => {
-            // Length-delimited
-            let (length, content_offset) = read_varint(data, offset)?;
-            Ok(content_offset + length as usize)
-        }
-        5 => {
-            // 32-bit
-            Ok(offset + 4)
-        }
-        _ => Err(format!("Unknown wire_type: {}", wire_type)),
+        // Varint
+        0 => read_varint(data, offset).map(|(_, end)| end),
+        // 64-bit
+        1 => checked_end(data, offset, 8),
+        // Length-delimited
+        2 => read_length_delimited(data, offset).map(|(_, end)| end),
+        // 32-bit
+        5 => checked_end(data, offset, 4),
+        _ => Err(ProtobufError::InvalidWireType { offset, wire_type }),
     }
 }
 
+/// Read a field tag, returning `(field_num, wire_type, value_offset)`
+fn read_tag(data: &[u8], offset: usize) -> Result<(u32, u8, usize), ProtobufError> {
+    let (tag, value_offset) = read_varint(data, offset)?;
+    Ok(((tag >> 3) as u32, (tag & 7) as u8, value_offset))
+}
+
 /// Remove specified Protobuf field
-pub fn remove_field(data: &[u8], field_num: u32) -> Result<Vec<u8>, String> {
+pub fn remove_field(data: &[u8], field_num: u32) -> Result<Vec<u8>, ProtobufError> {
     let mut result = Vec::new();
     let mut offset = 0;
 
     while offset < data.len() {
-        let start_offset = offset;
-        let (tag, new_offset) = read_varint(data, offset)?;
-        let wire_type = (tag & 7) as u8;
-        let current_field = (tag >> 3) as u32;
-
-        if current_field == field_num {
-            // Skip this field
-            offset = skip_field(data, new_offset, wire_type)?;
-        } else {
-            // Keep other fields
-            let next_offset = skip_field(data, new_offset, wire_type)?;
-            result.extend_from_slice(&data[start_offset..next_offset]);
-            offset = next_offset;
+        let (current_field, wire_type, value_offset) = read_tag(data, offset)?;
+        let next_offset = skip_field(data, value_offset, wire_type)?;
+        // Keep other fields
+        if current_field != field_num {
+            result.extend_from_slice(&data[offset..next_offset]);
         }
+        offset = next_offset;
     }
 
     Ok(result)
 }
 
-/// Find specified Protobuf field content (Length-Delimited only)
-pub fn find_field(data: &[u8], target_field: u32) -> Result<Option<Vec<u8>>, String> {
+/// Collect the contents of every length-delimited occurrence of `target_field`
+fn collect_field(data: &[u8], target_field: u32) -> Result<Vec<&[u8]>, ProtobufError> {
+    let mut found = Vec::new();
     let mut offset = 0;
 
     while offset < data.len() {
-        let (tag, new_offset) = match read_varint(data, offset) {
-            Ok(v) => v,
-            Err(_) => break, // Data incomplete, stop
-        };
-
-        let wire_type = (tag & 7) as u8;
-        let field_num = (tag >> 3) as u32;
-
+        let (field_num, wire_type, value_offset) = read_tag(data, offset)?;
         if field_num == target_field && wire_type == 2 {
-            let (length, content_offset) = read_varint(data, new_offset)?;
-            return Ok(Some(
-                data[content_offset..content_offset + length as usize].to_vec(),
-            ));
+            let (start, end) = read_length_delimited(data, value_offset)?;
+            found.push(&data[start..end]);
+            offset = end;
+        } else {
+            offset = skip_field(data, value_offset, wire_type)?;
         }
+    }
+
+    Ok(found)
+}
 
-        // Skip field
-        offset = skip_field(data, new_offset, wire_type)?;
+/// Find specified Protobuf field content (Length-Delimited only)
+///
+/// Repeated fields return the last occurrence.
+pub fn find_field(data: &[u8], target_field: u32) -> Result<Option<Vec<u8>>, ProtobufError> {
+    find_nested_field(data, &[target_field])
+}
+
+/// Find a length-delimited field by path through nested messages, e.g. `&[6, 3]`
+///
+/// An embedded message split across several occurrences is merged (concatenated)
+/// before descending, matching protobuf merge semantics; the final field returns
+/// its last occurrence.
+pub fn find_nested_field(data: &[u8], path: &[u32]) -> Result<Option<Vec<u8>>, ProtobufError> {
+    if path.len() > MAX_NESTING_DEPTH {
+        return Err(ProtobufError::DepthExceeded { depth: path.len() });
     }
+    let Some((&last, parents)) = path.split_last() else {
+        return Ok(None);
+    };
 
-    Ok(None)
+    let mut message = data.to_vec();
+    for &field in parents {
+        let parts = collect_field(&message, field)?;
+        if parts.is_empty() {
+            return Ok(None);
+        }
+        message = parts.concat();
+    }
+
+    Ok(collect_field(&message, last)?.last().map(|v| v.to_vec()))
 }
 
 /// Create OAuthTokenInfo (Field 6)
@@ -174,3 +246,78 @@ pub fn create_oauth_field(access_token: &str, refresh_token: &str, expiry: i64)
 
     field6
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn length_delimited(field: u32, content: &[u8]) -> Vec<u8> {
+        let mut f = encode_varint(((field as u64) << 3) | 2);
+        f.extend(encode_varint(content.len() as u64));
+        f.extend_from_slice(content);
+        f
+    }
+
+    #[test]
+    fn test_varint_round_trip_and_overflow() {
+        for value in [0, 1, 127, 128, 300, 16_384, u32::MAX as u64, u64::MAX] {
+            let encoded = encode_varint(value);
+            assert_eq!(read_varint(&encoded, 0), Ok((value, encoded.len())));
+        }
+        assert_eq!(encode_varint(u64::MAX).len(), 10);
+
+        assert_eq!(read_varint(&[0x80; 11], 0), Err(ProtobufError::VarintOverflow { offset: 0 }));
+        assert_eq!(read_varint(&[0x80, 0x80], 0), Err(ProtobufError::UnexpectedEof { offset: 0 }));
+    }
+
+    #[test]
+    fn test_find_field_with_multi_byte_lengths() {
+        // 300 字节的字段长度需要 2 字节 varint
+        let long = vec![b'x'; 300];
+        let mut data = vec![0x08, 0x96, 0x01]; // field 1 varint 150
+        data.extend(length_delimited(2, &long));
+        data.extend(length_delimited(3, b"first"));
+        data.extend(length_delimited(3, b"last"));
+
+        assert_eq!(find_field(&data, 2).unwrap(), Some(long));
+        assert_eq!(find_field(&data, 3).unwrap(), Some(b"last".to_vec()));
+        assert_eq!(find_field(&data, 4).unwrap(), None);
+
+        let truncated = &data[..data.len() - 1];
+        assert!(matches!(find_field(truncated, 9), Err(ProtobufError::UnexpectedEof { .. })));
+        assert!(matches!(find_field(&[0x0B], 1), Err(ProtobufError::InvalidWireType { wire_type: 3, .. })));
+    }
+
+    #[test]
+    fn test_find_nested_field() {
+        let oauth = create_oauth_field("access", "refresh-token", 1_700_000_000);
+        assert_eq!(find_nested_field(&oauth, &[6, 3]).unwrap(), Some(b"refresh-token".to_vec()));
+        assert_eq!(
+            find_field(&find_field(&oauth, 6).unwrap().unwrap(), 3).unwrap(),
+            Some(b"refresh-token".to_vec())
+        );
+
+        // 嵌套 10 层
+        let mut message = length_delimited(1, b"deep");
+        for _ in 0..9 {
+            message = length_delimited(1, &message);
+        }
+        assert_eq!(find_nested_field(&message, &[1; 10]).unwrap(), Some(b"deep".to_vec()));
+        assert!(matches!(
+            find_nested_field(&message, &[1; MAX_NESTING_DEPTH + 1]),
+            Err(ProtobufError::DepthExceeded { .. })
+        ));
+
+        // 同一嵌套消息分多段出现时合并后再查找
+        let mut split = length_delimited(6, &length_delimited(1, b"a"));
+        split.extend(length_delimited(6, &length_delimited(3, b"rt")));
+        assert_eq!(find_nested_field(&split, &[6, 3]).unwrap(), Some(b"rt".to_vec()));
+    }
+
+    #[test]
+    fn test_remove_field_keeps_others() {
+        let mut data = length_delimited(1, b"keep");
+        data.extend(create_oauth_field("a", "r", 1));
+        assert_eq!(remove_field(&data, 6).unwrap(), length_delimited(1, b"keep"));
+    }
+}
@@ -13,7 +13,9 @@ import { useConfigStore } from './stores/useConfigStore';
 import { useAccountStore } from './stores/useAccountStore';
 import { useTranslation } from 'react-i18next';
 import { listen } from '@tauri-apps/api/event';
-import { invoke } from '@tauri-apps/api/core';
+import { request as invoke } from './utils/request';
+import { showToast } from './components/common/ToastContainer';
+import { TokenRefreshFailedEvent } from './services/accountService';
 
 const router = createBrowserRouter([
   {
@@ -47,7 +49,7 @@ const router = createBrowserRouter([
 function App() {
   const { config, loadConfig } = useConfigStore();
   const { fetchCurrentAccount, fetchAccounts } = useAccountStore();
-  const { i18n } = useTranslation();
+  const { t, i18n } = useTranslation();
 
   useEffect(() => {
     loadConfig();
@@ -82,13 +84,32 @@ function App() {
       })
     );
 
+    // Listen for background DB sync event
+    unlistenPromises.push(
+      listen('account://synced', () => {
+        console.log('[App] Account synced from DB, refreshing...');
+        fetchCurrentAccount();
+        fetchAccounts();
+      })
+    );
+
+    // Listen for background token refresh failures
+    unlistenPromises.push(
+      listen<TokenRefreshFailedEvent>('account://token-refresh-failed', (event) => {
+        const { email, error } = event.payload;
+        console.warn('[App] Token refresh failed:', email, error);
+        showToast(t('accounts.toast.token_refresh_failed', { email, error }), 'error', 6000);
+        fetchAccounts();
+      })
+    );
+
     // Cleanup
     return () => {
       Promise.all(unlistenPromises).then(unlisteners => {
         unlisteners.forEach(unlisten => unlisten());
       });
     };
-  }, [fetchCurrentAccount, fetchAccounts]);
+  }, [fetchCurrentAccount, fetchAccounts, t]);
 
   // Update notification state
   const [showUpdateNotification, setShowUpdateNotification] = useState(false);
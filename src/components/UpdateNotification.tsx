@@ -1,6 +1,6 @@
 import React, { useEffect, useState } from 'react';
 import { X, Download, Sparkles, ArrowRight } from 'lucide-react';
-import { invoke } from '@tauri-apps/api/core';
+import { request as invoke } from '../utils/request';
 import { useTranslation } from 'react-i18next';
 
 interface UpdateInfo {
@@ -6,6 +6,8 @@ import { useTranslation } from 'react-i18next';
 import { listen } from '@tauri-apps/api/event';
 import { open } from '@tauri-apps/plugin-dialog';
 import { request as invoke } from '../../utils/request';
+import { validateRefreshToken, TokenValidationResult } from '../../services/accountService';
+import { getErrorMessage } from '../../types/error';
 
 interface AddAccountDialogProps {
     onAdd: (email: string, refreshToken: string) => Promise<void>;
@@ -13,6 +15,36 @@ interface AddAccountDialogProps {
 
 type Status = 'idle' | 'loading' | 'success' | 'error';
 
+// 从输入中提取 Refresh Token (JSON 数组 / 任意包含 1//... 的文本)，已去重
+function extractTokens(input: string): string[] {
+    let tokens: string[] = [];
+    try {
+        // 尝试解析为 JSON
+        if (input.startsWith('[') && input.endsWith(']')) {
+            const parsed = JSON.parse(input);
+            if (Array.isArray(parsed)) {
+                tokens = parsed
+                    .map((item: any) => item.refresh_token)
+                    .filter((t: any) => typeof t === 'string' && t.startsWith('1//'));
+            }
+        }
+    } catch (e) {
+        // JSON 解析失败,忽略
+        console.debug('JSON parse failed, falling back to regex', e);
+    }
+
+    // 如果 JSON 解析没有结果,尝试正则提取 (或者输入不是 JSON)
+    if (tokens.length === 0) {
+        const regex = /1\/\/[a-zA-Z0-9_\-]+/g;
+        const matches = input.match(regex);
+        if (matches) {
+            tokens = matches;
+        }
+    }
+
+    return [...new Set(tokens)];
+}
+
 function AddAccountDialog({ onAdd }: AddAccountDialogProps) {
     const { t } = useTranslation();
     const [isOpen, setIsOpen] = useState(false);
@@ -24,8 +56,10 @@ function AddAccountDialog({ onAdd }: AddAccountDialogProps) {
     // UI State
     const [status, setStatus] = useState<Status>('idle');
     const [message, setMessage] = useState('');
+    const [tokenPreview, setTokenPreview] = useState<TokenValidationResult | null>(null);
+    const [validating, setValidating] = useState(false);
 
-    const { startOAuthLogin, completeOAuthLogin, cancelOAuthLogin, importFromDb, importV1Accounts, importFromCustomDb } = useAccountStore();
+    const { startOAuthLogin, completeOAuthLogin, cancelOAuthLogin, importFromDb, importV1Accounts, importFromCustomDb, importFromCursor } = useAccountStore();
 
     const oauthUrlRef = useRef(oauthUrl);
     const statusRef = useRef(status);
@@ -89,7 +123,7 @@ function AddAccountDialog({ onAdd }: AddAccountDialogProps) {
                     }, 1500);
                 } catch (error) {
                     setStatus('error');
-                    let errorMsg = String(error);
+                    let errorMsg = getErrorMessage(error);
                     if (errorMsg.includes('Refresh Token') || errorMsg.includes('refresh_token')) {
                         setMessage(errorMsg);
                     } else if (errorMsg.includes('Tauri') || errorMsg.toLowerCase().includes('environment') || errorMsg.includes('环境')) {
@@ -139,6 +173,7 @@ function AddAccountDialog({ onAdd }: AddAccountDialogProps) {
         setStatus('idle');
         setMessage('');
         setRefreshToken('');
+        setTokenPreview(null);
         setOauthUrl('');
         setOauthUrlCopied(false);
     };
@@ -167,7 +202,7 @@ function AddAccountDialog({ onAdd }: AddAccountDialogProps) {
             setStatus('error');
 
             // 改进错误信息显示
-            let errorMsg = String(error);
+            let errorMsg = getErrorMessage(error);
 
             // 如果是 refresh_token 缺失错误,显示完整信息(包含解决方案)
             if (errorMsg.includes('Refresh Token') || errorMsg.includes('refresh_token')) {
@@ -191,36 +226,8 @@ function AddAccountDialog({ onAdd }: AddAccountDialogProps) {
 
         setStatus('loading');
 
-        // 1. 尝试解析输入
-        let tokens: string[] = [];
-        const input = refreshToken.trim();
-
-        try {
-            // 尝试解析为 JSON
-            if (input.startsWith('[') && input.endsWith(']')) {
-                const parsed = JSON.parse(input);
-                if (Array.isArray(parsed)) {
-                    tokens = parsed
-                        .map((item: any) => item.refresh_token)
-                        .filter((t: any) => typeof t === 'string' && t.startsWith('1//'));
-                }
-            }
-        } catch (e) {
-            // JSON 解析失败,忽略
-            console.debug('JSON parse failed, falling back to regex', e);
-        }
-
-        // 2. 如果 JSON 解析没有结果,尝试正则提取 (或者输入不是 JSON)
-        if (tokens.length === 0) {
-            const regex = /1\/\/[a-zA-Z0-9_\-]+/g;
-            const matches = input.match(regex);
-            if (matches) {
-                tokens = matches;
-            }
-        }
-
-        // 去重
-        tokens = [...new Set(tokens)];
+        // 1-2. 解析输入并提取 Token
+        const tokens = extractTokens(refreshToken.trim());
 
         if (tokens.length === 0) {
             setStatus('error');
@@ -267,6 +274,28 @@ function AddAccountDialog({ onAdd }: AddAccountDialogProps) {
         }
     };
 
+    // 添加前预览首个 Token 对应的账号 (不写入任何数据)
+    const handleValidateToken = async () => {
+        const [firstToken] = extractTokens(refreshToken.trim());
+        if (!firstToken) {
+            setStatus('error');
+            setMessage(t('accounts.add.token.error_token'));
+            return;
+        }
+        setValidating(true);
+        setTokenPreview(null);
+        try {
+            setTokenPreview(await validateRefreshToken(firstToken));
+            setStatus('idle');
+            setMessage('');
+        } catch (error) {
+            setStatus('error');
+            setMessage(getErrorMessage(error));
+        } finally {
+            setValidating(false);
+        }
+    };
+
     const handleOAuth = () => {
         // Default flow: opens the default browser and completes automatically.
         // (If user opened the URL manually, completion is also triggered by oauth-callback-received.)
@@ -294,6 +323,10 @@ function AddAccountDialog({ onAdd }: AddAccountDialogProps) {
         handleAction(t('accounts.add.tabs.import'), importFromDb);
     };
 
+    const handleImportCursor = () => {
+        handleAction(t('accounts.add.import.btn_cursor'), importFromCursor);
+    };
+
     const handleImportV1 = () => {
         handleAction(t('accounts.add.import.btn_v1'), importV1Accounts);
     };
@@ -464,17 +497,36 @@ function AddAccountDialog({ onAdd }: AddAccountDialogProps) {
                                     <div className="bg-gray-50 dark:bg-base-200 p-4 rounded-lg border border-gray-200 dark:border-base-300">
                                         <div className="flex justify-between items-center mb-2">
                                             <span className="text-sm font-medium text-gray-500 dark:text-gray-400">{t('accounts.add.token.label')}</span>
+                                            <button
+                                                className="btn btn-xs btn-ghost text-blue-500"
+                                                onClick={handleValidateToken}
+                                                disabled={validating || !refreshToken.trim() || status === 'loading' || status === 'success'}
+                                            >
+                                                {validating && <Loader2 className="w-3 h-3 animate-spin" />}
+                                                {t('accounts.add.token.validate_btn')}
+                                            </button>
                                         </div>
                                         <textarea
                                             className="textarea textarea-bordered w-full h-32 font-mono text-xs leading-relaxed focus:outline-none focus:border-blue-500 transition-colors bg-white dark:bg-base-100 text-gray-900 dark:text-base-content border-gray-300 dark:border-base-300 placeholder:text-gray-400"
                                             placeholder={t('accounts.add.token.placeholder')}
                                             value={refreshToken}
-                                            onChange={(e) => setRefreshToken(e.target.value)}
+                                            onChange={(e) => {
+                                                setRefreshToken(e.target.value);
+                                                setTokenPreview(null);
+                                            }}
                                             disabled={status === 'loading' || status === 'success'}
                                         />
                                         <p className="text-[10px] text-gray-400 mt-2">
                                             {t('accounts.add.token.hint')}
                                         </p>
+                                        {tokenPreview && (
+                                            <div className="mt-2 text-xs text-gray-600 dark:text-gray-300">
+                                                <div>{t('accounts.add.token.preview', { email: tokenPreview.email, name: tokenPreview.display_name || '-' })}</div>
+                                                {tokenPreview.is_duplicate && (
+                                                    <div className="text-amber-500">{t('accounts.add.token.preview_duplicate')}</div>
+                                                )}
+                                            </div>
+                                        )}
                                     </div>
                                 </div>
                             )}
@@ -506,6 +558,14 @@ function AddAccountDialog({ onAdd }: AddAccountDialogProps) {
                                             <Database className="w-4 h-4" />
                                             {t('accounts.add.import.btn_custom_db') || 'Custom DB (state.vscdb)'}
                                         </button>
+                                        <button
+                                            className="w-full px-4 py-3 bg-gray-50 dark:bg-base-200 text-gray-700 dark:text-gray-300 font-medium rounded-xl border border-gray-200 dark:border-base-300 hover:bg-indigo-50 dark:hover:bg-indigo-900/20 hover:border-indigo-200 dark:hover:border-indigo-800 hover:text-indigo-600 dark:hover:text-indigo-400 transition-all flex items-center justify-center gap-2 disabled:opacity-50 disabled:cursor-not-allowed shadow-sm"
+                                            onClick={handleImportCursor}
+                                            disabled={status === 'loading' || status === 'success'}
+                                        >
+                                            <Database className="w-4 h-4" />
+                                            {t('accounts.add.import.btn_cursor')}
+                                        </button>
                                     </div>
 
                                     <div className="divider text-xs text-gray-300 dark:text-gray-600">{t('accounts.add.import.or')}</div>
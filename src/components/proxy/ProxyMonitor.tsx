@@ -24,10 +24,22 @@ interface ProxyRequestLog {
     account_email?: string;
 }
 
+interface ModelStats {
+    name: string;
+    request_count: number;
+    estimated_input_tokens: number;
+    estimated_output_tokens: number;
+    error_count: number;
+}
+
 interface ProxyStats {
     total_requests: number;
     success_count: number;
     error_count: number;
+    model_stats?: ModelStats[];
+    account_stats?: ModelStats[];
+    last_stats_reset_at?: string | null;
+    actual_port?: number;
 }
 
 interface ProxyMonitorProps {
@@ -127,13 +139,22 @@ export const ProxyMonitor: React.FC<ProxyMonitorProps> = ({ className }) => {
     useEffect(() => {
         loadData();
         let unlistenFn: (() => void) | null = null;
+        let unlistenStatsCleared: (() => void) | null = null;
         const setupListener = async () => {
+            unlistenStatsCleared = await listen('proxy://stats-cleared', async () => {
+                try {
+                    setStats(await invoke<ProxyStats>('get_proxy_stats'));
+                } catch (e) {
+                    console.error("Failed to reload proxy stats", e);
+                }
+            });
             unlistenFn = await listen<ProxyRequestLog>('proxy://request', (event) => {
                 const newLog = event.payload;
                 setLogs(prev => [newLog, ...prev].slice(0, 1000));
                 setStats((prev: ProxyStats) => {
                     const isSuccess = newLog.status >= 200 && newLog.status < 400;
                     return {
+                        ...prev,
                         total_requests: prev.total_requests + 1,
                         success_count: prev.success_count + (isSuccess ? 1 : 0),
                         error_count: prev.error_count + (isSuccess ? 0 : 1),
@@ -142,7 +163,10 @@ export const ProxyMonitor: React.FC<ProxyMonitorProps> = ({ className }) => {
             });
         };
         setupListener();
-        return () => { if (unlistenFn) unlistenFn(); };
+        return () => {
+            if (unlistenFn) unlistenFn();
+            if (unlistenStatsCleared) unlistenStatsCleared();
+        };
     }, []);
 
     const filteredLogs = logs
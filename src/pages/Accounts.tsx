@@ -23,6 +23,7 @@ type FilterType = 'all' | 'pro' | 'ultra' | 'free';
 type ViewMode = 'list' | 'grid';
 
 import { useTranslation } from 'react-i18next';
+import { getErrorMessage } from '../types/error';
 
 function Accounts() {
     const { t } = useTranslation();
@@ -75,7 +76,7 @@ function Accounts() {
             const msg = await warmUpAccount(accountId);
             showToast(msg, 'success');
         } catch (error) {
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         } finally {
             setRefreshingIds(prev => {
                 const next = new Set(prev);
@@ -102,7 +103,7 @@ function Accounts() {
                 showToast(msg, 'success');
             }
         } catch (error) {
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         } finally {
             setIsWarmuping(false);
             setRefreshingIds(new Set());
@@ -269,7 +270,7 @@ function Accounts() {
             showToast(t('common.success'), 'success');
         } catch (error) {
             console.error('[Accounts] Switch failed:', error);
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         } finally {
             // Add a small delay for smoother UX
             setTimeout(() => {
@@ -290,7 +291,7 @@ function Accounts() {
             await refreshQuota(accountId);
             showToast(t('common.success'), 'success');
         } catch (error) {
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         } finally {
             setRefreshingIds(prev => {
                 const next = new Set(prev);
@@ -316,7 +317,7 @@ function Accounts() {
             showToast(t('common.success'), 'success');
         } catch (error) {
             console.error('[Accounts] Batch delete failed:', error);
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         }
     };
 
@@ -335,7 +336,7 @@ function Accounts() {
             showToast(t('common.success'), 'success');
         } catch (error) {
             console.error('[Accounts] Delete failed:', error);
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         } finally {
             setDeleteConfirmId(null);
         }
@@ -357,7 +358,7 @@ function Accounts() {
             showToast(t('common.success'), 'success');
         } catch (error) {
             console.error('[Accounts] Toggle proxy status failed:', error);
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         } finally {
             setToggleProxyConfirm(null);
         }
@@ -380,7 +381,7 @@ function Accounts() {
             setSelectedIds(new Set());
         } catch (error) {
             console.error('[Accounts] Batch toggle proxy status failed:', error);
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         }
     };
 
@@ -437,7 +438,7 @@ function Accounts() {
                 }
             }
         } catch (error) {
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         } finally {
             setIsRefreshing(false);
             setRefreshingIds(new Set());
@@ -484,7 +485,7 @@ function Accounts() {
             showToast(`${t('common.success')} ${path}`, 'success');
         } catch (error) {
             console.error('Export failed:', error);
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         }
     };
 
@@ -1,7 +1,8 @@
 import { useState, useEffect, useMemo } from 'react';
 import { useTranslation } from 'react-i18next';
 import { useNavigate } from 'react-router-dom';
-import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { request as invoke } from '../utils/request';
 import {
     Power,
     Copy,
@@ -28,6 +29,7 @@ import { AppConfig, ProxyConfig, StickySessionConfig } from '../types/config';
 import HelpTooltip from '../components/common/HelpTooltip';
 import ModalDialog from '../components/common/ModalDialog';
 import { showToast } from '../components/common/ToastContainer';
+import { listActiveStreams } from '../services/configService';
 import { cn } from '../utils/cn';
 import { useProxyModels } from '../hooks/useProxyModels';
 import GroupedSelect, { SelectOption } from '../components/common/GroupedSelect';
@@ -140,6 +142,7 @@ export default function ApiProxy() {
         base_url: '',
         active_accounts: 0,
     });
+    const [activeStreams, setActiveStreams] = useState(0);
 
     const [appConfig, setAppConfig] = useState<AppConfig | null>(null);
     const [loading, setLoading] = useState(false);
@@ -184,7 +187,12 @@ export default function ApiProxy() {
         loadConfig();
         loadStatus();
         const interval = setInterval(loadStatus, 3000);
-        return () => clearInterval(interval);
+        // 主端口被占用、改用备用端口时立即刷新显示的地址
+        const unlistenPortChanged = listen<number>('proxy://port-changed', () => loadStatus());
+        return () => {
+            clearInterval(interval);
+            unlistenPortChanged.then(unlisten => unlisten());
+        };
     }, []);
 
     const loadConfig = async () => {
@@ -200,6 +208,7 @@ export default function ApiProxy() {
         try {
             const s = await invoke<ProxyStatus>('get_proxy_status');
             setStatus(s);
+            setActiveStreams(s.running ? (await listActiveStreams()).count : 0);
         } catch (error) {
             console.error('获取状态失败:', error);
         }
@@ -211,7 +220,7 @@ export default function ApiProxy() {
             setAppConfig(newConfig);
         } catch (error) {
             console.error('保存配置失败:', error);
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         }
     };
 
@@ -231,7 +240,7 @@ export default function ApiProxy() {
             showToast(t('common.saved'), 'success');
         } catch (error) {
             console.error('Failed to update mapping:', error);
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         }
     };
 
@@ -256,7 +265,7 @@ export default function ApiProxy() {
             showToast(t('common.success'), 'success');
         } catch (error) {
             console.error('Failed to reset mapping:', error);
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         }
     };
 
@@ -292,7 +301,7 @@ export default function ApiProxy() {
             showToast(t('proxy.router.presets_applied'), 'success');
         } catch (error) {
             console.error('Failed to apply presets:', error);
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         }
     };
 
@@ -347,7 +356,7 @@ export default function ApiProxy() {
             showToast(t('common.success'), 'success');
         } catch (error) {
             console.error('Failed to clear session bindings:', error);
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         }
     };
 
@@ -364,7 +373,7 @@ export default function ApiProxy() {
             setZaiAvailableModels(models);
         } catch (error: any) {
             console.error('Failed to fetch z.ai models:', error);
-            setZaiModelsError(error.toString());
+            setZaiModelsError(getErrorMessage(error));
         } finally {
             setZaiModelsLoading(false);
         }
@@ -442,14 +451,18 @@ export default function ApiProxy() {
         setLoading(true);
         try {
             if (status.running) {
-                await invoke('stop_proxy_service');
+                // 返回强制关闭时仍未完成的请求数
+                const remaining = await invoke<number>('stop_proxy_service');
+                if (remaining > 0) {
+                    showToast(t('proxy.dialog.stop_forced', { count: remaining }), 'warning');
+                }
             } else {
                 // 使用当前的 appConfig.proxy 启动
                 await invoke('start_proxy_service', { config: appConfig.proxy });
             }
             await loadStatus();
         } catch (error: any) {
-            showToast(t('proxy.dialog.operate_failed', { error: error.toString() }), 'error');
+            showToast(t('proxy.dialog.operate_failed', { error: getErrorMessage(error) }), 'error');
         } finally {
             setLoading(false);
         }
@@ -467,7 +480,7 @@ export default function ApiProxy() {
             showToast(t('common.success'), 'success');
         } catch (error: any) {
             console.error('生成 API Key 失败:', error);
-            showToast(t('proxy.dialog.operate_failed', { error: error.toString() }), 'error');
+            showToast(t('proxy.dialog.operate_failed', { error: getErrorMessage(error) }), 'error');
         }
     };
 
@@ -521,6 +534,7 @@ genai.configure(
 model = genai.GenerativeModel('${modelId}')
 response = model.generate_content("Hello")
 print(response.text)`;
+import { getErrorMessage } from '../types/error';
         }
 
         // 3. OpenAI Protocol
@@ -596,7 +610,7 @@ print(response.text)`;
                                     <div className={`w-2 h-2 rounded-full ${status.running ? 'bg-green-500 animate-pulse' : 'bg-gray-400'}`} />
                                     <span className={`text-xs font-medium ${status.running ? 'text-green-600' : 'text-gray-500'}`}>
                                         {status.running
-                                            ? `${t('proxy.status.running')} (${status.active_accounts} ${t('common.accounts') || 'Accounts'})`
+                                            ? `${t('proxy.status.running')} (${status.active_accounts} ${t('common.accounts') || 'Accounts'}, ${t('proxy.status.active_streams', { count: activeStreams })})`
                                             : t('proxy.status.stopped')}
                                     </span>
                                 </div>
@@ -10,6 +10,7 @@ import { save } from '@tauri-apps/plugin-dialog';
 import { request as invoke } from '../utils/request';
 import { showToast } from '../components/common/ToastContainer';
 import { Account } from '../types/account';
+import { getErrorMessage } from '../types/error';
 
 function Dashboard() {
     const { t } = useTranslation();
@@ -78,7 +79,7 @@ function Dashboard() {
             showToast(t('dashboard.toast.switch_success'), 'success');
         } catch (error) {
             console.error('切换账号失败:', error);
-            showToast(`${t('dashboard.toast.switch_error')}: ${error}`, 'error');
+            showToast(`${t('dashboard.toast.switch_error')}: ${getErrorMessage(error)}`, 'error');
         } finally {
             setTimeout(() => {
                 isSwitchingRef.current = false;
@@ -104,7 +105,7 @@ function Dashboard() {
             showToast(t('dashboard.toast.refresh_success'), 'success');
         } catch (error) {
             console.error('[Dashboard] Refresh failed:', error);
-            showToast(`${t('dashboard.toast.refresh_error')}: ${error}`, 'error');
+            showToast(`${t('dashboard.toast.refresh_error')}: ${getErrorMessage(error)}`, 'error');
         } finally {
             setIsRefreshing(false);
         }
@@ -139,7 +140,7 @@ function Dashboard() {
             showToast(t('dashboard.toast.export_success', { path }), 'success');
         } catch (error) {
             console.error('Export failed:', error);
-            showToast(`${t('dashboard.toast.export_error')}: ${error}`, 'error');
+            showToast(`${t('dashboard.toast.export_error')}: ${getErrorMessage(error)}`, 'error');
         }
     };
 
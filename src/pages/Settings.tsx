@@ -10,6 +10,7 @@ import QuotaProtection from '../components/settings/QuotaProtection';
 import SmartWarmup from '../components/settings/SmartWarmup';
 
 import { useTranslation } from 'react-i18next';
+import { getErrorMessage } from '../types/error';
 
 
 function Settings() {
@@ -99,7 +100,7 @@ function Settings() {
             await saveConfig({ ...formData, auto_refresh: true });
             showToast(t('common.saved'), 'success');
         } catch (error) {
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         }
     };
 
@@ -108,7 +109,7 @@ function Settings() {
             await invoke('clear_log_cache');
             showToast(t('settings.advanced.logs_cleared'), 'success');
         } catch (error) {
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         }
         setIsClearLogsOpen(false);
     };
@@ -117,7 +118,7 @@ function Settings() {
         try {
             await invoke('open_data_folder');
         } catch (error) {
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         }
     };
 
@@ -133,7 +134,7 @@ function Settings() {
                 setFormData({ ...formData, default_export_path: selected });
             }
         } catch (error) {
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         }
     };
 
@@ -148,7 +149,7 @@ function Settings() {
                 setFormData({ ...formData, antigravity_executable: selected });
             }
         } catch (error) {
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         }
     };
 
@@ -159,7 +160,7 @@ function Settings() {
             setFormData({ ...formData, antigravity_executable: path });
             showToast(t('settings.advanced.antigravity_path_detected'), 'success');
         } catch (error) {
-            showToast(`${t('common.error')}: ${error}`, 'error');
+            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
         }
     };
 
@@ -187,7 +188,7 @@ function Settings() {
                 showToast(t('settings.about.latest_version'), 'success');
             }
         } catch (error) {
-            showToast(`${t('settings.about.update_check_failed')}: ${error}`, 'error');
+            showToast(`${t('settings.about.update_check_failed')}: ${getErrorMessage(error)}`, 'error');
         } finally {
             setIsCheckingUpdate(false);
         }
@@ -306,7 +307,7 @@ function Settings() {
                                             setFormData({ ...formData, auto_launch: enabled });
                                             showToast(enabled ? t('settings.general.auto_launch_enabled') : t('settings.general.auto_launch_disabled'), 'success');
                                         } catch (error) {
-                                            showToast(`${t('common.error')}: ${error}`, 'error');
+                                            showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
                                         }
                                     }}
                                 >
@@ -340,7 +341,7 @@ function Settings() {
                                                 setFormData({ ...formData, auto_check_update: enabled });
                                                 showToast(enabled ? t('settings.general.auto_check_update_enabled') : t('settings.general.auto_check_update_disabled'), 'success');
                                             } catch (error) {
-                                                showToast(`${t('common.error')}: ${error}`, 'error');
+                                                showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
                                             }
                                         }}
                                     />
@@ -370,7 +371,7 @@ function Settings() {
                                                 });
                                                 showToast(t('settings.general.update_check_interval_saved'), 'success');
                                             } catch (error) {
-                                                showToast(`${t('common.error')}: ${error}`, 'error');
+                                                showToast(`${t('common.error')}: ${getErrorMessage(error)}`, 'error');
                                             }
                                         }}
                                     />
@@ -594,7 +595,7 @@ function Settings() {
                                                 setFormData({ ...formData, antigravity_args: args });
                                                 showToast(t('settings.advanced.antigravity_args_detected'), 'success');
                                             } catch (error) {
-                                                showToast(`${t('settings.advanced.antigravity_args_detect_error')}: ${error}`, 'error');
+                                                showToast(`${t('settings.advanced.antigravity_args_detect_error')}: ${getErrorMessage(error)}`, 'error');
                                             }
                                         }}
                                     >
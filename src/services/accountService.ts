@@ -1,6 +1,6 @@
 import i18n from '../i18n';
 import { request as invoke } from '../utils/request';
-import { Account, QuotaData, DeviceProfile, DeviceProfileVersion } from '../types/account';
+import { Account, QuotaData, QuotaSnapshot, DeviceProfile, DeviceProfileVersion } from '../types/account';
 
 // 检查 Tauri 环境
 function ensureTauriEnvironment() {
@@ -23,6 +23,18 @@ export async function addAccount(email: string, refreshToken: string): Promise<A
     return await invoke('add_account', { email, refreshToken });
 }
 
+export interface TokenValidationResult {
+    email: string;
+    display_name: string | null;
+    expires_in: number;
+    is_duplicate: boolean;  // 已存在相同邮箱的账号
+}
+
+// 校验 Refresh Token 而不创建账号 (添加前预览)
+export async function validateRefreshToken(refreshToken: string): Promise<TokenValidationResult> {
+    return await invoke('validate_refresh_token', { refreshToken });
+}
+
 export async function deleteAccount(accountId: string): Promise<void> {
     return await invoke('delete_account', { accountId });
 }
@@ -31,6 +43,25 @@ export async function deleteAccounts(accountIds: string[]): Promise<void> {
     return await invoke('delete_accounts', { accountIds });
 }
 
+export interface ForbiddenAccountSummary {
+    id: string;
+    email: string;
+    name?: string;
+    priority: number;
+    created_at: number;
+    last_used: number;
+}
+
+// 被禁止 (quota.is_forbidden) 的账号，当前账号除外；仅列出不删除
+export async function listForbiddenAccounts(): Promise<ForbiddenAccountSummary[]> {
+    return await invoke('list_forbidden_accounts');
+}
+
+// 删除所有被禁止的账号 (当前账号除外)，返回删除数量
+export async function purgeForbiddenAccounts(): Promise<number> {
+    return await invoke('purge_forbidden_accounts');
+}
+
 export async function switchAccount(accountId: string): Promise<void> {
     return await invoke('switch_account', { accountId });
 }
@@ -44,12 +75,34 @@ export interface RefreshStats {
     success: number;
     failed: number;
     details: string[];
+    duration_ms: number;
 }
 
 export async function refreshAllQuotas(): Promise<RefreshStats> {
     return await invoke('refresh_all_quotas');
 }
 
+// 定时刷新配额 (最小间隔 60 秒)，每次完成后发送 quota://refreshed 事件 (payload: RefreshStats)
+export async function scheduleQuotaRefresh(intervalSecs: number): Promise<void> {
+    return await invoke('schedule_quota_refresh', { intervalSecs });
+}
+
+export async function cancelQuotaRefresh(): Promise<void> {
+    return await invoke('cancel_quota_refresh');
+}
+
+export interface TokenTestResult {
+    valid: boolean;
+    latency_ms: number;
+    error: string | null;
+    model_used: string;
+}
+
+// 轻量检测账号是否可用 (结果写入账号健康度的 last_error / last_success_at)
+export async function testAccountToken(accountId: string): Promise<TokenTestResult> {
+    return await invoke('test_account_token', { accountId });
+}
+
 // OAuth
 export async function startOAuthLogin(): Promise<Account> {
     ensureTauriEnvironment();
@@ -85,6 +138,34 @@ export async function completeOAuthLogin(): Promise<Account> {
     }
 }
 
+// 设备码授权 (无浏览器环境)，验证码通过 device-flow-code 事件下发
+export interface DeviceFlowCode {
+    user_code: string;
+    verification_url: string;
+    expires_in: number;
+}
+
+// 设备码流程结束时通过 device-flow-result 事件下发
+export interface DeviceFlowResult {
+    success: boolean;
+    error: string | null;
+}
+
+export async function startDeviceOAuthLogin(): Promise<Account> {
+    ensureTauriEnvironment();
+    try {
+        return await invoke('start_device_oauth_login');
+    } catch (error) {
+        if (typeof error === 'string') {
+            if (error.includes('Refresh Token') || error.includes('refresh_token')) {
+                throw error;
+            }
+            throw i18n.t('accounts.add.oauth_error', { error });
+        }
+        throw error;
+    }
+}
+
 export async function cancelOAuthLogin(): Promise<void> {
     ensureTauriEnvironment();
     return await invoke('cancel_oauth_login');
@@ -95,10 +176,67 @@ export async function importV1Accounts(): Promise<Account[]> {
     return await invoke('import_v1_accounts');
 }
 
+export interface AccountPreview {
+    email: string;
+    name?: string;
+    source_file: string;
+    token_verified: boolean;
+}
+
+export interface ImportResult {
+    accounts: AccountPreview[];
+    already_exists: string[];
+    would_fail: ImportError[];
+}
+
+// 预览 V1 导入 (不写入数据)
+export async function previewV1Import(): Promise<ImportResult> {
+    return await invoke('preview_v1_import');
+}
+
+export interface ImportError {
+    line: number;
+    email?: string;
+    message: string;
+}
+
+export interface TokenListImport {
+    accounts: Account[];
+    errors: ImportError[];
+}
+
+// 账号备份 (提供密码时使用 Argon2id + AES-256-GCM 加密)
+export async function exportAccounts(path: string, password?: string): Promise<void> {
+    return await invoke('export_accounts', { path, password });
+}
+
+export async function importAccountsFromFile(path: string, password?: string): Promise<Account[]> {
+    return await invoke('import_accounts_from_file', { path, password });
+}
+
+// 账号目录与索引的 tar.gz 归档备份，返回归档路径
+export async function backupAccounts(destPath: string): Promise<string> {
+    return await invoke('backup_accounts', { destPath });
+}
+
+export async function restoreAccounts(archivePath: string): Promise<number> {
+    return await invoke('restore_accounts', { archivePath });
+}
+
+// 每行一个 email:refresh_token，# 开头为注释
+export async function importFromTokenList(path: string): Promise<TokenListImport> {
+    return await invoke('import_from_token_list', { path });
+}
+
 export async function importFromDb(): Promise<Account> {
     return await invoke('import_from_db');
 }
 
+// 导入 Cursor IDE 当前登录的账号 (globalStorage/storage.json)
+export async function importFromCursor(): Promise<Account> {
+    return await invoke('import_from_cursor');
+}
+
 export async function importFromCustomDb(path: string): Promise<Account> {
     return await invoke('import_custom_db', { path });
 }
@@ -111,6 +249,82 @@ export async function toggleProxyStatus(accountId: string, enable: boolean, reas
     return await invoke('toggle_proxy_status', { accountId, enable, reason });
 }
 
+// 每日配额快照 (按时间升序)，用于绘制配额走势
+export interface ModelQuotaSummary {
+    name: string;
+    accounts: number;
+    avg_remaining_percentage: number;
+    exhausted_accounts: number;
+}
+
+// 上游只提供剩余比例; *_remaining 按每个模型 100 次请求 / 1,000,000 token 的名义容量估算
+export interface QuotaSummary {
+    total_accounts: number;
+    accounts_with_quota: number;
+    forbidden_accounts: number;
+    avg_remaining_percentage: number;
+    total_requests_remaining: number;
+    total_tokens_remaining: number;
+    avg_requests_remaining: number;
+    models: ModelQuotaSummary[];
+    last_refreshed_at: number | null;  // Unix 秒
+}
+
+// 多账号配额汇总 (读取本地缓存，不发起网络请求)
+export async function getQuotaSummary(): Promise<QuotaSummary> {
+    return await invoke('get_quota_summary');
+}
+
+// account://token-refresh-failed 事件负载 (后台 Token 刷新失败)
+export interface TokenRefreshFailedEvent {
+    account_id: string;
+    email: string;
+    error: string;
+    timestamp: number;  // Unix 秒
+}
+
+export async function getQuotaHistory(accountId: string): Promise<QuotaSnapshot[]> {
+    return await invoke('get_quota_history', { accountId });
+}
+
+// 账号级上游代理 (传空值回退到全局代理)
+export async function updateAccountProxy(accountId: string, proxyUrl?: string): Promise<Account> {
+    return await invoke('update_account_proxy', { accountId, proxyUrl });
+}
+
+// 账号级系统提示词前缀 (传空值清除)
+export async function setAccountSystemPrompt(accountId: string, prefix?: string): Promise<Account> {
+    return await invoke('set_account_system_prompt', { accountId, prefix });
+}
+
+// 账号调度优先级 (1-10)
+export async function setAccountPriority(accountId: string, priority: number): Promise<void> {
+    return await invoke('set_account_priority', { accountId, priority });
+}
+
+// 冻结账号 (保留给手动使用，不再分配给反代) / 解冻
+export async function freezeAccount(accountId: string, reason: string): Promise<void> {
+    return await invoke('freeze_account', { accountId, reason });
+}
+
+export async function unfreezeAccount(accountId: string): Promise<void> {
+    return await invoke('unfreeze_account', { accountId });
+}
+
+// 账号备注 (传空字符串清除)
+// 重命名账号 (displayName 为空时恢复 OAuth 名称)
+export async function renameAccount(accountId: string, displayName: string): Promise<void> {
+    return await invoke('rename_account', { accountId, displayName });
+}
+
+export async function setAccountNotes(accountId: string, notes: string): Promise<void> {
+    return await invoke('set_account_notes', { accountId, notes });
+}
+
+export async function getAccountNotes(accountId: string): Promise<string | null> {
+    return await invoke('get_account_notes', { accountId });
+}
+
 /**
  * 重新排序账号列表
  * @param accountIds 按新顺序排列的账号ID数组
@@ -1,5 +1,5 @@
 import { request as invoke } from '../utils/request';
-import { AppConfig } from '../types/config';
+import { AppConfig, ProxyConfig } from '../types/config';
 
 export async function loadConfig(): Promise<AppConfig> {
     return await invoke('load_config');
@@ -8,3 +8,178 @@ export async function loadConfig(): Promise<AppConfig> {
 export async function saveConfig(config: AppConfig): Promise<void> {
     return await invoke('save_config', { config });
 }
+
+// 合并后的有效模型映射 (内置 + 自定义 + 别名)
+export async function getEffectiveModelMapping(): Promise<Record<string, string>> {
+    return await invoke('get_effective_model_mapping');
+}
+
+// 生成仅能使用指定账号的 API Key (写入 proxy.api_key_routes)
+export async function generateApiKeyForAccounts(accountIds: string[]): Promise<string> {
+    return await invoke('generate_api_key_for_accounts', { accountIds });
+}
+
+export interface LogEntry {
+    timestamp: string;
+    level: string;
+    message: string;
+}
+
+export interface LogPage {
+    entries: LogEntry[];
+    total: number;
+}
+
+// 检索内存日志 (最新在前)
+export async function getLogEntries(
+    limit: number,
+    offset: number,
+    levelFilter?: 'INFO' | 'WARN' | 'ERROR',
+    search?: string,
+): Promise<LogPage> {
+    return await invoke('get_log_entries', { levelFilter, search, limit, offset });
+}
+
+export interface PreflightCheck {
+    name: string;
+    status: 'pass' | 'warn' | 'fail';
+    message: string;
+}
+
+export interface PreflightReport {
+    checks: PreflightCheck[];
+    passed: boolean;
+}
+
+// 反代启动前检查 (端口 / 上游代理 / API Key / 账号 / 连通性)
+export async function preflightCheck(config: ProxyConfig): Promise<PreflightReport> {
+    return await invoke('preflight_check', { config });
+}
+
+// 使用新配置重启反代服务 (端口变化时无停机切换；新实例启动失败时保留原服务)
+export async function restartProxyService(config: ProxyConfig): Promise<void> {
+    return await invoke('restart_proxy_service', { config });
+}
+
+export interface ProxyHealth {
+    status: 'ok' | 'degraded';
+    reason?: string;
+    uptime_secs?: number;
+    accounts?: {
+        total: number;
+        available: number;
+        open_circuit: number;
+    };
+    version?: string;
+}
+
+// 反代服务健康状态 (调用本机 /health 端点)
+export async function checkProxyHealth(): Promise<ProxyHealth> {
+    return await invoke('check_proxy_health');
+}
+
+export interface ConnectionTestResult {
+    dns_resolved: boolean;
+    tcp_connected: boolean;
+    tls_valid: boolean;
+    proxy_reachable: boolean | null;  // 未启用上游代理时为 null
+    latency_ms: number;
+    error: string | null;
+}
+
+// 基础网络连通性测试 (DNS / TCP / TLS / 上游代理)，不携带任何凭据
+export async function connectionTest(): Promise<ConnectionTestResult> {
+    return await invoke('connection_test');
+}
+
+export interface AccountLatencyStats {
+    account_id: string;
+    email: string;
+    ema_ms: number;
+    p50_ms: number;
+    p95_ms: number;
+    samples: number;
+}
+
+// 各账号近期请求延迟 (反代未运行时为空)
+export async function getAccountLatencyStats(): Promise<AccountLatencyStats[]> {
+    return await invoke('get_account_latency_stats');
+}
+
+// 调试: 加载 record_streams 录制的 .sse 文件，之后可通过 GET /debug/replay 回放
+export async function replayStream(path: string): Promise<void> {
+    return await invoke('replay_stream', { path });
+}
+
+// 清零反代用量统计 (完成后发送 proxy://stats-cleared 事件)
+export async function clearProxyStats(): Promise<void> {
+    return await invoke('clear_proxy_stats');
+}
+
+export interface ActiveStreamStats {
+    count: number;
+    oldest_started_at: string | null;  // RFC 3339
+}
+
+// 当前打开的流式连接 (反代未运行时 count 为 0)
+export async function listActiveStreams(): Promise<ActiveStreamStats> {
+    return await invoke('list_active_streams');
+}
+
+// 已安装 / 运行中的 Antigravity 版本 (未检测到时为 null)
+export async function getAntigravityVersion(): Promise<string | null> {
+    return await invoke('get_antigravity_version');
+}
+
+export interface ProcessInfo {
+    pid: number;
+    cpu_usage_percent: number;
+    memory_mb: number;
+    start_time: number;  // Unix 秒
+    status: string;
+}
+
+// Antigravity 主进程资源占用 (未运行时为 null)
+export async function getProcessInfo(): Promise<ProcessInfo | null> {
+    return await invoke('get_process_info');
+}
+
+// Antigravity 进程状态监听 (状态变化时发送 antigravity://status-changed 事件, payload: { running })
+export async function watchAntigravityProcess(): Promise<void> {
+    return await invoke('watch_antigravity_process');
+}
+
+export async function stopWatchingAntigravityProcess(): Promise<void> {
+    return await invoke('stop_watching_antigravity_process');
+}
+
+export interface DiagnosticReport {
+    generated_at: string;  // RFC 3339
+    app_version: string;
+    os: {
+        name: string | null;
+        version: string | null;
+        kernel_version: string | null;
+        arch: string;
+    };
+    account_count: number;
+    proxy_config: Record<string, unknown>;  // 已脱敏
+    proxy_running: boolean;
+    proxy_stats: Record<string, unknown>;
+    recent_errors: LogEntry[];
+    antigravity: {
+        running: boolean;
+        version: string | null;
+    };
+    data_dir: string | null;
+    disk_available_bytes: number | null;
+}
+
+// 诊断报告 (凭据已替换为 [REDACTED])，用于提交 Bug
+export async function generateDiagnosticReport(): Promise<DiagnosticReport> {
+    return await invoke('generate_diagnostic_report');
+}
+
+export async function saveDiagnosticReport(path: string): Promise<void> {
+    return await invoke('save_diagnostic_report', { path });
+}
@@ -1,6 +1,7 @@
 import { create } from 'zustand';
 import { Account } from '../types/account';
 import * as accountService from '../services/accountService';
+import { getErrorMessage } from '../types/error';
 
 interface AccountState {
     accounts: Account[];
@@ -26,6 +27,7 @@ interface AccountState {
     importV1Accounts: () => Promise<void>;
     importFromDb: () => Promise<void>;
     importFromCustomDb: (path: string) => Promise<void>;
+    importFromCursor: () => Promise<void>;
     syncAccountFromDb: () => Promise<void>;
     toggleProxyStatus: (accountId: string, enable: boolean, reason?: string) => Promise<void>;
     warmUpAccounts: () => Promise<string>;
@@ -46,7 +48,7 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             set({ accounts, loading: false });
         } catch (error) {
             console.error('[Store] Fetch accounts failed:', error);
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
         }
     },
 
@@ -56,7 +58,7 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             const account = await accountService.getCurrentAccount();
             set({ currentAccount: account, loading: false });
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
         }
     },
 
@@ -67,7 +69,7 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             await get().fetchAccounts();
             set({ loading: false });
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
             throw error;
         }
     },
@@ -82,7 +84,7 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             ]);
             set({ loading: false });
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
             throw error;
         }
     },
@@ -97,7 +99,7 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             ]);
             set({ loading: false });
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
             throw error;
         }
     },
@@ -109,7 +111,7 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             await get().fetchCurrentAccount();
             set({ loading: false });
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
             throw error;
         }
     },
@@ -121,7 +123,7 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             await get().fetchAccounts();
             set({ loading: false });
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
             throw error;
         }
     },
@@ -134,7 +136,7 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             set({ loading: false });
             return stats;
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
             throw error;
         }
     },
@@ -178,7 +180,7 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             await get().fetchAccounts();
             set({ loading: false });
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
             throw error;
         }
     },
@@ -190,7 +192,7 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             await get().fetchAccounts();
             set({ loading: false });
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
             throw error;
         }
     },
@@ -211,7 +213,7 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             await get().fetchAccounts();
             set({ loading: false });
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
             throw error;
         }
     },
@@ -226,7 +228,7 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             ]);
             set({ loading: false });
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
             throw error;
         }
     },
@@ -241,7 +243,19 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             ]);
             set({ loading: false });
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
+            throw error;
+        }
+    },
+
+    importFromCursor: async () => {
+        set({ loading: true, error: null });
+        try {
+            await accountService.importFromCursor();
+            await get().fetchAccounts();
+            set({ loading: false });
+        } catch (error) {
+            set({ error: getErrorMessage(error), loading: false });
             throw error;
         }
     },
@@ -277,7 +291,7 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             set({ loading: false });
             return result;
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
             throw error;
         }
     },
@@ -290,7 +304,7 @@ export const useAccountStore = create<AccountState>((set, get) => ({
             set({ loading: false });
             return result;
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
             throw error;
         }
     },
@@ -1,6 +1,7 @@
 import { create } from 'zustand';
 import { AppConfig } from '../types/config';
 import * as configService from '../services/configService';
+import { getErrorMessage } from '../types/error';
 
 interface ConfigState {
     config: AppConfig | null;
@@ -25,7 +26,7 @@ export const useConfigStore = create<ConfigState>((set, get) => ({
             const config = await configService.loadConfig();
             set({ config, loading: false });
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
         }
     },
 
@@ -35,7 +36,7 @@ export const useConfigStore = create<ConfigState>((set, get) => ({
             await configService.saveConfig(config);
             set({ config, loading: false });
         } catch (error) {
-            set({ error: String(error), loading: false });
+            set({ error: getErrorMessage(error), loading: false });
             throw error;
         }
     },
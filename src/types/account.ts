@@ -2,20 +2,39 @@ export interface Account {
     id: string;
     email: string;
     name?: string;
+    custom_name?: string;  // 用户自定义名称，list_accounts 返回时已合并到 name
     token: TokenData;
     device_profile?: DeviceProfile;
     device_history?: DeviceProfileVersion[];
     quota?: QuotaData;
+    quota_history?: [number, QuotaData][];  // 每日配额快照 (时间戳, 配额)
     disabled?: boolean;
     disabled_reason?: string;
     disabled_at?: number;
     proxy_disabled?: boolean;
     proxy_disabled_reason?: string;
     proxy_disabled_at?: number;
+    frozen?: boolean;  // 冻结的账号不进入反代账号池，但仍可手动切换
+    freeze_reason?: string;
+    health?: AccountHealth;
+    upstream_proxy?: string;
+    system_prompt_prefix?: string;
+    notes?: string;
+    priority?: number;  // 反代加权轮询优先级 1-10，默认 5
     created_at: number;
     last_used: number;
 }
 
+export interface AccountHealth {
+    consecutive_errors: number;
+    last_error_at?: number;
+    last_success_at?: number;
+    last_error?: string;  // 最近一次配额查询 / 连通性测试的错误，成功后清除
+    avg_latency_ms: number;
+    error_rate_7d: number;
+    score: number;  // 健康分 0-100
+}
+
 export interface TokenData {
     access_token: string;
     refresh_token: string;
@@ -32,6 +51,11 @@ export interface QuotaData {
     subscription_tier?: string;  // 订阅类型: FREE/PRO/ULTRA
 }
 
+export interface QuotaSnapshot {
+    timestamp: number;
+    quota: QuotaData;
+}
+
 export interface ModelQuota {
     name: string;
     percentage: number;
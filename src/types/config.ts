@@ -8,9 +8,36 @@ export interface ProxyConfig {
     allow_lan_access?: boolean;
     auth_mode?: 'off' | 'strict' | 'all_except_health' | 'auto';
     port: number;
+    port_fallback_range?: [number, number] | null;  // 主端口被占用时尝试的备用端口范围 (闭区间)
     api_key: string;
+    api_key_routes?: Record<string, string[]>;
     auto_start: boolean;
     custom_mapping?: Record<string, string>;
+    model_aliases?: Record<string, string>;
+    dedup_window_secs?: number;
+    shutdown_timeout_secs?: number;
+    include_usage_headers?: boolean;
+    mock_moderation_endpoint?: boolean;  // POST /v1/moderations 桩端点 (始终 flagged: false)，默认开启
+    record_streams?: string | null;  // 调试: SSE 响应录制目录 (保留最近 100 个)，配合 replay_stream 与 GET /debug/replay 使用
+    admin_api_key?: string;
+    fallback_model_on_503?: string;
+    compression_threshold_bytes?: number;
+    max_request_body_mb?: number;
+    propagate_request_id?: boolean;
+    allowed_origins?: string[];  // CORS 来源白名单，默认 ["*"]
+    allow_credentials?: boolean;
+    stream_inactivity_timeout_secs?: number;  // 默认 60
+    stream_total_timeout_secs?: number;  // 0 表示不限制
+    max_input_tokens?: number;  // Claude 请求输入 token 上限，超出时丢弃最早的消息
+    upstream_pool_max_idle_per_host?: number;  // 默认 10
+    upstream_pool_idle_timeout_secs?: number;  // 默认 90
+    upstream_tcp_keepalive_secs?: number;  // 默认 30
+    upstream_http2_keep_alive_interval_secs?: number;  // 默认 20
+    upstream_http2_prior_knowledge?: boolean;
+    upstream_connect_timeout_secs?: number;  // 默认 10
+    upstream_read_timeout_secs?: number;  // 默认 120
+    response_cache_ttl_secs?: number;  // 非流式响应缓存有效期，未设置时不缓存
+    response_cache_max_entries?: number;  // 默认 100
     request_timeout: number;
     enable_logging: boolean;
     upstream_proxy: UpstreamProxyConfig;
@@ -23,6 +50,7 @@ export type SchedulingMode = 'CacheFirst' | 'Balance' | 'PerformanceFirst';
 export interface StickySessionConfig {
     mode: SchedulingMode;
     max_wait_seconds: number;
+    session_ttl_secs?: number;  // 会话绑定有效期，默认 3600
 }
 
 export type ZaiDispatchMode = 'off' | 'exclusive' | 'pooled' | 'fallback';
@@ -75,6 +103,15 @@ export interface AppConfig {
     auto_check_update?: boolean; // 自动检查更新
     update_check_interval?: number; // 更新检查间隔（小时）
     accounts_page_size?: number; // 账号列表每页显示数量,默认 0 表示自动计算
+    refresh_concurrency?: number; // 批量刷新配额的并发数,默认 3
+    log_format?: 'plain' | 'json'; // 日志输出格式,未设置时 stdout 非终端自动使用 json
+    max_log_size_mb?: number; // 单个日志文件大小上限 (MB),默认 10
+    max_log_files?: number; // 保留的归档日志数量,默认 5
+    db_sync_interval_secs?: number; // 后台从 Antigravity DB 同步账号的间隔(秒),未设置时禁用
+    max_quota_history_entries?: number; // 每个账号保留的每日配额快照数量,默认 30
+    auto_watch_process?: boolean; // 启动时自动监听 Antigravity 进程状态
+    allowed_email_domains?: string[]; // 允许添加的账号邮箱域名,为空表示不限制
+    fsync_on_write?: boolean; // 写入账号索引后 fsync 落盘 (默认 true)
     scheduled_warmup: ScheduledWarmupConfig;
     quota_protection: QuotaProtectionConfig; // [NEW] 配额保护配置
     proxy: ProxyConfig;
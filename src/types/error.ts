@@ -0,0 +1,59 @@
+// 后端命令返回的结构化错误 (对应 Rust crate::error::AppError)
+export type AppErrorCode =
+    | 'OAUTH_ERROR'
+    | 'NETWORK_ERROR'
+    | 'DATABASE_ERROR'
+    | 'ACCOUNT_ERROR'
+    | 'ACCOUNT_NOT_FOUND'
+    | 'QUOTA_FORBIDDEN'
+    | 'CONFIG_ERROR'
+    | 'IO_ERROR'
+    | 'TAURI_ERROR'
+    | 'UNKNOWN_ERROR';
+
+// CONFIG_ERROR 的 detail 为逐字段的校验错误，其余错误码为 null
+export interface ConfigFieldError {
+    field: string;
+    message: string;
+}
+
+export interface AppErrorPayload {
+    code: AppErrorCode;
+    message: string;
+    detail: ConfigFieldError[] | null;
+}
+
+export class AppError extends Error {
+    code: AppErrorCode;
+    detail: ConfigFieldError[] | null;
+
+    constructor(payload: AppErrorPayload) {
+        super(payload.message);
+        this.name = 'AppError';
+        this.code = payload.code;
+        this.detail = payload.detail;
+    }
+
+    // 保持 String(error) / `${error}` 输出原始错误信息
+    toString(): string {
+        return this.message;
+    }
+}
+
+export function isAppErrorPayload(value: unknown): value is AppErrorPayload {
+    return typeof value === 'object' && value !== null
+        && typeof (value as AppErrorPayload).code === 'string'
+        && typeof (value as AppErrorPayload).message === 'string';
+}
+
+// 任意 catch 到的错误 → 可展示的文本 (AppError / 结构化错误对象 / Error / 字符串)
+export function getErrorMessage(error: unknown): string {
+    if (error instanceof Error) return error.message;
+    if (isAppErrorPayload(error)) return error.message;
+    if (typeof error === 'string') return error;
+    try {
+        return JSON.stringify(error);
+    } catch {
+        return String(error);
+    }
+}
@@ -1,10 +1,12 @@
 import { invoke } from '@tauri-apps/api/core';
+import { AppError, isAppErrorPayload } from '../types/error';
 
 export async function request<T>(cmd: string, args?: any): Promise<T> {
   try {
     return await invoke<T>(cmd, args);
   } catch (error) {
     console.error(`API Error [${cmd}]:`, error);
-    throw error;
+    // 结构化错误转为 AppError，调用方可按 error.code 分支处理
+    throw isAppErrorPayload(error) ? new AppError(error) : error;
   }
 }